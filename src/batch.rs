@@ -0,0 +1,217 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use hrm_interpreter::{
+    interpreter::{memory::Memory, settings::Settings, Interpreter},
+    script_object::{instruction::ParseOptions, ScriptObject},
+};
+
+use crate::{
+    log,
+    manifest::{self, CaseResult, TestCase},
+};
+
+#[derive(Debug, PartialEq)]
+/// The results of a valid script on the test cases
+pub struct Summary {
+    pub passed: usize,
+    pub cases: usize,
+    /// Number of instructions
+    pub size: usize,
+    /// Average steps of the cases that ran to the end, if any
+    pub steps: Option<usize>,
+}
+
+/// The results of a script of the directory: its summary, or why it can't run
+pub struct ScriptResults {
+    pub file: String,
+    pub summary: Result<Summary, String>,
+}
+
+/// The scripts of the directory (the `.hrm` files), sorted by name
+pub fn scripts(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut scripts = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .filter(|path| {
+            path.as_ref()
+                .map_or(true, |path| path.extension().is_some_and(|e| e == "hrm"))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    scripts.sort();
+    Ok(scripts)
+}
+
+/// Run the script of the file on all the cases
+pub fn run_script(
+    path: &Path,
+    cases: &[TestCase],
+    settings: &Settings,
+    parse_options: &ParseOptions,
+) -> ScriptResults {
+    let file = path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().to_string(),
+    );
+    log::verbose("batch", format!("running {}", path.display()));
+    let summary = fs::read_to_string(path)
+        .map_err(|e| format!("could not read the file: {}", e))
+        .and_then(|source| {
+            let parse_options = ParseOptions {
+                include_dir: path.parent().map(Path::to_path_buf),
+                ..parse_options.clone()
+            };
+            let script = ScriptObject::parse_with_options(&source, &parse_options)
+                .map_err(|e| e.to_string())?;
+            script.validate().map_err(|e| e.to_string())?;
+            Ok(script)
+        })
+        .map(|script| {
+            let mut passed = 0;
+            let mut steps = vec![];
+            let mut interpreter = Interpreter::with_settings(Memory::default(), settings.clone());
+            for case in cases {
+                match manifest::run_case_with(&mut interpreter, &script, case) {
+                    CaseResult::Passed { steps: case_steps } => {
+                        passed += 1;
+                        steps.push(case_steps);
+                    }
+                    CaseResult::Failed {
+                        steps: case_steps, ..
+                    } => steps.push(case_steps),
+                    CaseResult::Error(_) => {}
+                }
+            }
+            Summary {
+                passed,
+                cases: cases.len(),
+                size: script.instruction_count(),
+                steps: (!steps.is_empty()).then(|| steps.iter().sum::<usize>() / steps.len()),
+            }
+        });
+
+    ScriptResults { file, summary }
+}
+
+/// Print a line per script to stderr, then the errors of the invalid scripts.
+/// Returns true if all the scripts passed all the cases.
+pub fn report(results: &[ScriptResults]) -> bool {
+    let width = results
+        .iter()
+        .map(|result| result.file.len())
+        .chain(std::iter::once(4))
+        .max()
+        .unwrap_or_default();
+
+    eprintln!(
+        "{: <width$}  {: <7} {: >7} {: >6} {: >8}",
+        "File", "Result", "Passed", "Size", "Steps"
+    );
+    for result in results {
+        match &result.summary {
+            Ok(summary) => eprintln!(
+                "{: <width$}  {: <7} {: >7} {: >6} {: >8}",
+                result.file,
+                if summary.passed == summary.cases {
+                    "PASS"
+                } else {
+                    "FAIL"
+                },
+                format!("{}/{}", summary.passed, summary.cases),
+                summary.size,
+                summary
+                    .steps
+                    .map_or("-".to_string(), |steps| steps.to_string())
+            ),
+            Err(_) => eprintln!(
+                "{: <width$}  {: <7} {: >7} {: >6} {: >8}",
+                result.file, "INVALID", "-", "-", "-"
+            ),
+        }
+    }
+
+    for result in results {
+        if let Err(e) = &result.summary {
+            eprintln!();
+            eprintln!("{}: {}", result.file, e);
+        }
+    }
+
+    let passing = results
+        .iter()
+        .filter(|result| {
+            result
+                .summary
+                .as_ref()
+                .is_ok_and(|summary| summary.passed == summary.cases)
+        })
+        .count();
+    eprintln!();
+    eprintln!(
+        "{} of {} script(s) pass all the cases",
+        passing,
+        results.len()
+    );
+    passing == results.len()
+}
+
+#[cfg(test)]
+mod batch_tests {
+    use hrm_interpreter::script_object::value_box::ValueBox;
+
+    use super::*;
+
+    fn case(inputs: Vec<ValueBox>, expected: Vec<ValueBox>) -> TestCase {
+        TestCase {
+            name: "case".to_string(),
+            inputs,
+            memory: Default::default(),
+            max_memory_address: usize::MAX,
+            expected,
+        }
+    }
+
+    #[test]
+    fn test_scripts() {
+        let scripts = scripts(Path::new("./samples")).unwrap();
+        assert!(scripts.len() > 1);
+        assert!(scripts
+            .iter()
+            .all(|script| script.extension().unwrap() == "hrm"));
+        assert!(scripts.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_run_script() {
+        let values = |values: &[i32]| values.iter().copied().map(ValueBox::from).collect();
+        let cases = [
+            case(values(&[1, 2]), values(&[1, 2])),
+            case(values(&[3]), values(&[4])),
+        ];
+        let results = run_script(
+            Path::new("./samples/01-MailRoom.hrm"),
+            &cases,
+            &Settings::default(),
+            &ParseOptions::default(),
+        );
+
+        assert_eq!(results.file, "01-MailRoom.hrm");
+        let summary = results.summary.unwrap();
+        assert_eq!(summary.passed, 1);
+        assert_eq!(summary.cases, 2);
+        assert!(summary.size > 0 && summary.steps > Some(0));
+        assert!(!report(&[ScriptResults {
+            file: results.file,
+            summary: Ok(summary),
+        }]));
+
+        let missing = run_script(
+            Path::new("./samples/missing.hrm"),
+            &cases,
+            &Settings::default(),
+            &ParseOptions::default(),
+        );
+        assert!(missing.summary.is_err());
+    }
+}
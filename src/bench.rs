@@ -0,0 +1,144 @@
+//! Benchmark of the interpreter: the script is run many times on the same inbox,
+//! and the wall time of the runs is measured.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use hrm_interpreter::{
+    interpreter::{memory::Memory, settings::Settings, ExecuteScriptError, Interpreter},
+    script_object::{value_box::ValueBox, ScriptObject},
+};
+
+/// Number of measured runs, when --iterations isn't given
+pub const DEFAULT_ITERATIONS: usize = 100;
+
+#[derive(Debug)]
+pub struct Benchmark {
+    /// Runs made before the measured ones, to warm up the caches and the allocations
+    pub warmups: usize,
+    /// Number of steps of each run
+    pub steps: usize,
+    /// How the tiles of the floor are stored (see `Memory::storage`)
+    pub storage: &'static str,
+    /// The wall time of the measured runs, sorted
+    pub times: Vec<Duration>,
+}
+
+impl Benchmark {
+    /// The time under which the given percentage of the runs are
+    pub fn percentile(&self, percent: usize) -> Duration {
+        let index = (self.times.len() * percent).div_ceil(100).max(1) - 1;
+        self.times[index.min(self.times.len() - 1)]
+    }
+
+    /// The steps executed per second, by the median run
+    pub fn steps_per_second(&self) -> f64 {
+        self.steps as f64 / self.percentile(50).as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+}
+
+/// Run the script `iterations` times (after a tenth of them as warmups),
+/// resetting the same interpreter between the runs
+pub fn run(
+    script: &ScriptObject,
+    memory: &HashMap<usize, ValueBox>,
+    max_memory_address: usize,
+    inputs: &[ValueBox],
+    settings: &Settings,
+    iterations: usize,
+) -> Result<Benchmark, ExecuteScriptError> {
+    let warmups = (iterations / 10).max(1);
+    let mut interpreter = Interpreter::with_settings(Memory::default(), settings.clone());
+    let mut times = Vec::with_capacity(iterations);
+    let mut steps = 0;
+    let mut storage = "";
+    for run in 0..warmups + iterations {
+        let memory = Memory::with_data(memory.clone(), max_memory_address);
+        storage = memory.storage();
+        interpreter.reset(memory);
+
+        let start = Instant::now();
+        let (_, stats) = interpreter.execute(script, inputs)?;
+        let time = start.elapsed();
+
+        steps = stats.steps;
+        if run >= warmups {
+            times.push(time);
+        }
+    }
+    times.sort();
+
+    Ok(Benchmark {
+        warmups,
+        steps,
+        storage,
+        times,
+    })
+}
+
+/// Print the statistics of the benchmark to stderr
+pub fn report(benchmark: &Benchmark) {
+    eprintln!(
+        "{} runs of {} steps (after {} warmups), memory stored in a {}",
+        benchmark.times.len(),
+        benchmark.steps,
+        benchmark.warmups,
+        benchmark.storage
+    );
+    eprintln!("Min:    {:.1?}", benchmark.percentile(0));
+    eprintln!("Median: {:.1?}", benchmark.percentile(50));
+    eprintln!("p99:    {:.1?}", benchmark.percentile(99));
+    eprintln!(
+        "Speed:  {:.1} million steps/s (median)",
+        benchmark.steps_per_second() / 1e6
+    );
+}
+
+#[cfg(test)]
+mod bench_tests {
+    use super::*;
+
+    #[test]
+    fn test_benchmark() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            COPYTO   0
+            OUTBOX
+            JUMP     a
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        let inputs = [1, 2, 3].map(ValueBox::from);
+
+        let benchmark = run(
+            &script,
+            &HashMap::new(),
+            4,
+            &inputs,
+            &Settings::default(),
+            20,
+        )
+        .unwrap();
+        assert_eq!(benchmark.times.len(), 20);
+        assert_eq!(benchmark.warmups, 2);
+        assert_eq!(benchmark.steps, 13);
+        assert_eq!(benchmark.storage, "vec");
+        assert!(benchmark.percentile(0) <= benchmark.percentile(50));
+        assert!(benchmark.percentile(50) <= benchmark.percentile(99));
+        assert_eq!(benchmark.percentile(99), benchmark.times[19]);
+
+        let benchmark = run(
+            &script,
+            &HashMap::new(),
+            usize::MAX,
+            &inputs,
+            &Settings::default(),
+            1,
+        )
+        .unwrap();
+        assert_eq!(benchmark.storage, "map");
+    }
+}
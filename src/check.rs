@@ -0,0 +1,163 @@
+use std::fmt::Display;
+
+use hrm_interpreter::script_object::value_box::ValueBox;
+
+use crate::style::{Stream, Style};
+
+#[derive(Debug, PartialEq)]
+/// The first output that differs from the expected outputs.
+/// A missing value means that one of the lists is shorter than the other.
+pub struct Mismatch {
+    pub index: usize,
+    pub expected: Option<ValueBox>,
+    pub actual: Option<ValueBox>,
+}
+
+impl Mismatch {
+    fn render(&self, paint: impl Fn(Style, String) -> String) -> String {
+        let value_or_nothing = |value: Option<ValueBox>| match value {
+            Some(value) => value.to_string(),
+            None => "(nothing)".to_string(),
+        };
+        format!(
+            "First mismatch at output #{}:\n  expected: {}\n  actual:   {}",
+            self.index + 1,
+            paint(Style::Success, value_or_nothing(self.expected)),
+            paint(Style::Error, value_or_nothing(self.actual))
+        )
+    }
+
+    /// The mismatch with the expected value in green and the actual one in red, on a colored stderr
+    pub fn painted(&self) -> String {
+        self.render(|style, text| Stream::Stderr.paint(style, text))
+    }
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render(|_, text| text))
+    }
+}
+
+/// Find the first output that differs from the expected outputs
+pub fn first_mismatch(expected: &[ValueBox], actual: &[ValueBox]) -> Option<Mismatch> {
+    (0..expected.len().max(actual.len()))
+        .map(|index| Mismatch {
+            index,
+            expected: expected.get(index).copied(),
+            actual: actual.get(index).copied(),
+        })
+        .find(|mismatch| mismatch.expected != mismatch.actual)
+}
+
+pub fn join_values(values: &[ValueBox]) -> String {
+    values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// The values, with the one of the index in the style on a colored stderr
+fn join_painted(values: &[ValueBox], index: usize, style: Style) -> String {
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            if i == index {
+                Stream::Stderr.paint(style, value)
+            } else {
+                value.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Compare the outputs with the expected ones and print the verdict to stderr.
+/// Returns true if they match.
+pub fn report(
+    title: &str,
+    inputs: &[ValueBox],
+    expected: &[ValueBox],
+    actual: &[ValueBox],
+) -> bool {
+    match first_mismatch(expected, actual) {
+        None => {
+            eprintln!(
+                "{}: {}",
+                title,
+                Stream::Stderr.paint(Style::Success, "passed")
+            );
+            true
+        }
+        Some(mismatch) => {
+            eprintln!(
+                "{}: {}",
+                title,
+                Stream::Stderr.paint(Style::Error, "failed")
+            );
+            eprintln!("{}", mismatch.painted());
+            eprintln!("Inbox:    {}", join_values(inputs));
+            eprintln!(
+                "Expected: {}",
+                join_painted(expected, mismatch.index, Style::Success)
+            );
+            eprintln!(
+                "Actual:   {}",
+                join_painted(actual, mismatch.index, Style::Error)
+            );
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod check_tests {
+    use super::*;
+
+    #[test]
+    fn test_first_mismatch() {
+        let expected = [ValueBox::Number(1), ValueBox::Number(2)];
+
+        assert_eq!(first_mismatch(&expected, &expected), None);
+        assert_eq!(
+            first_mismatch(&expected, &[ValueBox::Number(1), ValueBox::Character('A')]),
+            Some(Mismatch {
+                index: 1,
+                expected: Some(ValueBox::Number(2)),
+                actual: Some(ValueBox::Character('A')),
+            })
+        );
+        assert_eq!(
+            first_mismatch(&expected, &[ValueBox::Number(1)]),
+            Some(Mismatch {
+                index: 1,
+                expected: Some(ValueBox::Number(2)),
+                actual: None,
+            })
+        );
+        assert_eq!(
+            first_mismatch(&[], &[ValueBox::Number(3)]),
+            Some(Mismatch {
+                index: 0,
+                expected: None,
+                actual: Some(ValueBox::Number(3)),
+            })
+        );
+    }
+
+    #[test]
+    fn test_mismatch_display() {
+        let mismatch = Mismatch {
+            index: 2,
+            expected: Some(ValueBox::Number(12)),
+            actual: None,
+        };
+
+        assert_eq!(
+            mismatch.to_string(),
+            "First mismatch at output #3:\n  expected: 12\n  actual:   (nothing)"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::{fs, io, path::Path};
+
+use hrm_interpreter::{
+    interpreter::{
+        snapshot::InterpreterSnapshot, ExecuteScriptError, ExecutionStatus, Interpreter,
+    },
+    script_object::{value_box::ValueBox, ScriptObject},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::log;
+
+/// Checkpoints are saved in this file unless another one is given
+pub const DEFAULT_FILE: &str = "checkpoint.json";
+/// Number of steps between checkpoints unless another one is given
+pub const DEFAULT_INTERVAL: usize = 1_000_000;
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Everything needed to resume an execution: the interpreter, and its inputs and outputs
+pub struct Checkpoint {
+    pub inputs: Vec<ValueBox>,
+    pub outputs: Vec<ValueBox>,
+    pub interpreter: InterpreterSnapshot,
+}
+
+#[derive(Debug, thiserror::Error)]
+/// Error that can occur when reading a checkpoint file.
+pub enum ReadCheckpointError {
+    #[error("could not read the checkpoint file:\n\t{0}")]
+    Io(#[from] io::Error),
+    #[error("invalid checkpoint:\n\t{0}")]
+    InvalidJson(#[from] serde_json::Error),
+}
+
+pub fn read_checkpoint(path: impl AsRef<Path>) -> Result<Checkpoint, ReadCheckpointError> {
+    let content = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Write the checkpoint to a temporary file first,
+/// so that an interruption never leaves a broken checkpoint behind.
+pub fn write_checkpoint(path: impl AsRef<Path>, checkpoint: &Checkpoint) -> io::Result<()> {
+    let path = path.as_ref();
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, serde_json::to_string(checkpoint)?)?;
+    fs::rename(temporary, path)
+}
+
+/// Execute the script to the end like `Interpreter::execute`,
+/// saving a checkpoint every `interval` steps, and when the execution fails.
+/// The outputs are added to `outputs`.
+pub fn execute_with_checkpoints(
+    interpreter: &mut Interpreter,
+    script: &ScriptObject,
+    inputs: &[ValueBox],
+    outputs: &mut Vec<ValueBox>,
+    interval: usize,
+    path: &str,
+) -> Result<(), ExecuteScriptError> {
+    let save = |interpreter: &Interpreter, outputs: &[ValueBox]| {
+        let checkpoint = Checkpoint {
+            inputs: inputs.to_vec(),
+            outputs: outputs.to_vec(),
+            interpreter: interpreter.snapshot(),
+        };
+        match write_checkpoint(path, &checkpoint) {
+            Ok(()) => log::trace(
+                "checkpoint",
+                format!("saved to {} at step {}", path, interpreter.steps()),
+            ),
+            Err(e) => log::warning(format!("could not save the checkpoint to {}: {}", path, e)),
+        }
+    };
+
+    loop {
+        match interpreter.step(script, inputs, &mut *outputs) {
+            Ok(ExecutionStatus::Finished) => return Ok(()),
+            Ok(ExecutionStatus::Paused(_)) => {
+                if interpreter.steps().is_multiple_of(interval) {
+                    save(interpreter, outputs);
+                }
+            }
+            Err(e) => {
+                // The state before the failing instruction, to inspect it
+                save(interpreter, outputs);
+                return Err(e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod checkpoint_tests {
+    use hrm_interpreter::interpreter::memory::Memory;
+
+    use super::*;
+
+    const SCRIPT: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+    a:
+        INBOX
+        OUTBOX
+        JUMP a
+    ";
+
+    #[test]
+    fn test_checkpoints_resume_the_execution() {
+        let script = SCRIPT.parse::<ScriptObject>().unwrap();
+        let inputs = [1.into(), 2.into(), 3.into()];
+        let path = std::env::temp_dir().join("hrm_checkpoint_test.json");
+        let path = path.to_str().unwrap();
+
+        let mut interpreter = Interpreter::new(Memory::default());
+        let mut outputs = vec![];
+        execute_with_checkpoints(&mut interpreter, &script, &inputs, &mut outputs, 5, path)
+            .unwrap();
+
+        // Last saved after the 5th step: the first two inputs were sent to the outbox
+        let checkpoint = read_checkpoint(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(checkpoint.outputs, vec![1.into(), 2.into()]);
+        assert_eq!(checkpoint.inputs, inputs);
+
+        let mut resumed = Interpreter::from_snapshot(checkpoint.interpreter);
+        let mut resumed_outputs = checkpoint.outputs;
+        let stats = resumed
+            .execute_into(&script, &inputs, &mut resumed_outputs)
+            .unwrap();
+        assert_eq!(resumed_outputs, outputs);
+        assert_eq!(&stats, interpreter.stats());
+    }
+}
@@ -1,36 +1,724 @@
-use std::{collections::HashMap, env, error::Error, fs, str::FromStr};
+use std::{
+    collections::{BTreeSet, HashMap},
+    env,
+    error::Error,
+    fs,
+    io::{self, IsTerminal, Read},
+    ops::Range,
+    path::Path,
+    str::FromStr,
+    time::Duration,
+};
 
-use crate::script_object::value_box::ValueBox;
+use crate::{
+    config::{self, Config, ReadConfigError},
+    level::LevelFile,
+    log::{self, Verbosity},
+    memory_file,
+    output::{ErrorFormat, OutputFormat, OutputOptions, TraceFormat},
+    property::Property,
+    style,
+};
+use hrm_interpreter::{
+    compile::Target,
+    generator::{self, InboxSpec},
+    interpreter::{
+        breakpoint::Breakpoint,
+        geometry::FloorGeometry,
+        memory::{Memory, SetMemoryError},
+        settings::{Limits, Settings},
+    },
+    levels::{self, Level},
+    script_object::{
+        game,
+        instruction::ParseOptions,
+        value_box::{TileNames, ValueBox},
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+/// What the interpreter does with the script
+pub enum Mode {
+    /// Execute the script and print its outputs
+    #[default]
+    Run,
+    /// Execute the script and only tell whether its outputs are the expected ones
+    Check,
+    /// Run the test cases written in the script ("-- TEST: inputs = 1 2 3 ; expected = 6")
+    Test,
+    /// Print the script in the canonical format, without executing it
+    Fmt,
+    /// Print the script in the format pasted in the game, without executing it
+    Export,
+    /// Report suspicious constructs in the script, without executing it
+    Lint,
+    /// Print the control flow graph of the script, without executing it
+    Graph,
+    /// Compile the script to the source of another language, without executing it
+    Transpile,
+    /// Print each instruction with its index, block, jump target and tile, without executing it
+    Listing,
+    /// Print the script as structured pseudocode (loops and ifs), without executing it
+    Decompile,
+    /// Serve the Debug Adapter Protocol on stdin and stdout, the script is given by the editor
+    Dap,
+    /// Run every script of a directory on the test cases, the script path being the directory
+    Batch,
+    /// Serve the Language Server Protocol on stdin and stdout, the scripts are given by the editor
+    Lsp,
+    /// Execute the instructions typed at the prompt, without a script
+    Repl,
+    /// Run the test cases on mutants of the script, to find the instructions they don't check
+    Mutate,
+    /// Compare the script with another one on all the inboxes up to a length
+    Equiv,
+    /// Run the script many times on the same inbox, and report its wall time
+    Bench,
+    /// Run several scripts, the outbox of each one being the inbox of the next one
+    Pipe,
+    /// Check properties of the outbox on every inbox up to a length by symbolic execution (experimental)
+    SolveFor,
+    /// Look for the smallest scripts solving a level by trying all the short ones (experimental)
+    Search,
+}
+
+/// The subcommands: their name, the mode they select, and their usage for the help
+const SUBCOMMANDS: [(&str, Mode, &str, &str); 20] = [
+    ("run", Mode::Run, "Usage:", "[run] <script_file> [options]"),
+    (
+        "check",
+        Mode::Check,
+        "Check:",
+        "check <script_file> -e <value>... | -L <number> [options]",
+    ),
+    (
+        "test",
+        Mode::Test,
+        "Test:",
+        "test <script_file> [-t <file>] [options]",
+    ),
+    (
+        "mutate",
+        Mode::Mutate,
+        "Mutate:",
+        "mutate <script_file> [-t <file>] [options]",
+    ),
+    (
+        "equiv",
+        Mode::Equiv,
+        "Equivalence:",
+        "equiv <script_file> <other_script_file> [--max-len <length>] [--range <min>..<max>] [options]",
+    ),
+    (
+        "solve-for",
+        Mode::SolveFor,
+        "Symbolic execution:",
+        "solve-for <script_file> [--assert <property>...] [--max-len <length>] [--range <min>..<max>] [options]",
+    ),
+    (
+        "search",
+        Mode::Search,
+        "Synthesis:",
+        "search -L <number> [--max-size <count>] [options]",
+    ),
+    (
+        "bench",
+        Mode::Bench,
+        "Benchmark:",
+        "bench <script_file> [-i <value>...] [--iterations <count>] [options]",
+    ),
+    (
+        "pipe",
+        Mode::Pipe,
+        "Pipeline:",
+        "pipe <script_file> <script_file>... [--stage-memory <stage>=<memory_file>...] [options]",
+    ),
+    ("fmt", Mode::Fmt, "Format:", "fmt <script_file> [--check]"),
+    (
+        "export",
+        Mode::Export,
+        "Export:",
+        "export <script_file> [--from-save -L <number>] [-x]",
+    ),
+    (
+        "lint",
+        Mode::Lint,
+        "Lint:",
+        "lint <script_file> [-m <address> <value>... | -L <number>]",
+    ),
+    (
+        "graph",
+        Mode::Graph,
+        "Graph:",
+        "graph <script_file> [--format dot]",
+    ),
+    (
+        "transpile",
+        Mode::Transpile,
+        "Transpile:",
+        "transpile <script_file> [--target <language>]",
+    ),
+    (
+        "listing",
+        Mode::Listing,
+        "Listing:",
+        "listing <script_file> [-l <name>=<address>...]",
+    ),
+    (
+        "decompile",
+        Mode::Decompile,
+        "Decompile:",
+        "decompile <script_file>",
+    ),
+    ("repl", Mode::Repl, "REPL:", "repl [options]"),
+    (
+        "batch",
+        Mode::Batch,
+        "Batch:",
+        "batch <directory> -t <file> [options]",
+    ),
+    ("dap", Mode::Dap, "Debug adapter:", "dap"),
+    ("lsp", Mode::Lsp, "LSP server:", "lsp"),
+];
+
+/// The subcommand given as the first argument, if any
+fn subcommand(
+    first_arg: Option<&String>,
+) -> Option<&'static (&'static str, Mode, &'static str, &'static str)> {
+    SUBCOMMANDS
+        .iter()
+        .find(|(name, ..)| first_arg.is_some_and(|arg| arg == name))
+}
+
+#[derive(Debug, thiserror::Error)]
+/// A command line that can't be used: it doesn't follow the usage of its subcommand,
+/// or an option is given an invalid value
+pub enum CliError {
+    #[error("expected a script file")]
+    MissingScript,
+    #[error("unknown option: {0}")]
+    UnknownOption(String),
+    #[error("unexpected argument: {0}")]
+    UnexpectedArgument(String),
+    #[error("{option} expects {expected}, got {got}")]
+    WrongValues {
+        option: String,
+        expected: String,
+        got: String,
+    },
+    #[error("invalid value for {option}: {value} ({reason})")]
+    InvalidValue {
+        option: String,
+        value: String,
+        reason: String,
+    },
+    #[error("{0}")]
+    Config(#[from] ReadConfigError),
+    #[error("could not read the inputs from stdin: {0}")]
+    Stdin(String),
+}
+
+impl CliError {
+    /// The option the error is about, to print its help
+    fn option(&self) -> Option<CommandLineOption> {
+        match self {
+            Self::WrongValues { option, .. } | Self::InvalidValue { option, .. } => {
+                option.parse().ok()
+            }
+            Self::Config(_) => Some(CommandLineOption::Config),
+            Self::Stdin(_) => Some(CommandLineOption::InputValues),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct CommandLineArgs {
+    pub mode: Mode,
+    /// The path of the script, to point at it in error messages
+    pub script_path: String,
+    /// The content of the script
     pub script_file: String,
     pub input_values: Vec<ValueBox>,
     pub memory: HashMap<usize, ValueBox>,
     pub max_memory_address: usize,
+    pub breakpoints: Vec<Breakpoint>,
+    pub max_steps: Option<usize>,
+    pub print_stats: bool,
+    pub strict_hrm: bool,
+    pub format: OutputFormat,
+    pub tile_names: TileNames,
+    pub level: Option<usize>,
+    pub expected_outputs: Option<Vec<ValueBox>>,
+    pub test_file: Option<String>,
+    pub input_spec: Option<InboxSpec>,
+    pub compare_script: Option<String>,
+    /// The scripts of the stages after the first one, with pipe
+    pub pipe_scripts: Vec<String>,
+    pub trace: bool,
+    pub trace_file: Option<String>,
+    pub trace_format: TraceFormat,
+    pub watchpoints: Vec<usize>,
+    pub stream: bool,
+    pub checkpoint_every: Option<usize>,
+    pub checkpoint_file: Option<String>,
+    pub resume_file: Option<String>,
+    pub check_format: bool,
+    pub coverage: bool,
+    pub require_full_coverage: bool,
+    pub profile: bool,
+    pub optimize: bool,
+    pub remove_dead_instructions: bool,
+    pub score: bool,
+    pub score_runs: Option<usize>,
+    pub tui: bool,
+    pub watch_files: bool,
+    /// The file the memory was read from, if any
+    pub memory_file: Option<String>,
+    pub jobs: usize,
+    pub error_format: ErrorFormat,
+    pub dump_memory: bool,
+    pub dump_memory_file: Option<String>,
+    pub dump_head: bool,
+    pub level_file: Option<String>,
+    pub report_file: Option<String>,
+    pub extensions: bool,
+    pub stack: Option<Range<usize>>,
+    pub second_hand: bool,
+    pub lenient: bool,
+    pub output_as_strings: bool,
+    pub output_separator: Option<String>,
+    pub newline: Option<bool>,
+    pub output_file: Option<String>,
+    pub config_file: Option<String>,
+    pub preset: Option<String>,
+    pub record_file: Option<String>,
+    pub replay_file: Option<String>,
+    pub seed: Option<u64>,
+    pub max_length: Option<usize>,
+    pub value_range: Option<Vec<ValueBox>>,
+    pub timeout: Option<Duration>,
+    pub iterations: Option<usize>,
+    pub cost_file: Option<String>,
+    pub stage_memory: HashMap<usize, HashMap<usize, ValueBox>>,
+    pub stage_max_memory: HashMap<usize, usize>,
+    pub floor: Option<FloorGeometry>,
+    pub max_instructions: Option<usize>,
+    pub check_loops: bool,
+    pub properties: Vec<Property>,
+    pub max_size: Option<usize>,
+    pub target: Target,
+    pub from_save: bool,
+    pub limits: Limits,
+    pub verbosity: Verbosity,
+    pub no_color: bool,
 }
 
 enum CommandLineOption {
     InputValues,
     Memory,
     MaxMemoryAddress,
+    Breakpoints,
+    MaxSteps,
+    Stats,
+    StrictHrm,
+    Format,
+    TileNames,
+    Level,
+    Expected,
+    TestFile,
+    GenerateInputs,
+    Compare,
+    Trace,
+    TraceFormat,
+    Watchpoints,
+    Stream,
+    CheckpointEvery,
+    CheckpointFile,
+    Resume,
+    CheckFormat,
+    Coverage,
+    RequireFullCoverage,
+    Profile,
+    Optimize,
+    RemoveDeadInstructions,
+    Score,
+    Runs,
+    Tui,
+    WatchFiles,
+    Jobs,
+    ErrorFormat,
+    DumpMemory,
+    DumpHead,
+    LevelFile,
+    Report,
+    Extensions,
+    Stack,
+    SecondHand,
+    Lenient,
+    InputString,
+    OutputAsStrings,
+    OutputSeparator,
+    Newline,
+    NoNewline,
+    OutputFile,
+    Config,
+    Preset,
+    Record,
+    Replay,
+    Seed,
+    MaxLength,
+    ValueRange,
+    Timeout,
+    Iterations,
+    Costs,
+    StageMemory,
+    StageMaxMemory,
+    Floor,
+    MaxInstructions,
+    CheckLoops,
+    Assert,
+    MaxSize,
+    Target,
+    FromSave,
+    MaxTiles,
+    MaxAllocations,
+    MaxOutputs,
+    Verbose,
+    Quiet,
+    NoColor,
 }
 
 impl CommandLineArgs {
     fn default(script_file: String) -> Self {
         Self {
+            mode: Mode::Run,
+            script_path: String::new(),
             script_file,
             input_values: Vec::new(),
             memory: HashMap::new(),
             max_memory_address: usize::MAX,
+            breakpoints: Vec::new(),
+            max_steps: None,
+            print_stats: false,
+            strict_hrm: false,
+            format: OutputFormat::Text,
+            tile_names: TileNames::new(),
+            level: None,
+            expected_outputs: None,
+            test_file: None,
+            input_spec: None,
+            compare_script: None,
+            pipe_scripts: vec![],
+            trace: false,
+            trace_file: None,
+            trace_format: TraceFormat::Text,
+            watchpoints: Vec::new(),
+            stream: false,
+            checkpoint_every: None,
+            checkpoint_file: None,
+            resume_file: None,
+            check_format: false,
+            coverage: false,
+            require_full_coverage: false,
+            profile: false,
+            optimize: false,
+            remove_dead_instructions: false,
+            score: false,
+            score_runs: None,
+            tui: false,
+            watch_files: false,
+            memory_file: None,
+            jobs: 1,
+            error_format: ErrorFormat::Text,
+            dump_memory: false,
+            dump_memory_file: None,
+            dump_head: false,
+            level_file: None,
+            report_file: None,
+            extensions: false,
+            stack: None,
+            second_hand: false,
+            lenient: false,
+            output_as_strings: false,
+            output_separator: None,
+            newline: None,
+            output_file: None,
+            config_file: None,
+            preset: None,
+            record_file: None,
+            replay_file: None,
+            seed: None,
+            max_length: None,
+            value_range: None,
+            timeout: None,
+            iterations: None,
+            cost_file: None,
+            stage_memory: HashMap::new(),
+            stage_max_memory: HashMap::new(),
+            floor: None,
+            max_instructions: None,
+            check_loops: false,
+            properties: vec![],
+            max_size: None,
+            target: Target::Rust,
+            from_save: false,
+            limits: Limits::default(),
+            verbosity: Verbosity::Normal,
+            no_color: false,
+        }
+    }
+
+    /// Whether the execution is controlled by the debugger
+    pub fn is_debugging(&self) -> bool {
+        !self.breakpoints.is_empty() || !self.watchpoints.is_empty()
+    }
+
+    /// How the scripts are read
+    pub fn parse_options(&self) -> ParseOptions {
+        // The names given with --labels take precedence over the ones of the floor
+        let mut tile_names = self
+            .floor
+            .map(|floor| floor.tile_names())
+            .unwrap_or_default();
+        tile_names.extend(self.tile_names.clone());
+        ParseOptions {
+            tile_names,
+            extensions: self.extensions,
+            include_dir: Path::new(&self.script_path).parent().map(Path::to_path_buf),
+            // The game's syntax is kept with --strict-hrm
+            lenient: self.lenient && !self.strict_hrm,
+        }
+    }
+
+    /// Take the options that aren't given on the command line from the configuration
+    fn apply_config(&mut self, config: Config) {
+        if self.max_memory_address == usize::MAX {
+            if let Some(max_memory_address) = config.max_memory_address {
+                self.max_memory_address = max_memory_address;
+            }
+        }
+        if self.memory.is_empty() && self.memory_file.is_none() {
+            self.memory = config.memory;
+        }
+        self.extensions |= config.extensions;
+        self.strict_hrm |= config.strict_hrm;
+        // With a test file, only its cases are run: it's only a default where cases are expected
+        if self.test_file.is_none() && matches!(self.mode, Mode::Test | Mode::Mutate | Mode::Batch)
+        {
+            self.test_file = config.test_file;
+        }
+    }
+
+    /// Check that the tiles of --memory are on the floor of -M (--max-mem),
+    /// and that the floor of each stage of pipe holds its tiles
+    fn check_memory(&self) -> Result<(), CliError> {
+        if let Err(SetMemoryError::OutOfBounds {
+            address,
+            max_address,
+        }) = Memory::check_tiles(&self.memory, self.max_memory_address)
+        {
+            return Err(CommandLineOption::Memory.invalid(
+                &address.to_string(),
+                format!("past {}, the last address of the floor", max_address),
+            ));
+        }
+        let stages = (self.stage_memory.keys())
+            .chain(self.stage_max_memory.keys())
+            .collect::<BTreeSet<_>>();
+        for stage in stages {
+            let max_memory_address =
+                (self.stage_max_memory.get(stage).copied()).unwrap_or(self.max_memory_address);
+            let stage_memory = self.stage_memory.get(stage);
+            let memory = stage_memory.unwrap_or(&self.memory);
+            if let Err(SetMemoryError::OutOfBounds {
+                address,
+                max_address,
+            }) = Memory::check_tiles(memory, max_memory_address)
+            {
+                return Err(match stage_memory {
+                    Some(_) => CommandLineOption::StageMemory.invalid(
+                        &address.to_string(),
+                        format!(
+                            "past {}, the last address of the floor of stage {}",
+                            max_address, stage
+                        ),
+                    ),
+                    None => CommandLineOption::StageMaxMemory.invalid(
+                        &format!("{}={}", stage, max_address),
+                        format!("the floor of the stage has a value on tile {}", address),
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Take the floor of the level where -M and --memory don't give it
+    pub fn apply_level(&mut self, level: &Level) -> Result<(), CliError> {
+        self.apply_floor(
+            CommandLineOption::Level,
+            &level.number.to_string(),
+            Some(level.max_address()),
+            level.memory(),
+        )
+    }
+
+    /// Take the floor of the level file where -M and --memory don't give it
+    pub fn apply_level_file(&mut self, level_file: &LevelFile) -> Result<(), CliError> {
+        let file = self.level_file.clone().unwrap_or_default();
+        self.apply_floor(
+            CommandLineOption::LevelFile,
+            &file,
+            level_file.floor_size.map(|size| size - 1),
+            level_file.floor.clone(),
+        )
+    }
+
+    /// Take the size of the floor and its values where -M and --memory don't give them,
+    /// and check that the values are on the floor
+    fn apply_floor(
+        &mut self,
+        option: CommandLineOption,
+        value: &str,
+        max_address: Option<usize>,
+        memory: HashMap<usize, ValueBox>,
+    ) -> Result<(), CliError> {
+        if let (Some(max_address), usize::MAX) = (max_address, self.max_memory_address) {
+            self.max_memory_address = max_address;
+        }
+        if !self.memory.is_empty() {
+            return self.check_memory();
+        }
+        match Memory::check_tiles(&memory, self.max_memory_address) {
+            Err(SetMemoryError::OutOfBounds {
+                address,
+                max_address,
+            }) => Err(option.invalid(
+                value,
+                format!(
+                    "its floor has a value on tile {}, past {}, the last address of the floor",
+                    address, max_address
+                ),
+            )),
+            _ => {
+                self.memory = memory;
+                Ok(())
+            }
+        }
+    }
+
+    /// Stop on an error found after reading the command line, like the ones found while reading it
+    pub fn exit_with_error(&self, error: &CliError) -> ! {
+        exit_with_error(error, usage(self.mode))
+    }
+
+    /// How the scripts are executed, without history: only the debugger can rewind them
+    pub fn settings(&self) -> Settings {
+        Settings {
+            max_steps: self.max_steps,
+            timeout: self.timeout,
+            strict_hrm: self.strict_hrm,
+            history_size: 0,
+            stack: self.stack.clone(),
+            second_hand: self.second_hand,
+            limits: self.limits,
+        }
+    }
+
+    /// How the results are written: the streamed outputs are one per line by default
+    pub fn output_options(&self) -> OutputOptions {
+        let separator = if self.stream { "\n" } else { " " };
+        OutputOptions {
+            format: self.format,
+            as_strings: self.output_as_strings,
+            separator: self
+                .output_separator
+                .clone()
+                .unwrap_or_else(|| separator.to_string()),
+            newline: self.newline.unwrap_or(self.stream),
+            file: self.output_file.clone(),
         }
     }
 }
 
 // Enum methods
 impl CommandLineOption {
-    fn all_options() -> [CommandLineOption; 3] {
-        [Self::InputValues, Self::Memory, Self::MaxMemoryAddress]
+    fn all_options() -> [CommandLineOption; 72] {
+        [
+            Self::InputValues,
+            Self::Memory,
+            Self::MaxMemoryAddress,
+            Self::Breakpoints,
+            Self::MaxSteps,
+            Self::Stats,
+            Self::StrictHrm,
+            Self::Format,
+            Self::TileNames,
+            Self::Level,
+            Self::Expected,
+            Self::TestFile,
+            Self::GenerateInputs,
+            Self::Compare,
+            Self::Trace,
+            Self::TraceFormat,
+            Self::Watchpoints,
+            Self::Stream,
+            Self::CheckpointEvery,
+            Self::CheckpointFile,
+            Self::Resume,
+            Self::CheckFormat,
+            Self::Coverage,
+            Self::RequireFullCoverage,
+            Self::Profile,
+            Self::Optimize,
+            Self::RemoveDeadInstructions,
+            Self::Score,
+            Self::Runs,
+            Self::Tui,
+            Self::WatchFiles,
+            Self::Jobs,
+            Self::ErrorFormat,
+            Self::DumpMemory,
+            Self::DumpHead,
+            Self::LevelFile,
+            Self::Report,
+            Self::Extensions,
+            Self::Stack,
+            Self::SecondHand,
+            Self::Lenient,
+            Self::InputString,
+            Self::OutputAsStrings,
+            Self::OutputSeparator,
+            Self::Newline,
+            Self::NoNewline,
+            Self::OutputFile,
+            Self::Config,
+            Self::Preset,
+            Self::Record,
+            Self::Replay,
+            Self::Seed,
+            Self::MaxLength,
+            Self::ValueRange,
+            Self::Timeout,
+            Self::Iterations,
+            Self::Costs,
+            Self::StageMemory,
+            Self::StageMaxMemory,
+            Self::Floor,
+            Self::MaxInstructions,
+            Self::CheckLoops,
+            Self::Assert,
+            Self::MaxSize,
+            Self::Target,
+            Self::FromSave,
+            Self::MaxTiles,
+            Self::MaxAllocations,
+            Self::MaxOutputs,
+            Self::Verbose,
+            Self::Quiet,
+            Self::NoColor,
+        ]
     }
 }
 
@@ -42,6 +730,75 @@ impl FromStr for CommandLineOption {
             "-i" | "--inputs" => Ok(Self::InputValues),
             "-m" | "--memory" => Ok(Self::Memory),
             "-M" | "--max-mem" => Ok(Self::MaxMemoryAddress),
+            "-b" | "--breakpoints" => Ok(Self::Breakpoints),
+            "-s" | "--max-steps" => Ok(Self::MaxSteps),
+            "-S" | "--stats" => Ok(Self::Stats),
+            "-H" | "--strict-hrm" => Ok(Self::StrictHrm),
+            "-f" | "--format" => Ok(Self::Format),
+            "-l" | "--labels" => Ok(Self::TileNames),
+            "-L" | "--level" => Ok(Self::Level),
+            "-e" | "--expected" => Ok(Self::Expected),
+            "-t" | "--test" => Ok(Self::TestFile),
+            "-g" | "--gen-inputs" => Ok(Self::GenerateInputs),
+            "-c" | "--compare" => Ok(Self::Compare),
+            "-T" | "--trace" => Ok(Self::Trace),
+            "-F" | "--trace-format" => Ok(Self::TraceFormat),
+            "-w" | "--watch" => Ok(Self::Watchpoints),
+            "-o" | "--stream" => Ok(Self::Stream),
+            "-k" | "--checkpoint-every" => Ok(Self::CheckpointEvery),
+            "-K" | "--checkpoint-file" => Ok(Self::CheckpointFile),
+            "-r" | "--resume" => Ok(Self::Resume),
+            "-C" | "--check" => Ok(Self::CheckFormat),
+            "-X" | "--coverage" => Ok(Self::Coverage),
+            "-V" | "--require-full-coverage" => Ok(Self::RequireFullCoverage),
+            "-p" | "--profile" => Ok(Self::Profile),
+            "-O" | "--optimize" => Ok(Self::Optimize),
+            "-D" | "--remove-dead-instructions" => Ok(Self::RemoveDeadInstructions),
+            "-z" | "--score" => Ok(Self::Score),
+            "-n" | "--runs" => Ok(Self::Runs),
+            "-u" | "--tui" => Ok(Self::Tui),
+            "-W" | "--watch-files" => Ok(Self::WatchFiles),
+            "-j" | "--jobs" => Ok(Self::Jobs),
+            "-E" | "--error-format" => Ok(Self::ErrorFormat),
+            "-d" | "--dump-memory" => Ok(Self::DumpMemory),
+            "-a" | "--dump-head" => Ok(Self::DumpHead),
+            "-y" | "--level-file" => Ok(Self::LevelFile),
+            "-R" | "--report" => Ok(Self::Report),
+            "-x" | "--extensions" => Ok(Self::Extensions),
+            "-P" | "--stack" => Ok(Self::Stack),
+            "-B" | "--second-hand" => Ok(Self::SecondHand),
+            "-N" | "--lenient" => Ok(Self::Lenient),
+            "-I" | "--input-string" => Ok(Self::InputString),
+            "-A" | "--output-as-strings" => Ok(Self::OutputAsStrings),
+            "-J" | "--output-sep" => Ok(Self::OutputSeparator),
+            "-Q" | "--newline" => Ok(Self::Newline),
+            "-Z" | "--no-newline" => Ok(Self::NoNewline),
+            "-U" | "--output-file" => Ok(Self::OutputFile),
+            "-G" | "--config" => Ok(Self::Config),
+            "-Y" | "--preset" => Ok(Self::Preset),
+            "--record" => Ok(Self::Record),
+            "--replay" => Ok(Self::Replay),
+            "--seed" => Ok(Self::Seed),
+            "--max-len" => Ok(Self::MaxLength),
+            "--range" => Ok(Self::ValueRange),
+            "--timeout" => Ok(Self::Timeout),
+            "--iterations" => Ok(Self::Iterations),
+            "--costs" => Ok(Self::Costs),
+            "--stage-memory" => Ok(Self::StageMemory),
+            "--stage-max-memory" => Ok(Self::StageMaxMemory),
+            "--floor" => Ok(Self::Floor),
+            "--max-instructions" => Ok(Self::MaxInstructions),
+            "--check-loops" => Ok(Self::CheckLoops),
+            "--assert" => Ok(Self::Assert),
+            "--max-size" => Ok(Self::MaxSize),
+            "--target" => Ok(Self::Target),
+            "--from-save" => Ok(Self::FromSave),
+            "--max-tiles" => Ok(Self::MaxTiles),
+            "--max-allocations" => Ok(Self::MaxAllocations),
+            "--max-outputs" => Ok(Self::MaxOutputs),
+            "-v" | "--verbose" => Ok(Self::Verbose),
+            "-q" | "--quiet" => Ok(Self::Quiet),
+            "--no-color" => Ok(Self::NoColor),
             _ => Err(format!("Invalid option: {}", s).into()),
         }
     }
@@ -49,11 +806,81 @@ impl FromStr for CommandLineOption {
 
 // Element methods
 impl CommandLineOption {
+    /// Empty for the options that only have a long name
     fn short_name(&self) -> &'static str {
         match self {
             Self::InputValues => "-i",
             Self::Memory => "-m",
             Self::MaxMemoryAddress => "-M",
+            Self::Breakpoints => "-b",
+            Self::MaxSteps => "-s",
+            Self::Stats => "-S",
+            Self::StrictHrm => "-H",
+            Self::Format => "-f",
+            Self::TileNames => "-l",
+            Self::Level => "-L",
+            Self::Expected => "-e",
+            Self::TestFile => "-t",
+            Self::GenerateInputs => "-g",
+            Self::Compare => "-c",
+            Self::Trace => "-T",
+            Self::TraceFormat => "-F",
+            Self::Watchpoints => "-w",
+            Self::Stream => "-o",
+            Self::CheckpointEvery => "-k",
+            Self::CheckpointFile => "-K",
+            Self::Resume => "-r",
+            Self::CheckFormat => "-C",
+            Self::Coverage => "-X",
+            Self::RequireFullCoverage => "-V",
+            Self::Profile => "-p",
+            Self::Optimize => "-O",
+            Self::RemoveDeadInstructions => "-D",
+            Self::Score => "-z",
+            Self::Runs => "-n",
+            Self::Tui => "-u",
+            Self::WatchFiles => "-W",
+            Self::Jobs => "-j",
+            Self::ErrorFormat => "-E",
+            Self::DumpMemory => "-d",
+            Self::DumpHead => "-a",
+            Self::LevelFile => "-y",
+            Self::Report => "-R",
+            Self::Extensions => "-x",
+            Self::Stack => "-P",
+            Self::SecondHand => "-B",
+            Self::Lenient => "-N",
+            Self::InputString => "-I",
+            Self::OutputAsStrings => "-A",
+            Self::OutputSeparator => "-J",
+            Self::Newline => "-Q",
+            Self::NoNewline => "-Z",
+            Self::OutputFile => "-U",
+            Self::Config => "-G",
+            Self::Preset => "-Y",
+            Self::Record => "",
+            Self::Replay => "",
+            Self::Seed => "",
+            Self::MaxLength => "",
+            Self::ValueRange => "",
+            Self::Timeout => "",
+            Self::Iterations => "",
+            Self::Costs => "",
+            Self::StageMemory => "",
+            Self::StageMaxMemory => "",
+            Self::Floor => "",
+            Self::MaxInstructions => "",
+            Self::CheckLoops => "",
+            Self::Assert => "",
+            Self::MaxSize => "",
+            Self::Target => "",
+            Self::FromSave => "",
+            Self::MaxTiles => "",
+            Self::MaxAllocations => "",
+            Self::MaxOutputs => "",
+            Self::Verbose => "-v",
+            Self::Quiet => "-q",
+            Self::NoColor => "",
         }
     }
 
@@ -62,6 +889,86 @@ impl CommandLineOption {
             Self::InputValues => "--inputs",
             Self::Memory => "--memory",
             Self::MaxMemoryAddress => "--max-mem",
+            Self::Breakpoints => "--breakpoints",
+            Self::MaxSteps => "--max-steps",
+            Self::Stats => "--stats",
+            Self::StrictHrm => "--strict-hrm",
+            Self::Format => "--format",
+            Self::TileNames => "--labels",
+            Self::Level => "--level",
+            Self::Expected => "--expected",
+            Self::TestFile => "--test",
+            Self::GenerateInputs => "--gen-inputs",
+            Self::Compare => "--compare",
+            Self::Trace => "--trace",
+            Self::TraceFormat => "--trace-format",
+            Self::Watchpoints => "--watch",
+            Self::Stream => "--stream",
+            Self::CheckpointEvery => "--checkpoint-every",
+            Self::CheckpointFile => "--checkpoint-file",
+            Self::Resume => "--resume",
+            Self::CheckFormat => "--check",
+            Self::Coverage => "--coverage",
+            Self::RequireFullCoverage => "--require-full-coverage",
+            Self::Profile => "--profile",
+            Self::Optimize => "--optimize",
+            Self::RemoveDeadInstructions => "--remove-dead-instructions",
+            Self::Score => "--score",
+            Self::Runs => "--runs",
+            Self::Tui => "--tui",
+            Self::WatchFiles => "--watch-files",
+            Self::Jobs => "--jobs",
+            Self::ErrorFormat => "--error-format",
+            Self::DumpMemory => "--dump-memory",
+            Self::DumpHead => "--dump-head",
+            Self::LevelFile => "--level-file",
+            Self::Report => "--report",
+            Self::Extensions => "--extensions",
+            Self::Stack => "--stack",
+            Self::SecondHand => "--second-hand",
+            Self::Lenient => "--lenient",
+            Self::InputString => "--input-string",
+            Self::OutputAsStrings => "--output-as-strings",
+            Self::OutputSeparator => "--output-sep",
+            Self::Newline => "--newline",
+            Self::NoNewline => "--no-newline",
+            Self::OutputFile => "--output-file",
+            Self::Config => "--config",
+            Self::Preset => "--preset",
+            Self::Record => "--record",
+            Self::Replay => "--replay",
+            Self::Seed => "--seed",
+            Self::MaxLength => "--max-len",
+            Self::ValueRange => "--range",
+            Self::Timeout => "--timeout",
+            Self::Iterations => "--iterations",
+            Self::Costs => "--costs",
+            Self::StageMemory => "--stage-memory",
+            Self::StageMaxMemory => "--stage-max-memory",
+            Self::Floor => "--floor",
+            Self::MaxInstructions => "--max-instructions",
+            Self::CheckLoops => "--check-loops",
+            Self::Assert => "--assert",
+            Self::MaxSize => "--max-size",
+            Self::Target => "--target",
+            Self::FromSave => "--from-save",
+            Self::MaxTiles => "--max-tiles",
+            Self::MaxAllocations => "--max-allocations",
+            Self::MaxOutputs => "--max-outputs",
+            Self::Verbose => "--verbose",
+            Self::Quiet => "--quiet",
+            Self::NoColor => "--no-color",
+        }
+    }
+
+    /// The minimum and maximum numbers of values, from their description:
+    /// "<value>" is one value, "<value>..." one or more, "[<value>]" an optional one
+    fn arity(&self) -> (usize, Option<usize>) {
+        match self.values_description() {
+            "" => (0, Some(0)),
+            values if values.starts_with('[') => (0, Some(1)),
+            values if values.contains("...") => (1, None),
+            _ => (1, Some(1)),
         }
     }
 
@@ -70,24 +977,235 @@ impl CommandLineOption {
             Self::InputValues => "<value> <value>...",
             Self::Memory => "<address> <value>... | <memory_file>",
             Self::MaxMemoryAddress => "<max_address>",
+            Self::Breakpoints => "<label | line> <label | line>...",
+            Self::MaxSteps => "<max_steps>",
+            Self::Stats => "",
+            Self::StrictHrm => "",
+            Self::Format => "<text | json | dot>",
+            Self::TileNames => "<name>=<address> <name>=<address>...",
+            Self::Level => "<number>",
+            Self::Expected => "<value> <value>...",
+            Self::TestFile => "<file>",
+            Self::GenerateInputs => "<spec>",
+            Self::Compare => "<script_file>",
+            Self::Trace => "[<file>]",
+            Self::TraceFormat => "<text | jsonl>",
+            Self::Watchpoints => "<address> <address>...",
+            Self::Stream => "",
+            Self::CheckpointEvery => "<steps>",
+            Self::CheckpointFile => "<file>",
+            Self::Resume => "<file>",
+            Self::CheckFormat => "",
+            Self::Coverage => "",
+            Self::RequireFullCoverage => "",
+            Self::Profile => "",
+            Self::Optimize => "",
+            Self::RemoveDeadInstructions => "",
+            Self::Score => "",
+            Self::Runs => "<number>",
+            Self::Tui => "",
+            Self::WatchFiles => "",
+            Self::Jobs => "<number>",
+            Self::ErrorFormat => "<text | json>",
+            Self::DumpMemory => "[<file>]",
+            Self::DumpHead => "",
+            Self::LevelFile => "<file>",
+            Self::Report => "<file>",
+            Self::Extensions => "",
+            Self::Stack => "<start>..<end>",
+            Self::SecondHand => "",
+            Self::Lenient => "",
+            Self::InputString => "<string>... [0]",
+            Self::OutputAsStrings => "",
+            Self::OutputSeparator => "<separator>",
+            Self::Newline => "",
+            Self::NoNewline => "",
+            Self::OutputFile => "<file>",
+            Self::Config => "<file>",
+            Self::Preset => "<name>",
+            Self::Record => "<file>",
+            Self::Replay => "<file>",
+            Self::Seed => "<number>",
+            Self::MaxLength => "<length>",
+            Self::ValueRange => "<min>..<max>",
+            Self::Timeout => "<duration>",
+            Self::Iterations => "<count>",
+            Self::Costs => "<file>",
+            Self::StageMemory => "<stage>=<memory_file> <stage>=<memory_file>...",
+            Self::StageMaxMemory => "<stage>=<max_address> <stage>=<max_address>...",
+            Self::Floor => "<columns>x<rows>",
+            Self::MaxInstructions => "<count>",
+            Self::CheckLoops => "",
+            Self::Assert => "<property> <property>...",
+            Self::MaxSize => "<count>",
+            Self::Target => "<language>",
+            Self::FromSave => "",
+            Self::MaxTiles => "<count>",
+            Self::MaxAllocations => "<count>",
+            Self::MaxOutputs => "<count>",
+            Self::Verbose => "",
+            Self::Quiet => "",
+            Self::NoColor => "",
         }
     }
 
     fn description(&self) -> &'static str {
         match self {
-            Self::InputValues => "sets the values to be used as input",
+            Self::InputValues => {
+                "sets the values to be used as input. With '-', or when piped, they're read from stdin"
+            }
             Self::Memory => "sets the values to be used as memory",
             Self::MaxMemoryAddress => {
-                "sets the maximum memory address. That's the last tile number in the game."
+                "sets the maximum memory address. That's the last tile number in the game. A script using a tile past it (COPYTO 99 on a 16-tile floor) is rejected before running"
             }
+            Self::Breakpoints => {
+                "pauses the execution before a block or a line, to inspect the state and step through the script"
+            }
+            Self::MaxSteps => "sets the maximum number of instructions to execute, to catch infinite loops",
+            Self::Stats => "prints statistics about the execution (steps, instructions and blocks executed, reads and writes of each tile) to stderr",
+            Self::StrictHrm => "behaves exactly like the game: numbers out of [-999, 999] are an error",
+            Self::Format => "sets how the results are printed. 'json' prints an object with the outputs, the number of steps and the final memory. 'dot' is the format of the graph mode",
+            Self::TileNames => "names memory tiles, so that the script can refer to them by name (COPYFROM zero)",
+            Self::Level => "solve a level of the game: its floor, inbox and expected outbox are used unless -M, -m or -i are given",
+            Self::Expected => "sets the outputs the script should produce. The outputs are then checked against them",
+            Self::TestFile => "runs the test cases of a TOML (or .json) file, and prints a summary. Other inputs and memory options are ignored",
+            Self::GenerateInputs => "generates random input values: <count> <numbers | letters | values> [<min>..<max>] [non-zero], separated by commas",
+            Self::Compare => "runs another script on the same inputs and reports the first divergence in outputs, and the step counts. Generated inputs are compared 100 times",
+            Self::Trace => "prints a line per executed instruction (step, line, block, instruction, head before and after, memory tile touched) to stderr, or to a file",
+            Self::TraceFormat => "sets the format of the trace (and enables it): text, or jsonl for one JSON object per step",
+            Self::Watchpoints => "sets watchpoints: the execution pauses in the debugger each time one of these tiles is read or written",
+            Self::Stream => "prints each output value as soon as it's produced, one per line (the output format is ignored)",
+            Self::CheckpointEvery => "saves the state of the execution to the checkpoint file every <steps> steps (and when the execution fails), so that it can be resumed with --resume",
+            Self::CheckpointFile => "sets the file in which checkpoints are saved (and enables them, every 1000000 steps by default)",
+            Self::Resume => "resumes the execution saved in a checkpoint file, with its inputs and outputs. The script must be the same",
+            Self::CheckFormat => "in fmt mode, doesn't print the formatted script: exits with 6 if the script isn't formatted",
+            Self::Coverage => "after the execution, prints how many instructions were executed and the lines that never were (to stderr)",
+            Self::RequireFullCoverage => "prints the coverage like --coverage, and exits with 6 if some instructions were never executed",
+            Self::Profile => "after the execution, prints the blocks and the instructions sorted by the number of steps spent in them (to stderr)",
+            Self::Optimize => "optimizes the script before anything else (collapses chains of jumps, removes the code never executed, the empty blocks and the unused labels), and prints the number of instructions before and after (to stderr)",
+            Self::RemoveDeadInstructions => "optimizes the script like --optimize, and also removes the instructions whose effect is never used (a value in hand replaced before being used, a COPYTO to a tile overwritten before being read). --optimize alone only lists them",
+            Self::Score => "prints the two scores of the game after the execution (to stderr): the size (number of instructions, without the labels and comments) and the speed (number of steps)",
+            Self::Runs => "with --score, also runs the script on <number> random inboxes (generated by the level or by -g) and prints the average number of steps",
+            Self::Tui => "shows the execution in the terminal like in the game (the floor, the head, the inbox and outbox belts, and the script with the next instruction highlighted), with keys to step, go back, play, run and reset. Breakpoints stop the run",
+            Self::WatchFiles => "watches the script, the memory file and the test file, and runs again with the same options each time one of them is modified, until interrupted with Ctrl+C",
+            Self::Jobs => "runs the test cases (with -t) and the scripts of a batch on this number of threads",
+            Self::ErrorFormat => "sets how the parse, validation and runtime errors are printed to stderr. 'json' prints an object per error, on one line, with the kind of error, a stable code, the message, the line and column, and the state of the interpreter",
+            Self::DumpMemory => "prints the final floor to stderr (a line per tile: address and value), or writes it to a file that --memory reads",
+            Self::DumpHead => "prints the final value in the head to stderr (and in the second hand, with --second-hand)",
+            Self::LevelFile => "solves a custom level described in a JSON file (name, floor_size, floor, inbox and outbox): its floor and inbox are used unless -M, -m or -i are given, and the outputs are checked against its outbox",
+            Self::Report => "writes a self-contained HTML report of the run: the source with the number of executions of each line, the inbox, the outbox, the final floor, a heat map of the tile accesses, the score and the result of the check",
+            Self::Extensions => "accepts the instructions that aren't in the game: MUL, DIV and MOD (arithmetic), SWAP (exchanges the head and a tile), JUMPP and JUMPE (jump if positive, or equal to a tile), PUSH and POP (see --stack), CALL and RET (subroutines), TCOPYTO, TCOPYFROM and XCHG (see --second-hand), DEBUG (prints to stderr), ASSERTZ, ASSERTN and ASSERT (stop with an error when the condition fails), NOP (does nothing) and HALT (terminates the program)",
+            Self::Stack => "sets the tiles used as a stack by PUSH and POP (with --extensions): from <start> to <end> excluded, or included with '..='. The stack is filled from <start>, the values already on its first tiles are on the stack",
+            Self::SecondHand => "gives the worker a second hand, for TCOPYTO (head to second hand), TCOPYFROM (second hand to head) and XCHG (exchanges them), with --extensions",
+            Self::Lenient => "accepts the operations in any case and their common aliases: bumpdown for BUMPDN, jmp for JUMP, copy_from for COPYFROM... Ignored with --strict-hrm",
+            Self::InputString => "adds the characters of the strings to the input values, word by word. With a last '0', each word is followed by a 0, like in the string levels of the game",
+            Self::OutputAsStrings => "prints the outputs as strings, a word per line: the characters before each 0 are joined, like in the string levels of the game. Only with the text format",
+            Self::OutputSeparator => "sets what separates the outputs in text, with \\n for a newline and \\t for a tab",
+            Self::Newline => "ends the outputs with a newline in text",
+            Self::NoNewline => "doesn't end the outputs with a newline in text, even with --stream",
+            Self::OutputFile => "writes the results to the file instead of stdout",
+            Self::Config => "reads the defaults of the options from the TOML file: max_memory, memory, extensions, strict_hrm, test (for the test and batch modes) and named inputs (see --preset)",
+            Self::Preset => "adds the inputs of the configuration file with this name to the input values",
+            Self::Record => "writes the inputs taken from the inbox and the outputs produced to a JSON file, even when the execution fails, to run the same session again with --replay",
+            Self::Replay => "runs the script on the inputs of a session written by --record, instead of the given or generated ones",
+            Self::Seed => "seeds the random generator of the generated inputs (-g, -L, --runs and --compare), to generate the same inputs again. The seed is printed when the execution fails on random inputs",
+            Self::MaxLength => "in equiv and solve-for modes, checks the inboxes of at most <length> values",
+            Self::ValueRange => "in equiv and solve-for modes, sets the values of the inboxes: the numbers or the letters of the range (inclusive), only numbers in solve-for mode",
+            Self::Timeout => "aborts an execution that takes longer than the duration (in ms, s or m), even within the step limit. Applies to each run of the test cases, comparisons and random inboxes",
+            Self::Iterations => "sets the number of measured runs of bench (after a tenth of them as warmups)",
+            Self::Costs => "prints the cycles of the execution after it (to stderr), with the cost of each instruction read from the TOML file (\"COPYFROM [x]\" for the indirect addressing, and default for the others)",
+            Self::StageMemory => "sets the floor of stages of pipe (numbered from 1) from memory files, instead of the floor given with -m",
+            Self::StageMaxMemory => "sets the last tile number of the floor of stages of pipe (numbered from 1), instead of the one given with -M",
+            Self::Floor => "lays out the floor in rows of tiles numbered row by row, like in the game: the scripts can name the tiles r<row>c<column> (r2c3 is the tile 13 of a 5x5 floor), the floor is printed as a grid, and its size is the maximum memory address unless -M is given",
+            Self::MaxInstructions => "sets the maximum size of the script, like the game on some challenges: a longer script fails the validation, with its actual size",
+            Self::CheckLoops => "warns about the loops the script can't leave before running it: no INBOX, HALT or end of the script can be reached from them, so the script would run until the step limit",
+            Self::Assert => "in solve-for mode, the properties to prove on every inbox, like 'len(out) == 1 and out[0] == in[0] + in[1]': comparisons of numbers, in[i], out[i], len(in) and len(out), joined by and, or and not",
+            Self::MaxSize => "in search mode, tries the scripts of at most <count> instructions",
+            Self::Target => "the language the script is compiled to, in transpile mode (rust or wat)",
+            Self::FromSave => "the script file is a save file of the game: its solution of the level given with -L is used (experimental: the solution is guessed from the layout of the file)",
+            Self::MaxTiles => "aborts the execution when more tiles than that hold a value, to bound the memory of untrusted scripts",
+            Self::MaxAllocations => "aborts the execution when it holds more values than that (tiles, outputs and pending CALLs), to bound the memory of untrusted scripts",
+            Self::MaxOutputs => "aborts the execution when it outputs more values than that, to catch the loops filling the outbox",
+            Self::Verbose => "prints what the parsing, the validation and the execution do (to stderr), with their details if given twice",
+            Self::Quiet => "only prints the errors to stderr: no warnings nor notes (the reports asked for are still printed)",
+            Self::NoColor => "never colors the errors, the warnings and the diffs, even on a terminal (as does setting NO_COLOR)",
         }
     }
 
     fn example(&self) -> &'static str {
         match self {
-            Self::InputValues => "-i 10 20 30 A E F",
+            Self::InputValues => "-i 10 20 30 A E F | -i -",
             Self::Memory => "-m 0 10 1 A 2 30 | -m memory.txt",
             Self::MaxMemoryAddress => "-M 24",
+            Self::Breakpoints => "-b a 12",
+            Self::MaxSteps => "-s 10000",
+            Self::Stats => "-S",
+            Self::StrictHrm => "-H",
+            Self::Format => "-f json",
+            Self::TileNames => "-l zero=24 ptr=23",
+            Self::Level => "-L 32",
+            Self::Expected => "-e 12 4 0 0 56",
+            Self::TestFile => "-t cases.toml",
+            Self::GenerateInputs => "-g \"10 numbers -10..10, 3 letters A..E\"",
+            Self::Compare => "-c ./samples/06-RainySummer-optimized.hrm",
+            Self::Trace => "-T trace.txt",
+            Self::TraceFormat => "-F jsonl",
+            Self::Watchpoints => "-w 5 24",
+            Self::Stream => "-o",
+            Self::CheckpointEvery => "-k 100000",
+            Self::CheckpointFile => "-K state.json",
+            Self::Resume => "-r state.json",
+            Self::CheckFormat => "-C",
+            Self::Coverage => "-X",
+            Self::RequireFullCoverage => "-V",
+            Self::Profile => "-p",
+            Self::Optimize => "-O",
+            Self::RemoveDeadInstructions => "-D",
+            Self::Score => "-z",
+            Self::Runs => "-L 2 -z -n 100",
+            Self::Tui => "-u",
+            Self::WatchFiles => "-W",
+            Self::Jobs => "-j 8",
+            Self::ErrorFormat => "-E json",
+            Self::DumpMemory => "-d final-memory.txt",
+            Self::DumpHead => "-a",
+            Self::LevelFile => "-y level.json",
+            Self::Report => "-R report.html",
+            Self::Extensions => "-x",
+            Self::Stack => "-P 20..30",
+            Self::SecondHand => "-B",
+            Self::Lenient => "-N",
+            Self::InputString => "-I \"HELLO WORLD\" 0",
+            Self::OutputAsStrings => "-A",
+            Self::OutputSeparator => "-J \"\\n\"",
+            Self::Newline => "-Q",
+            Self::NoNewline => "-Z",
+            Self::OutputFile => "-U outputs.txt",
+            Self::Config => "-G levels/hrm.toml",
+            Self::Preset => "-Y sample",
+            Self::Record => "--record session.json",
+            Self::Replay => "--replay session.json",
+            Self::Seed => "--seed 42",
+            Self::MaxLength => "--max-len 4",
+            Self::ValueRange => "--range -5..5 | --range A..E",
+            Self::Timeout => "--timeout 2s",
+            Self::Iterations => "--iterations 1000",
+            Self::Costs => "--costs costs.toml",
+            Self::StageMemory => "--stage-memory 2=floor.txt",
+            Self::StageMaxMemory => "--stage-max-memory 2=9",
+            Self::Floor => "--floor 5x5",
+            Self::MaxInstructions => "--max-instructions 20",
+            Self::CheckLoops => "--check-loops",
+            Self::Assert => "--assert 'len(out) == len(in)'",
+            Self::MaxSize => "--max-size 8",
+            Self::Target => "--target rust",
+            Self::FromSave => "--from-save -L 4",
+            Self::MaxTiles => "--max-tiles 1000",
+            Self::MaxAllocations => "--max-allocations 100000",
+            Self::MaxOutputs => "--max-outputs 1000",
+            Self::Verbose => "-v -v",
+            Self::Quiet => "-q",
+            Self::NoColor => "--no-color",
         }
     }
 
@@ -96,159 +1214,1025 @@ impl CommandLineOption {
             Self::InputValues => "no input values",
             Self::Memory => "no starting memory values",
             Self::MaxMemoryAddress => "no (theoretical) maximum",
+            Self::Breakpoints => "no breakpoints",
+            Self::MaxSteps => "no limit",
+            Self::Stats => "no statistics",
+            Self::StrictHrm => "numbers are only bound by the interpreter",
+            Self::Format => "text",
+            Self::TileNames => "no tile names",
+            Self::Level => "none",
+            Self::Expected => "no check (unless a level is given)",
+            Self::TestFile => "no test file",
+            Self::GenerateInputs => "no generated values",
+            Self::Compare => "no comparison",
+            Self::Trace => "no trace",
+            Self::TraceFormat => "text",
+            Self::Watchpoints => "no watchpoints",
+            Self::Stream => "outputs printed at the end",
+            Self::CheckpointEvery => "no checkpoint",
+            Self::CheckpointFile => "checkpoint.json",
+            Self::Resume => "start from the beginning",
+            Self::CheckFormat => "the formatted script is printed",
+            Self::Coverage => "no coverage report",
+            Self::RequireFullCoverage => "not required",
+            Self::Profile => "no profile",
+            Self::Optimize => "the script is used as is",
+            Self::RemoveDeadInstructions => {
+                "the instructions whose effect is never used are only listed"
+            }
+            Self::Score => "no score",
+            Self::Runs => "only the given inbox is scored",
+            Self::Tui => "no terminal view",
+            Self::WatchFiles => "runs once",
+            Self::Jobs => "1 (one at a time)",
+            Self::ErrorFormat => "text",
+            Self::DumpMemory => "no dump",
+            Self::DumpHead => "no dump",
+            Self::LevelFile => "none",
+            Self::Report => "no report",
+            Self::Extensions => "only the instructions of the game",
+            Self::Stack => "no stack",
+            Self::SecondHand => "one hand",
+            Self::Lenient => "only the syntax of the game",
+            Self::InputString => "no input string",
+            Self::OutputAsStrings => "the outputs separated by spaces",
+            Self::OutputSeparator => "a space, or a newline with --stream",
+            Self::Newline => "no newline, except with --stream",
+            Self::NoNewline => "no newline, except with --stream",
+            Self::OutputFile => "stdout",
+            Self::Config => "hrm.toml, if it's in the working directory",
+            Self::Preset => "no preset",
+            Self::Record => "no recording",
+            Self::Replay => "no replay",
+            Self::Seed => "a random seed",
+            Self::MaxLength => "3",
+            Self::ValueRange => "-9..9 in equiv mode, -999..999 in solve-for mode",
+            Self::Timeout => "no time limit",
+            Self::Iterations => "100",
+            Self::Costs => "no cycle count",
+            Self::StageMemory => "the floor given with -m",
+            Self::StageMaxMemory => "the one given with -M",
+            Self::Floor => "a flat floor",
+            Self::MaxInstructions => "no limit",
+            Self::CheckLoops => "no check",
+            Self::Assert => "the script doesn't fail",
+            Self::MaxSize => "6",
+            Self::Target => "rust",
+            Self::FromSave => "the script file is a script",
+            Self::MaxTiles => "no limit",
+            Self::MaxAllocations => "no limit",
+            Self::MaxOutputs => "no limit",
+            Self::Verbose => "only the errors, the warnings and the notes",
+            Self::Quiet => "the errors, the warnings and the notes",
+            Self::NoColor => "colored on a terminal, unless NO_COLOR is set",
+        }
+    }
+
+    /// The help of the option, as printed with -h
+    fn help(&self) -> String {
+        // The options without a short name are aligned with the others
+        let short_name = match self.short_name() {
+            "" => "   ".to_string(),
+            short_name => format!("{},", short_name),
+        };
+        let short_name_long_name_and_values = format!(
+            "{} {} {}",
+            short_name,
+            self.long_name(),
+            self.values_description()
+        );
+        format!(
+            "  {: <55} {}\n  {: <55}   Example: {}\n  {: <55}   Default: {}\n",
+            short_name_long_name_and_values,
+            self.description(),
+            "",
+            self.example(),
+            "",
+            self.default_value()
+        )
+    }
+
+    fn invalid(&self, value: &str, reason: impl ToString) -> CliError {
+        CliError::InvalidValue {
+            option: self.long_name().to_string(),
+            value: value.to_string(),
+            reason: reason.to_string(),
         }
     }
 
-    fn handle_args(&self, option_args: &Vec<String>, command_line_args: &mut CommandLineArgs) {
+    fn handle_args(
+        &self,
+        option_args: &Vec<String>,
+        command_line_args: &mut CommandLineArgs,
+    ) -> Result<(), CliError> {
         match self {
+            Self::InputValues if option_args.len() == 1 && option_args[0] == "-" => {
+                command_line_args
+                    .input_values
+                    .extend(read_input_values_from_stdin()?);
+            }
             Self::InputValues => {
                 for arg in option_args {
                     command_line_args.input_values.push(
                         arg.parse::<ValueBox>()
-                            .unwrap_or_else(|_| panic!("Invalid input value: {}", arg)),
+                            .map_err(|_| self.invalid(arg, "expected a number or a character"))?,
                     );
                 }
             }
+            Self::Memory if option_args.len() == 1 => {
+                let memory_file = option_args[0].clone();
+                command_line_args.memory = memory_file::read_memory_file(&memory_file)
+                    .map_err(|e| self.invalid(&memory_file, e))?;
+                command_line_args.memory_file = Some(memory_file);
+            }
             Self::Memory => {
-                let args = if option_args.len() == 1 {
-                    let memory_file = option_args.first().unwrap().clone();
-                    let memory_content = fs::read_to_string(memory_file.clone())
-                        .unwrap_or_else(|_| panic!("Could not read file {}", memory_file));
-                    let memory_content = memory_content.lines().collect::<Vec<&str>>().join(" ");
-                    memory_content
-                        .split(' ')
-                        .map(|s| s.to_string())
-                        .collect::<Vec<String>>()
-                } else {
-                    option_args
-                        .iter()
-                        .map(|s| s.to_string())
-                        .collect::<Vec<String>>()
-                };
-
-                if args.len() % 2 != 0 {
-                    panic!("Invalid memory arguments: expected an even number of arguments (couples of address and value)");
+                if !option_args.len().is_multiple_of(2) {
+                    return Err(self.invalid(
+                        &option_args.join(" "),
+                        "expected couples of address and value",
+                    ));
                 }
 
-                for i in 0..args.len() / 2 {
-                    let address = args[i * 2]
+                for i in 0..option_args.len() / 2 {
+                    let address = option_args[i * 2]
                         .parse::<usize>()
-                        .unwrap_or_else(|_| panic!("Invalid memory address: {}", args[i * 2]));
-                    let value = args[i * 2 + 1]
-                        .parse::<ValueBox>()
-                        .unwrap_or_else(|_| panic!("Invalid memory value: {}", args[i * 2 + 1]));
+                        .map_err(|_| self.invalid(&option_args[i * 2], "expected an address"))?;
+                    let value = option_args[i * 2 + 1].parse::<ValueBox>().map_err(|_| {
+                        self.invalid(&option_args[i * 2 + 1], "expected a number or a character")
+                    })?;
                     command_line_args.memory.insert(address, value);
                 }
             }
             Self::MaxMemoryAddress => {
                 let max_memory_address = option_args[0]
                     .parse::<usize>()
-                    .unwrap_or_else(|_| panic!("Invalid max memory address: {}", option_args[0]));
+                    .map_err(|_| self.invalid(&option_args[0], "expected an address"))?;
                 command_line_args.max_memory_address = max_memory_address;
             }
+            Self::Breakpoints => {
+                for arg in option_args {
+                    command_line_args
+                        .breakpoints
+                        .push(arg.parse::<Breakpoint>().unwrap_or_else(|e| match e {}));
+                }
+            }
+            Self::MaxSteps => {
+                let max_steps = option_args[0]
+                    .parse::<usize>()
+                    .map_err(|_| self.invalid(&option_args[0], "expected a number of steps"))?;
+                command_line_args.max_steps = Some(max_steps);
+            }
+            Self::Stats => command_line_args.print_stats = true,
+            Self::StrictHrm => command_line_args.strict_hrm = true,
+            Self::Format => {
+                command_line_args.format = option_args[0]
+                    .parse::<OutputFormat>()
+                    .map_err(|_| self.invalid(&option_args[0], "expected text, json or dot"))?;
+            }
+            Self::TileNames => {
+                for arg in option_args {
+                    let (name, address) = arg
+                        .split_once('=')
+                        .ok_or_else(|| self.invalid(arg, "expected <name>=<address>"))?;
+                    let address = address
+                        .parse::<usize>()
+                        .map_err(|_| self.invalid(arg, "expected an address after '='"))?;
+                    command_line_args
+                        .tile_names
+                        .insert(name.to_string(), address);
+                }
+            }
+            Self::Level => {
+                let number = option_args[0]
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|number| levels::get_level(*number).is_some())
+                    .ok_or_else(|| self.invalid(&option_args[0], "not a level of the game"))?;
+                command_line_args.level = Some(number);
+            }
+            Self::Expected => {
+                command_line_args.expected_outputs =
+                    Some(parse_input_values(&option_args.join(" ")).map_err(|value| {
+                        self.invalid(value, "expected a number or a character")
+                    })?);
+            }
+            Self::TestFile => command_line_args.test_file = Some(option_args[0].clone()),
+            Self::GenerateInputs => {
+                let spec = option_args.join(" ");
+                command_line_args.input_spec = Some(
+                    spec.parse::<InboxSpec>()
+                        .map_err(|e| self.invalid(&spec, e))?,
+                );
+            }
+            Self::Compare => {
+                let compare_file = option_args[0].clone();
+                command_line_args.compare_script = Some(
+                    fs::read_to_string(&compare_file)
+                        .map_err(|e| self.invalid(&compare_file, e))?,
+                );
+            }
+            Self::Trace => {
+                command_line_args.trace = true;
+                command_line_args.trace_file = option_args.first().cloned();
+            }
+            Self::TraceFormat => {
+                command_line_args.trace = true;
+                command_line_args.trace_format = option_args[0]
+                    .parse::<TraceFormat>()
+                    .map_err(|_| self.invalid(&option_args[0], "expected text or jsonl"))?;
+            }
+            Self::Watchpoints => {
+                for arg in option_args {
+                    command_line_args.watchpoints.push(
+                        arg.parse::<usize>()
+                            .map_err(|_| self.invalid(arg, "expected an address"))?,
+                    );
+                }
+            }
+            Self::Stream => command_line_args.stream = true,
+            Self::CheckpointEvery => {
+                let steps = option_args[0]
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|steps| *steps > 0)
+                    .ok_or_else(|| self.invalid(&option_args[0], "expected a positive number"))?;
+                command_line_args.checkpoint_every = Some(steps);
+            }
+            Self::CheckpointFile => {
+                command_line_args.checkpoint_file = Some(option_args[0].clone())
+            }
+            Self::Resume => command_line_args.resume_file = Some(option_args[0].clone()),
+            Self::CheckFormat => command_line_args.check_format = true,
+            Self::Coverage => command_line_args.coverage = true,
+            Self::RequireFullCoverage => {
+                command_line_args.coverage = true;
+                command_line_args.require_full_coverage = true;
+            }
+            Self::Profile => command_line_args.profile = true,
+            Self::Optimize => command_line_args.optimize = true,
+            Self::RemoveDeadInstructions => {
+                command_line_args.optimize = true;
+                command_line_args.remove_dead_instructions = true;
+            }
+            Self::Score => command_line_args.score = true,
+            Self::Runs => {
+                let runs = option_args[0]
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|runs| *runs > 0)
+                    .ok_or_else(|| self.invalid(&option_args[0], "expected a positive number"))?;
+                command_line_args.score = true;
+                command_line_args.score_runs = Some(runs);
+            }
+            Self::Tui => command_line_args.tui = true,
+            Self::WatchFiles => command_line_args.watch_files = true,
+            Self::Jobs => {
+                command_line_args.jobs = option_args[0]
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|jobs| *jobs > 0)
+                    .ok_or_else(|| self.invalid(&option_args[0], "expected a positive number"))?;
+            }
+            Self::ErrorFormat => {
+                command_line_args.error_format = option_args[0]
+                    .parse::<ErrorFormat>()
+                    .map_err(|_| self.invalid(&option_args[0], "expected text or json"))?;
+            }
+            Self::DumpMemory => {
+                command_line_args.dump_memory = true;
+                command_line_args.dump_memory_file = option_args.first().cloned();
+            }
+            Self::DumpHead => command_line_args.dump_head = true,
+            Self::LevelFile => command_line_args.level_file = Some(option_args[0].clone()),
+            Self::Report => command_line_args.report_file = Some(option_args[0].clone()),
+            Self::Extensions => command_line_args.extensions = true,
+            Self::Stack => {
+                let stack = memory_file::parse_range(&option_args[0])
+                    .map_err(|e| self.invalid(&option_args[0], e))?
+                    .ok_or_else(|| self.invalid(&option_args[0], "expected <start>..<end>"))?;
+                command_line_args.stack = Some(stack);
+            }
+            Self::SecondHand => command_line_args.second_hand = true,
+            Self::Lenient => command_line_args.lenient = true,
+            Self::InputString => {
+                // A last "0" ends each word with a 0
+                let (strings, terminated) = match option_args.split_last() {
+                    Some((last, strings)) if last == "0" => (strings, true),
+                    _ => (option_args.as_slice(), false),
+                };
+                for word in strings.iter().flat_map(|s| s.split_whitespace()) {
+                    command_line_args
+                        .input_values
+                        .extend(word.chars().map(ValueBox::Character));
+                    if terminated {
+                        command_line_args.input_values.push(ValueBox::Number(0));
+                    }
+                }
+            }
+            Self::OutputAsStrings => command_line_args.output_as_strings = true,
+            Self::OutputSeparator => {
+                command_line_args.output_separator =
+                    Some(option_args[0].replace("\\n", "\n").replace("\\t", "\t"))
+            }
+            Self::Newline => command_line_args.newline = Some(true),
+            Self::NoNewline => command_line_args.newline = Some(false),
+            Self::OutputFile => command_line_args.output_file = Some(option_args[0].clone()),
+            Self::Config => command_line_args.config_file = Some(option_args[0].clone()),
+            Self::Preset => command_line_args.preset = Some(option_args[0].clone()),
+            Self::Record => command_line_args.record_file = Some(option_args[0].clone()),
+            Self::Replay => command_line_args.replay_file = Some(option_args[0].clone()),
+            Self::Seed => {
+                command_line_args.seed = Some(
+                    option_args[0]
+                        .parse::<u64>()
+                        .map_err(|_| self.invalid(&option_args[0], "expected a positive number"))?,
+                )
+            }
+            Self::MaxLength => {
+                command_line_args.max_length = Some(
+                    option_args[0]
+                        .parse::<usize>()
+                        .map_err(|_| self.invalid(&option_args[0], "expected a length"))?,
+                )
+            }
+            Self::ValueRange => {
+                command_line_args.value_range = Some(
+                    generator::range_values(&option_args[0])
+                        .ok_or_else(|| self.invalid(&option_args[0], "expected <min>..<max>"))?,
+                )
+            }
+            Self::Timeout => {
+                command_line_args.timeout =
+                    Some(parse_duration(&option_args[0]).ok_or_else(|| {
+                        self.invalid(&option_args[0], "expected a duration like 500ms, 2s or 1m")
+                    })?)
+            }
+            Self::Iterations => {
+                command_line_args.iterations = Some(
+                    option_args[0]
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|iterations| *iterations > 0)
+                        .ok_or_else(|| {
+                            self.invalid(&option_args[0], "expected a positive number")
+                        })?,
+                )
+            }
+            Self::Costs => command_line_args.cost_file = Some(option_args[0].clone()),
+            Self::StageMemory => {
+                for arg in option_args {
+                    let (stage, file) = arg
+                        .split_once('=')
+                        .ok_or_else(|| self.invalid(arg, "expected <stage>=<memory_file>"))?;
+                    let stage = stage
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|stage| *stage > 0)
+                        .ok_or_else(|| self.invalid(arg, "expected a stage number before '='"))?;
+                    let memory =
+                        memory_file::read_memory_file(file).map_err(|e| self.invalid(arg, e))?;
+                    command_line_args.stage_memory.insert(stage, memory);
+                }
+            }
+            Self::StageMaxMemory => {
+                for arg in option_args {
+                    let (stage, max_memory_address) = arg
+                        .split_once('=')
+                        .ok_or_else(|| self.invalid(arg, "expected <stage>=<max_address>"))?;
+                    let stage = stage
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|stage| *stage > 0)
+                        .ok_or_else(|| self.invalid(arg, "expected a stage number before '='"))?;
+                    let max_memory_address = max_memory_address
+                        .parse::<usize>()
+                        .map_err(|_| self.invalid(arg, "expected an address after '='"))?;
+                    command_line_args
+                        .stage_max_memory
+                        .insert(stage, max_memory_address);
+                }
+            }
+            Self::Floor => {
+                command_line_args.floor = Some(
+                    option_args[0]
+                        .parse::<FloorGeometry>()
+                        .map_err(|e| self.invalid(&option_args[0], e))?,
+                )
+            }
+            Self::MaxInstructions => {
+                command_line_args.max_instructions =
+                    Some(option_args[0].parse::<usize>().map_err(|_| {
+                        self.invalid(&option_args[0], "expected a number of instructions")
+                    })?)
+            }
+            Self::CheckLoops => command_line_args.check_loops = true,
+            Self::Assert => {
+                for arg in option_args {
+                    command_line_args
+                        .properties
+                        .push(arg.parse::<Property>().map_err(|e| self.invalid(arg, e))?);
+                }
+            }
+            Self::MaxSize => {
+                command_line_args.max_size =
+                    Some(option_args[0].parse::<usize>().map_err(|_| {
+                        self.invalid(&option_args[0], "expected a number of instructions")
+                    })?)
+            }
+            Self::Target => {
+                command_line_args.target = option_args[0]
+                    .parse::<Target>()
+                    .map_err(|_| self.invalid(&option_args[0], "expected rust or wat"))?;
+            }
+            Self::FromSave => command_line_args.from_save = true,
+            Self::MaxTiles => {
+                let max_tiles = option_args[0]
+                    .parse::<usize>()
+                    .map_err(|_| self.invalid(&option_args[0], "expected a number of tiles"))?;
+                command_line_args.limits.max_tiles = Some(max_tiles);
+            }
+            Self::MaxAllocations => {
+                let max_allocations = option_args[0]
+                    .parse::<usize>()
+                    .map_err(|_| self.invalid(&option_args[0], "expected a number of values"))?;
+                command_line_args.limits.max_allocations = Some(max_allocations);
+            }
+            Self::MaxOutputs => {
+                let max_outputs = option_args[0]
+                    .parse::<usize>()
+                    .map_err(|_| self.invalid(&option_args[0], "expected a number of outputs"))?;
+                command_line_args.limits.max_outputs = Some(max_outputs);
+            }
+            Self::Verbose => command_line_args.verbosity = command_line_args.verbosity.louder(),
+            Self::Quiet => command_line_args.verbosity = Verbosity::Quiet,
+            Self::NoColor => command_line_args.no_color = true,
         }
+        Ok(())
     }
 }
 
 fn print_help() {
     println!("Human Resource Machine interpreter");
     println!("Get this help: hrm-interpreter.exe -h | --help");
-    println!("Usage:         hrm-interpreter.exe <script_file> [options]");
+    for (_, _, title, usage) in SUBCOMMANDS {
+        println!("{: <14} hrm-interpreter.exe {}", title, usage);
+    }
+    println!(
+        "Flags can be combined (-xH), and '--' ends the options: the arguments after it are values"
+    );
     println!("Options:");
     for option in CommandLineOption::all_options() {
-        let short_name_long_name_and_values = format!(
-            "{}, {} {}",
-            option.short_name(),
-            option.long_name(),
-            option.values_description()
-        );
-        println!(
-            "  {: <55} {}",
-            short_name_long_name_and_values,
-            option.description()
-        );
-        println!("  {: <55}   Example: {}", "", option.example());
-        println!("  {: <55}   Default: {}", "", option.default_value());
+        print!("{}", option.help());
+    }
+}
+
+/// Print the error with the help of its option, or the usage of the subcommand, and exit
+fn exit_with_error(error: &CliError, usage: &str) -> ! {
+    log::error(format!("error: {}", error));
+    match error.option() {
+        Some(option) => log::error(option.help().trim_end()),
+        None => log::error(format!("Usage: hrm-interpreter.exe {}", usage)),
     }
+    log::error("See '-h' for help");
+    std::process::exit(1);
+}
+
+/// Parse whitespace-separated ValueBoxes, or return the first invalid one
+/// Parse a positive duration: a number of milliseconds (`500ms`), seconds (`2s`, or `2`) or minutes (`1m`)
+fn parse_duration(text: &str) -> Option<Duration> {
+    let (number, unit) = text
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or((text, ""), |i| text.split_at(i));
+    let number = number.parse::<u64>().ok().filter(|number| *number > 0)?;
+    match unit {
+        "ms" => Some(Duration::from_millis(number)),
+        "" | "s" => Some(Duration::from_secs(number)),
+        "m" => Some(Duration::from_secs(number * 60)),
+        _ => None,
+    }
+}
+
+fn parse_input_values(text: &str) -> Result<Vec<ValueBox>, &str> {
+    text.split_whitespace()
+        .map(|value| value.parse::<ValueBox>().map_err(|_| value))
+        .collect()
+}
+
+fn read_input_values_from_stdin() -> Result<Vec<ValueBox>, CliError> {
+    let mut text = String::new();
+    io::stdin()
+        .read_to_string(&mut text)
+        .map_err(|e| CliError::Stdin(e.to_string()))?;
+    parse_input_values(&text).map_err(|value| CliError::Stdin(format!("invalid value: {}", value)))
+}
+
+/// The usage of the subcommand of the mode
+fn usage(mode: Mode) -> &'static str {
+    SUBCOMMANDS
+        .iter()
+        .find(|(_, subcommand_mode, ..)| *subcommand_mode == mode)
+        .map_or(SUBCOMMANDS[0].3, |(.., usage)| usage)
 }
 
 pub fn read_args() -> CommandLineArgs {
-    let mut args = env::args().skip(1);
+    let mut command_line_args = read_command_line();
+    let usage = usage(command_line_args.mode);
 
-    let first_arg = args.next().unwrap_or_else(|| {
-        print_help();
-        std::process::exit(1);
-    });
+    let config = config::find_config(command_line_args.config_file.as_deref())
+        .unwrap_or_else(|e| exit_with_error(&e.into(), usage));
+    if let Some(name) = &command_line_args.preset {
+        let inputs = config
+            .as_ref()
+            .and_then(|config| config.presets.get(name))
+            .unwrap_or_else(|| {
+                let error =
+                    CommandLineOption::Preset.invalid(name, "not in the configuration file");
+                exit_with_error(&error, usage)
+            });
+        command_line_args.input_values.extend(inputs);
+    }
+    if let Some(config) = config {
+        command_line_args.apply_config(config);
+        command_line_args
+            .check_memory()
+            .unwrap_or_else(|e| exit_with_error(&e, usage));
+    }
+    let inputs_given =
+        !command_line_args.input_values.is_empty() || command_line_args.input_spec.is_some();
 
-    if first_arg == "-h" || first_arg == "--help" {
-        print_help();
-        std::process::exit(0);
+    // Inputs piped to the interpreter
+    // (unless stdin is needed to control the debugger, or the inputs come from test cases, a checkpoint or a session)
+    if !inputs_given
+        && !command_line_args.is_debugging()
+        && !command_line_args.tui
+        && command_line_args.test_file.is_none()
+        && command_line_args.resume_file.is_none()
+        && command_line_args.replay_file.is_none()
+        && !matches!(
+            command_line_args.mode,
+            Mode::Test
+                | Mode::Mutate
+                | Mode::Equiv
+                | Mode::SolveFor
+                | Mode::Search
+                | Mode::Fmt
+                | Mode::Export
+                | Mode::Lint
+                | Mode::Graph
+                | Mode::Transpile
+                | Mode::Listing
+                | Mode::Decompile
+                | Mode::Dap
+                | Mode::Lsp
+                | Mode::Repl
+        )
+        && !io::stdin().is_terminal()
+    {
+        command_line_args.input_values =
+            read_input_values_from_stdin().unwrap_or_else(|e| exit_with_error(&e, usage));
+        log::verbose(
+            "inputs",
+            format!(
+                "{} values read from stdin",
+                command_line_args.input_values.len()
+            ),
+        );
     }
 
-    let script_file = fs::read_to_string(first_arg.clone())
-        .unwrap_or_else(|_| panic!("Could not read file {}", first_arg));
+    command_line_args
+}
 
-    let mut option = match args.next() {
-        Some(option) => Some(
-            option
-                .parse::<CommandLineOption>()
-                .unwrap_or_else(|_| panic!("Invalid option: {}. See '-h' for help", option)),
-        ),
+fn read_command_line() -> CommandLineArgs {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(String::as_str) {
         None => {
-            // No options, use default values
-            return CommandLineArgs::default(script_file);
+            print_help();
+            std::process::exit(1);
         }
-    };
+        Some("-h" | "--help") => {
+            print_help();
+            std::process::exit(0);
+        }
+        Some(_) => {}
+    }
 
-    let mut command_line_args = CommandLineArgs::default(script_file);
+    let subcommand = subcommand(args.first());
+    let mut command_line_args = parse_command_line(&args).unwrap_or_else(|e| {
+        let usage = subcommand.map_or(SUBCOMMANDS[0].3, |(.., usage)| usage);
+        if subcommand.is_none() && !Path::new(&args[0]).exists() {
+            let names = SUBCOMMANDS.map(|(name, ..)| name);
+            log::error(format!(
+                "error: {} is neither a subcommand nor a script file",
+                args[0]
+            ));
+            log::error(format!("The subcommands are: {}", names.join(", ")));
+            std::process::exit(1);
+        }
+        exit_with_error(&e, usage)
+    });
+    log::set_verbosity(command_line_args.verbosity);
+    style::init(command_line_args.no_color);
+
+    // The scripts of a batch are read one by one, and the other modes have no script
+    if matches!(
+        command_line_args.mode,
+        Mode::Batch | Mode::Repl | Mode::Search | Mode::Dap | Mode::Lsp
+    ) {
+        return command_line_args;
+    }
+    let path = &command_line_args.script_path;
+    command_line_args.script_file = fs::read_to_string(path).unwrap_or_else(|e| {
+        log::error(format!(
+            "error: could not read the script file {}: {}",
+            path, e
+        ));
+        if subcommand.is_none() {
+            let names = SUBCOMMANDS.map(|(name, ..)| name);
+            log::error(format!("The subcommands are: {}", names.join(", ")));
+        }
+        std::process::exit(1);
+    });
+    if command_line_args.from_save {
+        let Some(level) = command_line_args.level else {
+            log::error("error: --from-save needs the level of the solution: give it with -L");
+            std::process::exit(1);
+        };
+        command_line_args.script_file = game::read_save_file(&command_line_args.script_file, level)
+            .unwrap_or_else(|e| {
+                log::error(format!("error: {}: {}", path, e));
+                std::process::exit(1);
+            });
+    }
+    command_line_args
+}
+
+/// Whether the argument is written like an option (and not like a negative number)
+fn is_option(arg: &str) -> bool {
+    arg.strip_prefix('-')
+        .is_some_and(|name| name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '-'))
+}
 
-    while option.is_some() {
-        let mut option_args: Vec<String> = Vec::new();
+/// Whether the argument is made of flags combined after a single "-" ("-xs")
+pub fn is_combined_flags(arg: &str) -> bool {
+    combined_flags(arg).is_some()
+}
 
-        loop {
-            let next_arg = args.next();
+/// The options of "-xs": flags combined after a single "-".
+/// Only the last one can take values.
+fn combined_flags(arg: &str) -> Option<Vec<(CommandLineOption, String)>> {
+    let flags = arg
+        .strip_prefix('-')
+        .filter(|flags| flags.len() > 1 && !flags.starts_with('-'))?;
+    let options = flags
+        .chars()
+        .map(|flag| {
+            let name = format!("-{}", flag);
+            name.parse::<CommandLineOption>()
+                .ok()
+                .map(|option| (option, name))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    options[..options.len() - 1]
+        .iter()
+        .all(|(option, _)| option.arity() == (0, Some(0)))
+        .then_some(options)
+}
 
-            if next_arg.is_none() {
-                // No more arguments
-                option
-                    .unwrap()
-                    .handle_args(&option_args, &mut command_line_args);
-                option = None;
-                break;
-            }
+/// Read the subcommand, the script and the options, without reading the files.
+///
+/// Each option takes the arguments up to the next option as values, and "--" ends the options:
+/// the arguments after it are values of the last option, even if they look like options.
+fn parse_command_line(args: &[String]) -> Result<CommandLineArgs, CliError> {
+    let (mode, mut args) = match subcommand(args.first()) {
+        Some((_, mode, ..)) => (*mode, &args[1..]),
+        None => (Mode::Run, args),
+    };
+    let mut command_line_args = CommandLineArgs {
+        mode,
+        ..CommandLineArgs::default(String::new())
+    };
 
-            let next_arg = next_arg.unwrap();
+    // The debug adapter and the language server get everything from the editor
+    if matches!(mode, Mode::Dap | Mode::Lsp) {
+        return match args.first() {
+            Some(arg) => Err(CliError::UnexpectedArgument(arg.clone())),
+            None => Ok(command_line_args),
+        };
+    }
+    if !matches!(mode, Mode::Repl | Mode::Search) {
+        match args.split_first() {
+            Some((script, rest)) if !is_option(script) => {
+                command_line_args.script_path = script.clone();
+                args = rest;
+            }
+            _ => return Err(CliError::MissingScript),
+        }
+    }
+    // The other script of equiv is read like the one of --compare
+    let mut other_script = None;
+    if mode == Mode::Equiv {
+        match args.split_first() {
+            Some((other, rest)) if !is_option(other) => {
+                other_script = Some(other.clone());
+                args = rest;
+            }
+            _ => return Err(CliError::MissingScript),
+        }
+    }
+    // The next stages of a pipeline, up to the first option
+    if mode == Mode::Pipe {
+        let stages = args.iter().take_while(|arg| !is_option(arg)).count();
+        if stages == 0 {
+            return Err(CliError::MissingScript);
+        }
+        command_line_args.pipe_scripts = args[..stages].to_vec();
+        args = &args[stages..];
+    }
 
-            if let Ok(next_option) = next_arg.parse::<CommandLineOption>() {
-                // Next argument is an option, so we're done with this option
-                option
-                    .unwrap()
-                    .handle_args(&option_args, &mut command_line_args);
-                option = Some(next_option);
-                break;
-            } else {
-                // Next argument is not an option, so it's an argument for the current option
-                option_args.push(next_arg);
+    // The options with the name they're given with, and their values
+    let mut options: Vec<(CommandLineOption, String, Vec<String>)> = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            match options.last_mut() {
+                Some((_, _, values)) => values.extend(args.by_ref().cloned()),
+                None => return Err(CliError::UnexpectedArgument(arg.clone())),
+            }
+        } else if let Ok(option) = arg.parse::<CommandLineOption>() {
+            options.push((option, arg.clone(), vec![]));
+        } else if let Some(flags) = combined_flags(arg) {
+            options.extend(
+                flags
+                    .into_iter()
+                    .map(|(option, name)| (option, name, vec![])),
+            );
+        } else if is_option(arg) {
+            return Err(CliError::UnknownOption(arg.clone()));
+        } else {
+            match options.last_mut() {
+                Some((_, _, values)) => values.push(arg.clone()),
+                None => return Err(CliError::UnexpectedArgument(arg.clone())),
             }
         }
     }
 
-    command_line_args
+    if let Some(other_script) = other_script {
+        let name = CommandLineOption::Compare.long_name().to_string();
+        options.push((CommandLineOption::Compare, name, vec![other_script]));
+    }
+    for (option, name, values) in options {
+        let (min, max) = option.arity();
+        if values.len() < min || max.is_some_and(|max| values.len() > max) {
+            return Err(CliError::WrongValues {
+                option: name,
+                expected: match option.values_description() {
+                    "" => "no value".to_string(),
+                    expected => expected.to_string(),
+                },
+                got: match values.as_slice() {
+                    [] => "nothing".to_string(),
+                    values => format!("'{}'", values.join(" ")),
+                },
+            });
+        }
+        option.handle_args(&values, &mut command_line_args)?;
+    }
+    // The geometry of the floor gives its size, unless -M is given
+    if let (Some(floor), usize::MAX) = (
+        command_line_args.floor,
+        command_line_args.max_memory_address,
+    ) {
+        command_line_args.max_memory_address = floor.max_address();
+    }
+    command_line_args.check_memory()?;
+    Ok(command_line_args)
 }
 
 #[cfg(test)]
 mod cli_tests {
     use super::*;
 
+    fn parse(args: &str) -> Result<CommandLineArgs, CliError> {
+        parse_command_line(&args.split(' ').map(str::to_string).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_parse_subcommands() {
+        let args = parse("script.hrm -i 1 2").unwrap();
+        assert_eq!(
+            (args.mode, args.script_path.as_str()),
+            (Mode::Run, "script.hrm")
+        );
+        assert_eq!(args.input_values, vec![1.into(), 2.into()]);
+
+        assert_eq!(parse("run script.hrm").unwrap().mode, Mode::Run);
+        assert_eq!(parse("test script.hrm").unwrap().mode, Mode::Test);
+        assert_eq!(parse("mutate script.hrm").unwrap().mode, Mode::Mutate);
+        let args = parse("bench script.hrm -i 1 --iterations 1000").unwrap();
+        assert_eq!((args.mode, args.iterations), (Mode::Bench, Some(1000)));
+        let args = parse("pipe a.hrm b.hrm c.hrm --stage-max-memory 2=9 -i 1").unwrap();
+        assert_eq!(args.mode, Mode::Pipe);
+        assert_eq!(args.pipe_scripts, vec!["b.hrm", "c.hrm"]);
+        assert_eq!(args.stage_max_memory, HashMap::from([(2, 9)]));
+        assert!(matches!(
+            parse("pipe a.hrm -i 1"),
+            Err(CliError::MissingScript)
+        ));
+        assert_eq!(parse("lint script.hrm").unwrap().mode, Mode::Lint);
+        assert_eq!(parse("repl -M 4").unwrap().mode, Mode::Repl);
+        let args = parse("search -L 6 --max-size 8").unwrap();
+        assert_eq!(args.mode, Mode::Search);
+        assert_eq!((args.level, args.max_size), (Some(6), Some(8)));
+        assert_eq!(parse("lsp").unwrap().mode, Mode::Lsp);
+        let args = parse("export profile.json --from-save -L 4").unwrap();
+        assert!(args.mode == Mode::Export && args.from_save);
+        let args = parse("transpile script.hrm --target rust").unwrap();
+        assert_eq!((args.mode, args.target), (Mode::Transpile, Target::Rust));
+        assert_eq!(parse("decompile script.hrm").unwrap().mode, Mode::Decompile);
+        let args =
+            parse("script.hrm --max-tiles 10 --max-allocations 100 --max-outputs 5").unwrap();
+        assert_eq!(args.limits.max_tiles, Some(10));
+        assert_eq!(args.limits.max_outputs, Some(5));
+        assert_eq!(args.limits.max_allocations, Some(100));
+        let args = parse("listing script.hrm -l ptr=1").unwrap();
+        assert_eq!(args.mode, Mode::Listing);
+        assert_eq!(args.tile_names.get("ptr"), Some(&1));
+    }
+
+    #[test]
+    fn test_parse_combined_flags() {
+        let args = parse("script.hrm -xHi 1 -2").unwrap();
+        assert!(args.extensions && args.strict_hrm);
+        assert_eq!(args.input_values, vec![1.into(), (-2).into()]);
+
+        // After "--", everything is a value
+        let args = parse("script.hrm -x -J -- --").unwrap();
+        assert_eq!(args.output_separator, Some("--".to_string()));
+    }
+
+    #[test]
+    fn test_usage_errors() {
+        assert!(matches!(parse("fmt --check"), Err(CliError::MissingScript)));
+        assert!(matches!(
+            parse("script.hrm --colour"),
+            Err(CliError::UnknownOption(option)) if option == "--colour"
+        ));
+        assert!(matches!(
+            parse("script.hrm -ix"),
+            Err(CliError::UnknownOption(option)) if option == "-ix"
+        ));
+        assert!(matches!(
+            parse("script.hrm 1 -i 1"),
+            Err(CliError::UnexpectedArgument(arg)) if arg == "1"
+        ));
+        assert!(matches!(
+            parse("dap script.hrm"),
+            Err(CliError::UnexpectedArgument(arg)) if arg == "script.hrm"
+        ));
+        assert!(matches!(
+            parse("script.hrm -M"),
+            Err(CliError::WrongValues { option, expected, got })
+                if option == "-M" && expected == "<max_address>" && got == "nothing"
+        ));
+        assert_eq!(
+            parse("script.hrm --stats 1").err().unwrap().to_string(),
+            "--stats expects no value, got '1'"
+        );
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        let error = parse("script.hrm -s many").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "invalid value for --max-steps: many (expected a number of steps)"
+        );
+        assert!(matches!(error.option(), Some(CommandLineOption::MaxSteps)));
+
+        let error = parse("script.hrm -M 4 -m 0 A 1").err().unwrap();
+        assert!(matches!(error.option(), Some(CommandLineOption::Memory)));
+        assert!(matches!(
+            parse("script.hrm -l zero"),
+            Err(CliError::InvalidValue { option, value, .. }) if option == "--labels" && value == "zero"
+        ));
+        assert!(parse("script.hrm -f xml").is_err());
+        assert!(parse("script.hrm -P 5..2").is_err());
+        assert!(parse("script.hrm -e 1 !!").is_err());
+    }
+
+    #[test]
+    fn test_input_string_from_args() {
+        let option = CommandLineOption::InputString;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option
+            .handle_args(&vec!["HI YOU".to_string()], &mut command_line_args)
+            .unwrap();
+        assert_eq!(
+            command_line_args.input_values,
+            "HIYOU".chars().map(ValueBox::Character).collect::<Vec<_>>()
+        );
+
+        command_line_args.input_values.clear();
+        option
+            .handle_args(
+                &vec!["HI YOU".to_string(), "A".to_string(), "0".to_string()],
+                &mut command_line_args,
+            )
+            .unwrap();
+        assert_eq!(
+            command_line_args.input_values,
+            vec![
+                'H'.into(),
+                'I'.into(),
+                0.into(),
+                'Y'.into(),
+                'O'.into(),
+                'U'.into(),
+                0.into(),
+                'A'.into(),
+                0.into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_output_as_strings_from_args() {
+        let option = CommandLineOption::OutputAsStrings;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option.handle_args(&vec![], &mut command_line_args).unwrap();
+
+        assert!(command_line_args.output_as_strings);
+    }
+
+    #[test]
+    fn test_output_options_from_args() {
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+        assert_eq!(command_line_args.output_options().separator, " ");
+        assert!(!command_line_args.output_options().newline);
+
+        CommandLineOption::Stream
+            .handle_args(&vec![], &mut command_line_args)
+            .unwrap();
+        assert_eq!(command_line_args.output_options().separator, "\n");
+        assert!(command_line_args.output_options().newline);
+
+        CommandLineOption::OutputSeparator
+            .handle_args(&vec![",\\t".to_string()], &mut command_line_args)
+            .unwrap();
+        CommandLineOption::NoNewline
+            .handle_args(&vec![], &mut command_line_args)
+            .unwrap();
+        CommandLineOption::OutputFile
+            .handle_args(&vec!["outputs.txt".to_string()], &mut command_line_args)
+            .unwrap();
+        let options = command_line_args.output_options();
+        assert_eq!(options.separator, ",\t");
+        assert!(!options.newline);
+        assert_eq!(options.file, Some("outputs.txt".to_string()));
+    }
+
+    #[test]
+    fn test_apply_config() {
+        let config = Config {
+            max_memory_address: Some(9),
+            memory: HashMap::from([(9, 0.into())]),
+            extensions: true,
+            test_file: Some("cases.toml".to_string()),
+            ..Default::default()
+        };
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+        command_line_args.apply_config(config.clone());
+        assert_eq!(command_line_args.max_memory_address, 9);
+        assert_eq!(command_line_args.memory, config.memory);
+        assert!(command_line_args.extensions);
+        assert!(!command_line_args.strict_hrm);
+        // A single run isn't turned into a test run
+        assert_eq!(command_line_args.test_file, None);
+
+        // The command line comes first
+        let mut command_line_args = CommandLineArgs {
+            mode: Mode::Test,
+            max_memory_address: 4,
+            memory: HashMap::from([(1, 'A'.into())]),
+            ..CommandLineArgs::default("".to_string())
+        };
+        command_line_args.apply_config(config);
+        assert_eq!(command_line_args.max_memory_address, 4);
+        assert_eq!(command_line_args.memory, HashMap::from([(1, 'A'.into())]));
+        assert_eq!(command_line_args.test_file, Some("cases.toml".to_string()));
+    }
+
     #[test]
     fn test_input_values_from_args() {
-        let args = vec!["10", "20", "30", "A", "E", "F"];
+        let args = ["10", "20", "30", "A", "E", "F"];
         let args = args.iter().map(|s| s.to_string()).collect();
 
         let option = CommandLineOption::InputValues;
         let mut command_line_args = CommandLineArgs::default("".to_string());
 
-        option.handle_args(&args, &mut command_line_args);
+        option.handle_args(&args, &mut command_line_args).unwrap();
 
         assert_eq!(
             command_line_args.input_values,
@@ -265,13 +2249,13 @@ mod cli_tests {
 
     #[test]
     fn test_memory_from_args() {
-        let args = vec!["0", "10", "1", "A", "2", "30", "10", "-5"];
+        let args = ["0", "10", "1", "A", "2", "30", "10", "-5"];
         let args = args.iter().map(|s| s.to_string()).collect();
 
         let option = CommandLineOption::Memory;
         let mut command_line_args = CommandLineArgs::default("".to_string());
 
-        option.handle_args(&args, &mut command_line_args);
+        option.handle_args(&args, &mut command_line_args).unwrap();
 
         assert_eq!(
             command_line_args.memory,
@@ -288,14 +2272,708 @@ mod cli_tests {
 
     #[test]
     fn test_max_memory_address_from_args() {
-        let args = vec!["24"];
+        let args = ["24"];
         let args = args.iter().map(|s| s.to_string()).collect();
 
         let option = CommandLineOption::MaxMemoryAddress;
         let mut command_line_args = CommandLineArgs::default("".to_string());
 
-        option.handle_args(&args, &mut command_line_args);
+        option.handle_args(&args, &mut command_line_args).unwrap();
 
         assert_eq!(command_line_args.max_memory_address, 24);
+
+        // The tiles of --memory must be on the floor, whatever the order of the options
+        assert!(parse("script.hrm -M 9 -m 9 1").is_ok());
+        assert!(matches!(
+            parse("script.hrm -m 9 1 -M 4"),
+            Err(CliError::InvalidValue { option, value, .. }) if option == "--memory" && value == "9"
+        ));
+    }
+
+    #[test]
+    fn test_breakpoints_from_args() {
+        let args = ["a", "12"];
+        let args = args.iter().map(|s| s.to_string()).collect();
+
+        let option = CommandLineOption::Breakpoints;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option.handle_args(&args, &mut command_line_args).unwrap();
+
+        assert_eq!(
+            command_line_args.breakpoints,
+            vec![Breakpoint::Label("a".to_string()), Breakpoint::Line(12)]
+        );
+    }
+
+    #[test]
+    fn test_timeout_from_args() {
+        let args = parse("script.hrm --timeout 2s").unwrap();
+        assert_eq!(args.timeout, Some(Duration::from_secs(2)));
+
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("3"), Some(Duration::from_secs(3)));
+        assert_eq!(parse_duration("1m"), Some(Duration::from_secs(60)));
+        assert_eq!(parse_duration("0s"), None);
+        assert_eq!(parse_duration("2h"), None);
+        assert!(matches!(
+            parse("script.hrm --timeout fast"),
+            Err(CliError::InvalidValue { option, .. }) if option == "--timeout"
+        ));
+    }
+
+    #[test]
+    fn test_check_loops_from_args() {
+        assert!(!parse("script.hrm").unwrap().check_loops);
+        assert!(parse("script.hrm --check-loops").unwrap().check_loops);
+    }
+
+    #[test]
+    fn test_max_instructions_from_args() {
+        let args = parse("script.hrm --max-instructions 20").unwrap();
+        assert_eq!(args.max_instructions, Some(20));
+        assert!(matches!(
+            parse("script.hrm --max-instructions many"),
+            Err(CliError::InvalidValue { option, .. }) if option == "--max-instructions"
+        ));
+    }
+
+    #[test]
+    fn test_max_steps_from_args() {
+        let args = ["10000"];
+        let args = args.iter().map(|s| s.to_string()).collect();
+
+        let option = CommandLineOption::MaxSteps;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option.handle_args(&args, &mut command_line_args).unwrap();
+
+        assert_eq!(command_line_args.max_steps, Some(10000));
+    }
+
+    #[test]
+    fn test_stats_from_args() {
+        let option = CommandLineOption::Stats;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option.handle_args(&vec![], &mut command_line_args).unwrap();
+
+        assert!(command_line_args.print_stats);
+    }
+
+    #[test]
+    fn test_strict_hrm_from_args() {
+        let option = CommandLineOption::StrictHrm;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option.handle_args(&vec![], &mut command_line_args).unwrap();
+
+        assert!(command_line_args.strict_hrm);
+    }
+
+    #[test]
+    fn test_lenient_from_args() {
+        let option = CommandLineOption::Lenient;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option.handle_args(&vec![], &mut command_line_args).unwrap();
+        assert!(command_line_args.parse_options().lenient);
+
+        CommandLineOption::StrictHrm
+            .handle_args(&vec![], &mut command_line_args)
+            .unwrap();
+        assert!(!command_line_args.parse_options().lenient);
+    }
+
+    #[test]
+    fn test_stack_from_args() {
+        let option = CommandLineOption::Stack;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option
+            .handle_args(&vec!["20..=29".to_string()], &mut command_line_args)
+            .unwrap();
+
+        assert_eq!(command_line_args.stack, Some(20..30));
+    }
+
+    #[test]
+    fn test_level_file_from_args() {
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        CommandLineOption::LevelFile
+            .handle_args(&vec!["level.json".to_string()], &mut command_line_args)
+            .unwrap();
+
+        assert_eq!(command_line_args.level_file, Some("level.json".to_string()));
+    }
+
+    #[test]
+    fn test_seed_from_args() {
+        assert_eq!(parse("script.hrm --seed 42").unwrap().seed, Some(42));
+        assert!(matches!(
+            parse("script.hrm --seed -1"),
+            Err(CliError::InvalidValue { option, .. }) if option == "--seed"
+        ));
+    }
+
+    #[test]
+    fn test_record_and_replay_from_args() {
+        let args = parse("script.hrm --record new.json --replay old.json").unwrap();
+        assert_eq!(args.record_file, Some("new.json".to_string()));
+        assert_eq!(args.replay_file, Some("old.json".to_string()));
+
+        // Only the long names
+        assert!(CommandLineOption::Record
+            .help()
+            .starts_with("      --record <file>"));
+        assert!("".parse::<CommandLineOption>().is_err());
+    }
+
+    #[test]
+    fn test_level_from_args() {
+        let args = ["32"];
+        let args = args.iter().map(|s| s.to_string()).collect();
+
+        let option = CommandLineOption::Level;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option.handle_args(&args, &mut command_line_args).unwrap();
+
+        assert_eq!(command_line_args.level, Some(32));
+    }
+
+    #[test]
+    fn test_unknown_level_from_args() {
+        let args = ["5"];
+        let args = args.iter().map(|s| s.to_string()).collect();
+
+        let option = CommandLineOption::Level;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        assert_eq!(
+            option
+                .handle_args(&args, &mut command_line_args)
+                .err()
+                .unwrap()
+                .to_string(),
+            "invalid value for --level: 5 (not a level of the game)"
+        );
+    }
+
+    #[test]
+    fn test_expected_from_args() {
+        let args = ["12", "4", "A"];
+        let args = args.iter().map(|s| s.to_string()).collect();
+
+        let option = CommandLineOption::Expected;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option.handle_args(&args, &mut command_line_args).unwrap();
+
+        assert_eq!(
+            command_line_args.expected_outputs,
+            Some(vec![
+                ValueBox::Number(12),
+                ValueBox::Number(4),
+                ValueBox::Character('A'),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_test_file_from_args() {
+        let args = ["cases.toml"];
+        let args = args.iter().map(|s| s.to_string()).collect();
+
+        let option = CommandLineOption::TestFile;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option.handle_args(&args, &mut command_line_args).unwrap();
+
+        assert_eq!(command_line_args.test_file, Some("cases.toml".to_string()));
+    }
+
+    #[test]
+    fn test_gen_inputs_from_args() {
+        let args = ["5", "numbers", "-10..10,", "2 letters"];
+        let args = args.iter().map(|s| s.to_string()).collect();
+
+        let option = CommandLineOption::GenerateInputs;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option.handle_args(&args, &mut command_line_args).unwrap();
+
+        let spec = command_line_args.input_spec.unwrap();
+        assert_eq!(spec.parts.len(), 2);
+        assert_eq!(spec.parts[0].count, 5);
+        assert_eq!(spec.parts[0].numbers, -10..=10);
+    }
+
+    #[test]
+    fn test_equiv_from_args() {
+        let args =
+            parse("equiv samples/01-MailRoom.hrm samples/01-MailRoom.hrm --max-len 4 --range A..C")
+                .unwrap();
+        assert_eq!(args.mode, Mode::Equiv);
+        assert!(args.compare_script.is_some());
+        assert_eq!(args.max_length, Some(4));
+        assert_eq!(
+            args.value_range,
+            Some(vec![
+                ValueBox::Character('A'),
+                ValueBox::Character('B'),
+                ValueBox::Character('C')
+            ])
+        );
+
+        assert!(matches!(parse("equiv a.hrm"), Err(CliError::MissingScript)));
+        assert!(matches!(
+            parse("equiv a.hrm b.hrm --range 5..1"),
+            Err(CliError::InvalidValue { option, .. }) if option == "--range"
+        ));
+    }
+
+    #[test]
+    fn test_solve_for_from_args() {
+        let args = parse(
+            "solve-for samples/01-MailRoom.hrm --assert len(out)==len(in) out[0]>=0 --max-len 2",
+        )
+        .unwrap();
+        assert_eq!(args.mode, Mode::SolveFor);
+        assert_eq!(args.properties.len(), 2);
+        assert_eq!(args.properties[0].to_string(), "len(out) == len(in)");
+        assert_eq!(args.max_length, Some(2));
+
+        assert!(matches!(
+            parse("solve-for a.hrm --assert out[0]=="),
+            Err(CliError::InvalidValue { option, .. }) if option == "--assert"
+        ));
+    }
+
+    #[test]
+    fn test_compare_from_args() {
+        let args = ["./samples/01-MailRoom.hrm"];
+        let args = args.iter().map(|s| s.to_string()).collect();
+
+        let option = CommandLineOption::Compare;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option.handle_args(&args, &mut command_line_args).unwrap();
+
+        assert!(command_line_args
+            .compare_script
+            .unwrap()
+            .contains("HUMAN RESOURCE MACHINE PROGRAM"));
+    }
+
+    #[test]
+    fn test_trace_from_args() {
+        let option = CommandLineOption::Trace;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option.handle_args(&vec![], &mut command_line_args).unwrap();
+
+        assert!(command_line_args.trace);
+        assert_eq!(command_line_args.trace_file, None);
+
+        option
+            .handle_args(&vec!["trace.txt".to_string()], &mut command_line_args)
+            .unwrap();
+
+        assert_eq!(command_line_args.trace_file, Some("trace.txt".to_string()));
+    }
+
+    #[test]
+    fn test_error_format_from_args() {
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+        assert_eq!(command_line_args.error_format, ErrorFormat::Text);
+
+        CommandLineOption::ErrorFormat
+            .handle_args(&vec!["json".to_string()], &mut command_line_args)
+            .unwrap();
+        assert_eq!(command_line_args.error_format, ErrorFormat::Json);
+    }
+
+    #[test]
+    fn test_trace_format_from_args() {
+        let args = ["jsonl"];
+        let args = args.iter().map(|s| s.to_string()).collect();
+
+        let option = CommandLineOption::TraceFormat;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option.handle_args(&args, &mut command_line_args).unwrap();
+
+        assert!(command_line_args.trace);
+        assert_eq!(command_line_args.trace_format, TraceFormat::Jsonl);
+    }
+
+    #[test]
+    fn test_watchpoints_from_args() {
+        let args = ["5", "24"];
+        let args = args.iter().map(|s| s.to_string()).collect();
+
+        let option = CommandLineOption::Watchpoints;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option.handle_args(&args, &mut command_line_args).unwrap();
+
+        assert_eq!(command_line_args.watchpoints, vec![5, 24]);
+        assert!(command_line_args.is_debugging());
+    }
+
+    #[test]
+    fn test_stream_from_args() {
+        let option = CommandLineOption::Stream;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option.handle_args(&vec![], &mut command_line_args).unwrap();
+
+        assert!(command_line_args.stream);
+    }
+
+    #[test]
+    fn test_checkpoints_from_args() {
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        CommandLineOption::CheckpointEvery
+            .handle_args(&vec!["500".to_string()], &mut command_line_args)
+            .unwrap();
+        CommandLineOption::CheckpointFile
+            .handle_args(&vec!["state.json".to_string()], &mut command_line_args)
+            .unwrap();
+        CommandLineOption::Resume
+            .handle_args(&vec!["old.json".to_string()], &mut command_line_args)
+            .unwrap();
+
+        assert_eq!(command_line_args.checkpoint_every, Some(500));
+        assert_eq!(
+            command_line_args.checkpoint_file,
+            Some("state.json".to_string())
+        );
+        assert_eq!(command_line_args.resume_file, Some("old.json".to_string()));
+    }
+
+    #[test]
+    fn test_checkpoint_every_zero() {
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+        assert!(CommandLineOption::CheckpointEvery
+            .handle_args(&vec!["0".to_string()], &mut command_line_args)
+            .is_err());
+    }
+
+    #[test]
+    fn test_check_format_from_args() {
+        let option = CommandLineOption::CheckFormat;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option.handle_args(&vec![], &mut command_line_args).unwrap();
+
+        assert!(command_line_args.check_format);
+    }
+
+    #[test]
+    fn test_require_full_coverage_from_args() {
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        CommandLineOption::Coverage
+            .handle_args(&vec![], &mut command_line_args)
+            .unwrap();
+        assert!(command_line_args.coverage);
+        assert!(!command_line_args.require_full_coverage);
+
+        CommandLineOption::RequireFullCoverage
+            .handle_args(&vec![], &mut command_line_args)
+            .unwrap();
+        assert!(command_line_args.require_full_coverage);
+    }
+
+    #[test]
+    fn test_profile_from_args() {
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        CommandLineOption::Profile
+            .handle_args(&vec![], &mut command_line_args)
+            .unwrap();
+
+        assert!(command_line_args.profile);
+    }
+
+    #[test]
+    fn test_optimize_from_args() {
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        CommandLineOption::Optimize
+            .handle_args(&vec![], &mut command_line_args)
+            .unwrap();
+
+        assert!(command_line_args.optimize);
+    }
+
+    #[test]
+    fn test_score_from_args() {
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        CommandLineOption::Score
+            .handle_args(&vec![], &mut command_line_args)
+            .unwrap();
+        assert!(command_line_args.score);
+        assert_eq!(command_line_args.score_runs, None);
+
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+        CommandLineOption::Runs
+            .handle_args(&vec!["50".to_string()], &mut command_line_args)
+            .unwrap();
+        assert!(command_line_args.score);
+        assert_eq!(command_line_args.score_runs, Some(50));
+    }
+
+    #[test]
+    fn test_zero_runs() {
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+        assert!(CommandLineOption::Runs
+            .handle_args(&vec!["0".to_string()], &mut command_line_args)
+            .is_err());
+    }
+
+    #[test]
+    fn test_jobs_from_args() {
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+        assert_eq!(command_line_args.jobs, 1);
+
+        CommandLineOption::Jobs
+            .handle_args(&vec!["8".to_string()], &mut command_line_args)
+            .unwrap();
+        assert_eq!(command_line_args.jobs, 8);
+    }
+
+    #[test]
+    fn test_zero_jobs() {
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+        assert!(CommandLineOption::Jobs
+            .handle_args(&vec!["0".to_string()], &mut command_line_args)
+            .is_err());
+    }
+
+    #[test]
+    fn test_tui_from_args() {
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        CommandLineOption::Tui
+            .handle_args(&vec![], &mut command_line_args)
+            .unwrap();
+
+        assert!(command_line_args.tui);
+    }
+
+    #[test]
+    fn test_watch_files_from_args() {
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        CommandLineOption::WatchFiles
+            .handle_args(&vec![], &mut command_line_args)
+            .unwrap();
+
+        assert!(command_line_args.watch_files);
+    }
+
+    #[test]
+    fn test_verbosity_from_args() {
+        assert_eq!(parse("script.hrm").unwrap().verbosity, Verbosity::Normal);
+        assert_eq!(
+            parse("script.hrm --verbose").unwrap().verbosity,
+            Verbosity::Verbose
+        );
+        assert_eq!(
+            parse("script.hrm --verbose --verbose").unwrap().verbosity,
+            Verbosity::Trace
+        );
+        assert_eq!(parse("script.hrm -vv").unwrap().verbosity, Verbosity::Trace);
+        let args = parse("script.hrm -q -Z").unwrap();
+        assert_eq!(
+            (args.verbosity, args.newline),
+            (Verbosity::Quiet, Some(false))
+        );
+        assert!(parse("script.hrm -X").unwrap().coverage);
+    }
+
+    #[test]
+    fn test_dump_from_args() {
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        CommandLineOption::DumpMemory
+            .handle_args(&vec![], &mut command_line_args)
+            .unwrap();
+        assert!(command_line_args.dump_memory);
+        assert_eq!(command_line_args.dump_memory_file, None);
+
+        CommandLineOption::DumpMemory
+            .handle_args(&vec!["floor.txt".to_string()], &mut command_line_args)
+            .unwrap();
+        assert_eq!(
+            command_line_args.dump_memory_file,
+            Some("floor.txt".to_string())
+        );
+
+        CommandLineOption::DumpHead
+            .handle_args(&vec![], &mut command_line_args)
+            .unwrap();
+        assert!(command_line_args.dump_head);
+    }
+
+    #[test]
+    fn test_remove_dead_instructions_from_args() {
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        CommandLineOption::RemoveDeadInstructions
+            .handle_args(&vec![], &mut command_line_args)
+            .unwrap();
+
+        assert!(command_line_args.optimize);
+        assert!(command_line_args.remove_dead_instructions);
+    }
+
+    #[test]
+    fn test_parse_input_values() {
+        assert_eq!(
+            parse_input_values("1 2\n  -3\tA\n"),
+            Ok(vec![
+                ValueBox::Number(1),
+                ValueBox::Number(2),
+                ValueBox::Number(-3),
+                ValueBox::Character('A'),
+            ])
+        );
+        assert_eq!(parse_input_values(""), Ok(vec![]));
+        assert_eq!(parse_input_values("1 AB 2"), Err("AB"));
+    }
+
+    #[test]
+    fn test_format_from_args() {
+        let args = ["json"];
+        let args = args.iter().map(|s| s.to_string()).collect();
+
+        let option = CommandLineOption::Format;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option.handle_args(&args, &mut command_line_args).unwrap();
+
+        assert_eq!(command_line_args.format, OutputFormat::Json);
+    }
+
+    #[test]
+    fn test_tile_names_from_args() {
+        let args = ["zero=24", "ptr=3"];
+        let args = args.iter().map(|s| s.to_string()).collect();
+
+        let option = CommandLineOption::TileNames;
+        let mut command_line_args = CommandLineArgs::default("".to_string());
+
+        option.handle_args(&args, &mut command_line_args).unwrap();
+
+        assert_eq!(
+            command_line_args.tile_names,
+            TileNames::from([("zero".to_string(), 24), ("ptr".to_string(), 3)])
+        );
+    }
+
+    #[test]
+    fn test_apply_level() {
+        let level = levels::get_level(30).unwrap();
+        let mut args = parse("script.hrm -L 30").unwrap();
+        args.apply_level(level).unwrap();
+        assert_eq!(args.max_memory_address, 24);
+        assert_eq!(args.memory, level.memory());
+
+        // The floor of the level must be within -M
+        let mut args = parse("script.hrm -L 30 -M 3").unwrap();
+        assert!(matches!(
+            args.apply_level(level),
+            Err(CliError::InvalidValue { option, value, .. }) if option == "--level" && value == "30"
+        ));
+        // The tiles of --memory replace the ones of the level, and must be on its floor
+        let mut args = parse("script.hrm -L 30 -m 30 1").unwrap();
+        assert!(matches!(
+            args.apply_level(level),
+            Err(CliError::InvalidValue { option, .. }) if option == "--memory"
+        ));
+    }
+
+    #[test]
+    fn test_apply_level_file() {
+        let level_file = LevelFile {
+            name: "Double Trouble".to_string(),
+            floor_size: Some(4),
+            floor: HashMap::from([(3, ValueBox::from(0))]),
+            inbox: vec![],
+            outbox: vec![],
+        };
+        let mut args = parse("script.hrm --level-file level.json").unwrap();
+        args.apply_level_file(&level_file).unwrap();
+        assert_eq!(args.max_memory_address, 3);
+        assert_eq!(args.memory, level_file.floor);
+
+        let mut args = parse("script.hrm --level-file level.json -M 2").unwrap();
+        assert!(matches!(
+            args.apply_level_file(&level_file),
+            Err(CliError::InvalidValue { option, value, .. })
+                if option == "--level-file" && value == "level.json"
+        ));
+    }
+
+    #[test]
+    fn test_stage_floor_from_args() {
+        assert!(parse("pipe a.hrm b.hrm -m 5 1 --stage-max-memory 1=9").is_ok());
+        // A stage without its own memory takes the one of --memory
+        assert!(matches!(
+            parse("pipe a.hrm b.hrm -m 5 1 --stage-max-memory 2=3"),
+            Err(CliError::InvalidValue { option, value, .. })
+                if option == "--stage-max-memory" && value == "2=3"
+        ));
+
+        let file = std::env::temp_dir().join("hrm_stage_memory_test.txt");
+        fs::write(&file, "7 1\n").unwrap();
+        let stage_memory = format!("--stage-memory 2={}", file.display());
+        assert!(parse(&format!("pipe a.hrm b.hrm -M 9 {}", stage_memory)).is_ok());
+        assert!(matches!(
+            parse(&format!("pipe a.hrm b.hrm -M 9 --stage-max-memory 2=4 {}", stage_memory)),
+            Err(CliError::InvalidValue { option, value, .. })
+                if option == "--stage-memory" && value == "7"
+        ));
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn test_floor_from_args() {
+        let args = parse("script.hrm --floor 5x5 -l r0c0=24").unwrap();
+        assert_eq!(
+            args.floor,
+            Some(FloorGeometry {
+                columns: 5,
+                rows: 5
+            })
+        );
+
+        // The names given with -l replace the ones of the floor
+        let tile_names = args.parse_options().tile_names;
+        assert_eq!(tile_names["r2c3"], 13);
+        assert_eq!(tile_names["r0c0"], 24);
+
+        assert!(matches!(
+            parse("script.hrm --floor 5"),
+            Err(CliError::InvalidValue { option, .. }) if option == "--floor"
+        ));
+
+        // The floor gives the last address, against which the tiles of --memory are checked
+        assert_eq!(
+            parse("script.hrm --floor 2x2").unwrap().max_memory_address,
+            3
+        );
+        assert!(matches!(
+            parse("script.hrm --floor 2x2 -m 30 1"),
+            Err(CliError::InvalidValue { option, value, .. }) if option == "--memory" && value == "30"
+        ));
+        assert!(parse("script.hrm --floor 2x2 -M 30 -m 30 1").is_ok());
     }
 }
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use hrm_interpreter::{
+    interpreter::{memory::Memory, settings::Settings, Interpreter},
+    script_object::{value_box::ValueBox, ScriptObject},
+};
+
+use crate::check::{self, Mismatch};
+
+/// Number of inboxes on which the scripts are compared, when the inputs are generated
+pub const GENERATED_RUNS: usize = 100;
+
+/// The outputs and the number of steps of a script, or its error
+type RunResult = Result<(Vec<ValueBox>, usize), String>;
+
+/// The results of both scripts on the same inbox
+pub struct Comparison {
+    pub inputs: Vec<ValueBox>,
+    pub first: RunResult,
+    pub second: RunResult,
+}
+
+impl Comparison {
+    /// The first output that differs between the scripts.
+    /// Returns None if both scripts produced the same outputs, or both failed.
+    pub fn divergence(&self) -> Option<Mismatch> {
+        match (&self.first, &self.second) {
+            (Ok((first, _)), Ok((second, _))) => check::first_mismatch(first, second),
+            (Err(_), Err(_)) => None,
+            // One script failed: the divergence is after its last output
+            (Ok((outputs, _)), Err(_)) | (Err(_), Ok((outputs, _))) => Some(Mismatch {
+                index: outputs.len(),
+                expected: None,
+                actual: None,
+            }),
+        }
+    }
+}
+
+fn run(
+    script: &ScriptObject,
+    memory: &HashMap<usize, ValueBox>,
+    max_memory_address: usize,
+    inputs: &[ValueBox],
+    settings: &Settings,
+) -> RunResult {
+    let memory = Memory::with_data(memory.clone(), max_memory_address);
+    let mut interpreter = Interpreter::with_settings(memory, settings.clone());
+    interpreter
+        .execute(script, inputs)
+        .map(|(outputs, stats)| (outputs, stats.steps))
+        .map_err(|e| e.to_string())
+}
+
+/// Run both scripts on the same inputs and floor
+pub fn compare(
+    first: &ScriptObject,
+    second: &ScriptObject,
+    memory: &HashMap<usize, ValueBox>,
+    max_memory_address: usize,
+    inputs: &[ValueBox],
+    settings: &Settings,
+) -> Comparison {
+    Comparison {
+        inputs: inputs.to_vec(),
+        first: run(first, memory, max_memory_address, inputs, settings),
+        second: run(second, memory, max_memory_address, inputs, settings),
+    }
+}
+
+fn describe(result: &RunResult) -> String {
+    match result {
+        Ok((outputs, steps)) => format!("{} ({} steps)", check::join_values(outputs), steps),
+        Err(e) => format!("error: {}", e.lines().next().unwrap_or_default()),
+    }
+}
+
+/// Print the first divergence (if any) and the step counts of the comparisons to stderr.
+/// Returns true if the scripts never diverged.
+pub fn report(comparisons: &[Comparison]) -> bool {
+    let divergence = comparisons
+        .iter()
+        .find_map(|comparison| Some((comparison, comparison.divergence()?)));
+
+    if let Some((comparison, mismatch)) = divergence {
+        eprintln!("The scripts diverge at output #{}", mismatch.index + 1);
+        eprintln!("Inbox:  {}", check::join_values(&comparison.inputs));
+        eprintln!("Script: {}", describe(&comparison.first));
+        eprintln!("Other:  {}", describe(&comparison.second));
+        return false;
+    }
+
+    let steps = |result: &RunResult| result.as_ref().map_or(0, |(_, steps)| *steps);
+    let first_steps = comparisons.iter().map(|c| steps(&c.first)).sum::<usize>();
+    let second_steps = comparisons.iter().map(|c| steps(&c.second)).sum::<usize>();
+    let runs = comparisons.len().max(1);
+
+    eprintln!("Same outputs on {} inbox(es)", comparisons.len());
+    eprintln!(
+        "Average steps: {} (script) vs {} (other), {:+}",
+        first_steps / runs,
+        second_steps / runs,
+        (second_steps as i64 - first_steps as i64) / runs as i64
+    );
+    true
+}
+
+#[cfg(test)]
+mod compare_tests {
+    use super::*;
+
+    const MAIL_ROOM: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+    a:
+        INBOX
+        OUTBOX
+        JUMP a
+    ";
+
+    const MAIL_ROOM_UNROLLED: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+    a:
+        INBOX
+        OUTBOX
+        INBOX
+        OUTBOX
+        JUMP a
+    ";
+
+    const FIRST_ONLY: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        INBOX
+        OUTBOX
+    ";
+
+    fn compare_scripts(first: &str, second: &str, inputs: &[ValueBox]) -> Comparison {
+        compare(
+            &first.parse::<ScriptObject>().unwrap(),
+            &second.parse::<ScriptObject>().unwrap(),
+            &HashMap::new(),
+            usize::MAX,
+            inputs,
+            &Settings::default(),
+        )
+    }
+
+    #[test]
+    fn test_same_outputs() {
+        let inputs = [1.into(), 2.into(), 3.into(), 4.into()];
+        let comparison = compare_scripts(MAIL_ROOM, MAIL_ROOM_UNROLLED, &inputs);
+
+        assert!(comparison.divergence().is_none());
+        assert_eq!(comparison.first.as_ref().unwrap().1, 13);
+        assert_eq!(comparison.second.as_ref().unwrap().1, 11);
+    }
+
+    #[test]
+    fn test_divergence() {
+        let inputs = [1.into(), 2.into()];
+        let comparison = compare_scripts(MAIL_ROOM, FIRST_ONLY, &inputs);
+
+        assert_eq!(
+            comparison.divergence(),
+            Some(Mismatch {
+                index: 1,
+                expected: Some(2.into()),
+                actual: None,
+            })
+        );
+    }
+}
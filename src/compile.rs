@@ -0,0 +1,117 @@
+//! Compile a script to the source of another language, to run a solution without the interpreter.
+//!
+//! The script is first lowered to the flat bytecode of the interpreter: each instruction gets
+//! an index, and the jumps go to indices. Only the instructions of the game, `HALT` and `NOP`
+//! can be compiled.
+
+use std::str::FromStr;
+
+use crate::{
+    interpreter::bytecode::Bytecode,
+    script_object::{instruction::Instruction, ScriptObject},
+};
+
+pub mod rust;
+pub mod wat;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+/// The language a script is compiled to
+pub enum Target {
+    /// A Rust function taking the inbox and the floor, and returning the outbox
+    #[default]
+    Rust,
+    /// A WebAssembly module in the text format, with the values in its memory
+    Wat,
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rust" => Ok(Self::Rust),
+            "wat" => Ok(Self::Wat),
+            _ => Err(format!("Invalid target: {} (expected rust or wat)", s)),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+/// A script that can't be compiled
+pub enum CompileError {
+    #[error("{instruction} on line {line} can't be compiled: only the instructions of the game, HALT and NOP can")]
+    Unsupported { instruction: String, line: usize },
+}
+
+/// Compile the script to the source of the target
+pub fn compile(script: &ScriptObject, target: Target) -> Result<String, CompileError> {
+    let bytecode = script.bytecode();
+    check_instructions(script, bytecode)?;
+    Ok(match target {
+        Target::Rust => rust::compile(script, bytecode),
+        Target::Wat => wat::compile(script, bytecode),
+    })
+}
+
+/// Fail on the first instruction that can't be compiled
+fn check_instructions(script: &ScriptObject, bytecode: &Bytecode) -> Result<(), CompileError> {
+    let unsupported = bytecode.ops.iter().find(|op| {
+        !matches!(
+            op.instruction,
+            Instruction::In
+                | Instruction::Out
+                | Instruction::CopyFrom(_)
+                | Instruction::CopyTo(_)
+                | Instruction::Add(_)
+                | Instruction::Sub(_)
+                | Instruction::BumpUp(_)
+                | Instruction::BumpDown(_)
+                | Instruction::Jump(_)
+                | Instruction::JumpIfZero(_)
+                | Instruction::JumpIfNegative(_)
+                | Instruction::Halt
+                | Instruction::Nop
+        )
+    });
+    match unsupported {
+        Some(op) => Err(CompileError::Unsupported {
+            instruction: op.instruction.name().to_string(),
+            line: script.get_line(op.position).unwrap_or_default(),
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod compile_tests {
+    use crate::script_object::instruction::ParseOptions;
+
+    use super::*;
+
+    #[test]
+    fn test_target() {
+        assert_eq!("rust".parse::<Target>(), Ok(Target::Rust));
+        assert_eq!("wat".parse::<Target>(), Ok(Target::Wat));
+        assert!("c".parse::<Target>().is_err());
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let options = ParseOptions {
+            extensions: true,
+            ..Default::default()
+        };
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            MUL 0
+        ";
+        let script = ScriptObject::parse_with_options(script, &options).unwrap();
+        assert_eq!(
+            compile(&script, Target::Rust),
+            Err(CompileError::Unsupported {
+                instruction: "MUL".to_string(),
+                line: 3
+            })
+        );
+    }
+}
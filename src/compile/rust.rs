@@ -0,0 +1,246 @@
+//! The Rust backend: a standalone `run` function, with a loop over a `match` on the index
+//! of the next instruction, that gives the same outbox as the interpreter (without `--strict-hrm`,
+//! the values are only bounded by `i32`).
+
+use std::fmt::Write;
+
+use crate::{
+    interpreter::bytecode::{Bytecode, Op},
+    script_object::{instruction::Instruction, value_box::ValueBoxMemoryAddress, ScriptObject},
+};
+
+/// The code before the instructions; the floor must be at least as large as the direct addresses.
+/// The methods of `Value` are the ones of [`ValueBox`](crate::script_object::value_box::ValueBox).
+const HEADER: &str = "// Compiled from a Human Resource Machine script by hrm-interpreter
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Number(i32),
+    Letter(char),
+}
+
+#[allow(unused)]
+impl Value {
+    /// The index of a letter in the alphabet, whatever its case
+    fn alphabet_index(letter: char) -> i32 {
+        letter.to_ascii_uppercase() as i32 - 'A' as i32
+    }
+
+    fn is_zero(self) -> bool {
+        matches!(self, Value::Number(0))
+    }
+
+    fn is_negative(self) -> bool {
+        matches!(self, Value::Number(n) if n < 0)
+    }
+}
+
+/// Run the script on the inbox, with the tiles of the floor (None for an empty tile).
+/// Returns the outbox, or a message if the script fails.
+#[allow(unused)]
+pub fn run(inbox: &[Value], floor: &mut [Option<Value>]) -> Result<Vec<Value>, String> {
+";
+
+/// The lines reading the address of the instruction into `address`, if it's a pointer,
+/// and the expression of the address
+fn address(vbma: &ValueBoxMemoryAddress, error: &str) -> (Vec<String>, String) {
+    match vbma {
+        ValueBoxMemoryAddress::Pointer(tile) => (vec![], tile.to_string()),
+        ValueBoxMemoryAddress::PointerAddress(tile) => (
+            vec![
+                format!("let address = match floor[{tile}] {{"),
+                "    Some(Value::Number(n)) if n >= 0 && (n as usize) < floor.len() => n as usize,"
+                    .to_string(),
+                format!("    _ => return Err(\"{error}: tile {tile} doesn't hold the address of a tile\".into()),"),
+                "};".to_string(),
+            ],
+            "address".to_string(),
+        ),
+    }
+}
+
+/// The lines of the op at `index`, indented from the arm, ending with the index of the next op
+fn statements(op: &Op, index: usize, end: usize, line: usize) -> Vec<String> {
+    let error = format!("line {}: {}", line, op.instruction);
+    let next = index + 1;
+    let target = op.jump.unwrap_or(end);
+    let (mut lines, address) = match op.instruction.memory_address() {
+        Some(vbma) => address(vbma, &error),
+        None => (vec![], String::new()),
+    };
+    match op.instruction {
+        Instruction::In => lines.extend([
+            "match inbox.next() {".to_string(),
+            "    Some(value) => head = Some(value),".to_string(),
+            "    None => return Ok(outbox),".to_string(),
+            "}".to_string(),
+            next.to_string(),
+        ]),
+        Instruction::Out => lines.extend([
+            format!("outbox.push(head.ok_or(\"{error} with empty hands\")?);"),
+            next.to_string(),
+        ]),
+        Instruction::CopyFrom(_) => lines.extend([
+            format!("head = Some(floor[{address}].ok_or(\"{error}: the tile is empty\")?);"),
+            next.to_string(),
+        ]),
+        Instruction::CopyTo(_) => lines.extend([
+            format!("floor[{address}] = Some(head.ok_or(\"{error} with empty hands\")?);"),
+            next.to_string(),
+        ]),
+        Instruction::Add(_) | Instruction::Sub(_) => {
+            let operation = match op.instruction {
+                Instruction::Add(_) => "checked_add",
+                _ => "checked_sub",
+            };
+            lines.extend([
+                format!("head = Some(match (head, floor[{address}]) {{"),
+                "    (Some(Value::Number(a)), Some(Value::Number(b))) => {".to_string(),
+                format!("        Value::Number(a.{operation}(b).ok_or(\"{error}: overflow\")?)"),
+                "    }".to_string(),
+            ]);
+            if let Instruction::Sub(_) = op.instruction {
+                lines.extend([
+                    "    (Some(Value::Letter(a)), Some(Value::Letter(b))) => {".to_string(),
+                    "        Value::Number(Value::alphabet_index(a) - Value::alphabet_index(b))"
+                        .to_string(),
+                    "    }".to_string(),
+                ]);
+            }
+            lines.extend([
+                format!("    _ => return Err(\"{error}: the hands and the tile can't be combined\".into()),"),
+                "});".to_string(),
+                next.to_string(),
+            ]);
+        }
+        Instruction::BumpUp(_) | Instruction::BumpDown(_) => {
+            let step = match op.instruction {
+                Instruction::BumpUp(_) => 1,
+                _ => -1,
+            };
+            lines.extend([
+                format!("match floor[{address}] {{"),
+                "    Some(Value::Number(n)) => {".to_string(),
+                format!("        let n = n.checked_add({step}).ok_or(\"{error}: overflow\")?;"),
+                format!("        floor[{address}] = Some(Value::Number(n));"),
+                "        head = Some(Value::Number(n));".to_string(),
+                "    }".to_string(),
+                format!("    _ => return Err(\"{error}: the tile doesn't hold a number\".into()),"),
+                "}".to_string(),
+                next.to_string(),
+            ]);
+        }
+        Instruction::Jump(_) => lines.push(target.to_string()),
+        Instruction::JumpIfZero(_) | Instruction::JumpIfNegative(_) => {
+            let condition = match op.instruction {
+                Instruction::JumpIfZero(_) => "Some(value) if value.is_zero()",
+                _ => "Some(value) if value.is_negative()",
+            };
+            lines.extend([
+                "match head {".to_string(),
+                format!("    {condition} => {target},"),
+                format!("    Some(_) => {next},"),
+                format!("    None => return Err(\"{error} with empty hands\".into()),"),
+                "}".to_string(),
+            ]);
+        }
+        Instruction::Halt => lines.push("return Ok(outbox)".to_string()),
+        // Only the supported instructions get here
+        _ => lines.push(next.to_string()),
+    }
+    lines
+}
+
+/// The source of the `run` function (the instructions must be supported)
+pub(super) fn compile(script: &ScriptObject, bytecode: &Bytecode) -> String {
+    let mut code = HEADER.to_string();
+    let floor_size = bytecode
+        .ops
+        .iter()
+        .filter_map(|op| op.instruction.memory_address())
+        .map(|vbma| match vbma {
+            ValueBoxMemoryAddress::Pointer(tile) | ValueBoxMemoryAddress::PointerAddress(tile) => {
+                tile + 1
+            }
+        })
+        .max();
+    if let Some(floor_size) = floor_size {
+        writeln!(
+            code,
+            "    if floor.len() < {floor_size} {{
+        return Err(\"the floor needs {floor_size} tiles\".into());
+    }}"
+        )
+        .unwrap();
+    }
+    code.push_str(
+        "    let mut inbox = inbox.iter().copied();
+    let mut outbox = Vec::new();
+    let mut head: Option<Value> = None;
+    let mut next = 0;
+    loop {
+        next = match next {
+",
+    );
+    let end = bytecode.ops.len();
+    for (index, op) in bytecode.ops.iter().enumerate() {
+        let line = script.get_line(op.position).unwrap_or_default();
+        writeln!(code, "            // line {}: {}", line, op.instruction).unwrap();
+        writeln!(code, "            {} => {{", index).unwrap();
+        for statement in statements(op, index, end, line) {
+            writeln!(code, "                {}", statement).unwrap();
+        }
+        code.push_str("            }\n");
+    }
+    code.push_str(
+        "            _ => return Ok(outbox),
+        };
+    }
+}
+",
+    );
+    code
+}
+
+#[cfg(test)]
+mod rust_tests {
+    use crate::compile::{compile, Target};
+
+    use super::*;
+
+    #[test]
+    fn test_compile() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            JUMPN    b
+            COPYTO   [1]
+            OUTBOX
+        b:
+            JUMP     a
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        let code = compile(&script, Target::Rust).unwrap();
+        assert!(code.starts_with(HEADER));
+        // A check of the floor, then an arm per instruction
+        assert!(code.contains("if floor.len() < 2 {"));
+        assert!(code.contains(
+            "            // line 4: JUMPN b
+            1 => {
+                match head {
+                    Some(value) if value.is_negative() => 4,
+                    Some(_) => 2,
+                    None => return Err(\"line 4: JUMPN b with empty hands\".into()),
+                }
+            }"
+        ));
+        assert!(code.contains("floor[address] = Some(head.ok_or("));
+        assert!(code.contains(
+            "            // line 8: JUMP a
+            4 => {
+                0
+            }"
+        ));
+    }
+}
@@ -0,0 +1,406 @@
+//! The WebAssembly backend: a module in the text format, exporting its memory and a `run` function.
+//!
+//! The values are 8 bytes in the memory: a kind (0 for an empty tile, 1 for a number,
+//! 2 for a letter) and the number or the code of the letter, as `i32`. The floor is at
+//! the beginning of the memory, the inbox and the outbox anywhere after it:
+//! `run(floor_size, inbox, inbox_len, outbox)` returns the length of the outbox,
+//! or minus the line of the instruction that failed.
+
+use crate::{
+    interpreter::bytecode::{Bytecode, Op},
+    script_object::{instruction::Instruction, value_box::ValueBoxMemoryAddress, ScriptObject},
+};
+
+/// The module until the instructions of `run`; `$index`, `$is_zero` and `$is_negative`
+/// are the methods of [`ValueBox`](crate::script_object::value_box::ValueBox)
+const HEADER: &str = ";; Compiled from a Human Resource Machine script by hrm-interpreter
+(module
+  (memory (export \"memory\") 1)
+
+  ;; The index of a letter in the alphabet
+  (func $index (param $letter i32) (result i32)
+    local.get $letter
+    i32.const 32
+    i32.const 0
+    local.get $letter
+    i32.const 97
+    i32.ge_u
+    select
+    i32.sub
+    i32.const 65
+    i32.sub)
+
+  ;; Whether the value is the number 0: letters never jump
+  (func $is_zero (param $kind i32) (param $value i32) (result i32)
+    local.get $kind
+    i32.const 1
+    i32.eq
+    local.get $value
+    i32.eqz
+    i32.and)
+
+  ;; Whether the value is a negative number: letters never jump
+  (func $is_negative (param $kind i32) (param $value i32) (result i32)
+    local.get $kind
+    i32.const 1
+    i32.eq
+    local.get $value
+    i32.const 0
+    i32.lt_s
+    i32.and)
+
+  ;; Run the script on the inbox and the floor: returns the length of the outbox,
+  ;; or minus the line of the instruction that failed
+  (func (export \"run\")
+    (param $floor_size i32) (param $inbox i32) (param $inbox_len i32) (param $outbox i32)
+    (result i32)
+    ;; The values in the hands
+    (local $kind i32) (local $value i32)
+    (local $read i32) (local $written i32) (local $next i32)
+    (local $address i32) (local $wide i64)
+";
+
+/// The instructions of `run`, indented by their nesting
+struct Code {
+    lines: Vec<String>,
+    depth: usize,
+}
+
+impl Code {
+    fn push(&mut self, instruction: &str) {
+        if instruction == "end" || instruction == "else" {
+            self.depth -= 1;
+        }
+        self.lines
+            .push(format!("{}{}", "  ".repeat(self.depth), instruction));
+        if ["block", "loop", "if", "else"]
+            .iter()
+            .any(|opening| instruction.split(' ').next() == Some(opening))
+        {
+            self.depth += 1;
+        }
+    }
+
+    fn extend(&mut self, instructions: &[&str]) {
+        for instruction in instructions {
+            self.push(instruction);
+        }
+    }
+
+    /// Return minus the line if the condition on the stack is true
+    fn fail_if(&mut self, line: usize) {
+        self.extend(&["if", &format!("i32.const -{}", line), "return", "end"]);
+    }
+
+    /// The address in the memory of the tile of the instruction, in `$address`
+    fn address(&mut self, vbma: &ValueBoxMemoryAddress, line: usize) {
+        match vbma {
+            ValueBoxMemoryAddress::Pointer(tile) => {
+                self.push(&format!("i32.const {}", tile));
+            }
+            // The tile holds the address of the tile
+            ValueBoxMemoryAddress::PointerAddress(tile) => {
+                self.extend(&[
+                    &format!("i32.const {}", tile),
+                    "local.get $floor_size",
+                    "i32.ge_u",
+                ]);
+                self.fail_if(line);
+                self.extend(&[
+                    &format!("i32.const {}", tile * 8),
+                    "i32.load",
+                    "i32.const 1",
+                    "i32.ne",
+                ]);
+                self.fail_if(line);
+                self.extend(&[&format!("i32.const {}", tile * 8), "i32.load offset=4"]);
+            }
+        }
+        self.extend(&["local.tee $address", "local.get $floor_size", "i32.ge_u"]);
+        self.fail_if(line);
+        self.extend(&[
+            "local.get $address",
+            "i32.const 8",
+            "i32.mul",
+            "local.set $address",
+        ]);
+    }
+
+    /// Fail if the kind of the value in the hands isn't the given one
+    fn expect_hands(&mut self, kind: u8, line: usize) {
+        self.extend(&["local.get $kind", &format!("i32.const {}", kind), "i32.ne"]);
+        self.fail_if(line);
+    }
+
+    /// Fail if the kind of the value on the tile isn't the given one
+    fn expect_tile(&mut self, kind: u8, line: usize) {
+        self.extend(&[
+            "local.get $address",
+            "i32.load",
+            &format!("i32.const {}", kind),
+            "i32.ne",
+        ]);
+        self.fail_if(line);
+    }
+
+    /// Put the number computed in `$wide` in the hands, failing if it doesn't fit in an `i32`
+    fn number_from_wide(&mut self, line: usize) {
+        self.extend(&[
+            "local.get $wide",
+            "local.get $wide",
+            "i32.wrap_i64",
+            "i64.extend_i32_s",
+            "i64.ne",
+        ]);
+        self.fail_if(line);
+        self.extend(&[
+            "local.get $wide",
+            "i32.wrap_i64",
+            "local.set $value",
+            "i32.const 1",
+            "local.set $kind",
+        ]);
+    }
+
+    /// Go on at the op of the index
+    fn jump(&mut self, target: usize) {
+        self.extend(&[
+            &format!("i32.const {}", target),
+            "local.set $next",
+            "br $next",
+        ]);
+    }
+
+    fn op(&mut self, op: &Op, end: usize, line: usize) {
+        if let Some(vbma) = op.instruction.memory_address() {
+            self.address(vbma, line);
+        }
+        match op.instruction {
+            Instruction::In => {
+                self.extend(&["local.get $read", "local.get $inbox_len", "i32.ge_u", "if"]);
+                self.extend(&["local.get $written", "return", "end"]);
+                self.extend(&[
+                    "local.get $inbox",
+                    "local.get $read",
+                    "i32.const 8",
+                    "i32.mul",
+                    "i32.add",
+                    "local.tee $address",
+                    "i32.load",
+                    "local.set $kind",
+                    "local.get $address",
+                    "i32.load offset=4",
+                    "local.set $value",
+                    "local.get $read",
+                    "i32.const 1",
+                    "i32.add",
+                    "local.set $read",
+                ]);
+            }
+            Instruction::Out => {
+                self.extend(&["local.get $kind", "i32.eqz"]);
+                self.fail_if(line);
+                self.extend(&[
+                    "local.get $outbox",
+                    "local.get $written",
+                    "i32.const 8",
+                    "i32.mul",
+                    "i32.add",
+                    "local.tee $address",
+                    "local.get $kind",
+                    "i32.store",
+                    "local.get $address",
+                    "local.get $value",
+                    "i32.store offset=4",
+                    "local.get $written",
+                    "i32.const 1",
+                    "i32.add",
+                    "local.set $written",
+                ]);
+            }
+            Instruction::CopyFrom(_) => {
+                self.extend(&["local.get $address", "i32.load", "i32.eqz"]);
+                self.fail_if(line);
+                self.extend(&[
+                    "local.get $address",
+                    "i32.load",
+                    "local.set $kind",
+                    "local.get $address",
+                    "i32.load offset=4",
+                    "local.set $value",
+                ]);
+            }
+            Instruction::CopyTo(_) => {
+                self.extend(&["local.get $kind", "i32.eqz"]);
+                self.fail_if(line);
+                self.extend(&[
+                    "local.get $address",
+                    "local.get $kind",
+                    "i32.store",
+                    "local.get $address",
+                    "local.get $value",
+                    "i32.store offset=4",
+                ]);
+            }
+            Instruction::Add(_) | Instruction::Sub(_) => {
+                let is_sub = matches!(op.instruction, Instruction::Sub(_));
+                if is_sub {
+                    self.extend(&[
+                        "local.get $kind",
+                        "i32.const 2",
+                        "i32.eq",
+                        "local.get $address",
+                        "i32.load",
+                        "i32.const 2",
+                        "i32.eq",
+                        "i32.and",
+                        "if",
+                        "local.get $value",
+                        "call $index",
+                        "local.get $address",
+                        "i32.load offset=4",
+                        "call $index",
+                        "i32.sub",
+                        "local.set $value",
+                        "i32.const 1",
+                        "local.set $kind",
+                        "else",
+                    ]);
+                }
+                self.expect_hands(1, line);
+                self.expect_tile(1, line);
+                self.extend(&[
+                    "local.get $value",
+                    "i64.extend_i32_s",
+                    "local.get $address",
+                    "i32.load offset=4",
+                    "i64.extend_i32_s",
+                    if is_sub { "i64.sub" } else { "i64.add" },
+                    "local.set $wide",
+                ]);
+                self.number_from_wide(line);
+                if is_sub {
+                    self.push("end");
+                }
+            }
+            Instruction::BumpUp(_) | Instruction::BumpDown(_) => {
+                self.expect_tile(1, line);
+                self.extend(&[
+                    "local.get $address",
+                    "i32.load offset=4",
+                    "i64.extend_i32_s",
+                    "i64.const 1",
+                    match op.instruction {
+                        Instruction::BumpUp(_) => "i64.add",
+                        _ => "i64.sub",
+                    },
+                    "local.set $wide",
+                ]);
+                self.number_from_wide(line);
+                self.extend(&[
+                    "local.get $address",
+                    "local.get $value",
+                    "i32.store offset=4",
+                ]);
+            }
+            Instruction::Jump(_) => self.jump(op.jump.unwrap_or(end)),
+            Instruction::JumpIfZero(_) | Instruction::JumpIfNegative(_) => {
+                self.extend(&["local.get $kind", "i32.eqz"]);
+                self.fail_if(line);
+                self.extend(&[
+                    "local.get $kind",
+                    "local.get $value",
+                    match op.instruction {
+                        Instruction::JumpIfZero(_) => "call $is_zero",
+                        _ => "call $is_negative",
+                    },
+                    "if",
+                ]);
+                self.jump(op.jump.unwrap_or(end));
+                self.push("end");
+            }
+            Instruction::Halt => self.extend(&["local.get $written", "return"]),
+            // Only the supported instructions get here
+            _ => {}
+        }
+    }
+}
+
+/// The source of the module (the instructions must be supported)
+pub(super) fn compile(script: &ScriptObject, bytecode: &Bytecode) -> String {
+    let mut code = Code {
+        lines: vec![],
+        depth: 2,
+    };
+    // A block per op, the innermost one jumping to the op of `$next`:
+    // the code of an op is after the end of its block
+    let end = bytecode.ops.len();
+    code.extend(&["loop $next", "block $end"]);
+    for index in (0..end).rev() {
+        code.push(&format!("block $op{}", index));
+    }
+    let labels = (0..end)
+        .map(|index| format!("$op{}", index))
+        .chain(["$end".to_string()])
+        .collect::<Vec<_>>();
+    code.extend(&["local.get $next", &format!("br_table {}", labels.join(" "))]);
+    for op in &bytecode.ops {
+        let line = script.get_line(op.position).unwrap_or_default();
+        code.push("end");
+        code.push(&format!(";; line {}: {}", line, op.instruction));
+        code.op(op, end, line);
+    }
+    code.extend(&["end", "end", "local.get $written)"]);
+    code.depth = 0;
+    code.push(")");
+
+    let mut module = HEADER.to_string();
+    for line in code.lines {
+        module.push_str(&line);
+        module.push('\n');
+    }
+    module
+}
+
+#[cfg(test)]
+mod wat_tests {
+    use crate::compile::{compile, Target};
+
+    use super::*;
+
+    #[test]
+    fn test_compile() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            OUTBOX
+            JUMP     a
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        let module = compile(&script, Target::Wat).unwrap();
+        assert!(module.starts_with(HEADER));
+        // The blocks of the ops are balanced, and the jumps go through the dispatch
+        let count = |word: &str| {
+            module
+                .lines()
+                .filter(|line| line.trim().split(' ').next() == Some(word))
+                .count()
+        };
+        assert_eq!(count("block") + count("loop") + count("if"), count("end"));
+        assert!(module.contains("br_table $op0 $op1 $op2 $end"));
+        assert!(module.contains(
+            "
+        end
+        ;; line 5: JUMP a
+        i32.const 0
+        local.set $next
+        br $next
+      end
+    end
+    local.get $written)
+)
+"
+        ));
+    }
+}
@@ -0,0 +1,180 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use hrm_interpreter::script_object::value_box::ValueBox;
+use serde_json::Value;
+
+use crate::{
+    log,
+    manifest::{parse_value, parse_values},
+};
+
+/// The configuration file read from the working directory, when `--config` isn't given
+pub const DEFAULT_FILE: &str = "hrm.toml";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// The defaults of a workspace, for the options that aren't given on the command line:
+///
+/// ```toml
+/// max_memory = 9
+/// memory = { 9 = 0 }
+/// extensions = true
+/// strict_hrm = false
+/// test = "cases.toml"
+///
+/// [inputs]
+/// sample = [4, 3, 4, 1]
+/// ```
+///
+/// The inputs are presets, chosen with `--preset <name>`.
+pub struct Config {
+    pub max_memory_address: Option<usize>,
+    /// The values on the floor, by address
+    pub memory: HashMap<usize, ValueBox>,
+    pub extensions: bool,
+    pub strict_hrm: bool,
+    /// The test file, relative to the configuration file
+    pub test_file: Option<String>,
+    /// The named inputs
+    pub presets: HashMap<String, Vec<ValueBox>>,
+}
+
+#[derive(Debug, thiserror::Error)]
+/// Error that can occur when reading a configuration file.
+pub enum ReadConfigError {
+    #[error("could not read the configuration file {0}:\n\t{1}")]
+    Io(String, #[source] io::Error),
+    #[error("invalid TOML in {0}:\n\t{1}")]
+    InvalidToml(String, #[source] toml::de::Error),
+    #[error("invalid configuration in {0}: {1}")]
+    InvalidConfig(String, String),
+}
+
+/// Read the configuration file: the given one, or the default one if it exists.
+/// None if there is no configuration.
+pub fn find_config(path: Option<&str>) -> Result<Option<Config>, ReadConfigError> {
+    match path {
+        Some(path) => read_config(path).map(Some),
+        None if Path::new(DEFAULT_FILE).is_file() => read_config(DEFAULT_FILE).map(Some),
+        None => Ok(None),
+    }
+}
+
+pub fn read_config(path: &str) -> Result<Config, ReadConfigError> {
+    log::verbose("config", format!("reading {}", path));
+    let content = fs::read_to_string(path).map_err(|e| ReadConfigError::Io(path.to_string(), e))?;
+    let config = toml::from_str::<toml::Value>(&content)
+        .map_err(|e| ReadConfigError::InvalidToml(path.to_string(), e))?;
+    let config = serde_json::to_value(config)
+        .map_err(|e| ReadConfigError::InvalidConfig(path.to_string(), e.to_string()))?;
+    let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    parse_config(&config, dir)
+        .map_err(|reason| ReadConfigError::InvalidConfig(path.to_string(), reason))
+}
+
+fn parse_config(config: &Value, dir: &Path) -> Result<Config, String> {
+    let flag = |key: &str| match config.get(key) {
+        Some(Value::Bool(flag)) => Ok(*flag),
+        Some(_) => Err(format!("'{}' should be true or false", key)),
+        None => Ok(false),
+    };
+
+    let max_memory_address = match config.get("max_memory") {
+        Some(max) => Some(
+            max.as_u64()
+                .map(|max| max as usize)
+                .ok_or("'max_memory' should be a positive number")?,
+        ),
+        None => None,
+    };
+
+    let mut memory = HashMap::new();
+    match config.get("memory") {
+        Some(Value::Object(tiles)) => {
+            for (address, value) in tiles {
+                let address = address
+                    .parse::<usize>()
+                    .map_err(|_| format!("invalid memory address: {}", address))?;
+                memory.insert(address, parse_value(value, "memory")?);
+            }
+        }
+        Some(_) => return Err("'memory' should be a table of address = value".to_string()),
+        None => {}
+    }
+
+    let test_file = match config.get("test") {
+        Some(Value::String(file)) => Some(dir.join(file).display().to_string()),
+        Some(_) => return Err("'test' should be the path of a test file".to_string()),
+        None => None,
+    };
+
+    let mut presets = HashMap::new();
+    match config.get("inputs") {
+        Some(Value::Object(inputs)) => {
+            for (name, values) in inputs {
+                presets.insert(name.clone(), parse_values(values, "inputs")?);
+            }
+        }
+        Some(_) => return Err("'inputs' should be a table of name = [values]".to_string()),
+        None => {}
+    }
+
+    Ok(Config {
+        max_memory_address,
+        memory,
+        extensions: flag("extensions")?,
+        strict_hrm: flag("strict_hrm")?,
+        test_file,
+        presets,
+    })
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+
+    fn parse(config: &str) -> Result<Config, String> {
+        let config = serde_json::to_value(toml::from_str::<toml::Value>(config).unwrap()).unwrap();
+        parse_config(&config, Path::new("levels"))
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = parse(
+            r#"
+            max_memory = 9
+            memory = { 9 = 0, 8 = "A" }
+            extensions = true
+            test = "cases.toml"
+
+            [inputs]
+            sample = [4, 3, "B"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config,
+            Config {
+                max_memory_address: Some(9),
+                memory: HashMap::from([(9, 0.into()), (8, 'A'.into())]),
+                extensions: true,
+                strict_hrm: false,
+                test_file: Some(Path::new("levels").join("cases.toml").display().to_string()),
+                presets: HashMap::from([(
+                    "sample".to_string(),
+                    vec![4.into(), 3.into(), 'B'.into()]
+                )]),
+            }
+        );
+        assert_eq!(parse("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_parse_invalid_config() {
+        assert!(parse("max_memory = -1").is_err());
+        assert!(parse("extensions = 1").is_err());
+        assert!(parse("memory = { a = 0 }").is_err());
+        assert!(parse("inputs = [1, 2]").is_err());
+        assert!(parse("[inputs]\nsample = [true]").is_err());
+    }
+}
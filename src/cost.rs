@@ -0,0 +1,179 @@
+//! The cost models given with `--costs`: the number of cycles each instruction takes,
+//! for the challenges scored with weighted costs rather than with the step count.
+
+use std::{collections::HashMap, fs, io, path::Path};
+
+use hrm_interpreter::{
+    interpreter::stats::ExecutionStats,
+    script_object::{
+        instruction::Instruction, value_box::ValueBoxMemoryAddress, Position, ScriptObject,
+    },
+};
+use serde_json::Value;
+
+/// The suffix of the keys giving the cost of an instruction addressing a tile indirectly
+const INDIRECT: &str = " [x]";
+
+#[derive(Debug, Clone, PartialEq)]
+/// A cost table, read from a TOML file (or a JSON file with the same structure):
+///
+/// ```toml
+/// default = 1
+/// BUMPUP = 1
+/// "COPYFROM [x]" = 2
+/// JUMP = 0
+/// ```
+///
+/// `"COPYFROM [x]"` is the cost of `COPYFROM` with an indirect address, which is the cost
+/// of `COPYFROM` if it isn't given. The instructions not in the table cost `default` (1 if it isn't given).
+pub struct CostModel {
+    pub default: usize,
+    /// The costs by instruction name, with " [x]" for the indirect addressing
+    pub costs: HashMap<String, usize>,
+}
+
+#[derive(Debug, thiserror::Error)]
+/// Error that can occur when reading a cost file.
+pub enum ReadCostsError {
+    #[error("could not read the cost file:\n\t{0}")]
+    Io(#[from] io::Error),
+    #[error("invalid TOML:\n\t{0}")]
+    InvalidToml(#[from] toml::de::Error),
+    #[error("invalid JSON:\n\t{0}")]
+    InvalidJson(#[from] serde_json::Error),
+    #[error("invalid cost table: {0}")]
+    InvalidCosts(String),
+}
+
+impl CostModel {
+    /// The cycles taken by one execution of the instruction
+    pub fn cost(&self, instruction: &Instruction) -> usize {
+        let name = instruction.name();
+        let indirect = matches!(
+            instruction.memory_address(),
+            Some(ValueBoxMemoryAddress::PointerAddress(_))
+        );
+        indirect
+            .then(|| self.costs.get(&format!("{}{}", name, INDIRECT)))
+            .flatten()
+            .or_else(|| self.costs.get(name))
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// The cycles taken by the execution of the script, from its statistics
+    pub fn cycles(&self, script: &ScriptObject, stats: &ExecutionStats) -> usize {
+        script
+            .blocks()
+            .iter()
+            .flat_map(|block| {
+                block
+                    .instructions
+                    .iter()
+                    .enumerate()
+                    .map(|(instruction, kind)| {
+                        let position = Position {
+                            block: block.index(),
+                            instruction,
+                        };
+                        stats.executions_of(position) * self.cost(kind)
+                    })
+            })
+            .sum()
+    }
+}
+
+pub fn read_costs(path: &str) -> Result<CostModel, ReadCostsError> {
+    let content = fs::read_to_string(path)?;
+    let costs: Value = match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("json") => serde_json::from_str(&content)?,
+        _ => serde_json::to_value(toml::from_str::<toml::Value>(&content)?)?,
+    };
+    parse_costs(&costs).map_err(ReadCostsError::InvalidCosts)
+}
+
+fn parse_costs(costs: &Value) -> Result<CostModel, String> {
+    let Value::Object(entries) = costs else {
+        return Err("expected a table of instruction = cost".to_string());
+    };
+
+    let mut model = CostModel {
+        default: 1,
+        costs: HashMap::new(),
+    };
+    for (key, cost) in entries {
+        let cost = cost
+            .as_u64()
+            .map(|cost| cost as usize)
+            .ok_or_else(|| format!("the cost of {} should be a positive number", key))?;
+        if key == "default" {
+            model.default = cost;
+            continue;
+        }
+
+        let name = key.strip_suffix(INDIRECT).unwrap_or(key);
+        let known = Instruction::NAMES
+            .into_iter()
+            .chain(Instruction::EXTENSION_NAMES)
+            .any(|known| known == name);
+        if !known {
+            return Err(format!("unknown instruction: {}", key));
+        }
+        model.costs.insert(key.clone(), cost);
+    }
+    Ok(model)
+}
+
+#[cfg(test)]
+mod cost_tests {
+    use hrm_interpreter::interpreter::{memory::Memory, Interpreter};
+
+    use super::*;
+
+    fn parse(costs: &str) -> Result<CostModel, String> {
+        parse_costs(&serde_json::to_value(toml::from_str::<toml::Value>(costs).unwrap()).unwrap())
+    }
+
+    #[test]
+    fn test_cycles() {
+        let model = parse(
+            r#"
+            default = 2
+            JUMP = 0
+            COPYFROM = 1
+            "COPYFROM [x]" = 5
+            "#,
+        )
+        .unwrap();
+
+        // Copies the tile pointed by tile 0 to the outbox, twice
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            COPYFROM [0]
+            OUTBOX
+            COPYFROM 1
+            OUTBOX
+            JUMP     a
+        a:
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        let memory = Memory::with_data(HashMap::from([(0, 1.into()), (1, 7.into())]), 1);
+        let mut interpreter = Interpreter::new(memory);
+        interpreter.execute(&script, &[]).unwrap();
+
+        // 5 for the indirect COPYFROM, 1 for the direct one, 2 for each OUTBOX and 0 for the JUMP
+        assert_eq!(model.cycles(&script, interpreter.stats()), 10);
+    }
+
+    #[test]
+    fn test_invalid_costs() {
+        assert_eq!(
+            parse("JUMPX = 1").unwrap_err(),
+            "unknown instruction: JUMPX"
+        );
+        assert_eq!(
+            parse("ADD = -1").unwrap_err(),
+            "the cost of ADD should be a positive number"
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use std::fmt::Display;
+
+use hrm_interpreter::{
+    interpreter::stats::ExecutionStats,
+    script_object::{Position, ScriptObject},
+};
+
+#[derive(Debug, PartialEq)]
+/// A part of the script that was never executed
+pub enum Uncovered {
+    /// A whole block: none of its instructions were executed
+    Block { label: String, line: usize },
+    /// An instruction of a block whose other instructions were executed
+    Instruction { line: usize, text: String },
+}
+
+impl Display for Uncovered {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Uncovered::Block { label, line } => write!(f, "line {}: block {}", line, label),
+            Uncovered::Instruction { line, text } => write!(f, "line {}: {}", line, text),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+/// Which instructions of the script were executed
+pub struct Coverage {
+    pub executed: usize,
+    pub total: usize,
+    /// What was never executed, in the order of the script
+    pub uncovered: Vec<Uncovered>,
+}
+
+impl Coverage {
+    pub fn is_full(&self) -> bool {
+        self.executed == self.total
+    }
+}
+
+/// Compute the coverage of the script from the statistics of its execution
+pub fn coverage(script: &ScriptObject, stats: &ExecutionStats) -> Coverage {
+    let mut coverage = Coverage {
+        executed: 0,
+        total: 0,
+        uncovered: vec![],
+    };
+
+    for block in script.blocks() {
+        let executed = (0..block.instructions.len())
+            .map(|instruction| Position {
+                block: block.index(),
+                instruction,
+            })
+            .map(|position| stats.executions_of(position) > 0)
+            .collect::<Vec<_>>();
+        coverage.total += executed.len();
+        coverage.executed += executed.iter().filter(|executed| **executed).count();
+
+        if !executed.is_empty() && !executed.contains(&true) {
+            coverage.uncovered.push(Uncovered::Block {
+                label: block.name().to_string(),
+                line: block.line(),
+            });
+            continue;
+        }
+        for (instruction, _) in executed.iter().enumerate().filter(|(_, e)| !**e) {
+            let span = block.span_of(instruction).unwrap();
+            coverage.uncovered.push(Uncovered::Instruction {
+                line: span.line,
+                text: span.text.clone(),
+            });
+        }
+    }
+
+    coverage
+}
+
+/// Print the coverage to stderr
+pub fn report(coverage: &Coverage) {
+    let percent = if coverage.total == 0 {
+        100.0
+    } else {
+        100.0 * coverage.executed as f64 / coverage.total as f64
+    };
+    eprintln!(
+        "Coverage: {}/{} instructions executed ({:.1}%)",
+        coverage.executed, coverage.total, percent
+    );
+    if !coverage.uncovered.is_empty() {
+        eprintln!("Never executed:");
+        for uncovered in &coverage.uncovered {
+            eprintln!("  {}", uncovered);
+        }
+    }
+}
+
+#[cfg(test)]
+mod coverage_tests {
+    use hrm_interpreter::{
+        interpreter::{memory::Memory, Interpreter},
+        script_object::value_box::ValueBox,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_coverage() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            JUMPZ    b
+            OUTBOX
+            JUMP     a
+        b:
+            OUTBOX
+            JUMP     a
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        let mut interpreter = Interpreter::new(Memory::default());
+        let (_, stats) = interpreter.execute(&script, &[ValueBox::from(1)]).unwrap();
+
+        assert_eq!(
+            coverage(&script, &stats),
+            Coverage {
+                executed: 4,
+                total: 6,
+                uncovered: vec![Uncovered::Block {
+                    label: "b".to_string(),
+                    line: 7
+                }],
+            }
+        );
+
+        let (_, stats) = Interpreter::new(Memory::default())
+            .execute(&script, &[ValueBox::from(0)])
+            .unwrap();
+        let coverage = coverage(&script, &stats);
+        assert!(!coverage.is_full());
+        assert_eq!(
+            coverage.uncovered,
+            vec![
+                Uncovered::Instruction {
+                    line: 5,
+                    text: "OUTBOX".to_string()
+                },
+                Uncovered::Instruction {
+                    line: 6,
+                    text: "JUMP     a".to_string()
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,671 @@
+//! A server of the Debug Adapter Protocol, so that editors (VS Code...) can debug scripts.
+//! The messages are read on stdin and written on stdout, each one being a JSON object
+//! preceded by a `Content-Length` header.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+use hrm_interpreter::{
+    interpreter::{
+        breakpoint::Breakpoint,
+        memory::{Memory, SetMemoryError},
+        ExecuteScriptError, ExecutionStatus, Interpreter,
+    },
+    levels,
+    script_object::{instruction::ParseOptions, value_box::ValueBox, ScriptObject},
+};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{check, log};
+
+/// The only thread of the execution
+const THREAD_ID: i64 = 1;
+/// The references of the scopes, which are also the references of their variables
+const STATE_SCOPE: i64 = 1;
+const FLOOR_SCOPE: i64 = 2;
+
+/// Read a message. Returns None at the end of the input, and the error of the body if it
+/// isn't JSON: the next message can still be read.
+pub fn read_message(
+    reader: &mut impl BufRead,
+) -> io::Result<Option<Result<Value, serde_json::Error>>> {
+    Ok(read_body(reader)?.map(|body| serde_json::from_slice(&body)))
+}
+
+/// Read the body of a message, after its headers. Returns None at the end of the input.
+fn read_body(reader: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+    })?;
+    let mut content = vec![0; length];
+    reader.read_exact(&mut content)?;
+    Ok(Some(content))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    writer.flush()
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+/// The arguments of the launch request, set in the launch configuration of the editor
+struct LaunchArguments {
+    /// The path of the script
+    program: String,
+    inputs: Option<Vec<ValueBox>>,
+    memory: HashMap<usize, ValueBox>,
+    /// The level providing the floor and the inbox, unless they're given
+    level: Option<usize>,
+    /// The seed of the inbox generated for the level
+    seed: Option<u64>,
+    stop_on_entry: bool,
+}
+
+/// The script being debugged
+struct Execution {
+    path: String,
+    script: ScriptObject,
+    source: String,
+    inputs: Vec<ValueBox>,
+    interpreter: Interpreter,
+    outputs: Vec<ValueBox>,
+    /// The number of outputs already sent to the editor
+    reported_outputs: usize,
+    finished: bool,
+}
+
+impl Execution {
+    fn launch(arguments: LaunchArguments) -> Result<Self, String> {
+        if arguments.program.is_empty() {
+            return Err("No program to debug: set 'program' in the launch configuration".into());
+        }
+        let source = fs::read_to_string(&arguments.program)
+            .map_err(|e| format!("Could not read file {}: {}", arguments.program, e))?;
+        let options = ParseOptions {
+            include_dir: Path::new(&arguments.program)
+                .parent()
+                .map(Path::to_path_buf),
+            ..Default::default()
+        };
+        let script = ScriptObject::parse_with_options(&source, &options).map_err(|e| {
+            e.diagnostics()
+                .iter()
+                .map(|diagnostic| diagnostic.render(&arguments.program, &source))
+                .collect::<String>()
+        })?;
+        script.validate().map_err(|e| e.to_string())?;
+
+        let level = match arguments.level {
+            Some(number) => {
+                Some(levels::get_level(number).ok_or_else(|| format!("Unknown level {}", number))?)
+            }
+            None => None,
+        };
+        let mut memory = arguments.memory;
+        let mut max_address = usize::MAX;
+        if let Some(level) = level {
+            max_address = level.max_address();
+            if memory.is_empty() {
+                memory = level.memory();
+            }
+        }
+        if let Err(SetMemoryError::OutOfBounds {
+            address,
+            max_address,
+        }) = Memory::check_tiles(&memory, max_address)
+        {
+            return Err(format!(
+                "Invalid memory: tile {} is past {}, the last address of the floor of the level",
+                address, max_address
+            ));
+        }
+        let inputs = match (arguments.inputs, level) {
+            (Some(inputs), _) => inputs,
+            (None, Some(level)) => {
+                let mut rng = arguments
+                    .seed
+                    .map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+                level.generate_inbox(&mut rng)
+            }
+            (None, None) => vec![],
+        };
+
+        Ok(Self {
+            path: arguments.program,
+            script,
+            source,
+            inputs,
+            interpreter: Interpreter::new(Memory::with_data(memory, max_address)),
+            outputs: vec![],
+            reported_outputs: 0,
+            finished: false,
+        })
+    }
+
+    fn set_breakpoints(&mut self, lines: &[usize]) {
+        for breakpoint in self.interpreter.breakpoints().to_vec() {
+            self.interpreter.remove_breakpoint(&breakpoint);
+        }
+        for line in lines {
+            self.interpreter.add_breakpoint(Breakpoint::Line(*line));
+        }
+    }
+
+    /// The line of the next instruction, None once the execution is finished
+    fn current_line(&self) -> Option<usize> {
+        self.script
+            .next_instruction_position(self.interpreter.position())
+            .and_then(|position| self.script.get_line(position))
+            .filter(|_| !self.finished)
+    }
+
+    fn variables(&self, reference: i64) -> Vec<Value> {
+        let variable = |name: String, value: String| json!({"name": name, "value": value, "variablesReference": 0});
+        let memory = self.interpreter.memory();
+
+        match reference {
+            STATE_SCOPE => {
+                let head = self.interpreter.head();
+                let inputs_left = self
+                    .inputs
+                    .get(self.interpreter.inputs_read()..)
+                    .unwrap_or_default();
+                vec![
+                    variable(
+                        "head".into(),
+                        head.map_or("(empty)".into(), |value| value.to_string()),
+                    ),
+                    variable("inbox".into(), check::join_values(inputs_left)),
+                    variable("outbox".into(), check::join_values(&self.outputs)),
+                    variable("steps".into(), self.interpreter.steps().to_string()),
+                ]
+            }
+            FLOOR_SCOPE => {
+                let addresses = if memory.get_max_address() == usize::MAX {
+                    // No maximum: only show the tiles holding a value
+                    let mut addresses = memory.addresses().collect::<Vec<usize>>();
+                    addresses.sort_unstable();
+                    addresses
+                } else {
+                    (0..=memory.get_max_address()).collect()
+                };
+                addresses
+                    .into_iter()
+                    .map(|address| {
+                        let value = memory
+                            .get(&address)
+                            .map_or("(empty)".into(), |value| value.to_string());
+                        variable(address.to_string(), value)
+                    })
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+}
+
+/// A debugging session with an editor
+pub struct Session<W: Write> {
+    writer: W,
+    seq: i64,
+    execution: Option<Execution>,
+    /// The lines of the breakpoints, which can be set before the launch
+    breakpoints: Vec<usize>,
+    /// Set once the editor has sent the breakpoints: the execution can start
+    configured: bool,
+    stop_on_entry: bool,
+}
+
+impl<W: Write> Session<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            seq: 0,
+            execution: None,
+            breakpoints: vec![],
+            configured: false,
+            stop_on_entry: false,
+        }
+    }
+
+    fn send(&mut self, mut message: Value) -> io::Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        write_message(&mut self.writer, &message)
+    }
+
+    fn respond(&mut self, request: &Value, result: Result<Value, String>) -> io::Result<()> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = json!(message),
+        }
+        self.send(response)
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> io::Result<()> {
+        self.send(json!({"type": "event", "event": event, "body": body}))
+    }
+
+    /// Handle a request of the editor. Returns false when the session is over.
+    pub fn handle(&mut self, request: &Value) -> io::Result<bool> {
+        let arguments = &request["arguments"];
+        let command = request["command"].as_str().unwrap_or_default();
+        log::trace("dap", command);
+        match command {
+            "initialize" => {
+                self.respond(
+                    request,
+                    Ok(json!({"supportsConfigurationDoneRequest": true})),
+                )?;
+                self.event("initialized", json!({}))?;
+            }
+            "launch" => {
+                let launched = LaunchArguments::deserialize(arguments)
+                    .map_err(|e| format!("Invalid launch configuration: {}", e))
+                    .and_then(Execution::launch);
+                match launched {
+                    Ok(mut execution) => {
+                        execution.set_breakpoints(&self.breakpoints);
+                        self.stop_on_entry = arguments["stopOnEntry"].as_bool() == Some(true);
+                        self.execution = Some(execution);
+                        self.respond(request, Ok(json!({})))?;
+                        if self.configured {
+                            self.start()?;
+                        }
+                    }
+                    Err(message) => self.respond(request, Err(message))?,
+                }
+            }
+            "setBreakpoints" => {
+                self.breakpoints = arguments["breakpoints"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|breakpoint| breakpoint["line"].as_u64())
+                    .map(|line| line as usize)
+                    .collect();
+                let breakpoints = self
+                    .breakpoints
+                    .iter()
+                    .map(|line| {
+                        let verified = self.execution.as_ref().is_none_or(|execution| {
+                            Breakpoint::Line(*line).resolve(&execution.script).is_some()
+                        });
+                        json!({"verified": verified, "line": line})
+                    })
+                    .collect::<Vec<_>>();
+                if let Some(execution) = &mut self.execution {
+                    execution.set_breakpoints(&self.breakpoints);
+                }
+                self.respond(request, Ok(json!({"breakpoints": breakpoints})))?;
+            }
+            "configurationDone" => {
+                self.configured = true;
+                self.respond(request, Ok(json!({})))?;
+                if self.execution.is_some() {
+                    self.start()?;
+                }
+            }
+            "threads" => self.respond(
+                request,
+                Ok(json!({"threads": [{"id": THREAD_ID, "name": "worker"}]})),
+            )?,
+            "stackTrace" => {
+                let frames = self.execution.as_ref().map(|execution| {
+                    let block = execution
+                        .script
+                        .get_block_by_index(execution.interpreter.position().block)
+                        .map(|block| block.name().to_string())
+                        .unwrap_or_default();
+                    execution
+                        .current_line()
+                        .map(|line| {
+                            json!({
+                                "id": 0,
+                                "name": block,
+                                "line": line,
+                                "column": 1,
+                                "source": {"path": execution.path},
+                            })
+                        })
+                        .into_iter()
+                        .collect::<Vec<_>>()
+                });
+                let frames = frames.unwrap_or_default();
+                self.respond(
+                    request,
+                    Ok(json!({"stackFrames": frames, "totalFrames": frames.len()})),
+                )?;
+            }
+            "scopes" => self.respond(
+                request,
+                Ok(json!({"scopes": [
+                    {"name": "State", "variablesReference": STATE_SCOPE, "expensive": false},
+                    {"name": "Floor", "variablesReference": FLOOR_SCOPE, "expensive": false},
+                ]})),
+            )?,
+            "variables" => {
+                let reference = arguments["variablesReference"].as_i64().unwrap_or_default();
+                let variables = self
+                    .execution
+                    .as_ref()
+                    .map(|execution| execution.variables(reference))
+                    .unwrap_or_default();
+                self.respond(request, Ok(json!({"variables": variables})))?;
+            }
+            "continue" => {
+                self.respond(request, Ok(json!({"allThreadsContinued": true})))?;
+                self.resume(false)?;
+            }
+            "next" | "stepIn" | "stepOut" => {
+                self.respond(request, Ok(json!({})))?;
+                self.resume(true)?;
+            }
+            "disconnect" | "terminate" => {
+                self.respond(request, Ok(json!({})))?;
+                return Ok(false);
+            }
+            command => self.respond(request, Err(format!("Unsupported request: {}", command)))?,
+        }
+        Ok(true)
+    }
+
+    /// Start the execution, once it's launched and configured
+    fn start(&mut self) -> io::Result<()> {
+        if self.stop_on_entry {
+            self.event("stopped", json!({"reason": "entry", "threadId": THREAD_ID}))
+        } else {
+            self.resume(false)
+        }
+    }
+
+    /// Execute the next instruction, or until the next breakpoint, and tell the editor where the execution stands
+    fn resume(&mut self, single_step: bool) -> io::Result<()> {
+        let Some(execution) = &mut self.execution else {
+            return Ok(());
+        };
+        if execution.finished {
+            return Ok(());
+        }
+
+        let inputs = execution.inputs.as_slice();
+        let status = if single_step {
+            execution
+                .interpreter
+                .step(&execution.script, inputs, &mut execution.outputs)
+        } else {
+            execution
+                .interpreter
+                .run(&execution.script, inputs, &mut execution.outputs)
+        };
+        let new_outputs = execution.outputs[execution.reported_outputs..].to_vec();
+        execution.reported_outputs = execution.outputs.len();
+        let error = status.as_ref().err().map(|e| describe_error(e, execution));
+        execution.finished = !matches!(status, Ok(ExecutionStatus::Paused(_)));
+
+        for value in new_outputs {
+            self.event(
+                "output",
+                json!({"category": "stdout", "output": format!("{}\n", value)}),
+            )?;
+        }
+        match (status, error) {
+            (Ok(ExecutionStatus::Paused(_)), _) => {
+                let reason = if single_step { "step" } else { "breakpoint" };
+                self.event("stopped", json!({"reason": reason, "threadId": THREAD_ID}))
+            }
+            (Ok(ExecutionStatus::Finished), _) => {
+                self.event("exited", json!({"exitCode": 0}))?;
+                self.event("terminated", json!({}))
+            }
+            (Err(_), error) => {
+                self.event(
+                    "output",
+                    json!({"category": "stderr", "output": error.unwrap_or_default()}),
+                )?;
+                self.event("exited", json!({"exitCode": 1}))?;
+                self.event("terminated", json!({}))
+            }
+        }
+    }
+}
+
+fn describe_error(error: &ExecuteScriptError, execution: &Execution) -> String {
+    match error.diagnostic() {
+        Some(diagnostic) => diagnostic.render(&execution.path, &execution.source),
+        None => format!("{}\n", error),
+    }
+}
+
+/// Serve the editor until it disconnects or closes the input
+pub fn serve(mut reader: impl BufRead, writer: impl Write) -> io::Result<()> {
+    let mut session = Session::new(writer);
+    while let Some(body) = read_body(&mut reader)? {
+        match serde_json::from_slice(&body) {
+            Ok(request) => {
+                if !session.handle(&request)? {
+                    break;
+                }
+            }
+            Err(e) => session.respond(
+                &salvage_request(&body),
+                Err(format!("Invalid request: {}", e)),
+            )?,
+        }
+    }
+    Ok(())
+}
+
+/// The `seq` and `command` of a request which isn't JSON, where they can still be read,
+/// so that the response refers to the request
+fn salvage_request(body: &[u8]) -> Value {
+    let body = String::from_utf8_lossy(body);
+    let mut request = json!({});
+    for key in ["seq", "command"] {
+        let value = body
+            .split_once(&format!("\"{}\"", key))
+            .and_then(|(_, rest)| rest.trim_start().strip_prefix(':'))
+            .and_then(|rest| {
+                serde_json::Deserializer::from_str(rest)
+                    .into_iter::<Value>()
+                    .next()?
+                    .ok()
+            });
+        if let Some(value) = value {
+            request[key] = value;
+        }
+    }
+    request
+}
+
+#[cfg(test)]
+mod dap_tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn requests(requests: &[Value]) -> Vec<u8> {
+        let mut input = vec![];
+        for (seq, request) in requests.iter().enumerate() {
+            let mut request = request.clone();
+            request["seq"] = json!(seq + 1);
+            request["type"] = json!("request");
+            write_message(&mut input, &request).unwrap();
+        }
+        input
+    }
+
+    fn messages(output: Vec<u8>) -> Vec<Value> {
+        let mut reader = Cursor::new(output);
+        let mut messages = vec![];
+        while let Some(Ok(message)) = read_message(&mut reader).unwrap() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    fn find<'a>(messages: &'a [Value], kind: &str, name: &str) -> Vec<&'a Value> {
+        let key = if kind == "event" { "event" } else { "command" };
+        messages
+            .iter()
+            .filter(|message| message["type"] == kind && message[key] == name)
+            .collect()
+    }
+
+    #[test]
+    fn test_debugging_session() {
+        let input = requests(&[
+            json!({"command": "initialize", "arguments": {}}),
+            json!({"command": "launch", "arguments": {
+                "program": "./samples/01-MailRoom.hrm",
+                "inputs": [4, "A", 2],
+            }}),
+            json!({"command": "setBreakpoints", "arguments": {
+                "source": {"path": "./samples/01-MailRoom.hrm"},
+                "breakpoints": [{"line": 5}, {"line": 20}],
+            }}),
+            json!({"command": "configurationDone"}),
+            json!({"command": "stackTrace", "arguments": {"threadId": 1}}),
+            json!({"command": "variables", "arguments": {"variablesReference": STATE_SCOPE}}),
+            json!({"command": "next", "arguments": {"threadId": 1}}),
+            json!({"command": "continue", "arguments": {"threadId": 1}}),
+            json!({"command": "disconnect"}),
+        ]);
+        let mut output = vec![];
+        serve(Cursor::new(input), &mut output).unwrap();
+        let messages = messages(output);
+
+        assert!(messages
+            .iter()
+            .filter(|message| message["type"] == "response")
+            .all(|response| response["success"] == true));
+        assert_eq!(find(&messages, "event", "initialized").len(), 1);
+
+        let breakpoints = &find(&messages, "response", "setBreakpoints")[0]["body"]["breakpoints"];
+        assert_eq!(
+            breakpoints,
+            &json!([{"verified": true, "line": 5}, {"verified": false, "line": 20}])
+        );
+
+        let stops = find(&messages, "event", "stopped")
+            .iter()
+            .map(|stop| stop["body"]["reason"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(stops, vec!["breakpoint", "step"]);
+        let frame = &find(&messages, "response", "stackTrace")[0]["body"]["stackFrames"][0];
+        assert_eq!(frame["line"], 5);
+
+        let variables = &find(&messages, "response", "variables")[0]["body"]["variables"];
+        assert_eq!(
+            variables[0],
+            json!({"name": "head", "value": "4", "variablesReference": 0})
+        );
+        assert_eq!(variables[1]["value"], "A 2");
+        assert_eq!(variables[2]["value"], "4");
+
+        let outputs = find(&messages, "event", "output")
+            .iter()
+            .map(|output| output["body"]["output"].as_str().unwrap())
+            .collect::<String>();
+        assert_eq!(outputs, "4\nA\n2\n");
+        assert_eq!(find(&messages, "event", "terminated").len(), 1);
+    }
+
+    #[test]
+    fn test_launch_errors() {
+        let input = requests(&[
+            json!({"command": "launch", "arguments": {"program": "./missing.hrm"}}),
+            json!({"command": "launch", "arguments": {"inputs": "not a list"}}),
+            json!({"command": "launch", "arguments": {
+                "program": "./samples/01-MailRoom.hrm",
+                "level": 30,
+                "memory": {"30": 1},
+            }}),
+        ]);
+        let mut output = vec![];
+        serve(Cursor::new(input), &mut output).unwrap();
+        let messages = messages(output);
+
+        assert_eq!(messages.len(), 3);
+        assert!(messages.iter().all(|message| message["success"] == false));
+        assert!(messages[0]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Could not read file ./missing.hrm"));
+        assert!(messages[1]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid launch configuration"));
+        assert!(messages[2]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid memory: tile 30"));
+        assert_eq!(messages[2]["request_seq"], 3);
+    }
+
+    #[test]
+    fn test_invalid_request() {
+        let body = r#"{"seq": 1, "command": "threads", "arguments": {"#;
+        let mut input = format!("Content-Length: {}\r\n\r\n{}", body.len(), body).into_bytes();
+        input.extend(b"Content-Length: 9\r\n\r\n{\"seq\": 2");
+        input.extend(requests(&[json!({"command": "threads"})]));
+        let mut output = vec![];
+        serve(Cursor::new(input), &mut output).unwrap();
+        let messages = messages(output);
+
+        // The session answers the invalid requests, and goes on with the next one
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0]["success"], false);
+        assert!(messages[0]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid request"));
+        // The response refers to the request as far as it can be read
+        assert_eq!(
+            (&messages[0]["request_seq"], &messages[0]["command"]),
+            (&json!(1), &json!("threads"))
+        );
+        assert_eq!(
+            (&messages[1]["request_seq"], &messages[1]["command"]),
+            (&json!(2), &Value::Null)
+        );
+        assert_eq!(
+            (&messages[2]["command"], &messages[2]["success"]),
+            (&json!("threads"), &json!(true))
+        );
+    }
+}
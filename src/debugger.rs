@@ -0,0 +1,201 @@
+use std::io::{self, BufRead, Write};
+
+use hrm_interpreter::{
+    interpreter::{breakpoint::Breakpoint, ExecuteScriptError, ExecutionStatus, Interpreter},
+    script_object::{value_box::ValueBox, Position, ScriptObject},
+};
+
+/// Number of executed instructions that can be undone with the `back` command
+pub const HISTORY_SIZE: usize = 10_000;
+
+/// How the debugging session ended
+pub enum DebuggerExit {
+    /// The script ran to its end, with these outputs
+    Finished(Vec<ValueBox>),
+    /// The user quit before the end
+    Quit,
+}
+
+/// What the user asked for at the debugger prompt
+enum DebuggerCommand {
+    Continue,
+    Step,
+    Back(usize),
+    Print,
+    AddBreakpoint(Breakpoint),
+    RemoveBreakpoint(Breakpoint),
+    ListBreakpoints,
+    Watch(usize),
+    Unwatch(usize),
+    Quit,
+    Help,
+}
+
+impl DebuggerCommand {
+    fn parse(line: &str) -> Result<Self, String> {
+        let mut parts = line.split_whitespace();
+        let command = parts.next().unwrap_or("continue");
+        let argument = parts.next();
+        let breakpoint = argument.map(|arg| arg.parse::<Breakpoint>().unwrap());
+
+        match (command, breakpoint) {
+            ("c" | "continue", None) => Ok(Self::Continue),
+            ("s" | "step", None) => Ok(Self::Step),
+            ("r" | "back", _) => match argument.map(|arg| arg.parse::<usize>()) {
+                None => Ok(Self::Back(1)),
+                Some(Ok(n_steps)) => Ok(Self::Back(n_steps)),
+                Some(Err(_)) => Err(format!(
+                    "Invalid number of steps: {}",
+                    argument.unwrap_or_default()
+                )),
+            },
+            ("p" | "print", None) => Ok(Self::Print),
+            ("b" | "break", Some(breakpoint)) => Ok(Self::AddBreakpoint(breakpoint)),
+            ("d" | "delete", Some(breakpoint)) => Ok(Self::RemoveBreakpoint(breakpoint)),
+            ("l" | "list", None) => Ok(Self::ListBreakpoints),
+            ("w" | "watch", Some(_)) => parse_address(argument).map(Self::Watch),
+            ("u" | "unwatch", Some(_)) => parse_address(argument).map(Self::Unwatch),
+            ("q" | "quit", None) => Ok(Self::Quit),
+            ("h" | "help", None) => Ok(Self::Help),
+            _ => Err(format!(
+                "Invalid command: {}. Type 'help' for help",
+                line.trim()
+            )),
+        }
+    }
+}
+
+fn parse_address(argument: Option<&str>) -> Result<usize, String> {
+    let argument = argument.unwrap_or_default();
+    argument
+        .parse::<usize>()
+        .map_err(|_| format!("Invalid memory address: {}", argument))
+}
+
+fn print_help() {
+    eprintln!("Debugger commands:");
+    eprintln!("  c, continue                 resume the execution until the next breakpoint");
+    eprintln!("  s, step                     execute the next instruction only");
+    eprintln!("  r, back [n]                 undo the last n executed instructions (1 by default)");
+    eprintln!("  p, print                    print the state of the interpreter");
+    eprintln!("  b, break <label | line>     add a breakpoint");
+    eprintln!("  d, delete <label | line>    remove a breakpoint");
+    eprintln!("  l, list                     list the breakpoints and watchpoints");
+    eprintln!("  w, watch <address>          pause when the tile is read or written");
+    eprintln!("  u, unwatch <address>        remove a watchpoint");
+    eprintln!("  q, quit                     stop the execution");
+    eprintln!("  h, help                     print this help");
+}
+
+fn print_position(script: &ScriptObject, position: Position) {
+    let block_name = script
+        .get_block_by_index(position.block)
+        .map(|block| block.name())
+        .unwrap_or_default();
+    eprintln!(
+        "Paused at line {} (block {}): {}",
+        script.get_line(position).unwrap_or_default(),
+        block_name,
+        script.get_instruction(position).unwrap()
+    );
+}
+
+/// Run the script, giving control to the user on the standard input
+/// each time the execution is paused on a breakpoint or a watchpoint.
+/// Reaching the end of the standard input resumes the execution.
+/// `outputs` are the outputs already produced, when resuming from a checkpoint.
+pub fn run_with_debugger(
+    interpreter: &mut Interpreter,
+    script: &ScriptObject,
+    inputs: &[ValueBox],
+    mut outputs: Vec<ValueBox>,
+) -> Result<DebuggerExit, ExecuteScriptError> {
+    let stdin = io::stdin();
+
+    let mut status = interpreter.run(script, inputs, &mut outputs)?;
+    while let ExecutionStatus::Paused(position) = status {
+        for hit in interpreter.watchpoint_hits() {
+            eprintln!("{}", hit);
+        }
+        print_position(script, position);
+
+        loop {
+            eprint!("(hrm) ");
+            io::stderr().flush().unwrap();
+
+            let mut line = String::new();
+            let command = match stdin.lock().read_line(&mut line) {
+                Ok(0) | Err(_) => DebuggerCommand::Continue,
+                Ok(_) => match DebuggerCommand::parse(&line) {
+                    Ok(command) => command,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        continue;
+                    }
+                },
+            };
+
+            match command {
+                DebuggerCommand::Continue => {
+                    status = interpreter.run(script, inputs, &mut outputs)?;
+                    break;
+                }
+                DebuggerCommand::Step => {
+                    status = interpreter.step(script, inputs, &mut outputs)?;
+                    break;
+                }
+                DebuggerCommand::Back(n_steps) => {
+                    let rewound = interpreter.rewind(n_steps, &mut outputs);
+                    if rewound < n_steps {
+                        eprintln!("Only {} instruction(s) could be undone", rewound);
+                    }
+                    if let Some(position) = script.next_instruction_position(interpreter.position())
+                    {
+                        print_position(script, position);
+                    }
+                }
+                DebuggerCommand::Print => {
+                    match interpreter.head() {
+                        Some(head) => eprintln!("Head: {}", head),
+                        None => eprintln!("Head: empty"),
+                    }
+                    eprintln!("{}", interpreter.state(inputs, &outputs));
+                }
+                DebuggerCommand::AddBreakpoint(breakpoint) => {
+                    if breakpoint.resolve(script).is_none() {
+                        eprintln!("Warning: {} doesn't match any instruction", breakpoint);
+                    }
+                    interpreter.add_breakpoint(breakpoint);
+                }
+                DebuggerCommand::RemoveBreakpoint(breakpoint) => {
+                    if !interpreter.remove_breakpoint(&breakpoint) {
+                        eprintln!("No breakpoint on {}", breakpoint);
+                    }
+                }
+                DebuggerCommand::ListBreakpoints => {
+                    for breakpoint in interpreter.breakpoints() {
+                        eprintln!("  {}", breakpoint);
+                    }
+                    for address in interpreter.watchpoints() {
+                        eprintln!("  tile {}", address);
+                    }
+                }
+                DebuggerCommand::Watch(address) => {
+                    if !interpreter.memory().is_valid_memory_address(&address) {
+                        eprintln!("Warning: tile {} is out of the memory", address);
+                    }
+                    interpreter.add_watchpoint(address);
+                }
+                DebuggerCommand::Unwatch(address) => {
+                    if !interpreter.remove_watchpoint(address) {
+                        eprintln!("No watchpoint on tile {}", address);
+                    }
+                }
+                DebuggerCommand::Quit => return Ok(DebuggerExit::Quit),
+                DebuggerCommand::Help => print_help(),
+            }
+        }
+    }
+
+    Ok(DebuggerExit::Finished(outputs))
+}
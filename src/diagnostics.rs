@@ -0,0 +1,134 @@
+//! Errors rendered with an excerpt of the script, pointing at the offending part of a line:
+//!
+//! ```text
+//! error: COPYTOO is not a valid instruction
+//!  --> solution.hrm:3:5
+//!   |
+//! 3 |     COPYTOO  2
+//!   |     ^^^^^^^
+//! ```
+
+use std::{fmt::Display, ops::Range};
+
+#[derive(Debug, Clone, PartialEq)]
+/// An error located in the source of a script.
+pub struct Diagnostic {
+    /// What went wrong, on one line
+    pub message: String,
+    /// Details shown under the excerpt
+    pub notes: Vec<String>,
+    /// The line of the error (starting at 1)
+    pub line: usize,
+    /// The byte range of the offending part of the line.
+    /// None points at the whole line (without its indentation).
+    pub range: Option<Range<usize>>,
+}
+
+impl Diagnostic {
+    /// A diagnostic for an error message, whose first line is the message
+    /// and whose following lines become notes.
+    pub fn new(message: impl Display, line: usize, range: Option<Range<usize>>) -> Self {
+        let message = message.to_string();
+        let mut lines = message.lines().map(str::trim).filter(|l| !l.is_empty());
+        Self {
+            message: lines
+                .next()
+                .unwrap_or_default()
+                .trim_end_matches(':')
+                .to_string(),
+            notes: lines.map(str::to_string).collect(),
+            line,
+            range,
+        }
+    }
+
+    /// The byte range pointed at in the given line of the source
+    fn range_in(&self, text: &str) -> Range<usize> {
+        match &self.range {
+            Some(range) => range.start.min(text.len())..range.end.min(text.len()),
+            None => text.len() - text.trim_start().len()..text.trim_end().len(),
+        }
+    }
+
+    fn line_of<'a>(&self, source: &'a str) -> &'a str {
+        source
+            .lines()
+            .nth(self.line.saturating_sub(1))
+            .unwrap_or("")
+    }
+
+    /// The column (starting at 1, in characters) of the start of the offending part of the line
+    pub fn column(&self, source: &str) -> usize {
+        let text = self.line_of(source);
+        text[..self.range_in(text).start].chars().count() + 1
+    }
+
+    /// Render the diagnostic with the line of the source it points at.
+    /// `file_name` is only displayed.
+    pub fn render(&self, file_name: &str, source: &str) -> String {
+        let text = self.line_of(source);
+        let range = self.range_in(text);
+        // Keep the tabs under the line, so that the carets are aligned with it
+        let padding = text[..range.start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        let carets = "^".repeat(text[range.clone()].chars().count().max(1));
+        let column = self.column(source);
+
+        let gutter = " ".repeat(self.line.to_string().len());
+        let mut rendered = format!("error: {}\n", self.message);
+        rendered += &format!("{}--> {}:{}:{}\n", gutter, file_name, self.line, column);
+        rendered += &format!("{} |\n", gutter);
+        rendered += &format!("{} | {}\n", self.line, text);
+        rendered += &format!("{} | {}{}\n", gutter, padding, carets);
+        for note in &self.notes {
+            rendered += &format!("{} = note: {}\n", gutter, note);
+        }
+        rendered
+    }
+}
+
+#[cfg(test)]
+mod diagnostics_tests {
+    use super::*;
+
+    const SOURCE: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+    INBOX
+    COPYTOO  2
+";
+
+    #[test]
+    fn test_render_with_range() {
+        let diagnostic = Diagnostic::new("COPYTOO is not a valid instruction", 3, Some(4..11));
+        assert_eq!(
+            diagnostic.render("solution.hrm", SOURCE),
+            "error: COPYTOO is not a valid instruction
+ --> solution.hrm:3:5
+  |
+3 |     COPYTOO  2
+  |     ^^^^^^^
+"
+        );
+    }
+
+    #[test]
+    fn test_render_whole_line_with_notes() {
+        let diagnostic = Diagnostic::new(
+            "cannot read memory value:\n\tno value at address 2",
+            3,
+            None,
+        );
+        assert_eq!(diagnostic.notes, vec!["no value at address 2".to_string()]);
+        assert_eq!(
+            diagnostic.render("solution.hrm", SOURCE),
+            "error: cannot read memory value
+ --> solution.hrm:3:5
+  |
+3 |     COPYTOO  2
+  |     ^^^^^^^^^^
+  = note: no value at address 2
+"
+        );
+    }
+}
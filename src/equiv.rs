@@ -0,0 +1,192 @@
+//! Equivalence checking: two scripts are run on every inbox of at most a given length, made of
+//! the values of a range, the shortest inboxes first. The first inbox on which they diverge is
+//! a minimal counterexample.
+
+use std::collections::HashMap;
+
+use hrm_interpreter::{
+    interpreter::settings::Settings,
+    script_object::{value_box::ValueBox, ScriptObject},
+};
+
+use crate::{
+    compare::{self, Comparison},
+    jobs,
+};
+
+/// The length of the longest inboxes, unless `--max-len` is given
+pub const DEFAULT_MAX_LEN: usize = 3;
+/// The values of the inboxes, unless `--range` is given
+pub const DEFAULT_RANGE: &str = "-9..9";
+/// A script taking more steps on an inbox is stuck in a loop, unless a step limit is given
+const MAX_STEPS: usize = 10_000;
+
+/// The inboxes on which the scripts are compared
+pub struct Bound {
+    /// The values of the inboxes
+    pub values: Vec<ValueBox>,
+    /// The length of the longest inboxes
+    pub max_len: usize,
+}
+
+pub enum Equivalence {
+    /// Both scripts behave the same on all the inboxes
+    Equivalent {
+        inboxes: usize,
+    },
+    Counterexample(Comparison),
+}
+
+/// The inboxes of `len` values, in the order of the values
+fn inboxes(values: &[ValueBox], len: usize) -> Vec<Vec<ValueBox>> {
+    (0..len).fold(vec![vec![]], |inboxes, _| {
+        inboxes
+            .iter()
+            .flat_map(|inbox| {
+                values.iter().map(|value| {
+                    let mut inbox = inbox.clone();
+                    inbox.push(*value);
+                    inbox
+                })
+            })
+            .collect()
+    })
+}
+
+/// Compare the scripts on all the inboxes within the bound, on `jobs` threads
+pub fn check(
+    first: &ScriptObject,
+    second: &ScriptObject,
+    memory: &HashMap<usize, ValueBox>,
+    max_memory_address: usize,
+    bound: &Bound,
+    settings: &Settings,
+    jobs: usize,
+) -> Equivalence {
+    let settings = Settings {
+        max_steps: settings.max_steps.or(Some(MAX_STEPS)),
+        ..settings.clone()
+    };
+    // The numbers closest to zero first, for the smallest counterexample
+    let mut values = bound.values.clone();
+    values.sort_by_key(|value| match value {
+        ValueBox::Number(n) => (n.unsigned_abs(), *n < 0),
+        ValueBox::Character(_) => (u32::MAX, false),
+    });
+
+    let mut count = 0;
+    for len in 0..=bound.max_len {
+        let inboxes = inboxes(&values, len);
+        let comparisons = jobs::parallel_map(&inboxes, jobs, |inputs| {
+            compare::compare(first, second, memory, max_memory_address, inputs, &settings)
+        });
+        if let Some(comparison) = comparisons
+            .into_iter()
+            .find(|comparison| comparison.divergence().is_some())
+        {
+            return Equivalence::Counterexample(comparison);
+        }
+        count += inboxes.len();
+    }
+    Equivalence::Equivalent { inboxes: count }
+}
+
+/// Print the counterexample, or the number of inboxes compared, to stderr.
+/// Returns true if the scripts are equivalent.
+pub fn report(equivalence: &Equivalence, bound: &Bound) -> bool {
+    match equivalence {
+        Equivalence::Equivalent { inboxes } => {
+            eprintln!(
+                "Same outputs on all the {} inboxes of at most {} values",
+                inboxes, bound.max_len
+            );
+            true
+        }
+        Equivalence::Counterexample(comparison) => {
+            compare::report(std::slice::from_ref(comparison))
+        }
+    }
+}
+
+#[cfg(test)]
+mod equiv_tests {
+    use super::*;
+
+    const MAIL_ROOM: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+    a:
+        INBOX
+        OUTBOX
+        JUMP a
+    ";
+
+    const MAIL_ROOM_UNROLLED: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+    a:
+        INBOX
+        OUTBOX
+        INBOX
+        OUTBOX
+        JUMP a
+    ";
+
+    // Wrong for the negative numbers after a zero
+    const SKIP_NEGATIVES_AFTER_ZERO: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+    a:
+        INBOX
+        OUTBOX
+        JUMPZ b
+        JUMP a
+    b:
+        INBOX
+        JUMPN b
+        OUTBOX
+        JUMP a
+    ";
+
+    fn check_scripts(first: &str, second: &str, max_len: usize) -> Equivalence {
+        check(
+            &first.parse::<ScriptObject>().unwrap(),
+            &second.parse::<ScriptObject>().unwrap(),
+            &HashMap::new(),
+            usize::MAX,
+            &Bound {
+                values: vec![(-1).into(), 0.into(), 1.into()],
+                max_len,
+            },
+            &Settings::default(),
+            2,
+        )
+    }
+
+    #[test]
+    fn test_inboxes() {
+        let values = [1.into(), 'A'.into()];
+        assert_eq!(inboxes(&values, 0), [vec![]]);
+        assert_eq!(
+            inboxes(&values, 2),
+            [
+                vec![1.into(), 1.into()],
+                vec![1.into(), 'A'.into()],
+                vec!['A'.into(), 1.into()],
+                vec!['A'.into(), 'A'.into()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_equivalent_scripts() {
+        assert!(matches!(
+            check_scripts(MAIL_ROOM, MAIL_ROOM_UNROLLED, 3),
+            Equivalence::Equivalent { inboxes: 40 }
+        ));
+    }
+
+    #[test]
+    fn test_minimal_counterexample() {
+        match check_scripts(MAIL_ROOM, SKIP_NEGATIVES_AFTER_ZERO, 4) {
+            Equivalence::Counterexample(comparison) => {
+                assert_eq!(comparison.inputs, [0.into(), (-1).into()]);
+            }
+            Equivalence::Equivalent { .. } => panic!("expected a counterexample"),
+        }
+    }
+}
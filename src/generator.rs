@@ -0,0 +1,371 @@
+//! Random generation of inputs, to test a script on many inboxes.
+//!
+//! An inbox is described by a list of specifications separated by commas,
+//! each one being `<count> <kind> [<min>..<max>] [non-zero]`:
+//! - `10 numbers -10..10`: 10 numbers between -10 and 10 (inclusive)
+//! - `5 letters A..E`: 5 letters between A and E
+//! - `8 values non-zero`: 8 numbers or letters, without zeros
+//! - `3 numbers 1..9, 2 letters`: 3 numbers, then 2 letters
+//!
+//! An inbox on which a script fails can then be shrunk with [`shrink`].
+
+use std::{iter::successors, ops::RangeInclusive, str::FromStr};
+
+use rand::Rng;
+
+use crate::script_object::value_box::ValueBox;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// What kind of values are generated
+pub enum ValueKind {
+    Numbers,
+    Letters,
+    /// Numbers or letters, randomly
+    Values,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Specification of a part of the inbox
+pub struct InputSpec {
+    pub count: usize,
+    pub kind: ValueKind,
+    /// Range of the numbers (default: -99..99)
+    pub numbers: RangeInclusive<i32>,
+    /// Range of the letters (default: A..Z)
+    pub letters: RangeInclusive<char>,
+    /// Don't generate zeros (zeros often terminate strings in the game)
+    pub non_zero: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+/// Specification of a whole inbox: its parts are generated one after the other
+pub struct InboxSpec {
+    pub parts: Vec<InputSpec>,
+}
+
+#[derive(Debug, thiserror::Error)]
+/// Error that can occur when parsing an input specification.
+pub enum ParseInputSpecError {
+    #[error("invalid count: {0} (expected a positive number)")]
+    InvalidCount(String),
+    #[error("invalid kind: {0} (expected numbers, letters or values)")]
+    InvalidKind(String),
+    #[error("invalid range: {0} (expected <min>..<max>, like -10..10 or A..E)")]
+    InvalidRange(String),
+    #[error("invalid constraint: {0}")]
+    InvalidConstraint(String),
+    #[error("the constraints of '{0}' leave no value to generate")]
+    NoPossibleValue(String),
+}
+
+impl InputSpec {
+    /// Generate the values of this part of the inbox
+    pub fn generate(&self, rng: &mut impl Rng) -> Vec<ValueBox> {
+        (0..self.count)
+            .map(|_| match self.kind {
+                ValueKind::Numbers => self.generate_number(rng),
+                ValueKind::Letters => self.generate_letter(rng),
+                ValueKind::Values if rng.gen_bool(0.5) => self.generate_number(rng),
+                ValueKind::Values => self.generate_letter(rng),
+            })
+            .collect()
+    }
+
+    fn generate_number(&self, rng: &mut impl Rng) -> ValueBox {
+        loop {
+            let n = rng.gen_range(self.numbers.clone());
+            if !(self.non_zero && n == 0) {
+                return ValueBox::Number(n);
+            }
+        }
+    }
+
+    fn generate_letter(&self, rng: &mut impl Rng) -> ValueBox {
+        ValueBox::Character(rng.gen_range(self.letters.clone()))
+    }
+
+    /// Whether this part of the inbox can contain the value
+    pub fn allows(&self, value: &ValueBox) -> bool {
+        match value {
+            ValueBox::Number(n) => {
+                self.kind != ValueKind::Letters
+                    && self.numbers.contains(n)
+                    && !(self.non_zero && *n == 0)
+            }
+            ValueBox::Character(c) => self.kind != ValueKind::Numbers && self.letters.contains(c),
+        }
+    }
+}
+
+impl InboxSpec {
+    /// Generate a whole inbox
+    pub fn generate(&self, rng: &mut impl Rng) -> Vec<ValueBox> {
+        self.parts
+            .iter()
+            .flat_map(|part| part.generate(rng))
+            .collect()
+    }
+
+    /// Whether some part of the inbox can contain the value
+    pub fn allows(&self, value: &ValueBox) -> bool {
+        self.parts.iter().any(|part| part.allows(value))
+    }
+}
+
+/// Shrink an inbox on which something fails to the smallest one on which it still fails:
+/// values are removed, and the remaining ones brought closer to 0 (or to A for letters).
+pub fn shrink(inbox: &[ValueBox], mut fails: impl FnMut(&[ValueBox]) -> bool) -> Vec<ValueBox> {
+    let mut inbox = inbox.to_vec();
+    loop {
+        let smaller = removals(&inbox)
+            .chain(simplifications(&inbox))
+            .find(|candidate| fails(candidate));
+        match smaller {
+            Some(smaller) => inbox = smaller,
+            None => return inbox,
+        }
+    }
+}
+
+/// The inbox without one of its chunks of values, the largest chunks first
+fn removals(inbox: &[ValueBox]) -> impl Iterator<Item = Vec<ValueBox>> + '_ {
+    successors(Some(inbox.len()), |size| Some(size / 2))
+        .take_while(|&size| size > 0)
+        .flat_map(move |size| {
+            (0..inbox.len()).step_by(size).map(move |start| {
+                let mut candidate = inbox.to_vec();
+                candidate.drain(start..(start + size).min(inbox.len()));
+                candidate
+            })
+        })
+}
+
+/// The inbox with one of its values replaced by a simpler one
+fn simplifications(inbox: &[ValueBox]) -> impl Iterator<Item = Vec<ValueBox>> + '_ {
+    (0..inbox.len()).flat_map(move |i| {
+        simpler_values(inbox[i]).into_iter().map(move |value| {
+            let mut candidate = inbox.to_vec();
+            candidate[i] = value;
+            candidate
+        })
+    })
+}
+
+/// The values simpler than the given one, the simplest first
+fn simpler_values(value: ValueBox) -> Vec<ValueBox> {
+    let mut values = match value {
+        ValueBox::Number(n) => {
+            // Positive numbers are simpler than negative ones of the same magnitude
+            let simplicity = |n: i32| (n.unsigned_abs(), n < 0);
+            [0, n / 2, n - n.signum(), n.saturating_neg()]
+                .into_iter()
+                .filter(|m| simplicity(*m) < simplicity(n))
+                .map(ValueBox::Number)
+                .collect::<Vec<_>>()
+        }
+        ValueBox::Character(c) => {
+            let first = match c {
+                'a'..='z' => b'a',
+                'A'..='Z' => b'A',
+                _ => return vec![],
+            };
+            let c = c as u8;
+            [first, first + (c - first) / 2, c.saturating_sub(1)]
+                .into_iter()
+                .filter(|l| *l >= first && *l < c)
+                .map(|l| ValueBox::Character(l as char))
+                .collect()
+        }
+    };
+    values.dedup();
+    values
+}
+
+fn parse_range<T: FromStr + PartialOrd>(s: &str) -> Option<RangeInclusive<T>> {
+    let (min, max) = s.split_once("..")?;
+    let (min, max) = (min.parse::<T>().ok()?, max.parse::<T>().ok()?);
+    (min <= max).then_some(min..=max)
+}
+
+/// The values of a range of numbers or letters: `-5..5` or `A..E` (inclusive)
+pub fn range_values(range: &str) -> Option<Vec<ValueBox>> {
+    if let Some(numbers) = parse_range::<i32>(range) {
+        Some(numbers.map(ValueBox::Number).collect())
+    } else {
+        parse_range::<char>(range)
+            .filter(|letters| letters.start().is_alphabetic() && letters.end().is_alphabetic())
+            .map(|letters| letters.map(ValueBox::Character).collect())
+    }
+}
+
+impl FromStr for InputSpec {
+    type Err = ParseInputSpecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+
+        let count = words.next().unwrap_or_default();
+        let count = count
+            .parse::<usize>()
+            .map_err(|_| ParseInputSpecError::InvalidCount(count.to_string()))?;
+
+        let kind = match words.next() {
+            Some("numbers" | "number") => ValueKind::Numbers,
+            Some("letters" | "letter" | "chars") => ValueKind::Letters,
+            Some("values" | "mixed") => ValueKind::Values,
+            kind => {
+                return Err(ParseInputSpecError::InvalidKind(
+                    kind.unwrap_or_default().to_string(),
+                ))
+            }
+        };
+
+        let mut spec = InputSpec {
+            count,
+            kind,
+            numbers: -99..=99,
+            letters: 'A'..='Z',
+            non_zero: false,
+        };
+        for word in words {
+            if word == "non-zero" {
+                spec.non_zero = true;
+            } else if word.contains("..") {
+                if let Some(numbers) = parse_range::<i32>(word) {
+                    spec.numbers = numbers;
+                } else if let Some(letters) = parse_range::<char>(word) {
+                    spec.letters = letters;
+                } else {
+                    return Err(ParseInputSpecError::InvalidRange(word.to_string()));
+                }
+            } else {
+                return Err(ParseInputSpecError::InvalidConstraint(word.to_string()));
+            }
+        }
+
+        if spec.kind != ValueKind::Letters && spec.non_zero && spec.numbers == (0..=0) {
+            return Err(ParseInputSpecError::NoPossibleValue(s.trim().to_string()));
+        }
+
+        Ok(spec)
+    }
+}
+
+impl FromStr for InboxSpec {
+    type Err = ParseInputSpecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s
+            .split(',')
+            .map(|part| part.parse::<InputSpec>())
+            .collect::<Result<Vec<InputSpec>, ParseInputSpecError>>()?;
+        Ok(Self { parts })
+    }
+}
+
+#[cfg(test)]
+mod generator_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_input_spec() {
+        assert_eq!(
+            "10 numbers -10..10".parse::<InputSpec>().unwrap(),
+            InputSpec {
+                count: 10,
+                kind: ValueKind::Numbers,
+                numbers: -10..=10,
+                letters: 'A'..='Z',
+                non_zero: false,
+            }
+        );
+        assert_eq!(
+            "5 letters A..E".parse::<InputSpec>().unwrap().letters,
+            'A'..='E'
+        );
+        assert!("8 values non-zero".parse::<InputSpec>().unwrap().non_zero);
+
+        assert!("ten numbers".parse::<InputSpec>().is_err());
+        assert!("10 things".parse::<InputSpec>().is_err());
+        assert!("10 numbers 10..-10".parse::<InputSpec>().is_err());
+        assert!("10 numbers positive".parse::<InputSpec>().is_err());
+        assert!("1 numbers 0..0 non-zero".parse::<InputSpec>().is_err());
+    }
+
+    #[test]
+    fn test_range_values() {
+        assert_eq!(
+            range_values("-1..1"),
+            Some(vec![(-1).into(), 0.into(), 1.into()])
+        );
+        assert_eq!(range_values("A..B"), Some(vec!['A'.into(), 'B'.into()]));
+        assert_eq!(range_values("1..-1"), None);
+        assert_eq!(range_values("1..B"), None);
+    }
+
+    #[test]
+    fn test_generate_inbox() {
+        let spec = "20 numbers -3..3 non-zero, 5 letters A..C"
+            .parse::<InboxSpec>()
+            .unwrap();
+        let inbox = spec.generate(&mut rand::thread_rng());
+
+        assert_eq!(inbox.len(), 25);
+        assert!(inbox[..20].iter().all(|value| matches!(
+            value,
+            ValueBox::Number(n) if (-3..=3).contains(n) && *n != 0
+        )));
+        assert!(inbox[20..]
+            .iter()
+            .all(|value| matches!(value, ValueBox::Character('A'..='C'))));
+    }
+
+    #[test]
+    fn test_spec_allows() {
+        let spec = "2 numbers -3..3 non-zero, 1 letter A..C"
+            .parse::<InboxSpec>()
+            .unwrap();
+        assert!(spec.allows(&ValueBox::Number(-3)));
+        assert!(!spec.allows(&ValueBox::Number(0)));
+        assert!(!spec.allows(&ValueBox::Number(4)));
+        assert!(spec.allows(&ValueBox::Character('B')));
+        assert!(!spec.allows(&ValueBox::Character('D')));
+    }
+
+    #[test]
+    fn test_shrink() {
+        let inbox = [3, -40, 7, 25, -8, 0, 12].map(ValueBox::Number).to_vec();
+
+        // Fails as soon as there is a number above 10
+        let shrunk = shrink(&inbox, |inbox| {
+            inbox
+                .iter()
+                .any(|value| matches!(value, ValueBox::Number(n) if *n > 10))
+        });
+        assert_eq!(shrunk, vec![ValueBox::Number(11)]);
+
+        // Fails on an inbox of 3 values or more
+        let shrunk = shrink(&inbox, |inbox| inbox.len() >= 3);
+        assert_eq!(shrunk, vec![ValueBox::Number(0); 3]);
+
+        // Fails when a letter comes after another
+        let inbox = "QXAMZ".chars().map(ValueBox::Character).collect::<Vec<_>>();
+        let shrunk = shrink(&inbox, |inbox| {
+            inbox.windows(2).any(|pair| pair[0] != pair[1])
+        });
+        assert_eq!(
+            shrunk,
+            vec![ValueBox::Character('A'), ValueBox::Character('B')]
+        );
+    }
+
+    #[test]
+    fn test_generate_seeded_inbox() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let spec = "10 values".parse::<InboxSpec>().unwrap();
+        let generate = |seed| spec.generate(&mut StdRng::seed_from_u64(seed));
+        assert_eq!(generate(42), generate(42));
+        assert_ne!(generate(42), generate(43));
+    }
+}
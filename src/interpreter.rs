@@ -1,52 +1,168 @@
-use std::fmt::Debug;
+use std::{
+    collections::BTreeMap,
+    fmt::{Debug, Display},
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
 
+use crate::diagnostics::Diagnostic;
 use crate::script_object::{
-    instruction::Instruction,
+    instruction::{DebugOperand, Instruction, JumpTarget},
     value_box::{ValueBox, ValueBoxMemoryAddress},
-    Block, ScriptObject,
+    Position, ScriptObject, Span,
 };
 
+pub mod breakpoint;
+pub(crate) mod bytecode;
+pub mod geometry;
+mod history;
+pub mod io;
 pub mod memory;
+pub mod observer;
+pub mod settings;
+pub mod snapshot;
+pub mod stats;
+pub mod trace;
+pub mod watchpoint;
+
+use self::{
+    breakpoint::Breakpoint,
+    history::{History, Snapshot},
+    io::{InputSource, OutputSink},
+    memory::{Memory, MAX_VEC_TILES},
+    observer::{JumpEvent, MemoryWriteEvent, Observer, OutputEvent, StepEvent},
+    settings::Settings,
+    snapshot::InterpreterSnapshot,
+    stats::{ExecutionStats, TileAccesses},
+    trace::{DebugCallback, TileAccess, TraceEntry, Tracer},
+    watchpoint::{TileAccessKind, WatchCallback, WatchpointHit},
+};
+
+/// The number of CALLs that can be in progress at once (extension):
+/// more is most likely an endless recursion.
+pub const MAX_CALL_DEPTH: usize = 10_000;
 
-use self::memory::Memory;
+/// The number of steps between two readings of the clock, when the execution has a timeout
+const DEADLINE_CHECK_INTERVAL: usize = 4096;
 
 /// The interpreter is the component that executes the script.
 /// It holds the state of the program.
+///
+/// An interpreter is `Send` (its callbacks and observers must be too), and the parsed script
+/// and the memory are `Send + Sync`: to run executions concurrently, share one script behind
+/// an `Arc` and give each thread its own interpreter:
+///
+/// ```text
+/// let script = Arc::new(script);
+/// let handles = inboxes.into_iter().map(|inbox| {
+///     let script = Arc::clone(&script);
+///     thread::spawn(move || Interpreter::new(Memory::default()).execute(&script, &inbox))
+/// });
+/// ```
 pub struct Interpreter {
     /// The tiles on the floor where ValueBoxes can be placed
     memory: Memory,
     /// The eventual ValueBox held by the character
     head: Option<ValueBox>,
+    /// The eventual ValueBox held in the second hand (extension)
+    second_hand: Option<ValueBox>,
     /// The index of the next input ValueBox to be read
     next_input: usize,
+    /// The number of ValueBoxes dropped on the output belt so far
+    outputs_count: usize,
+    /// The position of the next instruction to be executed
+    position: Position,
+    /// The positions to return to, of the CALLs in progress (the innermost is the last one)
+    calls: Vec<Position>,
+    /// The points at which `run` pauses the execution
+    breakpoints: Vec<Breakpoint>,
+    /// Set when the execution is paused (on a breakpoint or after a step),
+    /// so that resuming doesn't pause again on the same breakpoint
+    resuming: bool,
+    /// Statistics about the execution so far
+    stats: ExecutionStats,
+    settings: Settings,
+    /// Called after each executed instruction
+    tracer: Option<Tracer>,
+    /// Called by the DEBUG instructions
+    debug_callback: Option<DebugCallback>,
+    /// The states before the last executed instructions
+    history: History,
+    /// The addresses of the tiles on which `run` pauses the execution when they're accessed
+    watchpoints: Vec<usize>,
+    /// The accesses to watched tiles made by the last executed instruction
+    watchpoint_hits: Vec<WatchpointHit>,
+    /// Called each time a watched tile is accessed
+    watch_callback: Option<WatchCallback>,
+    /// Notified of each step, jump, memory write and output
+    observers: Vec<Box<dyn Observer + Send>>,
+    /// When the execution started by `execute` or `run` must stop, with a timeout in the settings
+    deadline: Option<Instant>,
 }
 
+// A field that can't be sent to another thread (an `Rc`, a callback that isn't `Send`...) fails here
+const _: () = {
+    const fn send<T: Send>() {}
+    const fn send_sync<T: Send + Sync>() {}
+    send::<Interpreter>();
+    send_sync::<Memory>();
+    send_sync::<ScriptObject>();
+};
+
 /// Holds the state of the interpreter at a given moment,
 /// for debugging purposes.
+/// Serialized as `{"head": ..., "inputs_left": [...], "outputs": [...], "memory": {"0": ...}}`,
+/// with null for the empty tiles and for the inputs and outputs that aren't known (when they're streamed).
+#[derive(Debug, Serialize)]
 pub struct InterpreterStateInfo {
-    inputs_left: Vec<String>,
-    outputs: Vec<String>,
-    memory: Vec<(usize, String)>,
+    head: Option<ValueBox>,
+    /// None if the inputs left aren't known
+    inputs_left: Option<Vec<ValueBox>>,
+    /// None if the outputs aren't kept: only their number is known
+    outputs: Option<Vec<ValueBox>>,
+    #[serde(skip)]
+    outputs_count: usize,
+    memory: BTreeMap<usize, Option<ValueBox>>,
+}
+
+impl InterpreterStateInfo {
+    /// The outputs produced so far, None if they aren't kept
+    pub fn outputs(&self) -> Option<&[ValueBox]> {
+        self.outputs.as_deref()
+    }
+
+    /// The value on the tile, None if the tile is empty
+    pub fn tile(&self, address: usize) -> Option<ValueBox> {
+        self.memory.get(&address).copied().flatten()
+    }
 }
 
-impl Debug for InterpreterStateInfo {
+impl Display for InterpreterStateInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let inputs_left = self
-            .inputs_left
-            .iter()
-            .map(|vb| vb.to_string())
-            .collect::<Vec<String>>()
-            .join(", ");
-        let outputs = self
-            .outputs
-            .iter()
-            .map(|vb| vb.to_string())
-            .collect::<Vec<String>>()
-            .join(", ");
+        let join = |values: &[ValueBox]| {
+            values
+                .iter()
+                .map(|vb| vb.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        let inputs_left = match &self.inputs_left {
+            Some(inputs_left) => join(inputs_left),
+            None => "(unknown)".to_string(),
+        };
+        let outputs = match &self.outputs {
+            Some(outputs) => join(outputs),
+            None => format!("({} values)", self.outputs_count),
+        };
         let memory = self
             .memory
             .iter()
-            .map(|(address, vb)| format!("{}: {}", address, vb))
+            .map(|(address, vb)| match vb {
+                Some(vb) => format!("{}: {}", address, vb),
+                None => format!("{}: None", address),
+            })
             .collect::<Vec<String>>()
             .join("\n");
 
@@ -60,38 +176,162 @@ impl Debug for InterpreterStateInfo {
 
 impl Interpreter {
     pub fn new(memory: Memory) -> Self {
+        Self::with_settings(memory, Settings::default())
+    }
+
+    pub fn with_settings(memory: Memory, settings: Settings) -> Self {
         Self {
             memory,
             head: None,
+            second_hand: None,
             next_input: 0,
+            outputs_count: 0,
+            position: Position::default(),
+            calls: Vec::new(),
+            breakpoints: Vec::new(),
+            resuming: false,
+            stats: ExecutionStats::default(),
+            history: History::new(settings.history_size),
+            settings,
+            tracer: None,
+            debug_callback: None,
+            watchpoints: Vec::new(),
+            watchpoint_hits: Vec::new(),
+            watch_callback: None,
+            observers: vec![],
+            deadline: None,
         }
     }
 
-    fn build_state(&self, inputs: &[ValueBox], outputs: &[ValueBox]) -> InterpreterStateInfo {
-        let inputs_left = inputs[self.next_input..]
-            .iter()
-            .map(|vb| vb.to_string())
-            .collect::<Vec<String>>();
-        let outputs = outputs
-            .iter()
-            .map(|vb| vb.to_string())
-            .collect::<Vec<String>>();
+    /// Start a new execution from the beginning of the script, on the given floor.
+    /// The settings, breakpoints, watchpoints, tracer and observers are kept,
+    /// and so are the buffers of the statistics and of the history, so that an interpreter
+    /// can run many executions without allocating them again.
+    pub fn reset(&mut self, memory: Memory) {
+        self.memory = memory;
+        self.head = None;
+        self.second_hand = None;
+        self.next_input = 0;
+        self.outputs_count = 0;
+        self.position = Position::default();
+        self.calls.clear();
+        self.resuming = false;
+        self.stats.reset();
+        self.history.clear();
+        self.watchpoint_hits.clear();
+    }
 
-        let memory_indices = 0..self.memory.get_max_address() + 1;
-        let memory = memory_indices
-            .map(|i| {
-                if let Some(vb) = self.memory.get(&i) {
-                    (i, vb.to_string())
-                } else {
-                    (i, "None".to_string())
-                }
-            })
-            .collect::<Vec<(usize, String)>>();
+    /// Save the state of the interpreter, to restore it later with `from_snapshot`
+    pub fn snapshot(&self) -> InterpreterSnapshot {
+        InterpreterSnapshot {
+            memory: self.memory.clone(),
+            head: self.head,
+            second_hand: self.second_hand,
+            next_input: self.next_input,
+            outputs_count: self.outputs_count,
+            position: self.position,
+            calls: self.calls.clone(),
+            resuming: self.resuming,
+            stats: self.stats.clone(),
+            settings: self.settings.clone(),
+            breakpoints: self.breakpoints.clone(),
+            watchpoints: self.watchpoints.clone(),
+        }
+    }
+
+    /// Restore an interpreter saved with `snapshot`, with an empty history
+    pub fn from_snapshot(snapshot: InterpreterSnapshot) -> Self {
+        Self {
+            head: snapshot.head,
+            second_hand: snapshot.second_hand,
+            next_input: snapshot.next_input,
+            outputs_count: snapshot.outputs_count,
+            position: snapshot.position,
+            calls: snapshot.calls,
+            resuming: snapshot.resuming,
+            stats: snapshot.stats,
+            breakpoints: snapshot.breakpoints,
+            watchpoints: snapshot.watchpoints,
+            ..Self::with_settings(snapshot.memory, snapshot.settings)
+        }
+    }
+
+    /// Register a function called after each executed instruction, with what the instruction did.
+    pub fn set_tracer(&mut self, tracer: impl FnMut(&TraceEntry) + Send + 'static) {
+        self.tracer = Some(Box::new(tracer));
+    }
+
+    /// Register a function called with what each DEBUG instruction (extension) prints.
+    /// Without it, the DEBUG instructions do nothing.
+    pub fn set_debug_callback(&mut self, callback: impl FnMut(Position, &str) + Send + 'static) {
+        self.debug_callback = Some(Box::new(callback));
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// The number of instructions executed so far.
+    pub fn steps(&self) -> usize {
+        self.stats.steps
+    }
+
+    /// Statistics about the execution so far.
+    pub fn stats(&self) -> &ExecutionStats {
+        &self.stats
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    pub fn head(&self) -> Option<ValueBox> {
+        self.head
+    }
+
+    /// The value in the second hand (extension)
+    pub fn second_hand(&self) -> Option<ValueBox> {
+        self.second_hand
+    }
+
+    /// The position of the next instruction to be executed.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// The number of inputs taken from the inbox so far.
+    pub fn inputs_read(&self) -> usize {
+        self.next_input
+    }
+
+    /// Get the state of the interpreter, to inspect it while the execution is paused.
+    pub fn state(&self, inputs: impl InputSource, outputs: &[ValueBox]) -> InterpreterStateInfo {
+        self.build_state(&inputs, &outputs.to_vec())
+    }
+
+    fn build_state(
+        &self,
+        inputs: &dyn InputSource,
+        outputs: &dyn OutputSink,
+    ) -> InterpreterStateInfo {
+        let memory_indices = if self.memory.get_max_address() == usize::MAX {
+            // No maximum: only show the tiles holding a value
+            self.memory.addresses().collect::<Vec<usize>>()
+        } else {
+            (0..=self.memory.get_max_address()).collect::<Vec<usize>>()
+        };
 
         InterpreterStateInfo {
-            inputs_left,
-            outputs,
-            memory,
+            head: self.head,
+            inputs_left: inputs
+                .remaining(self.next_input)
+                .map(|inputs_left| inputs_left.to_vec()),
+            outputs: outputs.outputs().map(|outputs| outputs.to_vec()),
+            outputs_count: self.outputs_count,
+            memory: memory_indices
+                .into_iter()
+                .map(|i| (i, self.memory.get(&i).copied()))
+                .collect(),
         }
     }
 }
@@ -102,208 +342,1150 @@ impl Interpreter {
 /// Wrapper for all the possible errors that can occur when executing a script.
 pub enum ExecuteScriptError {
     #[error("INTERPRETER ERROR | cannot jump: no block with label {1} found\n-- STATE --\n{0:?}")]
-    InvalidJumpError(InterpreterStateInfo, String),
-    #[error("INTERPRETER ERROR | error executing an instruction:\n\t{1}\n-- STATE --\n{0:?}")]
-    ExecuteInstructionError(InterpreterStateInfo, #[source] ExecuteInstructionError),
+    InvalidJumpError(Box<InterpreterStateInfo>, String),
+    #[error("INTERPRETER ERROR | error executing an instruction{}:\n\t{2}\n-- STATE --\n{0:?}", location(.1))]
+    /// The span is where the failing instruction is written in the script
+    ExecuteInstructionError(
+        Box<InterpreterStateInfo>,
+        Option<Box<Span>>,
+        #[source] ExecuteInstructionError,
+    ),
+    #[error("INTERPRETER ERROR | step limit of {1} exceeded, the script may be stuck in an infinite loop\n-- STATE --\n{0:?}")]
+    StepLimitExceeded(Box<InterpreterStateInfo>, usize),
+    #[error("INTERPRETER ERROR | time limit of {1:?} exceeded, the script may be stuck in an infinite loop\n-- STATE --\n{0:?}")]
+    Timeout(Box<InterpreterStateInfo>, Duration),
+    #[error("INTERPRETER ERROR | tile limit of {1} exceeded, the script holds too many values on the floor\n-- STATE --\n{0:?}")]
+    TileLimitExceeded(Box<InterpreterStateInfo>, usize),
+    #[error("INTERPRETER ERROR | output limit of {1} exceeded, the script may be stuck in an infinite loop\n-- STATE --\n{0:?}")]
+    OutputLimitExceeded(Box<InterpreterStateInfo>, usize),
+    #[error("INTERPRETER ERROR | allocation limit of {1} exceeded, the script holds too many values\n-- STATE --\n{0:?}")]
+    AllocationLimitExceeded(Box<InterpreterStateInfo>, usize),
 }
 
-impl Interpreter {
-    /// Execute a given script with given outputs, starting at first block.
-    pub fn execute(
-        &mut self,
-        script: &ScriptObject,
-        inputs: &[ValueBox],
-    ) -> Result<Vec<ValueBox>, ExecuteScriptError> {
-        let mut output: Vec<ValueBox> = vec![];
-        let mut current_block: &Block = script.get_block_by_index(0).unwrap();
+impl ExecuteScriptError {
+    /// A stable identifier of the kind of error, for tools
+    pub fn code(&self) -> &'static str {
+        match self {
+            ExecuteScriptError::InvalidJumpError(..) => "invalid-jump",
+            ExecuteScriptError::ExecuteInstructionError(_, _, error) => error.code(),
+            ExecuteScriptError::StepLimitExceeded(..) => "step-limit-exceeded",
+            ExecuteScriptError::Timeout(..) => "timeout",
+            ExecuteScriptError::TileLimitExceeded(..) => "tile-limit-exceeded",
+            ExecuteScriptError::OutputLimitExceeded(..) => "output-limit-exceeded",
+            ExecuteScriptError::AllocationLimitExceeded(..) => "allocation-limit-exceeded",
+        }
+    }
 
-        loop {
-            match self
-                .execute_block(current_block, inputs, &mut output)
-                .map_err(|e| {
-                    ExecuteScriptError::ExecuteInstructionError(
-                        self.build_state(inputs, &output),
-                        e,
-                    )
-                })? {
-                BlockResult::JumpBlock(label) => match script.get_block_by_label(&label) {
-                    Some(block) => current_block = block,
-                    None => {
-                        return Err(ExecuteScriptError::InvalidJumpError(
-                            self.build_state(inputs, &output),
-                            label,
-                        ))
-                    }
-                },
-                BlockResult::NextBlock => match script.get_next(current_block) {
-                    Some(block) => current_block = block,
-                    None => break,
-                },
-                BlockResult::Terminate => break,
+    /// What went wrong, without the state of the interpreter
+    pub fn message(&self) -> String {
+        match self {
+            ExecuteScriptError::InvalidJumpError(_, label) => {
+                format!("cannot jump: no block with label {} found", label)
             }
+            ExecuteScriptError::ExecuteInstructionError(_, _, error) => error.to_string(),
+            ExecuteScriptError::StepLimitExceeded(_, max_steps) => format!(
+                "step limit of {} exceeded, the script may be stuck in an infinite loop",
+                max_steps
+            ),
+            ExecuteScriptError::Timeout(_, timeout) => format!(
+                "time limit of {:?} exceeded, the script may be stuck in an infinite loop",
+                timeout
+            ),
+            ExecuteScriptError::TileLimitExceeded(_, max_tiles) => format!(
+                "tile limit of {} exceeded, the script holds too many values on the floor",
+                max_tiles
+            ),
+            ExecuteScriptError::OutputLimitExceeded(_, max_outputs) => format!(
+                "output limit of {} exceeded, the script may be stuck in an infinite loop",
+                max_outputs
+            ),
+            ExecuteScriptError::AllocationLimitExceeded(_, max_allocations) => format!(
+                "allocation limit of {} exceeded, the script holds too many values",
+                max_allocations
+            ),
+        }
+    }
+
+    /// The state of the interpreter when the error occurred
+    pub fn state(&self) -> &InterpreterStateInfo {
+        match self {
+            ExecuteScriptError::InvalidJumpError(state, _)
+            | ExecuteScriptError::ExecuteInstructionError(state, _, _)
+            | ExecuteScriptError::StepLimitExceeded(state, _)
+            | ExecuteScriptError::Timeout(state, _)
+            | ExecuteScriptError::TileLimitExceeded(state, _)
+            | ExecuteScriptError::OutputLimitExceeded(state, _)
+            | ExecuteScriptError::AllocationLimitExceeded(state, _) => state,
         }
+    }
 
-        Ok(output)
+    /// The error located on the failing instruction, to show it with an excerpt of the script.
+    /// None if the error isn't about an instruction written in the script.
+    pub fn diagnostic(&self) -> Option<Diagnostic> {
+        match self {
+            ExecuteScriptError::ExecuteInstructionError(_, Some(span), error) => {
+                let start = span.column - 1;
+                Some(Diagnostic::new(
+                    error,
+                    span.line,
+                    Some(start..start + span.text.len()),
+                ))
+            }
+            _ => None,
+        }
     }
 }
 
-// ==================== Block execution ====================
+/// " at line N: <source text>", to point at the failing instruction in the script
+fn location(span: &Option<Box<Span>>) -> String {
+    span.as_ref()
+        .map(|span| format!(" at {}", span))
+        .unwrap_or_default()
+}
 
-/// All the possible things that can happen after executing a block
-enum BlockResult {
-    /// A jump instruction was executed inside the block
-    JumpBlock(String),
-    /// The block reached its end, go to the next one
-    NextBlock,
-    /// The program has terminated.
-    Terminate,
+/// Where the execution stands after running or stepping through the script
+#[derive(Debug, PartialEq)]
+pub enum ExecutionStatus {
+    /// The program has terminated
+    Finished,
+    /// The execution is paused before the instruction at the given position
+    Paused(Position),
 }
 
 impl Interpreter {
-    /// Execute the instructions of a given block one by one,
-    /// mutating the output along the way.
-    fn execute_block(
+    /// Execute a given script with given outputs, from the current position
+    /// (the start of the script for a new interpreter) to the end.
+    /// Breakpoints are ignored.
+    /// When nothing observes the execution (no tracer, observer, watchpoint or history),
+    /// the script is executed as a flat bytecode, which is faster.
+    /// Returns the outputs along with the statistics of the execution.
+    pub fn execute(
         &mut self,
-        block: &Block,
-        inputs: &[ValueBox],
-        outputs: &mut Vec<ValueBox>,
-    ) -> Result<BlockResult, ExecuteInstructionError> {
-        for instruction in block.instructions.iter() {
-            match self.execute_instruction(instruction, inputs, outputs)? {
-                InstructionResult::JumpBlock(label) => return Ok(BlockResult::JumpBlock(label)),
-                InstructionResult::NextInstruction => {}
-                InstructionResult::Terminate => return Ok(BlockResult::Terminate),
-            }
+        script: &ScriptObject,
+        inputs: impl InputSource,
+    ) -> Result<(Vec<ValueBox>, ExecutionStats), ExecuteScriptError> {
+        let mut output: Vec<ValueBox> = vec![];
+        let stats = self.execute_into(script, inputs, &mut output)?;
+        Ok((output, stats))
+    }
+
+    /// Like `execute`, but the outputs are sent to the given sink as soon as they're produced.
+    /// Returns the statistics of the execution.
+    pub fn execute_into(
+        &mut self,
+        script: &ScriptObject,
+        mut inputs: impl InputSource,
+        mut outputs: impl OutputSink,
+    ) -> Result<ExecutionStats, ExecuteScriptError> {
+        self.start_deadline();
+        if self.observed() {
+            while let ExecutionStatus::Paused(_) =
+                self.step_from(script, &mut inputs, &mut outputs)?
+            {}
+        } else {
+            self.execute_bytecode(script, &mut inputs, &mut outputs)?;
         }
 
-        // All instructions executed
-        // Go to next chronological block
-        Ok(BlockResult::NextBlock)
+        Ok(self.stats.clone())
     }
-}
 
-// ==================== Instruction execution ====================
+    /// Execute the script from the current position, until the program terminates,
+    /// a breakpoint is reached or a watched tile is accessed. Call it again to resume the execution.
+    pub fn run(
+        &mut self,
+        script: &ScriptObject,
+        mut inputs: impl InputSource,
+        mut outputs: impl OutputSink,
+    ) -> Result<ExecutionStatus, ExecuteScriptError> {
+        let breakpoint_positions = self
+            .breakpoints
+            .iter()
+            .filter_map(|breakpoint| breakpoint.resolve(script))
+            .collect::<Vec<Position>>();
 
-#[derive(Debug, PartialEq)]
-/// All the possible things that can happen after executing an instruction
-enum InstructionResult {
-    /// A jump instruction was executed
-    JumpBlock(String),
-    /// The instruction was successfully executed, read the next one
-    NextInstruction,
-    /// The program has terminated.
-    /// (Can happen if an INBOX instruction is executed with no more inputs to read)
-    Terminate,
-}
+        self.start_deadline();
+        loop {
+            let position = match script.next_instruction_position(self.position) {
+                Some(position) => position,
+                None => return Ok(ExecutionStatus::Finished),
+            };
+            self.position = position;
+
+            if !self.resuming && breakpoint_positions.contains(&position) {
+                self.resuming = true;
+                return Ok(ExecutionStatus::Paused(position));
+            }
 
-#[derive(Debug, thiserror::Error)]
-/// All errors that can occur when executing an instruction
-/// Errors are voluntarily redundant from one instruction type to another,
-/// to make it easier to understand what went wrong.
-pub enum ExecuteInstructionError {
-    #[error("cannot output: head empty")]
-    OutputNone,
+            let status = self.step_from(script, &mut inputs, &mut outputs)?;
+            if status == ExecutionStatus::Finished {
+                return Ok(ExecutionStatus::Finished);
+            }
+            if !self.watchpoint_hits.is_empty() {
+                // Pause right after the instruction that accessed a watched tile
+                return Ok(status);
+            }
+            self.resuming = false;
+        }
+    }
 
-    #[error("cannot copy from:\n\t{0}")]
-    CopyFromInvalidAddress(#[source] memory::GetMemoryError),
-    #[error("cannot copy to:\n\t{0}")]
-    CopyToInvalidAddress(#[source] memory::SetMemoryError),
-    #[error("cannot copy to: head empty")]
-    CopyToHeadNone,
+    /// Execute the next instruction only.
+    pub fn step(
+        &mut self,
+        script: &ScriptObject,
+        mut inputs: impl InputSource,
+        mut outputs: impl OutputSink,
+    ) -> Result<ExecutionStatus, ExecuteScriptError> {
+        self.deadline = None;
+        self.step_from(script, &mut inputs, &mut outputs)
+    }
 
-    #[error("cannot read memory value from VBMA:\n\t{0}")]
-    AddInvalidAddress(#[source] memory::GetMemoryError),
-    #[error("cannot add: empty head")]
-    AddHeadNone,
-    #[error("cannot add characters (head: {head} and mem: {mem} at address {address})")]
-    AddCharacters {
-        head: char,
-        mem: char,
-        address: usize,
-    },
-    #[error("cannot add characters and numbers together (head: {head:?} and mem: {mem:?} at address {address})")]
-    AddCharacterAndNumber {
-        head: ValueBox,
-        mem: ValueBox,
-        address: usize,
-    },
+    /// Start counting the time of the execution, if the settings limit it
+    fn start_deadline(&mut self) {
+        self.deadline = self
+            .settings
+            .timeout
+            .map(|timeout| Instant::now() + timeout);
+    }
 
-    #[error("cannot read memory value from VBMA:\n\t{0}")]
-    SubInvalidAddress(#[source] memory::GetMemoryError),
-    #[error("cannot subtract: empty head")]
-    SubHeadNone,
-    #[error("cannot subtract characters and numbers together (head: {head:?} and mem: {mem:?} at address {address})")]
-    SubCharacterAndNumber {
-        head: ValueBox,
-        mem: ValueBox,
-        address: usize,
-    },
+    /// Whether the execution has run out of time after the given number of steps.
+    /// The clock is only read every few thousand steps, as it's slow.
+    fn deadline_passed(&self, steps: usize) -> bool {
+        self.deadline.is_some_and(|deadline| {
+            steps.is_multiple_of(DEADLINE_CHECK_INTERVAL) && Instant::now() >= deadline
+        })
+    }
 
-    #[error("cannot test IfZero if head ({0:?}) is not a valid number")]
-    JumpIfZeroInvalidHead(Option<ValueBox>),
-    #[error("cannot test IfNegative if head ({0:?}) is not a valid number")]
-    JumpIfNegativeInvalidHead(Option<ValueBox>),
+    /// Fail if the execution holds more than the limits of the settings allow
+    fn check_limits(
+        &self,
+        inputs: &dyn InputSource,
+        outputs: &dyn OutputSink,
+    ) -> Result<(), ExecuteScriptError> {
+        let limits = &self.settings.limits;
+        let tiles = self.memory.used_tiles();
+        let allocations = tiles + self.outputs_count + self.calls.len();
+        let checks: [(_, _, fn(_, _) -> _); 3] = [
+            (
+                limits.max_tiles,
+                tiles,
+                ExecuteScriptError::TileLimitExceeded,
+            ),
+            (
+                limits.max_outputs,
+                self.outputs_count,
+                ExecuteScriptError::OutputLimitExceeded,
+            ),
+            (
+                limits.max_allocations,
+                allocations,
+                ExecuteScriptError::AllocationLimitExceeded,
+            ),
+        ];
+        let exceeded = checks.into_iter().find_map(|(limit, used, error)| {
+            limit
+                .filter(|limit| used > *limit)
+                .map(|limit| (limit, error))
+        });
+        match exceeded {
+            Some((limit, error)) => Err(error(Box::new(self.build_state(inputs, outputs)), limit)),
+            None => Ok(()),
+        }
+    }
 
-    #[error("cannot bump memory value from VBMA:\n\t{0}")]
-    BumpInvalidAddress(#[source] memory::GetMemoryError),
-    #[error("cannot bump a character")]
-    BumpCharacter,
+    fn step_from(
+        &mut self,
+        script: &ScriptObject,
+        inputs: &mut dyn InputSource,
+        outputs: &mut dyn OutputSink,
+    ) -> Result<ExecutionStatus, ExecuteScriptError> {
+        let position = match script.next_instruction_position(self.position) {
+            Some(position) => position,
+            None => return Ok(ExecutionStatus::Finished),
+        };
+        self.position = position;
+
+        if let Some(max_steps) = self.settings.max_steps {
+            if self.stats.steps >= max_steps {
+                return Err(ExecuteScriptError::StepLimitExceeded(
+                    Box::new(self.build_state(inputs, outputs)),
+                    max_steps,
+                ));
+            }
+        }
+        if self.deadline_passed(self.stats.steps) {
+            return Err(ExecuteScriptError::Timeout(
+                Box::new(self.build_state(inputs, outputs)),
+                self.settings.timeout.unwrap_or_default(),
+            ));
+        }
+
+        let instruction = script.get_instruction(position).unwrap();
+        if self.history.is_enabled() {
+            self.save_snapshot(position, instruction);
+        }
+        self.record_stats(script, position, instruction);
+        self.record_dynamic_tile_accesses(instruction);
+        let state_before = self.tracer.is_some().then(|| self.trace_state(instruction));
+        let watched_tiles = self.watched_tiles(instruction);
+        let observed_before =
+            (!self.observers.is_empty()).then(|| (self.head, self.written_tile(instruction)));
+        self.watchpoint_hits.clear();
+        let result = self.execute_instruction(instruction, &mut *inputs, &mut *outputs);
+        if let Some(state_before) = state_before {
+            self.trace(script, position, instruction, state_before);
+        }
+        let result = result.map_err(|e| {
+            ExecuteScriptError::ExecuteInstructionError(
+                Box::new(self.build_state(inputs, outputs)),
+                script.get_span(position).cloned().map(Box::new),
+                e,
+            )
+        })?;
+        if !watched_tiles.is_empty() {
+            self.record_watchpoint_hits(position, instruction, watched_tiles);
+        }
+        self.check_limits(inputs, outputs)?;
+
+        let jumped = matches!(result, InstructionResult::JumpBlock(_));
+        self.position = match result {
+            InstructionResult::JumpBlock(target) => match target.block() {
+                Some(block) => Position {
+                    block,
+                    instruction: 0,
+                },
+                None => {
+                    return Err(ExecuteScriptError::InvalidJumpError(
+                        Box::new(self.build_state(inputs, outputs)),
+                        target.label().to_string(),
+                    ))
+                }
+            },
+            InstructionResult::NextInstruction => Position {
+                instruction: position.instruction + 1,
+                ..position
+            },
+            InstructionResult::Return(position) => position,
+            InstructionResult::Terminate => script.end_position(),
+        };
+        if let Some(observed_before) = observed_before {
+            self.notify_observers(position, instruction, jumped, observed_before);
+        }
+
+        match script.next_instruction_position(self.position) {
+            Some(next_position) => {
+                self.position = next_position;
+                self.resuming = true;
+                Ok(ExecutionStatus::Paused(next_position))
+            }
+            None => Ok(ExecutionStatus::Finished),
+        }
+    }
 }
 
+// ==================== Bytecode ====================
+
 impl Interpreter {
-    /// Execute 1 instruction
-    /// using one big match to handle all the possible instructions
-    fn execute_instruction(
+    /// Whether something needs to know what happens at each step
+    /// (tracer, observers, watchpoints or history)
+    fn observed(&self) -> bool {
+        self.tracer.is_some()
+            || !self.observers.is_empty()
+            || !self.watchpoints.is_empty()
+            || self.history.is_enabled()
+    }
+
+    /// Execute the script to the end from the current position, like `step` in a loop,
+    /// but on the flat bytecode of the script.
+    /// Nothing is traced, observed or saved in the history.
+    fn execute_bytecode(
         &mut self,
-        instruction: &Instruction,
-        inputs: &[ValueBox],
-        outputs: &mut Vec<ValueBox>,
-    ) -> Result<InstructionResult, ExecuteInstructionError> {
-        match instruction {
-            Instruction::In => {
-                match inputs.get(self.next_input) {
-                    Some(value) => {
-                        self.next_input += 1;
-                        self.head = Some(*value);
+        script: &ScriptObject,
+        inputs: &mut dyn InputSource,
+        outputs: &mut dyn OutputSink,
+    ) -> Result<(), ExecuteScriptError> {
+        let bytecode = script.bytecode();
+        // The number of times each op is executed, added to the statistics at the end
+        let mut executions = vec![0; bytecode.ops.len()];
+        // The same for the accesses to the tiles behind pointers or on the stack, by address,
+        // like the memory below MAX_VEC_TILES
+        let mut tiles = vec![];
+        let mut steps = self.stats.steps;
+        let mut ip = bytecode.index_of(self.position);
+
+        let result = loop {
+            let op = match bytecode.ops.get(ip) {
+                Some(op) => op,
+                None => break Ok(()),
+            };
+            self.position = op.position;
+
+            if let Some(max_steps) = self.settings.max_steps {
+                if steps >= max_steps {
+                    break Err(ExecuteScriptError::StepLimitExceeded(
+                        Box::new(self.build_state(inputs, outputs)),
+                        max_steps,
+                    ));
+                }
+            }
+            if self.deadline_passed(steps) {
+                break Err(ExecuteScriptError::Timeout(
+                    Box::new(self.build_state(inputs, outputs)),
+                    self.settings.timeout.unwrap_or_default(),
+                ));
+            }
+
+            steps += 1;
+            executions[ip] += 1;
+            match self.dynamic_tile_access(&op.instruction) {
+                Some((address, reads, writes)) if address < MAX_VEC_TILES => {
+                    if tiles.len() <= address {
+                        tiles.resize(address + 1, TileAccesses::default());
                     }
-                    // No more inputs => terminate program
+                    tiles[address].reads += reads;
+                    tiles[address].writes += writes;
+                }
+                Some((address, reads, writes)) => self.record_tile_accesses(address, reads, writes),
+                None => {}
+            }
+            ip = match self.execute_instruction(&op.instruction, &mut *inputs, &mut *outputs) {
+                Ok(InstructionResult::NextInstruction) => ip + 1,
+                Ok(InstructionResult::JumpBlock(target)) => match op.jump {
+                    Some(jump) => jump,
                     None => {
-                        return Ok(InstructionResult::Terminate);
+                        break Err(ExecuteScriptError::InvalidJumpError(
+                            Box::new(self.build_state(inputs, outputs)),
+                            target.label().to_string(),
+                        ))
                     }
+                },
+                Ok(InstructionResult::Return(position)) => bytecode.index_of(position),
+                Ok(InstructionResult::Terminate) => bytecode.ops.len(),
+                Err(e) => {
+                    break Err(ExecuteScriptError::ExecuteInstructionError(
+                        Box::new(self.build_state(inputs, outputs)),
+                        script.get_span(op.position).cloned().map(Box::new),
+                        e,
+                    ))
                 }
+            };
+            if let Err(e) = self.check_limits(inputs, outputs) {
+                break Err(e);
             }
-            Instruction::Out => match &self.head {
-                Some(value) => outputs.push(*value),
-                None => return Err(ExecuteInstructionError::OutputNone),
-            },
-            Instruction::CopyFrom(vbma) => {
-                let value = self
-                    .memory
-                    .get_with_vbma(vbma)
-                    .map_err(ExecuteInstructionError::CopyFromInvalidAddress)?;
-                self.head = Some(*value);
+        };
+
+        for (op, count) in bytecode.ops.iter().zip(executions) {
+            if count > 0 {
+                self.record_executions(script, op.position, &op.instruction, count);
             }
-            Instruction::CopyTo(_) if self.head.is_none() => {
-                return Err(ExecuteInstructionError::CopyToHeadNone);
+        }
+        for (address, accesses) in tiles.into_iter().enumerate() {
+            self.record_tile_accesses(address, accesses.reads, accesses.writes);
+        }
+        if result.is_ok() {
+            self.position = script.end_position();
+        }
+        result
+    }
+}
+
+impl Interpreter {
+    fn record_stats(
+        &mut self,
+        script: &ScriptObject,
+        position: Position,
+        instruction: &Instruction,
+    ) {
+        self.record_executions(script, position, instruction, 1);
+    }
+
+    /// Count `count` executions of the instruction at the given position
+    fn record_executions(
+        &mut self,
+        script: &ScriptObject,
+        position: Position,
+        instruction: &Instruction,
+        count: usize,
+    ) {
+        self.stats.steps += count;
+        *self
+            .stats
+            .instructions
+            .entry(instruction.name())
+            .or_insert(0) += count;
+        let executions = &mut self.stats.executions;
+        if executions.len() <= position.block {
+            executions.resize(position.block + 1, vec![]);
+        }
+        let block_executions = &mut executions[position.block];
+        if block_executions.len() <= position.instruction {
+            block_executions.resize(position.instruction + 1, 0);
+        }
+        block_executions[position.instruction] += count;
+
+        // The tiles of direct addresses are known without executing the instruction
+        let (reads, writes) = tile_accesses(instruction);
+        match instruction.memory_address() {
+            Some(ValueBoxMemoryAddress::Pointer(address)) => {
+                self.record_tile_accesses(*address, reads * count, writes * count)
             }
-            Instruction::CopyTo(vbma) => {
-                let head_value = self.head.ok_or(ExecuteInstructionError::CopyToHeadNone)?;
+            Some(ValueBoxMemoryAddress::PointerAddress(pointer)) if reads + writes > 0 => {
+                self.record_tile_accesses(*pointer, count, 0)
+            }
+            _ => {}
+        }
 
-                self.memory
-                    .set_with_vbma(vbma, Some(head_value))
-                    .map_err(ExecuteInstructionError::CopyToInvalidAddress)?;
+        if position.instruction == 0 {
+            let label = script.get_block_by_index(position.block).unwrap().name();
+            match self.stats.blocks.get_mut(label) {
+                Some(hits) => *hits += count,
+                None => {
+                    self.stats.blocks.insert(label.to_string(), count);
+                }
             }
+        }
+    }
 
-            Instruction::Add(vbma) => {
-                let mem_value = self
-                    .memory
-                    .get_with_vbma(vbma)
-                    .map_err(ExecuteInstructionError::AddInvalidAddress)?;
-                let head_value = &self.head.ok_or(ExecuteInstructionError::AddHeadNone)?;
+    /// Count the accesses to the tile an instruction is about to use, when it's only known
+    /// during the execution: the tile behind a pointer, or the top of the stack
+    fn record_dynamic_tile_accesses(&mut self, instruction: &Instruction) {
+        if let Some((address, reads, writes)) = self.dynamic_tile_access(instruction) {
+            self.record_tile_accesses(address, reads, writes);
+        }
+    }
+
+    /// The tile an instruction is about to use when it's only known during the execution,
+    /// with the number of reads and writes of the instruction on it
+    fn dynamic_tile_access(&self, instruction: &Instruction) -> Option<(usize, usize, usize)> {
+        let address = match instruction.memory_address() {
+            Some(vbma @ ValueBoxMemoryAddress::PointerAddress(_)) => {
+                self.memory.translate_vbma_to_mem_address(vbma).ok()
+            }
+            Some(_) => None,
+            None if matches!(instruction, Instruction::Push | Instruction::Pop) => {
+                self.stack_tile(instruction)
+            }
+            None => None,
+        }?;
+        let (reads, writes) = tile_accesses(instruction);
+        Some((address, reads, writes))
+    }
+
+    fn record_tile_accesses(&mut self, address: usize, reads: usize, writes: usize) {
+        if reads + writes == 0 {
+            return;
+        }
+        let accesses = self.stats.tiles.entry(address).or_default();
+        accesses.reads += reads;
+        accesses.writes += writes;
+    }
+}
+
+/// The number of reads and writes an instruction makes on its tile
+fn tile_accesses(instruction: &Instruction) -> (usize, usize) {
+    match instruction {
+        Instruction::CopyTo(_) | Instruction::Push => (0, 1),
+        Instruction::BumpUp(_) | Instruction::BumpDown(_) | Instruction::Swap(_) => (1, 1),
+        Instruction::Debug(_) => (0, 0),
+        _ => (1, 0),
+    }
+}
+
+// ==================== History ====================
+
+impl Interpreter {
+    fn save_snapshot(&mut self, position: Position, instruction: &Instruction) {
+        let tile = instruction
+            .memory_address()
+            .and_then(|vbma| self.memory.translate_vbma_to_mem_address(vbma).ok())
+            .or_else(|| self.stack_tile(instruction))
+            .map(|address| (address, self.memory.get(&address).copied()));
+        self.history.push(Snapshot {
+            position,
+            head: self.head,
+            second_hand: self.second_hand,
+            next_input: self.next_input,
+            outputs_len: self.outputs_count,
+            tile,
+            calls: matches!(instruction, Instruction::Call(_) | Instruction::Ret)
+                .then(|| self.calls.clone()),
+            instruction: instruction.name(),
+        });
+    }
+
+    /// Number of executed instructions that can be rewound
+    pub fn rewindable_steps(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Undo the last `n_steps` executed instructions (as far as the history goes),
+    /// restoring the head, the memory, the inputs, the outputs and the position.
+    /// The step count and the instruction counts are rewound too,
+    /// but not the block hits and the tile accesses.
+    /// Returns the number of instructions actually rewound.
+    pub fn rewind(&mut self, n_steps: usize, mut outputs: impl OutputSink) -> usize {
+        for rewound in 0..n_steps {
+            let snapshot = match self.history.pop() {
+                Some(snapshot) => snapshot,
+                None => return rewound,
+            };
+
+            self.position = snapshot.position;
+            self.head = snapshot.head;
+            self.second_hand = snapshot.second_hand;
+            self.next_input = snapshot.next_input;
+            self.outputs_count = snapshot.outputs_len;
+            outputs.rewind_to(snapshot.outputs_len);
+            if let Some((address, value)) = snapshot.tile {
+                // The tile was valid when it was saved
+                self.memory.set(&address, value).unwrap();
+            }
+            if let Some(calls) = snapshot.calls {
+                self.calls = calls;
+            }
+
+            self.stats.steps -= 1;
+            if let Some(count) = self.stats.instructions.get_mut(snapshot.instruction) {
+                *count -= 1;
+                if *count == 0 {
+                    self.stats.instructions.remove(snapshot.instruction);
+                }
+            }
+        }
+        // Don't pause again on a breakpoint at the restored position
+        self.resuming = true;
+        n_steps
+    }
+}
+
+// ==================== Trace ====================
+
+impl Interpreter {
+    /// The head, and the tile accessed by the instruction (with its value), before executing it
+    fn trace_state(&self, instruction: &Instruction) -> (Option<ValueBox>, Option<TileAccess>) {
+        let tile = instruction
+            .memory_address()
+            .and_then(|vbma| self.memory.translate_vbma_to_mem_address(vbma).ok())
+            .or_else(|| self.stack_tile(instruction))
+            .map(|address| TileAccess {
+                address,
+                before: self.memory.get(&address).copied(),
+                after: None,
+            });
+        (self.head, tile)
+    }
+
+    fn trace(
+        &mut self,
+        script: &ScriptObject,
+        position: Position,
+        instruction: &Instruction,
+        (head_before, tile): (Option<ValueBox>, Option<TileAccess>),
+    ) {
+        let entry = TraceEntry {
+            step: self.stats.steps,
+            position,
+            line: script.get_line(position).unwrap_or_default(),
+            block: script
+                .get_block_by_index(position.block)
+                .map(|block| block.name().to_string())
+                .unwrap_or_default(),
+            instruction: instruction.to_string(),
+            head_before,
+            head_after: self.head,
+            tile: tile.map(|tile| TileAccess {
+                after: self.memory.get(&tile.address).copied(),
+                ..tile
+            }),
+        };
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer(&entry);
+        }
+    }
+}
+
+// ==================== Breakpoints ====================
+
+impl Interpreter {
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+    }
+
+    /// Remove the given breakpoint. Returns false if it wasn't registered.
+    pub fn remove_breakpoint(&mut self, breakpoint: &Breakpoint) -> bool {
+        let len_before = self.breakpoints.len();
+        self.breakpoints.retain(|b| b != breakpoint);
+        self.breakpoints.len() != len_before
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+}
+
+// ==================== Watchpoints ====================
+
+impl Interpreter {
+    pub fn add_watchpoint(&mut self, address: usize) {
+        if !self.watchpoints.contains(&address) {
+            self.watchpoints.push(address);
+        }
+    }
+
+    /// Remove the watchpoint on the given tile. Returns false if it wasn't registered.
+    pub fn remove_watchpoint(&mut self, address: usize) -> bool {
+        let len_before = self.watchpoints.len();
+        self.watchpoints.retain(|a| *a != address);
+        self.watchpoints.len() != len_before
+    }
+
+    pub fn watchpoints(&self) -> &[usize] {
+        &self.watchpoints
+    }
+
+    /// The accesses to watched tiles made by the last executed instruction
+    pub fn watchpoint_hits(&self) -> &[WatchpointHit] {
+        &self.watchpoint_hits
+    }
+
+    /// Register a function called each time a watched tile is accessed,
+    /// including when executing the script with `execute`.
+    pub fn set_watch_callback(&mut self, callback: impl FnMut(&WatchpointHit) + Send + 'static) {
+        self.watch_callback = Some(Box::new(callback));
+    }
+
+    /// The watched tiles the instruction is about to access, with their current values
+    fn watched_tiles(
+        &self,
+        instruction: &Instruction,
+    ) -> Vec<(usize, TileAccessKind, Option<ValueBox>)> {
+        let vbma = match instruction.memory_address() {
+            Some(vbma) if !self.watchpoints.is_empty() => vbma,
+            _ => return vec![],
+        };
+        let mut tiles = vec![];
+
+        // The tile holding the pointer is read
+        if let ValueBoxMemoryAddress::PointerAddress(pointer) = vbma {
+            tiles.push((*pointer, TileAccessKind::Read));
+        }
+        if let Ok(address) = self.memory.translate_vbma_to_mem_address(vbma) {
+            let access = match instruction {
+                Instruction::CopyTo(_)
+                | Instruction::BumpUp(_)
+                | Instruction::BumpDown(_)
+                | Instruction::Swap(_) => TileAccessKind::Write,
+                _ => TileAccessKind::Read,
+            };
+            tiles.push((address, access));
+        }
+
+        tiles
+            .into_iter()
+            .filter(|(address, _)| self.watchpoints.contains(address))
+            .map(|(address, access)| (address, access, self.memory.get(&address).copied()))
+            .collect()
+    }
+
+    fn record_watchpoint_hits(
+        &mut self,
+        position: Position,
+        instruction: &Instruction,
+        watched_tiles: Vec<(usize, TileAccessKind, Option<ValueBox>)>,
+    ) {
+        for (address, access, before) in watched_tiles {
+            let hit = WatchpointHit {
+                address,
+                access,
+                before,
+                after: self.memory.get(&address).copied(),
+                position,
+                instruction: instruction.to_string(),
+            };
+            if let Some(callback) = self.watch_callback.as_mut() {
+                callback(&hit);
+            }
+            self.watchpoint_hits.push(hit);
+        }
+    }
+}
+
+// ==================== Observers ====================
+
+impl Interpreter {
+    /// Register an observer, notified of what happens during the execution
+    /// (with `execute`, `run` or `step`)
+    pub fn add_observer(&mut self, observer: impl Observer + Send + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// The tile the instruction is about to write, with its current value
+    fn written_tile(&self, instruction: &Instruction) -> Option<(usize, Option<ValueBox>)> {
+        match instruction {
+            Instruction::CopyTo(vbma)
+            | Instruction::BumpUp(vbma)
+            | Instruction::BumpDown(vbma)
+            | Instruction::Swap(vbma) => {
+                let address = self.memory.translate_vbma_to_mem_address(vbma).ok()?;
+                Some((address, self.memory.get(&address).copied()))
+            }
+            Instruction::Push | Instruction::Pop => {
+                let address = self.stack_tile(instruction)?;
+                Some((address, self.memory.get(&address).copied()))
+            }
+            _ => None,
+        }
+    }
+
+    fn notify_observers(
+        &mut self,
+        position: Position,
+        instruction: &Instruction,
+        jumped: bool,
+        (head_before, written_tile): (Option<ValueBox>, Option<(usize, Option<ValueBox>)>),
+    ) {
+        let step = self.stats.steps;
+
+        if let Some((address, before)) = written_tile {
+            let event = MemoryWriteEvent {
+                step,
+                position,
+                address,
+                before,
+                after: self.memory.get(&address).copied(),
+            };
+            for observer in &mut self.observers {
+                observer.on_memory_write(&event);
+            }
+        }
+        if let (Instruction::Out, Some(value)) = (instruction, self.head) {
+            let event = OutputEvent {
+                step,
+                position,
+                index: self.outputs_count - 1,
+                value,
+            };
+            for observer in &mut self.observers {
+                observer.on_output(&event);
+            }
+        }
+        if let (true, Some(target)) = (jumped, instruction.jump_target()) {
+            let event = JumpEvent {
+                step,
+                from: position,
+                to: self.position,
+                label: target.label(),
+            };
+            for observer in &mut self.observers {
+                observer.on_jump(&event);
+            }
+        }
+
+        let event = StepEvent {
+            step,
+            position,
+            instruction,
+            head_before,
+            head_after: self.head,
+        };
+        for observer in &mut self.observers {
+            observer.on_step(&event);
+        }
+    }
+}
+
+// ==================== Instruction execution ====================
+
+#[derive(Debug, PartialEq)]
+/// All the possible things that can happen after executing an instruction
+enum InstructionResult<'a> {
+    /// A jump instruction was executed
+    JumpBlock(&'a JumpTarget),
+    /// The instruction was successfully executed, read the next one
+    NextInstruction,
+    /// A RET was executed: go back after the CALL
+    Return(Position),
+    /// The program has terminated.
+    /// (Can happen if an INBOX instruction is executed with no more inputs to read)
+    Terminate,
+}
+
+#[derive(Debug, thiserror::Error)]
+/// All errors that can occur when executing an instruction
+/// Errors are voluntarily redundant from one instruction type to another,
+/// to make it easier to understand what went wrong.
+pub enum ExecuteInstructionError {
+    #[error("cannot output: head empty")]
+    OutputNone,
+
+    #[error("cannot copy from:\n\t{0}")]
+    CopyFromInvalidAddress(#[source] memory::GetMemoryError),
+    #[error("cannot copy to:\n\t{0}")]
+    CopyToInvalidAddress(#[source] memory::SetMemoryError),
+    #[error("cannot copy to: head empty")]
+    CopyToHeadNone,
+
+    #[error("cannot read memory value from VBMA:\n\t{0}")]
+    AddInvalidAddress(#[source] memory::GetMemoryError),
+    #[error("cannot add: empty head")]
+    AddHeadNone,
+    #[error("cannot add characters (head: {head} and mem: {mem} at address {address})")]
+    AddCharacters {
+        head: char,
+        mem: char,
+        address: usize,
+    },
+    #[error("cannot add characters and numbers together (head: {head:?} and mem: {mem:?} at address {address})")]
+    AddCharacterAndNumber {
+        head: ValueBox,
+        mem: ValueBox,
+        address: usize,
+    },
+
+    #[error("cannot read memory value from VBMA:\n\t{0}")]
+    SubInvalidAddress(#[source] memory::GetMemoryError),
+    #[error("cannot subtract: empty head")]
+    SubHeadNone,
+    #[error("cannot subtract characters and numbers together (head: {head:?} and mem: {mem:?} at address {address})")]
+    SubCharacterAndNumber {
+        head: ValueBox,
+        mem: ValueBox,
+        address: usize,
+    },
+
+    #[error("cannot read memory value from VBMA:\n\t{0}")]
+    MulInvalidAddress(#[source] memory::GetMemoryError),
+    #[error("cannot multiply: empty head")]
+    MulHeadNone,
+    #[error("cannot multiply characters (head: {head:?} and mem: {mem:?} at address {address})")]
+    MulCharacter {
+        head: ValueBox,
+        mem: ValueBox,
+        address: usize,
+    },
+
+    #[error("cannot read memory value from VBMA:\n\t{0}")]
+    DivInvalidAddress(#[source] memory::GetMemoryError),
+    #[error("cannot divide: empty head")]
+    DivHeadNone,
+    #[error("cannot divide characters (head: {head:?} and mem: {mem:?} at address {address})")]
+    DivCharacter {
+        head: ValueBox,
+        mem: ValueBox,
+        address: usize,
+    },
+    #[error("cannot divide by zero (mem at address {address})")]
+    DivByZero { address: usize },
+
+    #[error("cannot read memory value from VBMA:\n\t{0}")]
+    ModInvalidAddress(#[source] memory::GetMemoryError),
+    #[error("cannot compute the remainder: empty head")]
+    ModHeadNone,
+    #[error("cannot compute the remainder of characters (head: {head:?} and mem: {mem:?} at address {address})")]
+    ModCharacter {
+        head: ValueBox,
+        mem: ValueBox,
+        address: usize,
+    },
+    #[error("cannot compute the remainder of a division by zero (mem at address {address})")]
+    ModByZero { address: usize },
+
+    #[error("cannot read memory value from VBMA:\n\t{0}")]
+    SwapInvalidAddress(#[source] memory::GetMemoryError),
+    #[error("cannot swap: empty head")]
+    SwapHeadNone,
+
+    #[error("cannot push: empty head")]
+    PushHeadNone,
+    #[error("cannot push:\n\t{0}")]
+    PushInvalidAddress(#[source] memory::SetMemoryError),
+    #[error("cannot push: the stack is full ({size} values)")]
+    StackOverflow { size: usize },
+    #[error("cannot pop: the stack is empty")]
+    StackUnderflow,
+    #[error("cannot {0}: no tiles are given to the stack")]
+    NoStack(&'static str),
+
+    #[error("cannot call: more than {0} CALLs in progress")]
+    CallStackOverflow(usize),
+    #[error("cannot return: no CALL in progress")]
+    RetWithoutCall,
+
+    #[error("cannot {0}: the worker has no second hand")]
+    NoSecondHand(&'static str),
+    #[error("cannot copy to the second hand: empty head")]
+    TempCopyToHeadNone,
+    #[error("cannot copy from the second hand: it's empty")]
+    TempCopyFromEmpty,
+
+    #[error("cannot test IfZero if head ({0:?}) is not a valid number")]
+    JumpIfZeroInvalidHead(Option<ValueBox>),
+    #[error("cannot test IfNegative if head ({0:?}) is not a valid number")]
+    JumpIfNegativeInvalidHead(Option<ValueBox>),
+    #[error("cannot test IfPositive if head ({0:?}) is not a valid number")]
+    JumpIfPositiveInvalidHead(Option<ValueBox>),
+    #[error("cannot read memory value from VBMA:\n\t{0}")]
+    JumpIfEqualInvalidAddress(#[source] memory::GetMemoryError),
+    #[error("cannot test IfEqual: empty head")]
+    JumpIfEqualHeadNone,
+
+    #[error("assertion failed: ASSERTZ with head {}", describe(.0))]
+    AssertZeroFailed(Option<ValueBox>),
+    #[error("assertion failed: ASSERTN with head {}", describe(.0))]
+    AssertNegativeFailed(Option<ValueBox>),
+    #[error("assertion failed: tile {address} holds {}, expected {expected}", describe(.actual))]
+    AssertEqualFailed {
+        address: usize,
+        expected: ValueBox,
+        actual: Option<ValueBox>,
+    },
+    #[error("cannot assert on memory value from VBMA:\n\t{0}")]
+    AssertEqualInvalidAddress(#[source] memory::ReadValueBoxMemoryAddressError),
+
+    #[error("cannot bump memory value from VBMA:\n\t{0}")]
+    BumpInvalidAddress(#[source] memory::GetMemoryError),
+    #[error("cannot bump a character")]
+    BumpCharacter,
+
+    #[error(
+        "overflow: {0} is out of the game's bounds [{}, {}]",
+        ValueBox::MIN_NUMBER,
+        ValueBox::MAX_NUMBER
+    )]
+    Overflow(i32),
+    #[error("cannot {operation}: {head} and {mem} give a number that doesn't fit in 32 bits")]
+    ArithmeticOverflow {
+        operation: &'static str,
+        head: i32,
+        mem: i32,
+    },
+}
+
+/// The value as in the messages: the value itself, or "empty"
+fn describe(value: &Option<ValueBox>) -> String {
+    value.map_or("empty".to_string(), |value| value.to_string())
+}
+
+impl ExecuteInstructionError {
+    /// A stable identifier of the kind of error, for tools
+    pub fn code(&self) -> &'static str {
+        match self {
+            ExecuteInstructionError::OutputNone => "output-empty-head",
+            ExecuteInstructionError::CopyFromInvalidAddress(_) => "copyfrom-invalid-address",
+            ExecuteInstructionError::CopyToInvalidAddress(_) => "copyto-invalid-address",
+            ExecuteInstructionError::CopyToHeadNone => "copyto-empty-head",
+            ExecuteInstructionError::AddInvalidAddress(_) => "add-invalid-address",
+            ExecuteInstructionError::AddHeadNone => "add-empty-head",
+            ExecuteInstructionError::AddCharacters { .. } => "add-characters",
+            ExecuteInstructionError::AddCharacterAndNumber { .. } => "add-character-and-number",
+            ExecuteInstructionError::SubInvalidAddress(_) => "sub-invalid-address",
+            ExecuteInstructionError::SubHeadNone => "sub-empty-head",
+            ExecuteInstructionError::SubCharacterAndNumber { .. } => "sub-character-and-number",
+            ExecuteInstructionError::MulInvalidAddress(_) => "mul-invalid-address",
+            ExecuteInstructionError::MulHeadNone => "mul-empty-head",
+            ExecuteInstructionError::MulCharacter { .. } => "mul-character",
+            ExecuteInstructionError::DivInvalidAddress(_) => "div-invalid-address",
+            ExecuteInstructionError::DivHeadNone => "div-empty-head",
+            ExecuteInstructionError::DivCharacter { .. } => "div-character",
+            ExecuteInstructionError::DivByZero { .. } => "div-by-zero",
+            ExecuteInstructionError::ModInvalidAddress(_) => "mod-invalid-address",
+            ExecuteInstructionError::ModHeadNone => "mod-empty-head",
+            ExecuteInstructionError::ModCharacter { .. } => "mod-character",
+            ExecuteInstructionError::ModByZero { .. } => "mod-by-zero",
+            ExecuteInstructionError::SwapInvalidAddress(_) => "swap-invalid-address",
+            ExecuteInstructionError::SwapHeadNone => "swap-empty-head",
+            ExecuteInstructionError::PushHeadNone => "push-empty-head",
+            ExecuteInstructionError::PushInvalidAddress(_) => "push-invalid-address",
+            ExecuteInstructionError::StackOverflow { .. } => "stack-overflow",
+            ExecuteInstructionError::StackUnderflow => "stack-underflow",
+            ExecuteInstructionError::NoStack(_) => "no-stack",
+            ExecuteInstructionError::CallStackOverflow(_) => "call-stack-overflow",
+            ExecuteInstructionError::RetWithoutCall => "ret-without-call",
+            ExecuteInstructionError::NoSecondHand(_) => "no-second-hand",
+            ExecuteInstructionError::TempCopyToHeadNone => "tcopyto-empty-head",
+            ExecuteInstructionError::TempCopyFromEmpty => "tcopyfrom-empty-hand",
+            ExecuteInstructionError::JumpIfZeroInvalidHead(_) => "jumpz-invalid-head",
+            ExecuteInstructionError::JumpIfNegativeInvalidHead(_) => "jumpn-invalid-head",
+            ExecuteInstructionError::JumpIfPositiveInvalidHead(_) => "jumpp-invalid-head",
+            ExecuteInstructionError::JumpIfEqualInvalidAddress(_) => "jumpe-invalid-address",
+            ExecuteInstructionError::JumpIfEqualHeadNone => "jumpe-empty-head",
+            ExecuteInstructionError::AssertZeroFailed(_) => "assertz-failed",
+            ExecuteInstructionError::AssertNegativeFailed(_) => "assertn-failed",
+            ExecuteInstructionError::AssertEqualFailed { .. } => "assert-failed",
+            ExecuteInstructionError::AssertEqualInvalidAddress(_) => "assert-invalid-address",
+            ExecuteInstructionError::BumpInvalidAddress(_) => "bump-invalid-address",
+            ExecuteInstructionError::BumpCharacter => "bump-character",
+            ExecuteInstructionError::Overflow(_) => "overflow",
+            ExecuteInstructionError::ArithmeticOverflow { .. } => "arithmetic-overflow",
+        }
+    }
+}
+
+impl Interpreter {
+    /// Execute 1 instruction
+    /// using one big match to handle all the possible instructions
+    fn execute_instruction<'a>(
+        &mut self,
+        instruction: &'a Instruction,
+        mut inputs: impl InputSource,
+        mut outputs: impl OutputSink,
+    ) -> Result<InstructionResult<'a>, ExecuteInstructionError> {
+        match instruction {
+            Instruction::In => {
+                match inputs.input(self.next_input) {
+                    Some(value) => {
+                        self.next_input += 1;
+                        self.head = Some(value);
+                    }
+                    // No more inputs => terminate program
+                    None => {
+                        return Ok(InstructionResult::Terminate);
+                    }
+                }
+            }
+            Instruction::Out => match &self.head {
+                Some(value) => {
+                    self.outputs_count += 1;
+                    outputs.output(*value);
+                }
+                None => return Err(ExecuteInstructionError::OutputNone),
+            },
+            Instruction::CopyFrom(vbma) => {
+                let value = self
+                    .memory
+                    .get_with_vbma(vbma)
+                    .map_err(ExecuteInstructionError::CopyFromInvalidAddress)?;
+                self.head = Some(*value);
+            }
+            Instruction::CopyTo(_) if self.head.is_none() => {
+                return Err(ExecuteInstructionError::CopyToHeadNone);
+            }
+            Instruction::CopyTo(vbma) => {
+                let head_value = self.head.ok_or(ExecuteInstructionError::CopyToHeadNone)?;
+
+                self.memory
+                    .set_with_vbma(vbma, Some(head_value))
+                    .map_err(ExecuteInstructionError::CopyToInvalidAddress)?;
+            }
+
+            Instruction::Add(vbma) => {
+                let mem_value = self
+                    .memory
+                    .get_with_vbma(vbma)
+                    .map_err(ExecuteInstructionError::AddInvalidAddress)?;
+                let head_value = &self.head.ok_or(ExecuteInstructionError::AddHeadNone)?;
 
                 match (head_value, mem_value) {
                     (ValueBox::Number(h), ValueBox::Number(m)) => {
-                        self.head = Some(ValueBox::from(h + m))
+                        let sum = h.checked_add(*m).ok_or(
+                            ExecuteInstructionError::ArithmeticOverflow {
+                                operation: "add",
+                                head: *h,
+                                mem: *m,
+                            },
+                        )?;
+                        self.head = Some(ValueBox::from(self.check_bounds(sum)?))
                     }
                     (ValueBox::Character(char_head), ValueBox::Character(char_mem)) => {
                         return Err(ExecuteInstructionError::AddCharacters {
@@ -330,18 +1512,18 @@ impl Interpreter {
 
                 match (head_value, mem_value) {
                     (ValueBox::Number(h), ValueBox::Number(m)) => {
-                        self.head = Some(ValueBox::from(h - m))
+                        let difference = h.checked_sub(*m).ok_or(
+                            ExecuteInstructionError::ArithmeticOverflow {
+                                operation: "subtract",
+                                head: *h,
+                                mem: *m,
+                            },
+                        )?;
+                        self.head = Some(ValueBox::from(self.check_bounds(difference)?))
                     }
                     (ValueBox::Character(h), ValueBox::Character(m)) => {
                         // Special case: in HRM, we CAN subtract characters together
-                        // The result is the distance between the two characters in the alphabet (an integer)
-                        let get_alphabetic_index = |c: &char| -> i8 {
-                            let c = c.to_ascii_uppercase();
-                            c as i8 - 'A' as i8
-                        };
-                        let h = get_alphabetic_index(h);
-                        let m = get_alphabetic_index(m);
-                        let result = (h - m) as i32;
+                        let result = ValueBox::alphabet_index(*h) - ValueBox::alphabet_index(*m);
                         self.head = Some(ValueBox::from(result));
                     }
                     _ => {
@@ -354,38 +1536,299 @@ impl Interpreter {
                 }
             }
 
-            Instruction::BumpUp(vbma) => self.bump_mem_value(vbma, true)?,
-            Instruction::BumpDown(vbma) => self.bump_mem_value(vbma, false)?,
+            Instruction::Mul(vbma) => {
+                let mem_value = self
+                    .memory
+                    .get_with_vbma(vbma)
+                    .map_err(ExecuteInstructionError::MulInvalidAddress)?;
+                let head_value = self.head.ok_or(ExecuteInstructionError::MulHeadNone)?;
 
-            Instruction::Jump(block_key) => {
-                return Ok(InstructionResult::JumpBlock(block_key.clone()))
-            }
-            Instruction::JumpIfZero(block_key) => match self.head {
-                Some(ValueBox::Number(0)) => {
-                    return Ok(InstructionResult::JumpBlock(block_key.clone()));
-                }
-                Some(ValueBox::Character(_)) => {} // Characters are never equal to 0
-                Some(ValueBox::Number(_)) => {}    // Number != 0 => do nothing
-                _ => {
-                    return Err(ExecuteInstructionError::JumpIfZeroInvalidHead(self.head));
-                }
-            },
-            Instruction::JumpIfNegative(block_key) => match self.head {
-                Some(ValueBox::Number(n)) if n < 0 => {
-                    return Ok(InstructionResult::JumpBlock(block_key.clone()));
+                match (head_value, mem_value) {
+                    (ValueBox::Number(h), ValueBox::Number(m)) => {
+                        let product = h.checked_mul(*m).ok_or(
+                            ExecuteInstructionError::ArithmeticOverflow {
+                                operation: "multiply",
+                                head: h,
+                                mem: *m,
+                            },
+                        )?;
+                        self.head = Some(ValueBox::from(self.check_bounds(product)?));
+                    }
+                    _ => {
+                        return Err(ExecuteInstructionError::MulCharacter {
+                            head: head_value,
+                            mem: *mem_value,
+                            address: self.memory.translate_vbma_to_mem_address(vbma).unwrap(),
+                        });
+                    }
+                }
+            }
+            Instruction::Div(vbma) => {
+                let mem_value = self
+                    .memory
+                    .get_with_vbma(vbma)
+                    .map_err(ExecuteInstructionError::DivInvalidAddress)?;
+                let head_value = self.head.ok_or(ExecuteInstructionError::DivHeadNone)?;
+                let address = self.memory.translate_vbma_to_mem_address(vbma).unwrap();
+
+                match (head_value, mem_value) {
+                    (ValueBox::Number(_), ValueBox::Number(0)) => {
+                        return Err(ExecuteInstructionError::DivByZero { address });
+                    }
+                    (ValueBox::Number(h), ValueBox::Number(m)) => {
+                        // Only i32::MIN / -1 overflows
+                        let quotient = h.checked_div(*m).ok_or(
+                            ExecuteInstructionError::ArithmeticOverflow {
+                                operation: "divide",
+                                head: h,
+                                mem: *m,
+                            },
+                        )?;
+                        self.head = Some(ValueBox::from(self.check_bounds(quotient)?));
+                    }
+                    _ => {
+                        return Err(ExecuteInstructionError::DivCharacter {
+                            head: head_value,
+                            mem: *mem_value,
+                            address,
+                        });
+                    }
+                }
+            }
+            Instruction::Mod(vbma) => {
+                let mem_value = self
+                    .memory
+                    .get_with_vbma(vbma)
+                    .map_err(ExecuteInstructionError::ModInvalidAddress)?;
+                let head_value = self.head.ok_or(ExecuteInstructionError::ModHeadNone)?;
+                let address = self.memory.translate_vbma_to_mem_address(vbma).unwrap();
+
+                match (head_value, mem_value) {
+                    (ValueBox::Number(_), ValueBox::Number(0)) => {
+                        return Err(ExecuteInstructionError::ModByZero { address });
+                    }
+                    (ValueBox::Number(h), ValueBox::Number(m)) => {
+                        // i32::MIN % -1 is 0, but overflows in Rust
+                        self.head = Some(ValueBox::from(h.checked_rem(*m).unwrap_or(0)));
+                    }
+                    _ => {
+                        return Err(ExecuteInstructionError::ModCharacter {
+                            head: head_value,
+                            mem: *mem_value,
+                            address,
+                        });
+                    }
+                }
+            }
+
+            Instruction::Swap(vbma) => {
+                let mem_value = *self
+                    .memory
+                    .get_with_vbma(vbma)
+                    .map_err(ExecuteInstructionError::SwapInvalidAddress)?;
+                let head_value = self.head.ok_or(ExecuteInstructionError::SwapHeadNone)?;
+
+                // The tile was just read, so its address is valid
+                let address = self.memory.translate_vbma_to_mem_address(vbma).unwrap();
+                self.memory.set(&address, Some(head_value)).unwrap();
+                self.head = Some(mem_value);
+            }
+
+            Instruction::BumpUp(vbma) => self.bump_mem_value(vbma, true)?,
+            Instruction::BumpDown(vbma) => self.bump_mem_value(vbma, false)?,
+
+            Instruction::Jump(block_key) => return Ok(InstructionResult::JumpBlock(block_key)),
+            Instruction::JumpIfZero(block_key) => match self.head {
+                Some(head) if head.is_zero() => {
+                    return Ok(InstructionResult::JumpBlock(block_key));
                 }
-                Some(ValueBox::Character(_)) => {} // Characters are never negative
-                Some(ValueBox::Number(_)) => {}    // Number >= 0 => do nothing
+                Some(_) => {}
+                _ => {
+                    return Err(ExecuteInstructionError::JumpIfZeroInvalidHead(self.head));
+                }
+            },
+            Instruction::JumpIfNegative(block_key) => match self.head {
+                Some(head) if head.is_negative() => {
+                    return Ok(InstructionResult::JumpBlock(block_key));
+                }
+                Some(_) => {}
                 _ => {
                     return Err(ExecuteInstructionError::JumpIfNegativeInvalidHead(
                         self.head,
                     ));
                 }
             },
+            Instruction::JumpIfPositive(block_key) => match self.head {
+                Some(head) if head.is_positive() => {
+                    return Ok(InstructionResult::JumpBlock(block_key));
+                }
+                Some(_) => {}
+                _ => {
+                    return Err(ExecuteInstructionError::JumpIfPositiveInvalidHead(
+                        self.head,
+                    ));
+                }
+            },
+            Instruction::JumpIfEqual(vbma, block_key) => {
+                let mem_value = self
+                    .memory
+                    .get_with_vbma(vbma)
+                    .map_err(ExecuteInstructionError::JumpIfEqualInvalidAddress)?;
+                let head_value = self
+                    .head
+                    .ok_or(ExecuteInstructionError::JumpIfEqualHeadNone)?;
+
+                // A character is never equal to a number
+                if head_value == *mem_value {
+                    return Ok(InstructionResult::JumpBlock(block_key));
+                }
+            }
+
+            Instruction::Push => {
+                let head_value = self.head.ok_or(ExecuteInstructionError::PushHeadNone)?;
+                let stack = self
+                    .settings
+                    .stack
+                    .as_ref()
+                    .ok_or(ExecuteInstructionError::NoStack("push"))?;
+                let address = self
+                    .stack_tile(instruction)
+                    .ok_or(ExecuteInstructionError::StackOverflow { size: stack.len() })?;
+                self.memory
+                    .set(&address, Some(head_value))
+                    .map_err(ExecuteInstructionError::PushInvalidAddress)?;
+            }
+            Instruction::Pop => {
+                if self.settings.stack.is_none() {
+                    return Err(ExecuteInstructionError::NoStack("pop"));
+                }
+                let address = self
+                    .stack_tile(instruction)
+                    .ok_or(ExecuteInstructionError::StackUnderflow)?;
+                self.head = self.memory.get(&address).copied();
+                // The tile holds a value, so its address is valid
+                self.memory.set(&address, None).unwrap();
+            }
+
+            Instruction::Call(block_key) => {
+                if self.calls.len() >= MAX_CALL_DEPTH {
+                    return Err(ExecuteInstructionError::CallStackOverflow(MAX_CALL_DEPTH));
+                }
+                self.calls.push(Position {
+                    instruction: self.position.instruction + 1,
+                    ..self.position
+                });
+                return Ok(InstructionResult::JumpBlock(block_key));
+            }
+            Instruction::Ret => {
+                let position = self
+                    .calls
+                    .pop()
+                    .ok_or(ExecuteInstructionError::RetWithoutCall)?;
+                return Ok(InstructionResult::Return(position));
+            }
+
+            Instruction::TempCopyTo | Instruction::TempCopyFrom | Instruction::Exchange
+                if !self.settings.second_hand =>
+            {
+                return Err(ExecuteInstructionError::NoSecondHand(instruction.name()));
+            }
+            Instruction::TempCopyTo => {
+                let head_value = self
+                    .head
+                    .ok_or(ExecuteInstructionError::TempCopyToHeadNone)?;
+                self.second_hand = Some(head_value);
+            }
+            Instruction::TempCopyFrom => {
+                let value = self
+                    .second_hand
+                    .ok_or(ExecuteInstructionError::TempCopyFromEmpty)?;
+                self.head = Some(value);
+            }
+            // Either hand can be empty
+            Instruction::Exchange => std::mem::swap(&mut self.head, &mut self.second_hand),
+
+            // The game has nothing like it
+            Instruction::Debug(_) if self.settings.strict_hrm => {}
+            Instruction::Debug(operand) => {
+                if self.debug_callback.is_some() {
+                    let text = self.debug_text(operand);
+                    if let Some(callback) = &mut self.debug_callback {
+                        callback(self.position, &text);
+                    }
+                }
+            }
+
+            Instruction::AssertZero => {
+                if self.head != Some(ValueBox::Number(0)) {
+                    return Err(ExecuteInstructionError::AssertZeroFailed(self.head));
+                }
+            }
+            Instruction::AssertNegative => match self.head {
+                Some(ValueBox::Number(h)) if h < 0 => {}
+                _ => return Err(ExecuteInstructionError::AssertNegativeFailed(self.head)),
+            },
+            Instruction::AssertEqual(vbma, expected) => {
+                let address = self
+                    .memory
+                    .translate_vbma_to_mem_address(vbma)
+                    .map_err(ExecuteInstructionError::AssertEqualInvalidAddress)?;
+                // An empty tile fails the assertion instead of being an invalid address
+                let actual = self.memory.get(&address).copied();
+                if actual != Some(*expected) {
+                    return Err(ExecuteInstructionError::AssertEqualFailed {
+                        address,
+                        expected: *expected,
+                        actual,
+                    });
+                }
+            }
+
+            Instruction::Nop => {}
+            Instruction::Halt => return Ok(InstructionResult::Terminate),
         };
         Ok(InstructionResult::NextInstruction)
     }
 
+    /// The number of values on the stack: they're on the first tiles of the stack, without gaps
+    fn stack_len(&self, stack: &Range<usize>) -> usize {
+        stack
+            .clone()
+            .take_while(|address| self.memory.get(address).is_some())
+            .count()
+    }
+
+    /// The tile a PUSH writes or a POP takes its value from.
+    /// None if there's no stack, or if it's full (PUSH) or empty (POP).
+    fn stack_tile(&self, instruction: &Instruction) -> Option<usize> {
+        let stack = self.settings.stack.as_ref()?;
+        let top = stack.start + self.stack_len(stack);
+        match instruction {
+            Instruction::Push => (top < stack.end).then_some(top),
+            Instruction::Pop => (top > stack.start).then(|| top - 1),
+            _ => None,
+        }
+    }
+
+    /// What a DEBUG instruction prints: the message, or the tile and its value
+    fn debug_text(&self, operand: &DebugOperand) -> String {
+        let vbma = match operand {
+            DebugOperand::Message(message) => return message.clone(),
+            DebugOperand::Tile(vbma) => vbma,
+        };
+        let address = match self.memory.translate_vbma_to_mem_address(vbma) {
+            Ok(address) => address,
+            Err(e) => return format!("tile {}: {}", vbma, e),
+        };
+        let value = describe(&self.memory.get(&address).copied());
+        match vbma {
+            ValueBoxMemoryAddress::Pointer(_) => format!("tile {}: {}", address, value),
+            ValueBoxMemoryAddress::PointerAddress(_) => {
+                format!("tile {} ({}): {}", vbma, address, value)
+            }
+        }
+    }
+
     fn bump_mem_value(
         &mut self,
         vbma: &ValueBoxMemoryAddress,
@@ -397,267 +1840,1639 @@ impl Interpreter {
             .map_err(ExecuteInstructionError::BumpInvalidAddress)?;
 
         let new_value = match mem_value {
-            ValueBox::Number(m) if up => m + 1,
-            ValueBox::Number(m) => m - 1,
+            ValueBox::Number(m) if up => m.checked_add(1).ok_or(("bump up", *m)),
+            ValueBox::Number(m) => m.checked_sub(1).ok_or(("bump down", *m)),
             ValueBox::Character(_) => return Err(ExecuteInstructionError::BumpCharacter),
+        }
+        .map_err(
+            |(operation, m)| ExecuteInstructionError::ArithmeticOverflow {
+                operation,
+                head: m,
+                mem: 1,
+            },
+        )?;
+        let new_value = self.check_bounds(new_value)?;
+
+        self.memory
+            .set_with_vbma(vbma, Some(ValueBox::from(new_value)))
+            .unwrap(); // Should never fail because we just read it
+        self.head = Some(ValueBox::from(new_value));
+        Ok(())
+    }
+
+    /// In strict HRM mode, check that the result of an operation is within the game's bounds.
+    fn check_bounds(&self, value: i32) -> Result<i32, ExecuteInstructionError> {
+        if self.settings.strict_hrm
+            && !(ValueBox::MIN_NUMBER..=ValueBox::MAX_NUMBER).contains(&value)
+        {
+            return Err(ExecuteInstructionError::Overflow(value));
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod test_instructions_execution {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::script_object::value_box::ValueBoxMemoryAddress;
+
+    #[test]
+    fn test_inbox() {
+        let mut interpreter = Interpreter {
+            memory: Memory::default(),
+            head: None,
+            next_input: 0,
+            ..Interpreter::new(Memory::default())
+        };
+
+        let result = interpreter.execute_instruction(&Instruction::In, &[], &mut vec![]);
+        assert_eq!(result.unwrap(), InstructionResult::Terminate);
+
+        let result =
+            interpreter.execute_instruction(&Instruction::In, &[ValueBox::from(10)], &mut vec![]);
+        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
+        assert_eq!(interpreter.head, Some(ValueBox::from(10)));
+    }
+
+    #[test]
+    fn test_outbox() {
+        let mut interpreter = Interpreter {
+            memory: Memory::default(),
+            head: Some(ValueBox::from(42)),
+            next_input: 0,
+            ..Interpreter::new(Memory::default())
+        };
+
+        let mut outputs = vec![];
+        let result = interpreter.execute_instruction(&Instruction::Out, &[], &mut outputs);
+        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
+        assert_eq!(outputs, vec![ValueBox::from(42)]);
+    }
+
+    #[test]
+    fn test_copy_from() {
+        let mut interpreter = Interpreter {
+            memory: Memory::with_data(HashMap::from_iter([(0, ValueBox::from(42))]), 10),
+            head: None,
+            next_input: 0,
+            ..Interpreter::new(Memory::default())
+        };
+
+        let result = interpreter.execute_instruction(
+            &Instruction::CopyFrom(ValueBoxMemoryAddress::Pointer(0)),
+            &[],
+            &mut vec![],
+        );
+        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
+        assert_eq!(interpreter.head, Some(ValueBox::from(42)));
+    }
+
+    #[test]
+    fn test_copy_to() {
+        let mut interpreter = Interpreter {
+            memory: Memory::with_data(HashMap::from_iter([(0, ValueBox::from(42))]), 10),
+            head: Some(ValueBox::from(10)),
+            next_input: 0,
+            ..Interpreter::new(Memory::default())
+        };
+
+        let result = interpreter.execute_instruction(
+            &Instruction::CopyTo(ValueBoxMemoryAddress::Pointer(0)),
+            &[],
+            &mut vec![],
+        );
+        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
+        assert_eq!(interpreter.memory.get(&0), Some(&ValueBox::from(10)));
+    }
+
+    #[test]
+    fn test_add() {
+        let mut interpreter = Interpreter {
+            memory: Memory::with_data(HashMap::from_iter([(0, ValueBox::from(42))]), 10),
+            head: Some(ValueBox::from(10)),
+            next_input: 0,
+            ..Interpreter::new(Memory::default())
+        };
+
+        let result = interpreter.execute_instruction(
+            &Instruction::Add(ValueBoxMemoryAddress::Pointer(0)),
+            &[],
+            &mut vec![],
+        );
+        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
+        assert_eq!(interpreter.head, Some(ValueBox::from(52)));
+        assert_eq!(interpreter.memory.get(&0), Some(&ValueBox::from(42)));
+    }
+
+    #[test]
+    fn test_sub() {
+        let mut interpreter = Interpreter {
+            memory: Memory::with_data(HashMap::from_iter([(0, ValueBox::from(42))]), 10),
+            head: Some(ValueBox::from(10)),
+            next_input: 0,
+            ..Interpreter::new(Memory::default())
+        };
+
+        let result = interpreter.execute_instruction(
+            &Instruction::Sub(ValueBoxMemoryAddress::Pointer(0)),
+            &[],
+            &mut vec![],
+        );
+        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
+        assert_eq!(interpreter.head, Some(ValueBox::from(-32)));
+        assert_eq!(interpreter.memory.get(&0), Some(&ValueBox::from(42)));
+    }
+
+    #[test]
+    fn test_sub_characters() {
+        let mut interpreter = Interpreter {
+            memory: Memory::with_data(HashMap::from_iter([(0, ValueBox::from('E'))]), 10),
+            head: Some(ValueBox::from('A')),
+            next_input: 0,
+            ..Interpreter::new(Memory::default())
+        };
+
+        let result = interpreter.execute_instruction(
+            &Instruction::Sub(ValueBoxMemoryAddress::Pointer(0)),
+            &[],
+            &mut vec![],
+        );
+        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
+        assert_eq!(interpreter.head, Some(ValueBox::from(-4)));
+        assert_eq!(interpreter.memory.get(&0), Some(&ValueBox::from('E')));
+    }
+
+    #[test]
+    fn test_bump_up() {
+        let mut interpreter = Interpreter {
+            memory: Memory::with_data(HashMap::from_iter([(0, ValueBox::from(42))]), 10),
+            head: Some(ValueBox::from(10)),
+            next_input: 0,
+            ..Interpreter::new(Memory::default())
+        };
+
+        let result = interpreter.execute_instruction(
+            &Instruction::BumpUp(ValueBoxMemoryAddress::Pointer(0)),
+            &[],
+            &mut vec![],
+        );
+        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
+        assert_eq!(interpreter.head, Some(ValueBox::from(43)));
+        assert_eq!(interpreter.memory.get(&0), Some(&ValueBox::from(43)));
+    }
+
+    #[test]
+    fn test_bump_down() {
+        let mut interpreter = Interpreter {
+            memory: Memory::with_data(HashMap::from_iter([(0, ValueBox::from(42))]), 10),
+            head: Some(ValueBox::from(10)),
+            next_input: 0,
+            ..Interpreter::new(Memory::default())
+        };
+
+        let result = interpreter.execute_instruction(
+            &Instruction::BumpDown(ValueBoxMemoryAddress::Pointer(0)),
+            &[],
+            &mut vec![],
+        );
+        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
+        assert_eq!(interpreter.head, Some(ValueBox::from(41)));
+        assert_eq!(interpreter.memory.get(&0), Some(&ValueBox::from(41)));
+    }
+
+    #[test]
+    fn test_mul_div_mod() {
+        let mut interpreter = Interpreter {
+            memory: Memory::with_data(
+                HashMap::from_iter([
+                    (0, ValueBox::from(-3)),
+                    (1, ValueBox::from(0)),
+                    (2, ValueBox::from('A')),
+                    (4, ValueBox::from(-1)),
+                ]),
+                10,
+            ),
+            head: Some(ValueBox::from(7)),
+            ..Interpreter::new(Memory::default())
+        };
+        let mut execute = |instruction, head: i32| {
+            interpreter.head = Some(ValueBox::from(head));
+            interpreter
+                .execute_instruction(&instruction, &[], &mut vec![])
+                .map(|_| interpreter.head.unwrap())
+        };
+        let tile = ValueBoxMemoryAddress::Pointer;
+
+        assert_eq!(
+            execute(Instruction::Mul(tile(0)), 7).unwrap(),
+            ValueBox::from(-21)
+        );
+        assert_eq!(
+            execute(Instruction::Div(tile(0)), 7).unwrap(),
+            ValueBox::from(-2)
+        );
+        assert_eq!(
+            execute(Instruction::Div(tile(0)), -7).unwrap(),
+            ValueBox::from(2)
+        );
+        assert_eq!(
+            execute(Instruction::Mod(tile(0)), 7).unwrap(),
+            ValueBox::from(1)
+        );
+        assert_eq!(
+            execute(Instruction::Mod(tile(0)), -7).unwrap(),
+            ValueBox::from(-1)
+        );
+
+        assert!(matches!(
+            execute(Instruction::Div(tile(1)), 7),
+            Err(ExecuteInstructionError::DivByZero { address: 1 })
+        ));
+        assert!(matches!(
+            execute(Instruction::Mod(tile(1)), 7),
+            Err(ExecuteInstructionError::ModByZero { address: 1 })
+        ));
+        assert!(matches!(
+            execute(Instruction::Mul(tile(2)), 7),
+            Err(ExecuteInstructionError::MulCharacter { address: 2, .. })
+        ));
+        assert!(matches!(
+            execute(Instruction::Mul(tile(3)), 7),
+            Err(ExecuteInstructionError::MulInvalidAddress(_))
+        ));
+        // Out of the 32 bits, whatever the bounds of the game
+        assert!(matches!(
+            execute(Instruction::Mul(tile(0)), i32::MAX),
+            Err(ExecuteInstructionError::ArithmeticOverflow {
+                operation: "multiply",
+                head: i32::MAX,
+                mem: -3
+            })
+        ));
+        assert!(matches!(
+            execute(Instruction::Div(tile(4)), i32::MIN),
+            Err(ExecuteInstructionError::ArithmeticOverflow { mem: -1, .. })
+        ));
+
+        interpreter.head = None;
+        assert!(matches!(
+            interpreter.execute_instruction(&Instruction::Div(tile(0)), &[], &mut vec![]),
+            Err(ExecuteInstructionError::DivHeadNone)
+        ));
+    }
+
+    #[test]
+    fn test_add_sub_bump_overflow() {
+        let mut interpreter = Interpreter {
+            memory: Memory::with_data(
+                HashMap::from_iter([
+                    (0, ValueBox::from(1)),
+                    (1, ValueBox::from(i32::MAX)),
+                    (2, ValueBox::from(i32::MIN)),
+                ]),
+                10,
+            ),
+            ..Interpreter::new(Memory::default())
+        };
+        let mut execute = |instruction, head: i32| {
+            interpreter.head = Some(ValueBox::from(head));
+            interpreter
+                .execute_instruction(&instruction, &[], &mut vec![])
+                .map(|_| interpreter.head.unwrap())
+        };
+        let tile = ValueBoxMemoryAddress::Pointer;
+
+        assert_eq!(
+            execute(Instruction::Add(tile(0)), i32::MAX - 1).unwrap(),
+            ValueBox::from(i32::MAX)
+        );
+        assert!(matches!(
+            execute(Instruction::Add(tile(0)), i32::MAX),
+            Err(ExecuteInstructionError::ArithmeticOverflow {
+                operation: "add",
+                head: i32::MAX,
+                mem: 1
+            })
+        ));
+        assert!(matches!(
+            execute(Instruction::Sub(tile(0)), i32::MIN),
+            Err(ExecuteInstructionError::ArithmeticOverflow {
+                operation: "subtract",
+                head: i32::MIN,
+                mem: 1
+            })
+        ));
+        assert!(matches!(
+            execute(Instruction::Sub(tile(1)), -2),
+            Err(ExecuteInstructionError::ArithmeticOverflow { .. })
+        ));
+        assert!(matches!(
+            execute(Instruction::BumpUp(tile(1)), 0),
+            Err(ExecuteInstructionError::ArithmeticOverflow {
+                operation: "bump up",
+                head: i32::MAX,
+                mem: 1
+            })
+        ));
+        assert!(matches!(
+            execute(Instruction::BumpDown(tile(2)), 0),
+            Err(ExecuteInstructionError::ArithmeticOverflow {
+                operation: "bump down",
+                head: i32::MIN,
+                mem: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn test_swap() {
+        let mut interpreter = Interpreter {
+            memory: Memory::with_data(
+                HashMap::from_iter([(0, ValueBox::from('A')), (1, ValueBox::from(0))]),
+                10,
+            ),
+            head: Some(ValueBox::from(5)),
+            ..Interpreter::new(Memory::default())
+        };
+
+        let result = interpreter.execute_instruction(
+            &Instruction::Swap(ValueBoxMemoryAddress::PointerAddress(1)),
+            &[],
+            &mut vec![],
+        );
+        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
+        assert_eq!(interpreter.head, Some(ValueBox::from('A')));
+        assert_eq!(interpreter.memory.get(&0), Some(&ValueBox::from(5)));
+
+        let result = interpreter.execute_instruction(
+            &Instruction::Swap(ValueBoxMemoryAddress::Pointer(2)),
+            &[],
+            &mut vec![],
+        );
+        assert!(matches!(
+            result,
+            Err(ExecuteInstructionError::SwapInvalidAddress(_))
+        ));
+
+        interpreter.head = None;
+        let result = interpreter.execute_instruction(
+            &Instruction::Swap(ValueBoxMemoryAddress::Pointer(0)),
+            &[],
+            &mut vec![],
+        );
+        assert!(matches!(result, Err(ExecuteInstructionError::SwapHeadNone)));
+        assert_eq!(interpreter.memory.get(&0), Some(&ValueBox::from(5)));
+    }
+
+    #[test]
+    fn test_overflow_strict_hrm() {
+        let mut interpreter = Interpreter {
+            memory: Memory::with_data(HashMap::from_iter([(0, ValueBox::from(999))]), 10),
+            head: Some(ValueBox::from(1)),
+            next_input: 0,
+            ..Interpreter::with_settings(
+                Memory::default(),
+                Settings {
+                    strict_hrm: true,
+                    ..Default::default()
+                },
+            )
+        };
+
+        let result = interpreter.execute_instruction(
+            &Instruction::Add(ValueBoxMemoryAddress::Pointer(0)),
+            &[],
+            &mut vec![],
+        );
+        assert!(matches!(
+            result,
+            Err(ExecuteInstructionError::Overflow(1000))
+        ));
+
+        let result = interpreter.execute_instruction(
+            &Instruction::BumpUp(ValueBoxMemoryAddress::Pointer(0)),
+            &[],
+            &mut vec![],
+        );
+        assert!(matches!(
+            result,
+            Err(ExecuteInstructionError::Overflow(1000))
+        ));
+        assert_eq!(interpreter.memory.get(&0), Some(&ValueBox::from(999)));
+
+        let result = interpreter.execute_instruction(
+            &Instruction::BumpDown(ValueBoxMemoryAddress::Pointer(0)),
+            &[],
+            &mut vec![],
+        );
+        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
+        assert_eq!(interpreter.head, Some(ValueBox::from(998)));
+    }
+
+    #[test]
+    fn test_no_overflow_by_default() {
+        let mut interpreter = Interpreter {
+            memory: Memory::with_data(HashMap::from_iter([(0, ValueBox::from(999))]), 10),
+            head: Some(ValueBox::from(1)),
+            next_input: 0,
+            ..Interpreter::new(Memory::default())
+        };
+
+        let result = interpreter.execute_instruction(
+            &Instruction::Add(ValueBoxMemoryAddress::Pointer(0)),
+            &[],
+            &mut vec![],
+        );
+        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
+        assert_eq!(interpreter.head, Some(ValueBox::from(1000)));
+    }
+
+    #[test]
+    fn test_jump() {
+        let mut interpreter = Interpreter {
+            memory: Memory::default(),
+            head: None,
+            next_input: 0,
+            ..Interpreter::new(Memory::default())
+        };
+
+        let instruction = Instruction::Jump("label".into());
+        let result = interpreter.execute_instruction(&instruction, &[], &mut vec![]);
+        assert_eq!(
+            result.unwrap(),
+            InstructionResult::JumpBlock(&"label".into())
+        );
+    }
+
+    #[test]
+    fn test_jump_if_zero() {
+        let mut interpreter = Interpreter {
+            memory: Memory::default(),
+            head: Some(ValueBox::from(0)),
+            next_input: 0,
+            ..Interpreter::new(Memory::default())
+        };
+
+        let instruction = Instruction::JumpIfZero("label".into());
+        let result = interpreter.execute_instruction(&instruction, &[], &mut vec![]);
+        assert_eq!(
+            result.unwrap(),
+            InstructionResult::JumpBlock(&"label".into())
+        );
+    }
+
+    #[test]
+    fn test_jump_if_zero_not_zero() {
+        let mut interpreter = Interpreter {
+            memory: Memory::default(),
+            head: Some(ValueBox::from(42)),
+            next_input: 0,
+            ..Interpreter::new(Memory::default())
+        };
+
+        let instruction = Instruction::JumpIfZero("label".into());
+        let result = interpreter.execute_instruction(&instruction, &[], &mut vec![]);
+        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
+    }
+
+    #[test]
+    fn test_jump_if_negative() {
+        let mut interpreter = Interpreter {
+            memory: Memory::default(),
+            head: Some(ValueBox::from(-42)),
+            next_input: 0,
+            ..Interpreter::new(Memory::default())
         };
 
-        self.memory
-            .set_with_vbma(vbma, Some(ValueBox::from(new_value)))
-            .unwrap(); // Should never fail because we just read it
-        self.head = Some(ValueBox::from(new_value));
-        Ok(())
+        let instruction = Instruction::JumpIfNegative("label".into());
+        let result = interpreter.execute_instruction(&instruction, &[], &mut vec![]);
+        assert_eq!(
+            result.unwrap(),
+            InstructionResult::JumpBlock(&"label".into())
+        );
+    }
+
+    #[test]
+    fn test_jump_if_negative_not_negative() {
+        let mut interpreter = Interpreter {
+            memory: Memory::default(),
+            head: Some(ValueBox::from(0)),
+            next_input: 0,
+            ..Interpreter::new(Memory::default())
+        };
+
+        let instruction = Instruction::JumpIfNegative("label".into());
+        let result = interpreter.execute_instruction(&instruction, &[], &mut vec![]);
+        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
+    }
+}
+
+#[cfg(test)]
+mod test_script_execution {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::interpreter::settings::Limits;
+    use crate::interpreter::stats::TileAccesses;
+    use crate::script_object::instruction::ParseOptions;
+
+    const SCRIPT: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+    a:
+        INBOX
+        OUTBOX
+        JUMP     a
+    ";
+
+    #[test]
+    fn test_execute() {
+        let script = SCRIPT.parse::<ScriptObject>().unwrap();
+        let mut interpreter = Interpreter::new(Memory::default());
+
+        let inputs = [ValueBox::from(1), ValueBox::from('B')];
+        let (outputs, _) = interpreter.execute(&script, &inputs).unwrap();
+        assert_eq!(outputs, inputs);
+    }
+
+    #[test]
+    fn test_concurrent_executions() {
+        let script = std::sync::Arc::new(SCRIPT.parse::<ScriptObject>().unwrap());
+        let handles = (0..4)
+            .map(|i| {
+                let script = std::sync::Arc::clone(&script);
+                std::thread::spawn(move || {
+                    let mut interpreter = Interpreter::new(Memory::default());
+                    let steps = std::sync::Arc::new(std::sync::Mutex::new(0));
+                    let counted = steps.clone();
+                    interpreter.set_tracer(move |_| *counted.lock().unwrap() += 1);
+                    let inputs = vec![ValueBox::from(i); i as usize];
+                    let (outputs, _) = interpreter.execute(&script, &inputs).unwrap();
+                    let steps = *steps.lock().unwrap();
+                    (outputs.len(), steps)
+                })
+            })
+            .collect::<Vec<_>>();
+        let results = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(results, vec![(0, 1), (1, 4), (2, 7), (3, 10)]);
+    }
+
+    #[test]
+    fn test_rewind() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            COPYTO 0
+            BUMPUP 0
+            OUTBOX
+            JUMP a
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        let settings = Settings {
+            history_size: 3,
+            ..Settings::default()
+        };
+        let mut interpreter = Interpreter::with_settings(Memory::default(), settings);
+        let inputs = [ValueBox::from(1), ValueBox::from(5)];
+        let mut outputs = vec![];
+
+        // INBOX, COPYTO, BUMPUP, OUTBOX, JUMP, INBOX, COPYTO
+        for _ in 0..7 {
+            interpreter.step(&script, &inputs, &mut outputs).unwrap();
+        }
+        assert_eq!(interpreter.memory().get(&0), Some(&ValueBox::from(5)));
+        assert_eq!(interpreter.rewindable_steps(), 3);
+
+        // Back before the first JUMP: the history doesn't go further
+        assert_eq!(interpreter.rewind(4, &mut outputs), 3);
+        assert_eq!(interpreter.steps(), 4);
+        assert_eq!(interpreter.head(), Some(ValueBox::from(2)));
+        assert_eq!(interpreter.memory().get(&0), Some(&ValueBox::from(2)));
+        assert_eq!(outputs, vec![ValueBox::from(2)]);
+        assert_eq!(
+            interpreter.position(),
+            Position {
+                block: 1,
+                instruction: 4
+            }
+        );
+
+        // Executing again gives the same result
+        let (_, stats) = interpreter.execute(&script, &inputs).unwrap();
+        assert_eq!(stats.steps, 11);
+    }
+
+    #[test]
+    fn test_run_pauses_on_watchpoints() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            COPYTO 1
+            COPYTO 2
+            COPYFROM [3]
+            JUMP a
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        let memory = Memory::with_data(HashMap::from([(3, ValueBox::from(1))]), 3);
+        let mut interpreter = Interpreter::new(memory);
+        interpreter.add_watchpoint(1);
+        let inputs = [ValueBox::from(7)];
+        let mut outputs = vec![];
+
+        // Written by COPYTO 1
+        let status = interpreter.run(&script, &inputs, &mut outputs).unwrap();
+        assert_eq!(
+            status,
+            ExecutionStatus::Paused(Position {
+                block: 1,
+                instruction: 2
+            })
+        );
+        assert_eq!(
+            interpreter.watchpoint_hits(),
+            [WatchpointHit {
+                address: 1,
+                access: TileAccessKind::Write,
+                before: None,
+                after: Some(ValueBox::from(7)),
+                position: Position {
+                    block: 1,
+                    instruction: 1
+                },
+                instruction: "COPYTO 1".to_string(),
+            }]
+        );
+
+        // Read through the pointer of COPYFROM [3]
+        interpreter.run(&script, &inputs, &mut outputs).unwrap();
+        assert_eq!(interpreter.watchpoint_hits().len(), 1);
+        assert_eq!(
+            interpreter.watchpoint_hits()[0].access,
+            TileAccessKind::Read
+        );
+        assert_eq!(interpreter.watchpoint_hits()[0].instruction, "COPYFROM [3]");
+
+        assert!(interpreter.remove_watchpoint(1));
+        let status = interpreter.run(&script, &inputs, &mut outputs).unwrap();
+        assert_eq!(status, ExecutionStatus::Finished);
+    }
+
+    #[test]
+    fn test_watch_callback() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            COPYTO 0
+            BUMPUP 0
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        let mut interpreter = Interpreter::new(Memory::default());
+        interpreter.add_watchpoint(0);
+        let hits = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let recorded_hits = hits.clone();
+        interpreter.set_watch_callback(move |hit| recorded_hits.lock().unwrap().push(hit.clone()));
+
+        interpreter.execute(&script, &[ValueBox::from(1)]).unwrap();
+
+        let hits = hits.lock().unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[1].before, Some(ValueBox::from(1)));
+        assert_eq!(hits[1].after, Some(ValueBox::from(2)));
+    }
+
+    #[test]
+    fn test_tracer() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            COPYTO 2
+            BUMPUP 2
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        let mut interpreter = Interpreter::new(Memory::default());
+        let entries = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let recorded_entries = entries.clone();
+        interpreter.set_tracer(move |entry| recorded_entries.lock().unwrap().push(entry.clone()));
+
+        interpreter.execute(&script, &[ValueBox::from(4)]).unwrap();
+
+        let entries = entries.lock().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].instruction, "INBOX");
+        assert_eq!(entries[0].head_after, Some(ValueBox::from(4)));
+        assert_eq!(entries[2].step, 3);
+        assert_eq!(entries[2].line, 4);
+        assert_eq!(
+            entries[2].tile,
+            Some(TileAccess {
+                address: 2,
+                before: Some(ValueBox::from(4)),
+                after: Some(ValueBox::from(5)),
+            })
+        );
+    }
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        events: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl Observer for RecordingObserver {
+        fn on_step(&mut self, event: &StepEvent) {
+            let name = event.instruction.name();
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("step {} {}", event.step, name));
+        }
+
+        fn on_jump(&mut self, event: &JumpEvent) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("jump {} to block {}", event.label, event.to.block));
+        }
+
+        fn on_memory_write(&mut self, event: &MemoryWriteEvent) {
+            self.events.lock().unwrap().push(format!(
+                "write {}: {:?} -> {:?}",
+                event.address, event.before, event.after
+            ));
+        }
+
+        fn on_output(&mut self, event: &OutputEvent) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("output #{}: {}", event.index, event.value));
+        }
+    }
+
+    #[test]
+    fn test_observer() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            COPYTO 0
+            OUTBOX
+            JUMP a
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        let mut interpreter = Interpreter::new(Memory::default());
+        let observer = RecordingObserver::default();
+        let events = observer.events.clone();
+        interpreter.add_observer(observer);
+
+        interpreter.execute(&script, &[ValueBox::from(7)]).unwrap();
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                "step 1 INBOX",
+                "write 0: None -> Some(Number(7))",
+                "step 2 COPYTO",
+                "output #0: 7",
+                "step 3 OUTBOX",
+                "jump a to block 1",
+                "step 4 JUMP",
+                "step 5 INBOX",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bytecode_execution_matches_step_by_step() {
+        let script = include_str!("../samples/41-SortingRoom.hrm")
+            .parse::<ScriptObject>()
+            .unwrap();
+        let level = crate::levels::get_level(41).unwrap();
+        let inputs = "5 2 9 0 C A B 0 -3 4 0"
+            .split_whitespace()
+            .map(|value| value.parse::<ValueBox>().unwrap())
+            .collect::<Vec<_>>();
+        let memory = || Memory::with_data(level.memory(), level.max_address());
+
+        let mut bytecode_interpreter = Interpreter::new(memory());
+        let bytecode_result = bytecode_interpreter.execute(&script, &inputs).unwrap();
+        // Keeping a history forces the execution step by step
+        let settings = Settings {
+            history_size: 1,
+            ..Settings::default()
+        };
+        let mut step_interpreter = Interpreter::with_settings(memory(), settings);
+        let step_result = step_interpreter.execute(&script, &inputs).unwrap();
+
+        assert_eq!(bytecode_result, step_result);
+        assert_eq!(bytecode_result.0, level.expected_outbox(&inputs).unwrap());
+        let floor = |interpreter: &Interpreter| {
+            let mut addresses = interpreter.memory().addresses().collect::<Vec<_>>();
+            addresses.sort();
+            addresses
+                .into_iter()
+                .map(|address| (address, interpreter.memory().get(&address).copied()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(floor(&bytecode_interpreter), floor(&step_interpreter));
+    }
+
+    #[test]
+    fn test_resume_from_snapshot() {
+        let script = SCRIPT.parse::<ScriptObject>().unwrap();
+        let inputs = [ValueBox::from(1), ValueBox::from('B'), ValueBox::from(3)];
+        let mut interpreter = Interpreter::new(Memory::default());
+        let mut outputs = vec![];
+        for _ in 0..4 {
+            interpreter.step(&script, &inputs, &mut outputs).unwrap();
+        }
+
+        let json = serde_json::to_string(&interpreter.snapshot()).unwrap();
+        let snapshot = serde_json::from_str::<InterpreterSnapshot>(&json).unwrap();
+        let mut resumed = Interpreter::from_snapshot(snapshot);
+        let stats = resumed
+            .execute_into(&script, &inputs, &mut outputs)
+            .unwrap();
+
+        assert_eq!(outputs, inputs);
+        assert_eq!(stats, interpreter.execute(&script, &inputs).unwrap().1);
     }
-}
 
-#[cfg(test)]
-mod test_instructions_execution {
-    use std::collections::HashMap;
+    #[test]
+    fn test_execute_with_lazy_inputs() {
+        let script = SCRIPT.parse::<ScriptObject>().unwrap();
+        let mut interpreter = Interpreter::new(Memory::default());
+        let mut produced = 0;
+        let inputs = io::LazyInputs::new(|| {
+            produced += 1;
+            (produced <= 3).then_some(ValueBox::from(produced * 10))
+        });
+
+        let (outputs, _) = interpreter.execute(&script, inputs).unwrap();
+        assert_eq!(
+            outputs,
+            vec![ValueBox::from(10), ValueBox::from(20), ValueBox::from(30)]
+        );
+    }
 
-    use super::*;
-    use crate::script_object::value_box::ValueBoxMemoryAddress;
+    #[test]
+    fn test_execute_into_output_sink() {
+        let script = SCRIPT.parse::<ScriptObject>().unwrap();
+        let mut interpreter = Interpreter::new(Memory::default());
+        let mut streamed = vec![];
+
+        let stats = interpreter
+            .execute_into(
+                &script,
+                &[ValueBox::from(1), ValueBox::from(2)],
+                io::OutputFn(|value| streamed.push(value.to_string())),
+            )
+            .unwrap();
+        assert_eq!(streamed, vec!["1", "2"]);
+        assert_eq!(stats.steps, 7);
+    }
 
     #[test]
-    fn test_inbox() {
-        let mut interpreter = Interpreter {
-            memory: Memory::default(),
-            head: None,
-            next_input: 0,
+    fn test_run_pauses_on_breakpoints() {
+        let script = SCRIPT.parse::<ScriptObject>().unwrap();
+        let mut interpreter = Interpreter::new(Memory::default());
+        interpreter.add_breakpoint(Breakpoint::Line(5));
+
+        let inputs = [ValueBox::from(1), ValueBox::from(2)];
+        let mut outputs = vec![];
+        let outbox_position = Position {
+            block: 1,
+            instruction: 1,
         };
 
-        let result = interpreter.execute_instruction(&Instruction::In, &[], &mut vec![]);
-        assert_eq!(result.unwrap(), InstructionResult::Terminate);
+        let status = interpreter.run(&script, &inputs, &mut outputs).unwrap();
+        assert_eq!(status, ExecutionStatus::Paused(outbox_position));
+        assert_eq!(interpreter.head(), Some(ValueBox::from(1)));
+        assert!(outputs.is_empty());
 
-        let result =
-            interpreter.execute_instruction(&Instruction::In, &[ValueBox::from(10)], &mut vec![]);
-        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
-        assert_eq!(interpreter.head, Some(ValueBox::from(10)));
+        let status = interpreter.run(&script, &inputs, &mut outputs).unwrap();
+        assert_eq!(status, ExecutionStatus::Paused(outbox_position));
+        assert_eq!(interpreter.head(), Some(ValueBox::from(2)));
+        assert_eq!(outputs, vec![ValueBox::from(1)]);
+
+        assert!(interpreter.remove_breakpoint(&Breakpoint::Line(5)));
+        let status = interpreter.run(&script, &inputs, &mut outputs).unwrap();
+        assert_eq!(status, ExecutionStatus::Finished);
+        assert_eq!(outputs, inputs);
     }
 
     #[test]
-    fn test_outbox() {
-        let mut interpreter = Interpreter {
-            memory: Memory::default(),
-            head: Some(ValueBox::from(42)),
-            next_input: 0,
-        };
+    fn test_run_pauses_on_label_breakpoint() {
+        let script = SCRIPT.parse::<ScriptObject>().unwrap();
+        let mut interpreter = Interpreter::new(Memory::default());
+        interpreter.add_breakpoint(Breakpoint::Label("a".to_string()));
 
+        let inputs = [ValueBox::from(1)];
         let mut outputs = vec![];
-        let result = interpreter.execute_instruction(&Instruction::Out, &[], &mut outputs);
-        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
-        assert_eq!(outputs, vec![ValueBox::from(42)]);
+
+        // Paused before executing anything
+        let status = interpreter.run(&script, &inputs, &mut outputs).unwrap();
+        assert_eq!(
+            status,
+            ExecutionStatus::Paused(Position {
+                block: 1,
+                instruction: 0
+            })
+        );
+        assert_eq!(interpreter.head(), None);
+
+        // Paused again after the jump
+        let status = interpreter.run(&script, &inputs, &mut outputs).unwrap();
+        assert_eq!(
+            status,
+            ExecutionStatus::Paused(Position {
+                block: 1,
+                instruction: 0
+            })
+        );
+        assert_eq!(outputs, inputs);
     }
 
     #[test]
-    fn test_copy_from() {
-        let mut interpreter = Interpreter {
-            memory: Memory::with_data(HashMap::from_iter([(0, ValueBox::from(42))]), 10),
-            head: None,
-            next_input: 0,
-        };
+    fn test_step() {
+        let script = SCRIPT.parse::<ScriptObject>().unwrap();
+        let mut interpreter = Interpreter::new(Memory::default());
 
-        let result = interpreter.execute_instruction(
-            &Instruction::CopyFrom(ValueBoxMemoryAddress::Pointer(0)),
-            &[],
-            &mut vec![],
+        let inputs = [ValueBox::from(7)];
+        let mut outputs = vec![];
+
+        let status = interpreter.step(&script, &inputs, &mut outputs).unwrap();
+        assert_eq!(
+            status,
+            ExecutionStatus::Paused(Position {
+                block: 1,
+                instruction: 1
+            })
         );
-        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
-        assert_eq!(interpreter.head, Some(ValueBox::from(42)));
+        assert_eq!(interpreter.head(), Some(ValueBox::from(7)));
+
+        interpreter.step(&script, &inputs, &mut outputs).unwrap();
+        interpreter.step(&script, &inputs, &mut outputs).unwrap();
+        // No more inputs
+        let status = interpreter.step(&script, &inputs, &mut outputs).unwrap();
+        assert_eq!(status, ExecutionStatus::Finished);
+        assert_eq!(outputs, inputs);
     }
 
     #[test]
-    fn test_copy_to() {
-        let mut interpreter = Interpreter {
-            memory: Memory::with_data(HashMap::from_iter([(0, ValueBox::from(42))]), 10),
-            head: Some(ValueBox::from(10)),
-            next_input: 0,
+    fn test_step_limit() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+        a:
+            JUMP     a
+        ";
+        let script = script.parse::<ScriptObject>().unwrap();
+        let settings = Settings {
+            max_steps: Some(100),
+            ..Default::default()
         };
-
-        let result = interpreter.execute_instruction(
-            &Instruction::CopyTo(ValueBoxMemoryAddress::Pointer(0)),
-            &[],
-            &mut vec![],
-        );
-        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
-        assert_eq!(interpreter.memory.get(&0), Some(&ValueBox::from(10)));
+        let mut interpreter = Interpreter::with_settings(Memory::default(), settings);
+
+        let result = interpreter.execute(&script, &[]);
+        assert!(matches!(
+            result,
+            Err(ExecuteScriptError::StepLimitExceeded(_, 100))
+        ));
+        assert_eq!(interpreter.steps(), 100);
     }
 
     #[test]
-    fn test_add() {
-        let mut interpreter = Interpreter {
-            memory: Memory::with_data(HashMap::from_iter([(0, ValueBox::from(42))]), 10),
-            head: Some(ValueBox::from(10)),
-            next_input: 0,
+    fn test_timeout() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+        a:
+            JUMP     a
+        ";
+        let script = script.parse::<ScriptObject>().unwrap();
+        let settings = Settings {
+            timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
         };
+        let mut interpreter = Interpreter::with_settings(Memory::default(), settings);
 
-        let result = interpreter.execute_instruction(
-            &Instruction::Add(ValueBoxMemoryAddress::Pointer(0)),
-            &[],
-            &mut vec![],
+        let result = interpreter.execute(&script, &[]);
+        assert!(matches!(result, Err(ExecuteScriptError::Timeout(..))));
+        assert_eq!(
+            result.unwrap_err().message(),
+            "time limit of 50ms exceeded, the script may be stuck in an infinite loop"
         );
-        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
-        assert_eq!(interpreter.head, Some(ValueBox::from(52)));
-        assert_eq!(interpreter.memory.get(&0), Some(&ValueBox::from(42)));
     }
 
     #[test]
-    fn test_sub() {
-        let mut interpreter = Interpreter {
-            memory: Memory::with_data(HashMap::from_iter([(0, ValueBox::from(42))]), 10),
-            head: Some(ValueBox::from(10)),
-            next_input: 0,
+    fn test_resource_limits() {
+        // Fills a new tile and outputs a value at each step
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            COPYTO   0
+        a:
+            COPYTO   [0]
+            BUMPUP   0
+            OUTBOX
+            JUMP     a
+        ";
+        let script = script.parse::<ScriptObject>().unwrap();
+        let run = |limits: Limits, history_size: usize| {
+            let settings = Settings {
+                limits,
+                history_size,
+                ..Default::default()
+            };
+            let mut interpreter = Interpreter::with_settings(Memory::default(), settings);
+            interpreter
+                .execute(&script, &[ValueBox::from(1)])
+                .unwrap_err()
         };
-
-        let result = interpreter.execute_instruction(
-            &Instruction::Sub(ValueBoxMemoryAddress::Pointer(0)),
-            &[],
-            &mut vec![],
-        );
-        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
-        assert_eq!(interpreter.head, Some(ValueBox::from(-32)));
-        assert_eq!(interpreter.memory.get(&0), Some(&ValueBox::from(42)));
+        // With and without the history, for the two ways of executing the script
+        for history_size in [0, 1] {
+            let max_tiles = Limits {
+                max_tiles: Some(5),
+                ..Default::default()
+            };
+            let error = run(max_tiles, history_size);
+            assert!(matches!(error, ExecuteScriptError::TileLimitExceeded(_, 5)));
+            assert_eq!(error.state().tile(5), Some(ValueBox::from(5)));
+
+            let max_outputs = Limits {
+                max_outputs: Some(3),
+                ..Default::default()
+            };
+            let error = run(max_outputs, history_size);
+            assert_eq!(error.code(), "output-limit-exceeded");
+            assert_eq!(error.state().outputs().map(<[_]>::len), Some(4));
+
+            let max_allocations = Limits {
+                max_allocations: Some(7),
+                ..Default::default()
+            };
+            let error = run(max_allocations, history_size);
+            assert_eq!(
+                error.message(),
+                "allocation limit of 7 exceeded, the script holds too many values"
+            );
+        }
     }
 
     #[test]
-    fn test_sub_characters() {
-        let mut interpreter = Interpreter {
-            memory: Memory::with_data(HashMap::from_iter([(0, ValueBox::from('E'))]), 10),
-            head: Some(ValueBox::from('A')),
-            next_input: 0,
+    fn test_error_points_at_the_instruction() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            ADD      4
+        ";
+        let script = script.parse::<ScriptObject>().unwrap();
+        let expected_span = Span {
+            line: 3,
+            column: 13,
+            text: "ADD      4".to_string(),
         };
 
-        let result = interpreter.execute_instruction(
-            &Instruction::Sub(ValueBoxMemoryAddress::Pointer(0)),
-            &[],
-            &mut vec![],
-        );
-        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
-        assert_eq!(interpreter.head, Some(ValueBox::from(-4)));
-        assert_eq!(interpreter.memory.get(&0), Some(&ValueBox::from('E')));
+        // Both step by step and as bytecode
+        let mut observed = Interpreter::new(Memory::default());
+        observed.add_observer(RecordingObserver::default());
+        for mut interpreter in [Interpreter::new(Memory::default()), observed] {
+            let error = interpreter
+                .execute(&script, &[ValueBox::from(1)])
+                .unwrap_err();
+            assert!(error
+                .to_string()
+                .contains("error executing an instruction at line 3: ADD      4:"));
+            let diagnostic = error.diagnostic().unwrap();
+            assert_eq!((diagnostic.line, diagnostic.range), (3, Some(12..22)));
+            match error {
+                ExecuteScriptError::ExecuteInstructionError(_, span, _) => {
+                    assert_eq!(span, Some(Box::new(expected_span.clone())))
+                }
+                error => panic!("Expected an instruction error, got {:?}", error),
+            }
+        }
     }
 
     #[test]
-    fn test_bump_up() {
-        let mut interpreter = Interpreter {
-            memory: Memory::with_data(HashMap::from_iter([(0, ValueBox::from(42))]), 10),
-            head: Some(ValueBox::from(10)),
-            next_input: 0,
+    fn test_step_limit_not_reached() {
+        let script = SCRIPT.parse::<ScriptObject>().unwrap();
+        let settings = Settings {
+            max_steps: Some(7),
+            ..Default::default()
         };
+        let mut interpreter = Interpreter::with_settings(Memory::default(), settings);
 
-        let result = interpreter.execute_instruction(
-            &Instruction::BumpUp(ValueBoxMemoryAddress::Pointer(0)),
-            &[],
-            &mut vec![],
-        );
-        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
-        assert_eq!(interpreter.head, Some(ValueBox::from(43)));
-        assert_eq!(interpreter.memory.get(&0), Some(&ValueBox::from(43)));
+        // 2 loops of 3 instructions, then the INBOX terminating the program
+        let inputs = [ValueBox::from(1), ValueBox::from(2)];
+        let (outputs, _) = interpreter.execute(&script, &inputs).unwrap();
+        assert_eq!(outputs, inputs);
+        assert_eq!(interpreter.steps(), 7);
     }
 
     #[test]
-    fn test_bump_down() {
-        let mut interpreter = Interpreter {
-            memory: Memory::with_data(HashMap::from_iter([(0, ValueBox::from(42))]), 10),
-            head: Some(ValueBox::from(10)),
-            next_input: 0,
+    fn test_execution_stats() {
+        let script = SCRIPT.parse::<ScriptObject>().unwrap();
+        let mut interpreter = Interpreter::new(Memory::default());
+
+        let inputs = [ValueBox::from(1), ValueBox::from(2)];
+        let (_, stats) = interpreter.execute(&script, &inputs).unwrap();
+
+        assert_eq!(stats.steps, 7);
+        assert_eq!(stats.instructions.get("INBOX"), Some(&3));
+        assert_eq!(stats.instructions.get("OUTBOX"), Some(&2));
+        assert_eq!(stats.instructions.get("JUMP"), Some(&2));
+        assert_eq!(stats.instructions.get("ADD"), None);
+        assert_eq!(stats.blocks.get("a"), Some(&3));
+        assert_eq!(stats.blocks.get("entry"), None);
+        let executions = |instruction| {
+            stats.executions_of(Position {
+                block: 1,
+                instruction,
+            })
         };
+        assert_eq!((executions(0), executions(1), executions(2)), (3, 2, 2));
+        assert_eq!(&stats, interpreter.stats());
+    }
 
-        let result = interpreter.execute_instruction(
-            &Instruction::BumpDown(ValueBoxMemoryAddress::Pointer(0)),
-            &[],
-            &mut vec![],
-        );
-        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
-        assert_eq!(interpreter.head, Some(ValueBox::from(41)));
-        assert_eq!(interpreter.memory.get(&0), Some(&ValueBox::from(41)));
+    #[test]
+    fn test_tile_accesses() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            COPYTO   0
+            BUMPUP   0
+            COPYFROM [1]
+            OUTBOX
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        let memory = || Memory::with_data(HashMap::from_iter([(1, ValueBox::from(0))]), 10);
+        let inputs = [ValueBox::from(4)];
+        let expected = BTreeMap::from([
+            (
+                0,
+                TileAccesses {
+                    reads: 2,
+                    writes: 2,
+                },
+            ),
+            (
+                1,
+                TileAccesses {
+                    reads: 1,
+                    writes: 0,
+                },
+            ),
+        ]);
+
+        let mut interpreter = Interpreter::new(memory());
+        let (_, stats) = interpreter.execute(&script, &inputs).unwrap();
+        assert_eq!(stats.tiles, expected);
+
+        // The same, one step at a time
+        let mut interpreter = Interpreter::new(memory());
+        let mut outputs = vec![];
+        for _ in 0..5 {
+            interpreter.step(&script, &inputs, &mut outputs).unwrap();
+        }
+        assert_eq!(interpreter.stats().tiles, expected);
+
+        // Far tiles are counted all the same
+        let far = MAX_VEC_TILES as i32;
+        let memory = HashMap::from_iter([(1, ValueBox::from(far)), (far as usize, 0.into())]);
+        let mut interpreter = Interpreter::new(Memory::with_data(memory, usize::MAX));
+        let (_, stats) = interpreter.execute(&script, &inputs).unwrap();
+        assert_eq!(stats.tiles[&(far as usize)].reads, 1);
     }
 
     #[test]
-    fn test_jump() {
-        let mut interpreter = Interpreter {
-            memory: Memory::default(),
-            head: None,
-            next_input: 0,
+    fn test_reset() {
+        let script = SCRIPT.parse::<ScriptObject>().unwrap();
+        let mut interpreter =
+            Interpreter::new(Memory::with_data(HashMap::from([(0, 5.into())]), 9));
+        interpreter.add_breakpoint(Breakpoint::Label("a".to_string()));
+        interpreter
+            .execute(&script, &[ValueBox::from(1), ValueBox::from(2)])
+            .unwrap();
+
+        interpreter.reset(Memory::default());
+        assert_eq!(interpreter.steps(), 0);
+        assert_eq!(interpreter.position(), Position::default());
+        assert_eq!(interpreter.head(), None);
+        assert_eq!(interpreter.memory().get(&0), None);
+        assert_eq!(interpreter.breakpoints().len(), 1);
+
+        // The same results as a new interpreter
+        let inputs = [ValueBox::from(3)];
+        let (outputs, stats) = interpreter.execute(&script, &inputs).unwrap();
+        let (new_outputs, new_stats) = Interpreter::new(Memory::default())
+            .execute(&script, &inputs)
+            .unwrap();
+        assert_eq!(outputs, new_outputs);
+        assert_eq!(stats.steps, new_stats.steps);
+        assert_eq!(stats.instructions, new_stats.instructions);
+        assert_eq!(stats.blocks, new_stats.blocks);
+    }
+
+    #[test]
+    fn test_jump_if_positive_and_equal() {
+        let options = ParseOptions {
+            extensions: true,
+            ..Default::default()
         };
+        // Outputs the positive inputs, until one is equal to the value of tile 0
+        let script = ScriptObject::parse_with_options(
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            JUMPE    0 b
+            JUMPP    c
+            JUMP     a
+        c:
+            OUTBOX
+            JUMP     a
+        b:
+        ",
+            &options,
+        )
+        .unwrap();
+        let memory = Memory::with_data(HashMap::from([(0, ValueBox::from('X'))]), 10);
+        let inputs = [
+            ValueBox::from(3),
+            ValueBox::from(0),
+            ValueBox::from(-2),
+            ValueBox::from('A'),
+            ValueBox::from(5),
+            ValueBox::from('X'),
+            ValueBox::from(7),
+        ];
+
+        let (outputs, _) = Interpreter::new(memory).execute(&script, &inputs).unwrap();
+        assert_eq!(outputs, [3, 5].map(ValueBox::from));
+
+        let error = Interpreter::new(Memory::default())
+            .execute(&script, &inputs)
+            .unwrap_err();
+        assert_eq!(error.code(), "jumpe-invalid-address");
+    }
 
-        let result = interpreter.execute_instruction(
-            &Instruction::Jump("label".to_string()),
-            &[],
-            &mut vec![],
-        );
-        assert_eq!(
-            result.unwrap(),
-            InstructionResult::JumpBlock("label".to_string())
-        );
+    #[test]
+    fn test_push_and_pop() {
+        let options = ParseOptions {
+            extensions: true,
+            ..Default::default()
+        };
+        // Reverses the inputs, until a 0
+        let script = ScriptObject::parse_with_options(
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            JUMPZ    b
+            PUSH
+            JUMP     a
+        b:
+            POP
+            OUTBOX
+            JUMP     b
+        ",
+            &options,
+        )
+        .unwrap();
+        let settings = Settings {
+            stack: Some(5..8),
+            history_size: 1,
+            ..Default::default()
+        };
+        let inputs = [1, 2, 3, 0].map(ValueBox::from);
+
+        let mut interpreter =
+            Interpreter::with_settings(Memory::with_data(HashMap::new(), 9), settings.clone());
+        let error = interpreter.execute(&script, &inputs).unwrap_err();
+        assert_eq!(error.code(), "stack-underflow");
+        assert_eq!(interpreter.memory().addresses().count(), 0);
+
+        let mut interpreter =
+            Interpreter::with_settings(Memory::with_data(HashMap::new(), 9), settings.clone());
+        let mut outputs = vec![];
+        // Up to the third PUSH, which is rewound
+        for _ in 0..11 {
+            interpreter.step(&script, &inputs, &mut outputs).unwrap();
+        }
+        assert_eq!(interpreter.memory().get(&7), Some(&ValueBox::from(3)));
+        interpreter.rewind(1, &mut outputs);
+        assert_eq!(interpreter.memory().get(&7), None);
+        assert_eq!(interpreter.head(), Some(ValueBox::from(3)));
+
+        let mut interpreter =
+            Interpreter::with_settings(Memory::with_data(HashMap::new(), 9), settings);
+        let error = interpreter
+            .execute(&script, &[1, 2, 3, 4, 0].map(ValueBox::from))
+            .unwrap_err();
+        assert_eq!(error.code(), "stack-overflow");
+
+        let error = Interpreter::new(Memory::default())
+            .execute(&script, &inputs)
+            .unwrap_err();
+        assert_eq!(error.code(), "no-stack");
     }
 
     #[test]
-    fn test_jump_if_zero() {
-        let mut interpreter = Interpreter {
-            memory: Memory::default(),
-            head: Some(ValueBox::from(0)),
-            next_input: 0,
+    fn test_call_and_ret() {
+        let options = ParseOptions {
+            extensions: true,
+            ..Default::default()
+        };
+        // Outputs each input, its double and its quadruple
+        let script = ScriptObject::parse_with_options(
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            OUTBOX
+            CALL     quadruple
+            OUTBOX
+            JUMP     a
+        double:
+            COPYFROM 0
+            ADD      0
+            COPYTO   0
+            OUTBOX
+            COPYFROM 0
+            RET
+        quadruple:
+            COPYTO   0
+            CALL     double
+            CALL     double
+            RET
+        ",
+            &options,
+        )
+        .unwrap();
+        let inputs = [1, 5].map(ValueBox::from);
+        let expected = [1, 2, 4, 4, 5, 10, 20, 20].map(ValueBox::from);
+
+        let (outputs, stats) = Interpreter::new(Memory::with_data(HashMap::new(), 1))
+            .execute(&script, &inputs)
+            .unwrap();
+        assert_eq!(outputs, expected);
+
+        // Step by step, with the CALLs and the RETs rewound
+        let settings = Settings {
+            history_size: 100,
+            ..Default::default()
+        };
+        let mut interpreter =
+            Interpreter::with_settings(Memory::with_data(HashMap::new(), 1), settings);
+        let mut outputs = vec![];
+        for _ in 0..12 {
+            interpreter.step(&script, &inputs, &mut outputs).unwrap();
+        }
+        assert_eq!(interpreter.rewind(12, &mut outputs), 12);
+        while interpreter.step(&script, &inputs, &mut outputs).unwrap() != ExecutionStatus::Finished
+        {
+        }
+        assert_eq!(outputs, expected);
+        assert_eq!(interpreter.steps(), stats.steps);
+
+        let script = ScriptObject::parse_with_options(
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+            RET
+        ",
+            &options,
+        )
+        .unwrap();
+        let error = Interpreter::new(Memory::default())
+            .execute(&script, &inputs)
+            .unwrap_err();
+        assert_eq!(error.code(), "ret-without-call");
+
+        let script = ScriptObject::parse_with_options(
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            CALL     a
+        ",
+            &options,
+        )
+        .unwrap();
+        let error = Interpreter::new(Memory::default())
+            .execute(&script, &inputs)
+            .unwrap_err();
+        assert_eq!(error.code(), "call-stack-overflow");
+    }
+
+    #[test]
+    fn test_second_hand() {
+        let options = ParseOptions {
+            extensions: true,
+            ..Default::default()
+        };
+        // Outputs the inputs two by two, swapped (OUTBOX keeps the head)
+        let script = ScriptObject::parse_with_options(
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            TCOPYTO
+            INBOX
+            OUTBOX
+            XCHG
+            OUTBOX
+            TCOPYFROM
+            JUMP     a
+        ",
+            &options,
+        )
+        .unwrap();
+        let settings = Settings {
+            second_hand: true,
+            history_size: 1,
+            ..Default::default()
         };
+        let inputs = [1, 2, 3, 4].map(ValueBox::from);
 
-        let result = interpreter.execute_instruction(
-            &Instruction::JumpIfZero("label".to_string()),
-            &[],
-            &mut vec![],
-        );
-        assert_eq!(
-            result.unwrap(),
-            InstructionResult::JumpBlock("label".to_string())
-        );
+        let mut interpreter = Interpreter::with_settings(Memory::default(), settings.clone());
+        let (outputs, _) = interpreter.execute(&script, &inputs).unwrap();
+        assert_eq!(outputs, [2, 1, 4, 3].map(ValueBox::from));
+        assert_eq!(interpreter.second_hand(), Some(ValueBox::from(4)));
+
+        // Up to the first XCHG, which is rewound
+        interpreter.reset(Memory::default());
+        let mut outputs = vec![];
+        for _ in 0..5 {
+            interpreter.step(&script, &inputs, &mut outputs).unwrap();
+        }
+        assert_eq!(interpreter.head(), Some(ValueBox::from(1)));
+        assert_eq!(interpreter.second_hand(), Some(ValueBox::from(2)));
+        interpreter.rewind(1, &mut outputs);
+        assert_eq!(interpreter.head(), Some(ValueBox::from(2)));
+        assert_eq!(interpreter.second_hand(), Some(ValueBox::from(1)));
+
+        let error = Interpreter::new(Memory::default())
+            .execute(&script, &inputs)
+            .unwrap_err();
+        assert_eq!(error.code(), "no-second-hand");
+
+        let script = ScriptObject::parse_with_options(
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+            TCOPYFROM
+        ",
+            &options,
+        )
+        .unwrap();
+        let error = Interpreter::with_settings(Memory::default(), settings)
+            .execute(&script, &inputs)
+            .unwrap_err();
+        assert_eq!(error.code(), "tcopyfrom-empty-hand");
     }
 
     #[test]
-    fn test_jump_if_zero_not_zero() {
-        let mut interpreter = Interpreter {
-            memory: Memory::default(),
-            head: Some(ValueBox::from(42)),
-            next_input: 0,
+    fn test_debug() {
+        let options = ParseOptions {
+            extensions: true,
+            ..Default::default()
         };
+        let script = ScriptObject::parse_with_options(
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            COPYTO   0
+            DEBUG    \"copied: COMMENT\"
+            DEBUG    0
+            DEBUG    [0]
+            DEBUG    [1]
+            OUTBOX
+        ",
+            &options,
+        )
+        .unwrap();
+        let memory = Memory::with_data(HashMap::from([(1, ValueBox::from('A'))]), 9);
+        let printed = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+
+        let mut interpreter = Interpreter::new(memory.clone());
+        let sink = printed.clone();
+        interpreter.set_debug_callback(move |position, text| {
+            sink.lock()
+                .unwrap()
+                .push((position.instruction, text.to_string()))
+        });
+        let (outputs, _) = interpreter.execute(&script, &[ValueBox::from(1)]).unwrap();
+        assert_eq!(outputs, [ValueBox::from(1)]);
+        assert_eq!(
+            printed.lock().unwrap()[..3],
+            [
+                (2, "copied: COMMENT".to_string()),
+                (3, "tile 0: 1".to_string()),
+                (4, "tile [0] (1): A".to_string()),
+            ]
+        );
+        // The pointer is invalid: the error is printed instead of the tile
+        assert!(printed.lock().unwrap()[3].1.starts_with("tile [1]: "));
 
-        let result = interpreter.execute_instruction(
-            &Instruction::JumpIfZero("label".to_string()),
-            &[],
-            &mut vec![],
+        // Nothing is printed in the conditions of the game
+        printed.lock().unwrap().clear();
+        let mut interpreter = Interpreter::with_settings(
+            memory,
+            Settings {
+                strict_hrm: true,
+                ..Default::default()
+            },
         );
-        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
+        let sink = printed.clone();
+        interpreter
+            .set_debug_callback(move |_, text| sink.lock().unwrap().push((0, text.to_string())));
+        interpreter.execute(&script, &[ValueBox::from(1)]).unwrap();
+        assert!(printed.lock().unwrap().is_empty());
     }
 
     #[test]
-    fn test_jump_if_negative() {
-        let mut interpreter = Interpreter {
-            memory: Memory::default(),
-            head: Some(ValueBox::from(-42)),
-            next_input: 0,
+    fn test_assertions() {
+        let options = ParseOptions {
+            extensions: true,
+            ..Default::default()
+        };
+        let script = ScriptObject::parse_with_options(
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            ASSERTN
+            INBOX
+            ASSERTZ
+            INBOX
+            COPYTO   0
+            ASSERT   0 == A
+            ASSERT   [1] == A
+            OUTBOX
+        ",
+            &options,
+        )
+        .unwrap();
+        let run = |inputs: &[ValueBox]| {
+            let memory = Memory::with_data(HashMap::from([(1, ValueBox::from(0))]), 9);
+            Interpreter::new(memory).execute(&script, inputs)
         };
 
-        let result = interpreter.execute_instruction(
-            &Instruction::JumpIfNegative("label".to_string()),
-            &[],
-            &mut vec![],
+        let (outputs, _) =
+            run(&[ValueBox::from(-1), ValueBox::from(0), ValueBox::from('A')]).unwrap();
+        assert_eq!(outputs, [ValueBox::from('A')]);
+
+        assert_eq!(
+            run(&[ValueBox::from(0)]).unwrap_err().code(),
+            "assertn-failed"
         );
         assert_eq!(
-            result.unwrap(),
-            InstructionResult::JumpBlock("label".to_string())
+            run(&[ValueBox::from(-1), ValueBox::from('A')])
+                .unwrap_err()
+                .code(),
+            "assertz-failed"
+        );
+        let error = run(&[-1, 0, 7].map(ValueBox::from)).unwrap_err();
+        assert_eq!(error.code(), "assert-failed");
+        assert_eq!(
+            error.message(),
+            "assertion failed: tile 0 holds 7, expected A"
+        );
+        let error = run(&[ValueBox::from(-1), ValueBox::from('A')]).unwrap_err();
+        assert_eq!(error.message(), "assertion failed: ASSERTZ with head A");
+        assert_eq!(
+            ExecuteInstructionError::AssertNegativeFailed(None).to_string(),
+            "assertion failed: ASSERTN with head empty"
         );
     }
 
     #[test]
-    fn test_jump_if_negative_not_negative() {
-        let mut interpreter = Interpreter {
-            memory: Memory::default(),
-            head: Some(ValueBox::from(0)),
-            next_input: 0,
+    fn test_nop_and_halt() {
+        let options = ParseOptions {
+            extensions: true,
+            ..Default::default()
         };
+        let script = ScriptObject::parse_with_options(
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            NOP
+            JUMPZ    b
+            OUTBOX
+            JUMP     a
+        b:
+            HALT
+            OUTBOX
+        ",
+            &options,
+        )
+        .unwrap();
+        let inputs = [1, 2, 0, 3].map(ValueBox::from);
 
-        let result = interpreter.execute_instruction(
-            &Instruction::JumpIfNegative("label".to_string()),
-            &[],
-            &mut vec![],
-        );
-        assert_eq!(result.unwrap(), InstructionResult::NextInstruction);
+        let (outputs, stats) = Interpreter::new(Memory::default())
+            .execute(&script, &inputs)
+            .unwrap();
+        assert_eq!(outputs, [1, 2].map(ValueBox::from));
+        assert_eq!(stats.steps, 14);
+
+        // Step by step too
+        let mut interpreter = Interpreter::new(Memory::default());
+        let mut outputs = vec![];
+        while interpreter.step(&script, &inputs, &mut outputs).unwrap() != ExecutionStatus::Finished
+        {
+        }
+        assert_eq!(outputs, [1, 2].map(ValueBox::from));
+        assert_eq!(interpreter.steps(), 14);
     }
 }
@@ -0,0 +1,108 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::script_object::{Position, ScriptObject};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// A point in the script where the interpreter pauses the execution,
+/// so that its state can be inspected before resuming.
+pub enum Breakpoint {
+    /// Pause before executing the first instruction of the block with this label
+    Label(String),
+    /// Pause before executing the first instruction written on this line (or after)
+    Line(usize),
+}
+
+impl Breakpoint {
+    /// Find the position of the instruction at which this breakpoint pauses the execution.
+    /// Returns None if the breakpoint doesn't correspond to any instruction in the script.
+    pub fn resolve(&self, script: &ScriptObject) -> Option<Position> {
+        match self {
+            Self::Label(label) => {
+                let block = script.get_block_by_label(label)?;
+                script.next_instruction_position(Position {
+                    block: block.index(),
+                    instruction: 0,
+                })
+            }
+            Self::Line(line) => script.find_position_of_line(*line),
+        }
+    }
+}
+
+impl FromStr for Breakpoint {
+    type Err = std::convert::Infallible;
+
+    /// A number is a line, anything else is a label.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().trim_end_matches(':');
+        match s.parse::<usize>() {
+            Ok(line) => Ok(Self::Line(line)),
+            Err(_) => Ok(Self::Label(s.to_string())),
+        }
+    }
+}
+
+impl Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Label(label) => write!(f, "label {}", label),
+            Self::Line(line) => write!(f, "line {}", line),
+        }
+    }
+}
+
+#[cfg(test)]
+mod breakpoint_tests {
+    use super::*;
+
+    const SCRIPT: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+    a:
+    b:
+        INBOX
+        OUTBOX
+        JUMP     a
+    ";
+
+    #[test]
+    fn test_breakpoint_from_str() {
+        assert_eq!("12".parse::<Breakpoint>().unwrap(), Breakpoint::Line(12));
+        assert_eq!(
+            "a".parse::<Breakpoint>().unwrap(),
+            Breakpoint::Label("a".to_string())
+        );
+        assert_eq!(
+            "b:".parse::<Breakpoint>().unwrap(),
+            Breakpoint::Label("b".to_string())
+        );
+    }
+
+    #[test]
+    fn test_breakpoint_resolve() {
+        let script = SCRIPT.parse::<ScriptObject>().unwrap();
+        let first_instruction = Position {
+            block: 2,
+            instruction: 0,
+        };
+
+        assert_eq!(
+            Breakpoint::Label("a".to_string()).resolve(&script),
+            Some(first_instruction)
+        );
+        assert_eq!(
+            Breakpoint::Label("b".to_string()).resolve(&script),
+            Some(first_instruction)
+        );
+        assert_eq!(
+            Breakpoint::Line(6).resolve(&script),
+            Some(Position {
+                block: 2,
+                instruction: 1
+            })
+        );
+        assert_eq!(Breakpoint::Label("z".to_string()).resolve(&script), None);
+        assert_eq!(Breakpoint::Line(100).resolve(&script), None);
+    }
+}
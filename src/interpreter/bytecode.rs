@@ -0,0 +1,104 @@
+use crate::script_object::{instruction::Instruction, Position, ScriptObject};
+
+/// An instruction of the flat program, with what is needed to execute it without looking at the blocks.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Op {
+    pub instruction: Instruction,
+    /// The position of the instruction in the script
+    pub position: Position,
+    /// For a jump, the index of the op to jump to (the length of the program if there's nothing after the target).
+    /// None if the target doesn't exist.
+    pub jump: Option<usize>,
+}
+
+/// The script lowered into a flat array of instructions, in the order of the script,
+/// in which jumps are absolute indices (the empty blocks and the block boundaries disappear).
+#[derive(Debug, PartialEq)]
+pub(crate) struct Bytecode {
+    pub ops: Vec<Op>,
+    /// The index of the first op of each block or of a following block,
+    /// plus one last index for the end of the script
+    block_starts: Vec<usize>,
+}
+
+impl Bytecode {
+    /// Lower the script. It's done once per script, see [`ScriptObject::bytecode`]
+    pub fn compile(script: &ScriptObject) -> Self {
+        let mut ops = vec![];
+        let mut block_starts = vec![];
+        for block in script.blocks() {
+            block_starts.push(ops.len());
+            ops.extend(block.instructions.iter().enumerate().map(
+                |(instruction_index, instruction)| Op {
+                    instruction: instruction.clone(),
+                    position: Position {
+                        block: block.index(),
+                        instruction: instruction_index,
+                    },
+                    jump: None,
+                },
+            ));
+        }
+        block_starts.push(ops.len());
+
+        for op in &mut ops {
+            if let Some(target) = op.instruction.jump_target() {
+                op.jump = target.block().map(|block| block_starts[block]);
+            }
+        }
+
+        Self { ops, block_starts }
+    }
+
+    /// The index of the op to execute from the given position of the script
+    pub fn index_of(&self, position: Position) -> usize {
+        match self.block_starts.get(position.block) {
+            Some(start) => (start + position.instruction).min(self.ops.len()),
+            None => self.ops.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod bytecode_tests {
+    use super::*;
+
+    #[test]
+    fn test_compile() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+        a:
+        b:
+            OUTBOX
+            JUMPZ    a
+            JUMP     c
+        c:
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        let bytecode = Bytecode::compile(&script);
+
+        let jumps = bytecode.ops.iter().map(|op| op.jump).collect::<Vec<_>>();
+        assert_eq!(jumps, vec![None, None, Some(1), Some(4)]);
+        assert_eq!(
+            bytecode.ops[2].position,
+            Position {
+                block: 2,
+                instruction: 1
+            }
+        );
+        assert_eq!(bytecode.index_of(Position::default()), 0);
+        assert_eq!(
+            bytecode.index_of(Position {
+                block: 1,
+                instruction: 0
+            }),
+            1
+        );
+        assert_eq!(bytecode.index_of(script.end_position()), 4);
+
+        // The script keeps its bytecode for the next executions
+        assert_eq!(script.bytecode(), &bytecode);
+        assert!(std::ptr::eq(script.bytecode(), script.bytecode()));
+    }
+}
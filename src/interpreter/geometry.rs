@@ -0,0 +1,138 @@
+//! The geometry of the floor: in the game, the tiles are laid out in rows,
+//! and they can be named by their row and column (`r2c3`) rather than by their address.
+
+use std::{fmt::Display, str::FromStr};
+
+use crate::script_object::value_box::TileNames;
+
+/// Above this number of tiles, a floor is too big to name all its tiles
+const MAX_TILES: usize = 1 << 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A floor of `rows` rows of `columns` tiles, numbered row by row from 0:
+/// the tile at row 2 and column 3 of a 5x5 floor (`r2c3`) is the tile 13.
+pub struct FloorGeometry {
+    pub columns: usize,
+    pub rows: usize,
+}
+
+#[derive(Debug, thiserror::Error)]
+/// Error that can occur when parsing a floor geometry.
+pub enum ParseFloorGeometryError {
+    #[error("invalid floor: {0} (expected <columns>x<rows>, like 5x5)")]
+    InvalidSize(String),
+    #[error("the floor {0} has too many tiles (at most {MAX_TILES})")]
+    TooManyTiles(String),
+}
+
+impl FromStr for FloorGeometry {
+    type Err = ParseFloorGeometryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let size = |n: &str| n.parse::<usize>().ok().filter(|n| *n > 0);
+        let (columns, rows) = s
+            .split_once('x')
+            .and_then(|(columns, rows)| Some((size(columns)?, size(rows)?)))
+            .ok_or_else(|| ParseFloorGeometryError::InvalidSize(s.to_string()))?;
+        if columns
+            .checked_mul(rows)
+            .is_none_or(|tiles| tiles > MAX_TILES)
+        {
+            return Err(ParseFloorGeometryError::TooManyTiles(s.to_string()));
+        }
+        Ok(Self { columns, rows })
+    }
+}
+
+impl FloorGeometry {
+    /// The last tile number of the floor
+    pub fn max_address(&self) -> usize {
+        self.columns * self.rows - 1
+    }
+
+    /// The name of the tile at the address: "r2c3"
+    pub fn tile_name(&self, address: usize) -> String {
+        format!("r{}c{}", address / self.columns, address % self.columns)
+    }
+
+    /// The names of all the tiles, to use them in the scripts like the names given to tiles
+    pub fn tile_names(&self) -> TileNames {
+        (0..=self.max_address())
+            .map(|address| (self.tile_name(address), address))
+            .collect()
+    }
+
+    /// The floor as a grid, a line per row, with the numbers of the rows and columns.
+    /// `tile` gives what to show on a tile (its value, its number of accesses...),
+    /// and the empty tiles are shown as '.'.
+    pub fn render<T: Display>(&self, tile: impl Fn(usize) -> Option<T>) -> String {
+        let cells = (0..=self.max_address())
+            .map(|address| tile(address).map_or(".".to_string(), |value| value.to_string()))
+            .collect::<Vec<_>>();
+        let column_header = |column: usize| format!("c{}", column);
+        let width = cells
+            .iter()
+            .map(String::len)
+            .chain((0..self.columns).map(|column| column_header(column).len()))
+            .max()
+            .unwrap_or_default();
+        let row_header_width = format!("r{}", self.rows - 1).len();
+
+        let mut grid = format!("{:row_header_width$}", "");
+        for column in 0..self.columns {
+            grid += &format!(" {:>width$}", column_header(column));
+        }
+        for (row, row_cells) in cells.chunks(self.columns).enumerate() {
+            grid += &format!("\n{:<row_header_width$}", format!("r{}", row));
+            for cell in row_cells {
+                grid += &format!(" {:>width$}", cell);
+            }
+        }
+        grid + "\n"
+    }
+}
+
+#[cfg(test)]
+mod geometry_tests {
+    use super::*;
+    use crate::script_object::value_box::ValueBox;
+
+    #[test]
+    fn test_parse_floor_geometry() {
+        assert_eq!(
+            "5x3".parse::<FloorGeometry>().unwrap(),
+            FloorGeometry {
+                columns: 5,
+                rows: 3
+            }
+        );
+        assert!("5".parse::<FloorGeometry>().is_err());
+        assert!("0x3".parse::<FloorGeometry>().is_err());
+        assert!(matches!(
+            "1000x1000".parse::<FloorGeometry>(),
+            Err(ParseFloorGeometryError::TooManyTiles(_))
+        ));
+    }
+
+    #[test]
+    fn test_tile_names() {
+        let floor = "5x5".parse::<FloorGeometry>().unwrap();
+        assert_eq!(floor.max_address(), 24);
+        assert_eq!(floor.tile_name(13), "r2c3");
+        let tile_names = floor.tile_names();
+        assert_eq!(tile_names.len(), 25);
+        assert_eq!(tile_names["r2c3"], 13);
+        assert_eq!(tile_names["r4c4"], 24);
+    }
+
+    #[test]
+    fn test_render() {
+        let floor = "3x2".parse::<FloorGeometry>().unwrap();
+        let grid = floor.render(|address| match address {
+            0 => Some(ValueBox::Number(-12)),
+            4 => Some(ValueBox::Character('A')),
+            _ => None,
+        });
+        assert_eq!(grid, "    c0  c1  c2\nr0 -12   .   .\nr1   .   A   .\n");
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::VecDeque;
+
+use crate::script_object::{value_box::ValueBox, Position};
+
+/// The state of the interpreter before an executed instruction.
+/// Only the tile the instruction could write is saved, as it's the only one that can change.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Snapshot {
+    pub position: Position,
+    pub head: Option<ValueBox>,
+    pub second_hand: Option<ValueBox>,
+    pub next_input: usize,
+    pub outputs_len: usize,
+    /// The address of the tile accessed by the instruction, and its value
+    pub tile: Option<(usize, Option<ValueBox>)>,
+    /// The positions to return to, saved only if the instruction changes them (CALL and RET)
+    pub calls: Option<Vec<Position>>,
+    /// The kind of the instruction, to rewind the statistics
+    pub instruction: &'static str,
+}
+
+/// Ring buffer of the states before the last executed instructions,
+/// so that the execution can be rewound.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct History {
+    snapshots: VecDeque<Snapshot>,
+    capacity: usize,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Save a state, forgetting the oldest one if the history is full
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if !self.is_enabled() {
+            return;
+        }
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    /// Take the most recent state
+    pub fn pop(&mut self) -> Option<Snapshot> {
+        self.snapshots.pop_back()
+    }
+
+    /// Forget all the states, keeping the buffer
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+}
+
+#[cfg(test)]
+mod history_tests {
+    use super::*;
+
+    fn snapshot(next_input: usize) -> Snapshot {
+        Snapshot {
+            position: Position::default(),
+            head: None,
+            second_hand: None,
+            next_input,
+            outputs_len: 0,
+            tile: None,
+            calls: None,
+            instruction: "INBOX",
+        }
+    }
+
+    #[test]
+    fn test_history_forgets_oldest_states() {
+        let mut history = History::new(2);
+        history.push(snapshot(0));
+        history.push(snapshot(1));
+        history.push(snapshot(2));
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.pop(), Some(snapshot(2)));
+        assert_eq!(history.pop(), Some(snapshot(1)));
+        assert_eq!(history.pop(), None);
+    }
+
+    #[test]
+    fn test_disabled_history() {
+        let mut history = History::default();
+        history.push(snapshot(0));
+
+        assert!(!history.is_enabled());
+        assert_eq!(history.len(), 0);
+        assert!(History::new(1).is_enabled());
+    }
+}
@@ -0,0 +1,189 @@
+use crate::script_object::value_box::ValueBox;
+
+/// Where the INBOX instruction reads its values from.
+///
+/// Inputs are read by index (0 for the first input), in increasing order,
+/// except after rewinding the execution, when previous inputs are read again.
+pub trait InputSource {
+    /// The input at the given index, or None if there are no more inputs
+    fn input(&mut self, index: usize) -> Option<ValueBox>;
+
+    /// The inputs from the given index to the end, to show the state of the interpreter.
+    /// None if they are not known in advance.
+    fn remaining(&self, _from: usize) -> Option<Vec<ValueBox>> {
+        None
+    }
+}
+
+impl InputSource for &[ValueBox] {
+    fn input(&mut self, index: usize) -> Option<ValueBox> {
+        self.get(index).copied()
+    }
+
+    fn remaining(&self, from: usize) -> Option<Vec<ValueBox>> {
+        Some(self.get(from..).unwrap_or_default().to_vec())
+    }
+}
+
+impl InputSource for &Vec<ValueBox> {
+    fn input(&mut self, index: usize) -> Option<ValueBox> {
+        self.as_slice().input(index)
+    }
+
+    fn remaining(&self, from: usize) -> Option<Vec<ValueBox>> {
+        self.as_slice().remaining(from)
+    }
+}
+
+impl<const N: usize> InputSource for &[ValueBox; N] {
+    fn input(&mut self, index: usize) -> Option<ValueBox> {
+        self.as_slice().input(index)
+    }
+
+    fn remaining(&self, from: usize) -> Option<Vec<ValueBox>> {
+        self.as_slice().remaining(from)
+    }
+}
+
+impl<S: InputSource + ?Sized> InputSource for &mut S {
+    fn input(&mut self, index: usize) -> Option<ValueBox> {
+        (**self).input(index)
+    }
+
+    fn remaining(&self, from: usize) -> Option<Vec<ValueBox>> {
+        (**self).remaining(from)
+    }
+}
+
+/// Where the OUTBOX instruction drops its values.
+pub trait OutputSink {
+    /// Called each time a value is dropped on the output belt
+    fn output(&mut self, value: ValueBox);
+
+    /// Take back the outputs after the first `count` ones, when the execution is rewound.
+    /// Outputs that were already sent somewhere can't be taken back: by default, nothing is done.
+    fn rewind_to(&mut self, _count: usize) {}
+
+    /// The outputs so far, to show the state of the interpreter. None if they are not kept.
+    fn outputs(&self) -> Option<&[ValueBox]> {
+        None
+    }
+}
+
+impl OutputSink for Vec<ValueBox> {
+    fn output(&mut self, value: ValueBox) {
+        self.push(value);
+    }
+
+    fn rewind_to(&mut self, count: usize) {
+        self.truncate(count);
+    }
+
+    fn outputs(&self) -> Option<&[ValueBox]> {
+        Some(self)
+    }
+}
+
+impl<S: OutputSink + ?Sized> OutputSink for &mut S {
+    fn output(&mut self, value: ValueBox) {
+        (**self).output(value)
+    }
+
+    fn rewind_to(&mut self, count: usize) {
+        (**self).rewind_to(count)
+    }
+
+    fn outputs(&self) -> Option<&[ValueBox]> {
+        (**self).outputs()
+    }
+}
+
+/// Outputs handed to a function as soon as they are produced (printed, sent to a stream...).
+pub struct OutputFn<F: FnMut(ValueBox)>(pub F);
+
+impl<F: FnMut(ValueBox)> OutputSink for OutputFn<F> {
+    fn output(&mut self, value: ValueBox) {
+        (self.0)(value)
+    }
+}
+
+/// Inputs produced one by one, only when INBOX needs them
+/// (read from a stream, asked to a human, generated...).
+///
+/// The produced values are kept, so that they can be read again after rewinding the execution.
+pub struct LazyInputs<F: FnMut() -> Option<ValueBox>> {
+    produce: F,
+    produced: Vec<ValueBox>,
+    exhausted: bool,
+}
+
+impl<F: FnMut() -> Option<ValueBox>> LazyInputs<F> {
+    /// `produce` returns the next input, or None when there are no more inputs
+    pub fn new(produce: F) -> Self {
+        Self {
+            produce,
+            produced: Vec::new(),
+            exhausted: false,
+        }
+    }
+}
+
+impl<F: FnMut() -> Option<ValueBox>> InputSource for LazyInputs<F> {
+    fn input(&mut self, index: usize) -> Option<ValueBox> {
+        while self.produced.len() <= index && !self.exhausted {
+            match (self.produce)() {
+                Some(value) => self.produced.push(value),
+                None => self.exhausted = true,
+            }
+        }
+        self.produced.get(index).copied()
+    }
+
+    fn remaining(&self, from: usize) -> Option<Vec<ValueBox>> {
+        // Only known once everything has been produced
+        self.exhausted
+            .then(|| self.produced.get(from..).unwrap_or_default().to_vec())
+    }
+}
+
+#[cfg(test)]
+mod io_tests {
+    use super::*;
+
+    #[test]
+    fn test_slice_input_source() {
+        let mut inputs: &[ValueBox] = &[ValueBox::from(1), ValueBox::from('A')];
+
+        assert_eq!(inputs.input(1), Some(ValueBox::from('A')));
+        assert_eq!(inputs.input(2), None);
+        assert_eq!(inputs.remaining(1), Some(vec![ValueBox::from('A')]));
+        assert_eq!(inputs.remaining(5), Some(vec![]));
+    }
+
+    #[test]
+    fn test_vec_output_sink() {
+        let mut outputs = vec![];
+        outputs.output(ValueBox::from(1));
+        outputs.output(ValueBox::from(2));
+        outputs.rewind_to(1);
+
+        assert_eq!(outputs.outputs(), Some([ValueBox::from(1)].as_slice()));
+    }
+
+    #[test]
+    fn test_lazy_inputs() {
+        let mut calls = 0;
+        let mut inputs = LazyInputs::new(|| {
+            calls += 1;
+            (calls <= 2).then_some(ValueBox::from(calls))
+        });
+
+        assert_eq!(inputs.input(0), Some(ValueBox::from(1)));
+        assert_eq!(inputs.remaining(0), None);
+        // Read again after a rewind
+        assert_eq!(inputs.input(0), Some(ValueBox::from(1)));
+        assert_eq!(inputs.input(1), Some(ValueBox::from(2)));
+        assert_eq!(inputs.input(2), None);
+        assert_eq!(inputs.remaining(1), Some(vec![ValueBox::from(2)]));
+    }
+}
@@ -1,39 +1,139 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
 
 use crate::script_object::value_box::{ValueBox, ValueBoxMemoryAddress};
 
 /// The memory is the component that holds the ValueBoxes placed on the floor.
 /// A key feature of Human Resource Machine is that the memory can be (very) limited in size.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "MemoryData", try_from = "MemoryData")]
 pub struct Memory {
-    data: HashMap<usize, ValueBox>,
+    data: Tiles,
     max_address: usize,
+    /// The number of tiles holding a value
+    used: usize,
+}
+
+/// Above this number of tiles, the memory is backed by a HashMap even if it's bounded
+pub(crate) const MAX_VEC_TILES: usize = 1 << 16;
+
+/// How the values on the floor are stored
+#[derive(Debug, Clone)]
+enum Tiles {
+    /// For an unbounded (or huge) memory
+    Map(HashMap<usize, ValueBox>),
+    /// For a bounded memory: one slot per tile, to avoid hashing the addresses
+    Vec(Vec<Option<ValueBox>>),
+}
+
+impl Tiles {
+    fn new(data: HashMap<usize, ValueBox>, max_address: usize) -> Self {
+        if max_address >= MAX_VEC_TILES {
+            return Tiles::Map(data);
+        }
+        let mut tiles = vec![None; max_address + 1];
+        // Values out of bounds are unreachable anyway (see `Memory::with_data`)
+        for (address, value) in data {
+            if let Some(tile) = tiles.get_mut(address) {
+                *tile = Some(value);
+            }
+        }
+        Tiles::Vec(tiles)
+    }
 }
 
 impl Default for Memory {
     fn default() -> Self {
         Self {
-            data: HashMap::new(),
+            data: Tiles::Map(HashMap::new()),
             max_address: usize::MAX,
+            used: 0,
         }
     }
 }
 
 impl Memory {
+    /// Check that the values are on a floor whose last address is `max_address`,
+    /// before building a memory from values given by the user
+    pub fn check_tiles(
+        data: &HashMap<usize, ValueBox>,
+        max_address: usize,
+    ) -> Result<(), SetMemoryError> {
+        match data.keys().filter(|address| **address > max_address).min() {
+            Some(&address) => Err(SetMemoryError::OutOfBounds {
+                address,
+                max_address,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// The memory holding the values, which must be on the floor (see [`Memory::check_tiles`])
     pub fn with_data(data: HashMap<usize, ValueBox>, max_address: usize) -> Self {
         #[cfg(debug_assertions)]
-        {
-            for address in data.keys() {
-                if address > &max_address {
-                    panic!("WARNING! You have built a memory with at least 1 invalid memory address:Memory address {address} out of bounds (accepted: [1, {}])", max_address);
-                }
-            }
+        if let Err(e) = Self::check_tiles(&data, max_address) {
+            panic!(
+                "WARNING! You have built a memory with at least 1 invalid memory address:{}",
+                e
+            );
         }
-        Self { data, max_address }
+        let mut memory = Self {
+            data: Tiles::new(data, max_address),
+            max_address,
+            used: 0,
+        };
+        memory.used = memory.addresses().count();
+        memory
     }
 
     pub fn get_max_address(&self) -> usize {
         self.max_address
     }
+
+    /// The number of tiles holding a value
+    pub fn used_tiles(&self) -> usize {
+        self.used
+    }
+
+    /// How the tiles are stored, for benchmarks: "vec" (one slot per tile) or "map"
+    pub fn storage(&self) -> &'static str {
+        match self.data {
+            Tiles::Map(_) => "map",
+            Tiles::Vec(_) => "vec",
+        }
+    }
+}
+
+/// The serialized form of a memory: the bound, and the tiles holding a value
+#[derive(Serialize, Deserialize)]
+struct MemoryData {
+    max_address: usize,
+    tiles: BTreeMap<usize, ValueBox>,
+}
+
+impl From<Memory> for MemoryData {
+    fn from(memory: Memory) -> Self {
+        Self {
+            max_address: memory.max_address,
+            tiles: memory
+                .addresses()
+                .map(|address| (address, *memory.get(&address).unwrap()))
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<MemoryData> for Memory {
+    type Error = SetMemoryError;
+
+    fn try_from(data: MemoryData) -> Result<Self, Self::Error> {
+        let mut memory = Memory::with_data(HashMap::new(), data.max_address);
+        for (address, value) in data.tiles {
+            memory.set(&address, Some(value))?;
+        }
+        Ok(memory)
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -61,9 +161,25 @@ impl Memory {
         at_address <= &self.max_address
     }
 
+    /// Get the addresses of all the tiles holding a value, in no particular order.
+    pub fn addresses(&self) -> Box<dyn Iterator<Item = usize> + '_> {
+        match &self.data {
+            Tiles::Map(tiles) => Box::new(tiles.keys().copied()),
+            Tiles::Vec(tiles) => Box::new(
+                tiles
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(address, value)| value.map(|_| address)),
+            ),
+        }
+    }
+
     /// Get the value at the given address.
     pub fn get(&self, address: &usize) -> Option<&ValueBox> {
-        self.data.get(address)
+        match &self.data {
+            Tiles::Map(tiles) => tiles.get(address),
+            Tiles::Vec(tiles) => tiles.get(*address)?.as_ref(),
+        }
     }
 
     /// Get the value at the given "value box memory address",
@@ -85,13 +201,17 @@ impl Memory {
             });
         }
 
-        match value {
-            Some(value) => {
-                self.data.insert(*address, value);
-            }
-            None => {
-                self.data.remove(address);
-            }
+        let filled = value.is_some();
+        let was_filled = match (&mut self.data, value) {
+            (Tiles::Map(tiles), Some(value)) => tiles.insert(*address, value).is_some(),
+            (Tiles::Map(tiles), None) => tiles.remove(address).is_some(),
+            // The address is valid, so it has a slot
+            (Tiles::Vec(tiles), value) => std::mem::replace(&mut tiles[*address], value).is_some(),
+        };
+        match (was_filled, filled) {
+            (false, true) => self.used += 1,
+            (true, false) => self.used -= 1,
+            _ => {}
         }
         Ok(())
     }
@@ -182,13 +302,58 @@ mod memory_tests {
         data.insert(1, ValueBox::from(42));
         let memory = Memory::with_data(data, 10);
 
-        assert_eq!(memory.data.get(&1), Some(&ValueBox::from(42)));
+        assert_eq!(memory.get(&1), Some(&ValueBox::from(42)));
+    }
+
+    #[test]
+    fn test_check_tiles() {
+        let data = HashMap::from([(3, ValueBox::from(1)), (9, 'A'.into()), (7, 'B'.into())]);
+        assert!(Memory::check_tiles(&data, 9).is_ok());
+        assert!(matches!(
+            Memory::check_tiles(&data, 4),
+            Err(SetMemoryError::OutOfBounds {
+                address: 7,
+                max_address: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn test_bounded_memory_is_vec_backed() {
+        let data = HashMap::from([(3, ValueBox::from('A'))]);
+        let mut bounded = Memory::with_data(data.clone(), 5);
+        let unbounded = Memory::with_data(data, usize::MAX);
+
+        assert!(matches!(bounded.data, Tiles::Vec(ref tiles) if tiles.len() == 6));
+        assert!(matches!(unbounded.data, Tiles::Map(_)));
+
+        bounded.set(&5, Some(ValueBox::from(1))).unwrap();
+        bounded.set(&3, None).unwrap();
+        assert_eq!(bounded.get(&5), Some(&ValueBox::from(1)));
+        assert_eq!(bounded.get(&3), None);
+        assert_eq!(bounded.addresses().collect::<Vec<_>>(), vec![5]);
+        assert!(bounded.set(&6, Some(ValueBox::from(1))).is_err());
+        assert_eq!((bounded.used_tiles(), unbounded.used_tiles()), (1, 1));
+    }
+
+    #[test]
+    fn test_memory_serialization() {
+        let memory = Memory::with_data(HashMap::from([(3, ValueBox::from('A'))]), 5);
+
+        let json = serde_json::to_string(&memory).unwrap();
+        assert_eq!(json, r#"{"max_address":5,"tiles":{"3":"A"}}"#);
+
+        let memory = serde_json::from_str::<Memory>(&json).unwrap();
+        assert_eq!(memory.get(&3), Some(&ValueBox::from('A')));
+        assert!(serde_json::from_str::<Memory>(r#"{"max_address":2,"tiles":{"3":1}}"#).is_err());
     }
 
     #[test]
     fn test_memory_can_set() {
-        let mut memory = Memory::default();
-        memory.max_address = 10;
+        let memory = Memory {
+            max_address: 10,
+            ..Default::default()
+        };
 
         assert!(memory.is_valid_memory_address(&1));
         assert!(memory.is_valid_memory_address(&0));
@@ -215,8 +380,10 @@ mod memory_tests {
     #[test]
     #[should_panic]
     fn test_memory_set_out_of_bounds() {
-        let mut memory = Memory::default();
-        memory.max_address = 10;
+        let mut memory = Memory {
+            max_address: 10,
+            ..Default::default()
+        };
         memory.set(&11, Some(ValueBox::from(42))).unwrap();
     }
 }
@@ -0,0 +1,63 @@
+use crate::script_object::{instruction::Instruction, value_box::ValueBox, Position};
+
+/// An instruction was executed successfully.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepEvent<'a> {
+    /// The number of the step (the first executed instruction is step 1)
+    pub step: usize,
+    pub position: Position,
+    pub instruction: &'a Instruction,
+    pub head_before: Option<ValueBox>,
+    pub head_after: Option<ValueBox>,
+}
+
+/// A jump instruction sent the execution to another block.
+/// Conditional jumps that aren't taken don't produce this event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JumpEvent<'a> {
+    pub step: usize,
+    /// The position of the jump instruction
+    pub from: Position,
+    /// The start of the target block (instruction 0)
+    pub to: Position,
+    pub label: &'a str,
+}
+
+/// A tile was written by COPYTO, BUMPUP or BUMPDOWN.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemoryWriteEvent {
+    pub step: usize,
+    pub position: Position,
+    pub address: usize,
+    pub before: Option<ValueBox>,
+    pub after: Option<ValueBox>,
+}
+
+/// A value was dropped on the output belt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputEvent {
+    pub step: usize,
+    pub position: Position,
+    /// The index of the output (0 for the first output)
+    pub index: usize,
+    pub value: ValueBox,
+}
+
+/// Notified of what happens during the execution, when registered with
+/// [`crate::interpreter::Interpreter::add_observer`].
+///
+/// All the methods do nothing by default, so that only the interesting events are handled.
+/// For one instruction, `on_memory_write`, `on_output` and `on_jump` are called before `on_step`.
+pub trait Observer {
+    /// Called after each successfully executed instruction
+    fn on_step(&mut self, _event: &StepEvent) {}
+
+    /// Called when a jump is taken
+    fn on_jump(&mut self, _event: &JumpEvent) {}
+
+    /// Called when a tile is written
+    fn on_memory_write(&mut self, _event: &MemoryWriteEvent) {}
+
+    /// Called when a value is dropped on the output belt
+    fn on_output(&mut self, _event: &OutputEvent) {}
+}
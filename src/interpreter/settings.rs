@@ -0,0 +1,48 @@
+use std::{ops::Range, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// Settings changing the way the interpreter executes a script.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Settings {
+    /// Maximum number of instructions to execute before aborting.
+    /// Catches scripts stuck in infinite loops.
+    pub max_steps: Option<usize>,
+    /// Maximum time an execution (`execute` or `run`) can take before aborting.
+    /// Catches the scripts that are too slow even within the step limit.
+    /// The clock isn't available on `wasm32-unknown-unknown`, where it must stay None.
+    #[serde(default)]
+    pub timeout: Option<Duration>,
+    /// Behave exactly like the game: numbers are bound to [-999, 999]
+    /// and going past these bounds is an error.
+    pub strict_hrm: bool,
+    /// Number of executed instructions that can be rewound with `Interpreter::rewind`.
+    /// 0 disables the history, which is the fastest.
+    pub history_size: usize,
+    /// The tiles on which PUSH and POP (extensions) put and take values, filled from the start.
+    /// None if the script has no stack.
+    #[serde(default)]
+    pub stack: Option<Range<usize>>,
+    /// The worker has a second hand, used by TCOPYTO, TCOPYFROM and XCHG (extensions)
+    #[serde(default)]
+    pub second_hand: bool,
+    /// Bounds on what the execution can hold, for the scripts that can't be trusted
+    #[serde(default)]
+    pub limits: Limits,
+}
+
+/// Bounds on the resources used by an execution, so that a script can't exhaust the host
+/// (along with `max_steps` and `timeout`). Going past one of them aborts the execution.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    /// Maximum number of tiles holding a value at once.
+    /// Catches the scripts filling an unbounded floor.
+    pub max_tiles: Option<usize>,
+    /// Maximum number of values dropped on the output belt.
+    /// Catches the scripts outputting in an infinite loop.
+    pub max_outputs: Option<usize>,
+    /// Maximum number of values held by the execution at once:
+    /// the tiles holding a value, the outputs and the pending CALLs.
+    pub max_allocations: Option<usize>,
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+use crate::script_object::{value_box::ValueBox, Position};
+
+use super::{breakpoint::Breakpoint, memory::Memory, settings::Settings, stats::ExecutionStats};
+
+/// The state of an interpreter, taken with [`crate::interpreter::Interpreter::snapshot`],
+/// that can be saved (to JSON for instance) and restored later
+/// with [`crate::interpreter::Interpreter::from_snapshot`].
+///
+/// The inputs and the outputs aren't part of the interpreter, so they're not saved:
+/// the execution must be resumed with the same inputs.
+/// The tracer, the observers and the history aren't saved either.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterpreterSnapshot {
+    pub memory: Memory,
+    pub head: Option<ValueBox>,
+    /// The value in the second hand (extension)
+    #[serde(default)]
+    pub second_hand: Option<ValueBox>,
+    /// The index of the next input to be read
+    pub next_input: usize,
+    /// The number of values dropped on the output belt so far
+    pub outputs_count: usize,
+    /// The position of the next instruction to be executed
+    pub position: Position,
+    /// The positions to return to, of the CALLs in progress
+    #[serde(default)]
+    pub calls: Vec<Position>,
+    /// Set when the execution was paused, so that resuming doesn't pause again on the same breakpoint
+    pub resuming: bool,
+    pub stats: ExecutionStats,
+    pub settings: Settings,
+    pub breakpoints: Vec<Breakpoint>,
+    pub watchpoints: Vec<usize>,
+}
@@ -0,0 +1,104 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::script_object::{instruction::Instruction, Position};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+/// Statistics about the execution of a script.
+/// In HRM, the number of steps is one of the two scores of a solution.
+pub struct ExecutionStats {
+    /// Total number of instructions executed
+    pub steps: usize,
+    /// Number of instructions executed, per instruction kind (INBOX, COPYTO...)
+    #[serde(deserialize_with = "deserialize_instruction_counts")]
+    pub instructions: BTreeMap<&'static str, usize>,
+    /// Number of times each block has been entered, by label.
+    /// Empty blocks are never counted, as no instruction is executed in them.
+    pub blocks: BTreeMap<String, usize>,
+    /// Number of times each instruction has been executed, by block index and index in the block.
+    /// The instructions after the last executed one of a block may be missing.
+    #[serde(default)]
+    pub executions: Vec<Vec<usize>>,
+    /// Number of reads and writes of each tile of the floor, by address
+    #[serde(default)]
+    pub tiles: BTreeMap<usize, TileAccesses>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+/// The number of times a tile has been read and written.
+/// BUMPUP, BUMPDN and SWAP both read and write their tile.
+pub struct TileAccesses {
+    pub reads: usize,
+    pub writes: usize,
+}
+
+impl TileAccesses {
+    pub fn total(&self) -> usize {
+        self.reads + self.writes
+    }
+}
+
+impl ExecutionStats {
+    /// Forget the executions, keeping the counts of the executions of the instructions allocated
+    pub(crate) fn reset(&mut self) {
+        self.steps = 0;
+        self.instructions.clear();
+        self.blocks.clear();
+        self.tiles.clear();
+        for block in &mut self.executions {
+            block.fill(0);
+        }
+    }
+
+    /// Number of times the instruction at the given position has been executed
+    pub fn executions_of(&self, position: Position) -> usize {
+        self.executions
+            .get(position.block)
+            .and_then(|block| block.get(position.instruction))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+/// Read the instruction counts, keyed by the static names of the instructions
+fn deserialize_instruction_counts<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<&'static str, usize>, D::Error> {
+    BTreeMap::<String, usize>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(name, count)| {
+            Instruction::NAMES
+                .into_iter()
+                .chain(Instruction::EXTENSION_NAMES)
+                .find(|known| *known == name)
+                .map(|name| (name, count))
+                .ok_or_else(|| serde::de::Error::custom(format!("unknown instruction {}", name)))
+        })
+        .collect()
+}
+
+impl Display for ExecutionStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Steps: {}", self.steps)?;
+        writeln!(f, "Instructions:")?;
+        for (instruction, count) in &self.instructions {
+            writeln!(f, "  {: <10} {}", instruction, count)?;
+        }
+        write!(f, "Blocks:")?;
+        for (label, count) in &self.blocks {
+            write!(f, "\n  {: <10} {}", label, count)?;
+        }
+        if !self.tiles.is_empty() {
+            write!(f, "\nTiles:")?;
+            for (address, accesses) in &self.tiles {
+                write!(
+                    f,
+                    "\n  {: <10} {} reads, {} writes",
+                    address, accesses.reads, accesses.writes
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use std::fmt::Display;
+
+use crate::script_object::{value_box::ValueBox, Position};
+
+/// A memory tile accessed by an instruction, with its value before and after the instruction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileAccess {
+    pub address: usize,
+    pub before: Option<ValueBox>,
+    pub after: Option<ValueBox>,
+}
+
+/// What happened during one step of the execution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEntry {
+    /// The number of the step (the first executed instruction is step 1)
+    pub step: usize,
+    pub position: Position,
+    /// The line of the instruction in the script
+    pub line: usize,
+    /// The name of the block of the instruction
+    pub block: String,
+    /// The instruction as written in the script
+    pub instruction: String,
+    pub head_before: Option<ValueBox>,
+    pub head_after: Option<ValueBox>,
+    /// The tile read or written by the instruction, if any (and if its address could be resolved)
+    pub tile: Option<TileAccess>,
+}
+
+/// Called after each executed instruction, when registered with [`crate::interpreter::Interpreter::set_tracer`]
+pub type Tracer = Box<dyn FnMut(&TraceEntry) + Send>;
+
+/// Called with the position and the text of each executed DEBUG instruction (extension),
+/// when registered with [`crate::interpreter::Interpreter::set_debug_callback`]
+pub type DebugCallback = Box<dyn FnMut(Position, &str) + Send>;
+
+fn value_or_empty(value: Option<ValueBox>) -> String {
+    value.map_or("_".to_string(), |value| value.to_string())
+}
+
+impl Display for TraceEntry {
+    /// One line: step, line, block, instruction, head before -> after, and the tile touched
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "#{:<5} line {:<4} {:<10} {:<16} head: {} -> {}",
+            self.step,
+            self.line,
+            self.block,
+            self.instruction,
+            value_or_empty(self.head_before),
+            value_or_empty(self.head_after)
+        )?;
+        if let Some(tile) = self.tile {
+            write!(
+                f,
+                "  mem[{}]: {}",
+                tile.address,
+                value_or_empty(tile.before)
+            )?;
+            if tile.after != tile.before {
+                write!(f, " -> {}", value_or_empty(tile.after))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod trace_tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_entry_display() {
+        let entry = TraceEntry {
+            step: 3,
+            position: Position::default(),
+            line: 7,
+            block: "a".to_string(),
+            instruction: "BUMPUP 2".to_string(),
+            head_before: None,
+            head_after: Some(ValueBox::Number(5)),
+            tile: Some(TileAccess {
+                address: 2,
+                before: Some(ValueBox::Number(4)),
+                after: Some(ValueBox::Number(5)),
+            }),
+        };
+
+        assert_eq!(
+            entry.to_string(),
+            "#3     line 7    a          BUMPUP 2         head: _ -> 5  mem[2]: 4 -> 5"
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use std::fmt::Display;
+
+use crate::script_object::{value_box::ValueBox, Position};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How an instruction accessed a tile
+pub enum TileAccessKind {
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A watched tile was read or written by an instruction.
+pub struct WatchpointHit {
+    pub address: usize,
+    pub access: TileAccessKind,
+    pub before: Option<ValueBox>,
+    pub after: Option<ValueBox>,
+    /// The position of the instruction responsible
+    pub position: Position,
+    /// The instruction responsible, as written in the script
+    pub instruction: String,
+}
+
+/// Called each time a watched tile is accessed,
+/// when registered with [`crate::interpreter::Interpreter::set_watch_callback`]
+pub type WatchCallback = Box<dyn FnMut(&WatchpointHit) + Send>;
+
+impl Display for WatchpointHit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = |value: Option<ValueBox>| value.map_or("_".to_string(), |v| v.to_string());
+        match self.access {
+            TileAccessKind::Read => write!(
+                f,
+                "Tile {} read by {}: {}",
+                self.address,
+                self.instruction,
+                value(self.before)
+            ),
+            TileAccessKind::Write => write!(
+                f,
+                "Tile {} written by {}: {} -> {}",
+                self.address,
+                self.instruction,
+                value(self.before),
+                value(self.after)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod watchpoint_tests {
+    use super::*;
+
+    #[test]
+    fn test_watchpoint_hit_display() {
+        let mut hit = WatchpointHit {
+            address: 5,
+            access: TileAccessKind::Write,
+            before: None,
+            after: Some(ValueBox::Character('A')),
+            position: Position::default(),
+            instruction: "COPYTO 5".to_string(),
+        };
+
+        assert_eq!(hit.to_string(), "Tile 5 written by COPYTO 5: _ -> A");
+
+        hit.access = TileAccessKind::Read;
+        hit.before = hit.after;
+        hit.instruction = "ADD [2]".to_string();
+        assert_eq!(hit.to_string(), "Tile 5 read by ADD [2]: A");
+    }
+}
@@ -0,0 +1,57 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// Apply the function to all the items on `jobs` threads, each one taking the next item when it's done.
+/// The results are in the order of the items.
+pub fn parallel_map<T: Sync, R: Send>(
+    items: &[T],
+    jobs: usize,
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    if jobs <= 1 || items.len() <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<Option<R>>>());
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(items.len()) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(i) else {
+                    break;
+                };
+                let result = f(item);
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every item is processed"))
+        .collect()
+}
+
+#[cfg(test)]
+mod jobs_tests {
+    use super::*;
+
+    #[test]
+    fn test_parallel_map() {
+        let items = (0..100).collect::<Vec<u64>>();
+        let squares = items.iter().map(|i| i * i).collect::<Vec<_>>();
+
+        assert_eq!(parallel_map(&items, 1, |i| i * i), squares);
+        assert_eq!(parallel_map(&items, 8, |i| i * i), squares);
+        assert_eq!(parallel_map(&items[..1], 8, |i| i * i), vec![0]);
+        assert!(parallel_map(&[] as &[u64], 8, |i| i * i).is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+use std::{collections::HashMap, fs, io};
+
+use hrm_interpreter::script_object::value_box::ValueBox;
+use serde_json::Value;
+
+use crate::manifest::{parse_value, parse_values};
+
+#[derive(Debug, Clone, PartialEq)]
+/// A custom level, shared as a JSON file:
+///
+/// ```json
+/// {
+///     "name": "Double Trouble",
+///     "floor_size": 4,
+///     "floor": {"3": 0},
+///     "inbox": [1, 5, "A"],
+///     "outbox": [2, 10, "A"]
+/// }
+/// ```
+///
+/// Only `inbox` and `outbox` are mandatory.
+pub struct LevelFile {
+    pub name: String,
+    /// Number of tiles on the floor, None if the floor has no limit
+    pub floor_size: Option<usize>,
+    /// The values on the floor when the level starts, by address
+    pub floor: HashMap<usize, ValueBox>,
+    pub inbox: Vec<ValueBox>,
+    /// The outbox a correct solution produces for the inbox
+    pub outbox: Vec<ValueBox>,
+}
+
+#[derive(Debug, thiserror::Error)]
+/// Error that can occur when reading a level file.
+pub enum ReadLevelError {
+    #[error("could not read the level file:\n\t{0}")]
+    Io(#[from] io::Error),
+    #[error("invalid JSON:\n\t{0}")]
+    InvalidJson(#[from] serde_json::Error),
+    #[error("invalid level: {0}")]
+    InvalidLevel(String),
+}
+
+pub fn read_level(path: &str) -> Result<LevelFile, ReadLevelError> {
+    let content = fs::read_to_string(path)?;
+    parse_level(&serde_json::from_str(&content)?).map_err(ReadLevelError::InvalidLevel)
+}
+
+fn parse_level(level: &Value) -> Result<LevelFile, String> {
+    let name = match level.get("name") {
+        Some(Value::String(name)) => name.clone(),
+        Some(_) => return Err("'name' should be a string".to_string()),
+        None => "custom level".to_string(),
+    };
+    let floor_size = match level.get("floor_size") {
+        Some(size) => Some(
+            size.as_u64()
+                .filter(|size| *size > 0)
+                .map(|size| size as usize)
+                .ok_or("'floor_size' should be a number of tiles")?,
+        ),
+        None => None,
+    };
+
+    let mut floor = HashMap::new();
+    match level.get("floor") {
+        Some(Value::Object(tiles)) => {
+            for (address, value) in tiles {
+                let address = address
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|address| floor_size.is_none_or(|size| *address < size))
+                    .ok_or_else(|| format!("invalid floor address: {}", address))?;
+                floor.insert(address, parse_value(value, "floor")?);
+            }
+        }
+        Some(_) => return Err("'floor' should be an object of address: value".to_string()),
+        None => {}
+    }
+
+    let inbox = match level.get("inbox") {
+        Some(inbox) => parse_values(inbox, "inbox")?,
+        None => return Err("'inbox' is missing".to_string()),
+    };
+    let outbox = match level.get("outbox") {
+        Some(outbox) => parse_values(outbox, "outbox")?,
+        None => return Err("'outbox' is missing".to_string()),
+    };
+
+    Ok(LevelFile {
+        name,
+        floor_size,
+        floor,
+        inbox,
+        outbox,
+    })
+}
+
+#[cfg(test)]
+mod level_tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_level() {
+        let level = parse_level(&json!({
+            "name": "Double Trouble",
+            "floor_size": 4,
+            "floor": {"3": 0},
+            "inbox": [1, 5, "A"],
+            "outbox": [2, 10, "A"],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            level,
+            LevelFile {
+                name: "Double Trouble".to_string(),
+                floor_size: Some(4),
+                floor: HashMap::from([(3, ValueBox::from(0))]),
+                inbox: vec![ValueBox::from(1), ValueBox::from(5), ValueBox::from('A')],
+                outbox: vec![ValueBox::from(2), ValueBox::from(10), ValueBox::from('A')],
+            }
+        );
+
+        let minimal = parse_level(&json!({"inbox": [], "outbox": []})).unwrap();
+        assert_eq!(minimal.floor_size, None);
+        assert!(minimal.floor.is_empty());
+    }
+
+    #[test]
+    fn test_parse_invalid_level() {
+        assert!(parse_level(&json!({"inbox": [1]})).is_err());
+        assert!(parse_level(&json!({"inbox": [1], "outbox": [{}]})).is_err());
+        assert!(parse_level(&json!({
+            "floor_size": 2,
+            "floor": {"2": 0},
+            "inbox": [],
+            "outbox": [],
+        }))
+        .is_err());
+    }
+}
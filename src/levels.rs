@@ -0,0 +1,881 @@
+//! Catalog of the official levels ("years") of the game.
+//!
+//! Each level describes its floor (size and preset tiles),
+//! how to generate an inbox, and the outbox expected for a given inbox.
+
+use std::{collections::HashMap, ops::RangeInclusive};
+
+use rand::{seq::SliceRandom, Rng, RngCore};
+
+use crate::script_object::value_box::ValueBox;
+
+use ValueBox::{Character as C, Number as N};
+
+/// The values on the floor, by address
+pub type Floor = HashMap<usize, ValueBox>;
+
+/// A level of the game
+pub struct Level {
+    /// The number of the level (its "year" in the game)
+    pub number: usize,
+    pub name: &'static str,
+    /// Number of tiles on the floor.
+    /// Levels without floor still get one tile, as the interpreter's memory can't be empty.
+    pub floor_size: usize,
+    /// The values on the floor when the level starts, by address
+    preset_tiles: &'static [(usize, ValueBox)],
+    generate_inbox: fn(&mut dyn RngCore) -> Vec<ValueBox>,
+    /// Returns None if the inbox is not a valid inbox for the level
+    expected_outbox: fn(&[ValueBox], &Floor) -> Option<Vec<ValueBox>>,
+}
+
+impl Level {
+    /// The last tile number of the floor
+    pub fn max_address(&self) -> usize {
+        self.floor_size.saturating_sub(1)
+    }
+
+    /// The floor when the level starts
+    pub fn memory(&self) -> Floor {
+        self.preset_tiles.iter().copied().collect()
+    }
+
+    /// Generate a random inbox, like the ones the game uses
+    pub fn generate_inbox(&self, rng: &mut dyn RngCore) -> Vec<ValueBox> {
+        (self.generate_inbox)(rng)
+    }
+
+    /// The outbox a correct solution produces for the given inbox.
+    /// Returns None if the inbox doesn't fit the level (e.g. characters where numbers are expected).
+    pub fn expected_outbox(&self, inbox: &[ValueBox]) -> Option<Vec<ValueBox>> {
+        (self.expected_outbox)(inbox, &self.memory())
+    }
+}
+
+/// Get the level with the given number
+pub fn get_level(number: usize) -> Option<&'static Level> {
+    LEVELS.iter().find(|level| level.number == number)
+}
+
+/// All the levels, in the order of the game
+pub fn all_levels() -> &'static [Level] {
+    &LEVELS
+}
+
+// ==================== Helpers ====================
+
+fn number(value: &ValueBox) -> Option<i32> {
+    match value {
+        ValueBox::Number(n) => Some(*n),
+        ValueBox::Character(_) => None,
+    }
+}
+
+fn numbers(rng: &mut dyn RngCore, count: usize, range: RangeInclusive<i32>) -> Vec<ValueBox> {
+    (0..count)
+        .map(|_| ValueBox::Number(rng.gen_range(range.clone())))
+        .collect()
+}
+
+fn letters(rng: &mut dyn RngCore, count: usize, alphabet: &str) -> Vec<ValueBox> {
+    let alphabet = alphabet.chars().collect::<Vec<char>>();
+    (0..count)
+        .map(|_| ValueBox::Character(*alphabet.choose(rng).unwrap()))
+        .collect()
+}
+
+/// Numbers or letters, randomly
+fn numbers_or_letters(rng: &mut dyn RngCore, count: usize) -> Vec<ValueBox> {
+    (0..count)
+        .map(|_| {
+            if rng.gen_bool(0.5) {
+                numbers(rng, 1, -9..=9)[0]
+            } else {
+                letters(rng, 1, ALPHABET)[0]
+            }
+        })
+        .collect()
+}
+
+/// Strings separated by zeros
+fn zero_terminated_strings(
+    rng: &mut dyn RngCore,
+    count: usize,
+    generate_string: fn(&mut dyn RngCore) -> Vec<ValueBox>,
+) -> Vec<ValueBox> {
+    let mut inbox = vec![];
+    for _ in 0..count {
+        inbox.extend(generate_string(rng));
+        inbox.push(ValueBox::Number(0));
+    }
+    inbox
+}
+
+/// Split the inbox on zeros. An unterminated last string is ignored.
+fn strings(inbox: &[ValueBox]) -> Vec<&[ValueBox]> {
+    let mut strings = inbox
+        .split(|value| *value == ValueBox::Number(0))
+        .collect::<Vec<&[ValueBox]>>();
+    strings.pop();
+    strings
+}
+
+/// Apply an operation on each pair of the inbox. An incomplete last pair is ignored.
+fn map_pairs(
+    inbox: &[ValueBox],
+    operation: impl Fn(&ValueBox, &ValueBox) -> Option<Vec<ValueBox>>,
+) -> Option<Vec<ValueBox>> {
+    let mut outbox = vec![];
+    for pair in inbox.chunks_exact(2) {
+        outbox.extend(operation(&pair[0], &pair[1])?);
+    }
+    Some(outbox)
+}
+
+/// Apply an operation on each number of the inbox
+fn map_numbers(inbox: &[ValueBox], operation: impl Fn(i32) -> Vec<i32>) -> Option<Vec<ValueBox>> {
+    let mut outbox = vec![];
+    for value in inbox {
+        outbox.extend(operation(number(value)?).into_iter().map(ValueBox::Number));
+    }
+    Some(outbox)
+}
+
+/// Order of the values in the game: numbers in their order, letters in the alphabetical order
+fn compare(a: &ValueBox, b: &ValueBox) -> std::cmp::Ordering {
+    match (a, b) {
+        (N(a), N(b)) => a.cmp(b),
+        (C(a), C(b)) => a.cmp(b),
+        (N(_), C(_)) => std::cmp::Ordering::Less,
+        (C(_), N(_)) => std::cmp::Ordering::Greater,
+    }
+}
+
+const ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+// ==================== Levels ====================
+
+static LEVELS: [Level; 36] = [
+    Level {
+        number: 1,
+        name: "Mail Room",
+        floor_size: 0,
+        preset_tiles: &[],
+        generate_inbox: |rng| numbers_or_letters(rng, 3),
+        expected_outbox: |inbox, _| Some(inbox.to_vec()),
+    },
+    Level {
+        number: 2,
+        name: "Busy Mail Room",
+        floor_size: 0,
+        preset_tiles: &[],
+        generate_inbox: |rng| letters(rng, 12, ALPHABET),
+        expected_outbox: |inbox, _| Some(inbox.to_vec()),
+    },
+    Level {
+        number: 3,
+        name: "Copy Floor",
+        floor_size: 6,
+        preset_tiles: &[
+            (0, C('U')),
+            (1, C('J')),
+            (2, C('X')),
+            (3, C('G')),
+            (4, C('B')),
+            (5, C('E')),
+        ],
+        generate_inbox: |rng| numbers(rng, 3, -99..=99),
+        expected_outbox: |_, _| Some(vec![C('B'), C('U'), C('G')]),
+    },
+    Level {
+        number: 4,
+        name: "Scrambler Handler",
+        floor_size: 3,
+        preset_tiles: &[],
+        generate_inbox: |rng| numbers_or_letters(rng, 6),
+        expected_outbox: |inbox, _| map_pairs(inbox, |a, b| Some(vec![*b, *a])),
+    },
+    Level {
+        number: 6,
+        name: "Rainy Summer",
+        floor_size: 3,
+        preset_tiles: &[],
+        generate_inbox: |rng| numbers(rng, 8, -9..=9),
+        expected_outbox: |inbox, _| map_pairs(inbox, |a, b| Some(vec![N(number(a)? + number(b)?)])),
+    },
+    Level {
+        number: 7,
+        name: "Zero Exterminator",
+        floor_size: 9,
+        preset_tiles: &[],
+        generate_inbox: |rng| {
+            let mut inbox = numbers_or_letters(rng, 5);
+            inbox.extend(numbers(rng, 3, 0..=0));
+            inbox.shuffle(rng);
+            inbox
+        },
+        expected_outbox: |inbox, _| Some(inbox.iter().copied().filter(|v| *v != N(0)).collect()),
+    },
+    Level {
+        number: 8,
+        name: "Tripler Room",
+        floor_size: 3,
+        preset_tiles: &[],
+        generate_inbox: |rng| numbers(rng, 4, -9..=9),
+        expected_outbox: |inbox, _| map_numbers(inbox, |n| vec![n * 3]),
+    },
+    Level {
+        number: 9,
+        name: "Zero Preservation Initiative",
+        floor_size: 9,
+        preset_tiles: &[],
+        generate_inbox: |rng| {
+            let mut inbox = numbers_or_letters(rng, 5);
+            inbox.extend(numbers(rng, 3, 0..=0));
+            inbox.shuffle(rng);
+            inbox
+        },
+        expected_outbox: |inbox, _| Some(inbox.iter().copied().filter(|v| *v == N(0)).collect()),
+    },
+    Level {
+        number: 10,
+        name: "Octoplier Suite",
+        floor_size: 5,
+        preset_tiles: &[],
+        generate_inbox: |rng| numbers(rng, 4, -9..=9),
+        expected_outbox: |inbox, _| map_numbers(inbox, |n| vec![n * 8]),
+    },
+    Level {
+        number: 11,
+        name: "Sub Hallway",
+        floor_size: 3,
+        preset_tiles: &[],
+        generate_inbox: |rng| numbers(rng, 8, -9..=9),
+        expected_outbox: |inbox, _| {
+            map_pairs(inbox, |a, b| {
+                let (a, b) = (number(a)?, number(b)?);
+                Some(vec![N(b - a), N(a - b)])
+            })
+        },
+    },
+    Level {
+        number: 12,
+        name: "Tetracontiplier",
+        floor_size: 5,
+        preset_tiles: &[],
+        generate_inbox: |rng| numbers(rng, 4, -9..=9),
+        expected_outbox: |inbox, _| map_numbers(inbox, |n| vec![n * 40]),
+    },
+    Level {
+        number: 13,
+        name: "Equalization Room",
+        floor_size: 3,
+        preset_tiles: &[],
+        generate_inbox: |rng| {
+            let mut inbox = vec![];
+            for _ in 0..4 {
+                let a = numbers(rng, 1, -9..=9)[0];
+                let b = if rng.gen_bool(0.5) {
+                    a
+                } else {
+                    numbers(rng, 1, -9..=9)[0]
+                };
+                inbox.extend([a, b]);
+            }
+            inbox
+        },
+        expected_outbox: |inbox, _| {
+            map_pairs(inbox, |a, b| Some(if a == b { vec![*a] } else { vec![] }))
+        },
+    },
+    Level {
+        number: 14,
+        name: "Maximization Room",
+        floor_size: 3,
+        preset_tiles: &[],
+        generate_inbox: |rng| numbers(rng, 8, -9..=9),
+        expected_outbox: |inbox, _| {
+            map_pairs(inbox, |a, b| Some(vec![N(number(a)?.max(number(b)?))]))
+        },
+    },
+    Level {
+        number: 16,
+        name: "Absolute Positivity",
+        floor_size: 3,
+        preset_tiles: &[],
+        generate_inbox: |rng| numbers(rng, 8, -9..=9),
+        expected_outbox: |inbox, _| map_numbers(inbox, |n| vec![n.abs()]),
+    },
+    Level {
+        number: 17,
+        name: "Exclusive Lounge",
+        floor_size: 6,
+        preset_tiles: &[(4, N(0)), (5, N(1))],
+        generate_inbox: |rng| numbers(rng, 8, -9..=9),
+        expected_outbox: |inbox, _| {
+            map_pairs(inbox, |a, b| {
+                let same_sign = (number(a)? < 0) == (number(b)? < 0);
+                Some(vec![N(if same_sign { 0 } else { 1 })])
+            })
+        },
+    },
+    Level {
+        number: 19,
+        name: "Countdown",
+        floor_size: 10,
+        preset_tiles: &[],
+        generate_inbox: |rng| numbers(rng, 4, -9..=9),
+        expected_outbox: |inbox, _| {
+            map_numbers(inbox, |n| {
+                if n >= 0 {
+                    (0..=n).rev().collect()
+                } else {
+                    (n..=0).collect()
+                }
+            })
+        },
+    },
+    Level {
+        number: 20,
+        name: "Multiplication Workshop",
+        floor_size: 10,
+        preset_tiles: &[(9, N(0))],
+        generate_inbox: |rng| numbers(rng, 10, 0..=9),
+        expected_outbox: |inbox, _| map_pairs(inbox, |a, b| Some(vec![N(number(a)? * number(b)?)])),
+    },
+    Level {
+        number: 21,
+        name: "Zero Terminated Sum",
+        floor_size: 6,
+        preset_tiles: &[(5, N(0))],
+        generate_inbox: |rng| {
+            zero_terminated_strings(rng, 4, |rng| {
+                let length = rng.gen_range(0..=4);
+                numbers(rng, length, 1..=9)
+            })
+        },
+        expected_outbox: |inbox, _| {
+            let mut outbox = vec![];
+            for string in strings(inbox) {
+                let mut sum = 0;
+                for value in string {
+                    sum += number(value)?;
+                }
+                outbox.push(N(sum));
+            }
+            Some(outbox)
+        },
+    },
+    Level {
+        number: 22,
+        name: "Fibonacci Visitor",
+        floor_size: 10,
+        preset_tiles: &[(9, N(0))],
+        generate_inbox: |rng| numbers(rng, 2, 1..=30),
+        expected_outbox: |inbox, _| {
+            map_numbers(inbox, |n| {
+                let mut sequence = vec![];
+                let (mut a, mut b) = (1, 1);
+                while a <= n {
+                    sequence.push(a);
+                    (a, b) = (b, a + b);
+                }
+                sequence
+            })
+        },
+    },
+    Level {
+        number: 23,
+        name: "The Littlest Number",
+        floor_size: 10,
+        preset_tiles: &[],
+        generate_inbox: |rng| {
+            zero_terminated_strings(rng, 3, |rng| {
+                let length = rng.gen_range(1..=5);
+                numbers(rng, length, 1..=99)
+            })
+        },
+        expected_outbox: |inbox, _| {
+            let mut outbox = vec![];
+            for string in strings(inbox) {
+                let mut min = None;
+                for value in string {
+                    let n = number(value)?;
+                    min = Some(min.map_or(n, |m: i32| m.min(n)));
+                }
+                outbox.extend(min.map(N));
+            }
+            Some(outbox)
+        },
+    },
+    Level {
+        number: 24,
+        name: "Mod Module",
+        floor_size: 10,
+        preset_tiles: &[],
+        generate_inbox: |rng| {
+            let mut inbox = vec![];
+            for _ in 0..4 {
+                inbox.push(N(rng.gen_range(0..=20)));
+                inbox.push(N(rng.gen_range(1..=9)));
+            }
+            inbox
+        },
+        expected_outbox: |inbox, _| {
+            map_pairs(inbox, |a, b| {
+                let (a, b) = (number(a)?, number(b)?);
+                Some(vec![N(a.checked_rem(b)?)])
+            })
+        },
+    },
+    Level {
+        number: 25,
+        name: "Cumulative Countdown",
+        floor_size: 10,
+        preset_tiles: &[(9, N(0))],
+        generate_inbox: |rng| numbers(rng, 4, 0..=9),
+        expected_outbox: |inbox, _| map_numbers(inbox, |n| vec![(0..=n).sum()]),
+    },
+    Level {
+        number: 26,
+        name: "Small Divide",
+        floor_size: 10,
+        preset_tiles: &[(9, N(0))],
+        generate_inbox: |rng| {
+            let mut inbox = vec![];
+            for _ in 0..4 {
+                inbox.push(N(rng.gen_range(0..=20)));
+                inbox.push(N(rng.gen_range(1..=9)));
+            }
+            inbox
+        },
+        expected_outbox: |inbox, _| {
+            map_pairs(inbox, |a, b| {
+                let (a, b) = (number(a)?, number(b)?);
+                Some(vec![N(a.checked_div(b)?)])
+            })
+        },
+    },
+    Level {
+        number: 28,
+        name: "Three Sort",
+        floor_size: 10,
+        preset_tiles: &[(9, N(0))],
+        generate_inbox: |rng| numbers(rng, 9, -9..=9),
+        expected_outbox: |inbox, _| {
+            let mut outbox = vec![];
+            for triple in inbox.chunks_exact(3) {
+                let mut triple = triple.to_vec();
+                triple.sort_by(compare);
+                outbox.extend(triple);
+            }
+            Some(outbox)
+        },
+    },
+    Level {
+        number: 29,
+        name: "Storage Floor",
+        floor_size: 16,
+        preset_tiles: &[
+            (0, C('N')),
+            (1, C('K')),
+            (2, C('A')),
+            (3, C('E')),
+            (4, C('R')),
+            (5, C('D')),
+            (6, C('O')),
+            (7, C('L')),
+            (8, C('J')),
+            (9, C('B')),
+        ],
+        generate_inbox: |rng| numbers(rng, 6, 0..=9),
+        expected_outbox: |inbox, memory| {
+            let mut outbox = vec![];
+            for value in inbox {
+                let address = usize::try_from(number(value)?).ok()?;
+                outbox.push(*memory.get(&address)?);
+            }
+            Some(outbox)
+        },
+    },
+    Level {
+        number: 30,
+        name: "String Storage Floor",
+        floor_size: 25,
+        preset_tiles: &[
+            (0, C('G')),
+            (1, C('E')),
+            (2, C('T')),
+            (3, N(0)),
+            (4, C('T')),
+            (5, C('H')),
+            (6, N(0)),
+            (7, C('T')),
+            (8, C('A')),
+            (9, C('R')),
+            (10, N(0)),
+            (11, C('A')),
+            (12, C('W')),
+            (13, C('A')),
+            (14, C('K')),
+            (15, C('E')),
+            (16, N(0)),
+            (17, C('I')),
+            (18, C('S')),
+            (19, N(0)),
+            (20, C('X')),
+            (21, C('X')),
+            (22, C('X')),
+            (23, N(0)),
+        ],
+        generate_inbox: |rng| {
+            let starts = [
+                0, 1, 2, 4, 5, 7, 8, 9, 11, 12, 13, 14, 15, 17, 18, 20, 21, 22,
+            ];
+            (0..8).map(|_| N(*starts.choose(rng).unwrap())).collect()
+        },
+        expected_outbox: |inbox, memory| {
+            let mut outbox = vec![];
+            for value in inbox {
+                let mut address = usize::try_from(number(value)?).ok()?;
+                loop {
+                    match memory.get(&address)? {
+                        N(0) => break,
+                        letter => outbox.push(*letter),
+                    }
+                    address += 1;
+                }
+            }
+            Some(outbox)
+        },
+    },
+    Level {
+        number: 31,
+        name: "String Reverse",
+        floor_size: 15,
+        preset_tiles: &[(14, N(0))],
+        generate_inbox: |rng| {
+            zero_terminated_strings(rng, 3, |rng| {
+                let length = rng.gen_range(1..=5);
+                letters(rng, length, ALPHABET)
+            })
+        },
+        expected_outbox: |inbox, _| {
+            Some(
+                strings(inbox)
+                    .into_iter()
+                    .flat_map(|string| string.iter().rev().copied())
+                    .collect(),
+            )
+        },
+    },
+    Level {
+        number: 32,
+        name: "Inventory Report",
+        floor_size: 16,
+        preset_tiles: &[
+            (0, C('B')),
+            (1, C('A')),
+            (2, C('X')),
+            (3, C('B')),
+            (4, C('C')),
+            (5, C('A')),
+            (6, C('X')),
+            (7, C('B')),
+            (8, C('A')),
+            (9, C('X')),
+            (10, C('B')),
+            (11, C('C')),
+            (12, C('X')),
+            (13, C('B')),
+            (14, N(0)),
+        ],
+        generate_inbox: |rng| letters(rng, 4, "ABCX"),
+        expected_outbox: |inbox, memory| {
+            Some(
+                inbox
+                    .iter()
+                    .map(|letter| {
+                        let count = (0..14).filter(|a| memory.get(a) == Some(letter)).count();
+                        N(count as i32)
+                    })
+                    .collect(),
+            )
+        },
+    },
+    Level {
+        number: 34,
+        name: "Vowel Incinerator",
+        floor_size: 10,
+        preset_tiles: &[
+            (0, C('A')),
+            (1, C('E')),
+            (2, C('I')),
+            (3, C('O')),
+            (4, C('U')),
+            (5, N(0)),
+        ],
+        generate_inbox: |rng| letters(rng, 10, ALPHABET),
+        expected_outbox: |inbox, _| {
+            Some(
+                inbox
+                    .iter()
+                    .copied()
+                    .filter(|v| !matches!(v, C('A' | 'E' | 'I' | 'O' | 'U')))
+                    .collect(),
+            )
+        },
+    },
+    Level {
+        number: 35,
+        name: "Duplicate Removal",
+        floor_size: 15,
+        preset_tiles: &[(14, N(0))],
+        generate_inbox: |rng| letters(rng, 10, "ABCDEFGH"),
+        expected_outbox: |inbox, _| {
+            let mut outbox: Vec<ValueBox> = vec![];
+            for value in inbox {
+                if !outbox.contains(value) {
+                    outbox.push(*value);
+                }
+            }
+            Some(outbox)
+        },
+    },
+    Level {
+        number: 36,
+        name: "Alphabetizer",
+        floor_size: 25,
+        preset_tiles: &[(23, N(0)), (24, N(10))],
+        generate_inbox: |rng| {
+            zero_terminated_strings(rng, 2, |rng| {
+                let length = rng.gen_range(1..=5);
+                letters(rng, length, "ABCDE")
+            })
+        },
+        expected_outbox: |inbox, _| {
+            let words = strings(inbox);
+            let (first, second) = (words.first()?, words.get(1)?);
+            let first_is_smaller = first
+                .iter()
+                .zip(second.iter())
+                .map(|(a, b)| compare(a, b))
+                .find(|ordering| ordering.is_ne())
+                .map_or(first.len() <= second.len(), |ordering| ordering.is_lt());
+            Some(if first_is_smaller { first } else { second }.to_vec())
+        },
+    },
+    Level {
+        number: 37,
+        name: "Scavenger Chain",
+        floor_size: 25,
+        preset_tiles: &[
+            (0, C('E')),
+            (1, N(13)),
+            (3, C('C')),
+            (4, N(23)),
+            (10, C('P')),
+            (11, N(20)),
+            (13, C('S')),
+            (14, N(3)),
+            (20, C('E')),
+            (21, N(-1)),
+            (23, C('A')),
+            (24, N(10)),
+        ],
+        generate_inbox: |rng| {
+            let starts = [0, 3, 10, 13, 20, 23];
+            (0..3).map(|_| N(*starts.choose(rng).unwrap())).collect()
+        },
+        expected_outbox: |inbox, memory| {
+            let mut outbox = vec![];
+            for value in inbox {
+                let mut address = number(value)?;
+                // Stop on loops
+                for _ in 0..memory.len() {
+                    if address < 0 {
+                        break;
+                    }
+                    outbox.push(*memory.get(&(address as usize))?);
+                    address = number(memory.get(&(address as usize + 1))?)?;
+                }
+            }
+            Some(outbox)
+        },
+    },
+    Level {
+        number: 38,
+        name: "Digit Exploder",
+        floor_size: 12,
+        preset_tiles: &[(9, N(0)), (10, N(10)), (11, N(100))],
+        generate_inbox: |rng| numbers(rng, 4, 0..=999),
+        expected_outbox: |inbox, _| {
+            map_numbers(inbox, |n| {
+                n.to_string()
+                    .chars()
+                    .filter_map(|digit| digit.to_digit(10))
+                    .map(|digit| digit as i32)
+                    .collect()
+            })
+        },
+    },
+    Level {
+        number: 39,
+        name: "Re-Coordinator",
+        floor_size: 16,
+        preset_tiles: &[(14, N(0)), (15, N(4))],
+        generate_inbox: |rng| numbers(rng, 4, 0..=15),
+        expected_outbox: |inbox, _| map_numbers(inbox, |n| vec![n % 4, n / 4]),
+    },
+    Level {
+        number: 40,
+        name: "Prime Factory",
+        floor_size: 25,
+        preset_tiles: &[(24, N(0))],
+        generate_inbox: |rng| numbers(rng, 4, 2..=99),
+        expected_outbox: |inbox, _| {
+            map_numbers(inbox, |mut n| {
+                let mut factors = vec![];
+                let mut divisor = 2;
+                while n > 1 && divisor <= n {
+                    if n % divisor == 0 {
+                        factors.push(divisor);
+                        n /= divisor;
+                    } else {
+                        divisor += 1;
+                    }
+                }
+                factors
+            })
+        },
+    },
+    Level {
+        number: 41,
+        name: "Sorting Room",
+        floor_size: 25,
+        preset_tiles: &[(24, N(0))],
+        generate_inbox: |rng| {
+            zero_terminated_strings(rng, 4, |rng| {
+                let length = rng.gen_range(1..=5);
+                if rng.gen_bool(0.5) {
+                    numbers(rng, length, 1..=99)
+                } else {
+                    letters(rng, length, ALPHABET)
+                }
+            })
+        },
+        expected_outbox: |inbox, _| {
+            let mut outbox = vec![];
+            for string in strings(inbox) {
+                let mut string = string.to_vec();
+                string.sort_by(compare);
+                outbox.extend(string);
+            }
+            Some(outbox)
+        },
+    },
+];
+
+#[cfg(test)]
+mod levels_tests {
+    use super::*;
+    use crate::{
+        interpreter::{memory::Memory, Interpreter},
+        script_object::ScriptObject,
+    };
+
+    fn values(s: &str) -> Vec<ValueBox> {
+        s.split_whitespace()
+            .map(|v| v.parse::<ValueBox>().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_get_level() {
+        assert_eq!(get_level(41).unwrap().name, "Sorting Room");
+        assert!(get_level(5).is_none());
+        assert!(all_levels()
+            .windows(2)
+            .all(|levels| levels[0].number < levels[1].number));
+    }
+
+    #[test]
+    fn test_generated_inboxes_are_valid() {
+        let mut rng = rand::thread_rng();
+        for level in all_levels() {
+            for _ in 0..20 {
+                let inbox = level.generate_inbox(&mut rng);
+                assert!(
+                    level.expected_outbox(&inbox).is_some(),
+                    "level {}: invalid generated inbox {:?}",
+                    level.number,
+                    inbox
+                );
+                assert!(level.memory().keys().all(|a| *a <= level.max_address()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_expected_outboxes() {
+        // Inboxes and outboxes of the samples
+        let level = get_level(20).unwrap();
+        assert_eq!(
+            level.expected_outbox(&values("4 3 4 1 9 0 0 1 7 8")),
+            Some(values("12 4 0 0 56"))
+        );
+
+        let level = get_level(30).unwrap();
+        assert_eq!(
+            level.expected_outbox(&values("4 15 7 0 17 11 22")),
+            Some(values("T H E T A R G E T I S A W A K E X"))
+        );
+
+        let level = get_level(41).unwrap();
+        assert_eq!(
+            level.expected_outbox(&values(
+                "71 26 65 0 A L I V E 0 35 74 69 90 67 72 65 74 84 14 0 86 0"
+            )),
+            Some(values(
+                "26 65 71 A E I L V 14 35 65 67 69 72 74 74 84 90 86"
+            ))
+        );
+
+        let level = get_level(37).unwrap();
+        assert_eq!(
+            level.expected_outbox(&values("0 20")),
+            Some(values("E S C A P E E"))
+        );
+
+        let level = get_level(6).unwrap();
+        assert_eq!(level.expected_outbox(&values("1 A")), None);
+    }
+
+    #[test]
+    fn test_samples_solve_their_level() {
+        let samples = [
+            (1, include_str!("../samples/01-MailRoom.hrm")),
+            (6, include_str!("../samples/06-RainySummer.hrm")),
+            (20, include_str!("../samples/20-MultiplicationWorkshop.hrm")),
+            (30, include_str!("../samples/30-StringStorageFloor.hrm")),
+            (41, include_str!("../samples/41-SortingRoom.hrm")),
+        ];
+        let mut rng = rand::thread_rng();
+        for (number, script) in samples {
+            let level = get_level(number).unwrap();
+            let script = script.parse::<ScriptObject>().unwrap();
+            let inbox = level.generate_inbox(&mut rng);
+
+            let mut interpreter =
+                Interpreter::new(Memory::with_data(level.memory(), level.max_address()));
+            let (outputs, _) = interpreter.execute(&script, &inbox).unwrap();
+
+            assert_eq!(
+                Some(outputs),
+                level.expected_outbox(&inbox),
+                "level {}",
+                number
+            );
+        }
+    }
+}
@@ -0,0 +1,18 @@
+//! Interpreter for the script language used in the game Human Resource Machine.
+//!
+//! The [`script_object`] module parses a script into a [`script_object::ScriptObject`],
+//! and the [`interpreter`] module executes it.
+//! The [`levels`] module describes the levels of the game, to check the solutions,
+//! and the [`generator`] module generates random inputs.
+//! The [`diagnostics`] module renders the errors with an excerpt of the script,
+//! and the [`compile`] module compiles a script to the source of another language.
+//! With the `wasm` feature, the `wasm` module exposes the interpreter to JavaScript.
+
+pub mod compile;
+pub mod diagnostics;
+pub mod generator;
+pub mod interpreter;
+pub mod levels;
+pub mod script_object;
+#[cfg(feature = "wasm")]
+pub mod wasm;
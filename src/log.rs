@@ -0,0 +1,110 @@
+//! The messages of the interpreter on stderr, shown according to the verbosity
+//! (`--quiet`, `--verbose`).
+//!
+//! The errors are always shown, the warnings and the notes unless the verbosity is quiet.
+//! The events tell what the parsing, the validation and the execution are doing: the main steps
+//! with `--verbose`, and their details with `--verbose` given twice. They are written
+//! `[topic] message`, the topic being the part of the interpreter that sends them.
+//!
+//! The reports asked for (`--stats`, `--coverage`, the results of the test cases...)
+//! aren't messages, and are printed whatever the verbosity.
+
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use crate::style::{Stream, Style};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only the errors
+    Quiet,
+    /// The errors, the warnings and the notes
+    #[default]
+    Normal,
+    /// The main steps of the parsing, the validation and the execution too
+    Verbose,
+    /// Their details too
+    Trace,
+}
+
+impl Verbosity {
+    /// The verbosity after one more `--verbose`
+    pub fn louder(self) -> Self {
+        match self {
+            Self::Quiet | Self::Normal => Self::Verbose,
+            Self::Verbose | Self::Trace => Self::Trace,
+        }
+    }
+}
+
+/// The verbosity of the process, set once the command line is read
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+/// Whether the messages of the verbosity are shown
+pub fn enabled(verbosity: Verbosity) -> bool {
+    verbosity as u8 <= VERBOSITY.load(Ordering::Relaxed)
+}
+
+/// A failure, that usually stops the interpreter
+pub fn error(message: impl Display) {
+    eprintln!("{}", Stream::Stderr.highlight(&message.to_string()));
+}
+
+/// Something suspicious, that doesn't stop the interpreter
+pub fn warning(message: impl Display) {
+    if enabled(Verbosity::Normal) {
+        eprintln!(
+            "{} {}",
+            Stream::Stderr.paint(Style::Warning, "Warning:"),
+            message
+        );
+    }
+}
+
+/// Something worth knowing about the run: the generated inputs, the random seed...
+pub fn note(message: impl Display) {
+    if enabled(Verbosity::Normal) {
+        eprintln!("{}", message);
+    }
+}
+
+/// What a part of the interpreter is doing, shown from the given verbosity
+pub fn event(verbosity: Verbosity, topic: &str, message: impl Display) {
+    if enabled(verbosity) {
+        eprintln!("[{}] {}", topic, message);
+    }
+}
+
+/// A main step, shown with `--verbose`
+pub fn verbose(topic: &str, message: impl Display) {
+    event(Verbosity::Verbose, topic, message);
+}
+
+/// A detail, shown with `--verbose` given twice
+pub fn trace(topic: &str, message: impl Display) {
+    event(Verbosity::Trace, topic, message);
+}
+
+#[cfg(test)]
+mod log_tests {
+    use super::*;
+
+    #[test]
+    fn test_verbosity() {
+        assert_eq!(Verbosity::Normal.louder(), Verbosity::Verbose);
+        assert_eq!(Verbosity::Quiet.louder(), Verbosity::Verbose);
+        assert_eq!(Verbosity::Verbose.louder().louder(), Verbosity::Trace);
+
+        // The default verbosity shows everything but the events
+        assert!(enabled(Verbosity::Quiet));
+        assert!(enabled(Verbosity::Normal));
+        assert!(!enabled(Verbosity::Verbose));
+        assert!(!enabled(Verbosity::Trace));
+    }
+}
@@ -0,0 +1,371 @@
+//! A server of the Language Server Protocol, so that editors can check scripts as they are typed.
+//! The messages are framed like the ones of the debug adapter (see [`crate::dap`]).
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+use hrm_interpreter::script_object::parser::Parser;
+use serde_json::{json, Value};
+
+use crate::{
+    dap::{read_message, write_message},
+    log,
+};
+
+/// "Parse error" code of JSON-RPC, for a message that isn't JSON
+const PARSE_ERROR: i64 = -32700;
+/// "Method not found" error code of JSON-RPC
+const METHOD_NOT_FOUND: i64 = -32601;
+/// "Error" severity of a diagnostic
+const ERROR: i64 = 1;
+/// "Function" kind of a symbol, used for the blocks
+const FUNCTION: i64 = 12;
+
+/// A range of the document, from a character of a line to another one, all starting at 0
+fn range(line: usize, characters: std::ops::Range<usize>) -> Value {
+    json!({
+        "start": {"line": line, "character": characters.start},
+        "end": {"line": line, "character": characters.end},
+    })
+}
+
+/// The word under the given character of the line
+fn word_at(line: &str, character: usize) -> &str {
+    let is_word = |c: char| !c.is_whitespace() && c != ':';
+    let byte = line
+        .char_indices()
+        .nth(character)
+        .map_or(line.len(), |(i, _)| i);
+    let start = line[..byte]
+        .rfind(|c: char| !is_word(c))
+        .map_or(0, |i| i + 1);
+    let end = line[byte..]
+        .find(|c: char| !is_word(c))
+        .map_or(line.len(), |i| byte + i);
+    &line[start..end]
+}
+
+/// A session with an editor, holding the documents it opened, each with its parser
+pub struct Server<W: Write> {
+    writer: W,
+    documents: HashMap<String, Parser>,
+}
+
+impl<W: Write> Server<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            documents: HashMap::new(),
+        }
+    }
+
+    fn respond(&mut self, id: &Value, result: Value) -> io::Result<()> {
+        write_message(
+            &mut self.writer,
+            &json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        )
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> io::Result<()> {
+        write_message(
+            &mut self.writer,
+            &json!({"jsonrpc": "2.0", "method": method, "params": params}),
+        )
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str) -> io::Result<()> {
+        let diagnostics = self
+            .documents
+            .get(uri)
+            .map(|parser| {
+                parser
+                    .diagnostics()
+                    .iter()
+                    .map(|diagnostic| {
+                        let mut message = diagnostic.message.clone();
+                        for note in &diagnostic.notes {
+                            message += &format!("\n{}", note);
+                        }
+                        let (line, characters) = parser.range(diagnostic);
+                        json!({
+                            "range": range(line, characters),
+                            "severity": ERROR,
+                            "source": "hrm",
+                            "message": message,
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        self.notify(
+            "textDocument/publishDiagnostics",
+            json!({"uri": uri, "diagnostics": diagnostics}),
+        )
+    }
+
+    /// Handle a request or a notification of the editor. Returns false when the session is over.
+    pub fn handle(&mut self, message: &Value) -> io::Result<bool> {
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let id = &message["id"];
+        let method = message["method"].as_str().unwrap_or_default();
+        log::trace("lsp", method);
+
+        match method {
+            "initialize" => self.respond(
+                id,
+                json!({
+                    "capabilities": {
+                        // Incremental changes
+                        "textDocumentSync": {"openClose": true, "change": 2},
+                        "definitionProvider": true,
+                        "documentSymbolProvider": true,
+                    },
+                    "serverInfo": {"name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION")},
+                }),
+            )?,
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), Parser::new(text));
+                self.publish_diagnostics(&uri)?;
+            }
+            "textDocument/didChange" => {
+                if let Some(parser) = self.documents.get_mut(&uri) {
+                    for change in params["contentChanges"].as_array().into_iter().flatten() {
+                        let text = change["text"].as_str().unwrap_or_default();
+                        let position = |key: &str| {
+                            let position = &change["range"][key];
+                            (
+                                position["line"].as_u64().unwrap_or_default() as usize,
+                                position["character"].as_u64().unwrap_or_default() as usize,
+                            )
+                        };
+                        match change.get("range") {
+                            Some(_) => parser.edit(position("start"), position("end"), text),
+                            // The whole text
+                            None => *parser = Parser::new(text),
+                        }
+                    }
+                }
+                self.publish_diagnostics(&uri)?;
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                self.publish_diagnostics(&uri)?;
+            }
+            "textDocument/definition" => {
+                let location = self.documents.get(&uri).and_then(|parser| {
+                    let line = params["position"]["line"].as_u64()? as usize;
+                    let character = params["position"]["character"].as_u64()? as usize;
+                    let word = word_at(parser.document().lines().get(line)?, character);
+                    let (_, label_line, label) = parser
+                        .labels()
+                        .into_iter()
+                        .find(|(_, _, label)| *label == word)?;
+                    Some(json!({
+                        "uri": uri,
+                        "range": range(label_line - 1, 0..label.chars().count()),
+                    }))
+                });
+                self.respond(id, location.unwrap_or(Value::Null))?;
+            }
+            "textDocument/documentSymbol" => {
+                let symbols = self
+                    .documents
+                    .get(&uri)
+                    .map(|parser| {
+                        let labels = parser.labels();
+                        // A block goes until the next label, or the end of the script
+                        let ends = labels
+                            .iter()
+                            .skip(1)
+                            .map(|(_, line, _)| line - 1)
+                            .chain(std::iter::once(parser.document().lines().len()));
+                        labels
+                            .iter()
+                            .zip(ends)
+                            .map(|((_, line, label), end)| {
+                                let selection = range(line - 1, 0..label.chars().count());
+                                json!({
+                                    "name": label,
+                                    "kind": FUNCTION,
+                                    "range": {
+                                        "start": selection["start"],
+                                        "end": {"line": end - 1, "character": 0},
+                                    },
+                                    "selectionRange": selection,
+                                })
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                self.respond(id, json!(symbols))?;
+            }
+            "shutdown" => self.respond(id, Value::Null)?,
+            "exit" => return Ok(false),
+            method if !id.is_null() => write_message(
+                &mut self.writer,
+                &json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {"code": METHOD_NOT_FOUND, "message": format!("Unsupported request: {}", method)},
+                }),
+            )?,
+            // Other notifications are ignored
+            _ => {}
+        }
+        Ok(true)
+    }
+}
+
+/// Serve the editor until it exits or closes the input
+pub fn serve(mut reader: impl BufRead, writer: impl Write) -> io::Result<()> {
+    let mut server = Server::new(writer);
+    while let Some(message) = read_message(&mut reader)? {
+        match message {
+            Ok(message) => {
+                if !server.handle(&message)? {
+                    break;
+                }
+            }
+            // The id of the request can't be read: the response has none
+            Err(e) => write_message(
+                &mut server.writer,
+                &json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": {"code": PARSE_ERROR, "message": format!("Invalid message: {}", e)},
+                }),
+            )?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod lsp_tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const URI: &str = "file:///solution.hrm";
+    const SCRIPT: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+a:
+    INBOX
+    JUMPZ    b
+    OUTBOX
+    JUMP     a
+b:
+    JUMP     a
+";
+
+    fn exchange(messages: &[Value]) -> Vec<Value> {
+        let mut input = vec![];
+        for message in messages {
+            write_message(&mut input, message).unwrap();
+        }
+        let mut output = vec![];
+        serve(Cursor::new(input), &mut output).unwrap();
+
+        let mut reader = Cursor::new(output);
+        let mut responses = vec![];
+        while let Some(Ok(response)) = read_message(&mut reader).unwrap() {
+            responses.push(response);
+        }
+        responses
+    }
+
+    fn change(range: Value, text: &str) -> Value {
+        json!({"method": "textDocument/didChange", "params": {
+            "textDocument": {"uri": URI},
+            "contentChanges": [{"range": range, "text": text}],
+        }})
+    }
+
+    #[test]
+    fn test_diagnostics_as_you_type() {
+        let responses = exchange(&[
+            json!({"method": "textDocument/didOpen", "params": {
+                "textDocument": {"uri": URI, "text": SCRIPT},
+            }}),
+            change(range(5, 4..10), "OUTBOXX"),
+            change(range(5, 4..11), "OUTBOX"),
+            change(range(8, 13..14), "c"),
+        ]);
+
+        let diagnostics = responses
+            .iter()
+            .map(|notification| &notification["params"]["diagnostics"])
+            .collect::<Vec<_>>();
+        assert_eq!(diagnostics[0], &json!([]));
+        assert_eq!(
+            diagnostics[1][0]["message"],
+            "OUTBOXX is not a valid instruction"
+        );
+        assert_eq!(diagnostics[1][0]["range"], range(5, 4..11));
+        assert_eq!(diagnostics[2], &json!([]));
+        assert_eq!(diagnostics[3][0]["message"], "unknown label c");
+        assert_eq!(diagnostics[3][0]["range"], range(8, 4..14));
+    }
+
+    #[test]
+    fn test_navigation() {
+        let responses = exchange(&[
+            json!({"method": "textDocument/didOpen", "params": {
+                "textDocument": {"uri": URI, "text": SCRIPT},
+            }}),
+            json!({"id": 1, "method": "textDocument/definition", "params": {
+                "textDocument": {"uri": URI}, "position": {"line": 4, "character": 13},
+            }}),
+            json!({"id": 2, "method": "textDocument/documentSymbol", "params": {
+                "textDocument": {"uri": URI},
+            }}),
+            json!({"id": 3, "method": "textDocument/hover", "params": {}}),
+            json!({"method": "exit"}),
+        ]);
+
+        assert_eq!(
+            responses[1]["result"],
+            json!({"uri": URI, "range": range(7, 0..1)})
+        );
+        let symbols = responses[2]["result"].as_array().unwrap();
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols[0]["name"], "a");
+        assert_eq!(symbols[0]["range"]["end"]["line"], 6);
+        assert_eq!(symbols[1]["selectionRange"], range(7, 0..1));
+        assert_eq!(responses[3]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(responses.len(), 4);
+    }
+
+    #[test]
+    fn test_invalid_message() {
+        let mut input = b"Content-Length: 8\r\n\r\n{\"id\": 1".to_vec();
+        write_message(&mut input, &json!({"id": 2, "method": "shutdown"})).unwrap();
+        let mut output = vec![];
+        serve(Cursor::new(input), &mut output).unwrap();
+
+        // The server answers the invalid message, and goes on with the next one
+        let mut reader = Cursor::new(output);
+        let response = read_message(&mut reader).unwrap().unwrap().unwrap();
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+        assert_eq!(response["id"], Value::Null);
+        let response = read_message(&mut reader).unwrap().unwrap().unwrap();
+        assert_eq!(
+            (&response["id"], &response["result"]),
+            (&json!(2), &Value::Null)
+        );
+    }
+
+    #[test]
+    fn test_word_at() {
+        assert_eq!(word_at("    JUMPZ    loop", 15), "loop");
+        assert_eq!(word_at("loop:", 4), "loop");
+        assert_eq!(word_at("    JUMP     a", 6), "JUMP");
+    }
+}
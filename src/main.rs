@@ -1,45 +1,1250 @@
-use interpreter::{memory::Memory, Interpreter};
-use script_object::ScriptObject;
+use std::{collections::HashMap, io, path::Path};
 
+use cli_reader::Mode;
+use hrm_interpreter::{
+    compile,
+    diagnostics::Diagnostic,
+    generator::{self, InboxSpec},
+    interpreter::{
+        io::OutputSink, memory::Memory, settings::Settings, Interpreter, InterpreterStateInfo,
+    },
+    levels::{self, Level},
+    script_object::{
+        decompile, game, graph,
+        instruction::{Instruction, ParseOptions},
+        lint, listing, loops,
+        synthesis::SearchSpace,
+        value_box::ValueBox,
+        Position, ScriptObject,
+    },
+};
+use output::{ErrorFormat, OutputFormat};
+use rand::{rngs::StdRng, SeedableRng};
+
+mod batch;
+mod bench;
+mod check;
+mod checkpoint;
 mod cli_reader;
-mod interpreter;
-mod script_object;
+mod compare;
+mod config;
+mod cost;
+mod coverage;
+mod dap;
+mod debugger;
+mod equiv;
+mod jobs;
+mod level;
+mod log;
+mod lsp;
+mod manifest;
+mod memory_file;
+mod mutate;
+mod output;
+mod pipe;
+mod profile;
+mod property;
+mod repl;
+mod report;
+mod score;
+mod search;
+mod session;
+mod solve;
+mod style;
+#[cfg(feature = "tui")]
+mod tui;
+mod watch;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The exit codes of the interpreter, so that the scripts running it can tell the failures apart
+enum ExitCode {
+    Success = 0,
+    /// Any other error: a file that can't be read or written, an inbox not valid for the level...
+    Error = 1,
+    /// The script can't be parsed
+    Parse = 2,
+    /// The script jumps to unknown labels, or defines a label twice
+    Validation = 3,
+    /// The execution failed
+    Runtime = 4,
+    /// The outputs aren't the expected ones (check mode, level, test cases, comparison)
+    Mismatch = 5,
+    /// The script doesn't meet a requirement: not formatted, lint warnings, instructions never executed
+    Rejected = 6,
+}
+
+impl ExitCode {
+    fn exit(self) -> ! {
+        std::process::exit(self as i32)
+    }
+}
 
 fn main() {
     // Read the command line arguments
-    let args = cli_reader::read_args();
+    let mut args = cli_reader::read_args();
+    if args.mode == Mode::Dap {
+        if let Err(e) = dap::serve(io::stdin().lock(), io::stdout().lock()) {
+            log::error(format!("Debug adapter error: {}", e));
+            ExitCode::Error.exit();
+        }
+        return;
+    }
+    if args.mode == Mode::Lsp {
+        if let Err(e) = lsp::serve(io::stdin().lock(), io::stdout().lock()) {
+            log::error(format!("Language server error: {}", e));
+            ExitCode::Error.exit();
+        }
+        return;
+    }
+    if args.mode == Mode::Batch {
+        run_batch(&args);
+    }
+    if args.mode == Mode::Repl {
+        run_repl(&args);
+    }
+    if args.mode == Mode::Search {
+        run_search(&args);
+    }
+    if args.watch_files {
+        // The inputs read at this point are the given or piped ones, the others are read by each run
+        let files = [
+            Some(&args.script_path),
+            args.memory_file.as_ref(),
+            args.level_file.as_ref(),
+            args.test_file.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(Path::new)
+        .collect::<Vec<_>>();
+        watch::watch(&files, &args.input_values);
+    }
+    let level = args.level.and_then(levels::get_level);
+    let inputs_generated =
+        args.input_spec.is_some() || (level.is_some() && args.input_values.is_empty());
+
+    // The generator of all the random inboxes, seeded to generate them again
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let random_seed = (inputs_generated || args.score_runs.is_some()).then_some(seed);
+
+    if let Some(input_spec) = &args.input_spec {
+        args.input_values = input_spec.generate(&mut rng);
+        log::note(format!(
+            "Generated inputs: {}",
+            check::join_values(&args.input_values)
+        ));
+    }
+
+    // The level provides the floor and the inbox, unless they were given explicitly
+    if let Some(seed) = random_seed {
+        log::trace("inputs", format!("random seed {}", seed));
+    }
+
+    if let Some(level) = level {
+        log::verbose(
+            "level",
+            format!(
+                "level {} ({}), {} tiles on the floor",
+                level.number, level.name, level.floor_size
+            ),
+        );
+        args.apply_level(level)
+            .unwrap_or_else(|e| args.exit_with_error(&e));
+        if args.input_values.is_empty() {
+            args.input_values = level.generate_inbox(&mut rng);
+        }
+    }
+
+    // The cost of each instruction, to count the cycles of the execution
+    let cost_model = args.cost_file.as_ref().map(|file| {
+        cost::read_costs(file).unwrap_or_else(|e| {
+            log::error(e);
+            ExitCode::Error.exit();
+        })
+    });
+
+    // A custom level too, and its outbox is only expected for its own inbox
+    let level_file = args.level_file.as_ref().map(|file| {
+        if level.is_some() {
+            log::error("Give either a level with -L, or a level file with --level-file");
+            ExitCode::Error.exit();
+        }
+        level::read_level(file).unwrap_or_else(|e| {
+            log::error(e);
+            ExitCode::Error.exit();
+        })
+    });
+    let mut level_file_outbox = None;
+    if let Some(level_file) = level_file {
+        log::verbose("level", format!("level {}", level_file.name));
+        args.apply_level_file(&level_file)
+            .unwrap_or_else(|e| args.exit_with_error(&e));
+        if args.input_values.is_empty() {
+            args.input_values = level_file.inbox;
+            level_file_outbox = Some((format!("Level {}", level_file.name), level_file.outbox));
+        }
+    }
+
+    // A resumed execution goes on with the inputs it started with
+    let checkpoint = args.resume_file.as_ref().map(|file| {
+        checkpoint::read_checkpoint(file).unwrap_or_else(|e| {
+            log::error(e);
+            ExitCode::Error.exit();
+        })
+    });
+    if let Some(checkpoint) = &checkpoint {
+        args.input_values = checkpoint.inputs.clone();
+    }
+
+    // A replayed session takes the inbox of the recorded one
+    if let Some(replay_file) = &args.replay_file {
+        let session = session::read_session(replay_file).unwrap_or_else(|e| {
+            log::error(e);
+            ExitCode::Error.exit();
+        });
+        args.input_values = session.inputs;
+    }
+
+    // The outputs to check, if any
+    let checked_level = level.filter(|_| args.expected_outputs.is_none());
+    let (title, expected_outputs) = match (args.expected_outputs.take(), level) {
+        (Some(expected), _) => ("Check".to_string(), Some(expected)),
+        (None, None) if level_file_outbox.is_some() => {
+            let (title, expected) = level_file_outbox.unwrap();
+            (title, Some(expected))
+        }
+        (None, Some(level)) => {
+            let title = format!("Level {} ({})", level.number, level.name);
+            let expected = level.expected_outbox(&args.input_values);
+            if expected.is_none() {
+                log::error(format!("{}: the inbox is not valid for this level", title));
+                ExitCode::Error.exit();
+            }
+            (title, expected)
+        }
+        (None, None) => ("Check".to_string(), None),
+    };
+    if args.mode == Mode::Check && expected_outputs.is_none() {
+        log::error("Nothing to check: give the expected outputs with -e, or a level with -L or --level-file");
+        ExitCode::Error.exit();
+    }
+    if args.score_runs.is_some() && args.input_spec.is_none() && level.is_none() {
+        log::error(
+            "Nothing to generate the inboxes from: give a level with -L, or an input spec with -g",
+        );
+        ExitCode::Error.exit();
+    }
 
     // Objects used to execute the script
 
-    let script_object = args
-        .script_file
-        .parse::<ScriptObject>()
+    let script_object = ScriptObject::parse_with_options(&args.script_file, &args.parse_options())
         .unwrap_or_else(|e| {
-            eprintln!("{}", e);
-            std::process::exit(1);
+            if args.error_format == ErrorFormat::Json {
+                for error in e.errors() {
+                    for diagnostic in error.diagnostics() {
+                        print_json_error(
+                            &args.script_file,
+                            "parse",
+                            error.code(),
+                            &diagnostic,
+                            None,
+                        );
+                    }
+                }
+            } else {
+                for diagnostic in e.diagnostics() {
+                    log::error(diagnostic.render(&args.script_path, &args.script_file));
+                }
+            }
+            ExitCode::Parse.exit();
         });
-    script_object.validate().unwrap_or_else(|e| {
-        eprintln!("{}", e);
-        std::process::exit(1);
-    });
+    log::verbose(
+        "parse",
+        format!(
+            "{}: {} instructions in {} blocks",
+            args.script_path,
+            script_object.instruction_count(),
+            script_object.blocks().len()
+        ),
+    );
+    for block in script_object.blocks() {
+        log::trace(
+            "parse",
+            format!(
+                "block {} on line {}: {} instructions",
+                block.name(),
+                block.line(),
+                block.instructions.len()
+            ),
+        );
+    }
+
+    // The optimized script replaces the original one for everything else
+    let script_object = if args.optimize {
+        validate(&args, &script_object);
+        let (optimized, report) = script_object.optimize(args.remove_dead_instructions);
+        log::note(report);
+        optimized
+    } else {
+        script_object
+    };
+
+    // Format the script instead of executing it
+    if args.mode == Mode::Fmt {
+        let formatted = script_object.to_string();
+        if !args.check_format {
+            print!("{}", formatted);
+        } else if formatted != args.script_file {
+            log::error("The script is not formatted: run 'fmt' without --check to see the formatted script");
+            ExitCode::Rejected.exit();
+        }
+        ExitCode::Success.exit();
+    }
+
+    validate(&args, &script_object);
+
+    // Print the script for the game instead of executing it
+    if args.mode == Mode::Export {
+        let exported = game::to_clipboard(&script_object).unwrap_or_else(|e| {
+            log::error(e);
+            ExitCode::Validation.exit();
+        });
+        print!("{}", exported);
+        ExitCode::Success.exit();
+    }
+
+    // Report the suspicious constructs instead of executing the script
+    if args.mode == Mode::Lint {
+        let warnings = lint::lint(&script_object, &args.memory);
+        for warning in &warnings {
+            println!("{}", style::lint_warning(warning));
+        }
+        if warnings.is_empty() {
+            ExitCode::Success
+        } else {
+            ExitCode::Rejected
+        }
+        .exit();
+    }
+
+    // Print the control flow graph instead of executing the script
+    if args.mode == Mode::Graph {
+        if args.format == OutputFormat::Json {
+            log::error("The graph can only be printed in the dot format");
+            ExitCode::Error.exit();
+        }
+        print!("{}", graph::to_dot(&script_object));
+        ExitCode::Success.exit();
+    }
+    // Print the script compiled to another language instead of executing it
+    if args.mode == Mode::Transpile {
+        let code = compile::compile(&script_object, args.target).unwrap_or_else(|e| {
+            log::error(e);
+            ExitCode::Error.exit();
+        });
+        print!("{}", code);
+        ExitCode::Success.exit();
+    }
+    // Print the instructions with their indices instead of executing the script
+    if args.mode == Mode::Listing {
+        print!("{}", listing::listing(&script_object, &args.tile_names));
+        ExitCode::Success.exit();
+    }
+    // Print the script as structured pseudocode instead of executing it
+    if args.mode == Mode::Decompile {
+        print!("{}", decompile::decompile(&script_object));
+        ExitCode::Success.exit();
+    }
+    if args.format == OutputFormat::Dot {
+        log::error("The dot format is only for the graph mode");
+        ExitCode::Error.exit();
+    }
+
+    let debugging = args.is_debugging();
+    let settings = Settings {
+        // The execution can only be rewound in the debugger and in the terminal view
+        history_size: if !debugging && !args.tui {
+            0
+        } else {
+            debugger::HISTORY_SIZE
+        },
+        ..args.settings()
+    };
+
+    // Run the test cases instead of a single execution
+    if args.test_file.is_some() || matches!(args.mode, Mode::Test | Mode::Mutate) {
+        let mut cases = vec![];
+        if matches!(args.mode, Mode::Test | Mode::Mutate) {
+            cases = manifest::read_script_cases(&args.script_file).unwrap_or_else(|e| {
+                log::error(e);
+                ExitCode::Parse.exit();
+            });
+        }
+        if let Some(test_file) = &args.test_file {
+            cases.extend(manifest::read_manifest(test_file).unwrap_or_else(|e| {
+                log::error(e);
+                ExitCode::Error.exit();
+            }));
+        }
+        log::verbose("test", format!("{} test cases", cases.len()));
+        if cases.is_empty() {
+            log::error(
+                "No test case in the script: write them as '-- TEST: inputs = 1 2 3 ; expected = 6'",
+            );
+            ExitCode::Error.exit();
+        }
+
+        // Or on each mutant of the script
+        if args.mode == Mode::Mutate {
+            let results =
+                mutate::run(&script_object, &cases, &settings, args.jobs).unwrap_or_else(|e| {
+                    log::error(e);
+                    ExitCode::Mismatch.exit();
+                });
+            if mutate::report(&results) {
+                ExitCode::Success
+            } else {
+                ExitCode::Rejected
+            }
+            .exit();
+        }
+        let all_passed = manifest::run_all(&script_object, &cases, &settings, args.jobs);
+        if all_passed {
+            ExitCode::Success
+        } else {
+            ExitCode::Mismatch
+        }
+        .exit();
+    }
+
+    // Run the next scripts on the outputs, and print the outputs of the last one
+    if args.mode == Mode::Pipe {
+        run_pipe(&args, script_object, &settings);
+    }
+
+    // Measure the wall time of many runs instead of a single execution
+    if args.mode == Mode::Bench {
+        let benchmark = bench::run(
+            &script_object,
+            &args.memory,
+            args.max_memory_address,
+            &args.input_values,
+            &settings,
+            args.iterations.unwrap_or(bench::DEFAULT_ITERATIONS),
+        )
+        .unwrap_or_else(|e| {
+            log::error(e);
+            ExitCode::Runtime.exit();
+        });
+        bench::report(&benchmark);
+        ExitCode::Success.exit();
+    }
+
+    // Compare the script with another one on all the inboxes within a bound
+    if args.mode == Mode::Equiv {
+        let other_script = args
+            .compare_script
+            .as_deref()
+            .map(|script| ScriptObject::parse_with_options(script, &args.parse_options()))
+            .expect("equiv is given another script")
+            .unwrap_or_else(|e| {
+                log::error(e);
+                ExitCode::Parse.exit();
+            });
+        let bound = equiv::Bound {
+            values: args.value_range.clone().unwrap_or_else(|| {
+                generator::range_values(equiv::DEFAULT_RANGE).expect("the default range is valid")
+            }),
+            max_len: args.max_length.unwrap_or(equiv::DEFAULT_MAX_LEN),
+        };
+        let equivalence = equiv::check(
+            &script_object,
+            &other_script,
+            &args.memory,
+            args.max_memory_address,
+            &bound,
+            &settings,
+            args.jobs,
+        );
+        if equiv::report(&equivalence, &bound) {
+            ExitCode::Success
+        } else {
+            ExitCode::Mismatch
+        }
+        .exit();
+    }
+
+    // Check the properties on all the inboxes within a bound by symbolic execution
+    if args.mode == Mode::SolveFor {
+        let values = args.value_range.clone().unwrap_or_else(|| {
+            generator::range_values(solve::DEFAULT_RANGE).expect("the default range is valid")
+        });
+        let numbers = values
+            .iter()
+            .map(|value| match value {
+                ValueBox::Number(n) => Some(i64::from(*n)),
+                ValueBox::Character(_) => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_else(|| {
+                log::error("solve-for only handles inboxes of numbers, not letters");
+                ExitCode::Error.exit();
+            });
+        let bound = solve::Bound {
+            min: numbers.iter().copied().min().unwrap_or_default(),
+            max: numbers.iter().copied().max().unwrap_or_default(),
+            max_len: args.max_length.unwrap_or(equiv::DEFAULT_MAX_LEN),
+        };
+        let outcome = solve::check(
+            &script_object,
+            &args.memory,
+            args.max_memory_address,
+            &args.properties,
+            &bound,
+            &settings,
+        )
+        .unwrap_or_else(|e| {
+            log::error(e);
+            ExitCode::Error.exit();
+        });
+        let proven = solve::report(&outcome, &args.properties, &bound);
+        match outcome {
+            _ if proven => ExitCode::Success,
+            solve::Outcome::Inconclusive(_) => ExitCode::Error,
+            solve::Outcome::Proven { .. } | solve::Outcome::Counterexample { .. } => {
+                ExitCode::Mismatch
+            }
+        }
+        .exit();
+    }
 
-    let memory = Memory::with_data(args.memory, args.max_memory_address);
-    let mut interpreter = Interpreter::new(memory);
+    // Run another script on the same inputs instead of a single execution
+    if let Some(compare_script) = &args.compare_script {
+        let other_script = ScriptObject::parse_with_options(compare_script, &args.parse_options())
+            .unwrap_or_else(|e| {
+                log::error(e);
+                ExitCode::Parse.exit();
+            });
+
+        let mut inboxes = vec![args.input_values.clone()];
+        if inputs_generated {
+            inboxes.extend(random_inboxes(
+                args.input_spec.as_ref(),
+                level,
+                &mut rng,
+                compare::GENERATED_RUNS - 1,
+            ));
+        }
+
+        let comparisons = inboxes
+            .iter()
+            .map(|inputs| {
+                compare::compare(
+                    &script_object,
+                    &other_script,
+                    &args.memory,
+                    args.max_memory_address,
+                    inputs,
+                    &settings,
+                )
+            })
+            .collect::<Vec<_>>();
+        if !compare::report(&comparisons) {
+            let diverging = comparisons
+                .iter()
+                .find(|comparison| comparison.divergence().is_some());
+            if let Some(comparison) = diverging.filter(|_| inputs_generated) {
+                report_shrunk(
+                    &comparison.inputs,
+                    args.input_spec.as_ref(),
+                    level,
+                    |inputs| {
+                        compare::compare(
+                            &script_object,
+                            &other_script,
+                            &args.memory,
+                            args.max_memory_address,
+                            inputs,
+                            &settings,
+                        )
+                        .divergence()
+                        .map(|mismatch| {
+                            format!("the scripts diverge at output #{}", mismatch.index + 1)
+                        })
+                    },
+                );
+            }
+            report_seed(random_seed);
+            ExitCode::Mismatch.exit();
+        }
+        ExitCode::Success.exit();
+    }
+
+    // Show the execution in the terminal instead of printing its results
+    if args.tui {
+        run_tui(&args, &script_object, settings);
+    }
+
+    // The outputs produced before the checkpoint, when resuming
+    let mut outputs = vec![];
+    let mut interpreter = match checkpoint {
+        Some(checkpoint) => {
+            outputs = checkpoint.outputs;
+            // The settings of this run apply to the rest of the execution
+            let mut snapshot = checkpoint.interpreter;
+            snapshot.settings = settings.clone();
+            Interpreter::from_snapshot(snapshot)
+        }
+        None => {
+            let memory = Memory::with_data(args.memory.clone(), args.max_memory_address);
+            Interpreter::with_settings(memory, settings.clone())
+        }
+    };
+
+    if args.trace {
+        let mut trace = Some(output::open_trace(args.trace_file.as_deref()));
+        let trace_format = args.trace_format;
+        interpreter.set_tracer(move |entry| {
+            // The execution goes on without its trace if it can't be written
+            if let Some(writer) = &mut trace {
+                if let Err(e) = output::write_trace_entry(trace_format, writer, entry) {
+                    log::error(format!("Could not write the trace: {}", e));
+                    trace = None;
+                }
+            }
+        });
+    }
+
+    if args.extensions {
+        // The lines of the DEBUG instructions, as the script isn't kept by the callback
+        let lines = script_object
+            .blocks()
+            .iter()
+            .flat_map(|block| {
+                (0..block.instructions.len()).map(|instruction| Position {
+                    block: block.index(),
+                    instruction,
+                })
+            })
+            .filter(|position| {
+                matches!(
+                    script_object.get_instruction(*position),
+                    Some(Instruction::Debug(_))
+                )
+            })
+            .filter_map(|position| Some((position, script_object.get_line(position)?)))
+            .collect::<HashMap<_, _>>();
+        interpreter.set_debug_callback(move |position, text| {
+            eprintln!(
+                "DEBUG line {}: {}",
+                lines.get(&position).copied().unwrap_or_default(),
+                text
+            );
+        });
+    }
 
     // Execute the script
-    match interpreter.execute(&script_object, &args.input_values) {
+    log::verbose(
+        "execute",
+        format!(
+            "inbox of {} values, {} tiles used on the floor",
+            args.input_values.len(),
+            interpreter.memory().used_tiles()
+        ),
+    );
+    log::trace(
+        "execute",
+        format!("tiles stored in a {}", interpreter.memory().storage()),
+    );
+    log::trace("execute", format!("{:?}", settings));
+    let streaming = args.stream && args.mode == Mode::Run;
+    let output_options = args.output_options();
+    let checkpointing = args.checkpoint_every.is_some() || args.checkpoint_file.is_some();
+    let result = if !debugging && checkpointing {
+        checkpoint::execute_with_checkpoints(
+            &mut interpreter,
+            &script_object,
+            &args.input_values,
+            &mut outputs,
+            args.checkpoint_every
+                .unwrap_or(checkpoint::DEFAULT_INTERVAL),
+            args.checkpoint_file
+                .as_deref()
+                .unwrap_or(checkpoint::DEFAULT_FILE),
+        )
+        .map(|_| outputs)
+    } else if !debugging && streaming {
+        let mut streamed =
+            output::WrittenOutputs::new(output_options.writer(), &output_options.separator);
+        for value in outputs {
+            streamed.output(value);
+        }
+        let result = interpreter.execute_into(&script_object, &args.input_values, &mut streamed);
+        let outputs = streamed.finish(output_options.newline);
+        result.map(|_| outputs)
+    } else if !debugging {
+        interpreter
+            .execute_into(&script_object, &args.input_values, &mut outputs)
+            .map(|_| outputs)
+    } else {
+        for breakpoint in args.breakpoints {
+            interpreter.add_breakpoint(breakpoint);
+        }
+        for address in args.watchpoints {
+            interpreter.add_watchpoint(address);
+        }
+        match debugger::run_with_debugger(
+            &mut interpreter,
+            &script_object,
+            &args.input_values,
+            outputs,
+        ) {
+            Ok(debugger::DebuggerExit::Finished(outputs)) => Ok(outputs),
+            Ok(debugger::DebuggerExit::Quit) => {
+                log::error("The execution was stopped at the debugger prompt");
+                ExitCode::Error.exit();
+            }
+            Err(e) => Err(e),
+        }
+    };
+    log::verbose(
+        "execute",
+        format!(
+            "{} after {} steps",
+            if result.is_ok() { "finished" } else { "failed" },
+            interpreter.steps()
+        ),
+    );
+
+    if let Some(record_file) = &args.record_file {
+        let outputs = match &result {
+            Ok(outputs) => outputs.as_slice(),
+            Err(e) => e.state().outputs().unwrap_or_default(),
+        };
+        let session = session::Session {
+            inputs: args.input_values[..interpreter.inputs_read()].to_vec(),
+            outputs: outputs.to_vec(),
+        };
+        session::write_session(record_file, &session).unwrap_or_else(|e| {
+            log::error(format!(
+                "Could not write the session to {}: {}",
+                record_file, e
+            ));
+            ExitCode::Error.exit();
+        });
+    }
+
+    match result {
         Ok(outputs) => {
-            // Print the outputs to stdout
-            let out_str = outputs
-                .iter()
-                .map(|value| value.to_string())
-                .collect::<Vec<String>>()
-                .join(" ");
-            print!("{}", out_str);
+            if args.mode == Mode::Run && (!streaming || debugging || checkpointing) {
+                output::print_results(&output_options, &outputs, &interpreter);
+            }
+
+            if args.dump_head {
+                match interpreter.head() {
+                    Some(head) => eprintln!("Head: {}", head),
+                    None => eprintln!("Head: empty"),
+                }
+                if args.second_hand {
+                    match interpreter.second_hand() {
+                        Some(value) => eprintln!("Second hand: {}", value),
+                        None => eprintln!("Second hand: empty"),
+                    }
+                }
+            }
+
+            if args.dump_memory {
+                let memory = output::memory_to_text(interpreter.memory());
+                match &args.dump_memory_file {
+                    Some(file) => std::fs::write(file, memory).unwrap_or_else(|e| {
+                        log::error(format!("Could not write the memory to {}: {}", file, e));
+                        ExitCode::Error.exit();
+                    }),
+                    None => match args.floor {
+                        Some(floor) => eprint!(
+                            "Floor:\n{}",
+                            floor.render(|address| interpreter.memory().get(&address).copied())
+                        ),
+                        None => eprint!("Memory:\n{}", memory),
+                    },
+                }
+            }
+
+            if args.print_stats {
+                let size = script_object.instruction_count();
+                match args.max_instructions {
+                    Some(max_instructions) => {
+                        eprintln!("Size: {} (max {})", size, max_instructions)
+                    }
+                    None => eprintln!("Size: {}", size),
+                }
+                eprintln!("{}", interpreter.stats());
+                if let Some(floor) = args.floor {
+                    let tiles = &interpreter.stats().tiles;
+                    eprint!(
+                        "Accesses:\n{}",
+                        floor.render(|address| tiles.get(&address).map(|a| a.total()))
+                    );
+                }
+            }
+            if let Some(cost_model) = &cost_model {
+                eprintln!(
+                    "Cycles: {}",
+                    cost_model.cycles(&script_object, interpreter.stats())
+                );
+            }
+
+            if args.profile {
+                profile::report(&profile::profile(&script_object, interpreter.stats()));
+            }
+
+            let mut score = score::Score {
+                size: script_object.instruction_count(),
+                steps: interpreter.stats().steps,
+                average: None,
+            };
+            if args.score {
+                score.average = args.score_runs.map(|runs| {
+                    score::average_steps(
+                        &script_object,
+                        &args.memory,
+                        args.max_memory_address,
+                        &random_inboxes(args.input_spec.as_ref(), level, &mut rng, runs),
+                        &settings,
+                        level,
+                    )
+                    .unwrap_or_else(|failure| {
+                        log::error(format!("Score: {}", failure));
+                        report_shrunk(&failure.inputs, args.input_spec.as_ref(), level, |inputs| {
+                            score::steps(
+                                &script_object,
+                                &args.memory,
+                                args.max_memory_address,
+                                inputs,
+                                &settings,
+                                level,
+                            )
+                            .err()
+                        });
+                        report_seed(random_seed);
+                        ExitCode::Runtime.exit();
+                    })
+                });
+                score::report(&score);
+            }
+
+            let mut coverage_ok = true;
+            if args.coverage {
+                let coverage = coverage::coverage(&script_object, interpreter.stats());
+                coverage::report(&coverage);
+                coverage_ok = coverage.is_full() || !args.require_full_coverage;
+            }
+
+            let passed = expected_outputs
+                .map(|expected| check::report(&title, &args.input_values, &expected, &outputs));
+
+            if let Some(report_file) = &args.report_file {
+                let report = report::Report {
+                    script_path: &args.script_path,
+                    source: &args.script_file,
+                    script: &script_object,
+                    stats: interpreter.stats(),
+                    inputs: &args.input_values,
+                    outputs: &outputs,
+                    memory: interpreter.memory(),
+                    score: &score,
+                    check: passed.map(|passed| (title.as_str(), passed)),
+                    floor: args.floor,
+                };
+                report::write_report(report_file, &report).unwrap_or_else(|e| {
+                    log::error(format!(
+                        "Could not write the report to {}: {}",
+                        report_file, e
+                    ));
+                    ExitCode::Error.exit();
+                });
+            }
+
+            if passed == Some(false) {
+                if inputs_generated && checked_level.is_some() {
+                    report_shrunk(
+                        &args.input_values,
+                        args.input_spec.as_ref(),
+                        level,
+                        |inputs| {
+                            score::steps(
+                                &script_object,
+                                &args.memory,
+                                args.max_memory_address,
+                                inputs,
+                                &settings,
+                                checked_level,
+                            )
+                            .err()
+                        },
+                    );
+                }
+                report_seed(random_seed);
+                ExitCode::Mismatch.exit();
+            }
+            if !coverage_ok {
+                ExitCode::Rejected.exit();
+            }
+        }
+        Err(e) if args.error_format == ErrorFormat::Json => {
+            let diagnostic = e
+                .diagnostic()
+                .unwrap_or_else(|| Diagnostic::new(e.message(), 0, None));
+            print_json_error(
+                &args.script_file,
+                "runtime",
+                e.code(),
+                &diagnostic,
+                Some(e.state()),
+            );
+            ExitCode::Runtime.exit();
         }
         Err(e) => {
-            eprintln!("{}", e);
-            std::process::exit(1);
+            match e.diagnostic() {
+                Some(diagnostic) => log::error(format!(
+                    "{}-- STATE --\n{}",
+                    diagnostic.render(&args.script_path, &args.script_file),
+                    e.state()
+                )),
+                None => log::error(&e),
+            }
+            if let Some(floor) = args.floor {
+                let floor = floor.render(|address| e.state().tile(address));
+                log::error(format!("-- FLOOR --\n{}", floor.trim_end()));
+            }
+            if inputs_generated {
+                report_shrunk(
+                    &args.input_values,
+                    args.input_spec.as_ref(),
+                    level,
+                    |inputs| {
+                        score::steps(
+                            &script_object,
+                            &args.memory,
+                            args.max_memory_address,
+                            inputs,
+                            &settings,
+                            checked_level,
+                        )
+                        .err()
+                    },
+                );
+            }
+            report_seed(random_seed);
+            ExitCode::Runtime.exit();
+        }
+    }
+}
+
+#[cfg(feature = "tui")]
+fn run_tui(args: &cli_reader::CommandLineArgs, script: &ScriptObject, settings: Settings) -> ! {
+    let new_interpreter = || {
+        let memory = Memory::with_data(args.memory.clone(), args.max_memory_address);
+        let mut interpreter = Interpreter::with_settings(memory, settings.clone());
+        for breakpoint in &args.breakpoints {
+            interpreter.add_breakpoint(breakpoint.clone());
+        }
+        interpreter
+    };
+    let result = tui::run_tui(
+        script,
+        &args.script_file,
+        &args.input_values,
+        args.floor,
+        new_interpreter,
+    );
+    if let Err(e) = result {
+        log::error(format!("Terminal error: {}", e));
+        ExitCode::Error.exit();
+    }
+    ExitCode::Success.exit();
+}
+
+#[cfg(not(feature = "tui"))]
+fn run_tui(_: &cli_reader::CommandLineArgs, _: &ScriptObject, _: Settings) -> ! {
+    log::error("The terminal view is not available: build with the 'tui' feature");
+    ExitCode::Error.exit();
+}
+
+/// Generate random inboxes with the input spec, or else with the level
+fn random_inboxes(
+    input_spec: Option<&InboxSpec>,
+    level: Option<&Level>,
+    rng: &mut StdRng,
+    count: usize,
+) -> Vec<Vec<ValueBox>> {
+    (0..count)
+        .map(|_| match (input_spec, level) {
+            (Some(input_spec), _) => input_spec.generate(rng),
+            (None, Some(level)) => level.generate_inbox(rng),
+            (None, None) => unreachable!(),
+        })
+        .collect()
+}
+
+/// Shrink a random inbox on which the run failed, and print the smallest one on which it still fails.
+/// The values of the smaller inboxes stay valid for the input spec and the level.
+fn report_shrunk(
+    inputs: &[ValueBox],
+    input_spec: Option<&InboxSpec>,
+    level: Option<&Level>,
+    failure: impl Fn(&[ValueBox]) -> Option<String>,
+) {
+    let valid = |inputs: &[ValueBox]| {
+        input_spec.is_none_or(|spec| inputs.iter().all(|value| spec.allows(value)))
+            && level.is_none_or(|level| level.expected_outbox(inputs).is_some())
+    };
+    let shrunk = generator::shrink(inputs, |inputs| valid(inputs) && failure(inputs).is_some());
+    if shrunk != inputs {
+        log::note(format!(
+            "Smallest failing inbox: {} ({})",
+            check::join_values(&shrunk),
+            failure(&shrunk).unwrap_or_default()
+        ));
+    }
+}
+
+/// Tell how to generate the random inboxes of a failed run again
+fn report_seed(seed: Option<u64>) {
+    if let Some(seed) = seed {
+        log::note(format!(
+            "Random seed: {} (give --seed {} to reproduce)",
+            seed, seed
+        ));
+    }
+}
+
+/// Run every script of the directory on the test cases, and print a table of the results
+fn run_batch(args: &cli_reader::CommandLineArgs) -> ! {
+    let Some(test_file) = &args.test_file else {
+        log::error("Nothing to run the scripts on: give the test cases with -t");
+        ExitCode::Error.exit();
+    };
+    let cases = manifest::read_manifest(test_file).unwrap_or_else(|e| {
+        log::error(e);
+        ExitCode::Error.exit();
+    });
+    let scripts = batch::scripts(Path::new(&args.script_path)).unwrap_or_else(|e| {
+        log::error(format!(
+            "Could not read the directory {}: {}",
+            args.script_path, e
+        ));
+        ExitCode::Error.exit();
+    });
+    let settings = args.settings();
+
+    let parse_options = args.parse_options();
+    let results = jobs::parallel_map(&scripts, args.jobs, |script| {
+        batch::run_script(script, &cases, &settings, &parse_options)
+    });
+    if batch::report(&results) {
+        ExitCode::Success
+    } else {
+        ExitCode::Mismatch
+    }
+    .exit();
+}
+
+/// Run the stages of the pipeline, each one on its own floor
+fn run_pipe(args: &cli_reader::CommandLineArgs, script: ScriptObject, settings: &Settings) -> ! {
+    let mut stages = vec![(args.script_path.clone(), script)];
+    for path in &args.pipe_scripts {
+        let source = std::fs::read_to_string(path).unwrap_or_else(|e| {
+            log::error(format!("Could not read the script {}: {}", path, e));
+            ExitCode::Error.exit();
+        });
+        let parse_options = ParseOptions {
+            include_dir: Path::new(path).parent().map(Path::to_path_buf),
+            ..args.parse_options()
+        };
+        let script =
+            ScriptObject::parse_with_options(&source, &parse_options).unwrap_or_else(|e| {
+                log::error(format!("{}: {}", path, e));
+                ExitCode::Parse.exit();
+            });
+        stages.push((path.clone(), script));
+    }
+    let configured = args.stage_memory.keys().chain(args.stage_max_memory.keys());
+    if let Some(stage) = configured.filter(|stage| **stage > stages.len()).min() {
+        log::error(format!(
+            "There is no stage {}: the pipeline has {} stages",
+            stage,
+            stages.len()
+        ));
+        ExitCode::Error.exit();
+    }
+
+    let stages = stages
+        .into_iter()
+        .enumerate()
+        .map(|(i, (path, script))| pipe::Stage {
+            path,
+            script,
+            memory: args
+                .stage_memory
+                .get(&(i + 1))
+                .unwrap_or(&args.memory)
+                .clone(),
+            max_memory_address: args
+                .stage_max_memory
+                .get(&(i + 1))
+                .copied()
+                .unwrap_or(args.max_memory_address),
+        })
+        .collect::<Vec<_>>();
+    let runs = pipe::run(&stages, &args.input_values, settings).unwrap_or_else(|e| {
+        log::error(e);
+        ExitCode::Runtime.exit();
+    });
+
+    if args.print_stats {
+        for (i, (stage, run)) in stages.iter().zip(&runs).enumerate() {
+            eprintln!(
+                "Stage {} ({}):\n{}",
+                i + 1,
+                stage.path,
+                run.interpreter.stats()
+            );
         }
     }
+    let last = runs.last().expect("a pipeline has stages");
+    output::print_results(&args.output_options(), &last.outputs, &last.interpreter);
+    ExitCode::Success.exit();
+}
+
+/// Execute the instructions typed at the prompt, on the given floor and inbox
+fn run_repl(args: &cli_reader::CommandLineArgs) -> ! {
+    let settings = args.settings();
+    let memory = Memory::with_data(args.memory.clone(), args.max_memory_address);
+    repl::run(memory, settings, &args.input_values, &args.parse_options());
+    ExitCode::Success.exit();
+}
+
+/// Search the smallest solutions of the level, on inboxes it generates
+fn run_search(args: &cli_reader::CommandLineArgs) -> ! {
+    let Some(level) = args.level.and_then(levels::get_level) else {
+        log::error("Nothing to search a solution for: give a level with -L");
+        ExitCode::Error.exit();
+    };
+    let settings = args.settings();
+    let memory = if args.memory.is_empty() {
+        level.memory()
+    } else {
+        args.memory.clone()
+    };
+    let max_memory_address = match args.max_memory_address {
+        usize::MAX => level.max_address(),
+        max_address => max_address,
+    };
+
+    let mut rng = StdRng::seed_from_u64(args.seed.unwrap_or_else(rand::random));
+    let cases = (0..search::INBOXES)
+        .map(|i| {
+            let inputs = level.generate_inbox(&mut rng);
+            manifest::TestCase {
+                name: format!("inbox #{}", i + 1),
+                expected: level
+                    .expected_outbox(&inputs)
+                    .expect("the generated inboxes are valid"),
+                inputs,
+                memory: memory.clone(),
+                max_memory_address,
+            }
+        })
+        .collect::<Vec<_>>();
+    let space = SearchSpace {
+        floor_size: level.floor_size.min(max_memory_address.saturating_add(1)),
+        memory,
+        examples: search::examples(&cases),
+    };
+
+    let max_size = args.max_size.unwrap_or(search::DEFAULT_MAX_SIZE);
+    let search = search::run(&space, &cases, max_size, &settings, args.jobs);
+    if search::report(&search, max_size) {
+        ExitCode::Success
+    } else {
+        ExitCode::Mismatch
+    }
+    .exit();
+}
+
+/// Exit if the script isn't valid
+fn validate(args: &cli_reader::CommandLineArgs, script: &ScriptObject) {
+    // The script is the first stage of a pipeline, which can have its own floor
+    let max_memory_address = args
+        .stage_max_memory
+        .get(&1)
+        .copied()
+        .unwrap_or(args.max_memory_address);
+    let result = script
+        .validate()
+        .and_then(|_| match args.max_instructions {
+            Some(max_instructions) => script.validate_size(max_instructions),
+            None => Ok(()),
+        })
+        .and_then(|_| match max_memory_address {
+            usize::MAX => Ok(()),
+            max_address => script.validate_addresses(max_address),
+        });
+    if let Err(e) = result {
+        if args.error_format == ErrorFormat::Json {
+            for diagnostic in e.diagnostics(script) {
+                print_json_error(&args.script_file, "validation", e.code(), &diagnostic, None);
+            }
+        } else {
+            log::error(e);
+        }
+        ExitCode::Validation.exit();
+    }
+    log::verbose("validate", "the labels are defined");
+    if let Some(max_instructions) = args.max_instructions {
+        log::trace(
+            "validate",
+            format!(
+                "{} instructions, at most {}",
+                script.instruction_count(),
+                max_instructions
+            ),
+        );
+    }
+    if max_memory_address != usize::MAX {
+        log::trace(
+            "validate",
+            format!("addresses up to {}", max_memory_address),
+        );
+    }
+
+    if args.check_loops {
+        for infinite_loop in loops::infinite_loops(script) {
+            let line = script.get_line(infinite_loop.entry).unwrap_or_default();
+            if args.error_format == ErrorFormat::Json {
+                let diagnostic = Diagnostic::new(loops::INFINITE_LOOP, line, None);
+                print_json_error(
+                    &args.script_file,
+                    "warning",
+                    "infinite-loop",
+                    &diagnostic,
+                    None,
+                );
+            } else {
+                log::warning(format!("line {}: {}", line, loops::INFINITE_LOOP));
+            }
+        }
+    }
+}
+
+fn print_json_error(
+    source: &str,
+    kind: &str,
+    code: &str,
+    diagnostic: &Diagnostic,
+    state: Option<&InterpreterStateInfo>,
+) {
+    log::error(output::error_to_json(kind, code, diagnostic, source, state));
 }
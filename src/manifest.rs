@@ -0,0 +1,516 @@
+use std::{collections::HashMap, path::Path};
+
+use hrm_interpreter::{
+    interpreter::{
+        memory::{Memory, SetMemoryError},
+        settings::Settings,
+        Interpreter,
+    },
+    script_object::{value_box::ValueBox, ScriptObject, TEST_ANNOTATION},
+};
+use serde_json::Value;
+
+use crate::{
+    check::{self, Mismatch},
+    jobs,
+    style::{Stream, Style},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+/// A named test case: the script is executed with these inputs and memory,
+/// and must produce the expected outputs.
+pub struct TestCase {
+    pub name: String,
+    pub inputs: Vec<ValueBox>,
+    pub memory: HashMap<usize, ValueBox>,
+    pub max_memory_address: usize,
+    pub expected: Vec<ValueBox>,
+}
+
+#[derive(Debug, thiserror::Error)]
+/// Error that can occur when reading a test manifest.
+pub enum ReadManifestError {
+    #[error("could not read the test file:\n\t{0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid TOML:\n\t{0}")]
+    InvalidToml(#[from] toml::de::Error),
+    #[error("invalid JSON:\n\t{0}")]
+    InvalidJson(#[from] serde_json::Error),
+    #[error("invalid test case #{case}: {reason}")]
+    InvalidCase { case: usize, reason: String },
+    #[error("invalid test annotation on line {line}: {reason}")]
+    InvalidAnnotation { line: usize, reason: String },
+}
+
+/// The result of a test case
+pub enum CaseResult {
+    Passed { steps: usize },
+    Failed { steps: usize, mismatch: Mismatch },
+    Error(String),
+}
+
+/// Read a test manifest. The format depends on the extension: `.json` for JSON, TOML otherwise.
+///
+/// The manifest holds a list of cases (`[[case]]` tables in TOML, a `case` array in JSON),
+/// each with a `name`, `inputs`, an initial `memory` (address = value), a `max_memory` address
+/// and the `expected` outputs. Only `expected` is mandatory.
+pub fn read_manifest(path: &str) -> Result<Vec<TestCase>, ReadManifestError> {
+    let content = std::fs::read_to_string(path)?;
+    let manifest: Value = match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("json") => serde_json::from_str(&content)?,
+        _ => serde_json::to_value(toml::from_str::<toml::Value>(&content)?)?,
+    };
+    parse_manifest(&manifest)
+}
+
+fn parse_manifest(manifest: &Value) -> Result<Vec<TestCase>, ReadManifestError> {
+    let cases = match manifest.get("case") {
+        Some(Value::Array(cases)) => cases,
+        _ => {
+            return Err(ReadManifestError::InvalidCase {
+                case: 0,
+                reason: "the manifest should have a list of cases named 'case'".to_string(),
+            })
+        }
+    };
+
+    cases
+        .iter()
+        .enumerate()
+        .map(|(i, case)| {
+            parse_case(case, i + 1).map_err(|reason| ReadManifestError::InvalidCase {
+                case: i + 1,
+                reason,
+            })
+        })
+        .collect()
+}
+
+fn parse_case(case: &Value, number: usize) -> Result<TestCase, String> {
+    let name = match case.get("name") {
+        Some(Value::String(name)) => name.clone(),
+        Some(_) => return Err("'name' should be a string".to_string()),
+        None => format!("case {}", number),
+    };
+    let inputs = match case.get("inputs") {
+        Some(inputs) => parse_values(inputs, "inputs")?,
+        None => vec![],
+    };
+    let expected = match case.get("expected") {
+        Some(expected) => parse_values(expected, "expected")?,
+        None => return Err("'expected' is missing".to_string()),
+    };
+
+    let mut memory = HashMap::new();
+    match case.get("memory") {
+        Some(Value::Object(tiles)) => {
+            for (address, value) in tiles {
+                let address = address
+                    .parse::<usize>()
+                    .map_err(|_| format!("invalid memory address: {}", address))?;
+                memory.insert(address, parse_value(value, "memory")?);
+            }
+        }
+        Some(_) => return Err("'memory' should be a table of address = value".to_string()),
+        None => {}
+    }
+
+    let max_memory_address = match case.get("max_memory") {
+        Some(max) => max
+            .as_u64()
+            .map(|max| max as usize)
+            .ok_or("'max_memory' should be a positive number")?,
+        None => usize::MAX,
+    };
+
+    let case = TestCase {
+        name,
+        inputs,
+        memory,
+        max_memory_address,
+        expected,
+    };
+    check_memory(&case)?;
+    Ok(case)
+}
+
+/// Check that the tiles of the memory of the case are on its floor
+fn check_memory(case: &TestCase) -> Result<(), String> {
+    match Memory::check_tiles(&case.memory, case.max_memory_address) {
+        Err(SetMemoryError::OutOfBounds {
+            address,
+            max_address,
+        }) => Err(format!(
+            "'{}' has a value on tile {} in 'memory', past 'max_memory' ({})",
+            case.name, address, max_address
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Read the test cases written in the script, one per annotation line:
+///
+/// ```text
+/// -- TEST: name = sample ; inputs = 1 2 3 ; memory = 9=0 ; max_memory = 9 ; expected = 6
+/// ```
+///
+/// The fields are the ones of the manifest, the values are separated by whitespaces,
+/// and only `expected` is mandatory.
+pub fn read_script_cases(script: &str) -> Result<Vec<TestCase>, ReadManifestError> {
+    script
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| Some((i + 1, line.trim().strip_prefix(TEST_ANNOTATION)?)))
+        .map(|(line, annotation)| {
+            parse_annotation(annotation, line)
+                .map_err(|reason| ReadManifestError::InvalidAnnotation { line, reason })
+        })
+        .collect()
+}
+
+fn parse_annotation(annotation: &str, line: usize) -> Result<TestCase, String> {
+    let mut case = TestCase {
+        name: format!("line {}", line),
+        inputs: vec![],
+        memory: HashMap::new(),
+        max_memory_address: usize::MAX,
+        expected: vec![],
+    };
+    let mut expected = false;
+    let values = |values: &str, field: &str| {
+        values
+            .split_whitespace()
+            .map(|value| {
+                value
+                    .parse::<ValueBox>()
+                    .map_err(|_| format!("invalid value in '{}': {}", field, value))
+            })
+            .collect::<Result<Vec<_>, _>>()
+    };
+
+    for field in annotation
+        .split(';')
+        .filter(|field| !field.trim().is_empty())
+    {
+        let (key, value) = field
+            .split_once('=')
+            .ok_or_else(|| format!("expected 'key = values': {}", field.trim()))?;
+        let value = value.trim();
+        match key.trim() {
+            "name" => case.name = value.to_string(),
+            "inputs" => case.inputs = values(value, "inputs")?,
+            "expected" => {
+                case.expected = values(value, "expected")?;
+                expected = true;
+            }
+            "memory" => {
+                for tile in value.split_whitespace() {
+                    let (address, value) = tile
+                        .split_once('=')
+                        .ok_or_else(|| format!("expected 'address=value' in 'memory': {}", tile))?;
+                    let address = address
+                        .parse::<usize>()
+                        .map_err(|_| format!("invalid memory address: {}", address))?;
+                    let value = value
+                        .parse::<ValueBox>()
+                        .map_err(|_| format!("invalid value in 'memory': {}", value))?;
+                    case.memory.insert(address, value);
+                }
+            }
+            "max_memory" => {
+                case.max_memory_address = value
+                    .parse::<usize>()
+                    .map_err(|_| "'max_memory' should be a positive number".to_string())?
+            }
+            key => return Err(format!("unknown key: {}", key)),
+        }
+    }
+
+    if !expected {
+        return Err("'expected' is missing".to_string());
+    }
+    check_memory(&case)?;
+    Ok(case)
+}
+
+/// Numbers are numbers, strings are parsed like the values of the command line
+pub fn parse_value(value: &Value, field: &str) -> Result<ValueBox, String> {
+    let invalid = || format!("invalid value in '{}': {}", field, value);
+    match value {
+        Value::Number(n) => n
+            .as_i64()
+            .and_then(|n| i32::try_from(n).ok())
+            .map(ValueBox::Number)
+            .ok_or_else(invalid),
+        Value::String(s) => s.parse::<ValueBox>().map_err(|_| invalid()),
+        _ => Err(invalid()),
+    }
+}
+
+pub fn parse_values(values: &Value, field: &str) -> Result<Vec<ValueBox>, String> {
+    match values {
+        Value::Array(values) => values
+            .iter()
+            .map(|value| parse_value(value, field))
+            .collect(),
+        _ => Err(format!("'{}' should be a list", field)),
+    }
+}
+
+/// Execute the script on a test case
+pub fn run_case(script: &ScriptObject, case: &TestCase, settings: &Settings) -> CaseResult {
+    let mut interpreter = Interpreter::with_settings(Memory::default(), settings.clone());
+    run_case_with(&mut interpreter, script, case)
+}
+
+/// Execute the script on a test case with the given interpreter, reset for the case
+pub fn run_case_with(
+    interpreter: &mut Interpreter,
+    script: &ScriptObject,
+    case: &TestCase,
+) -> CaseResult {
+    interpreter.reset(Memory::with_data(
+        case.memory.clone(),
+        case.max_memory_address,
+    ));
+
+    match interpreter.execute(script, &case.inputs) {
+        Ok((outputs, stats)) => match check::first_mismatch(&case.expected, &outputs) {
+            None => CaseResult::Passed { steps: stats.steps },
+            Some(mismatch) => CaseResult::Failed {
+                steps: stats.steps,
+                mismatch,
+            },
+        },
+        Err(e) => CaseResult::Error(e.to_string()),
+    }
+}
+
+/// Run all the cases on `jobs` threads, printing a line per case and a summary to stderr.
+/// Returns true if all the cases passed.
+pub fn run_all(
+    script: &ScriptObject,
+    cases: &[TestCase],
+    settings: &Settings,
+    jobs: usize,
+) -> bool {
+    let mut passed = 0;
+    let mut steps = vec![];
+
+    let results = jobs::parallel_map(cases, jobs, |case| run_case(script, case, settings));
+    for (case, result) in cases.iter().zip(results) {
+        match result {
+            CaseResult::Passed { steps: case_steps } => {
+                eprintln!(
+                    "{}  {} ({} steps)",
+                    Stream::Stderr.paint(Style::Success, "PASS"),
+                    case.name,
+                    case_steps
+                );
+                passed += 1;
+                steps.push(case_steps);
+            }
+            CaseResult::Failed {
+                steps: case_steps,
+                mismatch,
+            } => {
+                eprintln!(
+                    "{}  {} ({} steps)",
+                    Stream::Stderr.paint(Style::Error, "FAIL"),
+                    case.name,
+                    case_steps
+                );
+                eprintln!("{}", mismatch.painted());
+                steps.push(case_steps);
+            }
+            CaseResult::Error(e) => {
+                eprintln!(
+                    "{} {}",
+                    Stream::Stderr.paint(Style::Error, "ERROR"),
+                    case.name
+                );
+                eprintln!("{}", Stream::Stderr.highlight(&e.to_string()));
+            }
+        }
+    }
+
+    eprintln!();
+    eprintln!("{} passed, {} failed", passed, cases.len() - passed);
+    if !steps.is_empty() {
+        eprintln!(
+            "Steps: {} total, {} average, {} max",
+            steps.iter().sum::<usize>(),
+            steps.iter().sum::<usize>() / steps.len(),
+            steps.iter().max().unwrap()
+        );
+    }
+
+    passed == cases.len()
+}
+
+#[cfg(test)]
+mod manifest_tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+        [[case]]
+        name = "multiply"
+        inputs = [4, 3, 4, 1]
+        memory = { 9 = 0 }
+        max_memory = 9
+        expected = [12, 4]
+
+        [[case]]
+        inputs = ["A", -2]
+        expected = ["A"]
+    "#;
+
+    #[test]
+    fn test_parse_toml_manifest() {
+        let manifest =
+            serde_json::to_value(toml::from_str::<toml::Value>(MANIFEST).unwrap()).unwrap();
+        let cases = parse_manifest(&manifest).unwrap();
+
+        assert_eq!(
+            cases,
+            vec![
+                TestCase {
+                    name: "multiply".to_string(),
+                    inputs: vec![4.into(), 3.into(), 4.into(), 1.into()],
+                    memory: HashMap::from([(9, 0.into())]),
+                    max_memory_address: 9,
+                    expected: vec![12.into(), 4.into()],
+                },
+                TestCase {
+                    name: "case 2".to_string(),
+                    inputs: vec!['A'.into(), (-2).into()],
+                    memory: HashMap::new(),
+                    max_memory_address: usize::MAX,
+                    expected: vec!['A'.into()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_json_manifest() {
+        let manifest = serde_json::json!({
+            "case": [{ "name": "mail", "inputs": [1, "B"], "expected": [1, "B"] }]
+        });
+        let cases = parse_manifest(&manifest).unwrap();
+
+        assert_eq!(cases[0].name, "mail");
+        assert_eq!(cases[0].expected, vec![1.into(), 'B'.into()]);
+    }
+
+    #[test]
+    fn test_parse_invalid_manifest() {
+        let manifest = serde_json::json!({ "case": [{ "inputs": [1] }] });
+        assert!(matches!(
+            parse_manifest(&manifest),
+            Err(ReadManifestError::InvalidCase { case: 1, .. })
+        ));
+
+        let manifest = serde_json::json!({ "case": [{ "expected": [true] }] });
+        assert!(parse_manifest(&manifest).is_err());
+
+        let manifest = serde_json::json!({
+            "case": [
+                { "expected": [], "memory": { "9": 0 }, "max_memory": 9 },
+                { "name": "floor", "expected": [], "memory": { "30": 1 }, "max_memory": 3 }
+            ]
+        });
+        match parse_manifest(&manifest) {
+            Err(ReadManifestError::InvalidCase { case: 2, reason }) => {
+                assert!(reason.contains("'floor'") && reason.contains("30"))
+            }
+            other => panic!("expected an invalid case, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_read_script_cases() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+-- TEST: inputs = 1 2 3 ; expected = 6
+    -- TEST: name = letters ; inputs = A -2 ; memory = 9=0 10=B ; max_memory = 10 ; expected = A ;
+    INBOX
+    OUTBOX
+";
+        let cases = read_script_cases(script).unwrap();
+
+        assert_eq!(
+            cases,
+            vec![
+                TestCase {
+                    name: "line 2".to_string(),
+                    inputs: vec![1.into(), 2.into(), 3.into()],
+                    memory: HashMap::new(),
+                    max_memory_address: usize::MAX,
+                    expected: vec![6.into()],
+                },
+                TestCase {
+                    name: "letters".to_string(),
+                    inputs: vec!['A'.into(), (-2).into()],
+                    memory: HashMap::from([(9, 0.into()), (10, 'B'.into())]),
+                    max_memory_address: 10,
+                    expected: vec!['A'.into()],
+                },
+            ]
+        );
+        assert_eq!(
+            read_script_cases("-- TEST: expected =").unwrap()[0].expected,
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_read_invalid_script_cases() {
+        let line_of = |script: &str| match read_script_cases(script) {
+            Err(ReadManifestError::InvalidAnnotation { line, .. }) => line,
+            other => panic!("expected an invalid annotation, got {:?}", other),
+        };
+
+        assert_eq!(line_of("    INBOX\n-- TEST: inputs = 1"), 2);
+        assert_eq!(line_of("-- TEST: inputs 1 ; expected = 1"), 1);
+        assert_eq!(line_of("-- TEST: expected = 1 ; outputs = 1"), 1);
+        assert_eq!(line_of("-- TEST: expected = AB"), 1);
+        assert_eq!(line_of("-- TEST: expected = 1 ; memory = 9"), 1);
+        assert_eq!(
+            line_of("\n-- TEST: expected = ; memory = 30=1 ; max_memory = 3"),
+            2
+        );
+    }
+
+    #[test]
+    fn test_run_case() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            OUTBOX
+            JUMP a
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        let case = TestCase {
+            name: "mail".to_string(),
+            inputs: vec![1.into(), 2.into()],
+            memory: HashMap::new(),
+            max_memory_address: usize::MAX,
+            expected: vec![1.into(), 2.into()],
+        };
+
+        assert!(matches!(
+            run_case(&script, &case, &Settings::default()),
+            CaseResult::Passed { .. }
+        ));
+
+        let case = TestCase {
+            expected: vec![1.into()],
+            ..case
+        };
+        assert!(matches!(
+            run_case(&script, &case, &Settings::default()),
+            CaseResult::Failed { .. }
+        ));
+    }
+}
@@ -0,0 +1,195 @@
+//! The memory files given with `--memory`: the initial values of the tiles of the floor.
+//!
+//! ```text
+//! # The word to reverse, zero-terminated
+//! 0 = G E T 0      # tiles 0 to 3
+//! 10..15 = 0       # tiles 10 to 14
+//! 20..=22 = A      # tiles 20 to 22
+//! 24 = 7
+//! 30 B 31 C        # couples of address and value
+//! ```
+//!
+//! A line assigns a value to a tile (`address = value`), consecutive values to the tiles
+//! starting at an address, or a value to a range of tiles. The lines without `=` are read
+//! as couples of address and value, as in the files written by `--dump-memory`.
+//! Everything after a `#` is a comment. The last value given to a tile is kept.
+
+use std::{collections::HashMap, fs, io, path::Path};
+
+use hrm_interpreter::script_object::value_box::ValueBox;
+
+#[derive(Debug, thiserror::Error)]
+/// Error that can occur when reading a memory file.
+pub enum ReadMemoryFileError {
+    #[error("could not read the memory file:\n\t{0}")]
+    Io(#[from] io::Error),
+    #[error("invalid memory file, line {line}: {reason}")]
+    InvalidLine { line: usize, reason: String },
+}
+
+pub fn read_memory_file(
+    path: impl AsRef<Path>,
+) -> Result<HashMap<usize, ValueBox>, ReadMemoryFileError> {
+    parse_memory_file(&fs::read_to_string(path)?)
+}
+
+pub fn parse_memory_file(content: &str) -> Result<HashMap<usize, ValueBox>, ReadMemoryFileError> {
+    let mut memory = HashMap::new();
+    // The address of a couple whose value is on a following line, with its line
+    let mut pending_address = None;
+
+    for (i, line) in content.lines().enumerate() {
+        let line_number = i + 1;
+        let invalid = |reason: String| ReadMemoryFileError::InvalidLine {
+            line: line_number,
+            reason,
+        };
+        let line = line.split('#').next().unwrap_or_default();
+
+        let Some((addresses, values)) = split_assignment(line) else {
+            for token in line.split_whitespace() {
+                match pending_address.take() {
+                    Some((address, _)) => {
+                        memory.insert(address, parse_value(token).map_err(&invalid)?);
+                    }
+                    None => {
+                        pending_address =
+                            Some((parse_address(token).map_err(&invalid)?, line_number));
+                    }
+                }
+            }
+            continue;
+        };
+        if let Some((address, line)) = pending_address {
+            return Err(no_value(address, line));
+        }
+        let values = values
+            .split_whitespace()
+            .map(|value| parse_value(value).map_err(&invalid))
+            .collect::<Result<Vec<_>, _>>()?;
+        if values.is_empty() {
+            return Err(invalid("no value after '='".to_string()));
+        }
+
+        let addresses = addresses.trim();
+        match parse_range(addresses).map_err(&invalid)? {
+            Some(range) if values.len() == 1 => {
+                memory.extend(range.map(|address| (address, values[0])));
+            }
+            Some(_) => {
+                return Err(invalid(
+                    "a range of tiles is filled with a single value".to_string(),
+                ))
+            }
+            None => {
+                let start = parse_address(addresses).map_err(&invalid)?;
+                memory.extend((start..).zip(values));
+            }
+        }
+    }
+
+    if let Some((address, line)) = pending_address {
+        return Err(no_value(address, line));
+    }
+
+    Ok(memory)
+}
+
+fn no_value(address: usize, line: usize) -> ReadMemoryFileError {
+    ReadMemoryFileError::InvalidLine {
+        line,
+        reason: format!("no value for the address {}", address),
+    }
+}
+
+/// Split the line at the '=' of the assignment (not the one of an inclusive range)
+fn split_assignment(line: &str) -> Option<(&str, &str)> {
+    let mut start = 0;
+    while let Some(i) = line[start..].find('=') {
+        let i = start + i;
+        if !line[..i].ends_with("..") {
+            return Some((&line[..i], &line[i + 1..]));
+        }
+        start = i + 1;
+    }
+    None
+}
+
+fn parse_address(address: &str) -> Result<usize, String> {
+    address
+        .parse::<usize>()
+        .map_err(|_| format!("invalid address: {}", address))
+}
+
+fn parse_value(value: &str) -> Result<ValueBox, String> {
+    value
+        .parse::<ValueBox>()
+        .map_err(|_| format!("invalid value: {}", value))
+}
+
+/// The tiles of `start..end` (without `end`) or `start..=end`, None if it's not a range
+pub fn parse_range(range: &str) -> Result<Option<std::ops::Range<usize>>, String> {
+    let Some((start, end)) = range.split_once("..") else {
+        return Ok(None);
+    };
+    let start = parse_address(start.trim())?;
+    let end = match end.strip_prefix('=') {
+        Some(end) => parse_address(end.trim())? + 1,
+        None => parse_address(end.trim())?,
+    };
+    if end <= start {
+        return Err(format!("empty range of tiles: {}", range));
+    }
+    Ok(Some(start..end))
+}
+
+#[cfg(test)]
+mod memory_file_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_memory_file() {
+        let memory = parse_memory_file(
+            "# The word to reverse
+            0 = G E T 0   # zero-terminated
+            10..12 = 5
+            20..=21 = A
+            10 = -1
+            30 B 31
+            C
+            31 = D
+            ",
+        )
+        .unwrap();
+
+        let expected = [
+            (0, ValueBox::from('G')),
+            (1, ValueBox::from('E')),
+            (2, ValueBox::from('T')),
+            (3, ValueBox::from(0)),
+            (10, ValueBox::from(-1)),
+            (11, ValueBox::from(5)),
+            (20, ValueBox::from('A')),
+            (21, ValueBox::from('A')),
+            (30, ValueBox::from('B')),
+            (31, ValueBox::from('D')),
+        ];
+        assert_eq!(memory, HashMap::from(expected));
+    }
+
+    #[test]
+    fn test_parse_invalid_memory_file() {
+        let line_of = |content| match parse_memory_file(content) {
+            Err(ReadMemoryFileError::InvalidLine { line, .. }) => line,
+            other => panic!("expected an invalid line, got {:?}", other),
+        };
+
+        assert_eq!(line_of("0 = 1\nx = 2"), 2);
+        assert_eq!(line_of("0 =   # nothing"), 1);
+        assert_eq!(line_of("5..2 = 0"), 1);
+        assert_eq!(line_of("0..2 = 1 2"), 1);
+        assert_eq!(line_of("0 1\n2"), 2);
+        assert_eq!(line_of("0 1 2 = 3"), 1);
+        assert_eq!(line_of("0 1\n2\n3 = 4"), 2);
+    }
+}
@@ -0,0 +1,141 @@
+//! Mutation testing: the test cases are run on each mutant of the script
+//! (see [`ScriptObject::mutants`]), and the mutants passing all of them are reported.
+
+use hrm_interpreter::{
+    interpreter::settings::Settings,
+    script_object::{mutate::Mutation, ScriptObject, Span},
+};
+
+use crate::{
+    jobs,
+    manifest::{self, CaseResult, TestCase},
+};
+
+/// A mutant taking this many times the steps of the script on a case is stuck in a loop,
+/// unless a step limit is given
+const STEPS_FACTOR: usize = 10;
+/// The steps a mutant is always allowed, for the cases the script runs in a few steps
+const MIN_STEPS: usize = 1000;
+
+#[derive(Debug)]
+pub struct MutantResult {
+    pub span: Span,
+    pub mutation: Mutation,
+    /// Whether a test case fails on the mutant
+    pub killed: bool,
+}
+
+/// Run the cases on all the mutants of the script, on `jobs` threads.
+/// The script itself must pass all the cases.
+pub fn run(
+    script: &ScriptObject,
+    cases: &[TestCase],
+    settings: &Settings,
+    jobs: usize,
+) -> Result<Vec<MutantResult>, String> {
+    let mut max_steps = 0;
+    for case in cases {
+        match manifest::run_case(script, case, settings) {
+            CaseResult::Passed { steps } => max_steps = max_steps.max(steps),
+            _ => {
+                return Err(format!(
+                    "The script fails the test case {}: fix it before testing its mutants",
+                    case.name
+                ))
+            }
+        }
+    }
+    let settings = Settings {
+        max_steps: settings
+            .max_steps
+            .or(Some((max_steps * STEPS_FACTOR).max(MIN_STEPS))),
+        ..settings.clone()
+    };
+
+    let mutants = script.mutants();
+    Ok(jobs::parallel_map(&mutants, jobs, |mutant| MutantResult {
+        span: mutant.span.clone(),
+        mutation: mutant.mutation.clone(),
+        killed: cases.iter().any(|case| {
+            !matches!(
+                manifest::run_case(&mutant.script, case, &settings),
+                CaseResult::Passed { .. }
+            )
+        }),
+    }))
+}
+
+/// Print the mutants that survived and a summary to stderr.
+/// Returns true if all the mutants were killed.
+pub fn report(results: &[MutantResult]) -> bool {
+    let survivors = results
+        .iter()
+        .filter(|result| !result.killed)
+        .collect::<Vec<_>>();
+    for result in &survivors {
+        eprintln!("SURVIVED  {} ({})", result.span, result.mutation);
+    }
+    if !survivors.is_empty() {
+        eprintln!();
+    }
+    eprintln!(
+        "{} mutants: {} killed, {} survived",
+        results.len(),
+        results.len() - survivors.len(),
+        survivors.len()
+    );
+    survivors.is_empty()
+}
+
+#[cfg(test)]
+mod mutate_tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn case(inputs: &[i32], expected: &[i32]) -> TestCase {
+        TestCase {
+            name: format!("{:?}", inputs),
+            inputs: inputs.iter().map(|&n| n.into()).collect(),
+            memory: HashMap::from([(0, 0.into())]),
+            max_memory_address: usize::MAX,
+            expected: expected.iter().map(|&n| n.into()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_surviving_mutants() {
+        // Outputs the non-zero inputs
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            JUMPZ    a
+            OUTBOX
+            JUMP     a
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+
+        // Without a zero or a negative input, JUMPZ and JUMPN can't be told apart
+        let cases = [case(&[3, 5], &[3, 5])];
+        let results = run(&script, &cases, &Settings::default(), 1).unwrap();
+        let survivors = results
+            .iter()
+            .filter(|result| !result.killed)
+            .map(|result| (result.span.line, result.mutation.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            survivors,
+            [
+                (4, "replaced by JUMPN a".to_string()),
+                (4, "deleted".to_string())
+            ]
+        );
+
+        let cases = [case(&[0, 3, -1], &[3, -1])];
+        let results = run(&script, &cases, &Settings::default(), 2).unwrap();
+        assert!(results.iter().all(|result| result.killed));
+
+        assert!(run(&script, &[case(&[0], &[0])], &Settings::default(), 1).is_err());
+    }
+}
@@ -0,0 +1,477 @@
+use std::{
+    fs::File,
+    io::{self, LineWriter, Write},
+    str::FromStr,
+};
+
+use hrm_interpreter::{
+    diagnostics::Diagnostic,
+    interpreter::{
+        io::OutputSink, memory::Memory, trace::TraceEntry, Interpreter, InterpreterStateInfo,
+    },
+    script_object::value_box::ValueBox,
+};
+use serde_json::{json, Map, Value};
+
+use crate::log;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+/// How the results of the execution are printed to stdout
+pub enum OutputFormat {
+    /// The outputs separated by spaces
+    #[default]
+    Text,
+    /// A JSON object with the outputs, the number of steps and the final memory
+    Json,
+    /// A Graphviz digraph of the blocks and the jumps, in graph mode
+    Dot,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "dot" => Ok(Self::Dot),
+            _ => Err(format!(
+                "Invalid output format: {} (expected text, json or dot)",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+/// How the errors are printed to stderr
+pub enum ErrorFormat {
+    /// The error with an excerpt of the script, and the state of the interpreter
+    #[default]
+    Text,
+    /// A JSON object per error, on one line (see [`error_to_json`])
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "Invalid error format: {} (expected text or json)",
+                s
+            )),
+        }
+    }
+}
+
+/// An error as a JSON object:
+/// `{"kind": "parse" | "validation" | "runtime", "code": ..., "message": ..., "notes": [...], "line": ..., "column": ..., "state": ...}`.
+/// The code doesn't change with the wording of the message.
+/// The line and the column (starting at 1) are null if the diagnostic has no line (0),
+/// the state of the interpreter is only given for runtime errors.
+pub fn error_to_json(
+    kind: &str,
+    code: &str,
+    diagnostic: &Diagnostic,
+    source: &str,
+    state: Option<&InterpreterStateInfo>,
+) -> Value {
+    let located = diagnostic.line > 0;
+    json!({
+        "kind": kind,
+        "code": code,
+        "message": diagnostic.message,
+        "notes": diagnostic.notes,
+        "line": located.then_some(diagnostic.line),
+        "column": located.then(|| diagnostic.column(source)),
+        "state": state,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+/// How the trace of the execution is written
+pub enum TraceFormat {
+    /// A human-readable line per step
+    #[default]
+    Text,
+    /// A JSON object per step (JSON Lines)
+    Jsonl,
+}
+
+impl FromStr for TraceFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "jsonl" => Ok(Self::Jsonl),
+            _ => Err(format!(
+                "Invalid trace format: {} (expected text or jsonl)",
+                s
+            )),
+        }
+    }
+}
+
+/// Numbers are JSON numbers, characters are JSON strings
+fn value_box_to_json(value: &ValueBox) -> Value {
+    match value {
+        ValueBox::Number(n) => json!(n),
+        ValueBox::Character(c) => json!(c.to_string()),
+    }
+}
+
+fn results_to_json(outputs: &[ValueBox], interpreter: &Interpreter) -> Value {
+    let memory = interpreter.memory();
+    let final_memory = memory
+        .addresses()
+        .map(|address| {
+            (
+                address.to_string(),
+                value_box_to_json(memory.get(&address).unwrap()),
+            )
+        })
+        .collect::<Map<String, Value>>();
+
+    json!({
+        "outputs": outputs.iter().map(value_box_to_json).collect::<Vec<Value>>(),
+        "steps": interpreter.steps(),
+        "final_memory": final_memory,
+    })
+}
+
+/// An empty head or tile is null
+fn optional_value_box_to_json(value: Option<ValueBox>) -> Value {
+    value.as_ref().map_or(Value::Null, value_box_to_json)
+}
+
+fn trace_entry_to_json(entry: &TraceEntry) -> Value {
+    json!({
+        "step": entry.step,
+        "line": entry.line,
+        "block": entry.block,
+        "instruction": entry.instruction,
+        "head_before": optional_value_box_to_json(entry.head_before),
+        "head_after": optional_value_box_to_json(entry.head_after),
+        "tile": entry.tile.map(|tile| json!({
+            "address": tile.address,
+            "before": optional_value_box_to_json(tile.before),
+            "after": optional_value_box_to_json(tile.after),
+        })),
+    })
+}
+
+/// Write a step of the trace, in the given format
+pub fn write_trace_entry(
+    format: TraceFormat,
+    writer: &mut dyn Write,
+    entry: &TraceEntry,
+) -> io::Result<()> {
+    match format {
+        TraceFormat::Text => writeln!(writer, "{}", entry),
+        TraceFormat::Jsonl => writeln!(writer, "{}", trace_entry_to_json(entry)),
+    }
+}
+
+/// The outputs as words, for the string levels: the characters before each 0 are joined
+/// (the 0 itself isn't kept), and the other numbers are words on their own
+pub fn outputs_as_strings(outputs: &[ValueBox]) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    for value in outputs {
+        match value {
+            ValueBox::Character(c) => word.push(*c),
+            ValueBox::Number(0) => words.push(std::mem::take(&mut word)),
+            ValueBox::Number(n) => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                words.push(n.to_string());
+            }
+        }
+    }
+    // The last word may not be terminated
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Where and how the results of the execution are written
+pub struct OutputOptions {
+    pub format: OutputFormat,
+    /// In text, print the outputs as strings (see [`outputs_as_strings`])
+    pub as_strings: bool,
+    /// What separates the outputs in text
+    pub separator: String,
+    /// Whether a newline ends the outputs in text
+    pub newline: bool,
+    /// The file the results are written to, None for stdout
+    pub file: Option<String>,
+}
+
+impl OutputOptions {
+    /// Where the results are written: the file, or stdout
+    pub fn writer(&self) -> Box<dyn Write> {
+        match &self.file {
+            Some(file) => Box::new(File::create(file).unwrap_or_else(|e| {
+                log::error(format!("Could not create output file {}: {}", file, e));
+                std::process::exit(1);
+            })),
+            None => Box::new(io::stdout()),
+        }
+    }
+}
+
+/// Stop if the results can't be written
+fn written(result: io::Result<()>) {
+    if let Err(e) = result {
+        log::error(format!("Could not write the outputs: {}", e));
+        std::process::exit(1);
+    }
+}
+
+/// Print the results of the execution, in the given format.
+/// In text, the outputs can be printed as strings, a word per line (see [`outputs_as_strings`]).
+pub fn print_results(options: &OutputOptions, outputs: &[ValueBox], interpreter: &Interpreter) {
+    let mut writer = options.writer();
+    match options.format {
+        OutputFormat::Text if options.as_strings => {
+            let newline = if options.newline { "\n" } else { "" };
+            written(write!(
+                writer,
+                "{}{}",
+                outputs_as_strings(outputs).join("\n"),
+                newline
+            ));
+        }
+        OutputFormat::Text => {
+            let mut text = WrittenOutputs::new(writer, &options.separator);
+            for value in outputs {
+                text.output(*value);
+            }
+            text.finish(options.newline);
+        }
+        OutputFormat::Json => written(writeln!(
+            writer,
+            "{}",
+            results_to_json(outputs, interpreter)
+        )),
+        OutputFormat::Dot => unreachable!("the dot format is only for the graph mode"),
+    }
+}
+
+/// Outputs written as soon as they're produced, with a separator between them
+pub struct WrittenOutputs {
+    writer: Box<dyn Write>,
+    separator: String,
+    outputs: Vec<ValueBox>,
+}
+
+impl WrittenOutputs {
+    pub fn new(writer: Box<dyn Write>, separator: &str) -> Self {
+        Self {
+            writer,
+            separator: separator.to_string(),
+            outputs: vec![],
+        }
+    }
+
+    /// Write the final newline if asked, and give back the outputs
+    pub fn finish(mut self, newline: bool) -> Vec<ValueBox> {
+        if newline {
+            written(writeln!(self.writer));
+        }
+        written(self.writer.flush());
+        self.outputs
+    }
+}
+
+impl OutputSink for WrittenOutputs {
+    fn output(&mut self, value: ValueBox) {
+        let separator = if self.outputs.is_empty() {
+            ""
+        } else {
+            &self.separator
+        };
+        written(write!(self.writer, "{}{}", separator, value));
+        written(self.writer.flush());
+        self.outputs.push(value);
+    }
+
+    fn outputs(&self) -> Option<&[ValueBox]> {
+        Some(&self.outputs)
+    }
+}
+
+/// The tiles holding a value, a line per tile ("address value") sorted by address:
+/// the format of the memory files read by `--memory`
+pub fn memory_to_text(memory: &Memory) -> String {
+    let mut addresses = memory.addresses().collect::<Vec<_>>();
+    addresses.sort_unstable();
+    addresses
+        .into_iter()
+        .map(|address| format!("{} {}\n", address, memory.get(&address).unwrap()))
+        .collect()
+}
+
+/// Where the trace is written: the given file, or stderr.
+/// Lines are flushed one by one, so that the trace is complete even if the execution fails.
+pub fn open_trace(file: Option<&str>) -> Box<dyn Write + Send> {
+    match file {
+        Some(file) => Box::new(LineWriter::new(File::create(file).unwrap_or_else(|e| {
+            log::error(format!("Could not create trace file {}: {}", file, e));
+            std::process::exit(1);
+        }))),
+        None => Box::new(io::stderr()),
+    }
+}
+
+#[cfg(test)]
+mod output_tests {
+    use std::collections::HashMap;
+
+    use hrm_interpreter::{interpreter::trace::TileAccess, script_object::ScriptObject};
+
+    use super::*;
+
+    #[test]
+    fn test_output_format_from_str() {
+        assert_eq!("text".parse::<OutputFormat>(), Ok(OutputFormat::Text));
+        assert_eq!("json".parse::<OutputFormat>(), Ok(OutputFormat::Json));
+        assert_eq!("dot".parse::<OutputFormat>(), Ok(OutputFormat::Dot));
+        assert!("xml".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn test_outputs_as_strings() {
+        let outputs = "H I 0 0 Y O U 0 5 A B -1 C"
+            .split(' ')
+            .map(|value| value.parse::<ValueBox>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outputs_as_strings(&outputs),
+            ["HI", "", "YOU", "5", "AB", "-1", "C"]
+        );
+        assert!(outputs_as_strings(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_results_to_json() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            COPYTO   1
+            OUTBOX
+            COPYFROM 0
+            OUTBOX
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        let memory = Memory::with_data(HashMap::from([(0, ValueBox::from('A'))]), 4);
+        let mut interpreter = Interpreter::new(memory);
+        let (outputs, _) = interpreter.execute(&script, &[ValueBox::from(3)]).unwrap();
+
+        assert_eq!(
+            results_to_json(&outputs, &interpreter),
+            json!({
+                "outputs": [3, "A"],
+                "steps": 5,
+                "final_memory": { "0": "A", "1": 3 },
+            })
+        );
+    }
+
+    #[test]
+    fn test_trace_entry_to_json() {
+        let entry = TraceEntry {
+            step: 3,
+            position: Default::default(),
+            line: 6,
+            block: "a".to_string(),
+            instruction: "COPYTO 0".to_string(),
+            head_before: Some(ValueBox::from(2)),
+            head_after: Some(ValueBox::from(2)),
+            tile: Some(TileAccess {
+                address: 0,
+                before: None,
+                after: Some(ValueBox::from(2)),
+            }),
+        };
+
+        let mut line = vec![];
+        write_trace_entry(TraceFormat::Jsonl, &mut line, &entry).unwrap();
+        let line = String::from_utf8(line).unwrap();
+
+        assert!(line.ends_with('\n'));
+        assert_eq!(
+            serde_json::from_str::<Value>(&line).unwrap(),
+            json!({
+                "step": 3,
+                "line": 6,
+                "block": "a",
+                "instruction": "COPYTO 0",
+                "head_before": 2,
+                "head_after": 2,
+                "tile": { "address": 0, "before": null, "after": 2 },
+            })
+        );
+    }
+
+    #[test]
+    fn test_error_to_json() {
+        assert_eq!("json".parse::<ErrorFormat>(), Ok(ErrorFormat::Json));
+        assert!("jsonl".parse::<ErrorFormat>().is_err());
+
+        let source = "-- HUMAN RESOURCE MACHINE PROGRAM --
+    INBOX
+    COPYTOO 2
+";
+        let diagnostic = Diagnostic::new("COPYTOO 2 is not a valid instruction", 3, None);
+        assert_eq!(
+            error_to_json("parse", "unknown-instruction", &diagnostic, source, None),
+            json!({
+                "kind": "parse",
+                "code": "unknown-instruction",
+                "message": "COPYTOO 2 is not a valid instruction",
+                "notes": [],
+                "line": 3,
+                "column": 5,
+                "state": null,
+            })
+        );
+
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+    OUTBOX
+"
+        .parse::<ScriptObject>()
+        .unwrap();
+        let mut interpreter = Interpreter::new(Memory::default());
+        let e = interpreter
+            .execute(&script, &[ValueBox::from(1)])
+            .unwrap_err();
+        let diagnostic = Diagnostic::new(e.message(), 0, None);
+        let error = error_to_json("runtime", e.code(), &diagnostic, source, Some(e.state()));
+        assert_eq!(error["code"], "output-empty-head");
+        assert_eq!(error["line"], Value::Null);
+        assert_eq!(error["column"], Value::Null);
+        assert_eq!(
+            error["state"],
+            json!({ "head": null, "inputs_left": [1], "outputs": [], "memory": {} })
+        );
+    }
+
+    #[test]
+    fn test_memory_to_text() {
+        let memory = Memory::with_data(
+            HashMap::from([(10, ValueBox::from(-3)), (2, ValueBox::from('B'))]),
+            usize::MAX,
+        );
+        assert_eq!(memory_to_text(&memory), "2 B\n10 -3\n");
+        assert_eq!(memory_to_text(&Memory::default()), "");
+    }
+}
@@ -0,0 +1,148 @@
+//! Pipelines of scripts: the outbox of each script is the inbox of the next one,
+//! like Unix filters, and each stage has its own floor.
+
+use std::{collections::HashMap, fmt};
+
+use hrm_interpreter::{
+    interpreter::{memory::Memory, settings::Settings, ExecuteScriptError, Interpreter},
+    script_object::{value_box::ValueBox, ScriptObject},
+};
+
+/// A script of the pipeline, with its floor
+pub struct Stage {
+    pub path: String,
+    pub script: ScriptObject,
+    pub memory: HashMap<usize, ValueBox>,
+    pub max_memory_address: usize,
+}
+
+/// The execution of a stage
+pub struct StageRun {
+    pub outputs: Vec<ValueBox>,
+    pub interpreter: Interpreter,
+}
+
+#[derive(Debug)]
+/// The error of the stage on which the pipeline stopped
+pub struct PipeError {
+    /// The number of the stage, from 1
+    pub stage: usize,
+    pub path: String,
+    pub error: ExecuteScriptError,
+}
+
+impl fmt::Display for PipeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Stage {} ({}) failed:\n{}",
+            self.stage, self.path, self.error
+        )
+    }
+}
+
+/// Run the stages one after the other, the first one on the inputs
+pub fn run(
+    stages: &[Stage],
+    inputs: &[ValueBox],
+    settings: &Settings,
+) -> Result<Vec<StageRun>, PipeError> {
+    let mut runs: Vec<StageRun> = Vec::with_capacity(stages.len());
+    for (i, stage) in stages.iter().enumerate() {
+        let inbox = runs.last().map_or(inputs, |run| &run.outputs);
+        let memory = Memory::with_data(stage.memory.clone(), stage.max_memory_address);
+        let mut interpreter = Interpreter::with_settings(memory, settings.clone());
+        let (outputs, _) =
+            interpreter
+                .execute(&stage.script, inbox)
+                .map_err(|error| PipeError {
+                    stage: i + 1,
+                    path: stage.path.clone(),
+                    error,
+                })?;
+        runs.push(StageRun {
+            outputs,
+            interpreter,
+        });
+    }
+    Ok(runs)
+}
+
+#[cfg(test)]
+mod pipe_tests {
+    use super::*;
+
+    fn stage(script: &str, memory: &[(usize, i32)], max_memory_address: usize) -> Stage {
+        Stage {
+            path: "stage.hrm".to_string(),
+            script: script.parse().unwrap(),
+            memory: memory
+                .iter()
+                .map(|&(address, n)| (address, n.into()))
+                .collect(),
+            max_memory_address,
+        }
+    }
+
+    #[test]
+    fn test_pipeline() {
+        // Doubles the inputs, then adds the number on tile 0 to them
+        let double = stage(
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+            a:
+                INBOX
+                COPYTO   0
+                ADD      0
+                OUTBOX
+                JUMP     a
+            ",
+            &[],
+            0,
+        );
+        let add = stage(
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+            a:
+                INBOX
+                ADD      0
+                OUTBOX
+                JUMP     a
+            ",
+            &[(0, 10)],
+            0,
+        );
+        let inputs = [1, 2, 3].map(ValueBox::from);
+
+        let runs = run(&[double, add], &inputs, &Settings::default()).unwrap();
+        assert_eq!(runs[0].outputs, [2, 4, 6].map(ValueBox::from));
+        assert_eq!(runs[1].outputs, [12, 14, 16].map(ValueBox::from));
+    }
+
+    #[test]
+    fn test_failing_stage() {
+        let copy = stage(
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+            a:
+                INBOX
+                OUTBOX
+                JUMP     a
+            ",
+            &[],
+            0,
+        );
+        // The floor of this stage is empty
+        let add = stage(
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+                INBOX
+                ADD      0
+                OUTBOX
+            ",
+            &[],
+            0,
+        );
+
+        let error = run(&[copy, add], &[ValueBox::from(1)], &Settings::default())
+            .err()
+            .unwrap();
+        assert_eq!(error.stage, 2);
+    }
+}
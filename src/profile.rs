@@ -0,0 +1,166 @@
+use std::cmp::Reverse;
+
+use hrm_interpreter::{
+    interpreter::stats::ExecutionStats,
+    script_object::{Position, ScriptObject},
+};
+
+#[derive(Debug, PartialEq)]
+/// The time spent in a block
+pub struct BlockProfile {
+    pub label: String,
+    pub line: usize,
+    /// Number of times the execution entered the block (by a jump or by falling through)
+    pub entries: usize,
+    /// Number of steps spent executing the instructions of the block
+    pub steps: usize,
+}
+
+#[derive(Debug, PartialEq)]
+/// The number of times an instruction was executed
+pub struct InstructionProfile {
+    pub line: usize,
+    pub block: String,
+    pub text: String,
+    pub count: usize,
+}
+
+#[derive(Debug, PartialEq)]
+/// Where the execution spent its steps, hottest first.
+/// Only what was executed is listed.
+pub struct Profile {
+    pub steps: usize,
+    /// Sorted by cumulative steps
+    pub blocks: Vec<BlockProfile>,
+    /// Sorted by execution count
+    pub instructions: Vec<InstructionProfile>,
+}
+
+/// Compute the profile of the script from the statistics of its execution
+pub fn profile(script: &ScriptObject, stats: &ExecutionStats) -> Profile {
+    let mut blocks = vec![];
+    let mut instructions = vec![];
+
+    for block in script.blocks() {
+        let counts = (0..block.instructions.len())
+            .map(|instruction| {
+                stats.executions_of(Position {
+                    block: block.index(),
+                    instruction,
+                })
+            })
+            .collect::<Vec<_>>();
+        let steps = counts.iter().sum();
+        if steps == 0 {
+            continue;
+        }
+
+        blocks.push(BlockProfile {
+            label: block.name().to_string(),
+            line: block.line(),
+            entries: counts[0],
+            steps,
+        });
+        for (instruction, count) in counts.into_iter().enumerate() {
+            let span = block.span_of(instruction).unwrap();
+            if count > 0 {
+                instructions.push(InstructionProfile {
+                    line: span.line,
+                    block: block.name().to_string(),
+                    text: span.text.clone(),
+                    count,
+                });
+            }
+        }
+    }
+
+    // The sorts are stable: equal entries stay in the order of the script
+    blocks.sort_by_key(|block| Reverse(block.steps));
+    instructions.sort_by_key(|instruction| Reverse(instruction.count));
+    Profile {
+        steps: stats.steps,
+        blocks,
+        instructions,
+    }
+}
+
+/// Print the profile to stderr, as two tables
+pub fn report(profile: &Profile) {
+    let percent = |steps: usize| 100.0 * steps as f64 / profile.steps.max(1) as f64;
+
+    eprintln!("Blocks:");
+    eprintln!(
+        "  {: <10} {: >6} {: >10} {: >10} {: >7}",
+        "Block", "Line", "Entries", "Steps", "%"
+    );
+    for block in &profile.blocks {
+        eprintln!(
+            "  {: <10} {: >6} {: >10} {: >10} {: >6.1}%",
+            block.label,
+            block.line,
+            block.entries,
+            block.steps,
+            percent(block.steps)
+        );
+    }
+
+    eprintln!("Instructions:");
+    eprintln!(
+        "  {: >6} {: <10} {: <16} {: >10} {: >7}",
+        "Line", "Block", "Instruction", "Count", "%"
+    );
+    for instruction in &profile.instructions {
+        eprintln!(
+            "  {: >6} {: <10} {: <16} {: >10} {: >6.1}%",
+            instruction.line,
+            instruction.block,
+            instruction.text,
+            instruction.count,
+            percent(instruction.count)
+        );
+    }
+}
+
+#[cfg(test)]
+mod profile_tests {
+    use hrm_interpreter::{
+        interpreter::{memory::Memory, Interpreter},
+        script_object::value_box::ValueBox,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_profile() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+        a:
+            OUTBOX
+        b:
+            INBOX
+            JUMPZ    b
+            JUMP     a
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        let inputs = [1, 2, 0, 0, 3].map(ValueBox::from);
+        let (_, stats) = Interpreter::new(Memory::default())
+            .execute(&script, &inputs)
+            .unwrap();
+        let profile = profile(&script, &stats);
+
+        assert_eq!(profile.steps, 15);
+        let blocks = profile
+            .blocks
+            .iter()
+            .map(|block| (block.label.as_str(), block.entries, block.steps))
+            .collect::<Vec<_>>();
+        assert_eq!(blocks, vec![("b", 5, 11), ("a", 3, 3), ("entry", 1, 1)]);
+        let instructions = profile
+            .instructions
+            .iter()
+            .map(|instruction| (instruction.line, instruction.count))
+            .collect::<Vec<_>>();
+        assert_eq!(instructions, vec![(6, 5), (7, 4), (4, 3), (8, 2), (2, 1)]);
+    }
+}
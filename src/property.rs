@@ -0,0 +1,418 @@
+//! The properties checked by solve-for: comparisons of the values and lengths of the inbox
+//! and the outbox, like `len(out) == 1 and out[0] == in[0] + in[1]`.
+
+use std::{fmt::Display, str::FromStr};
+
+use hrm_interpreter::script_object::value_box::ValueBox;
+
+#[derive(Debug, Clone, PartialEq)]
+/// A number computed from the inbox and the outbox
+pub enum Term {
+    Number(i64),
+    /// `in[i]`
+    Input(usize),
+    /// `out[i]`
+    Output(usize),
+    /// `len(in)`
+    InputLen,
+    /// `len(out)`
+    OutputLen,
+    Add(Box<Term>, Box<Term>),
+    Sub(Box<Term>, Box<Term>),
+    Mul(Box<Term>, Box<Term>),
+    Neg(Box<Term>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A property of an execution. A comparison using a value that isn't in the inbox or the outbox,
+/// or a letter, is false.
+pub enum Property {
+    Compare(Term, Comparison, Term),
+    Not(Box<Property>),
+    And(Box<Property>, Box<Property>),
+    Or(Box<Property>, Box<Property>),
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum ParsePropertyError {
+    #[error("unexpected '{0}'")]
+    Unexpected(String),
+    #[error("unexpected end of the property")]
+    UnexpectedEnd,
+    #[error("invalid number {0}")]
+    InvalidNumber(String),
+}
+
+impl Term {
+    /// The value of the term, None if it uses a value that isn't in the inbox or the outbox, or a letter
+    pub fn value(&self, inputs: &[ValueBox], outputs: &[ValueBox]) -> Option<i64> {
+        let number = |value: Option<&ValueBox>| match value? {
+            ValueBox::Number(n) => Some(i64::from(*n)),
+            ValueBox::Character(_) => None,
+        };
+        match self {
+            Term::Number(n) => Some(*n),
+            Term::Input(i) => number(inputs.get(*i)),
+            Term::Output(i) => number(outputs.get(*i)),
+            Term::InputLen => Some(inputs.len() as i64),
+            Term::OutputLen => Some(outputs.len() as i64),
+            Term::Add(a, b) => a
+                .value(inputs, outputs)?
+                .checked_add(b.value(inputs, outputs)?),
+            Term::Sub(a, b) => a
+                .value(inputs, outputs)?
+                .checked_sub(b.value(inputs, outputs)?),
+            Term::Mul(a, b) => a
+                .value(inputs, outputs)?
+                .checked_mul(b.value(inputs, outputs)?),
+            Term::Neg(a) => a.value(inputs, outputs)?.checked_neg(),
+        }
+    }
+}
+
+impl Comparison {
+    pub fn holds(self, a: i64, b: i64) -> bool {
+        match self {
+            Comparison::Equal => a == b,
+            Comparison::NotEqual => a != b,
+            Comparison::Less => a < b,
+            Comparison::LessOrEqual => a <= b,
+            Comparison::Greater => a > b,
+            Comparison::GreaterOrEqual => a >= b,
+        }
+    }
+}
+
+impl Property {
+    /// Whether the property holds for an execution reading `inputs` and giving `outputs`
+    pub fn holds(&self, inputs: &[ValueBox], outputs: &[ValueBox]) -> bool {
+        match self {
+            Property::Compare(a, comparison, b) => {
+                match (a.value(inputs, outputs), b.value(inputs, outputs)) {
+                    (Some(a), Some(b)) => comparison.holds(a, b),
+                    _ => false,
+                }
+            }
+            Property::Not(property) => !property.holds(inputs, outputs),
+            Property::And(a, b) => a.holds(inputs, outputs) && b.holds(inputs, outputs),
+            Property::Or(a, b) => a.holds(inputs, outputs) || b.holds(inputs, outputs),
+        }
+    }
+}
+
+impl Display for Term {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Term::Number(n) => write!(f, "{}", n),
+            Term::Input(i) => write!(f, "in[{}]", i),
+            Term::Output(i) => write!(f, "out[{}]", i),
+            Term::InputLen => write!(f, "len(in)"),
+            Term::OutputLen => write!(f, "len(out)"),
+            Term::Add(a, b) => write!(f, "{} + {}", a, b),
+            Term::Sub(a, b) => match **b {
+                Term::Add(..) | Term::Sub(..) => write!(f, "{} - ({})", a, b),
+                _ => write!(f, "{} - {}", a, b),
+            },
+            Term::Mul(a, b) => {
+                let factor = |term: &Term| match term {
+                    Term::Add(..) | Term::Sub(..) => format!("({})", term),
+                    _ => term.to_string(),
+                };
+                write!(f, "{} * {}", factor(a), factor(b))
+            }
+            Term::Neg(a) => match **a {
+                Term::Add(..) | Term::Sub(..) | Term::Mul(..) => write!(f, "-({})", a),
+                _ => write!(f, "-{}", a),
+            },
+        }
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let operator = match self {
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+        };
+        write!(f, "{}", operator)
+    }
+}
+
+impl Display for Property {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let group = |property: &Property| match property {
+            Property::And(..) | Property::Or(..) => format!("({})", property),
+            _ => property.to_string(),
+        };
+        match self {
+            Property::Compare(a, comparison, b) => write!(f, "{} {} {}", a, comparison, b),
+            Property::Not(property) => write!(f, "not {}", group(property)),
+            Property::And(a, b) => write!(f, "{} and {}", group(a), group(b)),
+            Property::Or(a, b) => write!(f, "{} or {}", group(a), group(b)),
+        }
+    }
+}
+
+/// Split the property into numbers, words and symbols
+fn tokens(s: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let mut token = c.to_string();
+        if c.is_ascii_alphanumeric() {
+            while let Some(next) = chars.next_if(char::is_ascii_alphanumeric) {
+                token.push(next);
+            }
+        } else if matches!(c, '=' | '!' | '<' | '>') {
+            if let Some(next) = chars.next_if_eq(&'=') {
+                token.push(next);
+            }
+        }
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// A recursive descent parser, from the loosest operator (`or`) to the terms
+struct Parser {
+    tokens: Vec<String>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.next).map(String::as_str)
+    }
+
+    fn take(&mut self) -> Result<&str, ParsePropertyError> {
+        let token = self
+            .tokens
+            .get(self.next)
+            .ok_or(ParsePropertyError::UnexpectedEnd)?;
+        self.next += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), ParsePropertyError> {
+        match self.take()? {
+            token if token == expected => Ok(()),
+            token => Err(ParsePropertyError::Unexpected(token.to_string())),
+        }
+    }
+
+    fn or(&mut self) -> Result<Property, ParsePropertyError> {
+        let mut property = self.and()?;
+        while self.peek() == Some("or") {
+            self.next += 1;
+            property = Property::Or(Box::new(property), Box::new(self.and()?));
+        }
+        Ok(property)
+    }
+
+    fn and(&mut self) -> Result<Property, ParsePropertyError> {
+        let mut property = self.not()?;
+        while self.peek() == Some("and") {
+            self.next += 1;
+            property = Property::And(Box::new(property), Box::new(self.not()?));
+        }
+        Ok(property)
+    }
+
+    fn not(&mut self) -> Result<Property, ParsePropertyError> {
+        match self.peek() {
+            Some("not") => {
+                self.next += 1;
+                Ok(Property::Not(Box::new(self.not()?)))
+            }
+            // The parentheses group properties, the terms don't need them
+            Some("(") => {
+                self.next += 1;
+                let property = self.or()?;
+                self.expect(")")?;
+                Ok(property)
+            }
+            _ => self.comparison(),
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Property, ParsePropertyError> {
+        let a = self.sum()?;
+        let comparison = match self.take()? {
+            "==" => Comparison::Equal,
+            "!=" => Comparison::NotEqual,
+            "<" => Comparison::Less,
+            "<=" => Comparison::LessOrEqual,
+            ">" => Comparison::Greater,
+            ">=" => Comparison::GreaterOrEqual,
+            token => return Err(ParsePropertyError::Unexpected(token.to_string())),
+        };
+        Ok(Property::Compare(a, comparison, self.sum()?))
+    }
+
+    fn sum(&mut self) -> Result<Term, ParsePropertyError> {
+        let mut term = self.product()?;
+        loop {
+            term = match self.peek() {
+                Some("+") => {
+                    self.next += 1;
+                    Term::Add(Box::new(term), Box::new(self.product()?))
+                }
+                Some("-") => {
+                    self.next += 1;
+                    Term::Sub(Box::new(term), Box::new(self.product()?))
+                }
+                _ => return Ok(term),
+            };
+        }
+    }
+
+    fn product(&mut self) -> Result<Term, ParsePropertyError> {
+        let mut term = self.factor()?;
+        while self.peek() == Some("*") {
+            self.next += 1;
+            term = Term::Mul(Box::new(term), Box::new(self.factor()?));
+        }
+        Ok(term)
+    }
+
+    fn factor(&mut self) -> Result<Term, ParsePropertyError> {
+        let token = self.take()?.to_string();
+        match token.as_str() {
+            "-" => Ok(Term::Neg(Box::new(self.factor()?))),
+            "in" | "out" => {
+                self.expect("[")?;
+                let index = self.take()?;
+                let index = index
+                    .parse::<usize>()
+                    .map_err(|_| ParsePropertyError::InvalidNumber(index.to_string()))?;
+                self.expect("]")?;
+                if token == "out" {
+                    Ok(Term::Output(index))
+                } else {
+                    Ok(Term::Input(index))
+                }
+            }
+            "len" => {
+                self.expect("(")?;
+                let term = match self.take()? {
+                    "in" => Term::InputLen,
+                    "out" => Term::OutputLen,
+                    token => return Err(ParsePropertyError::Unexpected(token.to_string())),
+                };
+                self.expect(")")?;
+                Ok(term)
+            }
+            number if number.starts_with(|c: char| c.is_ascii_digit()) => number
+                .parse::<i64>()
+                .map(Term::Number)
+                .map_err(|_| ParsePropertyError::InvalidNumber(token.clone())),
+            _ => Err(ParsePropertyError::Unexpected(token)),
+        }
+    }
+}
+
+impl FromStr for Property {
+    type Err = ParsePropertyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokens(s),
+            next: 0,
+        };
+        let property = parser.or()?;
+        match parser.peek() {
+            Some(token) => Err(ParsePropertyError::Unexpected(token.to_string())),
+            None => Ok(property),
+        }
+    }
+}
+
+#[cfg(test)]
+mod property_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_property() {
+        let property = "len(out) == 1 and out[0] == in[0] + 2 * in[1]"
+            .parse::<Property>()
+            .unwrap();
+        assert_eq!(
+            property,
+            Property::And(
+                Box::new(Property::Compare(
+                    Term::OutputLen,
+                    Comparison::Equal,
+                    Term::Number(1)
+                )),
+                Box::new(Property::Compare(
+                    Term::Output(0),
+                    Comparison::Equal,
+                    Term::Add(
+                        Box::new(Term::Input(0)),
+                        Box::new(Term::Mul(
+                            Box::new(Term::Number(2)),
+                            Box::new(Term::Input(1))
+                        ))
+                    )
+                ))
+            )
+        );
+        assert_eq!(
+            property.to_string(),
+            "len(out) == 1 and out[0] == in[0] + 2 * in[1]"
+        );
+
+        let property = "not (out[0] < 0 or out[1]>=-in[0])"
+            .parse::<Property>()
+            .unwrap();
+        assert_eq!(property.to_string(), "not (out[0] < 0 or out[1] >= -in[0])");
+
+        assert_eq!(
+            "out[0] =< 1".parse::<Property>(),
+            Err(ParsePropertyError::Unexpected("=".to_string()))
+        );
+        assert_eq!(
+            "out[0] ==".parse::<Property>(),
+            Err(ParsePropertyError::UnexpectedEnd)
+        );
+        assert_eq!(
+            "out[x] == 1".parse::<Property>(),
+            Err(ParsePropertyError::InvalidNumber("x".to_string()))
+        );
+        assert_eq!(
+            "out[0] == 1 1".parse::<Property>(),
+            Err(ParsePropertyError::Unexpected("1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_property_holds() {
+        let property = "out[0] == in[0] - in[1] or len(out) > 1"
+            .parse::<Property>()
+            .unwrap();
+        let inputs = [ValueBox::from(5), ValueBox::from(2)];
+        assert!(property.holds(&inputs, &[ValueBox::from(3)]));
+        assert!(!property.holds(&inputs, &[ValueBox::from(7)]));
+        assert!(property.holds(&inputs, &[ValueBox::from(7), ValueBox::from(0)]));
+        // Missing values and letters make the comparisons false
+        assert!(!property.holds(&inputs, &[]));
+        assert!(!property.holds(&inputs, &[ValueBox::from('A')]));
+    }
+}
@@ -0,0 +1,81 @@
+use std::io::{self, BufRead, Write};
+
+use hrm_interpreter::{
+    interpreter::{memory::Memory, settings::Settings, Interpreter},
+    script_object::{instruction::ParseOptions, value_box::ValueBox, Position, ScriptObject},
+};
+
+fn print_help() {
+    eprintln!("Type an instruction to execute it (INBOX, COPYTO 0...), or a command:");
+    eprintln!("  p, print  print the state of the worker");
+    eprintln!("  q, quit   exit");
+    eprintln!("  h, help   print this help");
+}
+
+/// Execute the line as a script of its own, from the state of the interpreter
+fn execute(
+    interpreter: &mut Interpreter,
+    line: &str,
+    inputs: &[ValueBox],
+    outputs: &mut Vec<ValueBox>,
+    options: &ParseOptions,
+) -> Result<(), String> {
+    let script = ScriptObject::parse_with_options(line, options).map_err(|e| e.to_string())?;
+    script.validate().map_err(|e| e.to_string())?;
+
+    let mut snapshot = interpreter.snapshot();
+    snapshot.position = Position::default();
+    let mut next = Interpreter::from_snapshot(snapshot);
+    next.execute_into(&script, inputs, &mut *outputs)
+        .map_err(|e| e.message())?;
+    *interpreter = next;
+    Ok(())
+}
+
+/// Execute the instructions typed at the prompt one by one. The head, the floor and the inbox
+/// are kept from one instruction to the next, until the end of stdin.
+pub fn run(memory: Memory, settings: Settings, inputs: &[ValueBox], options: &ParseOptions) {
+    let mut interpreter = Interpreter::with_settings(memory, settings);
+    let mut outputs = vec![];
+    let stdin = io::stdin();
+    print_help();
+
+    loop {
+        eprint!("hrm> ");
+        io::stderr().flush().unwrap();
+
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+
+        match line.trim() {
+            "" => {}
+            "q" | "quit" => return,
+            "h" | "help" => print_help(),
+            "p" | "print" => {
+                match interpreter.head() {
+                    Some(head) => eprintln!("Head: {}", head),
+                    None => eprintln!("Head: empty"),
+                }
+                eprintln!("{}", interpreter.state(inputs, &outputs));
+            }
+            line => {
+                let produced = outputs.len();
+                match execute(&mut interpreter, line, inputs, &mut outputs, options) {
+                    Ok(()) => {
+                        for value in &outputs[produced..] {
+                            eprintln!("Output: {}", value);
+                        }
+                        match interpreter.head() {
+                            Some(head) => eprintln!("Head: {}", head),
+                            None => eprintln!("Head: empty"),
+                        }
+                    }
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use std::{collections::HashMap, fmt::Write as _, fs, io};
+
+use hrm_interpreter::{
+    interpreter::{geometry::FloorGeometry, memory::Memory, stats::ExecutionStats},
+    script_object::{value_box::ValueBox, Position, ScriptObject},
+};
+
+use crate::score::Score;
+
+/// Everything shown in the report of a run
+pub struct Report<'a> {
+    pub script_path: &'a str,
+    pub source: &'a str,
+    pub script: &'a ScriptObject,
+    pub stats: &'a ExecutionStats,
+    pub inputs: &'a [ValueBox],
+    pub outputs: &'a [ValueBox],
+    pub memory: &'a Memory,
+    pub score: &'a Score,
+    /// The title of the check and whether it passed, if the outputs were checked
+    pub check: Option<(&'a str, bool)>,
+    /// The layout of the floor, for the heat map of the tile accesses
+    pub floor: Option<FloorGeometry>,
+}
+
+/// The number of columns of the heat map when the layout of the floor isn't given
+const HEAT_MAP_COLUMNS: usize = 10;
+
+const STYLE: &str =
+    "body { font-family: sans-serif; margin: 2em auto; max-width: 60em; color: #222; }
+h1 { font-size: 1.4em; }
+h2 { font-size: 1.1em; margin-top: 2em; }
+table { border-collapse: collapse; }
+td, th { padding: 0.1em 0.8em; text-align: left; }
+.source td { font-family: monospace; white-space: pre; }
+.source .number, .source .count { color: #888; text-align: right; }
+.never { background: #fdd; }
+.passed { color: #080; }
+.failed { color: #c00; }
+.values { font-family: monospace; }
+.heat td { font-family: monospace; text-align: center; border: 1px solid #ddd; min-width: 4em; }
+.heat .address { color: #888; font-size: 0.8em; }";
+
+/// The number of times the instructions of each line were executed, by line.
+/// Only the lines of instructions are listed.
+pub fn line_counts(script: &ScriptObject, stats: &ExecutionStats) -> HashMap<usize, usize> {
+    let mut counts = HashMap::new();
+    for block in script.blocks() {
+        for instruction in 0..block.instructions.len() {
+            if let Some(span) = block.span_of(instruction) {
+                *counts.entry(span.line).or_default() += stats.executions_of(Position {
+                    block: block.index(),
+                    instruction,
+                });
+            }
+        }
+    }
+    counts
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn values(values: &[ValueBox]) -> String {
+    if values.is_empty() {
+        return "(none)".to_string();
+    }
+    escape(
+        &values
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+/// A self-contained HTML page (no external style or script)
+pub fn to_html(report: &Report) -> String {
+    let mut html = String::new();
+    let title = escape(report.script_path);
+    writeln!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">"
+    )
+    .unwrap();
+    writeln!(
+        html,
+        "<title>{}</title>\n<style>\n{}\n</style>",
+        title, STYLE
+    )
+    .unwrap();
+    writeln!(html, "</head>\n<body>\n<h1>{}</h1>", title).unwrap();
+
+    if let Some((check_title, passed)) = report.check {
+        let (class, result) = if passed {
+            ("passed", "passed")
+        } else {
+            ("failed", "failed")
+        };
+        writeln!(
+            html,
+            "<p class=\"{}\">{}: {}</p>",
+            class,
+            escape(check_title),
+            result
+        )
+        .unwrap();
+    }
+
+    writeln!(html, "<h2>Score</h2>\n<table>").unwrap();
+    writeln!(
+        html,
+        "<tr><th>Size</th><td>{} instructions</td></tr>",
+        report.score.size
+    )
+    .unwrap();
+    writeln!(
+        html,
+        "<tr><th>Speed</th><td>{} steps</td></tr>",
+        report.score.steps
+    )
+    .unwrap();
+    if let Some(average) = &report.score.average {
+        writeln!(
+            html,
+            "<tr><th>Average speed</th><td>{:.1} steps on {} random inbox(es) (min {}, max {})</td></tr>",
+            average.mean, average.runs, average.min, average.max
+        )
+        .unwrap();
+    }
+    writeln!(html, "</table>").unwrap();
+
+    writeln!(html, "<h2>Inbox and outbox</h2>\n<table class=\"values\">").unwrap();
+    writeln!(
+        html,
+        "<tr><th>Inbox</th><td>{}</td></tr>",
+        values(report.inputs)
+    )
+    .unwrap();
+    writeln!(
+        html,
+        "<tr><th>Outbox</th><td>{}</td></tr>",
+        values(report.outputs)
+    )
+    .unwrap();
+    writeln!(html, "</table>").unwrap();
+
+    writeln!(html, "<h2>Final floor</h2>").unwrap();
+    let mut addresses = report.memory.addresses().collect::<Vec<_>>();
+    addresses.sort_unstable();
+    if addresses.is_empty() {
+        writeln!(html, "<p>(empty)</p>").unwrap();
+    } else {
+        writeln!(
+            html,
+            "<table class=\"values\">\n<tr><th>Tile</th><th>Value</th></tr>"
+        )
+        .unwrap();
+        for address in addresses {
+            let value = report.memory.get(&address).unwrap();
+            writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td></tr>",
+                address,
+                escape(&value.to_string())
+            )
+            .unwrap();
+        }
+        writeln!(html, "</table>").unwrap();
+    }
+
+    writeln!(html, "<h2>Tile accesses</h2>").unwrap();
+    heat_map(&mut html, report);
+
+    // The instructions that never ran are highlighted
+    writeln!(html, "<h2>Source</h2>\n<table class=\"source\">").unwrap();
+    writeln!(html, "<tr><th>Line</th><th>Runs</th><th></th></tr>").unwrap();
+    let counts = line_counts(report.script, report.stats);
+    for (i, text) in report.source.lines().enumerate() {
+        let (class, count) = match counts.get(&(i + 1)) {
+            Some(0) => (" class=\"never\"", "0".to_string()),
+            Some(count) => ("", count.to_string()),
+            None => ("", String::new()),
+        };
+        writeln!(
+            html,
+            "<tr{}><td class=\"number\">{}</td><td class=\"count\">{}</td><td>{}</td></tr>",
+            class,
+            i + 1,
+            count,
+            escape(text)
+        )
+        .unwrap();
+    }
+    writeln!(html, "</table>\n</body>\n</html>").unwrap();
+
+    html
+}
+
+/// The floor as a grid, each tile with its numbers of reads and writes,
+/// and a background as dark as the tile is accessed
+fn heat_map(html: &mut String, report: &Report) {
+    let tiles = &report.stats.tiles;
+    let max_total = tiles.values().map(|a| a.total()).max().unwrap_or_default();
+    if max_total == 0 {
+        writeln!(html, "<p>(no tile accessed)</p>").unwrap();
+        return;
+    }
+    let columns = report.floor.map_or(HEAT_MAP_COLUMNS, |floor| floor.columns);
+    let last_address = tiles
+        .keys()
+        .copied()
+        .chain(report.floor.map(|floor| floor.max_address()))
+        .max()
+        .unwrap_or_default();
+
+    writeln!(html, "<table class=\"heat\">").unwrap();
+    for row in 0..=last_address / columns {
+        write!(html, "<tr>").unwrap();
+        for address in row * columns..(row + 1) * columns {
+            let accesses = tiles.get(&address).copied().unwrap_or_default();
+            let name = match report.floor {
+                Some(floor) => floor.tile_name(address),
+                None => address.to_string(),
+            };
+            write!(
+                html,
+                "<td style=\"background: rgba(220, 60, 30, {:.2})\" title=\"{} reads, {} writes\">\
+                <div class=\"address\">{}</div>{}/{}</td>",
+                accesses.total() as f64 / max_total as f64,
+                accesses.reads,
+                accesses.writes,
+                name,
+                accesses.reads,
+                accesses.writes
+            )
+            .unwrap();
+        }
+        writeln!(html, "</tr>").unwrap();
+    }
+    writeln!(html, "</table>\n<p>Reads/writes of each tile.</p>").unwrap();
+}
+
+pub fn write_report(path: &str, report: &Report) -> io::Result<()> {
+    fs::write(path, to_html(report))
+}
+
+#[cfg(test)]
+mod report_tests {
+    use hrm_interpreter::interpreter::Interpreter;
+
+    use super::*;
+
+    const SOURCE: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+a:
+    INBOX
+    JUMPZ b
+    COPYTO 0
+    OUTBOX
+    JUMP a
+b:
+    OUTBOX
+";
+
+    #[test]
+    fn test_line_counts() {
+        let script = SOURCE.parse::<ScriptObject>().unwrap();
+        let mut interpreter = Interpreter::new(Memory::default());
+        let (_, stats) = interpreter
+            .execute(&script, &[ValueBox::from(1), ValueBox::from(2)])
+            .unwrap();
+
+        assert_eq!(
+            line_counts(&script, &stats),
+            HashMap::from([(3, 3), (4, 2), (5, 2), (6, 2), (7, 2), (9, 0)])
+        );
+    }
+
+    #[test]
+    fn test_to_html() {
+        let script = SOURCE.parse::<ScriptObject>().unwrap();
+        let mut interpreter = Interpreter::new(Memory::default());
+        let inputs = [ValueBox::from('A')];
+        let (outputs, stats) = interpreter.execute(&script, &inputs).unwrap();
+        let score = Score {
+            size: script.instruction_count(),
+            steps: stats.steps,
+            average: None,
+        };
+
+        let html = to_html(&Report {
+            script_path: "<script>.hrm",
+            source: SOURCE,
+            script: &script,
+            stats: &stats,
+            inputs: &inputs,
+            outputs: &outputs,
+            memory: interpreter.memory(),
+            score: &score,
+            check: Some(("Check", true)),
+            floor: None,
+        });
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>&lt;script&gt;.hrm</title>"));
+        assert!(html.contains("<p class=\"passed\">Check: passed</p>"));
+        assert!(html.contains("<td>6 instructions</td>"));
+        assert!(html.contains("<tr><td>0</td><td>A</td></tr>"));
+        assert!(html.contains(
+            "<td style=\"background: rgba(220, 60, 30, 1.00)\" title=\"0 reads, 1 writes\">\
+            <div class=\"address\">0</div>0/1</td>"
+        ));
+        assert!(html.contains(
+            "<tr class=\"never\"><td class=\"number\">9</td><td class=\"count\">0</td><td>    OUTBOX</td></tr>"
+        ));
+    }
+}
@@ -0,0 +1,174 @@
+use std::{collections::HashMap, fmt};
+
+use hrm_interpreter::{
+    interpreter::{memory::Memory, settings::Settings, Interpreter},
+    levels::Level,
+    script_object::{value_box::ValueBox, ScriptObject},
+};
+
+use crate::check;
+
+#[derive(Debug, PartialEq)]
+/// The number of steps of the script on several inboxes
+pub struct AverageSteps {
+    pub runs: usize,
+    pub mean: f64,
+    pub min: usize,
+    pub max: usize,
+}
+
+#[derive(Debug)]
+/// An inbox on which the script fails
+pub struct Failure {
+    pub inputs: Vec<ValueBox>,
+    pub reason: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the script fails on the inbox {}: {}",
+            check::join_values(&self.inputs),
+            self.reason
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+/// The two scores of a solution in the game
+pub struct Score {
+    /// Number of instructions, without the labels and comments
+    pub size: usize,
+    /// Number of steps on the given inbox
+    pub steps: usize,
+    /// Steps on random inboxes, if any
+    pub average: Option<AverageSteps>,
+}
+
+/// Run the script on the inbox and count its steps.
+/// Returns why the script fails, or doesn't produce the outbox expected by the level.
+pub fn steps(
+    script: &ScriptObject,
+    memory: &HashMap<usize, ValueBox>,
+    max_memory_address: usize,
+    inputs: &[ValueBox],
+    settings: &Settings,
+    level: Option<&Level>,
+) -> Result<usize, String> {
+    let memory = Memory::with_data(memory.clone(), max_memory_address);
+    let mut interpreter = Interpreter::with_settings(memory, settings.clone());
+    let (outputs, stats) = interpreter
+        .execute(script, inputs)
+        .map_err(|e| e.to_string().lines().next().unwrap_or_default().to_string())?;
+    if let Some(expected) = level.and_then(|level| level.expected_outbox(inputs)) {
+        if let Some(mismatch) = check::first_mismatch(&expected, &outputs) {
+            return Err(format!("wrong output #{}", mismatch.index + 1));
+        }
+    }
+    Ok(stats.steps)
+}
+
+/// Run the script on each inbox and average its number of steps.
+/// Fails on the first inbox on which the script fails, or doesn't produce the outbox expected by the level.
+pub fn average_steps(
+    script: &ScriptObject,
+    memory: &HashMap<usize, ValueBox>,
+    max_memory_address: usize,
+    inboxes: &[Vec<ValueBox>],
+    settings: &Settings,
+    level: Option<&Level>,
+) -> Result<AverageSteps, Failure> {
+    let mut steps = vec![];
+    for inputs in inboxes {
+        let run_steps = self::steps(script, memory, max_memory_address, inputs, settings, level)
+            .map_err(|reason| Failure {
+                inputs: inputs.clone(),
+                reason,
+            })?;
+        steps.push(run_steps);
+    }
+
+    Ok(AverageSteps {
+        runs: steps.len(),
+        mean: steps.iter().sum::<usize>() as f64 / steps.len().max(1) as f64,
+        min: steps.iter().copied().min().unwrap_or_default(),
+        max: steps.iter().copied().max().unwrap_or_default(),
+    })
+}
+
+/// Print the score to stderr
+pub fn report(score: &Score) {
+    eprintln!("Size: {} instructions", score.size);
+    eprintln!("Speed: {} steps", score.steps);
+    if let Some(average) = &score.average {
+        eprintln!(
+            "Average speed: {:.1} steps on {} random inbox(es) (min {}, max {})",
+            average.mean, average.runs, average.min, average.max
+        );
+    }
+}
+
+#[cfg(test)]
+mod score_tests {
+    use hrm_interpreter::levels;
+
+    use super::*;
+
+    const MAIL_ROOM: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+    a:
+        INBOX
+        OUTBOX
+        JUMP a
+    ";
+
+    #[test]
+    fn test_average_steps() {
+        let script = MAIL_ROOM.parse::<ScriptObject>().unwrap();
+        let inboxes = [vec![1], vec![1, 2, 3]]
+            .map(|inbox| inbox.into_iter().map(ValueBox::from).collect::<Vec<_>>());
+
+        let average = average_steps(
+            &script,
+            &HashMap::new(),
+            usize::MAX,
+            &inboxes,
+            &Settings::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            average,
+            AverageSteps {
+                runs: 2,
+                mean: 7.0,
+                min: 4,
+                max: 10,
+            }
+        );
+    }
+
+    #[test]
+    fn test_average_steps_of_a_wrong_solution() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            OUTBOX
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        let level = levels::get_level(1).unwrap();
+        let inboxes = [level.generate_inbox(&mut rand::thread_rng())];
+
+        let error = average_steps(
+            &script,
+            &level.memory(),
+            level.max_address(),
+            &inboxes,
+            &Settings::default(),
+            Some(level),
+        )
+        .unwrap_err();
+        assert_eq!(error.inputs, inboxes[0]);
+        assert_eq!(error.reason, "wrong output #2");
+    }
+}
@@ -1,42 +1,288 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+    str::FromStr,
+    sync::OnceLock,
+};
 
+use serde::{Deserialize, Serialize};
+
+pub mod decompile;
+pub mod document;
+pub mod drawing;
+pub mod game;
+pub mod graph;
+pub mod include;
+pub mod initialization;
 pub mod instruction;
+pub mod lint;
+pub mod listing;
+pub mod liveness;
+pub mod loops;
+pub mod macros;
+pub mod mutate;
+pub mod optimize;
+pub mod parser;
+pub mod synthesis;
 pub mod value_box;
 
-use instruction::Instruction;
+use crate::{diagnostics::Diagnostic, interpreter::bytecode::Bytecode};
+use drawing::Drawing;
+use instruction::{Instruction, JumpTarget, ParseOptions};
+use macros::Macros;
+use value_box::{TileNames, ValueBoxMemoryAddress};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(from = "ScriptObjectData")]
 /// The ScriptObject is the representation of the script.
 /// It doesn't execute itself, nor it holds the state of the program.
 /// It's a transcription of the text file that can be read by the interpreter.
 pub struct ScriptObject {
     blocks: Vec<Block>,
+    #[serde(skip)]
     blocks_map: HashMap<String, usize>,
+    /// The comments placed between the instructions
+    comments: Vec<Comment>,
+    /// The drawings of the comments, by id ("DEFINE COMMENT <id>")
+    comment_drawings: BTreeMap<usize, Drawing>,
+    /// The drawings labelling memory tiles, by address ("DEFINE LABEL <address>")
+    tile_labels: BTreeMap<usize, Drawing>,
+    /// The remarks written after ";" or "#"
+    remarks: Vec<Remark>,
+    /// The directives and the uses of the macros, written again by the `fmt` mode
+    directives: Vec<Directive>,
+    /// The names given to the tiles with "#const", with their addresses.
+    /// They're written again instead of the addresses
+    constants: BTreeMap<String, usize>,
+    /// The flat program executed by the interpreter, compiled at the first execution
+    #[serde(skip)]
+    bytecode: OnceLock<Bytecode>,
+}
+
+/// The serialized fields of a ScriptObject, from which the labels and the jumps are resolved again
+#[derive(Deserialize)]
+struct ScriptObjectData {
+    blocks: Vec<Block>,
+    comments: Vec<Comment>,
+    comment_drawings: BTreeMap<usize, Drawing>,
+    tile_labels: BTreeMap<usize, Drawing>,
+    #[serde(default)]
+    remarks: Vec<Remark>,
+    #[serde(default)]
+    directives: Vec<Directive>,
+    #[serde(default)]
+    constants: BTreeMap<String, usize>,
+}
+
+impl From<ScriptObjectData> for ScriptObject {
+    fn from(data: ScriptObjectData) -> Self {
+        Self {
+            comments: data.comments,
+            comment_drawings: data.comment_drawings,
+            tile_labels: data.tile_labels,
+            remarks: data.remarks,
+            directives: data.directives,
+            constants: data.constants,
+            ..Self::new(data.blocks)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A comment placed in the script ("COMMENT 0").
+/// It refers to a drawing defined at the end of the script.
+pub struct Comment {
+    /// The id of the drawing of the comment
+    pub id: usize,
+    /// The comment is placed just before the instruction at this position
+    pub position: Position,
+    /// The line of the script on which the comment is placed
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// Where a remark is written, relative to its position
+pub enum RemarkPlacement {
+    /// On a line of its own, before the instruction at its position
+    OwnLine,
+    /// At the end of the line of the instruction at its position
+    AfterInstruction,
+    /// At the end of the line of the label of its block
+    AfterLabel,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A remark written after ";" or "#" in the script ("ADD 0   ; accumulate"), or a test annotation.
+/// The game has no such thing: it's only kept to write the script again.
+pub struct Remark {
+    /// The remark, starting with its ";" or "#" (or with [`TEST_ANNOTATION`])
+    pub text: String,
+    pub position: Position,
+    pub placement: RemarkPlacement,
+    /// The line of the script on which the remark is written
+    pub line: usize,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A line the game doesn't know, written again as it is by the `fmt` mode: a directive
+/// ("#define", "#include", "#const"), a line of the body of a macro, or the use of a macro.
+/// The instructions brought by an "#include" or the use of a macro aren't written by `fmt`.
+pub struct Directive {
+    /// The line as it's written again
+    pub text: String,
+    /// The directive is placed just before the instruction at this position
+    pub position: Position,
+    /// The line of the script on which the directive is written
+    pub line: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 /// A block is a set of instructions after a "jump point".
 /// In a program without jumps, there is only one unnamed block.
 pub struct Block {
     name: String,
     index: usize,
+    /// The line of the script on which the label is written (0 for the entry block, which has no label)
+    line: usize,
     pub instructions: Vec<Instruction>,
+    /// Where each instruction is written in the script
+    spans: Vec<Span>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+/// The position of an instruction in the script:
+/// the index of its block and its index inside this block.
+pub struct Position {
+    pub block: usize,
+    pub instruction: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Where an instruction is written in the source of the script, to point at it in error messages.
+pub struct Span {
+    /// The line of the instruction (starting at 1)
+    pub line: usize,
+    /// The column of the first character of the instruction (starting at 1)
+    pub column: usize,
+    /// The instruction as written in the script
+    pub text: String,
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.text)
+    }
+}
+
+impl Block {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Get the line of the script on which the label of the block is written (0 for the entry block)
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Get the line of the script on which the instruction at the given index is written.
+    pub fn line_of(&self, instruction: usize) -> Option<usize> {
+        self.spans.get(instruction).map(|span| span.line)
+    }
+
+    /// Get where the instruction at the given index is written in the script.
+    pub fn span_of(&self, instruction: usize) -> Option<&Span> {
+        self.spans.get(instruction)
+    }
 }
 
 impl PartialEq for ScriptObject {
     fn eq(&self, other: &Self) -> bool {
         self.blocks == other.blocks
+            && self.comments == other.comments
+            && self.comment_drawings == other.comment_drawings
+            && self.tile_labels == other.tile_labels
+            && self.remarks == other.remarks
+            && self.directives == other.directives
     }
 }
 
 impl ScriptObject {
-    fn new(blocks: Vec<Block>) -> Self {
+    fn new(mut blocks: Vec<Block>) -> Self {
         let mut blocks_map = HashMap::new();
         for (i, block) in blocks.iter().enumerate() {
             blocks_map.insert(block.name.clone(), i);
         }
 
-        Self { blocks, blocks_map }
+        // Resolve the jumps once, so that the interpreter jumps to indices
+        for block in &mut blocks {
+            for instruction in &mut block.instructions {
+                if let Instruction::Jump(target)
+                | Instruction::JumpIfZero(target)
+                | Instruction::JumpIfNegative(target)
+                | Instruction::JumpIfPositive(target)
+                | Instruction::JumpIfEqual(_, target)
+                | Instruction::Call(target) = instruction
+                {
+                    target.resolve(blocks_map.get(target.label()).copied());
+                }
+            }
+        }
+
+        Self {
+            blocks,
+            blocks_map,
+            comments: Vec::new(),
+            comment_drawings: BTreeMap::new(),
+            tile_labels: BTreeMap::new(),
+            remarks: Vec::new(),
+            directives: Vec::new(),
+            constants: BTreeMap::new(),
+            bytecode: OnceLock::new(),
+        }
+    }
+
+    /// The script as a flat program, compiled once
+    pub(crate) fn bytecode(&self) -> &Bytecode {
+        self.bytecode.get_or_init(|| Bytecode::compile(self))
+    }
+
+    /// The comments placed between the instructions, in the order of the script.
+    pub fn comments(&self) -> &[Comment] {
+        &self.comments
+    }
+
+    /// The remarks written after ";" or "#", in the order of the script
+    pub fn remarks(&self) -> &[Remark] {
+        &self.remarks
+    }
+
+    /// The directives and the uses of the macros, in the order of the script
+    pub fn directives(&self) -> &[Directive] {
+        &self.directives
+    }
+
+    /// The drawings of the comments, by id.
+    pub fn comment_drawings(&self) -> &BTreeMap<usize, Drawing> {
+        &self.comment_drawings
+    }
+
+    /// The drawings labelling memory tiles, by address.
+    pub fn tile_labels(&self) -> &BTreeMap<usize, Drawing> {
+        &self.tile_labels
+    }
+
+    /// The addresses of the tiles named with "#const", by name.
+    pub fn constants(&self) -> &BTreeMap<String, usize> {
+        &self.constants
+    }
+
+    /// The blocks of the script, in order. The first one is the unnamed entry block.
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
     }
 
     /// Get the block at the given index.
@@ -57,6 +303,306 @@ impl ScriptObject {
         let curr_index = current_block.index;
         self.get_block_by_index(curr_index + 1)
     }
+
+    /// Get the instruction at the given position.
+    pub fn get_instruction(&self, position: Position) -> Option<&Instruction> {
+        self.get_block_by_index(position.block)?
+            .instructions
+            .get(position.instruction)
+    }
+
+    /// Get the line of the script on which the instruction at the given position is written.
+    pub fn get_line(&self, position: Position) -> Option<usize> {
+        self.get_block_by_index(position.block)?
+            .line_of(position.instruction)
+    }
+
+    /// Get where the instruction at the given position is written in the script.
+    pub fn get_span(&self, position: Position) -> Option<&Span> {
+        self.get_block_by_index(position.block)?
+            .span_of(position.instruction)
+    }
+
+    /// The position after the last instruction of the script.
+    /// There is never any instruction to execute from there.
+    pub fn end_position(&self) -> Position {
+        Position {
+            block: self.blocks.len(),
+            instruction: 0,
+        }
+    }
+
+    /// Get the position of the first instruction to be executed from the given position,
+    /// going through the end of blocks (and empty blocks) in the order of the script.
+    /// Returns None if there is no more instruction to execute.
+    pub fn next_instruction_position(&self, from: Position) -> Option<Position> {
+        let mut position = from;
+        loop {
+            let block = self.get_block_by_index(position.block)?;
+            if position.instruction < block.instructions.len() {
+                return Some(position);
+            }
+            position = Position {
+                block: position.block + 1,
+                instruction: 0,
+            };
+        }
+    }
+
+    /// Get the positions of the instructions that can be executed right after the one at the given position:
+    /// the target of an unconditional jump, the target and the next instruction of a conditional jump,
+    /// and the next instruction otherwise.
+    pub fn successors(&self, position: Position) -> Vec<Position> {
+        let Some(instruction) = self.get_instruction(position) else {
+            return vec![];
+        };
+        let next = Position {
+            instruction: position.instruction + 1,
+            ..position
+        };
+        let target = instruction
+            .jump_target()
+            .and_then(|target| target.block())
+            .map(|block| Position {
+                block,
+                instruction: 0,
+            });
+
+        let candidates = match instruction {
+            Instruction::Jump(_) => vec![target],
+            // Where a RET goes depends on the CALL: the instruction after each CALL is a successor of the CALL instead
+            Instruction::Halt | Instruction::Ret => vec![],
+            Instruction::JumpIfZero(_)
+            | Instruction::JumpIfNegative(_)
+            | Instruction::JumpIfPositive(_)
+            | Instruction::JumpIfEqual(..)
+            | Instruction::Call(_) => vec![target, Some(next)],
+            _ => vec![Some(next)],
+        };
+        candidates
+            .into_iter()
+            .flatten()
+            .filter_map(|position| self.next_instruction_position(position))
+            .collect()
+    }
+
+    /// Get the positions of all the instructions that can be executed, starting from the beginning of the script.
+    pub fn reachable_positions(&self) -> HashSet<Position> {
+        let mut reachable = HashSet::new();
+        let mut to_visit: Vec<Position> = self
+            .next_instruction_position(Position::default())
+            .into_iter()
+            .collect();
+        while let Some(position) = to_visit.pop() {
+            if reachable.insert(position) {
+                to_visit.extend(self.successors(position));
+            }
+        }
+        reachable
+    }
+
+    /// Get the position of the first instruction written on the given line or after.
+    pub fn find_position_of_line(&self, line: usize) -> Option<Position> {
+        self.blocks.iter().find_map(|block| {
+            block
+                .spans
+                .iter()
+                .position(|span| span.line >= line)
+                .map(|instruction| Position {
+                    block: block.index,
+                    instruction,
+                })
+        })
+    }
+}
+
+// ==================== Formatting ====================
+
+/// The header of the scripts exported by the game
+const HEADER: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --";
+
+#[derive(Default)]
+/// What is written again as it's written in the script, in the `fmt` mode
+struct Source<'a> {
+    /// The directives placed in the block
+    directives: Vec<&'a Directive>,
+    /// The lines of the directives: the instructions and the labels written on them are
+    /// brought by an "#include" or a macro, and aren't written
+    lines: HashSet<usize>,
+    constants: Option<&'a BTreeMap<String, usize>>,
+}
+
+impl Source<'_> {
+    /// The instruction as written in the script, with the names given with "#const" if any
+    fn instruction(&self, instruction: &Instruction, span: Option<&Span>) -> String {
+        let written = format!("{:#}", instruction);
+        let (Some(constants), Some(span)) = (self.constants, span) else {
+            return written;
+        };
+        // The operand of the tile, as written: "tmp" or "[ptr]"
+        let operand = span.text.split_whitespace().nth(1).unwrap_or_default();
+        let name = operand.trim_start_matches('[').trim_end_matches(']');
+        if instruction.memory_address().is_none() || !constants.contains_key(name) {
+            return written;
+        }
+        // The commands have no digits nor brackets: the first match is the address
+        let address = written.split_whitespace().nth(1).unwrap_or_default();
+        written.replacen(address, operand, 1)
+    }
+}
+
+/// Write the label of the block (unless it's the entry block) and its instructions,
+/// with the comments placed in this block.
+fn write_block(
+    f: &mut std::fmt::Formatter<'_>,
+    block: &Block,
+    comments: &[Comment],
+    remarks: &[Remark],
+    source: &Source,
+) -> std::fmt::Result {
+    let remark = |instruction: usize, placement: RemarkPlacement| {
+        remarks.iter().find(|remark| {
+            remark.position.instruction == instruction && remark.placement == placement
+        })
+    };
+    // The comments and the remarks on their own lines, in the order of the script
+    let write_comments = |f: &mut std::fmt::Formatter<'_>, instruction: usize| {
+        let mut lines = comments
+            .iter()
+            .filter(|comment| comment.position.instruction == instruction)
+            .map(|comment| (comment.line, format!("    {:<8} {}", "COMMENT", comment.id)))
+            .chain(
+                remarks
+                    .iter()
+                    .filter(|remark| {
+                        remark.position.instruction == instruction
+                            && remark.placement == RemarkPlacement::OwnLine
+                    })
+                    .map(|remark| {
+                        // The test annotations are written like the title, without indent
+                        if remark.text.starts_with(TEST_ANNOTATION) {
+                            (remark.line, remark.text.clone())
+                        } else {
+                            (remark.line, format!("    {}", remark.text))
+                        }
+                    }),
+            )
+            .chain(
+                source
+                    .directives
+                    .iter()
+                    .filter(|directive| directive.position.instruction == instruction)
+                    .map(|directive| (directive.line, directive.text.clone())),
+            )
+            .collect::<Vec<_>>();
+        lines.sort_by_key(|(line, _)| *line);
+        lines
+            .iter()
+            .try_for_each(|(_, text)| writeln!(f, "{}", text))
+    };
+
+    if block.index != 0 && !source.lines.contains(&block.line) {
+        match remark(0, RemarkPlacement::AfterLabel) {
+            Some(remark) => writeln!(f, "{}: {}", block.name, remark.text)?,
+            None => writeln!(f, "{}:", block.name)?,
+        }
+    }
+    for (i, instruction) in block.instructions.iter().enumerate() {
+        write_comments(f, i)?;
+        let span = block.span_of(i);
+        if span.is_some_and(|span| source.lines.contains(&span.line)) {
+            continue;
+        }
+        let instruction = source.instruction(instruction, span);
+        match remark(i, RemarkPlacement::AfterInstruction) {
+            Some(remark) => writeln!(f, "    {:<16} {}", instruction, remark.text)?,
+            None => writeln!(f, "    {}", instruction)?,
+        }
+    }
+    write_comments(f, block.instructions.len())
+}
+
+impl Display for Block {
+    /// The label of the block (unless it's the entry block), then one aligned instruction per line
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_block(f, self, &[], &[], &Source::default())
+    }
+}
+
+impl Display for ScriptObject {
+    /// The canonical source of the script: the header, the blocks separated by blank lines,
+    /// the comments where they were placed, and the drawings
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, true)
+    }
+}
+
+impl ScriptObject {
+    /// Write the source of the script: as it's written, with its remarks, its directives and
+    /// the uses of its macros, or else only what the game knows
+    fn write(&self, f: &mut std::fmt::Formatter<'_>, as_written: bool) -> std::fmt::Result {
+        let (remarks, directives) = if as_written {
+            (self.remarks.as_slice(), self.directives.as_slice())
+        } else {
+            (&[][..], &[][..])
+        };
+        let lines = directives
+            .iter()
+            .map(|directive| directive.line)
+            .collect::<HashSet<_>>();
+        writeln!(f, "{}", HEADER)?;
+        for block in &self.blocks {
+            let comments = self
+                .comments
+                .iter()
+                .filter(|comment| comment.position.block == block.index)
+                .cloned()
+                .collect::<Vec<Comment>>();
+            let remarks = remarks
+                .iter()
+                .filter(|remark| remark.position.block == block.index)
+                .cloned()
+                .collect::<Vec<Remark>>();
+            let source = Source {
+                directives: directives
+                    .iter()
+                    .filter(|directive| directive.position.block == block.index)
+                    .collect(),
+                lines: lines.clone(),
+                constants: as_written.then_some(&self.constants),
+            };
+            // The entry block has no label: skip it if there's nothing in it
+            if block.index == 0
+                && block.instructions.is_empty()
+                && comments.is_empty()
+                && remarks.is_empty()
+                && source.directives.is_empty()
+            {
+                continue;
+            }
+            // A label brought by a macro or an "#include" is written with its use
+            if block.index == 0 || !lines.contains(&block.line) {
+                writeln!(f)?;
+            }
+            write_block(f, block, &comments, &remarks, &source)?;
+        }
+
+        let definitions = self
+            .comment_drawings
+            .iter()
+            .map(|(id, drawing)| ("COMMENT", id, drawing))
+            .chain(
+                self.tile_labels
+                    .iter()
+                    .map(|(address, drawing)| ("LABEL", address, drawing)),
+            );
+        for (kind, id, drawing) in definitions {
+            writeln!(f)?;
+            writeln!(f, "DEFINE {} {}", kind, id)?;
+            writeln!(f, "{}", drawing)?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -65,11 +611,96 @@ impl ScriptObject {
 pub enum ScriptObjectValidationError {
     #[error("Some jumps have invalid anchors")]
     InvalidJumps,
+    #[error("Label {name} is defined twice, on line {first_line} and on line {second_line}")]
+    DuplicateLabel {
+        name: String,
+        first_line: usize,
+        second_line: usize,
+    },
+    #[error("The script has {count} instructions, more than the limit of {max}")]
+    /// The script is longer than allowed (the game limits the size on some challenges)
+    TooManyInstructions { count: usize, max: usize },
+    #[error(
+        "Tiles past the end of the floor (max address {max_address}) are used on {}",
+        on_lines(lines)
+    )]
+    /// Direct addresses (or pointers) beyond the floor: they would fail when executed
+    AddressOutOfRange {
+        max_address: usize,
+        lines: Vec<usize>,
+    },
+}
+
+/// "line 3", or "lines 3, 5"
+fn on_lines(lines: &[usize]) -> String {
+    let lines = lines.iter().map(ToString::to_string).collect::<Vec<_>>();
+    match lines.len() {
+        1 => format!("line {}", lines[0]),
+        _ => format!("lines {}", lines.join(", ")),
+    }
+}
+
+impl ScriptObjectValidationError {
+    /// A stable identifier of the kind of error, for tools
+    pub fn code(&self) -> &'static str {
+        match self {
+            ScriptObjectValidationError::InvalidJumps => "invalid-jumps",
+            ScriptObjectValidationError::DuplicateLabel { .. } => "duplicate-label",
+            ScriptObjectValidationError::TooManyInstructions { .. } => "too-many-instructions",
+            ScriptObjectValidationError::AddressOutOfRange { .. } => "address-out-of-range",
+        }
+    }
+
+    /// The errors located in the script: the jumps to unknown labels, the second definition of a label,
+    /// the first instruction past the size limit, or the instructions using tiles past the floor
+    pub fn diagnostics(&self, script: &ScriptObject) -> Vec<Diagnostic> {
+        match self {
+            ScriptObjectValidationError::InvalidJumps => script
+                .jumps()
+                .filter(|(_, _, target)| target.block().is_none())
+                .filter_map(|(position, _, target)| {
+                    let span = script.get_span(position)?;
+                    Some(Diagnostic::new(
+                        format!("unknown label {}", target.label()),
+                        span.line,
+                        None,
+                    ))
+                })
+                .collect(),
+            ScriptObjectValidationError::DuplicateLabel { second_line, .. } => {
+                vec![Diagnostic::new(self, *second_line, None)]
+            }
+            ScriptObjectValidationError::TooManyInstructions { max, .. } => script
+                .blocks
+                .iter()
+                .flat_map(|block| (0..block.instructions.len()).map(|i| block.span_of(i)))
+                .nth(*max)
+                .flatten()
+                .map(|span| Diagnostic::new(self, span.line, None))
+                .into_iter()
+                .collect(),
+            ScriptObjectValidationError::AddressOutOfRange { max_address, .. } => script
+                .addresses_past(*max_address)
+                .filter_map(|(position, address)| {
+                    let span = script.get_span(position)?;
+                    Some(Diagnostic::new(
+                        format!(
+                            "tile {} is past the end of the floor (max address {})",
+                            address, max_address
+                        ),
+                        span.line,
+                        None,
+                    ))
+                })
+                .collect(),
+        }
+    }
 }
 
 impl ScriptObject {
     /// After parsing the script, we can validate it.
     pub fn validate(&self) -> Result<(), ScriptObjectValidationError> {
+        self.all_labels_are_unique()?;
         if !self.all_jumps_have_valid_anchors() {
             Err(ScriptObjectValidationError::InvalidJumps)
         } else {
@@ -77,29 +708,95 @@ impl ScriptObject {
         }
     }
 
-    /// Check if all jumps points to existing blocks.
-    fn all_jumps_have_valid_anchors(&self) -> bool {
-        let instructions = self
-            .blocks
-            .iter()
-            .flat_map(|block| block.instructions.iter());
-        for instruction in instructions {
-            match instruction {
-                Instruction::Jump(label)
-                | Instruction::JumpIfZero(label)
-                | Instruction::JumpIfNegative(label) => match self.get_block_by_label(label) {
-                    Some(other) => {
-                        if other.name != *label {
-                            return false;
-                        }
-                    }
-                    None => return false,
-                },
-                _ => {}
+    /// Check that the script has at most `max_instructions` instructions
+    pub fn validate_size(
+        &self,
+        max_instructions: usize,
+    ) -> Result<(), ScriptObjectValidationError> {
+        let count = self.instruction_count();
+        if count > max_instructions {
+            Err(ScriptObjectValidationError::TooManyInstructions {
+                count,
+                max: max_instructions,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check that the direct addresses, and the pointers of the indirect addresses,
+    /// are on a floor whose last tile is `max_address`
+    pub fn validate_addresses(
+        &self,
+        max_address: usize,
+    ) -> Result<(), ScriptObjectValidationError> {
+        let lines = self
+            .addresses_past(max_address)
+            .filter_map(|(position, _)| self.get_line(position))
+            .collect::<Vec<_>>();
+        if lines.is_empty() {
+            Ok(())
+        } else {
+            Err(ScriptObjectValidationError::AddressOutOfRange { max_address, lines })
+        }
+    }
+
+    /// The instructions whose address (or pointer) is past `max_address`, with the address
+    fn addresses_past(&self, max_address: usize) -> impl Iterator<Item = (Position, usize)> + '_ {
+        self.blocks.iter().flat_map(move |block| {
+            block
+                .instructions
+                .iter()
+                .enumerate()
+                .filter_map(move |(i, instruction)| {
+                    let address = match instruction.memory_address()? {
+                        ValueBoxMemoryAddress::Pointer(address)
+                        | ValueBoxMemoryAddress::PointerAddress(address) => *address,
+                    };
+                    let position = Position {
+                        block: block.index,
+                        instruction: i,
+                    };
+                    (address > max_address).then_some((position, address))
+                })
+        })
+    }
+
+    /// Check that no label is defined twice: the jumps would only lead to the last definition.
+    fn all_labels_are_unique(&self) -> Result<(), ScriptObjectValidationError> {
+        let mut first_lines = HashMap::new();
+        for block in self.blocks.iter().skip(1) {
+            if let Some(first_line) = first_lines.insert(block.name(), block.line()) {
+                return Err(ScriptObjectValidationError::DuplicateLabel {
+                    name: block.name().to_string(),
+                    first_line,
+                    second_line: block.line(),
+                });
             }
         }
+        Ok(())
+    }
 
-        true
+    /// Check if all jumps points to existing blocks.
+    fn all_jumps_have_valid_anchors(&self) -> bool {
+        self.jumps().all(|(_, _, target)| target.block().is_some())
+    }
+
+    /// Get all the jumps of the script, in order: their position, the jump instruction and its target.
+    pub fn jumps(&self) -> impl Iterator<Item = (Position, &Instruction, &JumpTarget)> {
+        self.blocks.iter().flat_map(|block| {
+            block
+                .instructions
+                .iter()
+                .enumerate()
+                .filter_map(move |(i, instruction)| {
+                    let position = Position {
+                        block: block.index,
+                        instruction: i,
+                    };
+                    Some((position, instruction, instruction.jump_target()?))
+                })
+        })
     }
 }
 
@@ -111,66 +808,717 @@ pub enum ParseScriptObjectError {
     )]
     InvalidInstruction {
         line: usize,
+        /// The column at which the instruction starts (starting at 1)
+        column: usize,
         instruction: String,
         #[source]
         error: instruction::ParseInstructionError,
     },
+    #[error(
+        "PARSER ERROR | invalid definition on line {line}: '{definition}' | Expected 'DEFINE COMMENT <id>' or 'DEFINE LABEL <address>' followed by a payload ending with ';'"
+    )]
+    InvalidDefinition { line: usize, definition: String },
+    #[error("PARSER ERROR | invalid macro on line {line}: {reason}")]
+    /// A macro is badly defined, or used with the wrong arguments
+    InvalidMacro { line: usize, reason: String },
+    #[error(
+        "PARSER ERROR | error parsing the expansion of {name} on line {line}, from line {macro_line}: '{instruction}' | Detailed error: {error}"
+    )]
+    /// An instruction of the body of a macro is invalid once the arguments are given
+    InvalidExpansion {
+        /// The line using the macro
+        line: usize,
+        name: String,
+        /// The line of the body of the macro holding the instruction
+        macro_line: usize,
+        instruction: String,
+        #[source]
+        error: instruction::ParseInstructionError,
+    },
+    #[error("PARSER ERROR | invalid constant on line {line}: {reason}")]
+    /// A "#const" isn't written as "#const NAME address"
+    InvalidConstant { line: usize, reason: String },
+    #[error("PARSER ERROR | invalid include on line {line}: {reason}")]
+    /// A file can't be included
+    InvalidInclude { line: usize, reason: String },
+    #[error(
+        "PARSER ERROR | error parsing {file} on line {file_line}, included on line {line}: '{instruction}' | Detailed error: {error}"
+    )]
+    /// An instruction of an included file is invalid
+    InvalidIncludedInstruction {
+        /// The line of the "#include" in the script
+        line: usize,
+        file: String,
+        /// The line of the instruction in the included file
+        file_line: usize,
+        instruction: String,
+        #[source]
+        error: instruction::ParseInstructionError,
+    },
+    #[error("{}", join_errors(.0))]
+    /// Several lines are invalid, in the order of the script
+    Multiple(Vec<ParseScriptObjectError>),
+}
+
+impl ParseScriptObjectError {
+    /// A stable identifier of the kind of error, for tools.
+    /// Several errors are identified as "multiple": see [`Self::errors`].
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseScriptObjectError::InvalidInstruction { error, .. } => error.code(),
+            ParseScriptObjectError::InvalidDefinition { .. } => "invalid-definition",
+            ParseScriptObjectError::InvalidMacro { .. } => "invalid-macro",
+            ParseScriptObjectError::InvalidExpansion { error, .. } => error.code(),
+            ParseScriptObjectError::InvalidConstant { .. } => "invalid-constant",
+            ParseScriptObjectError::InvalidInclude { .. } => "invalid-include",
+            ParseScriptObjectError::InvalidIncludedInstruction { error, .. } => error.code(),
+            ParseScriptObjectError::Multiple(_) => "multiple",
+        }
+    }
+
+    /// The errors of the invalid lines, in the order of the script
+    pub fn errors(&self) -> Vec<&ParseScriptObjectError> {
+        match self {
+            ParseScriptObjectError::Multiple(errors) => errors
+                .iter()
+                .flat_map(ParseScriptObjectError::errors)
+                .collect(),
+            error => vec![error],
+        }
+    }
+
+    /// The errors located in the script, to show them with an excerpt of the script
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            ParseScriptObjectError::InvalidInstruction {
+                line,
+                column,
+                error,
+                ..
+            } => {
+                let range = error.range();
+                let offset = column - 1;
+                vec![Diagnostic::new(
+                    error,
+                    *line,
+                    Some(range.start + offset..range.end + offset),
+                )]
+            }
+            ParseScriptObjectError::InvalidDefinition { line, .. } => vec![Diagnostic::new(
+                "invalid definition: expected 'DEFINE COMMENT <id>' or 'DEFINE LABEL <address>' followed by a payload ending with ';'",
+                *line,
+                None,
+            )],
+            ParseScriptObjectError::InvalidMacro { line, reason } => {
+                vec![Diagnostic::new(format!("invalid macro: {}", reason), *line, None)]
+            }
+            // Shown on the body of the macro, where the instruction is written
+            ParseScriptObjectError::InvalidExpansion {
+                line,
+                name,
+                macro_line,
+                error,
+                ..
+            } => {
+                let mut diagnostic = Diagnostic::new(error, *macro_line, None);
+                diagnostic
+                    .notes
+                    .push(format!("in the expansion of {} on line {}", name, line));
+                vec![diagnostic]
+            }
+            ParseScriptObjectError::InvalidConstant { line, reason } => {
+                vec![Diagnostic::new(format!("invalid constant: {}", reason), *line, None)]
+            }
+            ParseScriptObjectError::InvalidInclude { line, reason } => {
+                vec![Diagnostic::new(format!("invalid include: {}", reason), *line, None)]
+            }
+            // Shown on the "#include", as the included file isn't the script
+            ParseScriptObjectError::InvalidIncludedInstruction {
+                line,
+                file,
+                file_line,
+                instruction,
+                error,
+            } => {
+                let mut diagnostic = Diagnostic::new(error, *line, None);
+                diagnostic.notes.push(format!(
+                    "in {} on line {}: {}",
+                    file, file_line, instruction
+                ));
+                vec![diagnostic]
+            }
+            ParseScriptObjectError::Multiple(errors) => errors
+                .iter()
+                .flat_map(ParseScriptObjectError::diagnostics)
+                .collect(),
+        }
+    }
+}
+
+fn join_errors(errors: &[ParseScriptObjectError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The kind of drawing defined in a "DEFINE" section
+enum DefinitionKind {
+    Comment,
+    Label,
+}
+
+/// Parse the "DEFINE" sections at the end of the script.
+/// Returns the drawings of the comments and of the labels.
+#[allow(clippy::type_complexity)]
+fn parse_definitions<'a>(
+    lines: impl Iterator<Item = (usize, &'a str)>,
+) -> Result<(BTreeMap<usize, Drawing>, BTreeMap<usize, Drawing>), ParseScriptObjectError> {
+    let mut comment_drawings = BTreeMap::new();
+    let mut tile_labels = BTreeMap::new();
+
+    // The definition being read: kind, id, line and payload so far
+    let mut current: Option<(DefinitionKind, usize, usize, String)> = None;
+
+    for (i, line) in lines {
+        let line = line.trim();
+        match current.as_mut() {
+            None if line.is_empty() => {}
+            None => {
+                let invalid_definition = || ParseScriptObjectError::InvalidDefinition {
+                    line: i + 1,
+                    definition: line.to_string(),
+                };
+                let parts = line.split_whitespace().collect::<Vec<&str>>();
+                let kind = match parts.as_slice() {
+                    ["DEFINE", "COMMENT", _] => DefinitionKind::Comment,
+                    ["DEFINE", "LABEL", _] => DefinitionKind::Label,
+                    _ => return Err(invalid_definition()),
+                };
+                let id = parts[2]
+                    .parse::<usize>()
+                    .map_err(|_| invalid_definition())?;
+                current = Some((kind, id, i + 1, String::new()));
+            }
+            Some((kind, id, _, payload)) => {
+                payload.push_str(line);
+                if line.ends_with(';') {
+                    let drawing = Drawing::new(payload);
+                    match kind {
+                        DefinitionKind::Comment => comment_drawings.insert(*id, drawing),
+                        DefinitionKind::Label => tile_labels.insert(*id, drawing),
+                    };
+                    current = None;
+                }
+            }
+        }
+    }
+
+    if let Some((kind, id, line, _)) = current {
+        // The payload never ended
+        let kind = match kind {
+            DefinitionKind::Comment => "COMMENT",
+            DefinitionKind::Label => "LABEL",
+        };
+        return Err(ParseScriptObjectError::InvalidDefinition {
+            line,
+            definition: format!("DEFINE {} {}", kind, id),
+        });
+    }
+
+    Ok((comment_drawings, tile_labels))
 }
 
 impl FromStr for ScriptObject {
     type Err = ParseScriptObjectError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut blocks: Vec<Block> = Vec::new();
-        blocks.push(Block {
-            name: "entry".to_string(),
-            index: 0,
-            instructions: Vec::new(),
-        });
+        Self::parse_with_options(s, &ParseOptions::default())
+    }
+}
 
-        for (i, line) in s.lines().enumerate() {
-            let line = line.trim();
-            if line.starts_with("--") // Title
-            || line.is_empty() // Empty line
-            || line.contains("COMMENT")
-            // Comment
-            {
-                continue;
+/// What a line of the script holds.
+/// Each line is parsed on its own, then the lines are assembled into blocks.
+#[derive(Debug, Clone)]
+enum ScriptLine {
+    /// Title, empty line or comment without drawing
+    Ignored,
+    /// "COMMENT <id>", referring to a drawing
+    Comment(usize),
+    /// "<label>:", starting a block, with its remark
+    Label(String, Option<String>),
+    /// A remark on a line of its own
+    Remark(String),
+    /// An instruction, without its line so that the parsed line can move with the edits of the script
+    Instruction {
+        /// The column at which the instruction starts (starting at 1)
+        column: usize,
+        text: String,
+        parsed: Result<Instruction, instruction::ParseInstructionError>,
+        remark: Option<String>,
+    },
+    /// "DEFINE ...": the drawings are defined until the end of the script
+    Definitions,
+    /// A line of the definition of a macro, or a "#const", written again by `fmt`
+    Directive(String),
+    /// A use of a macro or an "#include", replaced by the lines it brings
+    Expansion {
+        /// The column at which the use of the macro starts (starting at 1)
+        column: usize,
+        text: String,
+        /// The use as `fmt` writes it again
+        written: String,
+        /// The lines brought, with where they are written
+        lines: Vec<(Origin, ScriptLine)>,
+    },
+}
+
+#[derive(Debug, Clone)]
+/// Where a line brought by a macro or an included file is written
+struct Origin {
+    /// The macro whose body holds the line, if it comes from a macro
+    macro_name: Option<String>,
+    /// The included file holding the line, None for the script itself
+    file: Option<String>,
+    /// The line in the script or in the included file (starting at 1)
+    line: usize,
+}
+
+/// The directives, whose "#" doesn't start a remark
+const DIRECTIVES: [&str; 4] = ["#define", "#end", "#include", "#const"];
+
+/// Split the line into its code and its remark, which starts at a ";" or a "#" outside of quotes.
+/// The "#" of a directive starting the line doesn't start a remark.
+pub(crate) fn split_remark(line: &str) -> (&str, Option<&str>) {
+    let start = line.len() - line.trim_start().len();
+    // The index of the "#" of the directive
+    let directive = DIRECTIVES
+        .iter()
+        .any(|directive| {
+            line[start..]
+                .strip_prefix(directive)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+        })
+        .then_some(start);
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' | '#' if !quoted && directive != Some(i) => {
+                return (&line[..i], Some(line[i..].trim_end()));
             }
+            _ => {}
+        }
+    }
+    (line, None)
+}
+
+/// Whether the line is a directive ("#define", "#include", "#const"...), read with the whole script
+fn is_directive(line: &str) -> bool {
+    macros::is_directive(line) || include::is_directive(line) || parse_constant(line).is_some()
+}
 
-            if line.starts_with("DEFINE") {
-                // Enter comment/label definition zone
-                break;
+/// The name and the address of "#const NAME address", None if the line isn't a "#const"
+fn parse_constant(line: &str) -> Option<Result<(String, usize), String>> {
+    let parts = split_remark(line).0.trim().strip_prefix("#const")?;
+    if !parts.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(
+        match parts.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [name, address] => {
+                // A name made of digits would be read as an address
+                if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                    return Some(Err(format!("invalid name: {}", name)));
+                }
+                address
+                    .parse::<usize>()
+                    .map(|address| (name.to_string(), address))
+                    .map_err(|_| format!("invalid address: {}", address))
             }
+            _ => Err("expected '#const NAME address'".to_string()),
+        },
+    )
+}
+
+/// The start of the lines describing a test case of the script:
+/// "-- TEST: inputs = 1 2 3 ; expected = 6"
+pub const TEST_ANNOTATION: &str = "-- TEST:";
+
+/// Parse a line of the script
+fn parse_line(raw_line: &str, options: &ParseOptions) -> ScriptLine {
+    // The test annotations are kept like the remarks, along with their ";"
+    if raw_line.trim().starts_with(TEST_ANNOTATION) {
+        return ScriptLine::Remark(raw_line.trim().to_string());
+    }
+    let (code, remark) = split_remark(raw_line);
+    let remark = remark.map(str::to_string);
+    let line = code.trim();
+
+    if line.starts_with("DEFINE") {
+        return ScriptLine::Definitions;
+    }
+
+    if let Some(comment_id) = line.strip_prefix("COMMENT") {
+        // Comment referring to a drawing
+        return match comment_id.trim().parse::<usize>() {
+            Ok(id) => ScriptLine::Comment(id),
+            Err(_) => ScriptLine::Ignored,
+        };
+    }
 
-            let line_split_colon = line.split(':').collect::<Vec<&str>>();
-            if line_split_colon.len() > 1 {
-                // <=> line contains a colon
-                // Block definition
-                let new_block = Block {
-                    name: line_split_colon[0].to_string(),
-                    index: blocks.len(),
-                    instructions: Vec::new(),
+    // The message of a DEBUG can hold anything
+    let is_debug_message = instruction::debug_message(line).is_some();
+
+    if line.is_empty() {
+        return match remark {
+            Some(remark) => ScriptLine::Remark(remark),
+            None => ScriptLine::Ignored,
+        };
+    }
+
+    if line.starts_with("--") // Title
+    || (line.contains("COMMENT") && !is_debug_message)
+    // Comment
+    {
+        return ScriptLine::Ignored;
+    }
+
+    if let (Some((label, _)), false) = (line.split_once(':'), is_debug_message) {
+        // Block definition
+        return ScriptLine::Label(label.to_string(), remark);
+    }
+
+    // Line is an instruction
+    let column = raw_line.len() - raw_line.trim_start().len() + 1;
+    ScriptLine::Instruction {
+        column,
+        text: line.to_string(),
+        parsed: Instruction::parse_with_options(line, options),
+        remark,
+    }
+}
+
+impl ScriptObject {
+    /// Parse a script in which memory tiles can be referred to by their names.
+    /// (The labels of the tiles in the game are drawings, so they can't be used as names)
+    pub fn parse_with_tile_names(
+        s: &str,
+        tile_names: &TileNames,
+    ) -> Result<Self, ParseScriptObjectError> {
+        Self::parse_with_options(
+            s,
+            &ParseOptions {
+                tile_names: tile_names.clone(),
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn parse_with_options(
+        s: &str,
+        options: &ParseOptions,
+    ) -> Result<Self, ParseScriptObjectError> {
+        let lines = s.lines().collect::<Vec<&str>>();
+        let source = include::resolve(&lines, options.include_dir.as_deref())?;
+        let origin = |line: usize, macro_name: Option<String>| Origin {
+            macro_name,
+            file: source[line - 1].file.clone(),
+            line: source[line - 1].line,
+        };
+        // The errors in the included files are placed on the "#include" of the script
+        let locate = |line: usize, reason: String| {
+            let source_line = &source[line - 1];
+            let reason = match &source_line.file {
+                Some(file) => format!("{} (in {} on line {})", reason, file, source_line.line),
+                None => reason,
+            };
+            (source_line.script_line, reason)
+        };
+        let relocate = |error| match error {
+            ParseScriptObjectError::InvalidMacro { line, reason } => {
+                let (line, reason) = locate(line, reason);
+                ParseScriptObjectError::InvalidMacro { line, reason }
+            }
+            error => error,
+        };
+        let texts = source
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>();
+        let macros = Macros::parse(&texts).map_err(relocate)?;
+
+        // The constants are added to the names of the tiles, for the following lines
+        let mut options = options.clone();
+        let mut constants = BTreeMap::new();
+        let mut parsed = Vec::with_capacity(lines.len());
+        for (i, source_line) in source.iter().enumerate() {
+            let line = source_line.text.as_str();
+            let expansion = |written, lines| ScriptLine::Expansion {
+                column: line.len() - line.trim_start().len() + 1,
+                text: line.trim().to_string(),
+                written,
+                lines,
+            };
+            let script_line = if macros.is_definition_line(i) {
+                ScriptLine::Directive(line.trim_end().to_string())
+            } else if let Some(constant) = parse_constant(line) {
+                let (name, address) = constant.map_err(|reason| {
+                    let (line, reason) = locate(i + 1, reason);
+                    ParseScriptObjectError::InvalidConstant { line, reason }
+                })?;
+                constants.insert(name.clone(), address);
+                options.tile_names.insert(name, address);
+                ScriptLine::Directive(line.trim().to_string())
+            } else if include::is_directive(line) {
+                // Filled with the lines of the included file, which follow
+                expansion(line.trim().to_string(), vec![])
+            } else if let Some(expanded) =
+                macros.expand(i, split_remark(line).0).map_err(relocate)?
+            {
+                // Written again like an instruction, with its remark
+                let written = match split_remark(line) {
+                    (code, Some(remark)) => format!("    {:<16} {}", code.trim(), remark),
+                    (code, None) => format!("    {}", code.trim()),
                 };
-                blocks.push(new_block);
-                continue;
+                expansion(
+                    written,
+                    expanded
+                        .into_iter()
+                        .map(|expanded| {
+                            let script_line = parse_line(&expanded.text, &options);
+                            (origin(expanded.line, Some(expanded.name)), script_line)
+                        })
+                        .collect(),
+                )
+            } else {
+                parse_line(line, &options)
+            };
+
+            match (&source_line.file, script_line) {
+                (None, script_line) => {
+                    let definitions = matches!(script_line, ScriptLine::Definitions);
+                    parsed.push(script_line);
+                    if definitions {
+                        // The rest of the script is read with the definitions
+                        break;
+                    }
+                }
+                (Some(_), ScriptLine::Ignored | ScriptLine::Directive(_)) => {}
+                // A line of an included file, brought by the last "#include" of the script
+                (Some(_), script_line) => {
+                    if let Some(ScriptLine::Expansion { lines: brought, .. }) = parsed.last_mut() {
+                        match script_line {
+                            ScriptLine::Expansion { lines, .. } => brought.extend(lines),
+                            script_line => brought.push((origin(i + 1, None), script_line)),
+                        }
+                    }
+                }
             }
+        }
+        Ok(Self {
+            constants,
+            ..Self::from_lines(&lines, &parsed)?
+        })
+    }
 
-            // Line is an instruction
-            blocks
-                .last_mut()
-                .unwrap()
-                .instructions
-                .push(Instruction::from_str(line).map_err(|err| {
-                    Self::Err::InvalidInstruction {
+    /// Assemble the parsed lines of the script into blocks.
+    /// `lines` is the text of the script, from which the definitions are read.
+    fn from_lines(lines: &[&str], parsed: &[ScriptLine]) -> Result<Self, ParseScriptObjectError> {
+        let mut blocks: Vec<Block> = Vec::new();
+        blocks.push(Block {
+            name: "entry".to_string(),
+            index: 0,
+            line: 0,
+            instructions: Vec::new(),
+            spans: Vec::new(),
+        });
+
+        let mut comments = Vec::new();
+        let mut remarks = Vec::new();
+        let mut directives = Vec::new();
+        let mut definitions = (BTreeMap::new(), BTreeMap::new());
+        // Keep assembling after an invalid instruction, to report all of them at once
+        let mut errors = Vec::new();
+
+        'lines: for (i, script_line) in parsed.iter().enumerate() {
+            // The lines brought by a macro or an "#include" are placed on the line bringing them
+            let (expansion, script_lines) = match script_line {
+                ScriptLine::Expansion {
+                    column,
+                    text,
+                    written,
+                    lines,
+                } => {
+                    let current_block = blocks.last().unwrap();
+                    directives.push(Directive {
+                        text: written.clone(),
+                        position: Position {
+                            block: current_block.index,
+                            instruction: current_block.instructions.len(),
+                        },
                         line: i + 1,
-                        instruction: line.to_string(),
-                        error: err,
+                    });
+                    (
+                        Some((*column, text)),
+                        lines
+                            .iter()
+                            .map(|(origin, line)| (Some(origin), line))
+                            .collect(),
+                    )
+                }
+                script_line => (None, vec![(None, script_line)]),
+            };
+            for (origin, script_line) in script_lines {
+                match script_line {
+                    ScriptLine::Ignored | ScriptLine::Expansion { .. } => {}
+                    ScriptLine::Directive(_) if origin.is_some() => {}
+                    ScriptLine::Directive(text) => {
+                        let current_block = blocks.last().unwrap();
+                        directives.push(Directive {
+                            text: text.clone(),
+                            position: Position {
+                                block: current_block.index,
+                                instruction: current_block.instructions.len(),
+                            },
+                            line: i + 1,
+                        });
+                    }
+                    // The drawings of an included file aren't read
+                    ScriptLine::Comment(_)
+                        if origin.is_some_and(|origin| origin.file.is_some()) => {}
+                    ScriptLine::Comment(id) => {
+                        let current_block = blocks.last().unwrap();
+                        comments.push(Comment {
+                            id: *id,
+                            position: Position {
+                                block: current_block.index,
+                                instruction: current_block.instructions.len(),
+                            },
+                            line: i + 1,
+                        });
+                    }
+                    ScriptLine::Label(name, remark) => {
+                        let new_block = Block {
+                            name: name.clone(),
+                            index: blocks.len(),
+                            line: i + 1,
+                            instructions: Vec::new(),
+                            spans: Vec::new(),
+                        };
+                        // The remarks of the macros and the included files aren't kept
+                        if let (Some(remark), None) = (remark, origin) {
+                            remarks.push(Remark {
+                                text: remark.clone(),
+                                position: Position {
+                                    block: new_block.index,
+                                    instruction: 0,
+                                },
+                                placement: RemarkPlacement::AfterLabel,
+                                line: i + 1,
+                            });
+                        }
+                        blocks.push(new_block);
+                    }
+                    ScriptLine::Remark(_) if origin.is_some() => {}
+                    ScriptLine::Remark(text) => {
+                        let current_block = blocks.last().unwrap();
+                        remarks.push(Remark {
+                            text: text.clone(),
+                            position: Position {
+                                block: current_block.index,
+                                instruction: current_block.instructions.len(),
+                            },
+                            placement: RemarkPlacement::OwnLine,
+                            line: i + 1,
+                        });
+                    }
+                    ScriptLine::Instruction {
+                        column,
+                        text,
+                        parsed,
+                        remark,
+                    } => match (parsed, origin) {
+                        (Ok(instruction), _) => {
+                            // An instruction of a macro or an included file points at the line bringing it
+                            let (column, text) = expansion.unwrap_or((*column, text));
+                            let current_block = blocks.last_mut().unwrap();
+                            current_block.instructions.push(instruction.clone());
+                            current_block.spans.push(Span {
+                                line: i + 1,
+                                column,
+                                text: text.clone(),
+                            });
+                            if let (Some(remark), None) = (remark, origin) {
+                                remarks.push(Remark {
+                                    text: remark.clone(),
+                                    position: Position {
+                                        block: current_block.index,
+                                        instruction: current_block.instructions.len() - 1,
+                                    },
+                                    placement: RemarkPlacement::AfterInstruction,
+                                    line: i + 1,
+                                });
+                            }
+                        }
+                        (Err(error), Some(origin)) => match &origin.file {
+                            Some(file) => {
+                                errors.push(ParseScriptObjectError::InvalidIncludedInstruction {
+                                    line: i + 1,
+                                    file: file.clone(),
+                                    file_line: origin.line,
+                                    instruction: text.clone(),
+                                    error: error.clone(),
+                                })
+                            }
+                            None => errors.push(ParseScriptObjectError::InvalidExpansion {
+                                line: i + 1,
+                                name: origin.macro_name.clone().unwrap_or_default(),
+                                macro_line: origin.line,
+                                instruction: text.clone(),
+                                error: error.clone(),
+                            }),
+                        },
+                        (Err(error), None) => {
+                            errors.push(ParseScriptObjectError::InvalidInstruction {
+                                line: i + 1,
+                                column: *column,
+                                instruction: text.clone(),
+                                error: error.clone(),
+                            })
+                        }
+                    },
+                    // The drawings can't be defined in a macro or an included file
+                    ScriptLine::Definitions if origin.is_some() => {}
+                    ScriptLine::Definitions => {
+                        // Enter comment/label definition zone, until the end of the script
+                        match parse_definitions(lines.iter().copied().enumerate().skip(i)) {
+                            Ok(parsed) => definitions = parsed,
+                            Err(e) => errors.push(e),
+                        }
+                        break 'lines;
                     }
-                })?);
+                }
+            }
+        }
+
+        if errors.len() > 1 {
+            return Err(ParseScriptObjectError::Multiple(errors));
+        }
+        if let Some(error) = errors.pop() {
+            return Err(error);
         }
 
-        Ok(Self::new(blocks))
+        let (comment_drawings, tile_labels) = definitions;
+        Ok(Self {
+            comments,
+            comment_drawings,
+            tile_labels,
+            remarks,
+            directives,
+            ..Self::new(blocks)
+        })
     }
 }
 
@@ -194,22 +1542,38 @@ mod test {
         ";
         let script_object = ScriptObject::from_str(script).unwrap();
 
+        let span = |line, text: &str| Span {
+            line,
+            column: 13,
+            text: text.to_string(),
+        };
         let theorical_so = ScriptObject::new(vec![
             Block {
                 name: "entry".to_string(),
                 index: 0,
+                line: 0,
                 instructions: vec![],
+                spans: vec![],
             },
             Block {
                 name: "a".to_string(),
                 index: 1,
+                line: 3,
                 instructions: vec![
                     Instruction::In,
                     Instruction::CopyTo(value_box::ValueBoxMemoryAddress::Pointer(0)),
                     Instruction::In,
                     Instruction::Add(value_box::ValueBoxMemoryAddress::Pointer(0)),
                     Instruction::Out,
-                    Instruction::Jump("a".to_string()),
+                    Instruction::Jump("a".into()),
+                ],
+                spans: vec![
+                    span(4, "INBOX"),
+                    span(5, "COPYTO   0"),
+                    span(6, "INBOX"),
+                    span(7, "ADD      0"),
+                    span(8, "OUTBOX"),
+                    span(9, "JUMP     a"),
                 ],
             },
         ]);
@@ -234,6 +1598,114 @@ mod test {
         assert!(script_object.all_jumps_have_valid_anchors());
     }
 
+    #[test]
+    fn test_script_display() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX   
+        a:
+          COMMENT 0
+         COPYTO 3
+        b:
+        c:
+            JUMPZ b
+            COMMENT  1
+
+        DEFINE COMMENT 0
+        eJxjYWBgSGE4waDDOIERyGT4whjBBAAd3QM+;
+        ";
+        let expected = "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+    INBOX
+
+a:
+    COMMENT  0
+    COPYTO   3
+
+b:
+
+c:
+    JUMPZ    b
+    COMMENT  1
+
+DEFINE COMMENT 0
+eJxjYWBgSGE4waDDOIERyGT4whjBBAAd3QM+;
+";
+        let script_object = ScriptObject::from_str(script).unwrap();
+
+        assert_eq!(script_object.to_string(), expected);
+        // Formatting is stable
+        let formatted = ScriptObject::from_str(expected).unwrap();
+        assert_eq!(formatted.to_string(), expected);
+    }
+
+    #[test]
+    fn test_samples_are_formatted() {
+        let samples = [
+            include_str!("../samples/01-MailRoom.hrm"),
+            include_str!("../samples/30-StringStorageFloor.hrm"),
+            include_str!("../samples/41-SortingRoom.hrm"),
+        ];
+        for sample in samples {
+            let script_object = ScriptObject::from_str(sample).unwrap();
+            let formatted = ScriptObject::from_str(&script_object.to_string()).unwrap();
+            fn instructions(script: &ScriptObject) -> Vec<(&str, &[Instruction])> {
+                script
+                    .blocks
+                    .iter()
+                    .map(|block| (block.name(), block.instructions.as_slice()))
+                    .collect()
+            }
+
+            assert_eq!(instructions(&formatted), instructions(&script_object));
+            assert_eq!(formatted.to_string(), script_object.to_string());
+        }
+    }
+
+    #[test]
+    fn test_script_serialization() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            COPYTO   [3]
+            JUMPZ    a
+        ";
+        let script_object = ScriptObject::from_str(script).unwrap();
+
+        let json = serde_json::to_string(&script_object).unwrap();
+        let deserialized = serde_json::from_str::<ScriptObject>(&json).unwrap();
+
+        assert_eq!(deserialized, script_object);
+        assert_eq!(
+            deserialized.blocks[1].instructions[2]
+                .jump_target()
+                .unwrap()
+                .block(),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_script_jumps_are_resolved() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            JUMP     b
+        a:
+            JUMPZ    a
+        b:
+            JUMPN    z
+        ";
+        let script_object = ScriptObject::from_str(script).unwrap();
+        let target = |block: usize| {
+            script_object.blocks[block].instructions[0]
+                .jump_target()
+                .unwrap()
+                .block()
+        };
+
+        assert_eq!(target(0), Some(2));
+        assert_eq!(target(1), Some(1));
+        assert_eq!(target(2), None);
+    }
+
     #[test]
     fn test_script_invalid_anchors() {
         let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
@@ -252,6 +1724,101 @@ mod test {
         assert!(!script_object.all_jumps_have_valid_anchors());
     }
 
+    #[test]
+    fn test_script_invalid_call() {
+        let options = instruction::ParseOptions {
+            extensions: true,
+            ..Default::default()
+        };
+        let valid = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            CALL     f
+        f:
+            RET
+        ";
+        let script_object = ScriptObject::parse_with_options(valid, &options).unwrap();
+        assert!(script_object.validate().is_ok());
+
+        let script_object =
+            ScriptObject::parse_with_options(&valid.replace("f:", "g:"), &options).unwrap();
+        assert!(matches!(
+            script_object.validate(),
+            Err(ScriptObjectValidationError::InvalidJumps)
+        ));
+    }
+
+    #[test]
+    fn test_script_duplicate_label() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+        b:
+            OUTBOX
+        a:
+            JUMP     b
+        ";
+        let script_object = ScriptObject::from_str(script).unwrap();
+
+        match script_object.validate() {
+            Err(ScriptObjectValidationError::DuplicateLabel {
+                name,
+                first_line,
+                second_line,
+            }) => {
+                assert_eq!(name, "a");
+                assert_eq!(first_line, 2);
+                assert_eq!(second_line, 6);
+            }
+            result => panic!("Expected a duplicate label error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_script_too_many_instructions() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            OUTBOX
+            JUMP     a
+        ";
+        let script_object = ScriptObject::from_str(script).unwrap();
+
+        assert!(script_object.validate_size(3).is_ok());
+        let error = script_object.validate_size(2).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The script has 3 instructions, more than the limit of 2"
+        );
+        let diagnostics = error.diagnostics(&script_object);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 5);
+    }
+
+    #[test]
+    fn test_script_address_out_of_range() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            COPYTO   99
+            COPYFROM [15]
+            ADD      [16]
+            OUTBOX
+        ";
+        let script_object = ScriptObject::from_str(script).unwrap();
+
+        assert!(script_object.validate_addresses(99).is_ok());
+        let error = script_object.validate_addresses(15).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Tiles past the end of the floor (max address 15) are used on lines 3, 5"
+        );
+        let diagnostics = error.diagnostics(&script_object);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0].message,
+            "tile 99 is past the end of the floor (max address 15)"
+        );
+        assert_eq!(diagnostics[1].line, 5);
+    }
+
     #[test]
     fn test_script_empty_block() {
         let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
@@ -301,4 +1868,529 @@ mod test {
             None
         );
     }
+
+    #[test]
+    fn test_script_next_instruction_position() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+        a:
+        b:
+            COPYTO   0
+            JUMP     a
+        c:
+            JUMPN    b
+        
+        ";
+        let script_object = ScriptObject::from_str(script).unwrap();
+
+        assert_eq!(
+            script_object.next_instruction_position(Position::default()),
+            Some(Position {
+                block: 2,
+                instruction: 0
+            })
+        );
+        assert_eq!(
+            script_object.next_instruction_position(Position {
+                block: 2,
+                instruction: 2
+            }),
+            Some(Position {
+                block: 3,
+                instruction: 0
+            })
+        );
+        assert_eq!(
+            script_object.next_instruction_position(script_object.end_position()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_script_find_position_of_line() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+        a:
+        b:
+            COPYTO   0
+            JUMP     a
+        c:
+            JUMPN    b
+        
+        ";
+        let script_object = ScriptObject::from_str(script).unwrap();
+
+        let position = script_object.find_position_of_line(6).unwrap();
+        assert_eq!(
+            position,
+            Position {
+                block: 2,
+                instruction: 1
+            }
+        );
+        assert_eq!(script_object.get_line(position), Some(6));
+        // Line of a label: first instruction after it
+        assert_eq!(
+            script_object.find_position_of_line(7),
+            Some(Position {
+                block: 3,
+                instruction: 0
+            })
+        );
+        assert_eq!(script_object.find_position_of_line(9), None);
+    }
+
+    #[test]
+    fn test_script_with_definitions() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+    COMMENT  0
+a:
+    INBOX   
+    COMMENT  1
+    COPYTO   3
+    JUMP     a
+
+
+DEFINE COMMENT 0
+eJxjYWBgSGE4waDDOIERyGT4whjBBAAd3QM+;
+
+DEFINE COMMENT 1
+eJxjYWBgSGE4waDDOIERyGT4whjBBAAd3QM+;
+
+DEFINE LABEL 3
+eJxjYWBgSGE4waDD
+OIERyGT4whjBBAAd3QM+;
+
+";
+        let script_object = ScriptObject::from_str(script).unwrap();
+
+        assert_eq!(
+            script_object.comments(),
+            &[
+                Comment {
+                    id: 0,
+                    position: Position {
+                        block: 0,
+                        instruction: 0
+                    },
+                    line: 3,
+                },
+                Comment {
+                    id: 1,
+                    position: Position {
+                        block: 1,
+                        instruction: 1
+                    },
+                    line: 6,
+                },
+            ]
+        );
+        assert_eq!(script_object.comment_drawings().len(), 2);
+        let label = script_object.tile_labels().get(&3).unwrap();
+        assert_eq!(label.payload(), "eJxjYWBgSGE4waDDOIERyGT4whjBBAAd3QM+");
+        assert!(label.decode().is_ok());
+        assert_eq!(
+            script_object
+                .get_block_by_label("a")
+                .unwrap()
+                .instructions
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_script_with_invalid_definitions() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+    INBOX
+
+DEFINE LABEL zero
+eJxjYWBgSGE4waDDOIERyGT4whjBBAAd3QM+;
+";
+        assert!(matches!(
+            ScriptObject::from_str(script),
+            Err(ParseScriptObjectError::InvalidDefinition { line: 4, .. })
+        ));
+
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+    INBOX
+
+DEFINE COMMENT 0
+eJxjYWBgSGE4waDDOIERyGT4whjBBAAd3QM+
+";
+        assert!(matches!(
+            ScriptObject::from_str(script),
+            Err(ParseScriptObjectError::InvalidDefinition { line: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn test_script_with_macros() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+#define OUT_TWICE(tile)
+    COPYFROM tile
+    OUTBOX
+    OUTBOX
+#end
+a:
+    INBOX
+    COPYTO   0
+    OUT_TWICE([1])
+    JUMP     a
+";
+        let script_object = ScriptObject::from_str(script).unwrap();
+        // The macros are kept in the formatted script, and expanded in the exported one
+        assert_eq!(
+            script_object.to_string(),
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+#define OUT_TWICE(tile)
+    COPYFROM tile
+    OUTBOX
+    OUTBOX
+#end
+
+a:
+    INBOX
+    COPYTO   0
+    OUT_TWICE([1])
+    JUMP     a
+"
+        );
+        assert_eq!(
+            game::to_clipboard(&script_object).unwrap(),
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+a:
+    INBOX
+    COPYTO   0
+    COPYFROM [1]
+    OUTBOX
+    OUTBOX
+    JUMP     a
+"
+        );
+
+        // The instructions of the macro point at its use
+        let block = script_object.get_block_by_label("a").unwrap();
+        assert_eq!(block.line(), 7);
+        assert_eq!(
+            block.span_of(3),
+            Some(&Span {
+                line: 10,
+                column: 5,
+                text: "OUT_TWICE([1])".to_string()
+            })
+        );
+        assert_eq!(block.line_of(5), Some(11));
+
+        // An invalid instruction of the body is shown in the macro, for its use
+        let error = ScriptObject::from_str(&script.replace("[1]", "[x]")).unwrap_err();
+        assert!(matches!(
+            error,
+            ParseScriptObjectError::InvalidExpansion {
+                line: 10,
+                macro_line: 3,
+                ..
+            }
+        ));
+        let diagnostics = error.diagnostics();
+        assert_eq!(diagnostics[0].line, 3);
+        assert_eq!(
+            diagnostics[0].notes.last().unwrap(),
+            "in the expansion of OUT_TWICE on line 10"
+        );
+
+        let error = ScriptObject::from_str(&script.replace("([1])", "()")).unwrap_err();
+        assert_eq!(error.code(), "invalid-macro");
+
+        // A remark can follow the use of a macro, and the lines of its body
+        let remarks = script
+            .replace("OUT_TWICE([1])", "OUT_TWICE([1]) ; twice")
+            .replace(
+                "#define OUT_TWICE(tile)",
+                "#define OUT_TWICE(tile)\n    OUT_ONCE()  # once",
+            )
+            .replace("#end", "#end\n#define OUT_ONCE()\n    OUTBOX\n#end");
+        let script_object = ScriptObject::from_str(&remarks).unwrap();
+        assert_eq!(script_object.instruction_count(), 7);
+        assert!(script_object
+            .to_string()
+            .contains("\n    OUT_TWICE([1])   ; twice\n"));
+        let formatted = script_object.to_string();
+        assert_eq!(
+            ScriptObject::from_str(&formatted).unwrap().to_string(),
+            formatted
+        );
+    }
+
+    #[test]
+    fn test_script_with_includes() {
+        let dir = std::env::temp_dir().join("hrm-script-includes");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("common.hrm"),
+            "#define OUT_TWICE()\n    OUTBOX\n    OUTBOX\n#end\n-- Routines\nloop:\n    INBOX\n",
+        )
+        .unwrap();
+        let options = ParseOptions {
+            include_dir: Some(dir.clone()),
+            ..Default::default()
+        };
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+#include \"common.hrm\"
+    OUT_TWICE()
+    JUMP     loop
+";
+        let script_object = ScriptObject::parse_with_options(script, &options).unwrap();
+        let block = script_object.get_block_by_label("loop").unwrap();
+        assert_eq!(block.line(), 2);
+        assert_eq!(
+            block.instructions,
+            [
+                Instruction::In,
+                Instruction::Out,
+                Instruction::Out,
+                Instruction::Jump("loop".into())
+            ]
+        );
+        assert_eq!(block.span_of(0).unwrap().text, "#include \"common.hrm\"");
+        assert_eq!(block.line_of(1), Some(3));
+
+        // The "#include" is kept in the formatted script, and the file inlined in the exported one
+        assert_eq!(
+            script_object.to_string(),
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+#include \"common.hrm\"
+    OUT_TWICE()
+    JUMP     loop
+"
+        );
+        assert_eq!(
+            game::to_clipboard(&script_object).unwrap(),
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+loop:
+    INBOX
+    OUTBOX
+    OUTBOX
+    JUMP     loop
+"
+        );
+
+        // An invalid instruction of the included file is shown on the "#include"
+        std::fs::write(dir.join("invalid.hrm"), "\n    OUTBOXX\n").unwrap();
+        let error =
+            ScriptObject::parse_with_options("#include \"invalid.hrm\"", &options).unwrap_err();
+        assert!(matches!(
+            error,
+            ParseScriptObjectError::InvalidIncludedInstruction {
+                line: 1,
+                file_line: 2,
+                ..
+            }
+        ));
+        assert_eq!(error.diagnostics()[0].line, 1);
+
+        // Without the directory of the script, nothing is included
+        assert_eq!(
+            ScriptObject::from_str(script).unwrap_err().code(),
+            "invalid-include"
+        );
+    }
+
+    #[test]
+    fn test_script_with_constants() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+    COPYFROM 3
+#const ZERO 24
+#const PTR  5
+    COPYFROM ZERO
+    BUMPUP   [PTR]
+";
+        let script_object = ScriptObject::from_str(script).unwrap();
+        assert_eq!(
+            script_object.blocks()[0].instructions,
+            [
+                Instruction::CopyFrom(value_box::ValueBoxMemoryAddress::Pointer(3)),
+                Instruction::CopyFrom(value_box::ValueBoxMemoryAddress::Pointer(24)),
+                Instruction::BumpUp(value_box::ValueBoxMemoryAddress::PointerAddress(5)),
+            ]
+        );
+        assert_eq!(script_object.blocks()[0].line_of(1), Some(5));
+
+        // The constants are kept in the formatted script, and resolved in the exported one
+        assert_eq!(
+            script_object.to_string(),
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+    COPYFROM 3
+#const ZERO 24
+#const PTR  5
+    COPYFROM ZERO
+    BUMPUP   [PTR]
+"
+        );
+        assert!(game::to_clipboard(&script_object)
+            .unwrap()
+            .ends_with("    COPYFROM 24\n    BUMPUP   [5]\n"));
+
+        // A constant is only known after its line
+        let script = script.replace("COPYFROM 3", "COPYFROM ZERO");
+        assert!(ScriptObject::from_str(&script).is_err());
+
+        for constant in ["#const ZERO", "#const 0 24", "#const ZERO -1"] {
+            let script = format!("    INBOX\n{}\n", constant);
+            assert!(matches!(
+                ScriptObject::from_str(&script),
+                Err(ParseScriptObjectError::InvalidConstant { line: 2, .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_script_with_remarks() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+# Sums the inputs
+a: ; loop
+    INBOX
+    ADD      0       ; accumulate
+    DEBUG    \"a; b\"
+    COMMENT  0
+    ; before the jump
+    JUMP     a
+";
+        let options = ParseOptions {
+            extensions: true,
+            ..Default::default()
+        };
+        let script_object = ScriptObject::parse_with_options(script, &options).unwrap();
+        let block = script_object.get_block_by_label("a").unwrap();
+        assert_eq!(
+            block.instructions[1],
+            Instruction::Add(value_box::ValueBoxMemoryAddress::Pointer(0))
+        );
+        assert_eq!(block.span_of(1).unwrap().text, "ADD      0");
+        assert_eq!(
+            script_object
+                .remarks()
+                .iter()
+                .map(|remark| (remark.text.as_str(), remark.position, remark.placement))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    "# Sums the inputs",
+                    Position::default(),
+                    RemarkPlacement::OwnLine
+                ),
+                (
+                    "; loop",
+                    Position {
+                        block: 1,
+                        instruction: 0
+                    },
+                    RemarkPlacement::AfterLabel
+                ),
+                (
+                    "; accumulate",
+                    Position {
+                        block: 1,
+                        instruction: 1
+                    },
+                    RemarkPlacement::AfterInstruction
+                ),
+                (
+                    "; before the jump",
+                    Position {
+                        block: 1,
+                        instruction: 3
+                    },
+                    RemarkPlacement::OwnLine
+                ),
+            ]
+        );
+
+        // The formatter keeps them where they are
+        let formatted = script_object.to_string();
+        assert_eq!(
+            formatted,
+            script.replace("\n# Sums the inputs\n", "\n    # Sums the inputs\n\n")
+        );
+        assert_eq!(
+            ScriptObject::parse_with_options(&formatted, &options)
+                .unwrap()
+                .to_string(),
+            formatted
+        );
+    }
+
+    #[test]
+    fn test_script_with_test_annotations() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+-- TEST: inputs = 1 2 ; expected = 1 2
+a:
+    INBOX
+    -- TEST: name = last ; expected =
+    OUTBOX
+    JUMP     a
+";
+        let script_object = script.parse::<ScriptObject>().unwrap();
+        assert_eq!(
+            script_object.remarks()[0].text,
+            "-- TEST: inputs = 1 2 ; expected = 1 2"
+        );
+        assert_eq!(
+            script_object.to_string(),
+            script.replace("    -- TEST", "-- TEST").replace(
+                "\n-- TEST: inputs = 1 2 ; expected = 1 2\n",
+                "\n\n-- TEST: inputs = 1 2 ; expected = 1 2\n\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_script_with_several_invalid_instructions() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            COPYTO
+            OUTBOX
+            JUMPY    a
+        ";
+        match ScriptObject::from_str(script) {
+            Err(ParseScriptObjectError::Multiple(errors)) => {
+                let lines = errors
+                    .iter()
+                    .map(|error| match error {
+                        ParseScriptObjectError::InvalidInstruction { line, .. } => *line,
+                        error => panic!("Unexpected error: {}", error),
+                    })
+                    .collect::<Vec<_>>();
+                assert_eq!(lines, vec![3, 5]);
+            }
+            result => panic!("Expected several errors, got {:?}", result),
+        }
+
+        // Each error points at its line of the script
+        let diagnostics = ScriptObject::from_str(script).unwrap_err().diagnostics();
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.line, diagnostic.range.clone()))
+                .collect::<Vec<_>>(),
+            vec![(3, Some(12..18)), (5, Some(12..17))]
+        );
+
+        // A single error is reported as is
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            COPYTO
+        ";
+        assert!(matches!(
+            ScriptObject::from_str(script),
+            Err(ParseScriptObjectError::InvalidInstruction { line: 3, .. })
+        ));
+    }
 }
@@ -0,0 +1,408 @@
+//! Decompile a script to structured pseudocode, to read a solution without following the jumps.
+//!
+//! The instructions jumped back to start a `loop`, and the conditional jumps over instructions
+//! become an `if` (with an `else` when the skipped instructions end with a jump over the next
+//! ones). Inside a loop, the jumps to its beginning and after its end are `continue` and `break`,
+//! and the jumps to the end of the script are `halt`. The other jumps are kept as `goto`, with
+//! the labels of the script.
+
+use std::collections::{BTreeSet, HashMap};
+
+use super::{instruction::Instruction, value_box::ValueBoxMemoryAddress, ScriptObject};
+use crate::interpreter::bytecode::Bytecode;
+
+/// A statement of the pseudocode, at the index of its first instruction
+enum Statement {
+    /// An instruction, or nothing for a jump that the structure makes useless
+    Line {
+        index: usize,
+        text: String,
+    },
+    If {
+        index: usize,
+        condition: String,
+        then: Vec<Statement>,
+        otherwise: Vec<Statement>,
+    },
+    Loop {
+        index: usize,
+        body: Vec<Statement>,
+    },
+}
+
+impl Statement {
+    fn index(&self) -> usize {
+        match self {
+            Statement::Line { index, .. }
+            | Statement::If { index, .. }
+            | Statement::Loop { index, .. } => *index,
+        }
+    }
+}
+
+/// The tile of the instruction, as an element of the floor
+fn tile(address: &ValueBoxMemoryAddress) -> String {
+    match address {
+        ValueBoxMemoryAddress::Pointer(tile) => format!("floor[{}]", tile),
+        ValueBoxMemoryAddress::PointerAddress(tile) => format!("floor[floor[{}]]", tile),
+    }
+}
+
+/// The condition of a conditional jump, and its negation
+fn condition(instruction: &Instruction) -> Option<(String, String)> {
+    let (condition, negation) = match instruction {
+        Instruction::JumpIfZero(_) => ("hands == 0", "hands != 0"),
+        Instruction::JumpIfNegative(_) => ("hands < 0", "hands >= 0"),
+        Instruction::JumpIfPositive(_) => ("hands > 0", "hands <= 0"),
+        Instruction::JumpIfEqual(address, _) => {
+            let tile = tile(address);
+            return Some((format!("hands == {}", tile), format!("hands != {}", tile)));
+        }
+        _ => return None,
+    };
+    Some((condition.to_string(), negation.to_string()))
+}
+
+/// The statement of an instruction that doesn't jump
+fn statement(instruction: &Instruction) -> String {
+    let tile = instruction.memory_address().map(tile).unwrap_or_default();
+    match instruction {
+        Instruction::In => "hands = inbox()".to_string(),
+        Instruction::Out => "outbox(hands)".to_string(),
+        Instruction::CopyFrom(_) => format!("hands = {}", tile),
+        Instruction::CopyTo(_) => format!("{} = hands", tile),
+        Instruction::Add(_) => format!("hands += {}", tile),
+        Instruction::Sub(_) => format!("hands -= {}", tile),
+        Instruction::Mul(_) => format!("hands *= {}", tile),
+        Instruction::Div(_) => format!("hands /= {}", tile),
+        Instruction::Mod(_) => format!("hands %= {}", tile),
+        Instruction::BumpUp(_) => format!("hands = ++{}", tile),
+        Instruction::BumpDown(_) => format!("hands = --{}", tile),
+        Instruction::Swap(_) => format!("swap(hands, {})", tile),
+        Instruction::Push => "push(hands)".to_string(),
+        Instruction::Pop => "hands = pop()".to_string(),
+        Instruction::TempCopyTo => "second = hands".to_string(),
+        Instruction::TempCopyFrom => "hands = second".to_string(),
+        Instruction::Exchange => "swap(hands, second)".to_string(),
+        Instruction::Ret => "return".to_string(),
+        Instruction::Halt => "halt".to_string(),
+        Instruction::Nop => String::new(),
+        _ => instruction.to_string(),
+    }
+}
+
+struct Decompiler<'a> {
+    bytecode: &'a Bytecode,
+    /// The labels of the instructions jumped to, that can't be left out
+    labels: HashMap<usize, String>,
+    /// The innermost loop being decompiled: its first instruction, and the one after it
+    loops: Vec<(usize, usize)>,
+    /// The instructions that need a label, for a goto or a call
+    labelled: BTreeSet<usize>,
+}
+
+impl Decompiler<'_> {
+    /// The statement of a jump that the structure gives, if any: a continue, a break, a halt
+    fn structured_jump(&self, target: usize) -> Option<&'static str> {
+        match self.loops.last() {
+            Some((start, _)) if *start == target => Some("continue"),
+            Some((_, end)) if *end == target => Some("break"),
+            _ if target == self.bytecode.ops.len() => Some("halt"),
+            _ => None,
+        }
+    }
+
+    /// The jump to the target, as a structured jump or a goto
+    fn jump(&mut self, target: usize) -> String {
+        match self.structured_jump(target) {
+            Some(jump) => jump.to_string(),
+            None => {
+                self.labelled.insert(target);
+                format!("goto {}", self.labels[&target])
+            }
+        }
+    }
+
+    /// Whether an instruction out of `start..end` jumps to the target
+    fn jumped_from_outside(&self, target: usize, start: usize, end: usize) -> bool {
+        self.bytecode
+            .ops
+            .iter()
+            .enumerate()
+            .any(|(index, op)| op.jump == Some(target) && !(start..end).contains(&index))
+    }
+
+    /// Decompile the instructions from `start` to `end` (excluded)
+    fn statements(&mut self, start: usize, end: usize) -> Vec<Statement> {
+        let mut statements = vec![];
+        let mut index = start;
+        while index < end {
+            // A loop starts at the instruction if a later one jumps back to it
+            let in_loop = self.loops.last().is_some_and(|(start, _)| *start == index);
+            let last = (index..end)
+                .rev()
+                .find(|last| self.bytecode.ops[*last].jump == Some(index));
+            if let (Some(last), false) = (last, in_loop) {
+                self.loops.push((index, last + 1));
+                let body = self.statements(index, last + 1);
+                self.loops.pop();
+                statements.push(Statement::Loop { index, body });
+                index = last + 1;
+                continue;
+            }
+
+            let op = &self.bytecode.ops[index];
+            let instruction = &op.instruction;
+            let target = op.jump.unwrap_or(self.bytecode.ops.len());
+            match (instruction, condition(instruction)) {
+                (Instruction::Jump(_), _) => {
+                    // The end of a loop goes back to its beginning, and a jump to the next
+                    // instruction does nothing
+                    let implicit = self.loops.last() == Some(&(target, index + 1));
+                    let text = if implicit || target == index + 1 {
+                        String::new()
+                    } else {
+                        self.jump(target)
+                    };
+                    statements.push(Statement::Line { index, text });
+                    index += 1;
+                }
+                (_, Some((condition, negation))) => {
+                    // The last instruction of a loop leaves it unless it jumps back
+                    if self.loops.last() == Some(&(target, index + 1)) {
+                        statements.push(Statement::Line {
+                            index,
+                            text: format!("if {} {{ break }}", negation),
+                        });
+                        index += 1;
+                    } else if let Some(jump) = self.structured_jump(target).filter(|jump| {
+                        // Going to the end of the script is also going past the skipped instructions
+                        *jump != "halt" || target <= index || target > end
+                    }) {
+                        statements.push(Statement::Line {
+                            index,
+                            text: format!("if {} {{ {} }}", condition, jump),
+                        });
+                        index += 1;
+                    } else if index < target && target <= end {
+                        // The skipped instructions end with a jump over the next ones: an else
+                        let over = self.bytecode.ops[target - 1].jump.filter(|after| {
+                            target - 1 > index
+                                && matches!(
+                                    self.bytecode.ops[target - 1].instruction,
+                                    Instruction::Jump(_)
+                                )
+                                && !self.jumped_from_outside(target - 1, index + 1, target - 1)
+                                && target < *after
+                                && *after <= end
+                                // Leaving a loop is a break, not the end of the else
+                                && self.loops.last().map(|(_, end)| end) != Some(after)
+                        });
+                        let (then, otherwise, next) = match over {
+                            Some(after) => {
+                                let mut then = self.statements(index + 1, target - 1);
+                                then.push(Statement::Line {
+                                    index: target - 1,
+                                    text: String::new(),
+                                });
+                                (then, self.statements(target, after), after)
+                            }
+                            None => (self.statements(index + 1, target), vec![], target),
+                        };
+                        // Nothing to do when the condition is false: only an if
+                        let (condition, then, otherwise) = if is_empty(&then) {
+                            (condition, otherwise, vec![])
+                        } else {
+                            (negation, then, otherwise)
+                        };
+                        statements.push(Statement::If {
+                            index,
+                            condition,
+                            then,
+                            otherwise,
+                        });
+                        index = next;
+                    } else {
+                        let jump = self.jump(target);
+                        statements.push(Statement::Line {
+                            index,
+                            text: format!("if {} {{ {} }}", condition, jump),
+                        });
+                        index += 1;
+                    }
+                }
+                (Instruction::Call(_), _) => {
+                    self.labelled.insert(target);
+                    statements.push(Statement::Line {
+                        index,
+                        text: format!("call {}", self.labels[&target]),
+                    });
+                    index += 1;
+                }
+                _ => {
+                    statements.push(Statement::Line {
+                        index,
+                        text: statement(instruction),
+                    });
+                    index += 1;
+                }
+            }
+        }
+        statements
+    }
+}
+
+/// Whether the statements do nothing
+fn is_empty(statements: &[Statement]) -> bool {
+    statements
+        .iter()
+        .all(|statement| matches!(statement, Statement::Line { text, .. } if text.is_empty()))
+}
+
+/// Write the statements at the depth, with a label before the ones that are jumped to
+fn write(
+    statements: &[Statement],
+    depth: usize,
+    labels: &HashMap<usize, String>,
+    labelled: &BTreeSet<usize>,
+    code: &mut String,
+) {
+    let indent = "    ".repeat(depth);
+    for statement in statements {
+        if labelled.contains(&statement.index()) {
+            code.push_str(&format!("{}{}:\n", indent, labels[&statement.index()]));
+        }
+        match statement {
+            Statement::Line { text, .. } if text.is_empty() => {}
+            Statement::Line { text, .. } => {
+                code.push_str(&format!("{}{}\n", indent, text));
+            }
+            Statement::If {
+                condition,
+                then,
+                otherwise,
+                ..
+            } => {
+                code.push_str(&format!("{}if {} {{\n", indent, condition));
+                write(then, depth + 1, labels, labelled, code);
+                if !otherwise.is_empty() {
+                    code.push_str(&format!("{}}} else {{\n", indent));
+                    write(otherwise, depth + 1, labels, labelled, code);
+                }
+                code.push_str(&format!("{}}}\n", indent));
+            }
+            Statement::Loop { body, .. } => {
+                code.push_str(&format!("{}loop {{\n", indent));
+                write(body, depth + 1, labels, labelled, code);
+                code.push_str(&format!("{}}}\n", indent));
+            }
+        }
+    }
+}
+
+/// The script as structured pseudocode
+pub fn decompile(script: &ScriptObject) -> String {
+    let bytecode = script.bytecode();
+    // The labels of the script, for the gotos
+    let mut labels = HashMap::new();
+    for op in &bytecode.ops {
+        if let (Some(target), Some(jump_target)) = (op.jump, op.instruction.jump_target()) {
+            labels
+                .entry(target)
+                .or_insert_with(|| jump_target.label().to_string());
+        }
+    }
+    let mut decompiler = Decompiler {
+        bytecode,
+        labels,
+        loops: vec![],
+        labelled: BTreeSet::new(),
+    };
+    let statements = decompiler.statements(0, decompiler.bytecode.ops.len());
+    let mut code = String::new();
+    write(
+        &statements,
+        0,
+        &decompiler.labels,
+        &decompiler.labelled,
+        &mut code,
+    );
+    code
+}
+
+#[cfg(test)]
+mod decompile_tests {
+    use super::*;
+
+    fn decompile_str(script: &str) -> String {
+        decompile(&script.parse::<ScriptObject>().unwrap())
+    }
+
+    #[test]
+    fn test_loop() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            JUMPZ    a
+            OUTBOX
+            JUMP     a
+        ";
+        assert_eq!(
+            decompile_str(script),
+            "loop {
+    hands = inbox()
+    if hands == 0 { continue }
+    outbox(hands)
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_if_else() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            JUMPN    a
+            COPYTO   0
+            JUMP     b
+        a:
+            COPYFROM [1]
+        b:
+            OUTBOX
+        ";
+        assert_eq!(
+            decompile_str(script),
+            "hands = inbox()
+if hands >= 0 {
+    floor[0] = hands
+} else {
+    hands = floor[floor[1]]
+}
+outbox(hands)
+"
+        );
+    }
+
+    #[test]
+    fn test_goto() {
+        // The jump into the loop can't be structured
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            JUMP     b
+        a:
+            INBOX
+        b:
+            OUTBOX
+            JUMP     a
+        ";
+        assert_eq!(
+            decompile_str(script),
+            "goto b
+loop {
+    hands = inbox()
+    b:
+    outbox(hands)
+}
+"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! A script being edited, as in an editor.
+//! Each line is parsed once: an edit only parses the lines it touches again,
+//! before the lines are assembled into a [`ScriptObject`].
+
+use super::{
+    instruction::ParseOptions, is_directive, parse_line, ParseScriptObjectError, ScriptLine,
+    ScriptObject,
+};
+
+#[derive(Debug, Clone)]
+pub struct Document {
+    lines: Vec<String>,
+    /// The parsed lines, one for each line of the text
+    parsed: Vec<ScriptLine>,
+}
+
+/// The byte index of the character at the given index in the line (the end of the line past its last character)
+fn byte_index(line: &str, character: usize) -> usize {
+    line.char_indices()
+        .nth(character)
+        .map_or(line.len(), |(i, _)| i)
+}
+
+impl Document {
+    pub fn new(text: &str) -> Self {
+        let lines = text.split('\n').map(str::to_string).collect::<Vec<_>>();
+        let parsed = lines
+            .iter()
+            .map(|line| parse_line(line, &ParseOptions::default()))
+            .collect();
+        Self { lines, parsed }
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Replace the text between two positions by the given text.
+    /// The positions are a line and a character in this line, both starting at 0.
+    pub fn edit(&mut self, start: (usize, usize), end: (usize, usize), text: &str) {
+        let last_line = self.lines.len() - 1;
+        let (start_line, end_line) = (start.0.min(last_line), end.0.min(last_line));
+        let before = &self.lines[start_line][..byte_index(&self.lines[start_line], start.1)];
+        let after = &self.lines[end_line][byte_index(&self.lines[end_line], end.1)..];
+        let edited = format!("{}{}{}", before, text, after)
+            .split('\n')
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+
+        // Only the edited lines are parsed again, the following ones just move
+        let parsed = edited
+            .iter()
+            .map(|line| parse_line(line, &ParseOptions::default()))
+            .collect::<Vec<_>>();
+        self.lines.splice(start_line..=end_line, edited);
+        self.parsed.splice(start_line..=end_line, parsed);
+    }
+
+    /// Parse the script, as [`ScriptObject::from_str`](std::str::FromStr) would
+    pub fn parse(&self) -> Result<ScriptObject, ParseScriptObjectError> {
+        // The directives change how the other lines are read: they can't be parsed on their own
+        if self.lines.iter().any(|line| is_directive(line)) {
+            return self.text().parse();
+        }
+        let lines = self.lines.iter().map(String::as_str).collect::<Vec<_>>();
+        ScriptObject::from_lines(&lines, &self.parsed)
+    }
+
+    /// The labels of the blocks, with the lines on which they are written (starting at 1).
+    /// They are found even if some lines of the script are invalid.
+    pub fn labels(&self) -> Vec<(usize, &str)> {
+        self.parsed
+            .iter()
+            .enumerate()
+            .take_while(|(_, line)| !matches!(line, ScriptLine::Definitions))
+            .filter_map(|(i, line)| match line {
+                ScriptLine::Label(name, _) => Some((i + 1, name.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod document_tests {
+    use super::*;
+
+    const SCRIPT: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+a:
+    INBOX
+    OUTBOX
+    JUMP     a
+";
+
+    #[test]
+    fn test_edits() {
+        let mut document = Document::new(SCRIPT);
+        assert_eq!(document.text(), SCRIPT);
+        assert_eq!(document.parse().unwrap(), SCRIPT.parse().unwrap());
+
+        // Insert lines, then replace a word
+        document.edit((3, 4), (3, 4), "COPYTO   0\n    ");
+        document.edit((5, 4), (5, 10), "COPYFROM 0");
+        let text = SCRIPT.replace("INBOX\n    OUTBOX", "COPYTO   0\n    INBOX\n    COPYFROM 0");
+        assert_eq!(document.text(), text);
+        let script = document.parse().unwrap();
+        assert_eq!(script, text.parse().unwrap());
+        assert_eq!(script.blocks()[1].span_of(3).unwrap().line, 7);
+
+        // Remove lines across a label
+        document.edit((1, 0), (3, 0), "");
+        assert_eq!(document.labels(), vec![]);
+        assert_eq!(document.parse().unwrap().blocks()[0].instructions.len(), 4);
+    }
+
+    #[test]
+    fn test_labels_of_an_invalid_script() {
+        let mut document = Document::new(SCRIPT);
+        document.edit((4, 4), (4, 10), "OUTBOXX");
+        document.edit((6, 0), (6, 0), "b:\n    JUMP     a\n");
+
+        assert!(document.parse().is_err());
+        assert_eq!(document.labels(), vec![(3, "a"), (7, "b")]);
+    }
+}
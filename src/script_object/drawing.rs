@@ -0,0 +1,140 @@
+use std::{fmt::Display, io::Read};
+
+use base64::Engine;
+use flate2::read::ZlibDecoder;
+use serde::{Deserialize, Serialize};
+
+/// Number of characters per line of a drawing payload, as exported by the game
+const PAYLOAD_LINE_WIDTH: usize = 80;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+/// A drawing made in the game, used for comments and memory tile labels.
+/// It's defined at the end of the script ("DEFINE COMMENT 0", "DEFINE LABEL 3")
+/// as a base64 payload ending with ";".
+///
+/// The payload is kept as is, so that the script can be written back exactly as the game exported it.
+pub struct Drawing {
+    payload: String,
+}
+
+/// A continuous line of the drawing, as a list of (x, y) points.
+pub type Stroke = Vec<(u16, u16)>;
+
+#[derive(Debug, thiserror::Error)]
+/// Error that can occur when decoding the payload of a drawing.
+pub enum DecodeDrawingError {
+    #[error("invalid base64 payload:\n\t{0}")]
+    InvalidBase64(#[from] base64::DecodeError),
+    #[error("invalid compressed data:\n\t{0}")]
+    InvalidCompressedData(#[from] std::io::Error),
+    #[error("the drawing should have {expected} points, but its data holds {actual}")]
+    InvalidPointCount { expected: usize, actual: usize },
+}
+
+impl Drawing {
+    /// Build a drawing from its payload. Whitespaces and the final ";" are ignored.
+    pub fn new(payload: &str) -> Self {
+        let payload = payload
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != ';')
+            .collect();
+        Self { payload }
+    }
+
+    pub fn payload(&self) -> &str {
+        &self.payload
+    }
+
+    /// Decode the strokes of the drawing.
+    ///
+    /// The payload is zlib-compressed data encoded in base64.
+    /// The data is the number of points (i32), followed by the points (2 x u16 each),
+    /// all in little endian. A (0, 0) point lifts the pen, starting a new stroke.
+    pub fn decode(&self) -> Result<Vec<Stroke>, DecodeDrawingError> {
+        let compressed = base64::engine::general_purpose::STANDARD.decode(&self.payload)?;
+        let mut data = Vec::new();
+        ZlibDecoder::new(compressed.as_slice()).read_to_end(&mut data)?;
+
+        let read_u16 = |bytes: &[u8]| u16::from_le_bytes([bytes[0], bytes[1]]);
+
+        let point_count = match data.get(0..4) {
+            Some(bytes) => i32::from_le_bytes(bytes.try_into().unwrap()).max(0) as usize,
+            None => 0,
+        };
+        let points = data
+            .get(4..)
+            .unwrap_or_default()
+            .chunks_exact(4)
+            .map(|point| (read_u16(&point[0..2]), read_u16(&point[2..4])))
+            .collect::<Vec<(u16, u16)>>();
+        if points.len() < point_count {
+            return Err(DecodeDrawingError::InvalidPointCount {
+                expected: point_count,
+                actual: points.len(),
+            });
+        }
+
+        let mut strokes: Vec<Stroke> = vec![];
+        let mut current_stroke = Stroke::new();
+        for point in points.into_iter().take(point_count) {
+            if point == (0, 0) {
+                strokes.push(std::mem::take(&mut current_stroke));
+            } else {
+                current_stroke.push(point);
+            }
+        }
+        strokes.push(current_stroke);
+        strokes.retain(|stroke| !stroke.is_empty());
+
+        Ok(strokes)
+    }
+}
+
+impl Display for Drawing {
+    /// Write the payload the way the game does: lines of 80 characters, ending with ";"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines = self
+            .payload
+            .as_bytes()
+            .chunks(PAYLOAD_LINE_WIDTH)
+            .map(|line| std::str::from_utf8(line).unwrap())
+            .collect::<Vec<&str>>();
+        write!(f, "{};", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod drawing_tests {
+    use super::*;
+
+    #[test]
+    fn test_drawing_decode() {
+        let drawing = Drawing::new("eJxjYWBgSGE4waDDOIERyGT4whjBBAAd3QM+;");
+
+        assert_eq!(
+            drawing.decode().unwrap(),
+            vec![vec![(100, 200), (300, 400)], vec![(500, 600)]]
+        );
+    }
+
+    #[test]
+    fn test_drawing_decode_invalid() {
+        assert!(Drawing::new("not base64!;").decode().is_err());
+        assert!(Drawing::new("aGVsbG8=;").decode().is_err());
+    }
+
+    #[test]
+    fn test_drawing_display() {
+        let payload =
+            "eJwB8AAP/zsAAABkAMgAyACQASwBWAKQASAD9AHoA1gCsAS8AngFIANABoQDCAfoA9AHTASYCLAEYAkU
+BSgKeAXwCtwFuAtABoAMpAZIDQgHEA5sB9gO0AegDzQIaBCYCDAR/Aj4EWAJwBLECYgTKApQFIwKGBXw
+CuAVVAuoFrgLcBccDDgYgAwAGeQMyBlIDZAarA1YGxAOIBx0Dugc2A6wHTwPeB6gD0AfBBAIIGgQ0CDM
+EJghMBFgIpQRKCP4EfAjXBK4JMASgCUkE0gmiBMQJ+wT2CdQFKAotBRoKRgVMCp8Ffgq4BXAK0QWiCyo
+FlAtDBcYLhBiQlQ=;";
+        let drawing = Drawing::new(payload);
+
+        assert_eq!(drawing.to_string(), payload);
+        assert_eq!(drawing.decode().unwrap()[0].len(), 59);
+    }
+}
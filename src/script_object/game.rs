@@ -0,0 +1,196 @@
+//! Exchanging scripts with the game: the solutions read from a save file,
+//! and the scripts written in the format that can be pasted in the game.
+//!
+//! The layout of the save files isn't documented, so the solution of a level is found with a
+//! heuristic. The save file is read as JSON (trailing commas are accepted), and the solution is
+//! the first script (a string starting with the header) found under the entry of the level:
+//! a member named after the number of the level, or an object whose `level` is that number.
+
+use std::fmt::Display;
+
+use serde_json::Value;
+
+use super::{Position, ScriptObject, HEADER};
+
+#[derive(Debug, thiserror::Error)]
+/// A save file from which the solution of a level can't be read
+pub enum SaveFileError {
+    #[error("the save file isn't valid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("the save file has no entry for level {0}")]
+    MissingLevel(usize),
+    #[error("the save file has no solution for level {0}")]
+    MissingSolution(usize),
+}
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+/// A script that can't be pasted in the game
+pub enum ExportError {
+    #[error("{instruction} on line {line} isn't an instruction of the game")]
+    Extension { instruction: String, line: usize },
+}
+
+/// Remove the commas before a closing bracket or brace, outside of the strings
+fn remove_trailing_commas(json: &str) -> String {
+    let mut result = String::with_capacity(json.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in json.char_indices() {
+        if in_string {
+            in_string = escaped || c != '"';
+            escaped = !escaped && c == '\\';
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let next = json[i + 1..].trim_start().chars().next();
+            if matches!(next, Some(']' | '}')) {
+                continue;
+            }
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Whether the JSON value is the entry of the level
+fn is_level(key: Option<&str>, value: &Value, level: usize) -> bool {
+    let number = |value: &Value| match value {
+        Value::Number(n) => n.as_u64() == Some(level as u64),
+        Value::String(s) => s.trim().parse::<usize>().ok() == Some(level),
+        _ => false,
+    };
+    key.is_some_and(|key| number(&Value::String(key.to_string())))
+        || value.get("level").is_some_and(number)
+}
+
+/// The entry of the level in the JSON value, depth first
+fn find_level<'a>(key: Option<&str>, value: &'a Value, level: usize) -> Option<&'a Value> {
+    if is_level(key, value, level) {
+        return Some(value);
+    }
+    match value {
+        Value::Object(members) => members
+            .iter()
+            .find_map(|(key, value)| find_level(Some(key), value, level)),
+        Value::Array(values) => values
+            .iter()
+            .find_map(|value| find_level(None, value, level)),
+        _ => None,
+    }
+}
+
+/// The first script in the JSON value, depth first
+fn find_script(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(s) if s.trim_start().starts_with(HEADER) => Some(s),
+        Value::Object(members) => members.values().find_map(find_script),
+        Value::Array(values) => values.iter().find_map(find_script),
+        _ => None,
+    }
+}
+
+/// The source of the solution of the level in the save file
+pub fn read_save_file(save_file: &str, level: usize) -> Result<String, SaveFileError> {
+    let save = serde_json::from_str::<Value>(save_file)
+        .or_else(|_| serde_json::from_str::<Value>(&remove_trailing_commas(save_file)))?;
+    let entry = find_level(None, &save, level).ok_or(SaveFileError::MissingLevel(level))?;
+    let script = find_script(entry).ok_or(SaveFileError::MissingSolution(level))?;
+    Ok(script.replace("\r\n", "\n"))
+}
+
+/// The script without the remarks, that the game doesn't know
+struct GameFormat<'a>(&'a ScriptObject);
+
+impl Display for GameFormat<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.write(f, false)
+    }
+}
+
+/// The script as it can be pasted in the game: only the instructions of the game,
+/// the comments and the drawings, with the macros expanded and the tiles as addresses
+pub fn to_clipboard(script: &ScriptObject) -> Result<String, ExportError> {
+    for block in script.blocks() {
+        for (instruction_index, instruction) in block.instructions.iter().enumerate() {
+            if instruction.is_extension() {
+                let position = Position {
+                    block: block.index(),
+                    instruction: instruction_index,
+                };
+                return Err(ExportError::Extension {
+                    instruction: instruction.name().to_string(),
+                    line: script.get_line(position).unwrap_or_default(),
+                });
+            }
+        }
+    }
+    Ok(GameFormat(script).to_string())
+}
+
+#[cfg(test)]
+mod game_tests {
+    use super::*;
+    use crate::script_object::instruction::ParseOptions;
+
+    #[test]
+    fn test_read_save_file() {
+        let save_file = r#"{
+            "name": "Player",
+            "levels": {
+                "1": { "solutions": ["-- HUMAN RESOURCE MACHINE PROGRAM --\r\n    INBOX\r\n"] },
+                "2": { "steps": 12, },
+            },
+            "floors": [{ "level": 3, "program": "-- HUMAN RESOURCE MACHINE PROGRAM --\n    OUTBOX\n" }],
+        }"#;
+        assert_eq!(
+            read_save_file(save_file, 1).unwrap(),
+            "-- HUMAN RESOURCE MACHINE PROGRAM --\n    INBOX\n"
+        );
+        assert!(read_save_file(save_file, 3).unwrap().ends_with("OUTBOX\n"));
+        assert!(matches!(
+            read_save_file(save_file, 2),
+            Err(SaveFileError::MissingSolution(2))
+        ));
+        assert!(matches!(
+            read_save_file(save_file, 4),
+            Err(SaveFileError::MissingLevel(4))
+        ));
+        assert!(matches!(
+            read_save_file("{", 1),
+            Err(SaveFileError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn test_to_clipboard() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        -- TEST: inputs = 1 ; expected = 1
+        a:
+            INBOX ; read
+            OUTBOX
+            JUMP a
+        ";
+        let script = script.parse::<ScriptObject>().unwrap();
+        assert_eq!(
+            to_clipboard(&script).unwrap(),
+            "-- HUMAN RESOURCE MACHINE PROGRAM --\n\na:\n    INBOX\n    OUTBOX\n    JUMP     a\n"
+        );
+
+        let options = ParseOptions {
+            extensions: true,
+            ..Default::default()
+        };
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            HALT
+        ";
+        let script = ScriptObject::parse_with_options(script, &options).unwrap();
+        assert_eq!(
+            to_clipboard(&script),
+            Err(ExportError::Extension {
+                instruction: "HALT".to_string(),
+                line: 3
+            })
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use std::fmt::Write;
+
+use super::{instruction::Instruction, ScriptObject};
+
+/// Export the control flow of the script as a Graphviz digraph (DOT language).
+///
+/// Each block is a node listing its instructions. The jumps are edges to their target,
+/// labeled with the jump (JUMPZ, JUMPN...) when they're conditional.
+/// Dashed edges go to the next block, when the execution falls through the end of a block.
+/// A block ending with a HALT has an edge to the end.
+pub fn to_dot(script: &ScriptObject) -> String {
+    let mut dot = String::from("digraph script {\n");
+    dot += "    node [shape=box, fontname=\"monospace\"];\n";
+    dot += "    end [shape=doublecircle, label=\"end\"];\n";
+
+    for block in script.blocks() {
+        let mut label = format!("{}:\\l", escape(block.name()));
+        for instruction in &block.instructions {
+            label += &format!("    {}\\l", escape(&format!("{:#}", instruction)));
+        }
+        writeln!(dot, "    b{} [label=\"{}\"];", block.index(), label).unwrap();
+    }
+
+    for (position, instruction, target) in script.jumps() {
+        let Some(target) = target.block() else {
+            continue;
+        };
+        let attributes = match instruction {
+            Instruction::Jump(_) => String::new(),
+            _ => format!(" [label=\"{}\"]", instruction.name()),
+        };
+        writeln!(dot, "    b{} -> b{}{};", position.block, target, attributes).unwrap();
+    }
+
+    for block in script.blocks() {
+        match block.instructions.last() {
+            Some(Instruction::Jump(_) | Instruction::Ret) => continue,
+            Some(Instruction::Halt) => {
+                writeln!(dot, "    b{} -> end [label=\"HALT\"];", block.index()).unwrap();
+                continue;
+            }
+            _ => {}
+        }
+        let next = match script.get_next(block) {
+            Some(next) => format!("b{}", next.index()),
+            None => "end".to_string(),
+        };
+        writeln!(dot, "    b{} -> {} [style=dashed];", block.index(), next).unwrap();
+    }
+
+    dot += "}\n";
+    dot
+}
+
+/// Escape the text to put it in a quoted DOT string
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod graph_tests {
+    use super::*;
+
+    #[test]
+    fn test_to_dot() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            JUMPZ    b
+            OUTBOX
+            JUMP     a
+        b:
+            JUMPN    a
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+
+        assert_eq!(
+            to_dot(&script),
+            r#"digraph script {
+    node [shape=box, fontname="monospace"];
+    end [shape=doublecircle, label="end"];
+    b0 [label="entry:\l"];
+    b1 [label="a:\l    INBOX\l    JUMPZ    b\l    OUTBOX\l    JUMP     a\l"];
+    b2 [label="b:\l    JUMPN    a\l"];
+    b1 -> b2 [label="JUMPZ"];
+    b1 -> b1;
+    b2 -> b1 [label="JUMPN"];
+    b0 -> b1 [style=dashed];
+    b2 -> end [style=dashed];
+}
+"#
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! The files included in the scripts, to share routines and macros between them.
+//!
+//! ```text
+//! #include "lib/common.hrm"
+//! ```
+//!
+//! The lines of the included file are read in place of the "#include", before the macros and
+//! the instructions. The path is relative to the file holding the "#include".
+//! An included file can include other files, but not itself.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{split_remark, ParseScriptObjectError};
+
+#[derive(Debug, Clone, PartialEq)]
+/// A line of the script, once the included files are read
+pub struct SourceLine {
+    pub text: String,
+    /// The line of the script (starting at 1): the "#include" for the lines of an included file
+    pub script_line: usize,
+    /// The included file holding the line, None for the script itself
+    pub file: Option<String>,
+    /// The line in the file holding it (starting at 1)
+    pub line: usize,
+}
+
+/// Whether the line includes a file
+pub fn is_directive(line: &str) -> bool {
+    split_remark(line).0.trim().starts_with("#include")
+}
+
+/// The path of "#include "path"", None if the line isn't written this way
+fn included_path(line: &str) -> Option<&str> {
+    let path = split_remark(line).0.trim().strip_prefix("#include")?;
+    if !path.starts_with(char::is_whitespace) {
+        return None;
+    }
+    path.trim()
+        .strip_prefix('"')?
+        .strip_suffix('"')
+        .filter(|path| !path.is_empty())
+}
+
+/// The lines of the script with the lines of the included files after each "#include".
+/// The files are found from `dir`, the directory of the script: without it, nothing can be included.
+pub fn resolve(
+    lines: &[&str],
+    dir: Option<&Path>,
+) -> Result<Vec<SourceLine>, ParseScriptObjectError> {
+    let mut source = Vec::with_capacity(lines.len());
+    let mut drawings = false;
+    for (i, line) in lines.iter().enumerate() {
+        source.push(SourceLine {
+            text: line.to_string(),
+            script_line: i + 1,
+            file: None,
+            line: i + 1,
+        });
+        // The drawings are never included
+        drawings |= line.trim().starts_with("DEFINE");
+        if drawings || !is_directive(line) {
+            continue;
+        }
+
+        let invalid = |reason: String| ParseScriptObjectError::InvalidInclude {
+            line: i + 1,
+            reason,
+        };
+        let path = included_path(line)
+            .ok_or_else(|| invalid("expected '#include \"path\"'".to_string()))?;
+        let dir = dir.ok_or_else(|| {
+            invalid("the path of the script isn't known, so files can't be included".to_string())
+        })?;
+        include(&dir.join(path), i + 1, &mut vec![], &mut source).map_err(invalid)?;
+    }
+    Ok(source)
+}
+
+/// Push the lines of the file, and of the files it includes.
+/// `stack` holds the files being included, inside each other.
+fn include(
+    path: &Path,
+    script_line: usize,
+    stack: &mut Vec<PathBuf>,
+    source: &mut Vec<SourceLine>,
+) -> Result<(), String> {
+    let name = path.display().to_string();
+    let canonical = fs::canonicalize(path).map_err(|e| format!("cannot read {}: {}", name, e))?;
+    if stack.contains(&canonical) {
+        return Err(format!("{} includes itself", name));
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", name, e))?;
+
+    stack.push(canonical);
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().starts_with("DEFINE") {
+            break;
+        }
+        source.push(SourceLine {
+            text: line.to_string(),
+            script_line,
+            file: Some(name.clone()),
+            line: i + 1,
+        });
+        if is_directive(line) {
+            let included = included_path(line).ok_or_else(|| {
+                format!("expected '#include \"path\"' in {} on line {}", name, i + 1)
+            })?;
+            let dir = path.parent().unwrap_or(Path::new(""));
+            include(&dir.join(included), script_line, stack, source)?;
+        }
+    }
+    stack.pop();
+    Ok(())
+}
+
+#[cfg(test)]
+mod include_tests {
+    use super::*;
+
+    /// A directory of the temporary directory, with the given files
+    fn directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hrm-include-{}", name));
+        for (path, content) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_resolve() {
+        let dir = directory(
+            "resolve",
+            &[
+                ("lib/common.hrm", "    OUTBOX\n#include \"more.hrm\""),
+                ("lib/more.hrm", "    INBOX\nDEFINE LABEL 0\nx;"),
+            ],
+        );
+        let lines = ["a:", "#include \"lib/common.hrm\"", "    JUMP     a"];
+        let source = resolve(&lines, Some(&dir)).unwrap();
+
+        let common = dir.join("lib/common.hrm").display().to_string();
+        let more = dir.join("lib/more.hrm").display().to_string();
+        assert_eq!(
+            source
+                .iter()
+                .map(|line| (
+                    line.text.as_str(),
+                    line.script_line,
+                    line.file.as_deref(),
+                    line.line
+                ))
+                .collect::<Vec<_>>(),
+            [
+                ("a:", 1, None, 1),
+                ("#include \"lib/common.hrm\"", 2, None, 2),
+                ("    OUTBOX", 2, Some(common.as_str()), 1),
+                ("#include \"more.hrm\"", 2, Some(common.as_str()), 2),
+                ("    INBOX", 2, Some(more.as_str()), 1),
+                ("    JUMP     a", 3, None, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_includes() {
+        let dir = directory(
+            "invalid",
+            &[
+                ("a.hrm", "#include \"b.hrm\""),
+                ("b.hrm", "#include \"a.hrm\""),
+            ],
+        );
+        let line_of = |lines: &[&str], dir: Option<&Path>| match resolve(lines, dir) {
+            Err(ParseScriptObjectError::InvalidInclude { line, .. }) => line,
+            other => panic!("expected an invalid include, got {:?}", other),
+        };
+
+        assert_eq!(line_of(&["    INBOX", "#include \"a.hrm\""], Some(&dir)), 2);
+        assert_eq!(line_of(&["#include \"missing.hrm\""], Some(&dir)), 1);
+        assert_eq!(line_of(&["#include a.hrm"], Some(&dir)), 1);
+        assert_eq!(line_of(&["#include \"a.hrm\""], None), 1);
+        // Not read after the drawings
+        assert!(resolve(&["DEFINE LABEL 0", "#include \"a.hrm\""], None).is_ok());
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::{BTreeSet, HashMap};
+
+use super::{
+    instruction::Instruction,
+    value_box::{ValueBox, ValueBoxMemoryAddress},
+    Position, ScriptObject,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// An instruction reading a tile that no path from the beginning of the script has written:
+/// it fails whenever it's executed
+pub struct UninitializedRead {
+    pub position: Position,
+    pub address: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// The tiles that may hold a value
+struct Written {
+    /// Any tile may have been written (through a pointer, or by a PUSH)
+    all_tiles: bool,
+    tiles: BTreeSet<usize>,
+}
+
+impl Written {
+    fn union(&mut self, other: &Written) {
+        self.all_tiles |= other.all_tiles;
+        self.tiles.extend(&other.tiles);
+    }
+
+    fn has_tile(&self, address: usize) -> bool {
+        self.all_tiles || self.tiles.contains(&address)
+    }
+}
+
+/// The tile the instruction needs a value on: its direct address, or the pointer of an indirect one
+fn read_tile(instruction: &Instruction) -> Option<usize> {
+    match instruction {
+        Instruction::CopyFrom(address)
+        | Instruction::Add(address)
+        | Instruction::Sub(address)
+        | Instruction::Mul(address)
+        | Instruction::Div(address)
+        | Instruction::Mod(address)
+        | Instruction::Swap(address)
+        | Instruction::JumpIfEqual(address, _)
+        | Instruction::AssertEqual(address, _)
+        | Instruction::BumpUp(address)
+        | Instruction::BumpDown(address) => match address {
+            ValueBoxMemoryAddress::Pointer(address)
+            | ValueBoxMemoryAddress::PointerAddress(address) => Some(*address),
+        },
+        _ => None,
+    }
+}
+
+/// What may have been written after the instruction, given what may have been written before it
+fn written_after(instruction: &Instruction, before: &Written) -> Written {
+    let mut written = before.clone();
+    match instruction {
+        Instruction::CopyTo(ValueBoxMemoryAddress::Pointer(address)) => {
+            written.tiles.insert(*address);
+        }
+        Instruction::CopyTo(ValueBoxMemoryAddress::PointerAddress(_)) | Instruction::Push => {
+            written.all_tiles = true
+        }
+        _ => {}
+    }
+    written
+}
+
+/// Find the reads of tiles that can't hold a value yet: neither on the floor at the beginning (`memory`)
+/// nor written on any path leading to the instruction.
+/// Through a pointer, only the tile holding the pointer is checked.
+///
+/// The instructions that can't be executed aren't reported.
+pub fn uninitialized_reads(
+    script: &ScriptObject,
+    memory: &HashMap<usize, ValueBox>,
+) -> Vec<UninitializedRead> {
+    let positions = script
+        .blocks()
+        .iter()
+        .flat_map(|block| {
+            (0..block.instructions.len()).map(|instruction| Position {
+                block: block.index(),
+                instruction,
+            })
+        })
+        .collect::<Vec<_>>();
+    // A RET can go back after any CALL
+    let after_calls = positions
+        .iter()
+        .filter(|position| {
+            matches!(
+                script.get_instruction(**position),
+                Some(Instruction::Call(_))
+            )
+        })
+        .filter_map(|position| {
+            script.next_instruction_position(Position {
+                instruction: position.instruction + 1,
+                ..*position
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // What may have been written before each instruction, computed forwards until nothing changes
+    let mut written_before: HashMap<Position, Written> = HashMap::new();
+    if let Some(entry) = script.next_instruction_position(Position::default()) {
+        let initial = Written {
+            all_tiles: false,
+            tiles: memory.keys().copied().collect(),
+        };
+        written_before.insert(entry, initial);
+    }
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for position in &positions {
+            let Some(before) = written_before.get(position) else {
+                continue;
+            };
+            let instruction = script.get_instruction(*position).unwrap();
+            let after = written_after(instruction, before);
+            let mut successors = script.successors(*position);
+            if matches!(instruction, Instruction::Ret) {
+                successors.extend(&after_calls);
+            }
+            for successor in successors {
+                let written = written_before.entry(successor).or_default();
+                let previous_len = written.tiles.len();
+                let previous_all_tiles = written.all_tiles;
+                written.union(&after);
+                if written.tiles.len() != previous_len || written.all_tiles != previous_all_tiles {
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    positions
+        .into_iter()
+        .filter_map(|position| {
+            let written = written_before.get(&position)?;
+            let address = read_tile(script.get_instruction(position).unwrap())?;
+            (!written.has_tile(address)).then_some(UninitializedRead { position, address })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod initialization_tests {
+    use super::*;
+    use crate::script_object::instruction::ParseOptions;
+
+    fn uninitialized_lines(script: &str, memory: &HashMap<usize, ValueBox>) -> Vec<(usize, usize)> {
+        let script = script.parse::<ScriptObject>().unwrap();
+        uninitialized_reads(&script, memory)
+            .into_iter()
+            .map(|read| (script.get_line(read.position).unwrap(), read.address))
+            .collect()
+    }
+
+    #[test]
+    fn test_uninitialized_reads() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            JUMPZ    b
+            COPYTO   0
+        b:
+            ADD      0
+            BUMPUP   1
+            COPYFROM [2]
+            COPYFROM 3
+            OUTBOX
+            JUMP     a
+        ";
+        let memory = HashMap::from([(3, ValueBox::from(0))]);
+        // Tile 0 may be written on the way to the ADD, and by the previous loop
+        assert_eq!(uninitialized_lines(script, &memory), vec![(8, 1), (9, 2)]);
+    }
+
+    #[test]
+    fn test_written_later_in_the_loop() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+        a:
+            JUMPZ    b
+            ADD      0
+        b:
+            COPYTO   0
+            INBOX
+            JUMP     a
+        ";
+        // The ADD fails if the first value isn't 0, but not always: the tile may be written by then
+        assert_eq!(uninitialized_lines(script, &HashMap::new()), vec![]);
+
+        // Without the loop, the tile is written too late
+        let script = script.replace("JUMP     a", "OUTBOX");
+        assert_eq!(uninitialized_lines(&script, &HashMap::new()), vec![(5, 0)]);
+    }
+
+    #[test]
+    fn test_writes_through_pointers_and_calls() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            COPYFROM 9
+            COPYTO   [9]
+            COPYFROM 5
+        ";
+        let memory = HashMap::from([(9, ValueBox::from(5))]);
+        assert_eq!(uninitialized_lines(script, &memory), vec![]);
+
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            CALL     f
+            COPYFROM 0
+            OUTBOX
+            HALT
+        f:
+            COPYTO   0
+            RET
+        ";
+        let options = ParseOptions {
+            extensions: true,
+            ..Default::default()
+        };
+        let script = ScriptObject::parse_with_options(script, &options).unwrap();
+        assert_eq!(uninitialized_reads(&script, &HashMap::new()), vec![]);
+    }
+}
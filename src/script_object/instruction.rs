@@ -1,13 +1,96 @@
-use std::str::FromStr;
+use std::{fmt::Display, ops::Range, path::PathBuf, str::FromStr};
 
 use collapse::collapse;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use super::value_box::{self, ParseValueBoxMemoryAddressError};
+use super::value_box::{self, ParseValueBoxMemoryAddressError, TileNames, ValueBox};
 
 use value_box::ValueBoxMemoryAddress as ValBoxMemAddr;
-type BlockKey = String;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone)]
+/// The target of a jump: the label of a block, and the index of this block.
+/// The index is only known once the whole script is parsed: see [`super::ScriptObject`],
+/// which resolves the targets of its jumps so that the interpreter doesn't look labels up.
+pub struct JumpTarget {
+    label: String,
+    block: Option<usize>,
+}
+
+impl JumpTarget {
+    /// A target that isn't resolved yet
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            block: None,
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// The index of the target block, or None if the label doesn't match any block
+    /// (or if the target hasn't been resolved).
+    pub fn block(&self) -> Option<usize> {
+        self.block
+    }
+
+    pub(crate) fn resolve(&mut self, block: Option<usize>) {
+        self.block = block;
+    }
+}
+
+impl From<&str> for JumpTarget {
+    fn from(label: &str) -> Self {
+        Self::new(label)
+    }
+}
+
+/// Targets are the same if they have the same label: the index of the block is derived from it.
+impl PartialEq for JumpTarget {
+    fn eq(&self, other: &Self) -> bool {
+        self.label == other.label
+    }
+}
+
+/// Only the label is written: the index of the block is resolved again when the script is read.
+impl Serialize for JumpTarget {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.label)
+    }
+}
+
+impl<'de> Deserialize<'de> for JumpTarget {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|label| Self::new(&label))
+    }
+}
+
+impl Display for JumpTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.label)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// What a DEBUG instruction prints (extension)
+pub enum DebugOperand {
+    /// A message, written between double quotes
+    Message(String),
+    /// The value on a tile
+    Tile(ValBoxMemAddr),
+}
+
+impl Display for DebugOperand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DebugOperand::Message(message) => write!(f, "\"{}\"", message),
+            DebugOperand::Tile(address) => write!(f, "{}", address),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// An instruction is a line of code in the script.
 /// It holds the operation and sometimes some additional data.
 /// The rust enum structure is perfect for this.
@@ -35,54 +118,434 @@ pub enum Instruction {
     BumpDown(ValBoxMemAddr),
 
     /// Jump to the given bock
-    Jump(BlockKey),
+    Jump(JumpTarget),
     /// Jump to the given block if the head is zero
-    JumpIfZero(BlockKey),
+    JumpIfZero(JumpTarget),
     /// Jump to the given block if the head is (strictly) negative
-    JumpIfNegative(BlockKey),
+    JumpIfNegative(JumpTarget),
+
+    /// Jump to the given block if the head is (strictly) positive (extension)
+    JumpIfPositive(JumpTarget),
+    /// Jump to the given block if the head is equal to the value at the given memory address (extension)
+    JumpIfEqual(ValBoxMemAddr, JumpTarget),
+
+    /// Multiply the head by the value at the given memory address (extension)
+    Mul(ValBoxMemAddr),
+    /// Divide the head by the value at the given memory address, rounding toward zero (extension)
+    Div(ValBoxMemAddr),
+    /// The remainder of the division of the head by the value at the given memory address,
+    /// with the sign of the head (extension)
+    Mod(ValBoxMemAddr),
+    /// Exchange the head and the value at the given memory address (extension)
+    Swap(ValBoxMemAddr),
+
+    /// Put the head on top of the stack (extension)
+    Push,
+    /// Take the value on top of the stack into the head (extension)
+    Pop,
+
+    /// Jump to the given block, and come back after the CALL on the next RET (extension)
+    Call(JumpTarget),
+    /// Go back after the last CALL (extension)
+    Ret,
+
+    /// Copy the head to the second hand (extension)
+    TempCopyTo,
+    /// Copy the second hand to the head (extension)
+    TempCopyFrom,
+    /// Exchange the head and the second hand (extension)
+    Exchange,
+
+    /// Print a message or the value on a tile, without changing anything (extension)
+    Debug(DebugOperand),
+
+    /// Stop with an error if the head isn't zero (extension)
+    AssertZero,
+    /// Stop with an error if the head isn't (strictly) negative (extension)
+    AssertNegative,
+    /// Stop with an error if the value at the given memory address isn't the given value (extension)
+    AssertEqual(ValBoxMemAddr, ValueBox),
+
+    /// Do nothing (extension)
+    Nop,
+    /// Terminate the program (extension)
+    Halt,
+}
+
+impl Instruction {
+    /// The keywords of all the instructions, as returned by `name`
+    pub const NAMES: [&'static str; 11] = [
+        "INBOX", "OUTBOX", "COPYFROM", "COPYTO", "ADD", "SUB", "BUMPUP", "BUMPDN", "JUMP", "JUMPZ",
+        "JUMPN",
+    ];
+    /// The keywords of the instructions that aren't in the game,
+    /// only accepted when the extensions are enabled
+    pub const EXTENSION_NAMES: [&'static str; 19] = [
+        "MUL",
+        "DIV",
+        "MOD",
+        "SWAP",
+        "JUMPP",
+        "JUMPE",
+        "PUSH",
+        "POP",
+        "CALL",
+        "RET",
+        "TCOPYTO",
+        "TCOPYFROM",
+        "XCHG",
+        "DEBUG",
+        "ASSERTZ",
+        "ASSERTN",
+        "ASSERT",
+        "NOP",
+        "HALT",
+    ];
+
+    /// Whether the instruction isn't in the game
+    pub fn is_extension(&self) -> bool {
+        Instruction::EXTENSION_NAMES.contains(&self.name())
+    }
+
+    /// The target of the jump, if the instruction is a jump
+    pub fn jump_target(&self) -> Option<&JumpTarget> {
+        match self {
+            Instruction::Jump(target)
+            | Instruction::JumpIfZero(target)
+            | Instruction::JumpIfNegative(target)
+            | Instruction::JumpIfPositive(target)
+            | Instruction::JumpIfEqual(_, target)
+            | Instruction::Call(target) => Some(target),
+            _ => None,
+        }
+    }
+
+    /// The keyword of the instruction in the script (INBOX, COPYTO...)
+    pub fn name(&self) -> &'static str {
+        match self {
+            Instruction::In => "INBOX",
+            Instruction::Out => "OUTBOX",
+            Instruction::CopyFrom(_) => "COPYFROM",
+            Instruction::CopyTo(_) => "COPYTO",
+            Instruction::Add(_) => "ADD",
+            Instruction::Sub(_) => "SUB",
+            Instruction::BumpUp(_) => "BUMPUP",
+            Instruction::BumpDown(_) => "BUMPDN",
+            Instruction::Jump(_) => "JUMP",
+            Instruction::JumpIfZero(_) => "JUMPZ",
+            Instruction::JumpIfNegative(_) => "JUMPN",
+            Instruction::JumpIfPositive(_) => "JUMPP",
+            Instruction::JumpIfEqual(..) => "JUMPE",
+            Instruction::Mul(_) => "MUL",
+            Instruction::Div(_) => "DIV",
+            Instruction::Mod(_) => "MOD",
+            Instruction::Swap(_) => "SWAP",
+            Instruction::Push => "PUSH",
+            Instruction::Pop => "POP",
+            Instruction::Call(_) => "CALL",
+            Instruction::Ret => "RET",
+            Instruction::TempCopyTo => "TCOPYTO",
+            Instruction::TempCopyFrom => "TCOPYFROM",
+            Instruction::Exchange => "XCHG",
+            Instruction::Debug(_) => "DEBUG",
+            Instruction::AssertZero => "ASSERTZ",
+            Instruction::AssertNegative => "ASSERTN",
+            Instruction::AssertEqual(..) => "ASSERT",
+            Instruction::Nop => "NOP",
+            Instruction::Halt => "HALT",
+        }
+    }
+
+    /// The memory address the instruction reads or writes, if any
+    pub fn memory_address(&self) -> Option<&ValBoxMemAddr> {
+        match self {
+            Instruction::CopyFrom(address)
+            | Instruction::CopyTo(address)
+            | Instruction::Add(address)
+            | Instruction::Sub(address)
+            | Instruction::BumpUp(address)
+            | Instruction::BumpDown(address)
+            | Instruction::Mul(address)
+            | Instruction::Div(address)
+            | Instruction::Mod(address)
+            | Instruction::Swap(address)
+            | Instruction::JumpIfEqual(address, _)
+            | Instruction::Debug(DebugOperand::Tile(address))
+            | Instruction::AssertEqual(address, _) => Some(address),
+            _ => None,
+        }
+    }
+
+    /// The memory address of the instruction, to change it
+    pub fn memory_address_mut(&mut self) -> Option<&mut ValBoxMemAddr> {
+        match self {
+            Instruction::CopyFrom(address)
+            | Instruction::CopyTo(address)
+            | Instruction::Add(address)
+            | Instruction::Sub(address)
+            | Instruction::BumpUp(address)
+            | Instruction::BumpDown(address)
+            | Instruction::Mul(address)
+            | Instruction::Div(address)
+            | Instruction::Mod(address)
+            | Instruction::Swap(address)
+            | Instruction::JumpIfEqual(address, _)
+            | Instruction::Debug(DebugOperand::Tile(address))
+            | Instruction::AssertEqual(address, _) => Some(address),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Instruction {
+    /// Write the instruction as in the script: "COPYFROM [3]", "JUMP a", "JUMPE 3 a".
+    /// With `{:#}`, the operand is aligned the way the game does: "COPYTO   3".
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let operand = match (self, self.jump_target(), self.memory_address()) {
+            (Instruction::Debug(operand), _, _) => operand.to_string(),
+            (Instruction::AssertEqual(address, value), _, _) => {
+                format!("{} == {}", address, value)
+            }
+            (_, Some(target), Some(address)) => format!("{} {}", address, target),
+            (_, Some(target), None) => target.to_string(),
+            (_, None, Some(address)) => address.to_string(),
+            (_, None, None) => return write!(f, "{}", self.name()),
+        };
+        if f.alternate() {
+            write!(f, "{:<8} {}", self.name(), operand)
+        } else {
+            write!(f, "{} {}", self.name(), operand)
+        }
+    }
 }
 
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, Clone, thiserror::Error)]
 /// Error that can occur when parsing an instruction.
+/// Each variant holds the byte range of the offending words in the parsed text.
 pub enum ParseInstructionError {
-    #[error("too much parts in the instruction line, expected 2 at most, got {}", .0.len())]
-    TooMuchParts(Vec<String>),
+    #[error("too much parts in the instruction line, expected {} at most, got {}", max_parts(&.0[0]), .0.len())]
+    TooMuchParts(Vec<String>, Range<usize>),
     #[error("{0} is not a valid instruction")]
-    InvalidInstruction(String),
+    InvalidInstruction(String, Range<usize>),
     #[error("instruction has an invalid memory address:\n\t{0}")]
-    InvalidMemoryAddress(#[from] ParseValueBoxMemoryAddressError),
+    InvalidMemoryAddress(#[source] ParseValueBoxMemoryAddressError, Range<usize>),
+    #[error("{0} is not an instruction of the game: it needs the extensions to be enabled")]
+    ExtensionDisabled(String, Range<usize>),
+}
+
+impl ParseInstructionError {
+    /// The byte range of the offending words in the parsed text
+    pub fn range(&self) -> Range<usize> {
+        match self {
+            ParseInstructionError::TooMuchParts(_, range)
+            | ParseInstructionError::InvalidInstruction(_, range)
+            | ParseInstructionError::InvalidMemoryAddress(_, range)
+            | ParseInstructionError::ExtensionDisabled(_, range) => range.clone(),
+        }
+    }
+
+    /// A stable identifier of the kind of error, for tools
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseInstructionError::TooMuchParts(..) => "too-many-parts",
+            ParseInstructionError::InvalidInstruction(..) => "unknown-instruction",
+            ParseInstructionError::InvalidMemoryAddress(..) => "invalid-address",
+            ParseInstructionError::ExtensionDisabled(..) => "extension-disabled",
+        }
+    }
+}
+
+/// The number of words of the instruction, with its operands
+fn max_parts(instruction_key: &str) -> usize {
+    match instruction_key {
+        "JUMPE" => 3,
+        "ASSERT" => 4,
+        _ => 2,
+    }
+}
+
+/// Other names given to the operations, accepted in lenient mode
+const ALIASES: [(&str, &str); 8] = [
+    ("BUMPDOWN", "BUMPDN"),
+    ("JMP", "JUMP"),
+    ("JMPZ", "JUMPZ"),
+    ("JMPN", "JUMPN"),
+    ("JZ", "JUMPZ"),
+    ("JN", "JUMPN"),
+    ("IN", "INBOX"),
+    ("OUT", "OUTBOX"),
+];
+
+/// The operation written the way of the game: uppercase, without underscores, and with
+/// the aliases replaced ("copy_from" and "jmp" become "COPYFROM" and "JUMP")
+pub fn lenient_key(instruction_key: &str) -> String {
+    let key = instruction_key.to_uppercase().replace('_', "");
+    match ALIASES.iter().find(|(alias, _)| *alias == key) {
+        Some((_, name)) => name.to_string(),
+        None => key,
+    }
+}
+
+/// The message of a DEBUG instruction: everything between the double quotes after DEBUG
+/// (in any case, for the lenient mode)
+pub(crate) fn debug_message(s: &str) -> Option<&str> {
+    let s = s.trim();
+    s.get(..5).filter(|key| key.eq_ignore_ascii_case("DEBUG"))?;
+    s[5..].trim_start().strip_prefix('"')?.strip_suffix('"')
+}
+
+/// The whitespace-separated words of the text, with their byte ranges
+fn words(s: &str) -> Vec<(&str, Range<usize>)> {
+    let mut words = vec![];
+    let mut start = None;
+    for (i, c) in s.char_indices().chain(std::iter::once((s.len(), ' '))) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(i),
+            (Some(word_start), true) => {
+                words.push((&s[word_start..i], word_start..i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// How the instructions are read
+pub struct ParseOptions {
+    /// Names of memory tiles, which can be used instead of their addresses
+    pub tile_names: TileNames,
+    /// Accept the instructions that aren't in the game (see [`Instruction::EXTENSION_NAMES`])
+    pub extensions: bool,
+    /// The directory of the script, from which the "#include" files are found.
+    /// None if the script isn't a file: nothing can be included.
+    pub include_dir: Option<PathBuf>,
+    /// Accept the operations in any case, and their common aliases (see [`lenient_key`])
+    pub lenient: bool,
 }
 
 impl FromStr for Instruction {
     type Err = ParseInstructionError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s: &str = &collapse(s);
-        let parts = s.split_whitespace().collect::<Vec<&str>>();
+        Self::parse_with_options(s, &ParseOptions::default())
+    }
+}
 
-        if parts.len() > 2 {
-            return Err(Self::Err::TooMuchParts(
-                parts.iter().map(|s| s.to_string()).collect(),
+impl Instruction {
+    /// Parse an instruction in which memory tiles can be referred to by their names.
+    pub fn parse_with_tile_names(
+        s: &str,
+        tile_names: &TileNames,
+    ) -> Result<Self, ParseInstructionError> {
+        Self::parse_with_options(
+            s,
+            &ParseOptions {
+                tile_names: tile_names.clone(),
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn parse_with_options(
+        s: &str,
+        options: &ParseOptions,
+    ) -> Result<Self, ParseInstructionError> {
+        let parts = words(s);
+        // The range from the given word to the end of the instruction
+        let from_word = |i: usize| match (parts.get(i), parts.last()) {
+            (Some((_, first)), Some((_, last))) => first.start..last.end,
+            _ => s.len()..s.len(),
+        };
+
+        let written_key = parts.first().map(|(word, _)| *word).unwrap_or_default();
+        let lenient_key = options.lenient.then(|| lenient_key(written_key));
+        let instruction_key = lenient_key.as_deref().unwrap_or(written_key);
+        let message = debug_message(s);
+        if parts.len() > max_parts(instruction_key) && message.is_none() {
+            return Err(ParseInstructionError::TooMuchParts(
+                parts.iter().map(|(word, _)| word.to_string()).collect(),
+                from_word(max_parts(instruction_key)),
             ));
         }
 
-        #[allow(clippy::get_first)]
-        let instruction_key = *parts.get(0).unwrap();
-        let address_key = parts.get(1).cloned();
+        let address_key = parts.get(1).map(|(word, _)| *word);
+        let label_key = parts.get(2).map(|(word, _)| *word);
+        let address = |akey: &str| {
+            let range = parts
+                .get(1)
+                .map_or(s.len()..s.len(), |(_, range)| range.clone());
+            ValBoxMemAddr::parse_with_tile_names(akey, &options.tile_names)
+                .map_err(|e| ParseInstructionError::InvalidMemoryAddress(e, range))
+        };
+        let invalid = || {
+            ParseInstructionError::InvalidInstruction(
+                collapse(s).trim().to_string(),
+                // Point at the operation if it doesn't exist, and at the whole instruction otherwise
+                if Instruction::NAMES.contains(&instruction_key)
+                    || Instruction::EXTENSION_NAMES.contains(&instruction_key)
+                {
+                    from_word(0)
+                } else {
+                    from_word(0).start..from_word(0).start + written_key.len()
+                },
+            )
+        };
+
+        if !options.extensions && Instruction::EXTENSION_NAMES.contains(&instruction_key) {
+            return Err(ParseInstructionError::ExtensionDisabled(
+                instruction_key.to_string(),
+                from_word(0).start..from_word(0).start + written_key.len(),
+            ));
+        }
 
         match (instruction_key, address_key) {
             ("INBOX", None) => Ok(Instruction::In),
             ("OUTBOX", None) => Ok(Instruction::Out),
-            ("COPYFROM", Some(akey)) => Ok(Instruction::CopyFrom(ValBoxMemAddr::from_str(akey)?)),
-            ("COPYTO", Some(akey)) => Ok(Instruction::CopyTo(ValBoxMemAddr::from_str(akey)?)),
-            ("ADD", Some(akey)) => Ok(Instruction::Add(ValBoxMemAddr::from_str(akey)?)),
-            ("SUB", Some(akey)) => Ok(Instruction::Sub(ValBoxMemAddr::from_str(akey)?)),
-            ("BUMPUP", Some(akey)) => Ok(Instruction::BumpUp(ValBoxMemAddr::from_str(akey)?)),
-            ("BUMPDN", Some(akey)) => Ok(Instruction::BumpDown(ValBoxMemAddr::from_str(akey)?)),
-            ("JUMP", Some(akey)) => Ok(Instruction::Jump(akey.to_string())),
-            ("JUMPZ", Some(akey)) => Ok(Instruction::JumpIfZero(akey.to_string())),
-            ("JUMPN", Some(akey)) => Ok(Instruction::JumpIfNegative(akey.to_string())),
-            _ => Err(Self::Err::InvalidInstruction(s.to_string())),
+            ("COPYFROM", Some(akey)) => Ok(Instruction::CopyFrom(address(akey)?)),
+            ("COPYTO", Some(akey)) => Ok(Instruction::CopyTo(address(akey)?)),
+            ("ADD", Some(akey)) => Ok(Instruction::Add(address(akey)?)),
+            ("SUB", Some(akey)) => Ok(Instruction::Sub(address(akey)?)),
+            ("BUMPUP", Some(akey)) => Ok(Instruction::BumpUp(address(akey)?)),
+            ("BUMPDN", Some(akey)) => Ok(Instruction::BumpDown(address(akey)?)),
+            ("JUMP", Some(akey)) => Ok(Instruction::Jump(akey.into())),
+            ("JUMPZ", Some(akey)) => Ok(Instruction::JumpIfZero(akey.into())),
+            ("JUMPN", Some(akey)) => Ok(Instruction::JumpIfNegative(akey.into())),
+            ("MUL", Some(akey)) => Ok(Instruction::Mul(address(akey)?)),
+            ("DIV", Some(akey)) => Ok(Instruction::Div(address(akey)?)),
+            ("MOD", Some(akey)) => Ok(Instruction::Mod(address(akey)?)),
+            ("SWAP", Some(akey)) => Ok(Instruction::Swap(address(akey)?)),
+            ("PUSH", None) => Ok(Instruction::Push),
+            ("POP", None) => Ok(Instruction::Pop),
+            ("CALL", Some(akey)) => Ok(Instruction::Call(akey.into())),
+            ("RET", None) => Ok(Instruction::Ret),
+            ("TCOPYTO", None) => Ok(Instruction::TempCopyTo),
+            ("TCOPYFROM", None) => Ok(Instruction::TempCopyFrom),
+            ("XCHG", None) => Ok(Instruction::Exchange),
+            ("DEBUG", Some(akey)) => match message {
+                Some(message) => Ok(Instruction::Debug(DebugOperand::Message(
+                    message.to_string(),
+                ))),
+                None => Ok(Instruction::Debug(DebugOperand::Tile(address(akey)?))),
+            },
+            ("ASSERTZ", None) => Ok(Instruction::AssertZero),
+            ("ASSERTN", None) => Ok(Instruction::AssertNegative),
+            ("ASSERT", Some(akey)) => match (parts.get(2), parts.get(3)) {
+                (Some(("==", _)), Some((value, _))) => match value.parse::<ValueBox>() {
+                    Ok(value) => Ok(Instruction::AssertEqual(address(akey)?, value)),
+                    Err(_) => Err(invalid()),
+                },
+                _ => Err(invalid()),
+            },
+            ("NOP", None) => Ok(Instruction::Nop),
+            ("HALT", None) => Ok(Instruction::Halt),
+            ("JUMPP", Some(akey)) => Ok(Instruction::JumpIfPositive(akey.into())),
+            ("JUMPE", Some(akey)) => match label_key {
+                Some(label) => Ok(Instruction::JumpIfEqual(address(akey)?, label.into())),
+                None => Err(invalid()),
+            },
+            _ => Err(invalid()),
         }
     }
 }
@@ -91,6 +554,23 @@ impl FromStr for Instruction {
 mod instruction_tests {
     use super::*;
 
+    #[test]
+    fn test_instruction_display() {
+        assert_eq!(Instruction::In.to_string(), "INBOX");
+        assert_eq!(
+            Instruction::CopyFrom(ValBoxMemAddr::PointerAddress(3)).to_string(),
+            "COPYFROM [3]"
+        );
+        assert_eq!(Instruction::JumpIfZero("a".into()).to_string(), "JUMPZ a");
+
+        assert_eq!(format!("{:#}", Instruction::Out), "OUTBOX");
+        assert_eq!(
+            format!("{:#}", Instruction::CopyTo(ValBoxMemAddr::Pointer(3))),
+            "COPYTO   3"
+        );
+        assert_eq!(format!("{:#}", Instruction::Jump("a".into())), "JUMP     a");
+    }
+
     #[test]
     fn test_instruction_from_str() {
         assert_eq!(Instruction::In, Instruction::from_str("INBOX").unwrap());
@@ -120,19 +600,28 @@ mod instruction_tests {
             Instruction::from_str("BUMPDN 9").unwrap()
         );
         assert_eq!(
-            Instruction::Jump("0".to_string()),
+            Instruction::Jump("0".into()),
             Instruction::from_str("JUMP 0").unwrap()
         );
         assert_eq!(
-            Instruction::JumpIfZero("b".to_string()),
+            Instruction::JumpIfZero("b".into()),
             Instruction::from_str("JUMPZ b").unwrap()
         );
         assert_eq!(
-            Instruction::JumpIfNegative("cd".to_string()),
+            Instruction::JumpIfNegative("cd".into()),
             Instruction::from_str("JUMPN cd").unwrap()
         );
     }
 
+    #[test]
+    fn test_instruction_error_ranges() {
+        let range = |s: &str| Instruction::from_str(s).unwrap_err().range();
+        assert_eq!(range("COPYTOO  2"), 0..7);
+        assert_eq!(range("  COPYTO"), 2..8);
+        assert_eq!(range("COPYTO  [x]"), 8..11);
+        assert_eq!(range("ADD 1 2  3"), 6..10);
+    }
+
     #[test]
     fn test_instructions_with_spaces() {
         assert_eq!(Instruction::In, Instruction::from_str(" INBOX ").unwrap());
@@ -165,15 +654,15 @@ mod instruction_tests {
             Instruction::from_str("BUMPDN\n 0 ").unwrap()
         );
         assert_eq!(
-            Instruction::Jump("0".to_string()),
+            Instruction::Jump("0".into()),
             Instruction::from_str("JUMP  0 ").unwrap()
         );
         assert_eq!(
-            Instruction::JumpIfZero("0".to_string()),
+            Instruction::JumpIfZero("0".into()),
             Instruction::from_str(" JUMPZ  0 ").unwrap()
         );
         assert_eq!(
-            Instruction::JumpIfNegative("0".to_string()),
+            Instruction::JumpIfNegative("0".into()),
             Instruction::from_str("  JUMPN 0 ").unwrap()
         );
     }
@@ -205,4 +694,170 @@ mod instruction_tests {
             Instruction::from_str("BUMPDN [9]").unwrap()
         );
     }
+
+    #[test]
+    fn test_instructions_with_tile_names() {
+        let tile_names = TileNames::from([("zero".to_string(), 9), ("ptr".to_string(), 4)]);
+
+        assert_eq!(
+            Instruction::CopyFrom(ValBoxMemAddr::Pointer(9)),
+            Instruction::parse_with_tile_names("COPYFROM zero", &tile_names).unwrap()
+        );
+        assert_eq!(
+            Instruction::BumpUp(ValBoxMemAddr::PointerAddress(4)),
+            Instruction::parse_with_tile_names("BUMPUP [ptr]", &tile_names).unwrap()
+        );
+        // Jump labels are not tile names
+        assert_eq!(
+            Instruction::Jump("zero".into()),
+            Instruction::parse_with_tile_names("JUMP zero", &tile_names).unwrap()
+        );
+        assert!(Instruction::parse_with_tile_names("ADD one", &tile_names).is_err());
+    }
+
+    #[test]
+    fn test_lenient_instructions() {
+        let lenient = ParseOptions {
+            lenient: true,
+            ..Default::default()
+        };
+        let parse = |s: &str| Instruction::parse_with_options(s, &lenient);
+        assert_eq!(parse("inbox").unwrap(), Instruction::In);
+        assert_eq!(parse("out").unwrap(), Instruction::Out);
+        assert_eq!(
+            parse("copy_from [3]").unwrap(),
+            Instruction::CopyFrom(ValBoxMemAddr::PointerAddress(3))
+        );
+        assert_eq!(
+            parse("BumpDown 2").unwrap(),
+            Instruction::BumpDown(ValBoxMemAddr::Pointer(2))
+        );
+        assert_eq!(
+            parse("jmp Start").unwrap(),
+            Instruction::Jump("Start".into())
+        );
+        assert_eq!(parse("jz a").unwrap(), Instruction::JumpIfZero("a".into()));
+        assert!(matches!(
+            parse("nop"),
+            Err(ParseInstructionError::ExtensionDisabled(name, range)) if name == "NOP" && range == (0..3)
+        ));
+        assert!(parse("copyfrom").is_err());
+
+        // The game's syntax only, by default
+        assert!("inbox".parse::<Instruction>().is_err());
+        assert!("BUMPDOWN 2".parse::<Instruction>().is_err());
+        assert_eq!(lenient_key("copy_to"), "COPYTO");
+        assert_eq!(lenient_key("Jmp"), "JUMP");
+    }
+
+    #[test]
+    fn test_extensions() {
+        let extensions = ParseOptions {
+            extensions: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            Instruction::parse_with_options("NOP", &extensions).unwrap(),
+            Instruction::Nop
+        );
+        assert_eq!(
+            Instruction::parse_with_options(" HALT ", &extensions).unwrap(),
+            Instruction::Halt
+        );
+        assert!(Instruction::Halt.is_extension());
+        assert!(!Instruction::In.is_extension());
+        assert!(Instruction::parse_with_options("HALT 1", &extensions).is_err());
+        assert_eq!(
+            Instruction::parse_with_options("MUL [3]", &extensions).unwrap(),
+            Instruction::Mul(ValBoxMemAddr::PointerAddress(3))
+        );
+        assert_eq!(
+            Instruction::parse_with_options("DIV 1", &extensions).unwrap(),
+            Instruction::Div(ValBoxMemAddr::Pointer(1))
+        );
+        assert_eq!(
+            Instruction::parse_with_options("MOD 0", &extensions).unwrap(),
+            Instruction::Mod(ValBoxMemAddr::Pointer(0))
+        );
+        assert!(Instruction::parse_with_options("MOD", &extensions).is_err());
+        assert!(Instruction::from_str("MUL 1").is_err());
+        assert_eq!(
+            Instruction::parse_with_options("SWAP [2]", &extensions).unwrap(),
+            Instruction::Swap(ValBoxMemAddr::PointerAddress(2))
+        );
+        assert!(Instruction::from_str("SWAP 2").is_err());
+        assert_eq!(
+            Instruction::parse_with_options("JUMPP a", &extensions).unwrap(),
+            Instruction::JumpIfPositive("a".into())
+        );
+        let jumpe = Instruction::parse_with_options("JUMPE   [4]   a", &extensions).unwrap();
+        assert_eq!(
+            jumpe,
+            Instruction::JumpIfEqual(ValBoxMemAddr::PointerAddress(4), "a".into())
+        );
+        assert_eq!(jumpe.jump_target().unwrap().label(), "a");
+        assert_eq!(format!("{:#}", jumpe), "JUMPE    [4] a");
+        assert!(Instruction::parse_with_options("JUMPE 4", &extensions).is_err());
+        let error = Instruction::parse_with_options("JUMPE 4 a b", &extensions).unwrap_err();
+        assert_eq!(error.code(), "too-many-parts");
+        assert_eq!(error.range(), 10..11);
+        let error = Instruction::parse_with_options("JUMPE x a", &extensions).unwrap_err();
+        assert_eq!(error.range(), 6..7);
+        assert!(Instruction::parse_with_options("JUMPP a b", &extensions).is_err());
+        assert_eq!(
+            Instruction::parse_with_options("PUSH", &extensions).unwrap(),
+            Instruction::Push
+        );
+        assert_eq!(
+            Instruction::parse_with_options("POP", &extensions).unwrap(),
+            Instruction::Pop
+        );
+        assert!(Instruction::parse_with_options("POP 1", &extensions).is_err());
+        let call = Instruction::parse_with_options("CALL f", &extensions).unwrap();
+        assert_eq!(call, Instruction::Call("f".into()));
+        assert_eq!(call.jump_target().unwrap().label(), "f");
+        assert_eq!(
+            Instruction::parse_with_options("RET", &extensions).unwrap(),
+            Instruction::Ret
+        );
+        assert!(Instruction::parse_with_options("CALL", &extensions).is_err());
+        assert_eq!(
+            Instruction::parse_with_options("TCOPYFROM", &extensions).unwrap(),
+            Instruction::TempCopyFrom
+        );
+        assert_eq!(
+            Instruction::parse_with_options("XCHG", &extensions).unwrap(),
+            Instruction::Exchange
+        );
+        assert!(Instruction::from_str("TCOPYTO").is_err());
+        let debug = Instruction::parse_with_options("DEBUG   \"x: a  b\"", &extensions).unwrap();
+        assert_eq!(
+            debug,
+            Instruction::Debug(DebugOperand::Message("x: a  b".to_string()))
+        );
+        assert_eq!(format!("{:#}", debug), "DEBUG    \"x: a  b\"");
+        assert_eq!(
+            Instruction::parse_with_options("DEBUG [3]", &extensions).unwrap(),
+            Instruction::Debug(DebugOperand::Tile(ValBoxMemAddr::PointerAddress(3)))
+        );
+        assert!(Instruction::parse_with_options("DEBUG \"a", &extensions).is_err());
+        assert!(Instruction::from_str("DEBUG \"a\"").is_err());
+        let assert = Instruction::parse_with_options("ASSERT [2] == A", &extensions).unwrap();
+        assert_eq!(
+            assert,
+            Instruction::AssertEqual(ValBoxMemAddr::PointerAddress(2), ValueBox::from('A'))
+        );
+        assert_eq!(format!("{:#}", assert), "ASSERT   [2] == A");
+        assert_eq!(
+            Instruction::parse_with_options("ASSERTN", &extensions).unwrap(),
+            Instruction::AssertNegative
+        );
+        assert!(Instruction::parse_with_options("ASSERT 2 = 3", &extensions).is_err());
+        assert!(Instruction::parse_with_options("ASSERT 2 == 3 4", &extensions).is_err());
+        assert!(Instruction::parse_with_options("ASSERT 2 ==", &extensions).is_err());
+
+        let error = Instruction::from_str("  NOP").unwrap_err();
+        assert_eq!(error.code(), "extension-disabled");
+        assert_eq!(error.range(), 2..5);
+    }
 }
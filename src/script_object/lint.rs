@@ -0,0 +1,295 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use super::{
+    initialization::uninitialized_reads,
+    instruction::Instruction,
+    liveness::{dead_instructions, DeadEffect},
+    loops::{infinite_loops, INFINITE_LOOP},
+    value_box::ValueBox,
+    Position, ScriptObject,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+/// A suspicious construct in a script, that doesn't prevent it from running.
+pub enum Warning {
+    /// No instruction of the block can ever be executed
+    UnreachableBlock { label: String, line: usize },
+    /// No jump leads to the label
+    UnusedLabel { label: String, line: usize },
+    /// The instruction follows an unconditional jump (or a HALT) in the same block, so it's never executed
+    DeadCode {
+        line: usize,
+        /// The line of the JUMP or the HALT
+        jump_line: usize,
+        jump: &'static str,
+    },
+    /// The jump leads to a block without instructions, so it really goes to the next block
+    JumpToEmptyBlock { label: String, line: usize },
+    /// The instruction reads a tile that is empty at the beginning and that no path to it writes
+    /// (see [`uninitialized_reads`])
+    UninitializedRead { address: usize, line: usize },
+    /// The effect of the instruction is never used (see [`dead_instructions`])
+    DeadInstruction { effect: DeadEffect, line: usize },
+    /// The script can't end after the instruction (see [`infinite_loops`])
+    InfiniteLoop { line: usize },
+}
+
+impl Warning {
+    /// The line of the script the warning is about
+    pub fn line(&self) -> usize {
+        match self {
+            Warning::UnreachableBlock { line, .. }
+            | Warning::UnusedLabel { line, .. }
+            | Warning::DeadCode { line, .. }
+            | Warning::JumpToEmptyBlock { line, .. }
+            | Warning::UninitializedRead { line, .. }
+            | Warning::DeadInstruction { line, .. }
+            | Warning::InfiniteLoop { line } => *line,
+        }
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: ", self.line())?;
+        match self {
+            Warning::UnreachableBlock { label, .. } => {
+                write!(f, "block {} is unreachable", label)
+            }
+            Warning::UnusedLabel { label, .. } => {
+                write!(f, "label {} is never jumped to", label)
+            }
+            Warning::DeadCode {
+                jump_line, jump, ..
+            } => {
+                write!(f, "dead code after the {} on line {}", jump, jump_line)
+            }
+            Warning::JumpToEmptyBlock { label, .. } => {
+                write!(f, "jump to the empty block {}", label)
+            }
+            Warning::UninitializedRead { address, .. } => {
+                write!(f, "tile {} is read before being written", address)
+            }
+            Warning::DeadInstruction { effect, .. } => write!(f, "useless instruction: {}", effect),
+            Warning::InfiniteLoop { .. } => write!(f, "{}", INFINITE_LOOP),
+        }
+    }
+}
+
+/// Look for suspicious constructs in the script, sorted by line.
+/// `memory` is the floor at the beginning of the execution: reading its tiles is fine.
+///
+/// The jumps must point to existing blocks (see [`ScriptObject::validate`]).
+pub fn lint(script: &ScriptObject, memory: &HashMap<usize, ValueBox>) -> Vec<Warning> {
+    let mut warnings = vec![];
+    let reachable = script.reachable_positions();
+    let is_reachable =
+        |block: usize, instruction: usize| reachable.contains(&Position { block, instruction });
+
+    let jumped_to: HashSet<usize> = script
+        .jumps()
+        .filter_map(|(_, _, target)| target.block())
+        .collect();
+
+    for block in script.blocks() {
+        let has_label = block.index() > 0;
+
+        if !block.instructions.is_empty() && !is_reachable(block.index(), 0) {
+            // The entry block is always reachable
+            warnings.push(Warning::UnreachableBlock {
+                label: block.name().to_string(),
+                line: block.line(),
+            });
+        } else if has_label && !jumped_to.contains(&block.index()) {
+            warnings.push(Warning::UnusedLabel {
+                label: block.name().to_string(),
+                line: block.line(),
+            });
+        }
+
+        for (i, instruction) in block.instructions.iter().enumerate() {
+            let line = block.line_of(i).unwrap_or_default();
+
+            if matches!(
+                instruction,
+                Instruction::Jump(_) | Instruction::Halt | Instruction::Ret
+            ) && is_reachable(block.index(), i)
+                && i + 1 < block.instructions.len()
+            {
+                warnings.push(Warning::DeadCode {
+                    line: block.line_of(i + 1).unwrap_or_default(),
+                    jump_line: line,
+                    jump: instruction.name(),
+                });
+            }
+
+            if let Some(target) = instruction.jump_target() {
+                let target_is_empty = target
+                    .block()
+                    .and_then(|index| script.get_block_by_index(index))
+                    .is_some_and(|target| target.instructions.is_empty());
+                if target_is_empty {
+                    warnings.push(Warning::JumpToEmptyBlock {
+                        label: target.label().to_string(),
+                        line,
+                    });
+                }
+            }
+        }
+    }
+
+    warnings.extend(uninitialized_reads(script, memory).into_iter().map(|read| {
+        Warning::UninitializedRead {
+            address: read.address,
+            line: script.get_line(read.position).unwrap_or_default(),
+        }
+    }));
+    warnings.extend(
+        dead_instructions(script)
+            .into_iter()
+            .filter(|dead| reachable.contains(&dead.position))
+            .map(|dead| Warning::DeadInstruction {
+                effect: dead.effect,
+                line: script.get_line(dead.position).unwrap_or_default(),
+            }),
+    );
+    warnings.extend(infinite_loops(script).into_iter().map(|infinite_loop| {
+        Warning::InfiniteLoop {
+            line: script.get_line(infinite_loop.entry).unwrap_or_default(),
+        }
+    }));
+    warnings.sort_by_key(Warning::line);
+    warnings
+}
+
+#[cfg(test)]
+mod lint_tests {
+    use super::*;
+
+    fn lint_script(script: &str, memory: &HashMap<usize, ValueBox>) -> Vec<Warning> {
+        lint(&script.parse::<ScriptObject>().unwrap(), memory)
+    }
+
+    #[test]
+    fn test_clean_script() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            COPYTO   0
+            ADD      0
+            JUMPZ    b
+            OUTBOX
+        b:
+            JUMP     a
+        ";
+        assert_eq!(lint_script(script, &HashMap::new()), vec![]);
+    }
+
+    #[test]
+    fn test_control_flow_warnings() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            JUMP     c
+            OUTBOX
+        b:
+            OUTBOX
+        c:
+        d:
+            OUTBOX
+            JUMP     a
+        ";
+        assert_eq!(
+            lint_script(script, &HashMap::new()),
+            vec![
+                Warning::JumpToEmptyBlock {
+                    label: "c".to_string(),
+                    line: 4
+                },
+                Warning::DeadCode {
+                    line: 5,
+                    jump_line: 4,
+                    jump: "JUMP",
+                },
+                Warning::UnreachableBlock {
+                    label: "b".to_string(),
+                    line: 6
+                },
+                Warning::UnusedLabel {
+                    label: "d".to_string(),
+                    line: 9
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_uninitialized_reads() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            COPYFROM 5
+            COPYTO   0
+            ADD      0
+            SUB      [1]
+            BUMPUP   2
+        ";
+        let memory = HashMap::from([(5, ValueBox::from(0))]);
+        let warnings = lint_script(script, &memory);
+        assert_eq!(
+            warnings,
+            vec![
+                Warning::UninitializedRead {
+                    address: 1,
+                    line: 5
+                },
+                Warning::DeadInstruction {
+                    effect: DeadEffect::UnusedHead,
+                    line: 5
+                },
+                Warning::UninitializedRead {
+                    address: 2,
+                    line: 6
+                },
+            ]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "line 5: tile 1 is read before being written"
+        );
+
+        // Any tile can be written through a pointer
+        let script = script.replace("COPYTO   0", "COPYTO   [5]");
+        assert!(!lint_script(&script, &memory)
+            .iter()
+            .any(|warning| matches!(warning, Warning::UninitializedRead { .. })));
+    }
+
+    #[test]
+    fn test_dead_instructions() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            COPYTO   0
+            COPYFROM 0
+            INBOX
+            COPYTO   0
+            OUTBOX
+            JUMP     a
+        ";
+        assert_eq!(
+            lint_script(script, &HashMap::new()),
+            vec![
+                Warning::DeadInstruction {
+                    effect: DeadEffect::UnusedHead,
+                    line: 5
+                },
+                Warning::DeadInstruction {
+                    effect: DeadEffect::UnusedTile(0),
+                    line: 7
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! The listing of a script, like the listing file of an assembler: each instruction with its
+//! index in the flat program, its line, its block, the index its jump goes to, and its tile.
+//!
+//! The index is the one of the bytecode, so that the jumps are numbers instead of labels.
+//! The tiles are shown with their names (`-l` and `#const`), or as "drawn" when the script
+//! labels them with a drawing.
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use super::{
+    value_box::{TileNames, ValueBoxMemoryAddress},
+    ScriptObject,
+};
+
+/// The tile of the instruction, with its name if known
+fn tile(
+    address: &ValueBoxMemoryAddress,
+    names: &BTreeMap<usize, Vec<&str>>,
+    script: &ScriptObject,
+) -> String {
+    let (tile, text) = match address {
+        ValueBoxMemoryAddress::Pointer(tile) => (*tile, tile.to_string()),
+        ValueBoxMemoryAddress::PointerAddress(tile) => (*tile, format!("[{}]", tile)),
+    };
+    match names.get(&tile) {
+        Some(names) => format!("{} ({})", text, names.join(", ")),
+        None if script.tile_labels().contains_key(&tile) => format!("{} (drawn)", text),
+        None => text,
+    }
+}
+
+/// The listing of the script, a line per instruction after a header
+pub fn listing(script: &ScriptObject, tile_names: &TileNames) -> String {
+    let bytecode = script.bytecode();
+    let mut names = BTreeMap::<usize, Vec<&str>>::new();
+    for (name, address) in tile_names.iter().chain(script.constants()) {
+        names.entry(*address).or_default().push(name);
+    }
+    for names in names.values_mut() {
+        names.sort_unstable();
+        names.dedup();
+    }
+
+    let rows = bytecode
+        .ops
+        .iter()
+        .enumerate()
+        .map(|(index, op)| {
+            let block = script
+                .get_block_by_index(op.position.block)
+                .map(|block| block.name().to_string())
+                .unwrap_or_default();
+            // A jump past the last instruction ends the script
+            let target = match (op.instruction.jump_target(), op.jump) {
+                (None, _) => String::new(),
+                (Some(_), Some(target)) if target == bytecode.ops.len() => "end".to_string(),
+                (Some(_), Some(target)) => target.to_string(),
+                (Some(_), None) => "?".to_string(),
+            };
+            let tile = op
+                .instruction
+                .memory_address()
+                .map(|address| tile(address, &names, script))
+                .unwrap_or_default();
+            [
+                index.to_string(),
+                script.get_line(op.position).unwrap_or_default().to_string(),
+                block,
+                op.instruction.to_string(),
+                target,
+                tile,
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let header = ["index", "line", "block", "instruction", "target", "tile"];
+    let widths = (0..header.len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].len())
+                .chain([header[column].len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+    let mut listing = String::new();
+    for row in [header.map(str::to_string)].iter().chain(&rows) {
+        // The numbers are aligned to the right, the text to the left
+        let line = format!(
+            "{:>w0$}  {:>w1$}  {:<w2$}  {:<w3$}  {:>w4$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            row[4],
+            row[5],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+            w4 = widths[4],
+        );
+        writeln!(listing, "{}", line.trim_end()).unwrap();
+    }
+    listing
+}
+
+#[cfg(test)]
+mod listing_tests {
+    use super::*;
+
+    #[test]
+    fn test_listing() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+        a:
+            COPYTO   0
+            BUMPUP   [1]
+            JUMPZ    b
+            JUMP     a
+        b:
+        ";
+        let script = script.parse::<ScriptObject>().unwrap();
+        let tile_names = TileNames::from([("ptr".to_string(), 1)]);
+        assert_eq!(
+            listing(&script, &tile_names),
+            "index  line  block  instruction  target  tile
+    0     2  entry  INBOX
+    1     4  a      COPYTO 0             0
+    2     5  a      BUMPUP [1]           [1] (ptr)
+    3     6  a      JUMPZ b         end
+    4     7  a      JUMP a            1
+"
+        );
+
+        // The tiles named with #const, along with the ones of -l
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        #const tmp 3
+        #const ptr 1
+            COPYFROM tmp
+            COPYTO   [ptr]
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        assert_eq!(
+            listing(&script, &tile_names),
+            "index  line  block  instruction  target  tile
+    0     4  entry  COPYFROM 3           3 (tmp)
+    1     5  entry  COPYTO [1]           [1] (ptr)
+"
+        );
+    }
+}
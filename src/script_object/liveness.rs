@@ -0,0 +1,268 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Display,
+};
+
+use super::{
+    instruction::{DebugOperand, Instruction},
+    value_box::ValueBoxMemoryAddress,
+    Position, ScriptObject,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Why an instruction has no use
+pub enum DeadEffect {
+    /// The value the instruction puts in the head is replaced (or the script ends) before being used
+    UnusedHead,
+    /// The tile written by the COPYTO is overwritten (or the script ends) before being read
+    UnusedTile(usize),
+}
+
+impl Display for DeadEffect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeadEffect::UnusedHead => write!(f, "the value in hand is replaced before being used"),
+            DeadEffect::UnusedTile(address) => {
+                write!(f, "tile {} is overwritten before being read", address)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// An instruction whose effect is never used: removing it doesn't change the outputs,
+/// assuming that the execution doesn't fail (a removed COPYFROM from an empty tile can't fail anymore)
+pub struct DeadInstruction {
+    pub position: Position,
+    pub effect: DeadEffect,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// What may be read later: the head, and tiles
+struct Live {
+    head: bool,
+    /// Any tile may be read (through a pointer)
+    all_tiles: bool,
+    tiles: BTreeSet<usize>,
+}
+
+impl Live {
+    fn union(&mut self, other: &Live) {
+        self.head |= other.head;
+        self.all_tiles |= other.all_tiles;
+        self.tiles.extend(&other.tiles);
+    }
+
+    fn has_tile(&self, address: usize) -> bool {
+        self.all_tiles || self.tiles.contains(&address)
+    }
+}
+
+/// What is live before the instruction, given what is live after it
+fn live_before(instruction: &Instruction, after: &Live) -> Live {
+    let mut live = after.clone();
+
+    // The head
+    match instruction {
+        Instruction::Out
+        | Instruction::CopyTo(_)
+        | Instruction::Add(_)
+        | Instruction::Sub(_)
+        | Instruction::Mul(_)
+        | Instruction::Div(_)
+        | Instruction::Mod(_)
+        | Instruction::Swap(_)
+        | Instruction::JumpIfZero(_)
+        | Instruction::JumpIfNegative(_)
+        | Instruction::JumpIfPositive(_)
+        | Instruction::JumpIfEqual(..)
+        | Instruction::Push
+        | Instruction::TempCopyTo
+        | Instruction::Exchange
+        | Instruction::AssertZero
+        | Instruction::AssertNegative => live.head = true,
+        Instruction::In
+        | Instruction::CopyFrom(_)
+        | Instruction::BumpUp(_)
+        | Instruction::BumpDown(_)
+        | Instruction::Pop
+        | Instruction::TempCopyFrom => live.head = false,
+        Instruction::Jump(_)
+        | Instruction::Call(_)
+        | Instruction::Debug(_)
+        | Instruction::AssertEqual(..)
+        | Instruction::Nop => {}
+        // Nothing is used after the end of the program
+        Instruction::Halt => return Live::default(),
+        // The instruction after any CALL could be next: everything may be used
+        Instruction::Ret => {
+            return Live {
+                head: true,
+                all_tiles: true,
+                ..Live::default()
+            }
+        }
+    }
+
+    // The tiles
+    match instruction {
+        Instruction::CopyTo(ValueBoxMemoryAddress::Pointer(address)) => {
+            live.tiles.remove(address);
+        }
+        Instruction::CopyTo(ValueBoxMemoryAddress::PointerAddress(pointer)) => {
+            live.tiles.insert(*pointer);
+        }
+        Instruction::CopyFrom(address)
+        | Instruction::Add(address)
+        | Instruction::Sub(address)
+        | Instruction::Mul(address)
+        | Instruction::Div(address)
+        | Instruction::Mod(address)
+        | Instruction::Swap(address)
+        | Instruction::JumpIfEqual(address, _)
+        | Instruction::AssertEqual(address, _)
+        | Instruction::Debug(DebugOperand::Tile(address))
+        | Instruction::BumpUp(address)
+        | Instruction::BumpDown(address) => match address {
+            ValueBoxMemoryAddress::Pointer(address) => {
+                live.tiles.insert(*address);
+            }
+            ValueBoxMemoryAddress::PointerAddress(pointer) => {
+                live.tiles.insert(*pointer);
+                live.all_tiles = true;
+            }
+        },
+        // The top of the stack could be any tile
+        Instruction::Pop => live.all_tiles = true,
+        _ => {}
+    }
+
+    live
+}
+
+/// Find the instructions whose effect is never used:
+/// a COPYFROM, ADD or SUB whose result is replaced before being used,
+/// and a COPYTO to a tile that is overwritten before being read.
+///
+/// The final floor isn't considered used: it isn't part of the outputs.
+pub fn dead_instructions(script: &ScriptObject) -> Vec<DeadInstruction> {
+    let positions = script
+        .blocks()
+        .iter()
+        .flat_map(|block| {
+            (0..block.instructions.len()).map(|instruction| Position {
+                block: block.index(),
+                instruction,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // What is live after each instruction, computed backwards until nothing changes
+    let mut live_after: HashMap<Position, Live> = HashMap::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for position in positions.iter().rev() {
+            let mut live = Live::default();
+            for successor in script.successors(*position) {
+                let after = live_after.get(&successor).cloned().unwrap_or_default();
+                live.union(&live_before(
+                    script.get_instruction(successor).unwrap(),
+                    &after,
+                ));
+            }
+            if live_after.get(position) != Some(&live) {
+                live_after.insert(*position, live);
+                changed = true;
+            }
+        }
+    }
+
+    positions
+        .into_iter()
+        .filter_map(|position| {
+            let live = &live_after[&position];
+            let effect = match script.get_instruction(position).unwrap() {
+                Instruction::CopyFrom(_) | Instruction::Add(_) | Instruction::Sub(_)
+                    if !live.head =>
+                {
+                    DeadEffect::UnusedHead
+                }
+                Instruction::CopyTo(ValueBoxMemoryAddress::Pointer(address))
+                    if !live.has_tile(*address) =>
+                {
+                    DeadEffect::UnusedTile(*address)
+                }
+                _ => return None,
+            };
+            Some(DeadInstruction { position, effect })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod liveness_tests {
+    use super::*;
+
+    fn dead_lines(script: &str) -> Vec<(usize, DeadEffect)> {
+        let script = script.parse::<ScriptObject>().unwrap();
+        dead_instructions(&script)
+            .into_iter()
+            .map(|dead| (script.get_line(dead.position).unwrap(), dead.effect))
+            .collect()
+    }
+
+    #[test]
+    fn test_unused_head() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            COPYTO   0
+            COPYFROM 0
+            COPYFROM 1
+            OUTBOX
+            ADD      0
+        ";
+        assert_eq!(
+            dead_lines(script),
+            vec![(4, DeadEffect::UnusedHead), (7, DeadEffect::UnusedHead)]
+        );
+    }
+
+    #[test]
+    fn test_unused_tile() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            COPYTO   0
+            COPYTO   1
+            JUMPZ    b
+            COPYTO   0
+            ADD      0
+            OUTBOX
+        b:
+            COPYTO   2
+            JUMP     a
+        ";
+        // The first COPYTO 0 is overwritten on both paths, the COPYTOs to 1 and 2 are never read
+        assert_eq!(
+            dead_lines(script),
+            vec![
+                (4, DeadEffect::UnusedTile(0)),
+                (5, DeadEffect::UnusedTile(1)),
+                (11, DeadEffect::UnusedTile(2))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reads_through_pointers_keep_all_tiles() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            COPYTO   0
+            COPYTO   1
+            COPYFROM [1]
+            OUTBOX
+        ";
+        assert_eq!(dead_lines(script), vec![]);
+    }
+}
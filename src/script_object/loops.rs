@@ -0,0 +1,145 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{instruction::Instruction, Position, ScriptObject};
+
+/// What is wrong with an infinite loop, to warn about it
+pub const INFINITE_LOOP: &str = "infinite loop from here: no INBOX, HALT or end of the script can be reached, the script runs until it fails or hits the step limit";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A part of the script that can't be left once entered: no INBOX can end the program,
+/// and no path leads to a HALT, a RET or the end of the script.
+/// The script runs until the step limit, or until an instruction fails.
+pub struct InfiniteLoop {
+    /// The first instruction from which the script can't end, on a path from its beginning
+    pub entry: Position,
+}
+
+/// Whether the program can end at the instruction: an INBOX on an empty inbox, a HALT, a RET
+/// (it may go anywhere), or the last instruction, or a jump to the end of the script
+fn can_end_at(script: &ScriptObject, position: Position) -> bool {
+    let instruction = script.get_instruction(position).unwrap();
+    let goes_to_end = |position| script.next_instruction_position(position).is_none();
+    let next = Position {
+        instruction: position.instruction + 1,
+        ..position
+    };
+    let target_is_end = instruction
+        .jump_target()
+        .and_then(|target| target.block())
+        .is_some_and(|block| {
+            goes_to_end(Position {
+                block,
+                instruction: 0,
+            })
+        });
+    match instruction {
+        Instruction::In | Instruction::Halt | Instruction::Ret => true,
+        Instruction::Jump(_) => target_is_end,
+        _ => target_is_end || goes_to_end(next),
+    }
+}
+
+/// Find the loops the script can never leave, among the instructions that can be executed.
+/// Conditional jumps are considered able to go both ways, whatever the value in hand.
+pub fn infinite_loops(script: &ScriptObject) -> Vec<InfiniteLoop> {
+    let reachable = script.reachable_positions();
+    let successors = reachable
+        .iter()
+        .map(|position| (*position, script.successors(*position)))
+        .collect::<HashMap<_, _>>();
+
+    // The instructions from which the program can end, found backwards from the ones that can end it
+    let mut can_end = reachable
+        .iter()
+        .copied()
+        .filter(|position| can_end_at(script, *position))
+        .collect::<HashSet<_>>();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (position, next) in &successors {
+            if !can_end.contains(position) && next.iter().any(|next| can_end.contains(next)) {
+                can_end.insert(*position);
+                changed = true;
+            }
+        }
+    }
+
+    // The trapped instructions reached from outside the loop, or the first one of the script
+    let trapped = |position: &Position| !can_end.contains(position);
+    let mut entries = successors
+        .iter()
+        .filter(|(position, _)| !trapped(position))
+        .flat_map(|(_, next)| next.iter().copied().filter(trapped))
+        .collect::<HashSet<_>>();
+    entries.extend(
+        script
+            .next_instruction_position(Position::default())
+            .filter(trapped),
+    );
+
+    let mut loops = entries
+        .into_iter()
+        .map(|entry| InfiniteLoop { entry })
+        .collect::<Vec<_>>();
+    loops.sort_by_key(|infinite_loop| (infinite_loop.entry.block, infinite_loop.entry.instruction));
+    loops
+}
+
+#[cfg(test)]
+mod loops_tests {
+    use super::*;
+
+    fn loop_lines(script: &str) -> Vec<usize> {
+        let script = script.parse::<ScriptObject>().unwrap();
+        infinite_loops(&script)
+            .into_iter()
+            .map(|infinite_loop| script.get_line(infinite_loop.entry).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_infinite_loops() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            COPYTO   0
+        a:
+            BUMPUP   0
+            OUTBOX
+            JUMP     a
+        ";
+        // Once the value is read, the script can't end
+        assert_eq!(loop_lines(script), vec![3]);
+
+        // A conditional jump can't leave the loop if both ways stay in it
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            JUMPZ    b
+        b:
+            JUMP     a
+        ";
+        assert_eq!(loop_lines(script), vec![3]);
+    }
+
+    #[test]
+    fn test_loops_that_can_end() {
+        // The INBOX ends the program when the inbox is empty
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            OUTBOX
+            JUMP     a
+        ";
+        assert!(loop_lines(script).is_empty());
+
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+        a:
+            BUMPDN   0
+            JUMPZ    b
+            JUMP     a
+        b:
+        ";
+        assert!(loop_lines(script).is_empty());
+    }
+}
@@ -0,0 +1,325 @@
+//! The macros of the scripts: lines written once and copied wherever they're used.
+//!
+//! ```text
+//! #define SWAP(a, b)
+//!     COPYFROM a
+//!     COPYTO   9
+//!     COPYFROM b
+//!     COPYTO   a
+//!     COPYFROM 9
+//!     COPYTO   b
+//! #end
+//!
+//!     SWAP(0, 1)
+//! ```
+//!
+//! The body of a macro replaces each use of the macro, with its parameters replaced by the
+//! arguments, before the instructions are parsed. The parameters are replaced as whole words,
+//! so they can be tiles, pointers (`[a]`) or labels. A macro can use the macros defined
+//! before it, but not itself.
+
+use std::collections::{HashMap, HashSet};
+
+use super::{split_remark, ParseScriptObjectError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Macro {
+    pub name: String,
+    pub parameters: Vec<String>,
+    /// The line of the "#define" (starting at 1)
+    pub line: usize,
+    /// The lines between the "#define" and the "#end"
+    pub body: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// The macros defined in a script, and the lines of their definitions
+pub struct Macros {
+    macros: HashMap<String, Macro>,
+    /// The lines of the definitions (starting at 0), which aren't part of the program
+    definition_lines: HashSet<usize>,
+}
+
+/// A line coming from the body of a macro
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpandedLine {
+    /// The macro whose body holds the line
+    pub name: String,
+    /// The line in the body of the macro, among the lines of the script (starting at 1)
+    pub line: usize,
+    /// The line with the parameters replaced by the arguments
+    pub text: String,
+}
+
+fn is_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Whether the line starts or ends the definition of a macro
+pub fn is_directive(line: &str) -> bool {
+    let line = split_remark(line).0.trim();
+    line.starts_with("#define") || line == "#end"
+}
+
+/// Split "NAME(a, b)" into the name and the trimmed items between the parentheses
+fn split_call(text: &str) -> Option<(&str, Vec<&str>)> {
+    let (name, rest) = text.trim().split_once('(')?;
+    let items = rest.strip_suffix(')')?.trim();
+    let items = match items {
+        "" => vec![],
+        items => items.split(',').map(str::trim).collect(),
+    };
+    Some((name.trim_end(), items))
+}
+
+/// Replace the parameters by the arguments, as whole words
+fn substitute(line: &str, parameters: &[String], arguments: &[&str]) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut word = String::new();
+    let flush = |word: &mut String, result: &mut String| {
+        match parameters.iter().position(|parameter| parameter == word) {
+            Some(i) => result.push_str(arguments[i]),
+            None => result.push_str(word),
+        }
+        word.clear();
+    };
+    for c in line.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut result);
+            result.push(c);
+        }
+    }
+    flush(&mut word, &mut result);
+    result
+}
+
+impl Macros {
+    /// Read the definitions of the macros, before the "DEFINE" sections
+    pub fn parse(lines: &[&str]) -> Result<Self, ParseScriptObjectError> {
+        let mut macros = Self::default();
+        // The macro being defined
+        let mut current: Option<Macro> = None;
+
+        for (i, raw_line) in lines.iter().enumerate() {
+            let line = split_remark(raw_line).0.trim();
+            let invalid = |reason: String| ParseScriptObjectError::InvalidMacro {
+                line: i + 1,
+                reason,
+            };
+            if line.starts_with("DEFINE") && current.is_none() {
+                break;
+            }
+
+            if let Some(header) = line.strip_prefix("#define") {
+                if current.is_some() {
+                    return Err(invalid(
+                        "a macro can't be defined inside another one".to_string(),
+                    ));
+                }
+                let expected = || invalid("expected '#define NAME(parameters)'".to_string());
+                let (name, parameters) = match split_call(header) {
+                    Some((name, parameters)) => (name, parameters),
+                    None => (header.trim(), vec![]),
+                };
+                if !header.starts_with(char::is_whitespace) || !is_name(name) {
+                    return Err(expected());
+                }
+                if !parameters.iter().all(|parameter| is_name(parameter)) {
+                    return Err(expected());
+                }
+                if let Some(i) =
+                    (1..parameters.len()).find(|&i| parameters[..i].contains(&parameters[i]))
+                {
+                    return Err(invalid(format!(
+                        "the parameter {} is given twice",
+                        parameters[i]
+                    )));
+                }
+                if let Some(defined) = macros.macros.get(name) {
+                    return Err(invalid(format!(
+                        "the macro {} is already defined on line {}",
+                        name, defined.line
+                    )));
+                }
+                current = Some(Macro {
+                    name: name.to_string(),
+                    parameters: parameters.into_iter().map(str::to_string).collect(),
+                    line: i + 1,
+                    body: vec![],
+                });
+            } else if line == "#end" {
+                let Some(definition) = current.take() else {
+                    return Err(invalid("'#end' without '#define'".to_string()));
+                };
+                macros.macros.insert(definition.name.clone(), definition);
+            } else if let Some(definition) = current.as_mut() {
+                if line.starts_with("DEFINE") {
+                    return Err(invalid(
+                        "a macro can't hold the drawings of the script".to_string(),
+                    ));
+                }
+                definition.body.push(raw_line.to_string());
+            } else {
+                continue;
+            }
+            macros.definition_lines.insert(i);
+        }
+
+        match current {
+            Some(definition) => Err(ParseScriptObjectError::InvalidMacro {
+                line: definition.line,
+                reason: format!("the macro {} never ends: expected '#end'", definition.name),
+            }),
+            None => Ok(macros),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Macro> {
+        self.macros.get(name)
+    }
+
+    /// Whether the line (starting at 0) is part of the definition of a macro
+    pub fn is_definition_line(&self, line: usize) -> bool {
+        self.definition_lines.contains(&line)
+    }
+
+    /// The lines replacing the line (starting at 0) of the script,
+    /// None if it doesn't use a macro
+    pub fn expand(
+        &self,
+        line: usize,
+        text: &str,
+    ) -> Result<Option<Vec<ExpandedLine>>, ParseScriptObjectError> {
+        let mut expanded = vec![];
+        match self.expand_into(text, &mut vec![], &mut expanded) {
+            Ok(true) => Ok(Some(expanded)),
+            Ok(false) => Ok(None),
+            Err(reason) => Err(ParseScriptObjectError::InvalidMacro {
+                line: line + 1,
+                reason,
+            }),
+        }
+    }
+
+    /// Push the lines replacing the text if it uses a macro, and return whether it does
+    fn expand_into(
+        &self,
+        text: &str,
+        stack: &mut Vec<String>,
+        expanded: &mut Vec<ExpandedLine>,
+    ) -> Result<bool, String> {
+        let Some((name, arguments)) = split_call(text) else {
+            return Ok(false);
+        };
+        let Some(definition) = self.macros.get(name) else {
+            return Ok(false);
+        };
+        // The macros being expanded, inside each other
+        if stack.iter().any(|used| used == name) {
+            return Err(format!("the macro {} uses itself", name));
+        }
+        if arguments.len() != definition.parameters.len() || arguments.contains(&"") {
+            return Err(format!(
+                "the macro {} takes {} argument(s): {}",
+                name,
+                definition.parameters.len(),
+                text.trim()
+            ));
+        }
+
+        stack.push(name.to_string());
+        for (i, line) in definition.body.iter().enumerate() {
+            let line = substitute(line, &definition.parameters, &arguments);
+            if !self.expand_into(split_remark(&line).0, stack, expanded)? {
+                expanded.push(ExpandedLine {
+                    name: name.to_string(),
+                    line: definition.line + 1 + i,
+                    text: line,
+                });
+            }
+        }
+        stack.pop();
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod macros_tests {
+    use super::*;
+
+    const SCRIPT: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+#define SWAP(a, b)
+    COPYFROM a
+    COPYTO   9
+    COPYFROM b
+    COPYTO   a
+    COPYFROM 9
+    COPYTO   b
+#end
+#define ROTATE(x, y, z)
+    SWAP(x, y)
+    SWAP(y, z)
+#end
+    SWAP(0, [1])
+";
+
+    #[test]
+    fn test_expand() {
+        let lines = SCRIPT.lines().collect::<Vec<_>>();
+        let macros = Macros::parse(&lines).unwrap();
+        assert!((1..12).all(|line| macros.is_definition_line(line)));
+        assert!(!macros.is_definition_line(13));
+        assert_eq!(macros.get("ROTATE").unwrap().parameters, ["x", "y", "z"]);
+
+        let expanded = macros.expand(13, lines[13]).unwrap().unwrap();
+        assert_eq!(
+            expanded
+                .iter()
+                .map(|line| line.text.trim())
+                .collect::<Vec<_>>(),
+            [
+                "COPYFROM 0",
+                "COPYTO   9",
+                "COPYFROM [1]",
+                "COPYTO   0",
+                "COPYFROM 9",
+                "COPYTO   [1]"
+            ]
+        );
+        assert_eq!(expanded[2].line, 5);
+
+        let expanded = macros.expand(0, "ROTATE(a1, b, c)").unwrap().unwrap();
+        assert_eq!(expanded.len(), 12);
+        assert_eq!(expanded[6].text.trim(), "COPYFROM b");
+        assert_eq!((expanded[6].name.as_str(), expanded[6].line), ("SWAP", 3));
+
+        assert_eq!(macros.expand(0, "    COPYFROM 3").unwrap(), None);
+        assert_eq!(macros.expand(0, "UNKNOWN(3)").unwrap(), None);
+        assert!(matches!(
+            macros.expand(4, "SWAP(1)"),
+            Err(ParseScriptObjectError::InvalidMacro { line: 5, .. })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_definitions() {
+        let line_of = |script: &str| match Macros::parse(&script.lines().collect::<Vec<_>>()) {
+            Err(ParseScriptObjectError::InvalidMacro { line, .. }) => line,
+            other => panic!("expected an invalid macro, got {:?}", other),
+        };
+
+        assert_eq!(line_of("#define A\n    INBOX"), 1);
+        assert_eq!(line_of("#define A\n#define B\n#end"), 2);
+        assert_eq!(line_of("#define 1A\n#end"), 1);
+        assert_eq!(line_of("#define A(x, x)\n#end"), 1);
+        assert_eq!(line_of("#define A\n#end\n#define A\n#end"), 3);
+        assert_eq!(line_of("    INBOX\n#end"), 2);
+
+        let recursive = "#define A\n    A()\n#end";
+        let macros = Macros::parse(&recursive.lines().collect::<Vec<_>>()).unwrap();
+        assert!(macros.expand(3, "A()").is_err());
+    }
+}
@@ -0,0 +1,180 @@
+//! The mutants of a script, for mutation testing: copies of the script with one small change
+//! to one instruction. A mutant that still passes all the tests of the script points at an
+//! instruction that the tests don't check.
+//!
+//! An instruction is mutated by replacing it (JUMPZ and JUMPN are swapped, and so are ADD and
+//! SUB, and BUMPUP and BUMPDN, and its address is moved to the previous or the next tile),
+//! or by deleting it.
+
+use std::fmt::Display;
+
+use super::{
+    instruction::Instruction, value_box::ValueBoxMemoryAddress, Block, Position, ScriptObject, Span,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mutation {
+    /// The instruction is replaced by another one
+    Replaced(Instruction),
+    Deleted,
+}
+
+impl Display for Mutation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mutation::Replaced(instruction) => write!(f, "replaced by {}", instruction),
+            Mutation::Deleted => write!(f, "deleted"),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// The script with one mutated instruction
+pub struct Mutant {
+    /// Where the mutated instruction is written in the original script
+    pub span: Span,
+    pub mutation: Mutation,
+    pub script: ScriptObject,
+}
+
+/// The instructions replacing the instruction in its mutants
+fn replacements(instruction: &Instruction) -> Vec<Instruction> {
+    let mut replacements = match instruction {
+        Instruction::JumpIfZero(target) => vec![Instruction::JumpIfNegative(target.clone())],
+        Instruction::JumpIfNegative(target) => vec![Instruction::JumpIfZero(target.clone())],
+        Instruction::Add(address) => vec![Instruction::Sub(*address)],
+        Instruction::Sub(address) => vec![Instruction::Add(*address)],
+        Instruction::BumpUp(address) => vec![Instruction::BumpDown(*address)],
+        Instruction::BumpDown(address) => vec![Instruction::BumpUp(*address)],
+        _ => vec![],
+    };
+
+    // The previous and the next tiles
+    if let Some(address) = instruction.memory_address() {
+        let (ValueBoxMemoryAddress::Pointer(tile) | ValueBoxMemoryAddress::PointerAddress(tile)) =
+            *address;
+        for neighbour in [tile.checked_sub(1), tile.checked_add(1)]
+            .into_iter()
+            .flatten()
+        {
+            let mut replacement = instruction.clone();
+            if let Some(
+                ValueBoxMemoryAddress::Pointer(tile) | ValueBoxMemoryAddress::PointerAddress(tile),
+            ) = replacement.memory_address_mut()
+            {
+                *tile = neighbour;
+            }
+            replacements.push(replacement);
+        }
+    }
+    replacements
+}
+
+impl ScriptObject {
+    /// Get the mutants of the script, in the order of its instructions.
+    /// DEBUG and NOP have no effect on the execution, so they aren't mutated.
+    pub fn mutants(&self) -> Vec<Mutant> {
+        let mut mutants = vec![];
+        for block in &self.blocks {
+            for (i, (instruction, span)) in block.instructions.iter().zip(&block.spans).enumerate()
+            {
+                if matches!(instruction, Instruction::Debug(_) | Instruction::Nop) {
+                    continue;
+                }
+                let position = Position {
+                    block: block.index,
+                    instruction: i,
+                };
+                let mutations = replacements(instruction)
+                    .into_iter()
+                    .map(Mutation::Replaced)
+                    .chain([Mutation::Deleted]);
+                for mutation in mutations {
+                    mutants.push(Mutant {
+                        span: span.clone(),
+                        script: self.mutated(position, &mutation),
+                        mutation,
+                    });
+                }
+            }
+        }
+        mutants
+    }
+
+    /// A copy of the script, with the instruction at the position mutated
+    fn mutated(&self, position: Position, mutation: &Mutation) -> ScriptObject {
+        let blocks = self
+            .blocks
+            .iter()
+            .map(|block| {
+                let mut instructions = block.instructions.clone();
+                let mut spans = block.spans.clone();
+                if block.index == position.block {
+                    match mutation {
+                        Mutation::Replaced(instruction) => {
+                            instructions[position.instruction] = instruction.clone()
+                        }
+                        Mutation::Deleted => {
+                            instructions.remove(position.instruction);
+                            spans.remove(position.instruction);
+                        }
+                    }
+                }
+                Block {
+                    name: block.name.clone(),
+                    index: block.index,
+                    line: block.line,
+                    instructions,
+                    spans,
+                }
+            })
+            .collect();
+        ScriptObject::new(blocks)
+    }
+}
+
+#[cfg(test)]
+mod mutate_tests {
+    use super::*;
+
+    #[test]
+    fn test_mutants() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            JUMPZ    a
+            ADD      [0]
+            OUTBOX
+            JUMP     a
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        let mutants = script.mutants();
+
+        assert_eq!(
+            mutants
+                .iter()
+                .map(|mutant| format!("{}: {}", mutant.span.line, mutant.mutation))
+                .collect::<Vec<_>>(),
+            [
+                "3: deleted",
+                "4: replaced by JUMPN a",
+                "4: deleted",
+                "5: replaced by SUB [0]",
+                "5: replaced by ADD [1]",
+                "5: deleted",
+                "6: deleted",
+                "7: deleted",
+            ]
+        );
+
+        // The jumps of a mutant are resolved
+        let jumpn = &mutants[1].script;
+        assert!(matches!(
+            jumpn.blocks()[1].instructions[1],
+            Instruction::JumpIfNegative(ref target) if target.block() == Some(1)
+        ));
+        assert_eq!(mutants[2].script.instruction_count(), 4);
+        assert_eq!(script.instruction_count(), 5);
+    }
+}
@@ -0,0 +1,670 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use super::{
+    instruction::{Instruction, JumpTarget},
+    liveness::{dead_instructions, DeadEffect},
+    Block, Comment, Position, Remark, RemarkPlacement, ScriptObject, Span,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+/// An instruction whose effect is never used, found by the optimization
+pub struct DeadLine {
+    /// Where the instruction is in the original script
+    pub span: Span,
+    pub effect: DeadEffect,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// What the optimization changed in the script
+pub struct OptimizationReport {
+    pub instructions_before: usize,
+    pub instructions_after: usize,
+    /// The instructions whose effect is never used, in the order of the script
+    pub dead_lines: Vec<DeadLine>,
+    /// Whether the dead lines were removed, or only found
+    pub dead_lines_removed: bool,
+}
+
+impl Display for OptimizationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Optimized: {} instructions before, {} after",
+            self.instructions_before, self.instructions_after
+        )?;
+        if !self.dead_lines.is_empty() {
+            let verb = if self.dead_lines_removed {
+                "Eliminated"
+            } else {
+                "Useless"
+            };
+            write!(f, "\n{} instructions:", verb)?;
+            for dead in &self.dead_lines {
+                write!(f, "\n  {} ({})", dead.span, dead.effect)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A comment or a remark of the script being optimized
+#[derive(Debug, Clone)]
+enum Note {
+    Comment(Comment),
+    Remark(Remark),
+}
+
+impl Note {
+    fn position_mut(&mut self) -> &mut Position {
+        match self {
+            Note::Comment(comment) => &mut comment.position,
+            Note::Remark(remark) => &mut remark.position,
+        }
+    }
+
+    /// The note on a line of its own, if it was at the end of a line that is removed
+    fn detached(mut self, placement: RemarkPlacement) -> Self {
+        if let Note::Remark(remark) = &mut self {
+            if remark.placement == placement {
+                remark.placement = RemarkPlacement::OwnLine;
+            }
+        }
+        self
+    }
+}
+
+/// An instruction of the script being optimized, with the notes placed just before it
+/// (and the remark at the end of its line, or of the line of the label of the block)
+#[derive(Debug, Clone)]
+struct Item {
+    instruction: Instruction,
+    span: Span,
+    comments: Vec<Note>,
+}
+
+/// A block of the script being optimized, in which instructions can be added and removed
+#[derive(Debug, Clone)]
+struct EditableBlock {
+    name: String,
+    line: usize,
+    items: Vec<Item>,
+    /// The notes placed after the last instruction
+    trailing_comments: Vec<Note>,
+}
+
+fn editable(script: &ScriptObject) -> Vec<EditableBlock> {
+    let comments_at = |position: Position| {
+        let comments = script
+            .comments
+            .iter()
+            .filter(|comment| comment.position == position)
+            .cloned()
+            .map(Note::Comment);
+        let remarks = script
+            .remarks
+            .iter()
+            .filter(|remark| remark.position == position)
+            .cloned()
+            .map(Note::Remark);
+        comments.chain(remarks).collect::<Vec<_>>()
+    };
+
+    script
+        .blocks
+        .iter()
+        .map(|block| EditableBlock {
+            name: block.name.clone(),
+            line: block.line,
+            items: block
+                .instructions
+                .iter()
+                .zip(&block.spans)
+                .enumerate()
+                .map(|(i, (instruction, span))| Item {
+                    instruction: instruction.clone(),
+                    span: span.clone(),
+                    comments: comments_at(Position {
+                        block: block.index,
+                        instruction: i,
+                    }),
+                })
+                .collect(),
+            trailing_comments: comments_at(Position {
+                block: block.index,
+                instruction: block.instructions.len(),
+            }),
+        })
+        .collect()
+}
+
+/// Build the script made of the blocks, with the drawings of the original script
+fn build(blocks: &[EditableBlock], original: &ScriptObject) -> ScriptObject {
+    let mut comments = vec![];
+    let mut remarks = vec![];
+    let blocks = blocks
+        .iter()
+        .enumerate()
+        .map(|(index, block)| {
+            let notes = block
+                .items
+                .iter()
+                .enumerate()
+                .flat_map(|(i, item)| item.comments.iter().map(move |note| (note, i)))
+                .chain(
+                    block
+                        .trailing_comments
+                        .iter()
+                        .map(|note| (note, block.items.len())),
+                );
+            for (note, instruction) in notes {
+                let mut note = note.clone();
+                *note.position_mut() = Position {
+                    block: index,
+                    instruction,
+                };
+                match note {
+                    Note::Comment(comment) => comments.push(comment),
+                    Note::Remark(remark) => remarks.push(remark),
+                }
+            }
+
+            Block {
+                name: block.name.clone(),
+                index,
+                line: block.line,
+                instructions: block
+                    .items
+                    .iter()
+                    .map(|item| item.instruction.clone())
+                    .collect(),
+                spans: block.items.iter().map(|item| item.span.clone()).collect(),
+            }
+        })
+        .collect();
+
+    ScriptObject {
+        comments,
+        remarks,
+        comment_drawings: original.comment_drawings.clone(),
+        tile_labels: original.tile_labels.clone(),
+        ..ScriptObject::new(blocks)
+    }
+}
+
+fn retarget(instruction: &mut Instruction, label: &str) {
+    if let Instruction::Jump(target)
+    | Instruction::JumpIfZero(target)
+    | Instruction::JumpIfNegative(target)
+    | Instruction::JumpIfPositive(target)
+    | Instruction::JumpIfEqual(_, target)
+    | Instruction::Call(target) = instruction
+    {
+        *target = JumpTarget::new(label);
+    }
+}
+
+fn is_jumped_to(blocks: &[EditableBlock], label: &str) -> bool {
+    blocks
+        .iter()
+        .flat_map(|block| &block.items)
+        .filter_map(|item| item.instruction.jump_target())
+        .any(|target| target.label() == label)
+}
+
+/// Make the jumps to a JUMP go directly to the end of the chain of jumps.
+/// Chains that loop forever are left as they are.
+fn thread_jumps(blocks: &mut [EditableBlock]) -> bool {
+    let indices: HashMap<String, usize> = blocks
+        .iter()
+        .enumerate()
+        .map(|(i, block)| (block.name.clone(), i))
+        .collect();
+    // The label targeted by the first instruction executed from the block, if it's a JUMP
+    let first_jump = |label: &str| {
+        let first = blocks[*indices.get(label)?..]
+            .iter()
+            .find_map(|block| block.items.first())?;
+        match &first.instruction {
+            Instruction::Jump(target) => Some(target.label().to_string()),
+            _ => None,
+        }
+    };
+    let end_of_chain = |label: &str| {
+        let mut visited = HashSet::new();
+        let mut current = label.to_string();
+        while let Some(next) = first_jump(&current) {
+            visited.insert(current);
+            if visited.contains(&next) {
+                return None;
+            }
+            current = next;
+        }
+        Some(current)
+    };
+
+    let mut retargeted = vec![];
+    for (b, block) in blocks.iter().enumerate() {
+        for (i, item) in block.items.iter().enumerate() {
+            if let Some(target) = item.instruction.jump_target() {
+                match end_of_chain(target.label()) {
+                    Some(end) if end != target.label() => retargeted.push((b, i, end)),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    for (b, i, label) in &retargeted {
+        retarget(&mut blocks[*b].items[*i].instruction, label);
+    }
+    !retargeted.is_empty()
+}
+
+/// Remove the instructions that are never executed, and the jumps to the instruction that comes next anyway
+fn remove_useless_instructions(blocks: &mut [EditableBlock], original: &ScriptObject) -> bool {
+    let script = build(blocks, original);
+    let reachable = script.reachable_positions();
+    let next_after = |position: Position| {
+        script.next_instruction_position(Position {
+            instruction: position.instruction + 1,
+            ..position
+        })
+    };
+    let useless_jumps: HashSet<Position> = script
+        .jumps()
+        // A CALL to the next instruction still has to be returned from
+        .filter(|(_, instruction, _)| !matches!(instruction, Instruction::Call(_)))
+        .filter(|(position, _, target)| {
+            let target = target.block().and_then(|block| {
+                script.next_instruction_position(Position {
+                    block,
+                    instruction: 0,
+                })
+            });
+            target == next_after(*position)
+        })
+        .map(|(position, _, _)| position)
+        .collect();
+
+    let unreachable = script
+        .blocks()
+        .iter()
+        .flat_map(|block| {
+            (0..block.instructions.len()).map(|instruction| Position {
+                block: block.index(),
+                instruction,
+            })
+        })
+        .filter(|position| !reachable.contains(position));
+    let useless = useless_jumps.into_iter().chain(unreachable).collect();
+    remove_positions(blocks, &useless)
+}
+
+/// Remove the instructions whose effect is never used, and record them in `eliminated`
+fn eliminate_dead_instructions(
+    blocks: &mut [EditableBlock],
+    original: &ScriptObject,
+    eliminated: &mut Vec<DeadLine>,
+) -> bool {
+    let script = build(blocks, original);
+    let dead = dead_instructions(&script);
+    for instruction in &dead {
+        eliminated.push(DeadLine {
+            span: script.get_span(instruction.position).unwrap().clone(),
+            effect: instruction.effect,
+        });
+    }
+    remove_positions(
+        blocks,
+        &dead
+            .iter()
+            .map(|instruction| instruction.position)
+            .collect(),
+    )
+}
+
+/// Remove the instructions at the positions. Their comments stay where they were.
+fn remove_positions(blocks: &mut [EditableBlock], positions: &HashSet<Position>) -> bool {
+    let mut changed = false;
+    for (b, block) in blocks.iter_mut().enumerate() {
+        let items = std::mem::take(&mut block.items);
+        let mut pending_comments = vec![];
+        for (i, mut item) in items.into_iter().enumerate() {
+            if positions.contains(&Position {
+                block: b,
+                instruction: i,
+            }) {
+                // The remark at the end of the line of the instruction stays on its own line
+                pending_comments.extend(
+                    item.comments
+                        .into_iter()
+                        .map(|note| note.detached(RemarkPlacement::AfterInstruction)),
+                );
+                changed = true;
+            } else {
+                pending_comments.append(&mut item.comments);
+                item.comments = std::mem::take(&mut pending_comments);
+                block.items.push(item);
+            }
+        }
+        pending_comments.append(&mut block.trailing_comments);
+        block.trailing_comments = pending_comments;
+    }
+    changed
+}
+
+/// Remove the blocks without instructions: the jumps to them go to the next block.
+/// An empty block at the end of the script is kept if it's jumped to, as the end of the script has no label.
+fn remove_empty_blocks(blocks: &mut Vec<EditableBlock>) -> bool {
+    let mut changed = false;
+    for i in (1..blocks.len()).rev() {
+        if !blocks[i].items.is_empty() {
+            continue;
+        }
+        let name = blocks[i].name.clone();
+        match blocks.get(i + 1).map(|next| next.name.clone()) {
+            Some(next_name) => {
+                for item in blocks.iter_mut().flat_map(|block| &mut block.items) {
+                    if item.instruction.jump_target().map(JumpTarget::label) == Some(name.as_str())
+                    {
+                        retarget(&mut item.instruction, &next_name);
+                    }
+                }
+            }
+            None if is_jumped_to(blocks, &name) => continue,
+            None => {}
+        }
+        let removed = blocks.remove(i);
+        blocks[i - 1].trailing_comments.extend(
+            removed
+                .trailing_comments
+                .into_iter()
+                .map(|note| note.detached(RemarkPlacement::AfterLabel)),
+        );
+        changed = true;
+    }
+    changed
+}
+
+/// Merge the blocks that are never jumped to with the previous block: their label is useless
+fn merge_fall_through_blocks(blocks: &mut Vec<EditableBlock>) -> bool {
+    let mut changed = false;
+    for i in (1..blocks.len()).rev() {
+        if is_jumped_to(blocks, &blocks[i].name) {
+            continue;
+        }
+        let mut merged = blocks.remove(i);
+        let previous = &mut blocks[i - 1];
+        let mut comments = std::mem::take(&mut previous.trailing_comments);
+        // The label is gone, but not its remark
+        let detach = |notes: &mut Vec<Note>| {
+            let detached = std::mem::take(notes)
+                .into_iter()
+                .map(|note| note.detached(RemarkPlacement::AfterLabel))
+                .collect();
+            *notes = detached;
+        };
+        detach(&mut merged.trailing_comments);
+        match merged.items.first_mut() {
+            Some(first) => {
+                detach(&mut first.comments);
+                comments.append(&mut first.comments);
+                first.comments = comments;
+                previous.items.append(&mut merged.items);
+                previous.trailing_comments = merged.trailing_comments;
+            }
+            None => {
+                comments.append(&mut merged.trailing_comments);
+                previous.trailing_comments = comments;
+            }
+        }
+        changed = true;
+    }
+    changed
+}
+
+impl ScriptObject {
+    /// Get the number of instructions of the script, which is the "size" score of a solution.
+    pub fn instruction_count(&self) -> usize {
+        self.blocks
+            .iter()
+            .map(|block| block.instructions.len())
+            .sum()
+    }
+
+    /// Get an equivalent script with fewer instructions and labels:
+    /// the chains of JUMPs are collapsed, the code that is never executed is removed,
+    /// and so are the empty blocks and the labels that are never jumped to.
+    ///
+    /// The instructions whose effect is never used (see [`dead_instructions`]) are listed in the report,
+    /// and removed if `remove_dead_instructions` is set.
+    ///
+    /// The jumps must point to existing blocks (see [`ScriptObject::validate`]).
+    /// The instructions keep their lines in the original script, for the error messages.
+    pub fn optimize(&self, remove_dead_instructions: bool) -> (ScriptObject, OptimizationReport) {
+        let mut blocks = editable(self);
+        let mut eliminated = vec![];
+        loop {
+            let mut changed = thread_jumps(&mut blocks);
+            changed |= remove_useless_instructions(&mut blocks, self);
+            if remove_dead_instructions {
+                changed |= eliminate_dead_instructions(&mut blocks, self, &mut eliminated);
+            }
+            changed |= remove_empty_blocks(&mut blocks);
+            changed |= merge_fall_through_blocks(&mut blocks);
+            if !changed {
+                break;
+            }
+        }
+
+        let optimized = build(&blocks, self);
+        let mut dead_lines = if remove_dead_instructions {
+            eliminated
+        } else {
+            dead_instructions(&optimized)
+                .into_iter()
+                .map(|instruction| DeadLine {
+                    span: optimized.get_span(instruction.position).unwrap().clone(),
+                    effect: instruction.effect,
+                })
+                .collect()
+        };
+        dead_lines.sort_by_key(|dead| dead.span.line);
+        let report = OptimizationReport {
+            instructions_before: self.instruction_count(),
+            instructions_after: optimized.instruction_count(),
+            dead_lines,
+            dead_lines_removed: remove_dead_instructions,
+        };
+        (optimized, report)
+    }
+}
+
+#[cfg(test)]
+mod optimize_tests {
+    use super::*;
+
+    fn optimize(script: &str) -> (String, OptimizationReport) {
+        let (optimized, report) = script.parse::<ScriptObject>().unwrap().optimize(false);
+        (optimized.to_string(), report)
+    }
+
+    #[test]
+    fn test_jump_threading() {
+        let (optimized, report) = optimize(
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            JUMPZ    b
+            OUTBOX
+            JUMP     c
+        b:
+            JUMP     c
+        c:
+        d:
+            JUMP     a
+        ",
+        );
+        assert_eq!(
+            optimized,
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+a:
+    INBOX
+    JUMPZ    a
+    OUTBOX
+    JUMP     a
+"
+        );
+        assert_eq!(
+            report,
+            OptimizationReport {
+                instructions_before: 6,
+                instructions_after: 4,
+                dead_lines: vec![],
+                dead_lines_removed: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_empty_blocks_and_fall_through() {
+        let (optimized, _) = optimize(
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+        a:       ; empty
+            COMMENT  0
+        b:
+            OUTBOX
+            JUMPN    c
+            JUMP     b
+        c:
+        ",
+        );
+        assert_eq!(
+            optimized,
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+    INBOX
+    ; empty
+    COMMENT  0
+
+b:
+    OUTBOX
+    JUMPN    c
+    JUMP     b
+
+c:
+"
+        );
+    }
+
+    #[test]
+    fn test_infinite_jump_chains_terminate() {
+        let (optimized, _) = optimize(
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            JUMP     b
+        b:
+            JUMP     c
+        c:
+            JUMP     a
+        ",
+        );
+        assert_eq!(
+            optimized,
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+c:
+    JUMP     c
+"
+        );
+    }
+
+    #[test]
+    fn test_dead_instructions() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            COPYTO   0
+            COPYFROM 0
+            INBOX
+            COPYTO   1
+            OUTBOX
+            JUMP     a
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+
+        let (optimized, report) = script.optimize(false);
+        assert_eq!(optimized.instruction_count(), 7);
+        let lines = |report: &OptimizationReport| {
+            report
+                .dead_lines
+                .iter()
+                .map(|dead| (dead.span.line, dead.effect))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            lines(&report),
+            vec![(5, DeadEffect::UnusedHead), (7, DeadEffect::UnusedTile(1))]
+        );
+
+        // Once the COPYFROM is removed, the tile 0 isn't read anymore
+        let (optimized, report) = script.optimize(true);
+        assert_eq!(
+            optimized.to_string(),
+            "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+a:
+    INBOX
+    INBOX
+    OUTBOX
+    JUMP     a
+"
+        );
+        assert_eq!(
+            lines(&report),
+            vec![
+                (4, DeadEffect::UnusedTile(0)),
+                (5, DeadEffect::UnusedHead),
+                (7, DeadEffect::UnusedTile(1))
+            ]
+        );
+        assert_eq!(
+            report.to_string(),
+            "Optimized: 7 instructions before, 4 after
+Eliminated instructions:
+  line 4: COPYTO   0 (tile 0 is overwritten before being read)
+  line 5: COPYFROM 0 (the value in hand is replaced before being used)
+  line 7: COPYTO   1 (tile 1 is overwritten before being read)"
+        );
+    }
+
+    #[test]
+    fn test_optimized_samples_behave_the_same() {
+        use crate::interpreter::{memory::Memory, Interpreter};
+        use crate::script_object::value_box::ValueBox;
+
+        let level = crate::levels::get_level(41).unwrap();
+        let script = include_str!("../../samples/41-SortingRoom.hrm")
+            .parse::<ScriptObject>()
+            .unwrap();
+        let (optimized, report) = script.optimize(true);
+        assert!(optimized.validate().is_ok());
+        assert!(report.instructions_after <= report.instructions_before);
+
+        let inputs = "5 2 9 0 C A B 0 -3 4 0"
+            .split_whitespace()
+            .map(|value| value.parse::<ValueBox>().unwrap())
+            .collect::<Vec<_>>();
+        let run = |script: &ScriptObject| {
+            let memory = Memory::with_data(level.memory(), level.max_address());
+            Interpreter::new(memory).execute(script, &inputs).unwrap().0
+        };
+        assert_eq!(run(&optimized), run(&script));
+    }
+}
@@ -0,0 +1,192 @@
+//! An incremental parser, for the editors: the script is kept between the edits, and an edit
+//! only parses the lines it touches again (see [`Document`]).
+//!
+//! The blocks get ids that don't change when lines are added or removed around them,
+//! so that an editor can follow a block while the script is typed. A label typed again,
+//! or renamed, keeps the id of the label it replaces.
+
+use std::{ops::Range, sync::OnceLock};
+
+use super::{document::Document, Block, ParseScriptObjectError, ScriptObject};
+use crate::diagnostics::Diagnostic;
+
+/// The id of a block, which stays the same across the edits (0 for the entry block)
+pub type BlockId = usize;
+
+#[derive(Debug)]
+pub struct Parser {
+    document: Document,
+    /// The id and the name of the label written on each line, if any
+    ids: Vec<Option<(BlockId, String)>>,
+    next_id: BlockId,
+    /// The script parsed since the last edit
+    script: OnceLock<Result<ScriptObject, ParseScriptObjectError>>,
+}
+
+impl Parser {
+    pub fn new(text: &str) -> Self {
+        let document = Document::new(text);
+        let mut parser = Self {
+            ids: vec![None; document.lines().len()],
+            document,
+            next_id: 1,
+            script: OnceLock::new(),
+        };
+        parser.assign_ids(vec![]);
+        parser
+    }
+
+    pub fn document(&self) -> &Document {
+        &self.document
+    }
+
+    /// Replace the text between two positions by the given text, as [`Document::edit`]
+    pub fn edit(&mut self, start: (usize, usize), end: (usize, usize), text: &str) {
+        let last_line = self.document.lines().len() - 1;
+        let (start_line, end_line) = (start.0.min(last_line), end.0.min(last_line));
+        let len = self.document.lines().len();
+        self.document.edit(start, end, text);
+
+        // The ids of the edited lines can be given to the labels written instead
+        let edited = self.document.lines().len() + (end_line - start_line + 1) - len;
+        let replaced = self
+            .ids
+            .splice(start_line..=end_line, vec![None; edited])
+            .flatten()
+            .collect();
+        self.assign_ids(replaced);
+        self.script = OnceLock::new();
+    }
+
+    /// Give an id to the labels without one: the id of a replaced label with the same name,
+    /// or else of another replaced label, or else a new one
+    fn assign_ids(&mut self, mut replaced: Vec<(BlockId, String)>) {
+        let labels = self.document.labels();
+        let mut ids = vec![None; self.ids.len()];
+        let mut missing = vec![];
+        for (line, name) in labels {
+            match self.ids[line - 1].take() {
+                Some(id) => ids[line - 1] = Some(id),
+                None => match replaced.iter().position(|(_, replaced)| replaced == name) {
+                    Some(i) => ids[line - 1] = Some((replaced.remove(i).0, name.to_string())),
+                    None => missing.push((line, name)),
+                },
+            }
+        }
+        let mut replaced = replaced.into_iter();
+        for (line, name) in missing {
+            let id = replaced.next().map_or_else(
+                || {
+                    self.next_id += 1;
+                    self.next_id - 1
+                },
+                |(id, _)| id,
+            );
+            ids[line - 1] = Some((id, name.to_string()));
+        }
+        self.ids = ids;
+    }
+
+    /// The script, parsed again only after an edit
+    pub fn script(&self) -> &Result<ScriptObject, ParseScriptObjectError> {
+        self.script.get_or_init(|| self.document.parse())
+    }
+
+    /// The id of a block of the parsed script.
+    /// None for the blocks whose labels are brought by a macro or an included file.
+    pub fn block_id(&self, block: &Block) -> Option<BlockId> {
+        match block.line() {
+            0 => Some(0),
+            line => self.ids.get(line - 1)?.as_ref().map(|(id, _)| *id),
+        }
+    }
+
+    /// The labels of the blocks with their ids and lines (starting at 1), even if the script is invalid
+    pub fn labels(&self) -> Vec<(BlockId, usize, &str)> {
+        self.ids
+            .iter()
+            .enumerate()
+            .filter_map(|(i, id)| id.as_ref().map(|(id, name)| (*id, i + 1, name.as_str())))
+            .collect()
+    }
+
+    /// The errors of the script: invalid lines, or else unknown and duplicate labels
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self.script() {
+            Ok(script) => match script.validate() {
+                Ok(()) => vec![],
+                Err(e) => e.diagnostics(script),
+            },
+            Err(e) => e.diagnostics(),
+        }
+    }
+
+    /// The line (starting at 0) and the characters of the line pointed at by the diagnostic,
+    /// as the editors count them
+    pub fn range(&self, diagnostic: &Diagnostic) -> (usize, Range<usize>) {
+        let line = diagnostic.line.saturating_sub(1);
+        let text = self.document.lines().get(line).map_or("", String::as_str);
+        let bytes = match &diagnostic.range {
+            Some(range) => range.start.min(text.len())..range.end.min(text.len()),
+            None => text.len() - text.trim_start().len()..text.trim_end().len(),
+        };
+        let characters = |bytes: usize| text.get(..bytes).map_or(0, |text| text.chars().count());
+        (line, characters(bytes.start)..characters(bytes.end))
+    }
+}
+
+#[cfg(test)]
+mod parser_tests {
+    use super::*;
+
+    const SCRIPT: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+a:
+    INBOX
+b:
+    OUTBOX
+    JUMP     a
+";
+
+    #[test]
+    fn test_stable_block_ids() {
+        let mut parser = Parser::new(SCRIPT);
+        assert_eq!(parser.labels(), vec![(1, 3, "a"), (2, 5, "b")]);
+
+        // Lines added before the labels move them without changing their ids
+        parser.edit((1, 0), (1, 0), "    INBOX\nc:\n");
+        assert_eq!(parser.labels(), vec![(3, 3, "c"), (1, 5, "a"), (2, 7, "b")]);
+        let Ok(script) = parser.script() else {
+            panic!("the script is valid");
+        };
+        let ids = script
+            .blocks()
+            .iter()
+            .map(|block| parser.block_id(block))
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![Some(0), Some(3), Some(1), Some(2)]);
+
+        // A renamed label keeps its id, a removed one loses it
+        parser.edit((4, 0), (4, 1), "start");
+        parser.edit((2, 0), (3, 0), "");
+        assert_eq!(parser.labels(), vec![(1, 4, "start"), (2, 6, "b")]);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let mut parser = Parser::new(SCRIPT);
+        assert_eq!(parser.diagnostics(), vec![]);
+
+        parser.edit((5, 4), (5, 10), "OUTBÖX");
+        let diagnostics = parser.diagnostics();
+        assert_eq!(diagnostics[0].message, "OUTBÖX is not a valid instruction");
+        // The characters, not the bytes
+        assert_eq!(parser.range(&diagnostics[0]), (5, 4..10));
+
+        parser.edit((5, 4), (5, 10), "OUTBOX");
+        parser.edit((6, 13), (6, 14), "c");
+        let diagnostics = parser.diagnostics();
+        assert_eq!(diagnostics[0].message, "unknown label c");
+        assert_eq!(parser.range(&diagnostics[0]), (6, 4..14));
+    }
+}
@@ -0,0 +1,637 @@
+//! Program synthesis: the scripts of a given size, enumerated for a brute-force search of the
+//! smallest solutions of a level.
+//!
+//! Only the instructions of the game are used, with direct addresses. The scripts that can't be
+//! the smallest solution are skipped: the ones with an instruction that can't be executed, or
+//! that has no effect (a jump to the next instruction, a value picked up and replaced right away),
+//! the ones reading a tile no path has written, and the ones that only differ by the numbering
+//! of the empty tiles (the first one used is the lowest one, and so on).
+//!
+//! The scripts are built an instruction at a time, and each beginning is run on the examples
+//! until it reaches an instruction that isn't chosen yet: a beginning that already fails, or
+//! gives a wrong output, is dropped with all the scripts starting with it.
+
+use std::collections::{BTreeSet, HashMap};
+
+use super::{
+    initialization::uninitialized_reads,
+    instruction::{Instruction, JumpTarget},
+    value_box::{ValueBox, ValueBoxMemoryAddress},
+    Block, ScriptObject, Span,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// An instruction of a candidate, the jumps going to the index of an instruction
+/// (or to the end of the script)
+enum Op {
+    In,
+    Out,
+    CopyFrom(usize),
+    CopyTo(usize),
+    Add(usize),
+    Sub(usize),
+    BumpUp(usize),
+    BumpDown(usize),
+    Jump(usize),
+    JumpIfZero(usize),
+    JumpIfNegative(usize),
+}
+
+impl Op {
+    fn tile(self) -> Option<usize> {
+        match self {
+            Op::CopyFrom(tile)
+            | Op::CopyTo(tile)
+            | Op::Add(tile)
+            | Op::Sub(tile)
+            | Op::BumpUp(tile)
+            | Op::BumpDown(tile) => Some(tile),
+            _ => None,
+        }
+    }
+
+    fn target(self) -> Option<usize> {
+        match self {
+            Op::Jump(target) | Op::JumpIfZero(target) | Op::JumpIfNegative(target) => Some(target),
+            _ => None,
+        }
+    }
+
+    /// Whether the instruction fails when the worker's hands are empty
+    fn needs_value(self) -> bool {
+        matches!(
+            self,
+            Op::Out
+                | Op::CopyTo(_)
+                | Op::Add(_)
+                | Op::Sub(_)
+                | Op::JumpIfZero(_)
+                | Op::JumpIfNegative(_)
+        )
+    }
+
+    /// Whether the instruction replaces the value in the worker's hands without using it
+    fn replaces_value(self) -> bool {
+        matches!(
+            self,
+            Op::In | Op::CopyFrom(_) | Op::BumpUp(_) | Op::BumpDown(_)
+        )
+    }
+
+    fn instruction(self, labels: &HashMap<usize, String>) -> Instruction {
+        let address = ValueBoxMemoryAddress::Pointer;
+        let target = |index: usize| JumpTarget::new(&labels[&index]);
+        match self {
+            Op::In => Instruction::In,
+            Op::Out => Instruction::Out,
+            Op::CopyFrom(tile) => Instruction::CopyFrom(address(tile)),
+            Op::CopyTo(tile) => Instruction::CopyTo(address(tile)),
+            Op::Add(tile) => Instruction::Add(address(tile)),
+            Op::Sub(tile) => Instruction::Sub(address(tile)),
+            Op::BumpUp(tile) => Instruction::BumpUp(address(tile)),
+            Op::BumpDown(tile) => Instruction::BumpDown(address(tile)),
+            Op::Jump(index) => Instruction::Jump(target(index)),
+            Op::JumpIfZero(index) => Instruction::JumpIfZero(target(index)),
+            Op::JumpIfNegative(index) => Instruction::JumpIfNegative(target(index)),
+        }
+    }
+}
+
+/// The labels of the jump targets, in the order of the script: a, b, ..., z, aa, ab...
+fn label(n: usize) -> String {
+    let letter = char::from(b'a' + (n % 26) as u8);
+    match n / 26 {
+        0 => letter.to_string(),
+        prefix => format!("{}{}", label(prefix - 1), letter),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// An independent part of the search: the candidates jumping to these instructions
+pub struct Branch {
+    targets: BTreeSet<usize>,
+}
+
+/// The steps a beginning is run for on an example, before keeping it undecided
+const PREFIX_STEPS: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+/// An inbox, and the outbox a solution gives for it
+pub struct Example {
+    pub inbox: Vec<ValueBox>,
+    pub outbox: Vec<ValueBox>,
+}
+
+/// The scripts searched: the tiles they can use, the floor they start with,
+/// and the examples they must not contradict
+pub struct SearchSpace {
+    /// The tiles from 0 to `floor_size - 1` can be used
+    pub floor_size: usize,
+    /// The values on the floor at the beginning, by address
+    pub memory: HashMap<usize, ValueBox>,
+    pub examples: Vec<Example>,
+}
+
+#[derive(Debug, Clone)]
+/// The beginning of a script run on an example, stopped at an instruction that isn't chosen yet
+struct Run {
+    floor: Vec<Option<ValueBox>>,
+    hands: Option<ValueBox>,
+    inputs: usize,
+    outputs: usize,
+    index: usize,
+    steps: usize,
+    /// False once the run ended well, or can't be decided without the interpreter
+    waiting: bool,
+}
+
+impl Run {
+    fn new(floor: Vec<Option<ValueBox>>) -> Run {
+        Run {
+            floor,
+            hands: None,
+            inputs: 0,
+            outputs: 0,
+            index: 0,
+            steps: 0,
+            waiting: true,
+        }
+    }
+
+    /// Run the script until an instruction that isn't chosen yet.
+    /// Returns false if it can't be a solution for the example anymore.
+    fn resume(&mut self, ops: &[Op], size: usize, example: &Example) -> bool {
+        let number = |value: Option<ValueBox>| match value {
+            Some(ValueBox::Number(n)) => Some(n),
+            _ => None,
+        };
+        while self.steps < PREFIX_STEPS {
+            // The script ends: all the outputs must have been given
+            if self.index == size {
+                self.waiting = false;
+                return self.outputs == example.outbox.len();
+            }
+            let Some(op) = ops.get(self.index) else {
+                return true;
+            };
+            self.steps += 1;
+            match *op {
+                Op::In => match example.inbox.get(self.inputs) {
+                    Some(value) => {
+                        self.hands = Some(*value);
+                        self.inputs += 1;
+                    }
+                    None => {
+                        self.waiting = false;
+                        return self.outputs == example.outbox.len();
+                    }
+                },
+                Op::Out => {
+                    if self.hands.is_none()
+                        || example.outbox.get(self.outputs) != self.hands.as_ref()
+                    {
+                        return false;
+                    }
+                    self.outputs += 1;
+                }
+                Op::CopyFrom(tile) => match self.floor[tile] {
+                    Some(value) => self.hands = Some(value),
+                    None => return false,
+                },
+                Op::CopyTo(tile) => match self.hands {
+                    Some(value) => self.floor[tile] = Some(value),
+                    None => return false,
+                },
+                Op::Add(tile) | Op::Sub(tile) => {
+                    let result = match (self.hands, self.floor[tile]) {
+                        (Some(ValueBox::Number(a)), Some(ValueBox::Number(b))) => match op {
+                            Op::Add(_) => a.checked_add(b),
+                            _ => a.checked_sub(b),
+                        },
+                        (Some(ValueBox::Character(a)), Some(ValueBox::Character(b)))
+                            if matches!(op, Op::Sub(_)) =>
+                        {
+                            Some(ValueBox::alphabet_index(a) - ValueBox::alphabet_index(b))
+                        }
+                        _ => return false,
+                    };
+                    // The bounds are left to the interpreter
+                    let Some(result) = result else {
+                        self.waiting = false;
+                        return true;
+                    };
+                    self.hands = Some(ValueBox::Number(result));
+                }
+                Op::BumpUp(tile) | Op::BumpDown(tile) => {
+                    let Some(n) = number(self.floor[tile]) else {
+                        return false;
+                    };
+                    let Some(result) =
+                        n.checked_add(if matches!(op, Op::BumpUp(_)) { 1 } else { -1 })
+                    else {
+                        self.waiting = false;
+                        return true;
+                    };
+                    self.floor[tile] = Some(ValueBox::Number(result));
+                    self.hands = self.floor[tile];
+                }
+                Op::Jump(target) => {
+                    self.index = target;
+                    continue;
+                }
+                Op::JumpIfZero(target) | Op::JumpIfNegative(target) => {
+                    let Some(value) = self.hands else {
+                        return false;
+                    };
+                    let jumps = match op {
+                        Op::JumpIfZero(_) => value.is_zero(),
+                        _ => value.is_negative(),
+                    };
+                    if jumps {
+                        self.index = target;
+                        continue;
+                    }
+                }
+            }
+            self.index += 1;
+        }
+        self.waiting = false;
+        true
+    }
+}
+
+/// The enumeration of the candidates of a branch, with the beginning built so far
+struct Walk<'a> {
+    space: &'a SearchSpace,
+    size: usize,
+    targets: &'a BTreeSet<usize>,
+    empty_tiles: Vec<usize>,
+    /// The instructions on the initialized tiles, that can be used anywhere
+    tile_ops: Vec<Op>,
+    ops: Vec<Op>,
+    /// The targets with no jump to them yet
+    missing: BTreeSet<usize>,
+    /// The OUTBOX instructions so far
+    outputs: usize,
+}
+
+impl Walk<'_> {
+    /// The instructions that can come next, when the first `used` empty tiles are already used
+    fn next_ops(&self, used: usize) -> Vec<Op> {
+        let index = self.ops.len();
+        let previous = self.ops.last().copied();
+        let is_target = self.targets.contains(&index);
+        // Only a jump can reach the instruction after a JUMP
+        if matches!(previous, Some(Op::Jump(_))) && !is_target {
+            return vec![];
+        }
+        let mut ops = vec![Op::In, Op::Out];
+        ops.extend_from_slice(&self.tile_ops);
+        // The empty tiles already used, and the next one
+        for &tile in self.empty_tiles.iter().take(used + 1) {
+            ops.extend([
+                Op::CopyFrom(tile),
+                Op::CopyTo(tile),
+                Op::Add(tile),
+                Op::Sub(tile),
+                Op::BumpUp(tile),
+                Op::BumpDown(tile),
+            ]);
+        }
+        for &target in self.targets.iter().filter(|target| **target != index + 1) {
+            ops.extend([Op::JumpIfZero(target), Op::JumpIfNegative(target)]);
+            if target != index {
+                ops.push(Op::Jump(target));
+            }
+        }
+
+        // The script starts with empty hands, and only the initialized tiles can be read
+        if index == 0 {
+            ops.retain(|op| {
+                !op.needs_value()
+                    && op
+                        .tile()
+                        .is_none_or(|tile| self.space.memory.contains_key(&tile))
+            });
+        }
+        // Undone right away, unless a jump lands in between
+        if let (Some(previous), false) = (previous, is_target) {
+            ops.retain(|op| match (previous, *op) {
+                (Op::CopyFrom(_), op) => {
+                    !op.replaces_value() && op != Op::CopyTo(previous.tile().unwrap())
+                }
+                (Op::CopyTo(a), Op::CopyFrom(b) | Op::CopyTo(b)) => a != b,
+                (Op::BumpUp(a), Op::BumpDown(b)) | (Op::BumpDown(a), Op::BumpUp(b)) => a != b,
+                _ => true,
+            });
+        }
+        ops
+    }
+
+    /// Whether the beginning can still be completed: a jump to each target, enough outputs
+    fn may_be_complete(&self) -> bool {
+        let remaining = self.size - self.ops.len();
+        let outbox = self
+            .space
+            .examples
+            .iter()
+            .map(|example| example.outbox.len())
+            .max()
+            .unwrap_or(0);
+        // Without jumps, each instruction runs at most once
+        let outputs_needed = if self.targets.is_empty() {
+            outbox
+        } else {
+            outbox.min(1)
+        };
+        self.missing.len() <= remaining && self.outputs + remaining >= outputs_needed
+    }
+
+    fn extend(
+        &mut self,
+        used: usize,
+        runs: &[Run],
+        visit: &mut impl FnMut(&ScriptObject) -> bool,
+    ) -> bool {
+        if self.ops.len() == self.size {
+            return match self.space.candidate(&self.ops, self.targets) {
+                Some(script) => visit(&script),
+                None => true,
+            };
+        }
+        for op in self.next_ops(used) {
+            let used = used
+                + usize::from(
+                    op.tile().is_some() && op.tile() == self.empty_tiles.get(used).copied(),
+                );
+            self.ops.push(op);
+            self.outputs += usize::from(op == Op::Out);
+            let jumps_to_missing = op
+                .target()
+                .is_some_and(|target| self.missing.remove(&target));
+
+            let go_on = !self.may_be_complete() || {
+                // Only the runs waiting for this instruction go on
+                let waiting = |run: &Run| run.waiting && run.index < self.ops.len();
+                if runs.iter().any(waiting) {
+                    let mut runs = runs.to_vec();
+                    let may_solve =
+                        runs.iter_mut()
+                            .zip(&self.space.examples)
+                            .all(|(run, example)| {
+                                !waiting(run) || run.resume(&self.ops, self.size, example)
+                            });
+                    !may_solve || self.extend(used, &runs, visit)
+                } else {
+                    self.extend(used, runs, visit)
+                }
+            };
+
+            if jumps_to_missing {
+                self.missing.insert(op.target().unwrap());
+            }
+            self.outputs -= usize::from(op == Op::Out);
+            self.ops.pop();
+            if !go_on {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl SearchSpace {
+    /// The parts of the search for the scripts of `size` instructions: one for each set of jump targets
+    pub fn branches(&self, size: usize) -> Vec<Branch> {
+        if size == 0 {
+            return vec![];
+        }
+        let mut branches = (0..1u64 << (size + 1))
+            .map(|bits| Branch {
+                targets: (0..=size).filter(|i| bits & (1 << i) != 0).collect(),
+            })
+            .collect::<Vec<_>>();
+        // The scripts with the fewest jumps first
+        branches.sort_by_key(|branch| branch.targets.len());
+        branches
+    }
+
+    /// Call `visit` on each candidate of `size` instructions of the branch, until it returns false.
+    /// Returns false if the search was stopped.
+    pub fn visit(
+        &self,
+        size: usize,
+        branch: &Branch,
+        visit: &mut impl FnMut(&ScriptObject) -> bool,
+    ) -> bool {
+        let mut initialized = self
+            .memory
+            .keys()
+            .copied()
+            .filter(|tile| *tile < self.floor_size)
+            .collect::<Vec<_>>();
+        initialized.sort();
+        let tile_ops = initialized
+            .into_iter()
+            .flat_map(|tile| {
+                [
+                    Op::CopyFrom(tile),
+                    Op::CopyTo(tile),
+                    Op::Add(tile),
+                    Op::Sub(tile),
+                    Op::BumpUp(tile),
+                    Op::BumpDown(tile),
+                ]
+            })
+            .collect();
+        let floor = (0..self.floor_size)
+            .map(|tile| self.memory.get(&tile).copied())
+            .collect::<Vec<_>>();
+        let runs = vec![Run::new(floor); self.examples.len()];
+        let mut walk = Walk {
+            space: self,
+            size,
+            targets: &branch.targets,
+            empty_tiles: (0..self.floor_size)
+                .filter(|tile| !self.memory.contains_key(tile))
+                .collect(),
+            tile_ops,
+            ops: vec![],
+            missing: branch.targets.clone(),
+            outputs: 0,
+        };
+        walk.extend(0, &runs, visit)
+    }
+
+    /// The script of the instructions, None if a target isn't jumped to, or if the script
+    /// always fails (an instruction that can't be reached, or reading an empty tile)
+    fn candidate(&self, ops: &[Op], targets: &BTreeSet<usize>) -> Option<ScriptObject> {
+        let jumped_to = ops
+            .iter()
+            .filter_map(|op| op.target())
+            .collect::<BTreeSet<_>>();
+        if jumped_to != *targets {
+            return None;
+        }
+        let script = script_of(ops, targets);
+        let all_reachable = script.reachable_positions().len() == ops.len();
+        (all_reachable && uninitialized_reads(&script, &self.memory).is_empty()).then_some(script)
+    }
+}
+
+/// Build the script, with a label before each jump target
+fn script_of(ops: &[Op], targets: &BTreeSet<usize>) -> ScriptObject {
+    let labels = targets
+        .iter()
+        .enumerate()
+        .map(|(n, target)| (*target, label(n)))
+        .collect::<HashMap<_, _>>();
+    let starts = [0]
+        .into_iter()
+        .chain(targets.iter().copied())
+        .collect::<Vec<_>>();
+    let ends = starts.iter().skip(1).copied().chain([ops.len()]);
+
+    // The lines are the ones of the script as it's printed: the header, then each block after a blank line
+    let mut line = 1;
+    let blocks = starts
+        .iter()
+        .zip(ends)
+        .enumerate()
+        .map(|(index, (start, end))| {
+            let name = match index {
+                0 => "entry".to_string(),
+                _ => labels[start].clone(),
+            };
+            let label_line = match index {
+                0 => 0,
+                _ => {
+                    line += 2;
+                    line
+                }
+            };
+            if index == 0 && start < &end {
+                line += 1;
+            }
+            let instructions = ops[*start..end]
+                .iter()
+                .map(|op| op.instruction(&labels))
+                .collect::<Vec<_>>();
+            let spans = instructions
+                .iter()
+                .map(|instruction| {
+                    line += 1;
+                    Span {
+                        line,
+                        column: 5,
+                        text: instruction.to_string(),
+                    }
+                })
+                .collect();
+            Block {
+                name,
+                index,
+                line: label_line,
+                instructions,
+                spans,
+            }
+        })
+        .collect();
+    ScriptObject::new(blocks)
+}
+
+#[cfg(test)]
+mod synthesis_tests {
+    use super::*;
+
+    fn candidates(space: &SearchSpace, size: usize) -> Vec<String> {
+        let mut scripts = vec![];
+        for branch in space.branches(size) {
+            space.visit(size, &branch, &mut |script| {
+                scripts.push(script.to_string());
+                true
+            });
+        }
+        scripts
+    }
+
+    fn example(inbox: &[i32], outbox: &[i32]) -> Example {
+        Example {
+            inbox: inbox.iter().copied().map(ValueBox::from).collect(),
+            outbox: outbox.iter().copied().map(ValueBox::from).collect(),
+        }
+    }
+
+    #[test]
+    fn test_smallest_candidates() {
+        let space = SearchSpace {
+            floor_size: 0,
+            memory: HashMap::new(),
+            examples: vec![],
+        };
+        assert!(candidates(&space, 1)
+            .contains(&"-- HUMAN RESOURCE MACHINE PROGRAM --\n\n    INBOX\n".to_string()));
+
+        // The beginnings giving a wrong outbox are dropped
+        let space = SearchSpace {
+            examples: vec![example(&[1, 2], &[1, 2])],
+            ..space
+        };
+        assert!(candidates(&space, 2).is_empty());
+        let scripts = candidates(&space, 3);
+        let mail_room = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            OUTBOX
+            JUMP a
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        assert!(scripts.contains(&mail_room.to_string()));
+
+        // A tile is used again after it's written
+        let space = SearchSpace {
+            floor_size: 1,
+            examples: vec![example(&[2], &[4])],
+            ..space
+        };
+        let doubler = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            COPYTO 0
+            ADD 0
+            OUTBOX
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        assert!(candidates(&space, 4).contains(&doubler.to_string()));
+    }
+
+    #[test]
+    fn test_pruned_candidates() {
+        let space = SearchSpace {
+            floor_size: 3,
+            memory: HashMap::from([(2, ValueBox::from(0))]),
+            examples: vec![],
+        };
+        for script in candidates(&space, 3) {
+            let script = script.parse::<ScriptObject>().unwrap();
+            // The first empty tile is always 0, and tile 1 is only used after it
+            let tiles = script
+                .blocks()
+                .iter()
+                .flat_map(|block| &block.instructions)
+                .filter_map(|instruction| instruction.memory_address())
+                .map(|address| match address {
+                    ValueBoxMemoryAddress::Pointer(tile)
+                    | ValueBoxMemoryAddress::PointerAddress(tile) => *tile,
+                })
+                .filter(|tile| *tile != 2)
+                .collect::<Vec<_>>();
+            if let Some(first) = tiles.first() {
+                assert_eq!(*first, 0, "{}", script);
+            }
+            assert!(uninitialized_reads(&script, &space.memory).is_empty());
+            assert_eq!(script.reachable_positions().len(), 3, "{}", script);
+        }
+    }
+}
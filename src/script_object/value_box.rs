@@ -1,6 +1,9 @@
-use std::str::FromStr;
+use std::{collections::HashMap, fmt::Display, str::FromStr};
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
 /// Wrapper for a value that can be stored in memory.
 /// The name comes from the fact that in HRM, the values are like cardboard boxes.
 /// A ValueBox can be either a number or a character.
@@ -9,21 +12,59 @@ pub enum ValueBox {
     Character(char),
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+impl ValueBox {
+    /// The smallest number a ValueBox can hold in the game
+    pub const MIN_NUMBER: i32 = -999;
+    /// The biggest number a ValueBox can hold in the game
+    pub const MAX_NUMBER: i32 = 999;
+
+    /// The index of a letter in the alphabet, whatever its case.
+    /// In HRM, SUB on two letters gives the distance between them.
+    pub fn alphabet_index(letter: char) -> i32 {
+        letter.to_ascii_uppercase() as i32 - 'A' as i32
+    }
+
+    /// Whether JUMPZ jumps with this value: letters are never equal to 0
+    pub fn is_zero(&self) -> bool {
+        matches!(self, ValueBox::Number(0))
+    }
+
+    /// Whether JUMPN jumps with this value: letters are never negative
+    pub fn is_negative(&self) -> bool {
+        matches!(self, ValueBox::Number(n) if *n < 0)
+    }
+
+    /// Whether JUMPP jumps with this value: letters aren't numbers, they never jump
+    pub fn is_positive(&self) -> bool {
+        matches!(self, ValueBox::Number(n) if *n > 0)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 /// Wrapper for a memory address.
 /// It can be either a direct memory address or a pointer at which the memory address is stored.
 ///
 /// Ex:
 /// - "Copy from 2" uses Pointer(2)
-/// and means "Copy from the value at memory address 2"
+///   and means "Copy from the value at memory address 2"
 /// - "Copy from \[2]" uses PointerAddress(2)
-/// and means "Copy from the value at the memory address stored at memory address 2",
-/// ie "Read the value at memory address 2, and use it as a memory address to read the desired value from"
+///   and means "Copy from the value at the memory address stored at memory address 2",
+///   ie "Read the value at memory address 2, and use it as a memory address to read the desired value from"
 pub enum ValueBoxMemoryAddress {
     Pointer(usize),
     PointerAddress(usize),
 }
 
+impl Display for ValueBoxMemoryAddress {
+    /// Write the address as in the script: "2" or "[2]"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pointer(address) => write!(f, "{}", address),
+            Self::PointerAddress(address) => write!(f, "[{}]", address),
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 /// Error that can occur when parsing a ValueBox.
 pub enum ParseValueBoxError {
@@ -59,46 +100,69 @@ impl From<char> for ValueBox {
     }
 }
 
-impl ToString for ValueBox {
-    fn to_string(&self) -> String {
+impl Display for ValueBox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Number(value) => value.to_string(),
-            Self::Character(value) => value.to_string(),
+            Self::Number(value) => write!(f, "{}", value),
+            Self::Character(value) => write!(f, "{}", value),
         }
     }
 }
 
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, Clone, thiserror::Error)]
 /// Error that can occur when parsing a "value box memory address".
 pub enum ParseValueBoxMemoryAddressError {
-    #[error("error parsing '{0}' as a pointer (should be a positive integer):\n\t{1}")]
+    #[error(
+        "error parsing '{0}' as a pointer (should be a positive integer or a tile name):\n\t{1}"
+    )]
     InvalidPointer(String, #[source] std::num::ParseIntError),
-    #[error("error parsing '{0}' as a pointer address (should be a positive integer between brackets: [10]):\n\t{0}")]
+    #[error("error parsing '{0}' as a pointer address (should be a positive integer or a tile name between brackets: [10]):\n\t{1}")]
     InvalidPointerAddress(String, #[source] std::num::ParseIntError),
 }
 
-impl FromStr for ValueBoxMemoryAddress {
-    type Err = ParseValueBoxMemoryAddressError;
+/// Names given to memory tiles, by name.
+/// Scripts can refer to these tiles by their names ("COPYFROM zero") instead of their addresses.
+pub type TileNames = HashMap<String, usize>;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+impl ValueBoxMemoryAddress {
+    /// Parse a "value box memory address" in which tiles can be referred to by their names.
+    pub fn parse_with_tile_names(
+        s: &str,
+        tile_names: &TileNames,
+    ) -> Result<Self, ParseValueBoxMemoryAddressError> {
         // remove whitespaces characters
         let s: &str = &s.replace(' ', "");
 
+        let parse_address = |address: &str| {
+            address
+                .parse::<usize>()
+                .or_else(|e| tile_names.get(address).copied().ok_or(e))
+        };
+
         if s.starts_with('[') && s.ends_with(']') {
             let s_without_brackets = s.trim_start_matches('[').trim_end_matches(']').trim();
 
-            s_without_brackets
-                .parse::<usize>()
+            parse_address(s_without_brackets)
                 .map(Self::PointerAddress)
-                .map_err(|e| Self::Err::InvalidPointerAddress(s.to_string(), e))
+                .map_err(|e| {
+                    ParseValueBoxMemoryAddressError::InvalidPointerAddress(s.to_string(), e)
+                })
         } else {
-            s.parse::<usize>()
+            parse_address(s)
                 .map(Self::Pointer)
-                .map_err(|e| Self::Err::InvalidPointer(s.to_string(), e))
+                .map_err(|e| ParseValueBoxMemoryAddressError::InvalidPointer(s.to_string(), e))
         }
     }
 }
 
+impl FromStr for ValueBoxMemoryAddress {
+    type Err = ParseValueBoxMemoryAddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_with_tile_names(s, &TileNames::new())
+    }
+}
+
 #[cfg(test)]
 mod value_box_tests {
     use super::*;
@@ -123,6 +187,17 @@ mod value_box_tests {
 
         assert_eq!(value.to_string(), "42");
     }
+
+    #[test]
+    fn test_value_box_semantics() {
+        assert_eq!(
+            ValueBox::alphabet_index('C') - ValueBox::alphabet_index('a'),
+            2
+        );
+        assert!(ValueBox::from(0).is_zero() && !ValueBox::from('A').is_zero());
+        assert!(ValueBox::from(-1).is_negative() && !ValueBox::from('A').is_negative());
+        assert!(ValueBox::from(1).is_positive() && !ValueBox::from('A').is_positive());
+    }
 }
 
 #[cfg(test)]
@@ -161,4 +236,29 @@ mod vbma_tests {
     fn test_value_box_mem_address_from_str_with_negative_number() {
         let _address = ValueBoxMemoryAddress::from_str("[-25]").unwrap();
     }
+
+    #[test]
+    fn test_value_box_mem_address_display() {
+        assert_eq!(ValueBoxMemoryAddress::Pointer(4).to_string(), "4");
+        assert_eq!(ValueBoxMemoryAddress::PointerAddress(4).to_string(), "[4]");
+    }
+
+    #[test]
+    fn test_value_box_mem_address_with_tile_names() {
+        let tile_names = TileNames::from([("zero".to_string(), 24)]);
+
+        assert_eq!(
+            ValueBoxMemoryAddress::parse_with_tile_names("zero", &tile_names).unwrap(),
+            ValueBoxMemoryAddress::Pointer(24)
+        );
+        assert_eq!(
+            ValueBoxMemoryAddress::parse_with_tile_names("[zero]", &tile_names).unwrap(),
+            ValueBoxMemoryAddress::PointerAddress(24)
+        );
+        assert_eq!(
+            ValueBoxMemoryAddress::parse_with_tile_names("3", &tile_names).unwrap(),
+            ValueBoxMemoryAddress::Pointer(3)
+        );
+        assert!(ValueBoxMemoryAddress::parse_with_tile_names("one", &tile_names).is_err());
+    }
 }
@@ -0,0 +1,179 @@
+//! Brute-force search of the smallest solutions of a level (experimental): the candidates of each
+//! size (see [`SearchSpace`]) are run on inboxes of the level, the smallest size first, and the
+//! ones giving the expected outbox on all of them are reported.
+
+use hrm_interpreter::{
+    interpreter::{memory::Memory, settings::Settings, Interpreter},
+    script_object::synthesis::{Example, SearchSpace},
+};
+
+use crate::{
+    jobs, log,
+    manifest::{self, CaseResult, TestCase},
+};
+
+/// The size of the largest candidates, unless `--max-size` is given
+pub const DEFAULT_MAX_SIZE: usize = 6;
+/// The number of inboxes generated by the level, that a solution must pass
+pub const INBOXES: usize = 10;
+/// A candidate taking more steps on an inbox is stuck in a loop, unless a step limit is given
+const MAX_STEPS: usize = 1000;
+/// The solutions of a size that are kept
+const MAX_SOLUTIONS: usize = 10;
+
+pub struct Search {
+    /// The size of the smallest solutions, None if there is none within the maximum size
+    pub size: Option<usize>,
+    /// The first solutions of that size, as their source
+    pub solutions: Vec<String>,
+    /// The number of candidates run
+    pub candidates: usize,
+}
+
+/// The different cases as examples, to drop the candidates that start wrong
+pub fn examples(cases: &[TestCase]) -> Vec<Example> {
+    let mut examples = Vec::<Example>::new();
+    for case in cases {
+        let example = Example {
+            inbox: case.inputs.clone(),
+            outbox: case.expected.clone(),
+        };
+        if !examples.contains(&example) {
+            examples.push(example);
+        }
+    }
+    examples
+}
+
+/// Search the smallest scripts of at most `max_size` instructions passing all the cases,
+/// on `jobs` threads
+pub fn run(
+    space: &SearchSpace,
+    cases: &[TestCase],
+    max_size: usize,
+    settings: &Settings,
+    jobs: usize,
+) -> Search {
+    let settings = Settings {
+        max_steps: settings.max_steps.or(Some(MAX_STEPS)),
+        history_size: 0,
+        ..settings.clone()
+    };
+    let mut candidates = 0;
+    for size in 1..=max_size {
+        let branches = space.branches(size);
+        let results = jobs::parallel_map(&branches, jobs, |branch| {
+            let mut interpreter = Interpreter::with_settings(Memory::default(), settings.clone());
+            let mut solutions = vec![];
+            let mut count = 0;
+            space.visit(size, branch, &mut |script| {
+                count += 1;
+                let passes = cases.iter().all(|case| {
+                    matches!(
+                        manifest::run_case_with(&mut interpreter, script, case),
+                        CaseResult::Passed { .. }
+                    )
+                });
+                if passes {
+                    solutions.push(script.to_string());
+                }
+                solutions.len() < MAX_SOLUTIONS
+            });
+            (solutions, count)
+        });
+
+        let mut solutions = vec![];
+        for (found, count) in results {
+            solutions.extend(found);
+            candidates += count;
+        }
+        log::note(format!(
+            "Size {}: {} candidates run so far",
+            size, candidates
+        ));
+        if !solutions.is_empty() {
+            solutions.truncate(MAX_SOLUTIONS);
+            return Search {
+                size: Some(size),
+                solutions,
+                candidates,
+            };
+        }
+    }
+    Search {
+        size: None,
+        solutions: vec![],
+        candidates,
+    }
+}
+
+/// Print the solutions to stdout, and a summary to stderr. Returns true if a solution was found.
+pub fn report(search: &Search, max_size: usize) -> bool {
+    let Some(size) = search.size else {
+        eprintln!(
+            "No solution of at most {} instructions ({} candidates)",
+            max_size, search.candidates
+        );
+        return false;
+    };
+    for (i, solution) in search.solutions.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        print!("{}", solution);
+    }
+    eprintln!(
+        "{} solution(s) of {} instructions ({} candidates, at most {} shown)",
+        search.solutions.len(),
+        size,
+        search.candidates,
+        MAX_SOLUTIONS
+    );
+    true
+}
+
+#[cfg(test)]
+mod search_tests {
+    use std::collections::HashMap;
+
+    use hrm_interpreter::script_object::{value_box::ValueBox, ScriptObject};
+
+    use super::*;
+
+    fn case(inputs: &[i32], expected: &[i32]) -> TestCase {
+        TestCase {
+            name: "case".to_string(),
+            inputs: inputs.iter().copied().map(ValueBox::from).collect(),
+            memory: HashMap::new(),
+            max_memory_address: 2,
+            expected: expected.iter().copied().map(ValueBox::from).collect(),
+        }
+    }
+
+    #[test]
+    fn test_search() {
+        // Output the values that aren't zero
+        let cases = [case(&[1, 0, -2, 0, 3], &[1, -2, 3]), case(&[0, 0], &[])];
+        let space = SearchSpace {
+            floor_size: 3,
+            memory: HashMap::new(),
+            examples: examples(&cases),
+        };
+        let search = run(&space, &cases, 5, &Settings::default(), 2);
+        assert_eq!(search.size, Some(4));
+        let expected = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        a:
+            INBOX
+            JUMPZ a
+            OUTBOX
+            JUMP a
+        "
+        .parse::<ScriptObject>()
+        .unwrap();
+        assert!(search.solutions.contains(&expected.to_string()));
+        assert!(search.candidates >= search.solutions.len());
+
+        let search = run(&space, &cases, 3, &Settings::default(), 2);
+        assert_eq!(search.size, None);
+    }
+}
@@ -0,0 +1,64 @@
+//! The sessions written by `--record` and read by `--replay`: the inputs an execution took
+//! from the inbox and the outputs it produced, to run it again on the same inbox, even when
+//! the inbox was generated.
+//!
+//! ```json
+//! {
+//!   "inputs": [3, "A", -4],
+//!   "outputs": [3, "A"]
+//! }
+//! ```
+
+use std::{fs, io, path::Path};
+
+use hrm_interpreter::script_object::value_box::ValueBox;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    /// The inputs taken from the inbox, in order
+    pub inputs: Vec<ValueBox>,
+    pub outputs: Vec<ValueBox>,
+}
+
+#[derive(Debug, thiserror::Error)]
+/// Error that can occur when reading a session file.
+pub enum ReadSessionError {
+    #[error("could not read the session file:\n\t{0}")]
+    Io(#[from] io::Error),
+    #[error("invalid session:\n\t{0}")]
+    InvalidJson(#[from] serde_json::Error),
+}
+
+pub fn read_session(path: impl AsRef<Path>) -> Result<Session, ReadSessionError> {
+    let content = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+pub fn write_session(path: impl AsRef<Path>, session: &Session) -> io::Result<()> {
+    fs::write(path, serde_json::to_string_pretty(session)? + "\n")
+}
+
+#[cfg(test)]
+mod session_tests {
+    use super::*;
+
+    #[test]
+    fn test_sessions_are_read_back() {
+        let path = std::env::temp_dir().join("hrm_session_test.json");
+        let session = Session {
+            inputs: vec![3.into(), 'A'.into(), (-4).into()],
+            outputs: vec![3.into()],
+        };
+        write_session(&path, &session).unwrap();
+        let read = read_session(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read.unwrap(), session);
+
+        let path = std::env::temp_dir().join("hrm_invalid_session_test.json");
+        fs::write(&path, "{\"inputs\": [1]}").unwrap();
+        let read = read_session(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(read, Err(ReadSessionError::InvalidJson(_))));
+    }
+}
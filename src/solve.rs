@@ -0,0 +1,1058 @@
+//! Symbolic execution (experimental): the script is run once per path instead of once per inbox.
+//! The values of the inbox are unknowns, the values computed from them are linear expressions of
+//! the unknowns, and a conditional jump on such a value splits the execution in two paths, each
+//! one with its condition on the unknowns. At the end of a path, a small solver looks for values
+//! of the unknowns meeting the conditions of the path and breaking a property, or for values
+//! leading to a path on which the script fails.
+
+use std::collections::HashMap;
+
+use hrm_interpreter::{
+    interpreter::{memory::Memory, settings::Settings, Interpreter},
+    script_object::{
+        instruction::Instruction,
+        value_box::{ValueBox, ValueBoxMemoryAddress},
+        Position, ScriptObject,
+    },
+};
+
+use crate::property::{Comparison, Property, Term};
+
+/// The values of the inboxes, unless `--range` is given: all the numbers of the game
+pub const DEFAULT_RANGE: &str = "-999..999";
+/// The number of paths explored before giving up
+const MAX_PATHS: usize = 10_000;
+/// A path taking more steps is stuck in a loop, unless a step limit is given
+const MAX_STEPS: usize = 10_000;
+/// The number of values the solver tries for a single question before giving up
+const SEARCH_BUDGET: usize = 100_000;
+
+/// The inboxes on which the properties are checked: all the inboxes of at most `max_len`
+/// numbers between `min` and `max`
+pub struct Bound {
+    pub min: i64,
+    pub max: i64,
+    pub max_len: usize,
+}
+
+pub enum Outcome {
+    /// The script doesn't fail and the properties hold on every inbox within the bound
+    Proven { paths: usize },
+    /// An inbox on which the script fails (the error), or gives an outbox (Ok) breaking a property
+    Counterexample {
+        inputs: Vec<ValueBox>,
+        result: Result<Vec<ValueBox>, String>,
+    },
+    /// No counterexample was found, but some inboxes may not have been checked
+    Inconclusive(String),
+}
+
+#[derive(Debug, thiserror::Error)]
+/// The script or the properties can't be handled symbolically
+pub enum SolveError {
+    #[error("{instruction} on line {line} can't be executed symbolically")]
+    Unsupported { instruction: String, line: usize },
+    #[error("the pointer used on line {line} depends on the inbox")]
+    SymbolicPointer { line: usize },
+    #[error(
+        "the property {0} multiplies values of the inbox: only linear properties can be checked"
+    )]
+    NonLinear(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// `coefficients[0] * in[0] + coefficients[1] * in[1] + ... + constant`
+struct Linear {
+    coefficients: Vec<i64>,
+    constant: i64,
+}
+
+impl Linear {
+    fn constant(constant: i64, unknowns: usize) -> Self {
+        Self {
+            coefficients: vec![0; unknowns],
+            constant,
+        }
+    }
+
+    fn unknown(index: usize, unknowns: usize) -> Self {
+        let mut linear = Self::constant(0, unknowns);
+        linear.coefficients[index] = 1;
+        linear
+    }
+
+    fn as_constant(&self) -> Option<i64> {
+        self.coefficients
+            .iter()
+            .all(|c| *c == 0)
+            .then_some(self.constant)
+    }
+
+    /// `self + factor * other`, None on overflow
+    fn add(&self, other: &Linear, factor: i64) -> Option<Linear> {
+        let coefficients = self
+            .coefficients
+            .iter()
+            .zip(&other.coefficients)
+            .map(|(a, b)| a.checked_add(b.checked_mul(factor)?))
+            .collect::<Option<_>>()?;
+        let constant = self
+            .constant
+            .checked_add(other.constant.checked_mul(factor)?)?;
+        Some(Linear {
+            coefficients,
+            constant,
+        })
+    }
+
+    fn scale(&self, factor: i64) -> Option<Linear> {
+        Linear::constant(0, self.coefficients.len()).add(self, factor)
+    }
+
+    fn offset(&self, offset: i64) -> Option<Linear> {
+        Some(Linear {
+            coefficients: self.coefficients.clone(),
+            constant: self.constant.checked_add(offset)?,
+        })
+    }
+
+    fn value(&self, values: &[i64]) -> i128 {
+        self.coefficients
+            .iter()
+            .zip(values)
+            .map(|(c, v)| i128::from(*c) * i128::from(*v))
+            .sum::<i128>()
+            + i128::from(self.constant)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Sign {
+    Zero,
+    NonZero,
+    Negative,
+    NonNegative,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A condition on the unknowns: the sign of a linear expression
+struct Constraint {
+    linear: Linear,
+    sign: Sign,
+}
+
+impl Constraint {
+    fn negated(&self) -> Constraint {
+        let sign = match self.sign {
+            Sign::Zero => Sign::NonZero,
+            Sign::NonZero => Sign::Zero,
+            Sign::Negative => Sign::NonNegative,
+            Sign::NonNegative => Sign::Negative,
+        };
+        Constraint {
+            linear: self.linear.clone(),
+            sign,
+        }
+    }
+
+    fn holds(&self, values: &[i64]) -> bool {
+        let value = self.linear.value(values);
+        match self.sign {
+            Sign::Zero => value == 0,
+            Sign::NonZero => value != 0,
+            Sign::Negative => value < 0,
+            Sign::NonNegative => value >= 0,
+        }
+    }
+}
+
+// ==================== Solver ====================
+
+enum Search {
+    Found(Vec<i64>),
+    Impossible,
+    GaveUp,
+}
+
+fn div_floor(a: i128, b: i128) -> i128 {
+    let quotient = a / b;
+    if a % b != 0 && (a < 0) != (b < 0) {
+        quotient - 1
+    } else {
+        quotient
+    }
+}
+
+fn div_ceil(a: i128, b: i128) -> i128 {
+    -div_floor(-a, b)
+}
+
+/// Narrow the domains so that `linear >= 0` can hold. Returns whether a domain changed,
+/// or None if the constraint can't hold anymore.
+fn narrow_non_negative(linear: &Linear, domains: &mut [(i64, i64)]) -> Option<bool> {
+    let term_maxes = domains
+        .iter()
+        .zip(&linear.coefficients)
+        .map(|((min, max), c)| {
+            let c = i128::from(*c);
+            (c * i128::from(*min)).max(c * i128::from(*max))
+        })
+        .collect::<Vec<_>>();
+    let total_max = term_maxes.iter().sum::<i128>() + i128::from(linear.constant);
+    if total_max < 0 {
+        return None;
+    }
+    let mut changed = false;
+    for i in 0..domains.len() {
+        let c = i128::from(linear.coefficients[i]);
+        if c == 0 {
+            continue;
+        }
+        // c * x >= -(the maximum of the rest)
+        let needed = term_maxes[i] - total_max;
+        let (min, max) = domains[i];
+        let (new_min, new_max) = if c > 0 {
+            (div_ceil(needed, c).max(i128::from(min)), i128::from(max))
+        } else {
+            (i128::from(min), div_floor(needed, c).min(i128::from(max)))
+        };
+        if new_min > new_max {
+            return None;
+        }
+        if (new_min, new_max) != (i128::from(min), i128::from(max)) {
+            domains[i] = (new_min as i64, new_max as i64);
+            changed = true;
+        }
+    }
+    Some(changed)
+}
+
+/// Narrow the domains so that the constraint can hold, None if it can't
+fn narrow(constraint: &Constraint, domains: &mut [(i64, i64)]) -> Option<bool> {
+    let linear = &constraint.linear;
+    let negated = || linear.scale(-1);
+    match constraint.sign {
+        Sign::NonNegative => narrow_non_negative(linear, domains),
+        // linear < 0 <=> -linear - 1 >= 0
+        Sign::Negative => narrow_non_negative(&negated()?.offset(-1)?, domains),
+        Sign::Zero => {
+            Some(narrow_non_negative(linear, domains)? | narrow_non_negative(&negated()?, domains)?)
+        }
+        // Only a single unknown left can be narrowed, when the value to avoid is a bound
+        Sign::NonZero => {
+            let free = (0..domains.len())
+                .filter(|i| linear.coefficients[*i] != 0 && domains[*i].0 < domains[*i].1)
+                .collect::<Vec<_>>();
+            let fixed = domains.iter().map(|(min, _)| *min).collect::<Vec<_>>();
+            match free[..] {
+                [] => (linear.value(&fixed) != 0).then_some(false),
+                [i] => {
+                    let c = i128::from(linear.coefficients[i]);
+                    let rest = linear.value(&fixed) - c * i128::from(fixed[i]);
+                    if rest % c != 0 {
+                        return Some(false);
+                    }
+                    let avoided = -rest / c;
+                    let (min, max) = domains[i];
+                    if avoided == i128::from(min) {
+                        domains[i].0 += 1;
+                    } else if avoided == i128::from(max) {
+                        domains[i].1 -= 1;
+                    } else {
+                        return Some(false);
+                    }
+                    Some(true)
+                }
+                _ => Some(false),
+            }
+        }
+    }
+}
+
+/// Look for values of the unknowns within their domains meeting all the constraints,
+/// the values closest to zero first
+fn search(constraints: &[Constraint], mut domains: Vec<(i64, i64)>, budget: &mut usize) -> Search {
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for constraint in constraints {
+            match narrow(constraint, &mut domains) {
+                Some(narrowed) => changed |= narrowed,
+                None => return Search::Impossible,
+            }
+        }
+    }
+
+    // Split the smallest domain
+    let unknown = (0..domains.len())
+        .filter(|i| domains[*i].0 < domains[*i].1)
+        .min_by_key(|i| domains[*i].1 - domains[*i].0);
+    let Some(unknown) = unknown else {
+        let values = domains.iter().map(|(min, _)| *min).collect::<Vec<_>>();
+        return if constraints
+            .iter()
+            .all(|constraint| constraint.holds(&values))
+        {
+            Search::Found(values)
+        } else {
+            Search::Impossible
+        };
+    };
+    let (min, max) = domains[unknown];
+    let start = 0.clamp(min, max);
+    let values = (0..=max - min)
+        .flat_map(|distance| {
+            [start + distance, start - distance]
+                .into_iter()
+                .take(1 + usize::from(distance > 0))
+        })
+        .filter(|value| (min..=max).contains(value));
+    for value in values {
+        if *budget == 0 {
+            return Search::GaveUp;
+        }
+        *budget -= 1;
+        let mut domains = domains.clone();
+        domains[unknown] = (value, value);
+        match search(constraints, domains, budget) {
+            Search::Impossible => {}
+            found_or_gave_up => return found_or_gave_up,
+        }
+    }
+    Search::Impossible
+}
+
+fn solve(constraints: &[Constraint], unknowns: usize, bound: &Bound) -> Search {
+    let mut budget = SEARCH_BUDGET;
+    search(
+        constraints,
+        vec![(bound.min, bound.max); unknowns],
+        &mut budget,
+    )
+}
+
+// ==================== Properties ====================
+
+/// The term as a linear expression of the unknowns, None if it uses a value that isn't
+/// in the inbox or the outbox, or a letter
+fn linear_term(
+    term: &Term,
+    unknowns: usize,
+    outputs: &[Value],
+) -> Result<Option<Linear>, SolveError> {
+    let both = |a: &Term, b: &Term| -> Result<Option<(Linear, Linear)>, SolveError> {
+        let a = linear_term(a, unknowns, outputs)?;
+        let b = linear_term(b, unknowns, outputs)?;
+        Ok(a.zip(b))
+    };
+    Ok(match term {
+        Term::Number(n) => Some(Linear::constant(*n, unknowns)),
+        Term::Input(i) => (*i < unknowns).then(|| Linear::unknown(*i, unknowns)),
+        Term::Output(i) => match outputs.get(*i) {
+            Some(Value::Number(linear)) => Some(linear.clone()),
+            _ => None,
+        },
+        Term::InputLen => Some(Linear::constant(unknowns as i64, unknowns)),
+        Term::OutputLen => Some(Linear::constant(outputs.len() as i64, unknowns)),
+        Term::Add(a, b) => both(a, b)?.and_then(|(a, b)| a.add(&b, 1)),
+        Term::Sub(a, b) => both(a, b)?.and_then(|(a, b)| a.add(&b, -1)),
+        Term::Mul(a, b) => match both(a, b)? {
+            Some((a, b)) => match (a.as_constant(), b.as_constant()) {
+                (Some(a), _) => b.scale(a),
+                (_, Some(b)) => a.scale(b),
+                (None, None) => return Err(SolveError::NonLinear(term.to_string())),
+            },
+            None => None,
+        },
+        Term::Neg(a) => linear_term(a, unknowns, outputs)?.and_then(|a| a.scale(-1)),
+    })
+}
+
+/// The ways the property can be `truth` at the end of a path: each case is a list of constraints
+/// that must all hold
+fn cases(
+    property: &Property,
+    truth: bool,
+    unknowns: usize,
+    outputs: &[Value],
+) -> Result<Vec<Vec<Constraint>>, SolveError> {
+    let both = |a: &Property, b: &Property| -> Result<Vec<Vec<Constraint>>, SolveError> {
+        let a = cases(a, truth, unknowns, outputs)?;
+        let b = cases(b, truth, unknowns, outputs)?;
+        Ok(a.iter()
+            .flat_map(|a| b.iter().map(move |b| [a.clone(), b.clone()].concat()))
+            .collect())
+    };
+    let either = |a: &Property, b: &Property| -> Result<Vec<Vec<Constraint>>, SolveError> {
+        Ok([
+            cases(a, truth, unknowns, outputs)?,
+            cases(b, truth, unknowns, outputs)?,
+        ]
+        .concat())
+    };
+    match property {
+        Property::Compare(a, comparison, b) => {
+            let a = linear_term(a, unknowns, outputs)?;
+            let b = linear_term(b, unknowns, outputs)?;
+            let constraint = a.zip(b).and_then(|(a, b)| {
+                let (linear, sign) = match comparison {
+                    Comparison::Equal => (a.add(&b, -1)?, Sign::Zero),
+                    Comparison::NotEqual => (a.add(&b, -1)?, Sign::NonZero),
+                    Comparison::Less => (a.add(&b, -1)?, Sign::Negative),
+                    Comparison::GreaterOrEqual => (a.add(&b, -1)?, Sign::NonNegative),
+                    Comparison::Greater => (b.add(&a, -1)?, Sign::Negative),
+                    Comparison::LessOrEqual => (b.add(&a, -1)?, Sign::NonNegative),
+                };
+                Some(Constraint { linear, sign })
+            });
+            // A comparison with a missing value is false
+            Ok(match (constraint, truth) {
+                (Some(constraint), true) => vec![vec![constraint]],
+                (Some(constraint), false) => vec![vec![constraint.negated()]],
+                (None, true) => vec![],
+                (None, false) => vec![vec![]],
+            })
+        }
+        Property::Not(property) => cases(property, !truth, unknowns, outputs),
+        Property::And(a, b) if truth => both(a, b),
+        Property::And(a, b) => either(a, b),
+        Property::Or(a, b) if truth => either(a, b),
+        Property::Or(a, b) => both(a, b),
+    }
+}
+
+// ==================== Execution ====================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(Linear),
+    Letter(char),
+}
+
+#[derive(Clone)]
+/// An execution on the inboxes meeting its constraints
+struct Path {
+    position: Position,
+    head: Option<Value>,
+    memory: HashMap<usize, Value>,
+    next_input: usize,
+    outputs: Vec<Value>,
+    constraints: Vec<Constraint>,
+    /// The conditions on which the last instruction went out of the bounds of the game (strict mode)
+    out_of_bounds: Vec<Constraint>,
+    steps: usize,
+}
+
+enum Step {
+    Next,
+    Jump(Position),
+    /// Jump if the constraint holds
+    Branch(Constraint, Position),
+    Finished,
+    /// The script fails on the inboxes of the path
+    Fails,
+}
+
+/// The symbolic execution of a script on the inboxes of a length
+struct Explorer<'a> {
+    script: &'a ScriptObject,
+    max_memory_address: usize,
+    strict_hrm: bool,
+    unknowns: usize,
+}
+
+impl Explorer<'_> {
+    /// The address of the tile, None if the script fails on it
+    fn address(
+        &self,
+        path: &Path,
+        vbma: &ValueBoxMemoryAddress,
+    ) -> Result<Option<usize>, SolveError> {
+        let address = match vbma {
+            ValueBoxMemoryAddress::Pointer(address) => *address,
+            ValueBoxMemoryAddress::PointerAddress(pointer) => match path.memory.get(pointer) {
+                Some(Value::Number(linear)) => match linear.as_constant() {
+                    Some(address) if address < 0 => return Ok(None),
+                    Some(address) => address as usize,
+                    None => {
+                        return Err(SolveError::SymbolicPointer {
+                            line: self.script.get_line(path.position).unwrap_or_default(),
+                        })
+                    }
+                },
+                Some(Value::Letter(_)) | None => return Ok(None),
+            },
+        };
+        Ok((address <= self.max_memory_address).then_some(address))
+    }
+
+    /// The tile and the value on it, None if the script fails on it
+    fn operand(
+        &self,
+        path: &Path,
+        vbma: &ValueBoxMemoryAddress,
+    ) -> Result<Option<(usize, Value)>, SolveError> {
+        Ok(self.address(path, vbma)?.and_then(|address| {
+            let value = path.memory.get(&address)?.clone();
+            Some((address, value))
+        }))
+    }
+
+    /// The result of an operation. In strict mode, the conditions on which it's out of the bounds
+    /// of the game are saved in the path.
+    fn bounded(&self, path: &mut Path, linear: Option<Linear>) -> Option<Value> {
+        let linear = linear?;
+        if self.strict_hrm {
+            let above = linear.offset(-i64::from(ValueBox::MAX_NUMBER) - 1)?;
+            let below = linear.offset(-i64::from(ValueBox::MIN_NUMBER))?;
+            path.out_of_bounds = vec![
+                Constraint {
+                    linear: above,
+                    sign: Sign::NonNegative,
+                },
+                Constraint {
+                    linear: below,
+                    sign: Sign::Negative,
+                },
+            ];
+        }
+        Some(Value::Number(linear))
+    }
+
+    fn step(&self, path: &mut Path) -> Result<Step, SolveError> {
+        let instruction = self.script.get_instruction(path.position).unwrap();
+        let target = |instruction: &Instruction| {
+            let block = instruction.jump_target().and_then(|target| target.block());
+            Position {
+                block: block.expect("the jumps are valid"),
+                instruction: 0,
+            }
+        };
+        match instruction {
+            Instruction::In => {
+                if path.next_input == self.unknowns {
+                    return Ok(Step::Finished);
+                }
+                path.head = Some(Value::Number(Linear::unknown(
+                    path.next_input,
+                    self.unknowns,
+                )));
+                path.next_input += 1;
+            }
+            Instruction::Out => match &path.head {
+                Some(value) => path.outputs.push(value.clone()),
+                None => return Ok(Step::Fails),
+            },
+            Instruction::CopyFrom(vbma) => match self.operand(path, vbma)? {
+                Some((_, value)) => path.head = Some(value),
+                None => return Ok(Step::Fails),
+            },
+            Instruction::CopyTo(vbma) => {
+                let address = match self.address(path, vbma)? {
+                    Some(address) => address,
+                    None => return Ok(Step::Fails),
+                };
+                match path.head.clone() {
+                    Some(value) => path.memory.insert(address, value),
+                    None => return Ok(Step::Fails),
+                };
+            }
+            Instruction::Add(vbma) | Instruction::Sub(vbma) => {
+                let (_, operand) = match self.operand(path, vbma)? {
+                    Some(operand) => operand,
+                    None => return Ok(Step::Fails),
+                };
+                let factor = if matches!(instruction, Instruction::Add(_)) {
+                    1
+                } else {
+                    -1
+                };
+                let result = match (path.head.clone(), operand) {
+                    (None, _) => return Ok(Step::Fails),
+                    (Some(Value::Number(head)), Value::Number(operand)) => {
+                        head.add(&operand, factor)
+                    }
+                    (Some(Value::Letter(head)), Value::Letter(operand)) if factor < 0 => {
+                        let distance =
+                            ValueBox::alphabet_index(head) - ValueBox::alphabet_index(operand);
+                        Some(Linear::constant(i64::from(distance), self.unknowns))
+                    }
+                    _ => return Ok(Step::Fails),
+                };
+                match self.bounded(path, result) {
+                    Some(value) => path.head = Some(value),
+                    None => return Ok(Step::Fails),
+                }
+            }
+            Instruction::BumpUp(vbma) | Instruction::BumpDown(vbma) => {
+                let (address, operand) = match self.operand(path, vbma)? {
+                    Some(operand) => operand,
+                    None => return Ok(Step::Fails),
+                };
+                let offset = if matches!(instruction, Instruction::BumpUp(_)) {
+                    1
+                } else {
+                    -1
+                };
+                let result = match operand {
+                    Value::Number(linear) => linear.offset(offset),
+                    Value::Letter(_) => return Ok(Step::Fails),
+                };
+                match self.bounded(path, result) {
+                    Some(value) => {
+                        path.memory.insert(address, value.clone());
+                        path.head = Some(value);
+                    }
+                    None => return Ok(Step::Fails),
+                }
+            }
+            Instruction::Jump(_) => return Ok(Step::Jump(target(instruction))),
+            Instruction::JumpIfZero(_) | Instruction::JumpIfNegative(_) => {
+                let (sign, jumps): (_, fn(&ValueBox) -> bool) =
+                    if matches!(instruction, Instruction::JumpIfZero(_)) {
+                        (Sign::Zero, ValueBox::is_zero)
+                    } else {
+                        (Sign::Negative, ValueBox::is_negative)
+                    };
+                match &path.head {
+                    None => return Ok(Step::Fails),
+                    Some(Value::Letter(letter)) => {
+                        if jumps(&ValueBox::from(*letter)) {
+                            return Ok(Step::Jump(target(instruction)));
+                        }
+                    }
+                    Some(Value::Number(linear)) => {
+                        let condition = Constraint {
+                            linear: linear.clone(),
+                            sign,
+                        };
+                        return Ok(match linear.as_constant() {
+                            Some(_) if condition.holds(&[]) => Step::Jump(target(instruction)),
+                            Some(_) => Step::Next,
+                            None => Step::Branch(condition, target(instruction)),
+                        });
+                    }
+                }
+            }
+            Instruction::Halt => return Ok(Step::Finished),
+            Instruction::Nop | Instruction::Debug(_) => {}
+            _ => {
+                return Err(SolveError::Unsupported {
+                    instruction: instruction.name().to_string(),
+                    line: self.script.get_line(path.position).unwrap_or_default(),
+                })
+            }
+        }
+        Ok(Step::Next)
+    }
+}
+
+/// The outcome of the paths of the inboxes of a length
+enum Exploration {
+    /// All the paths were explored, without counterexample
+    Done { paths: usize },
+    /// A counterexample, as the values of the unknowns
+    Found(Vec<i64>),
+}
+
+impl Explorer<'_> {
+    /// Run all the paths of the script, depth first.
+    /// `gave_up` is set to the reason why some inboxes couldn't be checked.
+    fn explore(
+        &self,
+        memory: &HashMap<usize, Value>,
+        properties: &[Property],
+        bound: &Bound,
+        max_steps: usize,
+        gave_up: &mut Option<String>,
+    ) -> Result<Exploration, SolveError> {
+        let start = Path {
+            position: Position::default(),
+            head: None,
+            memory: memory.clone(),
+            next_input: 0,
+            outputs: vec![],
+            constraints: vec![],
+            out_of_bounds: vec![],
+            steps: 0,
+        };
+        let mut paths = vec![start];
+        let mut explored = 0;
+        while let Some(mut path) = paths.pop() {
+            let Some(position) = self.script.next_instruction_position(path.position) else {
+                explored += 1;
+                if let Some(values) = self.broken_property(&path, properties, bound, gave_up)? {
+                    return Ok(Exploration::Found(values));
+                }
+                continue;
+            };
+            path.position = position;
+            if path.steps >= max_steps {
+                gave_up.get_or_insert(format!("a path takes more than {} steps", max_steps));
+                continue;
+            }
+            if explored >= MAX_PATHS {
+                gave_up.get_or_insert(format!("there are more than {} paths", MAX_PATHS));
+                break;
+            }
+            path.steps += 1;
+
+            let next = Position {
+                instruction: position.instruction + 1,
+                ..position
+            };
+            match self.step(&mut path)? {
+                Step::Next => path.position = next,
+                Step::Jump(target) => path.position = target,
+                Step::Branch(condition, target) => {
+                    for (condition, position) in [(condition.negated(), next), (condition, target)]
+                    {
+                        let mut constraints = path.constraints.clone();
+                        constraints.push(condition);
+                        if self.solve(&constraints, bound, gave_up).is_some() {
+                            paths.push(Path {
+                                position,
+                                constraints,
+                                ..path.clone()
+                            });
+                        }
+                    }
+                    continue;
+                }
+                Step::Finished => {
+                    explored += 1;
+                    if let Some(values) = self.broken_property(&path, properties, bound, gave_up)? {
+                        return Ok(Exploration::Found(values));
+                    }
+                    continue;
+                }
+                Step::Fails => {
+                    if let Some(values) = self.solve(&path.constraints, bound, gave_up) {
+                        return Ok(Exploration::Found(values));
+                    }
+                    explored += 1;
+                    continue;
+                }
+            }
+
+            // In strict mode, the inboxes on which the value is out of bounds fail
+            for outside in std::mem::take(&mut path.out_of_bounds) {
+                let mut constraints = path.constraints.clone();
+                constraints.push(outside.clone());
+                if let Some(values) = self.solve(&constraints, bound, gave_up) {
+                    return Ok(Exploration::Found(values));
+                }
+                path.constraints.push(outside.negated());
+            }
+            paths.push(path);
+        }
+        Ok(Exploration::Done { paths: explored })
+    }
+
+    /// Values of the unknowns meeting the constraints, if the solver finds some
+    fn solve(
+        &self,
+        constraints: &[Constraint],
+        bound: &Bound,
+        gave_up: &mut Option<String>,
+    ) -> Option<Vec<i64>> {
+        match solve(constraints, self.unknowns, bound) {
+            Search::Found(values) => Some(values),
+            Search::Impossible => None,
+            Search::GaveUp => {
+                gave_up.get_or_insert("the solver gave up on a path".to_string());
+                None
+            }
+        }
+    }
+
+    /// Values of the unknowns on which the finished path breaks a property
+    fn broken_property(
+        &self,
+        path: &Path,
+        properties: &[Property],
+        bound: &Bound,
+        gave_up: &mut Option<String>,
+    ) -> Result<Option<Vec<i64>>, SolveError> {
+        for property in properties {
+            for case in cases(property, false, self.unknowns, &path.outputs)? {
+                let constraints = [path.constraints.clone(), case].concat();
+                if let Some(values) = self.solve(&constraints, bound, gave_up) {
+                    return Ok(Some(values));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Check the properties on all the inboxes within the bound, the shortest inboxes first.
+/// The counterexamples are confirmed by running the script on them.
+pub fn check(
+    script: &ScriptObject,
+    memory: &HashMap<usize, ValueBox>,
+    max_memory_address: usize,
+    properties: &[Property],
+    bound: &Bound,
+    settings: &Settings,
+) -> Result<Outcome, SolveError> {
+    let settings = Settings {
+        max_steps: settings.max_steps.or(Some(MAX_STEPS)),
+        ..settings.clone()
+    };
+    let mut gave_up = None;
+    let mut paths = 0;
+    for len in 0..=bound.max_len {
+        let explorer = Explorer {
+            script,
+            max_memory_address,
+            strict_hrm: settings.strict_hrm,
+            unknowns: len,
+        };
+        let symbolic_memory = memory
+            .iter()
+            .map(|(address, value)| {
+                let value = match value {
+                    ValueBox::Number(n) => Value::Number(Linear::constant(i64::from(*n), len)),
+                    ValueBox::Character(c) => Value::Letter(*c),
+                };
+                (*address, value)
+            })
+            .collect();
+        let max_steps = settings.max_steps.unwrap_or(MAX_STEPS);
+        let values =
+            match explorer.explore(&symbolic_memory, properties, bound, max_steps, &mut gave_up)? {
+                Exploration::Done { paths: explored } => {
+                    paths += explored;
+                    continue;
+                }
+                Exploration::Found(values) => values,
+            };
+
+        let inputs = values
+            .into_iter()
+            .map(|value| ValueBox::Number(value as i32))
+            .collect::<Vec<_>>();
+        let mut interpreter = Interpreter::with_settings(
+            Memory::with_data(memory.clone(), max_memory_address),
+            settings.clone(),
+        );
+        let result = interpreter
+            .execute(script, &inputs)
+            .map(|(outputs, _)| outputs)
+            .map_err(|e| e.message());
+        let confirmed = match &result {
+            Ok(outputs) => properties
+                .iter()
+                .any(|property| !property.holds(&inputs, outputs)),
+            Err(_) => true,
+        };
+        if !confirmed {
+            gave_up.get_or_insert(format!(
+                "the inbox {} looked like a counterexample, but isn't one",
+                inbox_text(&inputs)
+            ));
+            continue;
+        }
+        return Ok(Outcome::Counterexample { inputs, result });
+    }
+    Ok(match gave_up {
+        Some(reason) => Outcome::Inconclusive(reason),
+        None => Outcome::Proven { paths },
+    })
+}
+
+fn inbox_text(values: &[ValueBox]) -> String {
+    if values.is_empty() {
+        return "(empty)".to_string();
+    }
+    values
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Print the outcome to stderr. Returns true if the properties were proven.
+pub fn report(outcome: &Outcome, properties: &[Property], bound: &Bound) -> bool {
+    let inboxes = format!(
+        "every inbox of at most {} values in {}..{}",
+        bound.max_len, bound.min, bound.max
+    );
+    match outcome {
+        Outcome::Proven { paths } if properties.is_empty() => {
+            eprintln!("The script doesn't fail on {} ({} paths)", inboxes, paths);
+            true
+        }
+        Outcome::Proven { paths } => {
+            eprintln!("The properties hold on {} ({} paths)", inboxes, paths);
+            true
+        }
+        Outcome::Counterexample { inputs, result } => {
+            eprintln!("Counterexample: {}", inbox_text(inputs));
+            match result {
+                Ok(outputs) => {
+                    eprintln!("  outbox: {}", inbox_text(outputs));
+                    for property in properties
+                        .iter()
+                        .filter(|property| !property.holds(inputs, outputs))
+                    {
+                        eprintln!("  false: {}", property);
+                    }
+                }
+                Err(e) => eprintln!("  the script fails: {}", e),
+            }
+            false
+        }
+        Outcome::Inconclusive(reason) => {
+            eprintln!("Inconclusive: no counterexample found, but {}", reason);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod solve_tests {
+    use super::*;
+
+    const SUM: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+    a:
+        INBOX
+        COPYTO 0
+        INBOX
+        ADD 0
+        OUTBOX
+        JUMP a
+    ";
+
+    // Outputs the largest of two values, but the smallest when the first one is zero
+    const MAX_BUT_ZERO: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+        INBOX
+        COPYTO 0
+        INBOX
+        COPYTO 1
+        COPYFROM 0
+        JUMPZ b
+        SUB 1
+        JUMPN b
+        COPYFROM 0
+        OUTBOX
+        JUMP c
+    b:
+        COPYFROM 1
+        OUTBOX
+    c:
+    ";
+
+    fn check_script(script: &str, properties: &[&str], settings: &Settings) -> Outcome {
+        let script = script.parse::<ScriptObject>().unwrap();
+        let properties = properties
+            .iter()
+            .map(|property| property.parse::<Property>().unwrap())
+            .collect::<Vec<_>>();
+        let bound = Bound {
+            min: -999,
+            max: 999,
+            max_len: 3,
+        };
+        check(&script, &HashMap::new(), 24, &properties, &bound, settings).unwrap()
+    }
+
+    #[test]
+    fn test_proven() {
+        let outcome = check_script(
+            SUM,
+            &["len(in) < 2 or out[0] == in[0] + in[1]"],
+            &Settings::default(),
+        );
+        assert!(matches!(outcome, Outcome::Proven { .. }));
+
+        let outcome = check_script(
+            MAX_BUT_ZERO,
+            &[
+                "len(in) < 2 or out[0] >= in[1]",
+                "len(out) == 1 or len(in) < 2",
+            ],
+            &Settings::default(),
+        );
+        assert!(matches!(outcome, Outcome::Proven { .. }));
+    }
+
+    #[test]
+    fn test_counterexample() {
+        let outcome = check_script(
+            MAX_BUT_ZERO,
+            &["len(in) < 2 or out[0] >= in[0]"],
+            &Settings::default(),
+        );
+        let Outcome::Counterexample { inputs, result } = outcome else {
+            panic!("expected a counterexample");
+        };
+        // The smallest one: the first value is zero, the second one negative
+        assert_eq!(inputs, vec![ValueBox::from(0), ValueBox::from(-1)]);
+        assert_eq!(result, Ok(vec![ValueBox::from(-1)]));
+    }
+
+    #[test]
+    fn test_failing_inbox() {
+        // Reads an empty tile on the negative values
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            JUMPN a
+            OUTBOX
+            JUMP b
+        a:
+            COPYFROM 0
+            OUTBOX
+        b:
+        ";
+        let outcome = check_script(script, &[], &Settings::default());
+        let Outcome::Counterexample { inputs, result } = outcome else {
+            panic!("expected a counterexample");
+        };
+        assert_eq!(inputs, vec![ValueBox::from(-1)]);
+        assert!(result.is_err());
+
+        // The sum only goes past the bounds of the game in strict mode
+        let script = SUM.replace("JUMP a", "");
+        let outcome = check_script(&script, &[], &Settings::default());
+        assert!(matches!(outcome, Outcome::Proven { .. }));
+        let settings = Settings {
+            strict_hrm: true,
+            ..Settings::default()
+        };
+        let Outcome::Counterexample { inputs, result } = check_script(&script, &[], &settings)
+        else {
+            panic!("expected a counterexample");
+        };
+        assert_eq!(inputs.len(), 2);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let script = "-- HUMAN RESOURCE MACHINE PROGRAM --
+            INBOX
+            COPYTO 0
+            COPYFROM [0]
+            OUTBOX
+        ";
+        let script = script.parse::<ScriptObject>().unwrap();
+        let bound = Bound {
+            min: 0,
+            max: 9,
+            max_len: 1,
+        };
+        assert!(matches!(
+            check(
+                &script,
+                &HashMap::new(),
+                24,
+                &[],
+                &bound,
+                &Settings::default()
+            ),
+            Err(SolveError::SymbolicPointer { line: 4 })
+        ));
+    }
+}
@@ -0,0 +1,230 @@
+//! Colors and bold text on the terminal: the errors and the states dumped with them,
+//! the warnings, the mismatches of the outputs and the lint warnings.
+//!
+//! A stream is colored only when it's a terminal, and never with `--no-color` or when the
+//! `NO_COLOR` environment variable is set (see <https://no-color.org>).
+
+use std::{
+    env,
+    fmt::Display,
+    io::{self, IsTerminal},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Style {
+    /// Bold red
+    Error,
+    /// Bold yellow
+    Warning,
+    /// Bold green
+    Success,
+    Bold,
+    /// Bold cyan, for the titles of the parts of a state
+    Heading,
+    /// Bold blue, for the margin of the excerpts of the script
+    Gutter,
+    Dim,
+}
+
+impl Style {
+    /// The parameters of the ANSI escape code
+    fn code(self) -> &'static str {
+        match self {
+            Self::Error => "1;31",
+            Self::Warning => "1;33",
+            Self::Success => "1;32",
+            Self::Bold => "1",
+            Self::Heading => "1;36",
+            Self::Gutter => "1;34",
+            Self::Dim => "2",
+        }
+    }
+
+    pub fn paint(self, text: impl Display) -> String {
+        format!("\x1b[{}m{}\x1b[0m", self.code(), text)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+static STDOUT_COLORED: AtomicBool = AtomicBool::new(false);
+static STDERR_COLORED: AtomicBool = AtomicBool::new(false);
+
+/// Color the streams that are terminals, unless the colors are turned off
+pub fn init(no_color: bool) {
+    let enabled = !no_color && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty());
+    STDOUT_COLORED.store(enabled && io::stdout().is_terminal(), Ordering::Relaxed);
+    STDERR_COLORED.store(enabled && io::stderr().is_terminal(), Ordering::Relaxed);
+}
+
+impl Stream {
+    pub fn is_colored(self) -> bool {
+        match self {
+            Self::Stdout => STDOUT_COLORED.load(Ordering::Relaxed),
+            Self::Stderr => STDERR_COLORED.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The text in the style if the stream is colored
+    pub fn paint(self, style: Style, text: impl Display) -> String {
+        if self.is_colored() {
+            style.paint(text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// The error highlighted as [`highlight`] does, if the stream is colored
+    pub fn highlight(self, error: &str) -> String {
+        if self.is_colored() {
+            highlight(error)
+        } else {
+            error.to_string()
+        }
+    }
+}
+
+/// The lint warning with its location in bold and its message in yellow, on a colored stdout
+pub fn lint_warning(warning: &impl Display) -> String {
+    let warning = warning.to_string();
+    match warning.split_once(": ") {
+        Some((location, message)) => format!(
+            "{} {}",
+            Stream::Stdout.paint(Style::Bold, format!("{}:", location)),
+            Stream::Stdout.paint(Style::Warning, message)
+        ),
+        None => warning,
+    }
+}
+
+/// The titles of the parts of the state dumped with an error
+const HEADINGS: [&str; 5] = [
+    "-- STATE --",
+    "-- FLOOR --",
+    "Inputs left:",
+    "Outputs:",
+    "Memory:",
+];
+
+/// The line of an error, with its parts highlighted
+fn highlight_line(line: &str) -> String {
+    for prefix in ["error:", "INTERPRETER ERROR"] {
+        if let Some(message) = line.strip_prefix(prefix) {
+            return format!(
+                "{}{}",
+                Style::Error.paint(prefix),
+                Style::Bold.paint(message)
+            );
+        }
+    }
+    if let Some(heading) = HEADINGS.iter().find(|heading| line.starts_with(*heading)) {
+        return format!(
+            "{}{}",
+            Style::Heading.paint(heading),
+            &line[heading.len()..]
+        );
+    }
+    let indent = line.len() - line.trim_start().len();
+    let (margin, rest) = line.split_at(indent);
+    if let Some(location) = rest.strip_prefix("-->") {
+        return format!(
+            "{}{}",
+            Style::Gutter.paint(line[..indent + 3].to_string()),
+            location
+        );
+    }
+    if let Some(note) = rest.strip_prefix("= note:") {
+        return format!(
+            "{}{} {}{}",
+            margin,
+            Style::Gutter.paint("="),
+            Style::Bold.paint("note:"),
+            note
+        );
+    }
+    // The margin of an excerpt: the number of the line, or spaces, then " |"
+    if let Some((number, text)) = line.split_once(" |") {
+        if number.chars().all(|c| c.is_ascii_digit() || c == ' ') && !number.is_empty() {
+            let margin = Style::Gutter.paint(format!("{} |", number));
+            if !text.trim().is_empty() && text.trim().chars().all(|c| c == '^') {
+                return format!("{}{}", margin, Style::Error.paint(text));
+            }
+            return format!("{}{}", margin, text);
+        }
+    }
+    // The tiles of the memory, "<address>: <value>"
+    if let Some((address, value)) = line.split_once(": ") {
+        if !address.is_empty() && address.chars().all(|c| c.is_ascii_digit()) {
+            return format!("{}: {}", Style::Dim.paint(address), value);
+        }
+    }
+    line.to_string()
+}
+
+/// The error with its parts highlighted: the kind of the error and its message,
+/// the margin of the excerpt of the script and the carets under the faulty part,
+/// and the titles of the state dumped with it
+pub fn highlight(error: &str) -> String {
+    error
+        .split('\n')
+        .map(highlight_line)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod style_tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight() {
+        let error = "error: COPYTOO is not a valid instruction
+ --> solution.hrm:3:5
+  |
+3 |     COPYTOO  2
+  |     ^^^^^^^
+  = note: no value
+-- STATE --
+Inputs left: 1, 2
+Memory:
+0: A
+";
+        let highlighted = highlight(error);
+        let lines = highlighted.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            "\x1b[1;31merror:\x1b[0m\x1b[1m COPYTOO is not a valid instruction\x1b[0m"
+        );
+        assert_eq!(lines[1], "\x1b[1;34m -->\x1b[0m solution.hrm:3:5");
+        assert_eq!(lines[3], "\x1b[1;34m3 |\x1b[0m     COPYTOO  2");
+        assert_eq!(
+            lines[4],
+            "\x1b[1;34m  |\x1b[0m\x1b[1;31m     ^^^^^^^\x1b[0m"
+        );
+        assert_eq!(
+            lines[5],
+            "  \x1b[1;34m=\x1b[0m \x1b[1mnote:\x1b[0m no value"
+        );
+        assert_eq!(lines[7], "\x1b[1;36mInputs left:\x1b[0m 1, 2");
+        assert_eq!(lines[9], "\x1b[2m0\x1b[0m: A");
+        assert!(highlighted.ends_with("A\n"));
+
+        // The other lines are kept as they are
+        assert_eq!(
+            highlight("Usage: hrm-interpreter.exe <a | b>"),
+            "Usage: hrm-interpreter.exe <a | b>"
+        );
+    }
+
+    #[test]
+    fn test_uncolored_stream() {
+        // The streams aren't colored until init
+        assert_eq!(Stream::Stderr.paint(Style::Error, "failed"), "failed");
+        assert_eq!(Stream::Stderr.highlight("error: x"), "error: x");
+    }
+}
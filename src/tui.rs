@@ -0,0 +1,361 @@
+use std::{io, time::Duration};
+
+use hrm_interpreter::{
+    interpreter::{geometry::FloorGeometry, ExecutionStatus, Interpreter},
+    script_object::{value_box::ValueBox, ScriptObject},
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Paragraph},
+    DefaultTerminal, Frame,
+};
+
+/// Time between two steps when the execution is playing
+const PLAY_INTERVAL: Duration = Duration::from_millis(200);
+/// Width of a tile of the floor, address and value included
+const TILE_WIDTH: usize = 11;
+
+/// The execution shown in the terminal, driven by the keys
+struct Stepper<'a, F: Fn() -> Interpreter> {
+    script: &'a ScriptObject,
+    inputs: &'a [ValueBox],
+    /// Creates the interpreter of a new execution
+    new_interpreter: F,
+    interpreter: Interpreter,
+    outputs: Vec<ValueBox>,
+    /// Why the execution can't go on, once it's finished or failed
+    ended: Option<String>,
+    playing: bool,
+    /// How the tiles are laid out, if the floor has a geometry
+    floor: Option<FloorGeometry>,
+}
+
+impl<'a, F: Fn() -> Interpreter> Stepper<'a, F> {
+    fn new(script: &'a ScriptObject, inputs: &'a [ValueBox], new_interpreter: F) -> Self {
+        Self {
+            script,
+            inputs,
+            interpreter: new_interpreter(),
+            new_interpreter,
+            outputs: vec![],
+            ended: None,
+            playing: false,
+            floor: None,
+        }
+    }
+
+    fn step(&mut self) {
+        if self.ended.is_none() {
+            let status = self
+                .interpreter
+                .step(self.script, self.inputs, &mut self.outputs);
+            self.update(status);
+        }
+    }
+
+    /// Execute until the end of the script or a breakpoint
+    fn run(&mut self) {
+        if self.ended.is_none() {
+            let status = self
+                .interpreter
+                .run(self.script, self.inputs, &mut self.outputs);
+            self.update(status);
+        }
+    }
+
+    fn back(&mut self) {
+        if self.interpreter.rewind(1, &mut self.outputs) > 0 {
+            self.ended = None;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.interpreter = (self.new_interpreter)();
+        self.outputs.clear();
+        self.ended = None;
+        self.playing = false;
+    }
+
+    fn update<E: ToString>(&mut self, status: Result<ExecutionStatus, E>) {
+        self.ended = match status {
+            Ok(ExecutionStatus::Paused(_)) => None,
+            Ok(ExecutionStatus::Finished) => Some("finished".to_string()),
+            // The message without the state, which is on the screen
+            Err(e) => Some(
+                e.to_string()
+                    .split("\n-- STATE --")
+                    .next()
+                    .unwrap_or_default()
+                    .replace("\n\t", " "),
+            ),
+        };
+        if self.ended.is_some() {
+            self.playing = false;
+        }
+    }
+}
+
+fn draw<F: Fn() -> Interpreter>(frame: &mut Frame, stepper: &Stepper<F>, source: &str) {
+    let [main, status] =
+        Layout::vertical([Constraint::Fill(1), Constraint::Length(3)]).areas(frame.area());
+    let [script, office] =
+        Layout::horizontal([Constraint::Percentage(40), Constraint::Fill(1)]).areas(main);
+    let [belts, floor] =
+        Layout::vertical([Constraint::Percentage(40), Constraint::Fill(1)]).areas(office);
+    let [inbox, head, outbox] = Layout::horizontal([
+        Constraint::Fill(1),
+        Constraint::Length(12),
+        Constraint::Fill(1),
+    ])
+    .areas(belts);
+
+    draw_script(frame, script, stepper, source);
+
+    let interpreter = &stepper.interpreter;
+    let inputs_left = stepper
+        .inputs
+        .get(interpreter.inputs_read()..)
+        .unwrap_or_default();
+    frame.render_widget(belt("Inbox", inputs_left), inbox);
+    let held = interpreter
+        .head()
+        .map(|value| value.to_string())
+        .unwrap_or_default();
+    frame.render_widget(
+        Paragraph::new(Line::from(held).bold().centered()).block(Block::bordered().title("Head")),
+        head,
+    );
+    frame.render_widget(belt("Outbox", &stepper.outputs), outbox);
+
+    draw_floor(frame, floor, interpreter, stepper.floor);
+
+    let state = match (&stepper.ended, stepper.playing) {
+        (Some(ended), _) => ended.clone(),
+        (None, true) => "playing".to_string(),
+        (None, false) => "paused".to_string(),
+    };
+    let keys = "s/→ step  ←/b back  space play/pause  r run  x reset  q quit";
+    frame.render_widget(
+        Paragraph::new(vec![
+            Line::from(format!("Step {} | {}", interpreter.steps(), state)),
+            Line::from(keys).dim(),
+        ])
+        .block(Block::bordered().title("Execution")),
+        status,
+    );
+}
+
+/// The source of the script, with the next instruction highlighted and kept in view
+fn draw_script<F: Fn() -> Interpreter>(
+    frame: &mut Frame,
+    area: Rect,
+    stepper: &Stepper<F>,
+    source: &str,
+) {
+    let current_line = stepper
+        .script
+        .next_instruction_position(stepper.interpreter.position())
+        .and_then(|position| stepper.script.get_line(position))
+        .filter(|_| stepper.ended.is_none());
+    let lines = source
+        .lines()
+        .enumerate()
+        .map(|(i, text)| {
+            let line = Line::from(format!("{:>4} {}", i + 1, text));
+            if current_line == Some(i + 1) {
+                line.reversed()
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let visible = area.height.saturating_sub(2) as usize;
+    let scroll = current_line.unwrap_or_default().saturating_sub(visible / 2);
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(Block::bordered().title("Script"))
+            .scroll((scroll as u16, 0)),
+        area,
+    );
+}
+
+/// The values on a belt, the next one first
+fn belt<'a>(title: &'a str, values: &[ValueBox]) -> Paragraph<'a> {
+    let lines = values
+        .iter()
+        .map(|value| Line::from(value.to_string()).centered())
+        .collect::<Vec<_>>();
+    Paragraph::new(lines).block(Block::bordered().title(title))
+}
+
+/// The tiles of the floor, as a grid: the rows of the geometry if any,
+/// else as many tiles per row as the width allows
+fn draw_floor(
+    frame: &mut Frame,
+    area: Rect,
+    interpreter: &Interpreter,
+    geometry: Option<FloorGeometry>,
+) {
+    let memory = interpreter.memory();
+    if let Some(geometry) = geometry {
+        let grid = geometry.render(|address| memory.get(&address).copied());
+        let lines = grid.lines().map(Line::from).collect::<Vec<_>>();
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Floor")),
+            area,
+        );
+        return;
+    }
+    let addresses = if memory.get_max_address() == usize::MAX {
+        // No maximum: only show the tiles holding a value
+        let mut addresses = memory.addresses().collect::<Vec<usize>>();
+        addresses.sort_unstable();
+        addresses
+    } else {
+        (0..=memory.get_max_address()).collect()
+    };
+
+    let columns = (area.width.saturating_sub(2) as usize / TILE_WIDTH).max(1);
+    let lines = addresses
+        .chunks(columns)
+        .map(|row| {
+            let tiles = row
+                .iter()
+                .map(|address| {
+                    let value = memory
+                        .get(address)
+                        .map(|value| value.to_string())
+                        .unwrap_or_default();
+                    format!("{:>3}:{:<w$}", address, value, w = TILE_WIDTH - 4)
+                })
+                .collect::<String>();
+            Line::from(tiles)
+        })
+        .collect::<Vec<_>>();
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("Floor")),
+        area,
+    );
+}
+
+fn event_loop<F: Fn() -> Interpreter>(
+    terminal: &mut DefaultTerminal,
+    stepper: &mut Stepper<F>,
+    source: &str,
+) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| draw(frame, stepper, source))?;
+
+        let timeout = if stepper.playing {
+            PLAY_INTERVAL
+        } else {
+            Duration::from_secs(3600)
+        };
+        if !event::poll(timeout)? {
+            if stepper.playing {
+                stepper.step();
+            }
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('s') | KeyCode::Right => stepper.step(),
+            KeyCode::Char('b') | KeyCode::Left => stepper.back(),
+            KeyCode::Char(' ') => stepper.playing = !stepper.playing && stepper.ended.is_none(),
+            KeyCode::Char('r') => stepper.run(),
+            KeyCode::Char('x') => stepper.reset(),
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+/// Show the execution of the script in the terminal, step by step, until the user quits.
+/// `source` is the text of the script, `floor` lays out its tiles, `new_interpreter` creates the interpreter
+/// at the beginning of the execution (and when it's reset).
+/// Going back needs an interpreter with a history (see [`crate::debugger::HISTORY_SIZE`]).
+pub fn run_tui(
+    script: &ScriptObject,
+    source: &str,
+    inputs: &[ValueBox],
+    floor: Option<FloorGeometry>,
+    new_interpreter: impl Fn() -> Interpreter,
+) -> io::Result<()> {
+    let mut stepper = Stepper::new(script, inputs, new_interpreter);
+    stepper.floor = floor;
+    let mut terminal = ratatui::try_init()?;
+    let result = event_loop(&mut terminal, &mut stepper, source);
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tui_tests {
+    use hrm_interpreter::interpreter::{memory::Memory, settings::Settings};
+    use ratatui::{backend::TestBackend, Terminal};
+
+    use super::*;
+
+    const SCRIPT: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+a:
+    INBOX
+    COPYTO   0
+    OUTBOX
+    JUMP     a
+";
+
+    fn new_interpreter() -> Interpreter {
+        let settings = Settings {
+            history_size: 100,
+            ..Settings::default()
+        };
+        Interpreter::with_settings(Memory::with_data(Default::default(), 2), settings)
+    }
+
+    fn screen<F: Fn() -> Interpreter>(stepper: &Stepper<F>) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(80, 20)).unwrap();
+        terminal.draw(|frame| draw(frame, stepper, SCRIPT)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_stepper() {
+        let script = SCRIPT.parse::<ScriptObject>().unwrap();
+        let inputs = [ValueBox::from(7), ValueBox::from(8)];
+        let mut stepper = Stepper::new(&script, &inputs, new_interpreter);
+
+        stepper.step();
+        stepper.step();
+        assert_eq!(stepper.interpreter.head(), Some(ValueBox::from(7)));
+        let screen = screen(&stepper);
+        assert!(screen.contains("Step 2 | paused"), "{}", screen);
+        assert!(screen.contains("  0:7"), "{}", screen);
+
+        stepper.run();
+        assert_eq!(stepper.outputs, inputs);
+        assert_eq!(stepper.ended.as_deref(), Some("finished"));
+
+        stepper.back();
+        assert_eq!(stepper.ended, None);
+        stepper.reset();
+        assert_eq!(stepper.interpreter.steps(), 0);
+        assert!(stepper.outputs.is_empty());
+    }
+}
@@ -0,0 +1,209 @@
+//! The JavaScript API, to run scripts in a browser.
+//!
+//! The values cross the boundary as JSON strings: the inputs are an array (`[1, "A"]`),
+//! the floor is an object from addresses to values (`{"0": 5}`), and the results are objects.
+//! The errors are the messages printed by the command line tool.
+
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{
+    interpreter::{memory::Memory, ExecuteScriptError, ExecutionStatus, Interpreter},
+    script_object::{value_box::ValueBox, ScriptObject},
+};
+
+/// The file name of the script in the error messages
+const SCRIPT_NAME: &str = "script";
+
+fn parse_script(script: &str) -> Result<ScriptObject, String> {
+    let script_object = script.parse::<ScriptObject>().map_err(|e| {
+        e.diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.render(SCRIPT_NAME, script))
+            .collect::<String>()
+    })?;
+    script_object.validate().map_err(|e| e.to_string())?;
+    Ok(script_object)
+}
+
+fn parse_inputs(inputs: &str) -> Result<Vec<ValueBox>, String> {
+    serde_json::from_str(inputs).map_err(|e| format!("Invalid inputs: {}", e))
+}
+
+/// The floor, without limit on the addresses
+fn parse_memory(memory: &str) -> Result<Memory, String> {
+    let data: HashMap<usize, ValueBox> =
+        serde_json::from_str(memory).map_err(|e| format!("Invalid memory: {}", e))?;
+    Ok(Memory::with_data(data, usize::MAX))
+}
+
+fn execution_error(error: &ExecuteScriptError, script: &str) -> String {
+    match error.diagnostic() {
+        Some(diagnostic) => diagnostic.render(SCRIPT_NAME, script),
+        None => error.to_string(),
+    }
+}
+
+fn memory_to_json(memory: &Memory) -> Value {
+    memory
+        .addresses()
+        .map(|address| (address.to_string(), json!(memory.get(&address))))
+        .collect::<serde_json::Map<String, Value>>()
+        .into()
+}
+
+/// Parse and validate the script. Returns it formatted.
+#[wasm_bindgen]
+pub fn parse(script: &str) -> Result<String, String> {
+    parse_script(script).map(|script_object| script_object.to_string())
+}
+
+/// Execute the script to the end.
+/// Returns `{"outputs": [...], "steps": n, "final_memory": {...}}`, like the JSON format of the command line.
+#[wasm_bindgen]
+pub fn run(script: &str, inputs: &str, memory: &str) -> Result<String, String> {
+    let script_object = parse_script(script)?;
+    let inputs = parse_inputs(inputs)?;
+    let mut interpreter = Interpreter::new(parse_memory(memory)?);
+    let (outputs, stats) = interpreter
+        .execute(&script_object, inputs.as_slice())
+        .map_err(|e| execution_error(&e, script))?;
+
+    Ok(json!({
+        "outputs": outputs,
+        "steps": stats.steps,
+        "final_memory": memory_to_json(interpreter.memory()),
+    })
+    .to_string())
+}
+
+/// An execution of a script that can be followed instruction by instruction
+#[wasm_bindgen]
+pub struct Stepper {
+    source: String,
+    script: ScriptObject,
+    inputs: Vec<ValueBox>,
+    interpreter: Interpreter,
+    outputs: Vec<ValueBox>,
+    finished: bool,
+}
+
+#[wasm_bindgen]
+impl Stepper {
+    #[wasm_bindgen(constructor)]
+    pub fn new(script: &str, inputs: &str, memory: &str) -> Result<Stepper, String> {
+        Ok(Stepper {
+            source: script.to_string(),
+            script: parse_script(script)?,
+            inputs: parse_inputs(inputs)?,
+            interpreter: Interpreter::new(parse_memory(memory)?),
+            outputs: vec![],
+            finished: false,
+        })
+    }
+
+    /// Execute the next instruction. Returns false once the execution is finished.
+    pub fn step(&mut self) -> Result<bool, String> {
+        if !self.finished {
+            let status = self
+                .interpreter
+                .step(&self.script, self.inputs.as_slice(), &mut self.outputs)
+                .map_err(|e| execution_error(&e, &self.source))?;
+            self.finished = status == ExecutionStatus::Finished;
+        }
+        Ok(!self.finished)
+    }
+
+    /// The state of the execution:
+    /// `{"line": n, "head": value, "inputs_left": [...], "outputs": [...], "memory": {...}, "steps": n, "finished": bool}`.
+    /// The line is the one of the next instruction, null once the execution is finished.
+    pub fn state(&self) -> String {
+        let line = self
+            .script
+            .next_instruction_position(self.interpreter.position())
+            .and_then(|position| self.script.get_line(position))
+            .filter(|_| !self.finished);
+        let inputs_left = self
+            .inputs
+            .get(self.interpreter.inputs_read()..)
+            .unwrap_or_default();
+
+        json!({
+            "line": line,
+            "head": self.interpreter.head(),
+            "inputs_left": inputs_left,
+            "outputs": self.outputs,
+            "memory": memory_to_json(self.interpreter.memory()),
+            "steps": self.interpreter.steps(),
+            "finished": self.finished,
+        })
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod wasm_tests {
+    use super::*;
+
+    const SCRIPT: &str = "-- HUMAN RESOURCE MACHINE PROGRAM --
+
+a:
+    INBOX
+    COPYTO   0
+    OUTBOX
+    JUMP     a
+";
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(SCRIPT).unwrap(), SCRIPT);
+        assert!(parse("-- HUMAN RESOURCE MACHINE PROGRAM --\n    JUMP     b\n").is_err());
+        let error = parse("-- HUMAN RESOURCE MACHINE PROGRAM --\n    JUMPP    b\n").unwrap_err();
+        assert!(error.contains("--> script:2:5"), "{}", error);
+    }
+
+    #[test]
+    fn test_run() {
+        let results: Value =
+            serde_json::from_str(&run(SCRIPT, r#"[3, "A"]"#, r#"{"5": 1}"#).unwrap()).unwrap();
+        assert_eq!(
+            results,
+            json!({
+                "outputs": [3, "A"],
+                "steps": 9,
+                "final_memory": {"0": "A", "5": 1},
+            })
+        );
+
+        let error = run(SCRIPT, "[1,", "{}").unwrap_err();
+        assert!(error.starts_with("Invalid inputs"), "{}", error);
+    }
+
+    #[test]
+    fn test_stepper() {
+        let mut stepper = Stepper::new(SCRIPT, "[7]", "{}").unwrap();
+        assert!(stepper.step().unwrap());
+        assert!(stepper.step().unwrap());
+
+        let state: Value = serde_json::from_str(&stepper.state()).unwrap();
+        assert_eq!(
+            state,
+            json!({
+                "line": 6,
+                "head": 7,
+                "inputs_left": [],
+                "outputs": [],
+                "memory": {"0": 7},
+                "steps": 2,
+                "finished": false,
+            })
+        );
+
+        while stepper.step().unwrap() {}
+        let state: Value = serde_json::from_str(&stepper.state()).unwrap();
+        assert_eq!(state["outputs"], json!([7]));
+        assert_eq!(state["line"], Value::Null);
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    env, fs,
+    path::Path,
+    process::Command,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use hrm_interpreter::script_object::value_box::ValueBox;
+
+use crate::{
+    cli_reader,
+    log::{self, Verbosity},
+};
+
+/// Time between two checks of the files
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// The arguments of a run: the ones of the interpreter, without the watch option.
+/// The inputs piped to the interpreter can't be read again, so they're given with `--inputs`.
+pub fn run_args(args: &[String], piped_inputs: &[ValueBox]) -> Vec<String> {
+    let mut run_args = args
+        .iter()
+        .filter(|arg| !["-W", "--watch-files"].contains(&arg.as_str()))
+        // "-xW" runs with "-x"
+        .map(|arg| {
+            if cli_reader::is_combined_flags(arg) {
+                arg.replace('W', "")
+            } else {
+                arg.clone()
+            }
+        })
+        .collect::<Vec<_>>();
+    let inputs_given = args
+        .iter()
+        .any(|arg| ["-i", "--inputs", "-g", "--gen-inputs"].contains(&arg.as_str()));
+    if !inputs_given && !piped_inputs.is_empty() {
+        run_args.push("--inputs".to_string());
+        run_args.extend(piped_inputs.iter().map(ToString::to_string));
+    }
+    run_args
+}
+
+/// The last modification times of the files (None if a file can't be read, e.g. while it's being saved)
+fn modification_times(files: &[&Path]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| {
+            fs::metadata(file)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect()
+}
+
+/// Run the interpreter again, with the same arguments, each time one of the files is modified.
+/// `piped_inputs` are the inputs read from stdin, if any.
+pub fn watch(files: &[&Path], piped_inputs: &[ValueBox]) -> ! {
+    let args = run_args(&env::args().skip(1).collect::<Vec<_>>(), piped_inputs);
+    let executable = env::current_exe().unwrap_or_else(|e| {
+        log::error(format!("Could not find the interpreter to run: {}", e));
+        std::process::exit(1);
+    });
+    let names = files
+        .iter()
+        .map(|file| file.display().to_string())
+        .collect::<Vec<_>>();
+
+    loop {
+        let times = modification_times(files);
+        match Command::new(&executable).args(&args).status() {
+            Ok(status) => log::event(
+                Verbosity::Normal,
+                "watch",
+                format!(
+                    "{} ({}), waiting for changes to {}",
+                    if status.success() { "done" } else { "failed" },
+                    status,
+                    names.join(", ")
+                ),
+            ),
+            Err(e) => log::error(format!("[watch] could not run the interpreter: {}", e)),
+        }
+
+        while modification_times(files) == times {
+            thread::sleep(POLL_INTERVAL);
+        }
+        // Let the editor finish writing the file
+        thread::sleep(POLL_INTERVAL);
+        log::event(Verbosity::Normal, "watch", "modified, running again");
+    }
+}
+
+#[cfg(test)]
+mod watch_tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_run_args() {
+        let args = strings(&["script.hrm", "-W", "-m", "memory.txt", "--watch-files"]);
+        assert_eq!(
+            run_args(&args, &[]),
+            strings(&["script.hrm", "-m", "memory.txt"])
+        );
+
+        let inputs = [ValueBox::from(1), ValueBox::from('A')];
+        assert_eq!(
+            run_args(&strings(&["script.hrm", "--watch-files"]), &inputs),
+            strings(&["script.hrm", "--inputs", "1", "A"])
+        );
+        // The inputs given on the command line are kept as they are
+        assert_eq!(
+            run_args(&strings(&["script.hrm", "-i", "1", "A", "-W"]), &inputs),
+            strings(&["script.hrm", "-i", "1", "A"])
+        );
+        assert_eq!(
+            run_args(&strings(&["script.hrm", "-xWs", "10", "-I", "WORD"]), &[]),
+            strings(&["script.hrm", "-xs", "10", "-I", "WORD"])
+        );
+    }
+
+    #[test]
+    fn test_modification_times() {
+        let file = env::temp_dir().join(format!("hrm-watch-{}.hrm", std::process::id()));
+        let missing = env::temp_dir().join("hrm-watch-missing.hrm");
+        fs::write(&file, "INBOX").unwrap();
+
+        let times = modification_times(&[&file, &missing]);
+        assert!(times[0].is_some());
+        assert_eq!(times[1], None);
+
+        fs::remove_file(&file).unwrap();
+        assert_ne!(modification_times(&[&file]), times[..1]);
+    }
+}